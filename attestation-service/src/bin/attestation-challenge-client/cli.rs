@@ -1,4 +1,5 @@
 use crate::rekor::DEFAULT_REKOR_URL;
+use attestation_service::config::DEFAULT_ARTIFACT_SERVER_ADDRESS;
 use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
@@ -141,6 +142,62 @@ pub enum Commands {
         #[arg(long = "rv-list")]
         rv_list: std::path::PathBuf,
     },
+
+    /// Develop attestation policies offline
+    #[command(subcommand)]
+    Policy(PolicyCommands),
+
+    #[command(
+        about = "Replay a captured evaluation against the current policies and reference values"
+    )]
+    Replay(ReplayArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ReplayArgs {
+    /// Capture file written by an AS with `capture` enabled
+    #[arg(long)]
+    pub capture: PathBuf,
+
+    /// AS config file whose policies and RVPS are used. Defaults to the local work dir
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Re-verify the captured evidence instead of only re-running the policies
+    /// on the captured claims
+    #[arg(long)]
+    pub evidence: bool,
+
+    /// Print the replay report as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PolicyCommands {
+    #[command(
+        about = "Evaluate a policy against recorded test cases and report pass/fail with diffs"
+    )]
+    Test(PolicyTestArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct PolicyTestArgs {
+    /// Path to the rego policy under test
+    #[arg(long)]
+    pub policy: PathBuf,
+
+    /// Directory of JSON test cases (claims, reference values, expected results)
+    #[arg(long)]
+    pub cases: PathBuf,
+
+    /// Print the per-case reports as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Artifact Server address used by `query_artifact_server`
+    #[arg(long = "artifact-server-address", default_value = DEFAULT_ARTIFACT_SERVER_ADDRESS)]
+    pub artifact_server_address: String,
 }
 
 #[derive(Args, Debug)]
@@ -165,7 +222,7 @@ pub struct SetReferenceValueArgs {
     #[arg(long = "rekor-api-version", default_value = "auto", value_enum)]
     pub rekor_api_version: RekorApiVersion,
 
-    /// Provenance source protocol (e.g. oci). If provided, provenance is pulled from source URI.
+    /// Provenance source protocol (oci/oci-referrers/https/file). If provided, provenance is pulled from source URI.
     #[arg(long = "provenance-source-protocol")]
     pub provenance_source_protocol: Option<String>,
 
@@ -177,6 +234,10 @@ pub struct SetReferenceValueArgs {
     #[arg(long = "provenance-source-artifact", default_value = "bundle")]
     pub provenance_source_artifact: String,
 
+    /// Digest pinning the provenance pulled from source URI (e.g. sha256:<hex>)
+    #[arg(long = "provenance-source-digest")]
+    pub provenance_source_digest: Option<String>,
+
     /// Path to the provenance payload JSON (required for sample)
     #[arg(long = "payload")]
     pub payload: Option<PathBuf>,
@@ -1,5 +1,7 @@
 pub mod get_evidence;
 pub mod inject_resource;
+pub mod policy_test;
+pub mod replay;
 pub mod set_reference_value;
 pub mod set_reference_value_list;
 pub mod verify;
@@ -0,0 +1,39 @@
+use crate::cli::PolicyTestArgs;
+use anyhow::{bail, Context, Result};
+use attestation_service::policy_engine::simulator::{load_test_cases, PolicyTestHarness};
+use std::fs;
+
+pub async fn run(args: PolicyTestArgs) -> Result<()> {
+    let policy = fs::read_to_string(&args.policy)
+        .with_context(|| format!("read policy file {}", args.policy.display()))?;
+    let harness = PolicyTestHarness::new(&policy, &args.artifact_server_address)
+        .with_context(|| format!("load policy {}", args.policy.display()))?;
+    let cases = load_test_cases(&args.cases)?;
+
+    let mut reports = Vec::with_capacity(cases.len());
+    for case in &cases {
+        reports.push(harness.run(case).await);
+    }
+    let failed = reports.iter().filter(|r| !r.passed()).count();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        for report in &reports {
+            if report.passed() {
+                println!("PASS {}", report.name);
+                continue;
+            }
+            println!("FAIL {}", report.name);
+            for mismatch in &report.mismatches {
+                println!("    {mismatch}");
+            }
+        }
+        println!("\n{} passed, {failed} failed", reports.len() - failed);
+    }
+
+    if failed > 0 {
+        bail!("{failed} of {} policy test case(s) failed", reports.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use crate::cli::ReplayArgs;
+use crate::config::{build_default_config, resolve_work_dir};
+use anyhow::{Context, Result};
+use attestation_service::capture::{load_capture, ReplayMode};
+use attestation_service::config::Config;
+use attestation_service::AttestationService;
+
+pub async fn run(args: ReplayArgs) -> Result<()> {
+    let capture = load_capture(&args.capture).await?;
+
+    let config = match &args.config {
+        Some(path) => Config::try_from(path.as_path())
+            .with_context(|| format!("load AS config {}", path.display()))?,
+        None => build_default_config(&resolve_work_dir())?,
+    };
+    let attestation_service = AttestationService::new(config)
+        .await
+        .context("initialize attestation service")?;
+
+    let mode = if args.evidence {
+        ReplayMode::Evidence
+    } else {
+        ReplayMode::Claims
+    };
+    let report = attestation_service
+        .replay(&capture, mode)
+        .await
+        .with_context(|| format!("replay capture {}", capture.id))?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!(
+        "capture:  {} (policies: {})",
+        report.capture_id,
+        capture.policy_ids.join(", ")
+    );
+    println!("captured: {}", report.original);
+    println!("replayed: {}", report.replayed);
+    if report.changed() {
+        println!("verdict changed");
+    } else {
+        println!("verdict unchanged");
+    }
+    Ok(())
+}
@@ -68,6 +68,7 @@ async fn handle_slsa(args: SetReferenceValueArgs) -> Result<()> {
                         "protocol": protocol,
                         "uri": uri,
                         "artifact": args.provenance_source_artifact,
+                        "digest": args.provenance_source_digest,
                     },
                     "operation_type": "refresh"
                 }
@@ -25,6 +25,7 @@ pub fn build_default_config(work_dir: &Path) -> Result<Config> {
                 .to_string_lossy()
                 .to_string(),
         }),
+        ..Default::default()
     });
 
     let policy_dir = work_dir.join("token/ear/policies");
@@ -7,7 +7,7 @@ mod rvps_message;
 
 use anyhow::Result;
 use clap::Parser;
-use cli::Commands;
+use cli::{Commands, PolicyCommands};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -77,6 +77,12 @@ async fn main() -> Result<()> {
         Commands::SetReferenceValueList { rv_list } => {
             commands::set_reference_value_list::run(rv_list).await?;
         }
+        Commands::Policy(PolicyCommands::Test(args)) => {
+            commands::policy_test::run(args).await?;
+        }
+        Commands::Replay(args) => {
+            commands::replay::run(args).await?;
+        }
     }
 
     Ok(())
@@ -1,4 +1,5 @@
 use anyhow::bail;
+use attestation_service::token::TokenFormat;
 use attestation_service::HashAlgorithm;
 use attestation_service::{
     config::Config, config::ConfigError, AttestationService as Service, ServiceError, Tee,
@@ -235,11 +236,24 @@ impl AttestationService for Arc<RwLock<AttestationServer>> {
             false => request.policy_ids,
         };
 
+        let token_format =
+            match request.token_format.as_str() {
+                "" => None,
+                format => Some(format.parse::<TokenFormat>().map_err(|e| {
+                    Status::aborted(format!("Illegal token format `{format}`: {e}"))
+                })?),
+            };
+
+        let namespace = match request.namespace.as_str() {
+            "" => None,
+            namespace => Some(namespace),
+        };
+
         let attestation_token = self
             .read()
             .await
             .attestation_service
-            .evaluate(verification_requests, policy_ids)
+            .evaluate_in_namespace(verification_requests, policy_ids, token_format, namespace)
             .await
             .map_err(|e| Status::aborted(format!("Attestation evaluation failed: {e:?}")))?;
 
@@ -343,6 +357,16 @@ impl AttestationService for Arc<RwLock<AttestationServer>> {
     }
 }
 
+/// The admin token sent as `authorization: Bearer <token>` metadata.
+fn admin_token<T>(request: &Request<T>) -> Option<&str> {
+    request
+        .metadata()
+        .get("authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
 #[tonic::async_trait]
 impl ReferenceValueProviderService for Arc<RwLock<AttestationServer>> {
     async fn query_reference_value(
@@ -351,12 +375,13 @@ impl ReferenceValueProviderService for Arc<RwLock<AttestationServer>> {
     ) -> Result<Response<ReferenceValueQueryResponse>, Status> {
         info!("QueryReferenceValue API called.");
 
-        let reference_value_id = request.into_inner().reference_value_id;
+        let request = request.into_inner();
+        let reference_value_id = request.reference_value_id;
         let service = self.read().await;
         let reference_value_results = if reference_value_id.is_empty() {
             let values = service
                 .attestation_service
-                .query_reference_values()
+                .query_reference_values_in(&request.namespace)
                 .await
                 .map_err(|e| Status::aborted(format!("Query reference values: {e}")))?;
             serde_json::to_string(&values)
@@ -364,7 +389,7 @@ impl ReferenceValueProviderService for Arc<RwLock<AttestationServer>> {
         } else {
             let value = service
                 .attestation_service
-                .query_reference_value(&reference_value_id)
+                .query_reference_value_in(&request.namespace, &reference_value_id)
                 .await
                 .map_err(|e| Status::aborted(format!("Query reference value: {e}")))?;
             value
@@ -384,7 +409,8 @@ impl ReferenceValueProviderService for Arc<RwLock<AttestationServer>> {
         &self,
         request: Request<ReferenceValueRegisterRequest>,
     ) -> Result<Response<ReferenceValueRegisterResponse>, Status> {
-        let request = request.into_inner();
+        let admin_token = admin_token(&request);
+        let request = request.get_ref();
 
         info!("RegisterReferenceValue API called.");
         debug!("registry reference value: {}", request.message);
@@ -392,7 +418,7 @@ impl ReferenceValueProviderService for Arc<RwLock<AttestationServer>> {
         self.write()
             .await
             .attestation_service
-            .register_reference_value(&request.message)
+            .register_reference_value_in(&request.namespace, &request.message, admin_token)
             .await
             .map_err(|e| Status::aborted(format!("Register reference value: {e}")))?;
 
@@ -404,7 +430,8 @@ impl ReferenceValueProviderService for Arc<RwLock<AttestationServer>> {
         &self,
         request: Request<ReferenceValueListRequest>,
     ) -> Result<Response<ReferenceValueListResponse>, Status> {
-        let request = request.into_inner();
+        let admin_token = admin_token(&request);
+        let request = request.get_ref();
 
         info!("SetReferenceValueList API called.");
         debug!(
@@ -415,7 +442,7 @@ impl ReferenceValueProviderService for Arc<RwLock<AttestationServer>> {
         self.write()
             .await
             .attestation_service
-            .set_reference_value_list(&request.payload)
+            .set_reference_value_list_in(&request.namespace, &request.payload, admin_token)
             .await
             .map_err(|e| Status::aborted(format!("Set reference value list: {e}")))?;
 
@@ -427,7 +454,8 @@ impl ReferenceValueProviderService for Arc<RwLock<AttestationServer>> {
         &self,
         request: Request<ReferenceValueDeleteRequest>,
     ) -> Result<Response<ReferenceValueDeleteResponse>, Status> {
-        let request = request.into_inner();
+        let admin_token = admin_token(&request);
+        let request = request.get_ref();
 
         info!("DeleteReferenceValue API called.");
         debug!("Delete reference value: {}", request.name);
@@ -435,7 +463,7 @@ impl ReferenceValueProviderService for Arc<RwLock<AttestationServer>> {
         self.write()
             .await
             .attestation_service
-            .delete_reference_value(request.name)
+            .delete_reference_value_in(&request.namespace, request.name.clone(), admin_token)
             .await
             .map_err(|e| Status::aborted(format!("Delete reference value: {e}")))?;
 
@@ -16,7 +16,7 @@ use tokio::sync::RwLock;
 
 use crate::restful::{
     attestation, delete_policy, get_certificate, get_challenge, get_jwks, get_openid_configuration,
-    get_policies, get_status, set_policy,
+    get_policies, get_revocations, get_status, introspect, revoke, set_policy, unrevoke,
 };
 
 mod restful;
@@ -79,6 +79,12 @@ enum WebApi {
 
     #[strum(serialize = "/status")]
     Status,
+
+    #[strum(serialize = "/revocations")]
+    Revocations,
+
+    #[strum(serialize = "/introspect")]
+    Introspect,
 }
 
 #[derive(Error, Debug)]
@@ -182,6 +188,13 @@ async fn main() -> Result<(), RestfulError> {
             )
             .service(web::resource(WebApi::Jwks.as_ref()).route(web::get().to(get_jwks)))
             .service(web::resource(WebApi::Status.as_ref()).route(web::get().to(get_status)))
+            .service(
+                web::resource(WebApi::Revocations.as_ref())
+                    .route(web::post().to(revoke))
+                    .route(web::delete().to(unrevoke))
+                    .route(web::get().to(get_revocations)),
+            )
+            .service(web::resource(WebApi::Introspect.as_ref()).route(web::post().to(introspect)))
             .service(
                 web::resource(WebApi::OpenIdConfiguration.as_ref())
                     .route(web::get().to(get_openid_configuration)),
@@ -2,6 +2,8 @@ use std::{collections::HashMap, sync::Arc};
 
 use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse, Responder, ResponseError};
 use anyhow::{anyhow, bail, Context};
+use attestation_service::revocation::Revocation;
+use attestation_service::token::TokenFormat;
 use attestation_service::{
     AttestationError, AttestationService, HashAlgorithm, InitDataInput as InnerInitDataInput,
     RuntimeData as InnerRuntimeData, VerificationRequest,
@@ -343,6 +345,10 @@ type Result<T> = std::result::Result<T, Error>;
 pub struct AttestationRequest {
     verification_requests: Vec<IndividualAttestationRequest>,
     policy_ids: Vec<String>,
+    #[serde(default)]
+    token_format: Option<TokenFormat>,
+    #[serde(default)]
+    namespace: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -542,7 +548,12 @@ pub async fn attestation(
     let token = cocoas
         .read()
         .await
-        .evaluate(verification_requests, policy_ids)
+        .evaluate_in_namespace(
+            verification_requests,
+            policy_ids,
+            request.token_format,
+            request.namespace.as_deref(),
+        )
         .await
         .map_err(|source| {
             Error::from_attestation_evaluation(source.context("attestation report evaluate"))
@@ -677,6 +688,83 @@ pub async fn delete_policy(
     Ok(HttpResponse::Ok().body(""))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RevokeRequest {
+    #[serde(flatten)]
+    revocation: Revocation,
+    #[serde(default)]
+    reason: Option<String>,
+    #[serde(default)]
+    expires_at: Option<i64>,
+}
+
+/// POST /revocations
+///
+/// The body is a revocation, one of
+/// ```json
+/// {"type": "token", "jti": <jti>}
+/// {"type": "tee_identity", "claim": <claim>, "value": <value>}
+/// {"type": "reference_value", "name": <name>}
+/// ```
+/// with an optional `reason` and an optional `expires_at` (Unix timestamp)
+/// the revocation lapses at. The response lists the `jti`s revoked.
+pub async fn revoke(
+    request: web::Json<RevokeRequest>,
+    cocoas: web::Data<Arc<RwLock<AttestationService>>>,
+) -> Result<HttpResponse> {
+    info!("Revoke API called.");
+    let request = request.into_inner();
+
+    debug!("revoke: {request:#?}");
+    let revoked = cocoas
+        .read()
+        .await
+        .revoke(request.revocation, request.reason, request.expires_at)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(json!({ "revoked_tokens": revoked })))
+}
+
+/// DELETE /revocations
+///
+/// The body is a revocation as for `POST`, which is withdrawn. The response
+/// lists the `jti`s reinstated.
+pub async fn unrevoke(
+    request: web::Json<Revocation>,
+    cocoas: web::Data<Arc<RwLock<AttestationService>>>,
+) -> Result<HttpResponse> {
+    info!("Unrevoke API called.");
+    let revocation = request.into_inner();
+
+    debug!("unrevoke: {revocation:#?}");
+    let reinstated = cocoas.read().await.unrevoke(&revocation).await?;
+
+    Ok(HttpResponse::Ok().json(json!({ "reinstated_tokens": reinstated })))
+}
+
+/// GET /revocations
+pub async fn get_revocations(cocoas: web::Data<Arc<RwLock<AttestationService>>>) -> impl Responder {
+    let list = cocoas.read().await.revocation_list().await;
+    web::Json(list)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IntrospectionRequest {
+    token: String,
+}
+
+/// POST /introspect
+///
+/// RFC 7662 token introspection. The form encoded body carries the `token`.
+pub async fn introspect(
+    request: web::Form<IntrospectionRequest>,
+    cocoas: web::Data<Arc<RwLock<AttestationService>>>,
+) -> impl Responder {
+    info!("Introspect API called.");
+    let introspection = cocoas.read().await.introspect(&request.token).await;
+    web::Json(introspection)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RemovePolicyRequest {
     pub policy_ids: Vec<String>,
@@ -0,0 +1,612 @@
+//! Evidence capture and replay.
+//!
+//! With capture enabled, every call to `AttestationService::evaluate` is
+//! written to the capture directory as one JSON file holding the verification
+//! requests, the claims parsed from the evidence and the verdict. A capture
+//! can later be replayed against the current policies and reference values to
+//! see how a policy edit changes the verdict, without reproducing the guest.
+//!
+//! Captures contain the raw evidence and init data of the guests, so the
+//! capture directory should be protected like the AS work directory. Files
+//! are created with mode `0600`.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use kbs_types::Tee;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{HashAlgorithm, InitDataInput, RuntimeData, TeeClaims, VerificationRequest};
+
+fn default_max_total_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
+fn default_max_captures() -> usize {
+    1000
+}
+
+fn default_retention_hours() -> u64 {
+    7 * 24
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct CaptureConfig {
+    /// Directory the captures are written to.
+    pub dir: PathBuf,
+
+    /// Total size of the capture directory. The oldest captures are removed
+    /// once it is exceeded. Default: 100 MiB.
+    #[serde(default = "default_max_total_bytes")]
+    pub max_total_bytes: u64,
+
+    /// Number of captures kept. Default: 1000.
+    #[serde(default = "default_max_captures")]
+    pub max_captures: usize,
+
+    /// Captures older than this are removed. Default: 168 (7 days).
+    #[serde(default = "default_retention_hours")]
+    pub retention_hours: u64,
+
+    /// Only capture evaluations that did not issue a token.
+    #[serde(default)]
+    pub failures_only: bool,
+}
+
+/// One recorded call to `AttestationService::evaluate`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Capture {
+    /// Unique id, also the file stem. Ids sort in capture order.
+    pub id: String,
+    /// Unix timestamp (seconds) of the evaluation.
+    pub captured_at: u64,
+    pub requests: Vec<CapturedRequest>,
+    pub policy_ids: Vec<String>,
+    /// Namespace of the reference values the policies saw.
+    #[serde(default)]
+    pub namespace: String,
+    /// Claims parsed from each piece of evidence that passed verification.
+    pub claims: Vec<CapturedClaims>,
+    pub verdict: Verdict,
+    /// Payload of the issued token, if any.
+    pub token_claims: Option<Value>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CapturedRequest {
+    pub evidence: Value,
+    pub tee: Tee,
+    pub runtime_data: Option<CapturedRuntimeData>,
+    pub runtime_data_hash_algorithm: HashAlgorithm,
+    pub init_data: Option<CapturedInitData>,
+    pub additional_data: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapturedRuntimeData {
+    /// Base64 of the raw runtime data.
+    Raw(String),
+    Structured(Value),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapturedInitData {
+    /// Hex of the init data digest.
+    Digest(String),
+    Toml(String),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CapturedClaims {
+    pub tee: Tee,
+    pub tee_class: String,
+    pub claims: Value,
+    pub init_data_claims: Value,
+    pub runtime_data_claims: Value,
+    pub additional_data: Option<Value>,
+    #[serde(default)]
+    pub nonce: Option<String>,
+    #[serde(default)]
+    pub evidence_digest: Option<String>,
+}
+
+/// The outcome of an evaluation, reduced to what a policy edit can change.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case", tag = "result")]
+pub enum Verdict {
+    /// A token was issued. For EAR tokens, `statuses` maps each submodule to
+    /// its `ear.status`.
+    Issued { statuses: BTreeMap<String, String> },
+    /// No token was issued.
+    Rejected { error: String },
+}
+
+impl Verdict {
+    /// Derive the verdict of an evaluation and the payload of its token.
+    pub fn from_result(result: &Result<String>) -> (Self, Option<Value>) {
+        match result {
+            Ok(token) => {
+                let claims = token_claims(token);
+                let statuses = claims
+                    .as_ref()
+                    .and_then(|c| c["submods"].as_object())
+                    .map(|submods| {
+                        submods
+                            .iter()
+                            .map(|(name, submod)| {
+                                let status = submod["ear.status"].as_str().unwrap_or_default();
+                                (name.clone(), status.to_string())
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (Verdict::Issued { statuses }, claims)
+            }
+            Err(e) => (
+                Verdict::Rejected {
+                    error: format!("{e:#}"),
+                },
+                None,
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Issued { statuses } if statuses.is_empty() => write!(f, "issued"),
+            Verdict::Issued { statuses } => {
+                let statuses: Vec<_> = statuses
+                    .iter()
+                    .map(|(submod, status)| format!("{submod}={status}"))
+                    .collect();
+                write!(f, "issued ({})", statuses.join(", "))
+            }
+            Verdict::Rejected { error } => write!(f, "rejected: {error}"),
+        }
+    }
+}
+
+/// How a capture is replayed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Re-run the policies on the captured claims. Verifiers are skipped, so
+    /// expired collateral or challenge tokens do not affect the result.
+    Claims,
+    /// Re-run the whole evaluation, verifiers included, on the captured
+    /// requests.
+    Evidence,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReplayReport {
+    pub capture_id: String,
+    pub original: Verdict,
+    pub replayed: Verdict,
+    pub token_claims: Option<Value>,
+}
+
+impl ReplayReport {
+    pub fn changed(&self) -> bool {
+        self.original != self.replayed
+    }
+}
+
+impl From<&VerificationRequest> for CapturedRequest {
+    fn from(request: &VerificationRequest) -> Self {
+        Self {
+            evidence: request.evidence.clone(),
+            tee: request.tee,
+            runtime_data: request.runtime_data.as_ref().map(|data| match data {
+                RuntimeData::Raw(raw) => CapturedRuntimeData::Raw(STANDARD.encode(raw)),
+                RuntimeData::Structured(value) => CapturedRuntimeData::Structured(value.clone()),
+            }),
+            runtime_data_hash_algorithm: request.runtime_data_hash_algorithm.clone(),
+            init_data: request.init_data.as_ref().map(|data| match data {
+                InitDataInput::Digest(digest) => CapturedInitData::Digest(hex::encode(digest)),
+                InitDataInput::Toml(toml) => CapturedInitData::Toml(toml.clone()),
+            }),
+            additional_data: request.additional_data.clone(),
+        }
+    }
+}
+
+impl TryFrom<&CapturedRequest> for VerificationRequest {
+    type Error = anyhow::Error;
+
+    fn try_from(request: &CapturedRequest) -> Result<Self> {
+        let runtime_data = match &request.runtime_data {
+            Some(CapturedRuntimeData::Raw(raw)) => Some(RuntimeData::Raw(
+                STANDARD
+                    .decode(raw)
+                    .context("decode captured runtime data")?,
+            )),
+            Some(CapturedRuntimeData::Structured(value)) => {
+                Some(RuntimeData::Structured(value.clone()))
+            }
+            None => None,
+        };
+        let init_data = match &request.init_data {
+            Some(CapturedInitData::Digest(digest)) => Some(InitDataInput::Digest(
+                hex::decode(digest).context("decode captured init data digest")?,
+            )),
+            Some(CapturedInitData::Toml(toml)) => Some(InitDataInput::Toml(toml.clone())),
+            None => None,
+        };
+
+        Ok(Self {
+            evidence: request.evidence.clone(),
+            tee: request.tee,
+            runtime_data,
+            runtime_data_hash_algorithm: request.runtime_data_hash_algorithm.clone(),
+            init_data,
+            additional_data: request.additional_data.clone(),
+        })
+    }
+}
+
+impl From<&TeeClaims> for CapturedClaims {
+    fn from(claims: &TeeClaims) -> Self {
+        Self {
+            tee: claims.tee,
+            tee_class: claims.tee_class.clone(),
+            claims: claims.claims.clone(),
+            init_data_claims: claims.init_data_claims.clone(),
+            runtime_data_claims: claims.runtime_data_claims.clone(),
+            additional_data: claims.additional_data.clone(),
+            nonce: claims.nonce.clone(),
+            evidence_digest: claims.evidence_digest.clone(),
+        }
+    }
+}
+
+impl From<&CapturedClaims> for TeeClaims {
+    fn from(claims: &CapturedClaims) -> Self {
+        Self {
+            tee: claims.tee,
+            tee_class: claims.tee_class.clone(),
+            claims: claims.claims.clone(),
+            init_data_claims: claims.init_data_claims.clone(),
+            runtime_data_claims: claims.runtime_data_claims.clone(),
+            additional_data: claims.additional_data.clone(),
+            nonce: claims.nonce.clone(),
+            evidence_digest: claims.evidence_digest.clone(),
+        }
+    }
+}
+
+/// Decode the payload of a JWT or COSE EAR without checking its signature.
+fn token_claims(token: &str) -> Option<Value> {
+    if !token.contains('.') {
+        return cose_token_claims(token);
+    }
+    let payload = token.split('.').nth(1)?;
+    let payload = URL_SAFE_NO_PAD.decode(payload).ok()?;
+    serde_json::from_slice(&payload).ok()
+}
+
+/// Decode the CWT claims of a COSE EAR into the JSON form of a JWT EAR.
+/// The `exp`, `cti` and namespace claims the broker adds are not part of the
+/// EAR claims set, so they are taken off before reading it and put back
+/// under their JWT names.
+fn cose_token_claims(token: &str) -> Option<Value> {
+    use coset::cbor::Value as CborValue;
+    use coset::{CoseSign1, TaggedCborSerializable};
+
+    use crate::token::ear_broker::{CWT_CTI, CWT_EXP, RVPS_NAMESPACE_CLAIM, RVPS_NAMESPACE_KEY};
+
+    let sign1 = CoseSign1::from_tagged_slice(&URL_SAFE_NO_PAD.decode(token).ok()?).ok()?;
+    let mut claims: CborValue = coset::cbor::from_reader(sign1.payload?.as_slice()).ok()?;
+    let entries = claims.as_map_mut()?;
+    let mut take = |key: i32| {
+        let label = CborValue::Integer(key.into());
+        let index = entries.iter().position(|(l, _)| *l == label)?;
+        Some(entries.remove(index).1)
+    };
+    let exp = take(CWT_EXP).and_then(|exp| i64::try_from(exp.as_integer()?).ok());
+    let jti = take(CWT_CTI).and_then(|cti| match cti {
+        CborValue::Bytes(bytes) => String::from_utf8(bytes).ok(),
+        CborValue::Text(text) => Some(text),
+        _ => None,
+    });
+    let namespace = take(RVPS_NAMESPACE_KEY).and_then(|namespace| namespace.into_text().ok());
+
+    let ear: ear::Ear = claims.deserialized().ok()?;
+    let mut claims = serde_json::to_value(&ear).ok()?;
+    let object = claims.as_object_mut()?;
+    for (name, value) in [
+        ("exp", exp.map(Value::from)),
+        ("jti", jti.map(Value::from)),
+        (RVPS_NAMESPACE_CLAIM, namespace.map(Value::from)),
+    ] {
+        if let Some(value) = value {
+            object.insert(name.to_string(), value);
+        }
+    }
+    Some(claims)
+}
+
+#[cfg(feature = "fs")]
+pub use store::{load_capture, CaptureStore};
+
+#[cfg(feature = "fs")]
+mod store {
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use anyhow::{Context, Result};
+    use log::{debug, warn};
+    use tokio::io::AsyncWriteExt;
+    use tokio::sync::Mutex;
+
+    use super::{Capture, CaptureConfig};
+
+    /// Writes captures and enforces the size and retention limits.
+    pub struct CaptureStore {
+        config: CaptureConfig,
+        lock: Mutex<()>,
+    }
+
+    impl CaptureStore {
+        pub fn new(config: CaptureConfig) -> Result<Self> {
+            std::fs::create_dir_all(&config.dir)
+                .with_context(|| format!("create capture dir {}", config.dir.display()))?;
+            Ok(Self {
+                config,
+                lock: Mutex::new(()),
+            })
+        }
+
+        pub fn failures_only(&self) -> bool {
+            self.config.failures_only
+        }
+
+        /// A new capture id: the capture time in milliseconds followed by a
+        /// random suffix.
+        pub fn new_id() -> (String, u64) {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let id = format!("{:013}-{}", now.as_millis(), uuid::Uuid::new_v4().simple());
+            (id, now.as_secs())
+        }
+
+        /// Write `capture` and prune the directory. Returns the file path.
+        pub async fn record(&self, capture: &Capture) -> Result<PathBuf> {
+            let content = serde_json::to_vec_pretty(capture).context("serialize capture")?;
+            if content.len() as u64 > self.config.max_total_bytes {
+                anyhow::bail!(
+                    "capture of {} bytes exceeds the {} bytes capture limit",
+                    content.len(),
+                    self.config.max_total_bytes
+                );
+            }
+
+            let _guard = self.lock.lock().await;
+            let path = self.config.dir.join(format!("{}.json", capture.id));
+            let mut file = tokio::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&path)
+                .await
+                .with_context(|| format!("create capture {}", path.display()))?;
+            file.write_all(&content)
+                .await
+                .with_context(|| format!("write capture {}", path.display()))?;
+
+            self.prune().await?;
+            Ok(path)
+        }
+
+        /// Remove expired captures, then the oldest ones until the count and
+        /// size limits hold.
+        async fn prune(&self) -> Result<()> {
+            let mut entries = Vec::new();
+            let mut dir = tokio::fs::read_dir(&self.config.dir)
+                .await
+                .with_context(|| format!("read capture dir {}", self.config.dir.display()))?;
+            while let Some(entry) = dir.next_entry().await? {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    let metadata = entry.metadata().await?;
+                    entries.push((path, metadata.len(), metadata.modified().ok()));
+                }
+            }
+            // Ids start with the capture time, so names sort oldest first.
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+            let retention = Duration::from_secs(self.config.retention_hours * 3600);
+            let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+            let mut count = entries.len();
+            for (path, len, modified) in entries {
+                let expired = modified
+                    .and_then(|m| m.elapsed().ok())
+                    .is_some_and(|age| age > retention);
+                if !expired
+                    && total <= self.config.max_total_bytes
+                    && count <= self.config.max_captures
+                {
+                    break;
+                }
+
+                debug!("Remove capture {}", path.display());
+                if let Err(e) = tokio::fs::remove_file(&path).await {
+                    warn!("Failed to remove capture {}: {e}", path.display());
+                    continue;
+                }
+                total -= len;
+                count -= 1;
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Read a capture file.
+    pub async fn load_capture(path: &Path) -> Result<Capture> {
+        let content = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("read capture {}", path.display()))?;
+        serde_json::from_slice(&content)
+            .with_context(|| format!("parse capture {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verdict_reads_ear_statuses() {
+        let payload = serde_json::json!({
+            "submods": {
+                "cpu0": {"ear.status": "affirming"},
+                "gpu0": {"ear.status": "warning"},
+            }
+        });
+        let token = format!(
+            "e30.{}.sig",
+            URL_SAFE_NO_PAD.encode(payload.to_string().as_bytes())
+        );
+
+        let (verdict, claims) = Verdict::from_result(&Ok(token));
+        assert_eq!(claims, Some(payload));
+        assert_eq!(verdict.to_string(), "issued (cpu0=affirming, gpu0=warning)");
+
+        let (verdict, claims) = Verdict::from_result(&Err(anyhow::anyhow!("denied")));
+        assert_eq!(
+            verdict,
+            Verdict::Rejected {
+                error: "denied".to_string()
+            }
+        );
+        assert!(claims.is_none());
+    }
+
+    #[test]
+    fn verdict_reads_cose_ear_statuses() {
+        use coset::cbor::Value as CborValue;
+        use coset::{CoseSign1Builder, TaggedCborSerializable};
+        use ear::{Ear, Extensions, VerifierID};
+
+        use crate::token::ear_broker::{appraisal_from_rules, CWT_CTI, RVPS_NAMESPACE_KEY};
+
+        let appraisal = appraisal_from_rules(&std::collections::HashMap::from([(
+            "executables".to_string(),
+            serde_json::json!(2),
+        )]))
+        .unwrap();
+        let ear = Ear {
+            profile: "tag:github.com,2024:confidential-containers/Trustee".into(),
+            iat: 0,
+            vid: VerifierID {
+                build: "test".into(),
+                developer: "test".into(),
+            },
+            raw_evidence: None,
+            nonce: None,
+            submods: BTreeMap::from([("cpu0".to_string(), appraisal)]),
+            extensions: Extensions::new(),
+        };
+        let mut claims = CborValue::serialized(&ear).unwrap();
+        claims.as_map_mut().unwrap().extend([
+            (
+                CborValue::Integer(CWT_CTI.into()),
+                CborValue::Bytes(b"ear-jti".to_vec()),
+            ),
+            (
+                CborValue::Integer(RVPS_NAMESPACE_KEY.into()),
+                CborValue::Text("default".into()),
+            ),
+        ]);
+        let mut payload = Vec::new();
+        coset::cbor::into_writer(&claims, &mut payload).unwrap();
+        let token = URL_SAFE_NO_PAD.encode(
+            CoseSign1Builder::new()
+                .payload(payload)
+                .build()
+                .to_tagged_vec()
+                .unwrap(),
+        );
+
+        let (verdict, claims) = Verdict::from_result(&Ok(token));
+        assert_eq!(verdict.to_string(), "issued (cpu0=affirming)");
+        let claims = claims.unwrap();
+        assert_eq!(claims["jti"], "ear-jti");
+        assert_eq!(claims["rvps-namespace"], "default");
+    }
+
+    #[test]
+    fn captured_request_round_trips() {
+        let request = VerificationRequest {
+            evidence: serde_json::json!({"quote": "abc"}),
+            tee: Tee::Sample,
+            runtime_data: Some(RuntimeData::Raw(vec![1, 2, 3])),
+            runtime_data_hash_algorithm: HashAlgorithm::Sha384,
+            init_data: Some(InitDataInput::Digest(vec![0xab; 4])),
+            additional_data: None,
+        };
+
+        let captured = CapturedRequest::from(&request);
+        let captured: CapturedRequest =
+            serde_json::from_str(&serde_json::to_string(&captured).unwrap()).unwrap();
+        let restored = VerificationRequest::try_from(&captured).unwrap();
+
+        assert_eq!(restored.evidence, request.evidence);
+        assert!(matches!(restored.runtime_data, Some(RuntimeData::Raw(raw)) if raw == [1, 2, 3]));
+        assert!(matches!(restored.init_data, Some(InitDataInput::Digest(d)) if d == [0xab; 4]));
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn store_enforces_count_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CaptureStore::new(CaptureConfig {
+            dir: dir.path().to_path_buf(),
+            max_total_bytes: default_max_total_bytes(),
+            max_captures: 2,
+            retention_hours: default_retention_hours(),
+            failures_only: false,
+        })
+        .unwrap();
+
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let capture = Capture {
+                id: format!("{i:013}-test"),
+                captured_at: i,
+                requests: vec![],
+                policy_ids: vec!["default".to_string()],
+                namespace: String::new(),
+                claims: vec![],
+                verdict: Verdict::Rejected {
+                    error: "denied".to_string(),
+                },
+                token_claims: None,
+            };
+            store.record(&capture).await.unwrap();
+            ids.push(capture.id);
+        }
+
+        let mut kept: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        kept.sort();
+        assert_eq!(
+            kept,
+            [format!("{}.json", ids[1]), format!("{}.json", ids[2])]
+        );
+
+        let loaded = load_capture(&dir.path().join(&kept[1])).await.unwrap();
+        assert_eq!(loaded.id, ids[2]);
+    }
+}
@@ -104,13 +104,14 @@ impl JwtChallenger {
         }
     }
 
-    /// Verify the challenge token JWT — signature and `exp` (freshness).
+    /// Verify the challenge token JWT — signature and `exp` (freshness) — and
+    /// return the nonce it was issued with.
     /// The token is the JWT the client echoes from `extra-params.jwt` in the
     /// challenge response; the client is not expected to send a separate
     /// nonce, so verification is signature + expiry only. Binding to the TEE
     /// report is handled by the TEE measuring the `runtime_data` (which
     /// carries the challenge token) into its report.
-    pub async fn verify_challenge_token(&self, challenge_token: &str) -> Result<()> {
+    pub async fn verify_challenge_token(&self, challenge_token: &str) -> Result<String> {
         match &self.key_source {
             PrivateKeySource::InMemory(key) => verify_jwt(challenge_token, key),
             #[cfg(feature = "fs")]
@@ -303,9 +304,9 @@ fn rs384_sign(rsa: &RsaPrivateKey, payload: &[u8]) -> Result<Vec<u8>> {
 /// enforce its `exp` (freshness). Signature + expiry is the only check the
 /// Attestation Service performs on the challenge token — the binding to the
 /// TEE report comes from the TEE measuring the `runtime_data` (which carries
-/// the token) into its report, so there is no nonce string to compare and
-/// nothing to return.
-fn verify_jwt(token: &str, key: &RsaPrivateKey) -> Result<()> {
+/// the token) into its report, so there is no nonce string to compare. The
+/// `nonce` claim is returned so it can be echoed in the attestation token.
+fn verify_jwt(token: &str, key: &RsaPrivateKey) -> Result<String> {
     let parts: Vec<&str> = token.split('.').collect();
     if parts.len() != 3 {
         bail!("invalid JWT format in challenge_token");
@@ -341,7 +342,11 @@ fn verify_jwt(token: &str, key: &RsaPrivateKey) -> Result<()> {
         bail!("challenge_token expired");
     }
 
-    Ok(())
+    let nonce = v
+        .get("nonce")
+        .and_then(|x| x.as_str())
+        .ok_or_else(|| anyhow!("missing nonce claim in challenge_token"))?;
+    Ok(nonce.to_string())
 }
 
 #[cfg(test)]
@@ -361,13 +366,16 @@ mod tests {
     /// Helper mirroring the `lib.rs` evaluate contract: the client echoes the
     /// challenge JSON's JWT into `runtime_data` as `challenge_token`, and the
     /// challenger verifies signature + `exp` (freshness only — no nonce is
-    /// sent by the client).
+    /// sent by the client) and hands back the nonce the token carries.
     async fn issue_and_verify(c: &JwtChallenger, challenge_json: &str) {
         let jwt = challenge_jwt(challenge_json);
 
-        c.verify_challenge_token(&jwt)
+        let nonce = c
+            .verify_challenge_token(&jwt)
             .await
             .expect("valid token verifies");
+        let outer: Value = serde_json::from_str(challenge_json).expect("outer json");
+        assert_eq!(outer["nonce"], nonce, "challenge nonce is returned");
     }
 
     /// In-memory challenger: issue + verify round-trip, no filesystem. JWT
@@ -1,7 +1,11 @@
+use crate::capture::CaptureConfig;
+use crate::revocation::RevocationConfig;
 use crate::rvps::RvpsConfig;
 use crate::token::AttestationTokenConfig;
+use crate::HashAlgorithm;
 
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs::File;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
@@ -36,6 +40,25 @@ pub struct Config {
     /// generated on the first challenge request if it does not exist.
     #[serde(default)]
     pub challenge_key_path: Option<PathBuf>,
+
+    /// Record every evaluation (requests, parsed claims and verdict) to a
+    /// local directory so it can be replayed later. Disabled when unset.
+    #[serde(default)]
+    pub capture: Option<CaptureConfig>,
+
+    /// Hash algorithm of the evidence digest embedded in issued tokens for
+    /// audit. No digest is embedded when unset.
+    #[serde(default)]
+    pub evidence_digest: Option<HashAlgorithm>,
+
+    /// Revocation of issued tokens.
+    #[serde(default)]
+    pub revocation: RevocationConfig,
+
+    /// Namespace of the reference values each policy sees, by policy id.
+    /// Policies not listed see the default namespace.
+    #[serde(default)]
+    pub policy_namespaces: HashMap<String, String>,
 }
 
 fn default_work_dir() -> PathBuf {
@@ -67,6 +90,10 @@ impl Default for Config {
             artifact_server_address: default_artifact_server_address(),
             attestation_token_broker: AttestationTokenConfig::default(),
             challenge_key_path: None,
+            capture: None,
+            evidence_digest: None,
+            revocation: RevocationConfig::default(),
+            policy_namespaces: HashMap::new(),
         }
     }
 }
@@ -104,7 +131,7 @@ mod tests {
     use crate::rvps::RvpsCrateConfig;
     use crate::{
         rvps::RvpsConfig,
-        token::{ear_broker, oidc, simple, AttestationTokenConfig},
+        token::{ear_broker, oidc, simple, AttestationTokenConfig, TokenFormat},
     };
     use reference_value_provider_service::storage::{local_fs, ReferenceValueStorageConfig};
 
@@ -113,6 +140,7 @@ mod tests {
         work_dir: PathBuf::from("/var/lib/attestation-service/"),
         rvps_config: RvpsConfig::BuiltIn(RvpsCrateConfig {
             storage: ReferenceValueStorageConfig::LocalFs(local_fs::Config::default()),
+            ..Default::default()
         }),
         attestation_token_broker: AttestationTokenConfig::Simple(simple::Configuration {
             settings: simple::TokenBrokerSettings {
@@ -123,12 +151,17 @@ mod tests {
             policy_dir: "/var/lib/attestation-service/policies".into(),
         }),
         challenge_key_path: None,
+        capture: None,
+        evidence_digest: None,
+        revocation: RevocationConfig::default(),
+        policy_namespaces: Default::default(),
         artifact_server_address: DEFAULT_ARTIFACT_SERVER_ADDRESS.to_string(),
     })]
     #[case("./tests/configs/example2.json", Config {
         work_dir: PathBuf::from("/var/lib/attestation-service/"),
         rvps_config: RvpsConfig::BuiltIn(RvpsCrateConfig {
             storage: ReferenceValueStorageConfig::LocalFs(local_fs::Config::default()),
+            ..Default::default()
         }),
         attestation_token_broker: AttestationTokenConfig::Simple(simple::Configuration {
             settings: simple::TokenBrokerSettings {
@@ -139,16 +172,24 @@ mod tests {
             signer: Some(simple::SignerConfig {
                 key_path: "/etc/key".into(),
                 cert_url: Some("https://example.io".into()),
-                cert_path: Some("/etc/cert.pem".into())
+                cert_path: Some("/etc/cert.pem".into()),
+                pkcs11: None,
+                kms: None,
+                rotation: None,
             }),
         }),
         challenge_key_path: None,
+        capture: None,
+        evidence_digest: None,
+        revocation: RevocationConfig::default(),
+        policy_namespaces: Default::default(),
         artifact_server_address: DEFAULT_ARTIFACT_SERVER_ADDRESS.to_string(),
     })]
     #[case("./tests/configs/example3.json", Config {
         work_dir: PathBuf::from("/var/lib/attestation-service/"),
         rvps_config: RvpsConfig::BuiltIn(RvpsCrateConfig {
             storage: ReferenceValueStorageConfig::LocalFs(local_fs::Config::default()),
+            ..Default::default()
         }),
         attestation_token_broker: AttestationTokenConfig::Ear(ear_broker::Configuration {
             settings: ear_broker::TokenBrokerSettings {
@@ -157,17 +198,23 @@ mod tests {
                 developer_name: "someone".into(),
                 build_name: "0.1.0".into(),
                 profile_name: "tag:github.com,2024:confidential-containers/Trustee".into(),
+                token_format: TokenFormat::Jwt,
             },
             signer: None,
             policy_dir: "/var/lib/attestation-service/policies".into(),
         }),
         challenge_key_path: None,
+        capture: None,
+        evidence_digest: None,
+        revocation: RevocationConfig::default(),
+        policy_namespaces: Default::default(),
         artifact_server_address: DEFAULT_ARTIFACT_SERVER_ADDRESS.to_string(),
     })]
     #[case("./tests/configs/example4.json", Config {
         work_dir: PathBuf::from("/var/lib/attestation-service/"),
         rvps_config: RvpsConfig::BuiltIn(RvpsCrateConfig {
             storage: ReferenceValueStorageConfig::LocalFs(local_fs::Config::default()),
+            ..Default::default()
         }),
         attestation_token_broker: AttestationTokenConfig::Ear(ear_broker::Configuration {
             settings: ear_broker::TokenBrokerSettings {
@@ -176,21 +223,30 @@ mod tests {
                 developer_name: "someone".into(),
                 build_name: "0.1.0".into(),
                 profile_name: "tag:github.com,2024:confidential-containers/Trustee".into(),
+                token_format: TokenFormat::Jwt,
             },
             policy_dir: "/var/lib/attestation-service/policies".into(),
             signer: Some(ear_broker::SignerConfig {
                 key_path: "/etc/key".into(),
                 cert_url: Some("https://example.io".into()),
-                cert_path: Some("/etc/cert.pem".into())
+                cert_path: Some("/etc/cert.pem".into()),
+                pkcs11: None,
+                kms: None,
+                rotation: None,
             }),
         }),
         challenge_key_path: None,
+        capture: None,
+        evidence_digest: None,
+        revocation: RevocationConfig::default(),
+        policy_namespaces: Default::default(),
         artifact_server_address: DEFAULT_ARTIFACT_SERVER_ADDRESS.to_string(),
     })]
     #[case("./tests/configs/example5.json", Config {
         work_dir: PathBuf::from("/var/lib/attestation-service/"),
         rvps_config: RvpsConfig::BuiltIn(RvpsCrateConfig {
             storage: ReferenceValueStorageConfig::LocalFs(local_fs::Config::default()),
+            ..Default::default()
         }),
         attestation_token_broker: AttestationTokenConfig::OIDC(oidc::Configuration {
             settings: oidc::TokenBrokerSettings {
@@ -209,10 +265,17 @@ mod tests {
                 key_path: "/etc/key".into(),
                 cert_url: Some("https://example.io".into()),
                 cert_path: Some("/etc/cert.pem".into()),
+                pkcs11: None,
+                kms: None,
+                rotation: None,
             }),
             policy_dir: "/var/lib/attestation-service/policies".into(),
         }),
         challenge_key_path: None,
+        capture: None,
+        evidence_digest: None,
+        revocation: RevocationConfig::default(),
+        policy_namespaces: Default::default(),
         artifact_server_address: DEFAULT_ARTIFACT_SERVER_ADDRESS.to_string(),
     })]
     fn read_config(#[case] config: &str, #[case] expected: Config) {
@@ -3,18 +3,23 @@
 //! # Features
 //! - `rvps-grpc`: The AS will connect a remote RVPS.
 
+pub mod capture;
 pub mod challenge;
 pub mod config;
 pub mod policy_engine;
+pub mod revocation;
 pub mod rvps;
 pub mod token;
 
 mod composite;
 
-use crate::{rvps::ReferenceValueResolver, token::AttestationTokenBroker};
+use crate::{
+    rvps::ReferenceValueResolver,
+    token::{AttestationTokenBroker, TokenFormat},
+};
 pub use challenge::JwtChallenger;
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use canon_json::CanonicalFormatter;
 #[cfg(feature = "fs")]
 use config::Config;
@@ -34,7 +39,7 @@ use tokio::fs;
 use verifier::{InitDataHash, ReportData, TeeEvidenceParsedClaim};
 
 /// Hash algorithms used to calculate runtime/init data binding
-#[derive(Debug, Display, EnumString, AsRefStr, Serialize, Deserialize)]
+#[derive(Clone, Debug, Display, EnumString, AsRefStr, Serialize, Deserialize, PartialEq)]
 pub enum HashAlgorithm {
     #[strum(ascii_case_insensitive)]
     #[serde(rename = "sha256")]
@@ -100,6 +105,10 @@ pub struct TeeClaims {
     init_data_claims: serde_json::Value,
     runtime_data_claims: serde_json::Value,
     additional_data: Option<serde_json::Value>,
+    /// The verified challenge nonce the evidence answers, if any.
+    nonce: Option<String>,
+    /// `<algorithm>:<hex>` digest of the evidence, when enabled.
+    evidence_digest: Option<String>,
 }
 
 /// Runtime Data used to check the binding relationship with report data
@@ -221,6 +230,11 @@ pub struct AttestationService {
     rvps: Arc<dyn RvpsApi>,
     token_broker: Box<dyn AttestationTokenBroker + Send + Sync>,
     challenger: JwtChallenger,
+    #[cfg(feature = "fs")]
+    capture: Option<capture::CaptureStore>,
+    evidence_digest: Option<HashAlgorithm>,
+    revocation: revocation::RevocationRegistry,
+    policy_namespaces: HashMap<String, String>,
 }
 
 /// Transport-neutral runtime status exposed by REST and gRPC AS binaries.
@@ -267,7 +281,18 @@ impl AttestationService {
             None => JwtChallenger::new_with_private_key_default_path().await?,
         };
 
-        Ok(Self::from_components(rvps, token_broker, challenger))
+        let revocation = revocation::RevocationRegistry::new(config.revocation).await?;
+
+        let mut service = Self::from_components(rvps, token_broker, challenger)
+            .with_revocation(revocation)
+            .with_policy_namespaces(config.policy_namespaces);
+        if let Some(algorithm) = config.evidence_digest {
+            service = service.with_evidence_digest(algorithm);
+        }
+        match config.capture {
+            Some(capture) => Ok(service.with_capture(capture::CaptureStore::new(capture)?)),
+            None => Ok(service),
+        }
     }
 
     /// Assemble an [`AttestationService`] from already-constructed component
@@ -282,9 +307,42 @@ impl AttestationService {
             rvps,
             token_broker,
             challenger,
+            #[cfg(feature = "fs")]
+            capture: None,
+            evidence_digest: None,
+            revocation: revocation::RevocationRegistry::default(),
+            policy_namespaces: HashMap::new(),
         }
     }
 
+    /// Use `registry` to index issued tokens and revoke them. By default an
+    /// in-memory registry with the default identity claims is used.
+    pub fn with_revocation(mut self, registry: revocation::RevocationRegistry) -> Self {
+        self.revocation = registry;
+        self
+    }
+
+    /// Let the policies listed in `policy_namespaces` see only the reference
+    /// values of the namespace given for them.
+    pub fn with_policy_namespaces(mut self, policy_namespaces: HashMap<String, String>) -> Self {
+        self.policy_namespaces = policy_namespaces;
+        self
+    }
+
+    /// Embed a digest of every piece of verified evidence, calculated with
+    /// `algorithm` over its canonical JSON form, into the issued tokens.
+    pub fn with_evidence_digest(mut self, algorithm: HashAlgorithm) -> Self {
+        self.evidence_digest = Some(algorithm);
+        self
+    }
+
+    /// Record every evaluation into `store`. See [`capture`].
+    #[cfg(feature = "fs")]
+    pub fn with_capture(mut self, store: capture::CaptureStore) -> Self {
+        self.capture = Some(store);
+        self
+    }
+
     /// Return AS and verifier dependency status without performing network I/O.
     pub async fn status(&self) -> ServiceStatus {
         let dependencies = verifier::dependency_statuses().await;
@@ -343,6 +401,162 @@ impl AttestationService {
         &self,
         verification_requests: Vec<VerificationRequest>,
         policy_ids: Vec<String>,
+    ) -> Result<String> {
+        self.evaluate_with_format(verification_requests, policy_ids, None)
+            .await
+    }
+
+    /// Evaluate Attestation Evidence like [`Self::evaluate`], issuing the
+    /// token in `token_format` instead of the token broker's configured
+    /// format when it is given.
+    pub async fn evaluate_with_format(
+        &self,
+        verification_requests: Vec<VerificationRequest>,
+        policy_ids: Vec<String>,
+        token_format: Option<TokenFormat>,
+    ) -> Result<String> {
+        self.evaluate_in_namespace(verification_requests, policy_ids, token_format, None)
+            .await
+    }
+
+    /// Evaluate Attestation Evidence like [`Self::evaluate_with_format`],
+    /// letting the policies see the reference values of `namespace`.
+    ///
+    /// Policies see the namespace configured for them in
+    /// `policy_namespaces`, and those not listed see the default one. It is
+    /// an error if `namespace` names another one, or if the policies see
+    /// different ones, so a request can't choose which reference values
+    /// its policies are checked against.
+    pub async fn evaluate_in_namespace(
+        &self,
+        verification_requests: Vec<VerificationRequest>,
+        policy_ids: Vec<String>,
+        token_format: Option<TokenFormat>,
+        namespace: Option<&str>,
+    ) -> Result<String> {
+        let namespace = select_namespace(&self.policy_namespaces, namespace, &policy_ids)?;
+
+        #[cfg(feature = "fs")]
+        if let Some(store) = &self.capture {
+            let requests = verification_requests
+                .iter()
+                .map(capture::CapturedRequest::from)
+                .collect();
+            let mut claims = Vec::new();
+            let result = self
+                .evaluate_requests(
+                    verification_requests,
+                    policy_ids.clone(),
+                    &namespace,
+                    token_format,
+                    Some(&mut claims),
+                )
+                .await;
+            if result.is_err() || !store.failures_only() {
+                self.record_capture(store, requests, policy_ids, namespace, claims, &result)
+                    .await;
+            }
+            return result;
+        }
+
+        self.evaluate_requests(
+            verification_requests,
+            policy_ids,
+            &namespace,
+            token_format,
+            None,
+        )
+        .await
+    }
+
+    #[cfg(feature = "fs")]
+    async fn record_capture(
+        &self,
+        store: &capture::CaptureStore,
+        requests: Vec<capture::CapturedRequest>,
+        policy_ids: Vec<String>,
+        namespace: String,
+        claims: Vec<capture::CapturedClaims>,
+        result: &Result<String>,
+    ) {
+        let (id, captured_at) = capture::CaptureStore::new_id();
+        let (verdict, token_claims) = capture::Verdict::from_result(result);
+        let record = capture::Capture {
+            id,
+            captured_at,
+            requests,
+            policy_ids,
+            namespace,
+            claims,
+            verdict,
+            token_claims,
+        };
+        match store.record(&record).await {
+            Ok(path) => info!("Evaluation captured to {}", path.display()),
+            Err(e) => log::warn!("Failed to capture evaluation {}: {e:#}", record.id),
+        }
+    }
+
+    /// Replay a capture against the current policies and reference values
+    /// and compare the verdict with the captured one.
+    pub async fn replay(
+        &self,
+        capture: &capture::Capture,
+        mode: capture::ReplayMode,
+    ) -> Result<capture::ReplayReport> {
+        let namespace = select_namespace(
+            &self.policy_namespaces,
+            Some(capture.namespace.as_str()),
+            &capture.policy_ids,
+        )?;
+        let result = match mode {
+            capture::ReplayMode::Claims => {
+                if capture.claims.is_empty() {
+                    bail!(
+                        "capture {} has no parsed claims, its evidence failed verification",
+                        capture.id
+                    );
+                }
+                let tee_claims = capture.claims.iter().map(TeeClaims::from).collect();
+                let reference_value_resolver = Arc::new(ReferenceValueResolver::new(
+                    Arc::clone(&self.rvps),
+                    &namespace,
+                ));
+                self.token_broker
+                    .issue(
+                        tee_claims,
+                        capture.policy_ids.clone(),
+                        reference_value_resolver,
+                    )
+                    .await
+            }
+            capture::ReplayMode::Evidence => {
+                let requests = capture
+                    .requests
+                    .iter()
+                    .map(VerificationRequest::try_from)
+                    .collect::<Result<Vec<_>>>()?;
+                self.evaluate_requests(requests, capture.policy_ids.clone(), &namespace, None, None)
+                    .await
+            }
+        };
+
+        let (replayed, token_claims) = capture::Verdict::from_result(&result);
+        Ok(capture::ReplayReport {
+            capture_id: capture.id.clone(),
+            original: capture.verdict.clone(),
+            replayed,
+            token_claims,
+        })
+    }
+
+    async fn evaluate_requests(
+        &self,
+        verification_requests: Vec<VerificationRequest>,
+        policy_ids: Vec<String>,
+        namespace: &str,
+        token_format: Option<TokenFormat>,
+        captured_claims: Option<&mut Vec<capture::CapturedClaims>>,
     ) -> Result<String> {
         if verification_requests.is_empty() {
             return Err(AttestationError::InvalidRequest {
@@ -358,19 +572,39 @@ impl AttestationService {
         let mut tee_claims: Vec<TeeClaims> = vec![];
 
         for (request_index, verification_request) in verification_requests.into_iter().enumerate() {
-            // Verify challenge token
+            // Verify challenge token. Its nonce, or else the nonce of the KBS
+            // protocol challenge, is echoed in the token. Both are bound to
+            // the evidence through the runtime data.
+            let mut nonce = None;
             if let Some(RuntimeData::Structured(v)) = &verification_request.runtime_data {
                 if let Some(challenge_token) = v.get("challenge_token").and_then(|x| x.as_str()) {
-                    self.challenger
+                    let challenge_nonce = self
+                        .challenger
                         .verify_challenge_token(challenge_token)
                         .await
                         .map_err(|source| AttestationError::InvalidChallengeToken {
                             request_index,
                             source,
                         })?;
+                    nonce = Some(challenge_nonce);
+                } else if let Some(kbs_nonce) = v.get("nonce").and_then(|x| x.as_str()) {
+                    nonce = Some(kbs_nonce.to_string());
                 }
             }
 
+            let evidence_digest = match &self.evidence_digest {
+                Some(algorithm) => {
+                    let evidence = serialize_canon_json(&verification_request.evidence)
+                        .context("serialize evidence")?;
+                    Some(format!(
+                        "{}:{}",
+                        algorithm.as_ref().to_lowercase(),
+                        hex::encode(algorithm.accumulate_hash(evidence))
+                    ))
+                }
+                None => None,
+            };
+
             let verifier = verifier::to_verifier(&verification_request.tee).map_err(|source| {
                 AttestationError::UnsupportedTee {
                     request_index,
@@ -435,55 +669,161 @@ impl AttestationService {
                 init_data_claims,
                 runtime_data_claims,
                 additional_data,
+                nonce,
+                evidence_digest,
             });
         }
 
-        let reference_value_resolver =
-            Arc::new(ReferenceValueResolver::new(Arc::clone(&self.rvps)));
+        if let Some(captured_claims) = captured_claims {
+            captured_claims.extend(tee_claims.iter().map(capture::CapturedClaims::from));
+        }
+
+        let identities = self.revocation.identities(&tee_claims);
+        let reference_value_resolver = Arc::new(ReferenceValueResolver::new(
+            Arc::clone(&self.rvps),
+            namespace,
+        ));
 
         let attestation_results_token = self
             .token_broker
-            .issue(tee_claims, policy_ids, reference_value_resolver)
+            .issue_with_format(
+                tee_claims,
+                policy_ids,
+                Arc::clone(&reference_value_resolver),
+                token_format,
+            )
+            .await?;
+        self.revocation
+            .record(
+                &attestation_results_token,
+                identities,
+                reference_value_resolver.consulted().await,
+            )
             .await?;
         Ok(attestation_results_token)
     }
 
+    /// Revoke issued tokens until `expires_at`, or until the revocation is
+    /// withdrawn. Returns the `jti`s of the tokens revoked.
+    pub async fn revoke(
+        &self,
+        revocation: revocation::Revocation,
+        reason: Option<String>,
+        expires_at: Option<i64>,
+    ) -> Result<Vec<String>> {
+        self.revocation
+            .revoke(revocation, reason, expires_at)
+            .await
+            .context("revoke tokens")
+    }
+
+    /// Withdraw a revocation. Returns the `jti`s of the tokens reinstated.
+    pub async fn unrevoke(&self, revocation: &revocation::Revocation) -> Result<Vec<String>> {
+        self.revocation
+            .unrevoke(revocation)
+            .await
+            .context("withdraw revocation")
+    }
+
+    /// Get the revocation list.
+    pub async fn revocation_list(&self) -> revocation::RevocationList {
+        self.revocation.list().await
+    }
+
+    /// Introspect a token issued by this AS.
+    pub async fn introspect(&self, token: &str) -> revocation::Introspection {
+        self.revocation.introspect(token).await
+    }
+
     /// Registry a new reference value
     pub async fn register_reference_value(&self, message: &str) -> Result<()> {
+        self.register_reference_value_in(rvps::DEFAULT_NAMESPACE, message, None)
+            .await
+    }
+
+    /// Registry a new reference value to `namespace`, presenting
+    /// `admin_token` if the namespace is configured with admin tokens.
+    pub async fn register_reference_value_in(
+        &self,
+        namespace: &str,
+        message: &str,
+        admin_token: Option<&str>,
+    ) -> Result<()> {
         self.rvps
-            .verify_and_extract(message)
+            .verify_and_extract(namespace, message, admin_token)
             .await
             .context("register reference value")
     }
 
     /// Set reference value list via RVPS
     pub async fn set_reference_value_list(&self, payload: &str) -> Result<()> {
+        self.set_reference_value_list_in(rvps::DEFAULT_NAMESPACE, payload, None)
+            .await
+    }
+
+    /// Set reference value list of `namespace` via RVPS
+    pub async fn set_reference_value_list_in(
+        &self,
+        namespace: &str,
+        payload: &str,
+        admin_token: Option<&str>,
+    ) -> Result<()> {
         self.rvps
-            .set_reference_value_list(payload)
+            .set_reference_value_list(namespace, payload, admin_token)
             .await
             .context("set reference value list")
     }
 
     /// Delete a reference value by name
     pub async fn delete_reference_value(&self, name: String) -> Result<bool> {
+        self.delete_reference_value_in(rvps::DEFAULT_NAMESPACE, name, None)
+            .await
+    }
+
+    /// Delete a reference value of `namespace` by name
+    pub async fn delete_reference_value_in(
+        &self,
+        namespace: &str,
+        name: String,
+        admin_token: Option<&str>,
+    ) -> Result<bool> {
         self.rvps
-            .delete_reference_value(&name)
+            .delete_reference_value(namespace, &name, admin_token)
             .await
             .context("delete reference value")
     }
 
     /// Query Reference Values
     pub async fn query_reference_values(&self) -> Result<HashMap<String, Value>> {
+        self.query_reference_values_in(rvps::DEFAULT_NAMESPACE)
+            .await
+    }
+
+    /// Query Reference Values of `namespace`
+    pub async fn query_reference_values_in(
+        &self,
+        namespace: &str,
+    ) -> Result<HashMap<String, Value>> {
         self.rvps
-            .get_reference_values()
+            .get_reference_values(namespace)
             .await
             .context("query reference values")
     }
 
     /// Query one Reference Value by identifier.
     pub async fn query_reference_value(&self, reference_value_id: &str) -> Result<Option<Value>> {
+        self.query_reference_value_in(rvps::DEFAULT_NAMESPACE, reference_value_id)
+            .await
+    }
+
+    /// Query one Reference Value of `namespace` by identifier.
+    pub async fn query_reference_value_in(
+        &self,
+        namespace: &str,
+        reference_value_id: &str,
+    ) -> Result<Option<Value>> {
         self.rvps
-            .query_reference_value(reference_value_id)
+            .query_reference_value(namespace, reference_value_id)
             .await
             .context("query reference value")
     }
@@ -570,6 +910,53 @@ impl AttestationService {
     }
 }
 
+/// The namespace of the reference values the policies `policy_ids` see,
+/// see [`AttestationService::evaluate_in_namespace`].
+fn select_namespace(
+    policy_namespaces: &HashMap<String, String>,
+    requested: Option<&str>,
+    policy_ids: &[String],
+) -> Result<String> {
+    // Entries may map tee classes to policies, see `PolicySelection`.
+    let mut namespaces = policy_ids
+        .iter()
+        .flat_map(|ids| ids.split(','))
+        .map(|entry| entry.split_once('=').map_or(entry, |(_, id)| id).trim())
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            let namespace = policy_namespaces
+                .get(id)
+                .map_or(rvps::DEFAULT_NAMESPACE, String::as_str);
+            (id, namespace)
+        });
+
+    let (policy_id, namespace) = namespaces
+        .next()
+        .unwrap_or(("default", rvps::DEFAULT_NAMESPACE));
+    if let Some((other_id, other)) = namespaces.find(|(_, other)| *other != namespace) {
+        return Err(AttestationError::InvalidRequest {
+            request_index: None,
+            field: "policy_ids",
+            source: anyhow!(
+                "policy {policy_id} sees namespace {namespace}, policy {other_id} sees {other}"
+            ),
+        }
+        .into());
+    }
+    if let Some(requested) =
+        requested.filter(|requested| !requested.is_empty() && *requested != namespace)
+    {
+        return Err(AttestationError::InvalidRequest {
+            request_index: None,
+            field: "namespace",
+            source: anyhow!("policy {policy_id} sees namespace {namespace}, not {requested}"),
+        }
+        .into());
+    }
+
+    Ok(namespace.to_string())
+}
+
 /// Get the expected runtime data and potential claims due to the given input
 /// and the hash algorithm
 fn parse_runtime_data(
@@ -617,6 +1004,31 @@ mod tests {
 
     use crate::{HashAlgorithm, RuntimeData};
 
+    #[rstest]
+    #[case(None, &[], Some("default"))]
+    #[case(Some(""), &["other"], Some("default"))]
+    #[case(Some("default"), &["other"], Some("default"))]
+    #[case(Some("team-a"), &["other"], None)]
+    #[case(Some("team-a"), &[], None)]
+    #[case(None, &["tenant"], Some("team-a"))]
+    #[case(Some("team-a"), &["cpu=tenant,gpu=tenant"], Some("team-a"))]
+    #[case(None, &["cpu=tenant,gpu=other"], None)]
+    #[case(Some("team-b"), &["tenant"], None)]
+    #[case(None, &["tenant", "tenant-b"], None)]
+    fn select_namespace(
+        #[case] requested: Option<&str>,
+        #[case] policy_ids: &[&str],
+        #[case] expected: Option<&str>,
+    ) {
+        let policy_namespaces = std::collections::HashMap::from([
+            ("tenant".to_string(), "team-a".to_string()),
+            ("tenant-b".to_string(), "team-b".to_string()),
+        ]);
+        let policy_ids: Vec<String> = policy_ids.iter().map(|id| id.to_string()).collect();
+        let namespace = crate::select_namespace(&policy_namespaces, requested, &policy_ids);
+        assert_eq!(namespace.ok().as_deref(), expected);
+    }
+
     #[rstest]
     #[case(Some(RuntimeData::Raw(b"aaaaa".to_vec())), Some(b"aaaaa".to_vec()), HashAlgorithm::Sha384, Value::Null)]
     #[case(None, None, HashAlgorithm::Sha384, Value::Null)]
@@ -11,6 +11,7 @@ use strum::EnumString;
 use thiserror::Error;
 
 pub mod opa;
+pub mod simulator;
 
 #[derive(Error, Debug)]
 pub enum PolicyError {
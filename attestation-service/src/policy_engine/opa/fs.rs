@@ -204,7 +204,7 @@ impl PolicyEngine for OPA {
 mod tests {
     use crate::{
         config::DEFAULT_ARTIFACT_SERVER_ADDRESS,
-        rvps::{RvpsApi, RvpsError},
+        rvps::{RvpsApi, RvpsError, DEFAULT_NAMESPACE},
     };
     use anyhow::anyhow;
     use ear::TrustVector;
@@ -327,19 +327,27 @@ default allow = true"
     #[cfg(feature = "policy-rvps")]
     #[async_trait::async_trait]
     impl RvpsApi for CountingRvps {
-        async fn verify_and_extract(&self, _message: &str) -> std::result::Result<(), RvpsError> {
+        async fn verify_and_extract(
+            &self,
+            _namespace: &str,
+            _message: &str,
+            _admin_token: Option<&str>,
+        ) -> std::result::Result<(), RvpsError> {
             unreachable!()
         }
 
         async fn set_reference_value_list(
             &self,
+            _namespace: &str,
             _payload: &str,
+            _admin_token: Option<&str>,
         ) -> std::result::Result<(), RvpsError> {
             unreachable!()
         }
 
         async fn query_reference_value(
             &self,
+            _namespace: &str,
             reference_value_id: &str,
         ) -> std::result::Result<Option<serde_json::Value>, RvpsError> {
             self.keyed_queries.fetch_add(1, Ordering::SeqCst);
@@ -352,6 +360,7 @@ default allow = true"
 
         async fn get_reference_values(
             &self,
+            _namespace: &str,
         ) -> std::result::Result<HashMap<String, serde_json::Value>, RvpsError> {
             self.bulk_queries.fetch_add(1, Ordering::SeqCst);
             Ok(HashMap::new())
@@ -359,7 +368,9 @@ default allow = true"
 
         async fn delete_reference_value(
             &self,
+            _namespace: &str,
             _name: &str,
+            _admin_token: Option<&str>,
         ) -> std::result::Result<bool, RvpsError> {
             unreachable!()
         }
@@ -390,7 +401,8 @@ svn_again = query_reference_value("svn")
             bulk_queries: AtomicUsize::new(0),
         });
         let resolver = Arc::new(ReferenceValueResolver::new(
-            Arc::clone(&rvps) as Arc<dyn RvpsApi>
+            Arc::clone(&rvps) as Arc<dyn RvpsApi>,
+            DEFAULT_NAMESPACE,
         ));
 
         let result = opa
@@ -430,19 +442,27 @@ svn_again = query_reference_value("svn")
     #[cfg(feature = "policy-rvps")]
     #[async_trait::async_trait]
     impl RvpsApi for UnavailableRvps {
-        async fn verify_and_extract(&self, _message: &str) -> std::result::Result<(), RvpsError> {
+        async fn verify_and_extract(
+            &self,
+            _namespace: &str,
+            _message: &str,
+            _admin_token: Option<&str>,
+        ) -> std::result::Result<(), RvpsError> {
             unreachable!()
         }
 
         async fn set_reference_value_list(
             &self,
+            _namespace: &str,
             _payload: &str,
+            _admin_token: Option<&str>,
         ) -> std::result::Result<(), RvpsError> {
             unreachable!()
         }
 
         async fn query_reference_value(
             &self,
+            _namespace: &str,
             _reference_value_id: &str,
         ) -> std::result::Result<Option<serde_json::Value>, RvpsError> {
             if self.timeout {
@@ -454,13 +474,16 @@ svn_again = query_reference_value("svn")
 
         async fn get_reference_values(
             &self,
+            _namespace: &str,
         ) -> std::result::Result<HashMap<String, serde_json::Value>, RvpsError> {
             unreachable!()
         }
 
         async fn delete_reference_value(
             &self,
+            _namespace: &str,
             _name: &str,
+            _admin_token: Option<&str>,
         ) -> std::result::Result<bool, RvpsError> {
             unreachable!()
         }
@@ -479,9 +502,10 @@ allow = query_reference_value("svn") == [7]
             DEFAULT_ARTIFACT_SERVER_ADDRESS,
         )
         .unwrap();
-        let resolver = Arc::new(ReferenceValueResolver::new(Arc::new(UnavailableRvps {
-            timeout,
-        })));
+        let resolver = Arc::new(ReferenceValueResolver::new(
+            Arc::new(UnavailableRvps { timeout }),
+            DEFAULT_NAMESPACE,
+        ));
 
         opa.evaluate("{}", "query", vec!["allow".to_string()], resolver)
             .await
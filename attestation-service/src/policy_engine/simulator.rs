@@ -0,0 +1,443 @@
+//! Policy test harness: evaluates a policy against recorded test cases with
+//! the same engine path as live attestation, but with reference values taken
+//! from the test case instead of RVPS.
+//!
+//! A test case is a JSON file:
+//!
+//! ```json
+//! {
+//!     "name": "tdx-debug-guest-is-rejected",
+//!     "tee": "tdx",
+//!     "claims": { "...": "parsed claims as produced by the verifier" },
+//!     "reference_values": { "measurement.kernel.SHA-384": ["5b7a..."] },
+//!     "expected": {
+//!         "rules_result": { "hardware": 2 },
+//!         "ear_status": "affirming"
+//!     }
+//! }
+//! ```
+//!
+//! When `tee` is set, `claims` are transformed into the EAR policy input like
+//! the EAR broker does. Without it, `claims` is passed to the policy verbatim.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context};
+use kbs_types::Tee;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::opa::OPAInMemory;
+use super::PolicyEngine;
+use crate::rvps::{ReferenceValueResolver, RvpsApi, RvpsError, DEFAULT_NAMESPACE};
+use crate::token::ear_broker;
+
+type Result<T> = std::result::Result<T, RvpsError>;
+
+/// Policy id the policy under test is loaded as.
+const POLICY_ID: &str = "policy-under-test";
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PolicyTestCase {
+    /// Name reported for the case. Defaults to the file stem.
+    #[serde(default)]
+    pub name: String,
+
+    /// TEE the claims were produced by.
+    #[serde(default)]
+    pub tee: Option<Tee>,
+
+    /// Parsed TEE claims.
+    pub claims: Value,
+
+    #[serde(default)]
+    pub init_data_claims: Value,
+
+    #[serde(default)]
+    pub runtime_data_claims: Value,
+
+    /// Reference values served to `query_reference_value` and `data.reference`.
+    #[serde(default)]
+    pub reference_values: HashMap<String, Value>,
+
+    /// Rules to evaluate. Defaults to the EAR trust claims.
+    #[serde(default)]
+    pub rules: Option<Vec<String>>,
+
+    pub expected: PolicyTestExpectation,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PolicyTestExpectation {
+    /// Expected value of each listed rule. Rules not listed are not checked.
+    #[serde(default)]
+    pub rules_result: HashMap<String, Value>,
+
+    /// Expected `ear.status` of the appraisal, e.g. `affirming`.
+    #[serde(default)]
+    pub ear_status: Option<String>,
+
+    /// The evaluation is expected to fail.
+    #[serde(default)]
+    pub error: bool,
+}
+
+/// A difference between the expected and the actual outcome of a case.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    pub field: String,
+    pub expected: Value,
+    pub actual: Value,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, got {}",
+            self.field, self.expected, self.actual
+        )
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PolicyTestReport {
+    pub name: String,
+    pub rules_result: BTreeMap<String, Value>,
+    pub ear_status: Option<String>,
+    pub error: Option<String>,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl PolicyTestReport {
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Evaluates one policy against test cases.
+pub struct PolicyTestHarness {
+    engine: OPAInMemory,
+}
+
+impl PolicyTestHarness {
+    /// Load the rego `policy`. `artifact_server_address` is used by
+    /// `query_artifact_server` calls, which are not replaced by fixtures.
+    pub fn new(policy: &str, artifact_server_address: &str) -> anyhow::Result<Self> {
+        regorus::Engine::new()
+            .add_policy(POLICY_ID.to_string(), policy.to_string())
+            .context("load policy")?;
+
+        let engine =
+            OPAInMemory::with_raw_default_policy(policy, POLICY_ID, artifact_server_address)?;
+        Ok(Self { engine })
+    }
+
+    /// Evaluate `case` and compare the outcome with its expectation.
+    pub async fn run(&self, case: &PolicyTestCase) -> PolicyTestReport {
+        let mut report = PolicyTestReport {
+            name: case.name.clone(),
+            rules_result: BTreeMap::new(),
+            ear_status: None,
+            error: None,
+            mismatches: Vec::new(),
+        };
+
+        match self.evaluate(case).await {
+            Ok((rules_result, ear_status)) => {
+                report.rules_result = rules_result;
+                report.ear_status = ear_status;
+            }
+            Err(e) => report.error = Some(format!("{e:#}")),
+        }
+
+        let expected = &case.expected;
+        match (&report.error, expected.error) {
+            (Some(error), false) => report.mismatches.push(Mismatch {
+                field: "error".to_string(),
+                expected: Value::Null,
+                actual: Value::String(error.clone()),
+            }),
+            (None, true) => report.mismatches.push(Mismatch {
+                field: "error".to_string(),
+                expected: Value::String("an evaluation error".to_string()),
+                actual: Value::Null,
+            }),
+            _ => {}
+        }
+        if report.error.is_some() {
+            return report;
+        }
+
+        let mut rules: Vec<_> = expected.rules_result.iter().collect();
+        rules.sort_by(|a, b| a.0.cmp(b.0));
+        for (rule, value) in rules {
+            let actual = report
+                .rules_result
+                .get(rule)
+                .cloned()
+                .unwrap_or(Value::Null);
+            if &actual != value {
+                report.mismatches.push(Mismatch {
+                    field: format!("rules_result.{rule}"),
+                    expected: value.clone(),
+                    actual,
+                });
+            }
+        }
+
+        if let Some(status) = &expected.ear_status {
+            let matches = report
+                .ear_status
+                .as_ref()
+                .is_some_and(|actual| actual.eq_ignore_ascii_case(status));
+            if !matches {
+                report.mismatches.push(Mismatch {
+                    field: "ear_status".to_string(),
+                    expected: Value::String(status.clone()),
+                    actual: report.ear_status.clone().map_or(Value::Null, Value::String),
+                });
+            }
+        }
+
+        report
+    }
+
+    async fn evaluate(
+        &self,
+        case: &PolicyTestCase,
+    ) -> anyhow::Result<(BTreeMap<String, Value>, Option<String>)> {
+        let input = match case.tee {
+            Some(tee) => serde_json::to_string(&ear_broker::transform_claims(
+                case.claims.clone(),
+                case.init_data_claims.clone(),
+                case.runtime_data_claims.clone(),
+                tee,
+            )?)?,
+            None => case.claims.to_string(),
+        };
+
+        let trust_claims = ear_broker::trust_vector_rules();
+        let rules = case.rules.clone().unwrap_or_else(|| trust_claims.clone());
+        let rvps = Arc::new(FixtureRvps {
+            values: case.reference_values.clone(),
+        }) as Arc<dyn RvpsApi>;
+
+        let result = self
+            .engine
+            .evaluate(
+                &input,
+                POLICY_ID,
+                rules,
+                Arc::new(ReferenceValueResolver::new(rvps, DEFAULT_NAMESPACE)),
+            )
+            .await?;
+
+        // The status is only meaningful for the EAR trust claims.
+        let trust_results: HashMap<String, Value> = result
+            .rules_result
+            .iter()
+            .filter(|(rule, _)| trust_claims.contains(rule))
+            .map(|(rule, value)| (rule.clone(), value.clone()))
+            .collect();
+        let ear_status = if trust_results.is_empty() {
+            None
+        } else {
+            let appraisal = ear_broker::appraisal_from_rules(&trust_results)?;
+            serde_json::to_value(&appraisal)?["ear.status"]
+                .as_str()
+                .map(str::to_string)
+        };
+
+        Ok((result.rules_result.into_iter().collect(), ear_status))
+    }
+}
+
+/// Load every `*.json` test case of `dir`, sorted by file name.
+pub fn load_test_cases(dir: &Path) -> anyhow::Result<Vec<PolicyTestCase>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("read test case directory {}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<_>>()?;
+    paths.retain(|p| p.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+
+    if paths.is_empty() {
+        bail!("no *.json test cases in {}", dir.display());
+    }
+
+    paths
+        .iter()
+        .map(|path| -> anyhow::Result<PolicyTestCase> {
+            let raw = std::fs::read_to_string(path)
+                .with_context(|| format!("read test case {}", path.display()))?;
+            let mut case: PolicyTestCase = serde_json::from_str(&raw)
+                .with_context(|| format!("parse test case {}", path.display()))?;
+            if case.name.is_empty() {
+                case.name = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .ok_or_else(|| anyhow!("test case path {} has no name", path.display()))?;
+            }
+            Ok(case)
+        })
+        .collect()
+}
+
+/// Read-only RVPS backed by the reference values of a test case.
+struct FixtureRvps {
+    values: HashMap<String, Value>,
+}
+
+#[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait::async_trait(?Send))]
+#[cfg_attr(
+    not(all(
+        target_arch = "wasm32",
+        target_vendor = "unknown",
+        target_os = "unknown"
+    )),
+    async_trait::async_trait
+)]
+impl RvpsApi for FixtureRvps {
+    async fn verify_and_extract(
+        &self,
+        _namespace: &str,
+        _message: &str,
+        _admin_token: Option<&str>,
+    ) -> Result<()> {
+        Err(anyhow!("fixture RVPS is read-only").into())
+    }
+
+    async fn set_reference_value_list(
+        &self,
+        _namespace: &str,
+        _payload: &str,
+        _admin_token: Option<&str>,
+    ) -> Result<()> {
+        Err(anyhow!("fixture RVPS is read-only").into())
+    }
+
+    async fn query_reference_value(
+        &self,
+        _namespace: &str,
+        reference_value_id: &str,
+    ) -> Result<Option<Value>> {
+        Ok(self.values.get(reference_value_id).cloned())
+    }
+
+    async fn get_reference_values(&self, _namespace: &str) -> Result<HashMap<String, Value>> {
+        Ok(self.values.clone())
+    }
+
+    async fn delete_reference_value(
+        &self,
+        _namespace: &str,
+        _name: &str,
+        _admin_token: Option<&str>,
+    ) -> Result<bool> {
+        Err(anyhow!("fixture RVPS is read-only").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"package policy
+import rego.v1
+
+default hardware := 97
+
+hardware := 2 if {
+    input.sample.svn in query_reference_value("svn")
+}
+"#;
+
+    fn case(svn: &str, expected_hardware: i64, status: &str) -> PolicyTestCase {
+        serde_json::from_value(serde_json::json!({
+            "name": format!("svn-{svn}"),
+            "tee": "sample",
+            "claims": {"svn": svn},
+            "reference_values": {"svn": ["1", "2"]},
+            "expected": {
+                "rules_result": {"hardware": expected_hardware},
+                "ear_status": status
+            }
+        }))
+        .unwrap()
+    }
+
+    #[cfg(feature = "policy-rvps")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reports_pass_and_diffs() {
+        let harness = PolicyTestHarness::new(POLICY, "http://127.0.0.1:1").unwrap();
+
+        let report = harness.run(&case("1", 2, "affirming")).await;
+        assert!(report.passed(), "{:?}", report.mismatches);
+
+        let report = harness.run(&case("3", 2, "affirming")).await;
+        assert_eq!(
+            report.mismatches,
+            vec![
+                Mismatch {
+                    field: "rules_result.hardware".to_string(),
+                    expected: serde_json::json!(2),
+                    actual: serde_json::json!(97),
+                },
+                Mismatch {
+                    field: "ear_status".to_string(),
+                    expected: serde_json::json!("affirming"),
+                    actual: serde_json::json!("warning"),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn expected_errors_are_checked() {
+        let harness =
+            PolicyTestHarness::new("package policy\nhardware := 1000", "http://127.0.0.1:1")
+                .unwrap();
+        let mut case = case("1", 2, "affirming");
+        assert!(!harness.run(&case).await.passed());
+
+        case.expected = PolicyTestExpectation {
+            error: true,
+            ..Default::default()
+        };
+        assert!(harness.run(&case).await.passed());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn example_policy_cases_pass() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/coco-as/policy");
+        let policy = std::fs::read_to_string(dir.join("example-2.rego")).unwrap();
+        let harness = PolicyTestHarness::new(&policy, "http://127.0.0.1:1").unwrap();
+
+        for case in load_test_cases(&dir.join("example-2-tests")).unwrap() {
+            let report = harness.run(&case).await;
+            assert!(report.passed(), "{}: {:?}", report.name, report.mismatches);
+        }
+    }
+
+    #[test]
+    fn invalid_policy_is_rejected() {
+        assert!(PolicyTestHarness::new("package policy\nallow {", "http://127.0.0.1:1").is_err());
+    }
+
+    #[test]
+    fn loads_cases_sorted_and_named_after_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = r#"{"claims": {}, "expected": {}}"#;
+        std::fs::write(dir.path().join("b.json"), body).unwrap();
+        std::fs::write(dir.path().join("a.json"), body).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let cases = load_test_cases(dir.path()).unwrap();
+        let names: Vec<_> = cases.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+    }
+}
@@ -0,0 +1,728 @@
+//! Revocation of issued attestation tokens.
+//!
+//! A relying party trusts an attestation token until it expires. The
+//! revocation list withdraws tokens before that: a single token by its `jti`,
+//! every token of a TEE identified by one of its parsed claims (a chip id or
+//! an FMSPC, for example), or every token whose appraisal consulted a
+//! reference value.
+//!
+//! The AS indexes the tokens it issues until they expire, so a rule resolves
+//! to the `jti`s of the tokens it affects. Those are published with the rules
+//! and can be fetched by relying parties such as the KBS. A token can also be
+//! checked with RFC 7662 style introspection. Evaluations whose evidence
+//! matches a rule are refused.
+//!
+//! A rule lapses at its `expires_at`, a rule revoking an indexed token at the
+//! token's expiration. Withdrawing a rule reinstates the indexed tokens no
+//! other rule matches.
+//!
+//! The index lives in memory. Rules and revoked `jti`s are persisted to
+//! `RevocationConfig::path` when it is set, but tokens issued before a restart
+//! are not matched by rules added after it.
+
+use std::collections::HashMap;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use coset::cbor::Value as CborValue;
+use coset::{CborSerializable, CoseSign1, TaggedCborSerializable};
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+
+use crate::TeeClaims;
+
+/// CWT claim keys (RFC 8392) of the claims the index needs.
+const CWT_EXP: i64 = 4;
+const CWT_IAT: i64 = 6;
+const CWT_CTI: i64 = 7;
+
+fn default_identity_claims() -> Vec<String> {
+    vec!["chip_id".into(), "fmspc".into()]
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct RevocationConfig {
+    /// File the revocation list is persisted to. Kept in memory only when
+    /// unset.
+    #[cfg(feature = "fs")]
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    /// Parsed claims that identify a TEE. A claim matches by its full
+    /// flattened name (`report.chip_id`) or by its last segment (`chip_id`).
+    /// Default: `chip_id` and `fmspc`.
+    #[serde(default = "default_identity_claims")]
+    pub identity_claims: Vec<String>,
+}
+
+impl Default for RevocationConfig {
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "fs")]
+            path: None,
+            identity_claims: default_identity_claims(),
+        }
+    }
+}
+
+/// What a revocation withdraws.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Revocation {
+    /// One token.
+    Token { jti: String },
+
+    /// Every token of the TEE whose identity claim `claim` is `value`.
+    TeeIdentity { claim: String, value: String },
+
+    /// Every token whose appraisal consulted the reference value `name`.
+    ReferenceValue { name: String },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RevocationEntry {
+    #[serde(flatten)]
+    pub revocation: Revocation,
+
+    /// Unix timestamp (seconds) of the revocation.
+    pub revoked_at: i64,
+
+    /// Unix timestamp (seconds) the rule lapses at. Kept until withdrawn
+    /// when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RevokedToken {
+    pub jti: String,
+
+    /// Expiration of the token. It is dropped from the list afterwards.
+    pub exp: i64,
+}
+
+/// The published revocation list.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct RevocationList {
+    pub entries: Vec<RevocationEntry>,
+    pub revoked_tokens: Vec<RevokedToken>,
+}
+
+/// RFC 7662 introspection response.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Introspection {
+    pub active: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+}
+
+impl Introspection {
+    fn inactive() -> Self {
+        Self {
+            active: false,
+            jti: None,
+            iat: None,
+            exp: None,
+        }
+    }
+}
+
+/// The claims of an issued token the index needs.
+#[derive(Debug, PartialEq)]
+struct TokenId {
+    jti: String,
+    iat: Option<i64>,
+    exp: i64,
+}
+
+impl TokenId {
+    /// Read the `jti`, `iat` and `exp` of a JWT or of a COSE encoded EAR.
+    fn from_token(token: &str) -> Result<Self> {
+        if token.contains('.') {
+            let payload = token
+                .split('.')
+                .nth(1)
+                .ok_or(anyhow!("Illegal JWT format"))?;
+            let claims: Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload)?)
+                .context("Illegal JWT claims")?;
+            return Ok(Self {
+                jti: claims["jti"]
+                    .as_str()
+                    .ok_or(anyhow!("token has no jti"))?
+                    .to_string(),
+                iat: claims["iat"].as_i64(),
+                exp: claims["exp"].as_i64().ok_or(anyhow!("token has no exp"))?,
+            });
+        }
+
+        let bytes = URL_SAFE_NO_PAD.decode(token)?;
+        let sign1 = CoseSign1::from_tagged_slice(&bytes)
+            .or_else(|_| CoseSign1::from_slice(&bytes))
+            .map_err(|e| anyhow!("Illegal COSE token: {e}"))?;
+        let payload = sign1.payload.ok_or(anyhow!("COSE token has no payload"))?;
+        let claims: CborValue =
+            coset::cbor::from_reader(payload.as_slice()).context("Illegal CWT claims")?;
+        let claims = claims.as_map().ok_or(anyhow!("Illegal CWT claims"))?;
+        let claim = |key: i64| {
+            claims
+                .iter()
+                .find(|(label, _)| *label == CborValue::Integer(key.into()))
+                .map(|(_, value)| value)
+        };
+        let integer = |key: i64| {
+            claim(key)
+                .and_then(CborValue::as_integer)
+                .and_then(|value| i64::try_from(value).ok())
+        };
+        // The `cti` is a byte string holding the UTF-8 bytes of the `jti`.
+        let jti = match claim(CWT_CTI) {
+            Some(CborValue::Bytes(bytes)) => {
+                String::from_utf8(bytes.clone()).context("Illegal CWT cti")?
+            }
+            Some(CborValue::Text(text)) => text.clone(),
+            _ => bail!("token has no jti"),
+        };
+        Ok(Self {
+            jti,
+            iat: integer(CWT_IAT),
+            exp: integer(CWT_EXP).ok_or(anyhow!("token has no exp"))?,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct IssuedToken {
+    id: TokenId,
+    identities: Vec<(String, String)>,
+    reference_values: Vec<String>,
+}
+
+impl IssuedToken {
+    fn matches(&self, revocation: &Revocation) -> bool {
+        match revocation {
+            Revocation::Token { jti } => self.id.jti == *jti,
+            Revocation::TeeIdentity { claim, value } => {
+                self.identities.iter().any(|(name, identity)| {
+                    identity == value
+                        && (name == claim || name.rsplit('.').next() == Some(claim.as_str()))
+                })
+            }
+            Revocation::ReferenceValue { name } => self.reference_values.contains(name),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    list: RevocationList,
+    /// Issued tokens by the SHA-256 digest of the token.
+    issued: HashMap<String, IssuedToken>,
+}
+
+impl State {
+    fn prune(&mut self, now: i64) {
+        self.issued.retain(|_, token| token.id.exp >= now);
+        self.list.revoked_tokens.retain(|token| token.exp >= now);
+        self.list
+            .entries
+            .retain(|entry| entry.expires_at.is_none_or(|expires_at| expires_at >= now));
+    }
+
+    /// Tokens revoked by `jti` are listed in the rules as well, as they may
+    /// not be indexed.
+    fn is_revoked(&self, jti: &str) -> bool {
+        self.list.revoked_tokens.iter().any(|token| token.jti == jti)
+            || self.list.entries.iter().any(|entry| {
+                matches!(&entry.revocation, Revocation::Token { jti: revoked } if revoked == jti)
+            })
+    }
+}
+
+pub struct RevocationRegistry {
+    config: RevocationConfig,
+    state: RwLock<State>,
+}
+
+impl Default for RevocationRegistry {
+    fn default() -> Self {
+        Self {
+            config: RevocationConfig::default(),
+            state: RwLock::new(State::default()),
+        }
+    }
+}
+
+fn now() -> i64 {
+    time::OffsetDateTime::now_utc().unix_timestamp()
+}
+
+fn token_digest(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Flatten `claims` into `a.b.c` names and keep the string or number leaves.
+fn flatten(prefix: &str, claims: &Value, out: &mut Vec<(String, String)>) {
+    let name = |key: &str| match prefix {
+        "" => key.to_string(),
+        prefix => format!("{prefix}.{key}"),
+    };
+    match claims {
+        Value::Object(map) => {
+            for (key, value) in map {
+                flatten(&name(key), value, out);
+            }
+        }
+        Value::String(value) => out.push((prefix.to_string(), value.clone())),
+        Value::Number(value) => out.push((prefix.to_string(), value.to_string())),
+        _ => {}
+    }
+}
+
+impl RevocationRegistry {
+    /// Create a registry, loading the persisted revocation list if any.
+    pub async fn new(config: RevocationConfig) -> Result<Self> {
+        let mut state = State::default();
+
+        #[cfg(feature = "fs")]
+        if let Some(path) = &config.path {
+            match tokio::fs::read(path).await {
+                Ok(content) => {
+                    state.list = serde_json::from_slice(&content)
+                        .with_context(|| format!("parse revocation list {}", path.display()))?;
+                    state.prune(now());
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("read {}", path.display()));
+                }
+            }
+        }
+
+        Ok(Self {
+            config,
+            state: RwLock::new(state),
+        })
+    }
+
+    /// The identity claims of the parsed claims of each TEE.
+    pub(crate) fn identities(&self, tee_claims: &[TeeClaims]) -> Vec<(String, String)> {
+        let mut claims = Vec::new();
+        for tee_claims in tee_claims {
+            flatten("", &tee_claims.claims, &mut claims);
+        }
+        claims.retain(|(name, _)| {
+            self.config.identity_claims.iter().any(|identity| {
+                name == identity || name.rsplit('.').next() == Some(identity.as_str())
+            })
+        });
+        claims
+    }
+
+    /// Index an issued token. Fails if the evidence behind it matches a
+    /// revocation, so the token must not be handed out.
+    pub(crate) async fn record(
+        &self,
+        token: &str,
+        identities: Vec<(String, String)>,
+        reference_values: Vec<String>,
+    ) -> Result<()> {
+        let issued = IssuedToken {
+            id: TokenId::from_token(token).context("index issued token")?,
+            identities,
+            reference_values,
+        };
+
+        let mut state = self.state.write().await;
+        state.prune(now());
+        if let Some(entry) = state
+            .list
+            .entries
+            .iter()
+            .find(|entry| issued.matches(&entry.revocation))
+        {
+            bail!(
+                "the evidence matches revocation {}",
+                serde_json::to_string(&entry.revocation)?
+            );
+        }
+
+        state.issued.insert(token_digest(token), issued);
+        Ok(())
+    }
+
+    /// Add a revocation lapsing at `expires_at` and return the `jti`s of the
+    /// indexed tokens it revokes.
+    pub async fn revoke(
+        &self,
+        revocation: Revocation,
+        reason: Option<String>,
+        expires_at: Option<i64>,
+    ) -> Result<Vec<String>> {
+        let now = now();
+        let mut state = self.state.write().await;
+        state.prune(now);
+
+        let mut revoked: Vec<RevokedToken> = state
+            .issued
+            .values()
+            .filter(|token| token.matches(&revocation))
+            .map(|token| RevokedToken {
+                jti: token.id.jti.clone(),
+                exp: token.id.exp,
+            })
+            .collect();
+        revoked.retain(|token| !state.is_revoked(&token.jti));
+        revoked.sort_by(|a, b| a.jti.cmp(&b.jti));
+        let jtis = revoked.iter().map(|token| token.jti.clone()).collect();
+
+        // A token rule is moot once the token has expired.
+        let expires_at = match &revocation {
+            Revocation::Token { jti } => state
+                .issued
+                .values()
+                .find(|token| token.id.jti == *jti)
+                .map(|token| expires_at.map_or(token.id.exp, |at| at.min(token.id.exp)))
+                .or(expires_at),
+            _ => expires_at,
+        };
+
+        info!(
+            "Revoked {} token(s) by {}",
+            revoked.len(),
+            serde_json::to_string(&revocation)?
+        );
+        state.list.revoked_tokens.extend(revoked);
+        match state
+            .list
+            .entries
+            .iter_mut()
+            .find(|e| e.revocation == revocation)
+        {
+            Some(entry) => entry.expires_at = expires_at,
+            None => state.list.entries.push(RevocationEntry {
+                revocation,
+                revoked_at: now,
+                expires_at,
+                reason,
+            }),
+        }
+
+        #[cfg(feature = "fs")]
+        self.persist(&state.list).await?;
+        Ok(jtis)
+    }
+
+    /// Withdraw a revocation and return the `jti`s of the tokens it
+    /// reinstates. Tokens another rule matches stay revoked, as do tokens
+    /// issued before a restart unless `revocation` names their `jti`.
+    pub async fn unrevoke(&self, revocation: &Revocation) -> Result<Vec<String>> {
+        let mut state = self.state.write().await;
+        state.prune(now());
+        let Some(index) = state
+            .list
+            .entries
+            .iter()
+            .position(|e| e.revocation == *revocation)
+        else {
+            bail!("{} is not revoked", serde_json::to_string(revocation)?);
+        };
+        state.list.entries.remove(index);
+
+        let State { list, issued } = &mut *state;
+        let mut reinstated = Vec::new();
+        list.revoked_tokens.retain(|token| {
+            let revoked = match issued.values().find(|issued| issued.id.jti == token.jti) {
+                Some(issued) => list
+                    .entries
+                    .iter()
+                    .any(|entry| issued.matches(&entry.revocation)),
+                None => !matches!(revocation, Revocation::Token { jti } if *jti == token.jti),
+            };
+            if !revoked {
+                reinstated.push(token.jti.clone());
+            }
+            revoked
+        });
+        reinstated.sort();
+
+        info!(
+            "Reinstated {} token(s) by withdrawing {}",
+            reinstated.len(),
+            serde_json::to_string(revocation)?
+        );
+        #[cfg(feature = "fs")]
+        self.persist(&state.list).await?;
+        Ok(reinstated)
+    }
+
+    /// The revocation rules and the revoked tokens that have not expired.
+    pub async fn list(&self) -> RevocationList {
+        let mut state = self.state.write().await;
+        state.prune(now());
+        state.list.clone()
+    }
+
+    /// Introspect a token issued by this AS. Unknown, expired and revoked
+    /// tokens are inactive.
+    pub async fn introspect(&self, token: &str) -> Introspection {
+        let state = self.state.read().await;
+        let Some(issued) = state.issued.get(&token_digest(token.trim())) else {
+            return Introspection::inactive();
+        };
+        if issued.id.exp < now() || state.is_revoked(&issued.id.jti) {
+            return Introspection::inactive();
+        }
+
+        Introspection {
+            active: true,
+            jti: Some(issued.id.jti.clone()),
+            iat: issued.id.iat,
+            exp: Some(issued.id.exp),
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    async fn persist(&self, list: &RevocationList) -> Result<()> {
+        let Some(path) = &self.config.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(list)?)
+            .await
+            .with_context(|| format!("write {}", tmp.display()))?;
+        tokio::fs::rename(&tmp, path)
+            .await
+            .with_context(|| format!("write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kbs_types::Tee;
+    use serde_json::json;
+
+    fn jwt(jti: &str, exp: i64) -> String {
+        let claims = json!({"jti": jti, "iat": 1, "exp": exp});
+        format!("e30.{}.sig", URL_SAFE_NO_PAD.encode(claims.to_string()))
+    }
+
+    fn tee_claims(chip_id: &str) -> TeeClaims {
+        TeeClaims {
+            tee: Tee::Sample,
+            tee_class: "cpu".into(),
+            claims: json!({"report": {"chip_id": chip_id, "svn": 1}}),
+            init_data_claims: Value::Null,
+            runtime_data_claims: Value::Null,
+            additional_data: None,
+            nonce: None,
+            evidence_digest: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn revokes_and_introspects_tokens() {
+        let registry = RevocationRegistry::default();
+        let exp = now() + 300;
+        let (token_a, token_b, token_c) = (jwt("a", exp), jwt("b", exp), jwt("c", exp));
+
+        let identities = registry.identities(&[tee_claims("chip-1")]);
+        assert_eq!(
+            identities,
+            vec![("report.chip_id".to_string(), "chip-1".to_string())]
+        );
+        registry
+            .record(&token_a, identities.clone(), vec!["svn".into()])
+            .await
+            .unwrap();
+        registry
+            .record(&token_b, identities, vec!["measurement".into()])
+            .await
+            .unwrap();
+        registry
+            .record(
+                &token_c,
+                registry.identities(&[tee_claims("chip-2")]),
+                vec!["svn".into()],
+            )
+            .await
+            .unwrap();
+
+        let active = registry.introspect(&token_a).await;
+        assert!(active.active);
+        assert_eq!(active.jti.as_deref(), Some("a"));
+        assert_eq!(active.exp, Some(exp));
+        assert!(!registry.introspect(&jwt("a", exp + 1)).await.active);
+
+        let revoked = registry
+            .revoke(
+                Revocation::TeeIdentity {
+                    claim: "chip_id".into(),
+                    value: "chip-1".into(),
+                },
+                Some("compromised".into()),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(revoked, vec!["a".to_string(), "b".to_string()]);
+        assert!(!registry.introspect(&token_a).await.active);
+        assert!(registry.introspect(&token_c).await.active);
+
+        let revoked = registry
+            .revoke(
+                Revocation::ReferenceValue { name: "svn".into() },
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(revoked, vec!["c".to_string()]);
+
+        let list = registry.list().await;
+        assert_eq!(list.entries.len(), 2);
+        assert_eq!(list.revoked_tokens.len(), 3);
+
+        // New evidence of a revoked TEE is refused.
+        let error = registry
+            .record(
+                &jwt("d", exp),
+                registry.identities(&[tee_claims("chip-1")]),
+                vec![],
+            )
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("tee_identity"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn expired_tokens_leave_the_list() {
+        let registry = RevocationRegistry::default();
+        let token = jwt("old", now() - 1);
+        registry.record(&token, vec![], vec![]).await.unwrap();
+        assert!(!registry.introspect(&token).await.active);
+
+        let revoked = registry
+            .revoke(Revocation::Token { jti: "old".into() }, None, None)
+            .await
+            .unwrap();
+        assert!(revoked.is_empty());
+        assert!(registry.list().await.revoked_tokens.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rules_lapse_and_can_be_withdrawn() {
+        let registry = RevocationRegistry::default();
+        let exp = now() + 300;
+        let chip = registry.identities(&[tee_claims("chip-1")]);
+        registry
+            .record(&jwt("a", exp), chip.clone(), vec!["svn".into()])
+            .await
+            .unwrap();
+        registry
+            .record(&jwt("b", exp), vec![], vec!["svn".into()])
+            .await
+            .unwrap();
+
+        // A token rule lapses with the token, other rules at `expires_at`.
+        registry
+            .revoke(Revocation::Token { jti: "a".into() }, None, None)
+            .await
+            .unwrap();
+        registry
+            .revoke(
+                Revocation::TeeIdentity {
+                    claim: "chip_id".into(),
+                    value: "chip-0".into(),
+                },
+                None,
+                Some(now() - 1),
+            )
+            .await
+            .unwrap();
+        let list = registry.list().await;
+        assert_eq!(list.entries.len(), 1);
+        assert_eq!(list.entries[0].expires_at, Some(exp));
+
+        let revoked = registry
+            .revoke(
+                Revocation::ReferenceValue { name: "svn".into() },
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(revoked, vec!["b".to_string()]);
+
+        // `a` is still revoked by its own rule.
+        let reinstated = registry
+            .unrevoke(&Revocation::ReferenceValue { name: "svn".into() })
+            .await
+            .unwrap();
+        assert_eq!(reinstated, vec!["b".to_string()]);
+        assert!(registry.introspect(&jwt("b", exp)).await.active);
+        assert!(!registry.introspect(&jwt("a", exp)).await.active);
+        registry
+            .record(&jwt("c", exp), vec![], vec!["svn".into()])
+            .await
+            .unwrap();
+
+        let reinstated = registry
+            .unrevoke(&Revocation::Token { jti: "a".into() })
+            .await
+            .unwrap();
+        assert_eq!(reinstated, vec!["a".to_string()]);
+        assert!(registry.introspect(&jwt("a", exp)).await.active);
+        assert!(registry
+            .unrevoke(&Revocation::Token { jti: "a".into() })
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn reads_the_cti_of_cose_tokens() {
+        let claims = CborValue::Map(vec![
+            (
+                CborValue::Integer(CWT_EXP.into()),
+                CborValue::Integer(9.into()),
+            ),
+            (
+                CborValue::Integer(CWT_CTI.into()),
+                CborValue::Bytes(b"jti-1".to_vec()),
+            ),
+        ]);
+        let mut payload = Vec::new();
+        coset::cbor::into_writer(&claims, &mut payload).unwrap();
+        let sign1 = coset::CoseSign1Builder::new().payload(payload).build();
+        let token = URL_SAFE_NO_PAD.encode(sign1.to_tagged_vec().unwrap());
+
+        assert_eq!(
+            TokenId::from_token(&token).unwrap(),
+            TokenId {
+                jti: "jti-1".into(),
+                iat: None,
+                exp: 9,
+            }
+        );
+    }
+}
@@ -28,40 +28,70 @@ impl BuiltinRvps {
     async_trait
 )]
 impl RvpsApi for BuiltinRvps {
-    async fn verify_and_extract(&self, message: &str) -> Result<()> {
-        self.rvps.write().await.verify_and_extract(message).await?;
+    async fn verify_and_extract(
+        &self,
+        namespace: &str,
+        message: &str,
+        admin_token: Option<&str>,
+    ) -> Result<()> {
+        let mut rvps = self.rvps.write().await;
+        let actor = rvps.authorize(namespace, admin_token)?;
+        rvps.verify_and_extract_as(namespace, message, &actor)
+            .await?;
         Ok(())
     }
 
-    async fn set_reference_value_list(&self, payload: &str) -> Result<()> {
-        self.rvps
-            .write()
-            .await
-            .set_reference_value_list(payload)
+    async fn set_reference_value_list(
+        &self,
+        namespace: &str,
+        payload: &str,
+        admin_token: Option<&str>,
+    ) -> Result<()> {
+        let mut rvps = self.rvps.write().await;
+        let actor = rvps.authorize(namespace, admin_token)?;
+        rvps.set_reference_value_list_as(namespace, payload, &actor)
             .await?;
         Ok(())
     }
 
     async fn query_reference_value(
         &self,
+        namespace: &str,
         reference_value_id: &str,
     ) -> Result<Option<serde_json::Value>> {
         let value = self
             .rvps
             .read()
             .await
-            .query_reference_value(reference_value_id)
+            .query_reference_value_in(namespace, reference_value_id)
             .await?;
         Ok(value)
     }
 
-    async fn get_reference_values(&self) -> Result<HashMap<String, serde_json::Value>> {
-        let values = self.rvps.read().await.get_reference_values().await?;
+    async fn get_reference_values(
+        &self,
+        namespace: &str,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        let values = self
+            .rvps
+            .read()
+            .await
+            .get_reference_values_in(namespace)
+            .await?;
         Ok(values)
     }
 
-    async fn delete_reference_value(&self, name: &str) -> Result<bool> {
-        let result = self.rvps.write().await.delete_reference_value(name).await?;
+    async fn delete_reference_value(
+        &self,
+        namespace: &str,
+        name: &str,
+        admin_token: Option<&str>,
+    ) -> Result<bool> {
+        let mut rvps = self.rvps.write().await;
+        let actor = rvps.authorize(namespace, admin_token)?;
+        let result = rvps
+            .delete_reference_value_as(namespace, name, &actor)
+            .await?;
         Ok(result)
     }
 }
@@ -47,27 +47,63 @@ impl Agent {
         })
     }
 }
+
+/// A request carrying `admin_token`, if any, as bearer token.
+fn request<T>(message: T, admin_token: Option<&str>) -> Result<tonic::Request<T>> {
+    let mut req = tonic::Request::new(message);
+    if let Some(token) = admin_token {
+        let value = format!("Bearer {token}")
+            .parse()
+            .map_err(|_| anyhow::anyhow!("invalid admin token"))?;
+        req.metadata_mut().insert("authorization", value);
+    }
+    Ok(req)
+}
+
 #[async_trait::async_trait]
 impl RvpsApi for Agent {
-    async fn verify_and_extract(&self, message: &str) -> Result<()> {
-        let req = tonic::Request::new(ReferenceValueRegisterRequest {
-            message: message.to_string(),
-        });
+    async fn verify_and_extract(
+        &self,
+        namespace: &str,
+        message: &str,
+        admin_token: Option<&str>,
+    ) -> Result<()> {
+        let req = request(
+            ReferenceValueRegisterRequest {
+                message: message.to_string(),
+                namespace: namespace.to_string(),
+            },
+            admin_token,
+        )?;
         self.client.clone().register_reference_value(req).await?;
         Ok(())
     }
 
-    async fn set_reference_value_list(&self, payload: &str) -> Result<()> {
-        let req = tonic::Request::new(ReferenceValueListRequest {
-            payload: payload.to_string(),
-        });
+    async fn set_reference_value_list(
+        &self,
+        namespace: &str,
+        payload: &str,
+        admin_token: Option<&str>,
+    ) -> Result<()> {
+        let req = request(
+            ReferenceValueListRequest {
+                payload: payload.to_string(),
+                namespace: namespace.to_string(),
+            },
+            admin_token,
+        )?;
         self.client.clone().set_reference_value_list(req).await?;
         Ok(())
     }
 
-    async fn query_reference_value(&self, reference_value_id: &str) -> Result<Option<Value>> {
+    async fn query_reference_value(
+        &self,
+        namespace: &str,
+        reference_value_id: &str,
+    ) -> Result<Option<Value>> {
         let req = tonic::Request::new(ReferenceValueQueryRequest {
             reference_value_id: reference_value_id.to_string(),
+            namespace: namespace.to_string(),
         });
         let response = self
             .client
@@ -84,9 +120,10 @@ impl RvpsApi for Agent {
         Ok(Some(serde_json::from_str(&response)?))
     }
 
-    async fn get_reference_values(&self) -> Result<HashMap<String, Value>> {
+    async fn get_reference_values(&self, namespace: &str) -> Result<HashMap<String, Value>> {
         let req = tonic::Request::new(ReferenceValueQueryRequest {
             reference_value_id: String::new(),
+            namespace: namespace.to_string(),
         });
         let response = self
             .client
@@ -98,10 +135,19 @@ impl RvpsApi for Agent {
         Ok(serde_json::from_str(&response)?)
     }
 
-    async fn delete_reference_value(&self, name: &str) -> Result<bool> {
-        let req = tonic::Request::new(ReferenceValueDeleteRequest {
-            name: name.to_string(),
-        });
+    async fn delete_reference_value(
+        &self,
+        namespace: &str,
+        name: &str,
+        admin_token: Option<&str>,
+    ) -> Result<bool> {
+        let req = request(
+            ReferenceValueDeleteRequest {
+                name: name.to_string(),
+                namespace: namespace.to_string(),
+            },
+            admin_token,
+        )?;
         self.client.clone().delete_reference_value(req).await?;
         Ok(true)
     }
@@ -5,9 +5,11 @@
 
 use log::info;
 pub use reference_value_provider_service::config::Config as RvpsCrateConfig;
+use reference_value_provider_service::namespace::normalize;
+pub use reference_value_provider_service::namespace::DEFAULT_NAMESPACE;
 use serde::Deserialize;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 use thiserror::Error;
 use tokio::sync::Mutex;
@@ -42,6 +44,10 @@ type Result<T> = std::result::Result<T, RvpsError>;
 /// * `query_reference_value` gets one policy-facing value by its identifier.
 /// * `get_reference_values` keeps the legacy bulk-query API available.
 /// * `delete_reference_value` is responsible for deleting a reference value.
+///
+/// Every reference value belongs to a namespace, [`DEFAULT_NAMESPACE`] if
+/// empty. Changes to a namespace configured with admin tokens must present
+/// one of them as `admin_token`.
 #[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait::async_trait(?Send))]
 #[cfg_attr(
     not(all(
@@ -53,37 +59,61 @@ type Result<T> = std::result::Result<T, RvpsError>;
 )]
 pub trait RvpsApi: Send + Sync {
     /// Verify the given message and register the reference value included.
-    async fn verify_and_extract(&self, message: &str) -> Result<()>;
+    async fn verify_and_extract(
+        &self,
+        namespace: &str,
+        message: &str,
+        admin_token: Option<&str>,
+    ) -> Result<()>;
 
     /// Set reference values list via RVPS.
-    async fn set_reference_value_list(&self, payload: &str) -> Result<()>;
+    async fn set_reference_value_list(
+        &self,
+        namespace: &str,
+        payload: &str,
+        admin_token: Option<&str>,
+    ) -> Result<()>;
 
     /// Get one policy-facing reference value.
-    async fn query_reference_value(&self, reference_value_id: &str) -> Result<Option<Value>>;
+    async fn query_reference_value(
+        &self,
+        namespace: &str,
+        reference_value_id: &str,
+    ) -> Result<Option<Value>>;
 
     /// Get all policy-facing reference values.
-    async fn get_reference_values(&self) -> Result<HashMap<String, Value>>;
+    async fn get_reference_values(&self, namespace: &str) -> Result<HashMap<String, Value>>;
 
     /// Delete a reference value by name.
-    async fn delete_reference_value(&self, name: &str) -> Result<bool>;
+    async fn delete_reference_value(
+        &self,
+        namespace: &str,
+        name: &str,
+        admin_token: Option<&str>,
+    ) -> Result<bool>;
 }
 
-/// A per-attestation view of RVPS.
+/// A per-attestation view of the reference values of one namespace of RVPS.
 ///
 /// Both values and misses are cached so multiple policies or repeated policy
 /// calls observe one consistent value and do not generate duplicate RPCs.
+/// Changes made to RVPS meanwhile are seen by the next attestation.
 pub struct ReferenceValueResolver {
     rvps: Arc<dyn RvpsApi>,
+    namespace: String,
     keyed_cache: Mutex<HashMap<String, Option<Value>>>,
     bulk_cache: Mutex<Option<HashMap<String, Value>>>,
+    consulted: Mutex<BTreeSet<String>>,
 }
 
 impl ReferenceValueResolver {
-    pub fn new(rvps: Arc<dyn RvpsApi>) -> Self {
+    pub fn new(rvps: Arc<dyn RvpsApi>, namespace: &str) -> Self {
         Self {
             rvps,
+            namespace: normalize(namespace).to_string(),
             keyed_cache: Mutex::new(HashMap::new()),
             bulk_cache: Mutex::new(None),
+            consulted: Mutex::new(BTreeSet::new()),
         }
     }
 
@@ -100,8 +130,17 @@ impl ReferenceValueResolver {
             return Ok(value.clone());
         }
 
-        let value = self.rvps.query_reference_value(reference_value_id).await?;
+        let value = self
+            .rvps
+            .query_reference_value(&self.namespace, reference_value_id)
+            .await?;
         keyed_cache.insert(reference_value_id.to_string(), value.clone());
+        if value.is_some() {
+            self.consulted
+                .lock()
+                .await
+                .insert(reference_value_id.to_string());
+        }
         Ok(value)
     }
 
@@ -111,7 +150,7 @@ impl ReferenceValueResolver {
             return Ok(values.clone());
         }
 
-        let mut values = self.rvps.get_reference_values().await?;
+        let mut values = self.rvps.get_reference_values(&self.namespace).await?;
         let mut keyed_cache = self.keyed_cache.lock().await;
         // Preserve the first value observed during this attestation if a
         // legacy policy triggers a bulk query after keyed queries.
@@ -130,9 +169,21 @@ impl ReferenceValueResolver {
                 .entry(key.clone())
                 .or_insert_with(|| Some(value.clone()));
         }
+        self.consulted.lock().await.extend(values.keys().cloned());
         *bulk_cache = Some(values.clone());
         Ok(values)
     }
+
+    /// The namespace of the reference values the attestation sees.
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// Names of the reference values the attestation has observed so far.
+    /// A bulk query observes all of them.
+    pub async fn consulted(&self) -> Vec<String> {
+        self.consulted.lock().await.iter().cloned().collect()
+    }
 }
 
 #[derive(Deserialize, Clone, Debug, PartialEq)]
@@ -171,23 +222,42 @@ struct StaticTestRvps {
 #[cfg(test)]
 #[async_trait::async_trait]
 impl RvpsApi for StaticTestRvps {
-    async fn verify_and_extract(&self, _message: &str) -> Result<()> {
+    async fn verify_and_extract(
+        &self,
+        _namespace: &str,
+        _message: &str,
+        _admin_token: Option<&str>,
+    ) -> Result<()> {
         unreachable!()
     }
 
-    async fn set_reference_value_list(&self, _payload: &str) -> Result<()> {
+    async fn set_reference_value_list(
+        &self,
+        _namespace: &str,
+        _payload: &str,
+        _admin_token: Option<&str>,
+    ) -> Result<()> {
         unreachable!()
     }
 
-    async fn query_reference_value(&self, reference_value_id: &str) -> Result<Option<Value>> {
+    async fn query_reference_value(
+        &self,
+        _namespace: &str,
+        reference_value_id: &str,
+    ) -> Result<Option<Value>> {
         Ok(self.values.get(reference_value_id).cloned())
     }
 
-    async fn get_reference_values(&self) -> Result<HashMap<String, Value>> {
+    async fn get_reference_values(&self, _namespace: &str) -> Result<HashMap<String, Value>> {
         Ok(self.values.clone())
     }
 
-    async fn delete_reference_value(&self, _name: &str) -> Result<bool> {
+    async fn delete_reference_value(
+        &self,
+        _namespace: &str,
+        _name: &str,
+        _admin_token: Option<&str>,
+    ) -> Result<bool> {
         unreachable!()
     }
 }
@@ -195,7 +265,7 @@ impl RvpsApi for StaticTestRvps {
 #[cfg(test)]
 pub(crate) fn test_resolver(values: HashMap<String, Value>) -> Arc<ReferenceValueResolver> {
     let rvps = Arc::new(StaticTestRvps { values }) as Arc<dyn RvpsApi>;
-    Arc::new(ReferenceValueResolver::new(rvps))
+    Arc::new(ReferenceValueResolver::new(rvps, DEFAULT_NAMESPACE))
 }
 
 #[cfg(test)]
@@ -209,39 +279,76 @@ mod tests {
     use std::sync::atomic::{AtomicUsize, Ordering};
 
     struct CountingRvps {
-        values: HashMap<String, Value>,
+        /// Values by namespace and name.
+        values: HashMap<(String, String), Value>,
         keyed_queries: AtomicUsize,
         bulk_queries: AtomicUsize,
     }
 
     #[async_trait::async_trait]
     impl RvpsApi for CountingRvps {
-        async fn verify_and_extract(&self, _message: &str) -> Result<()> {
+        async fn verify_and_extract(
+            &self,
+            _namespace: &str,
+            _message: &str,
+            _admin_token: Option<&str>,
+        ) -> Result<()> {
             unreachable!()
         }
 
-        async fn set_reference_value_list(&self, _payload: &str) -> Result<()> {
+        async fn set_reference_value_list(
+            &self,
+            _namespace: &str,
+            _payload: &str,
+            _admin_token: Option<&str>,
+        ) -> Result<()> {
             unreachable!()
         }
 
-        async fn query_reference_value(&self, reference_value_id: &str) -> Result<Option<Value>> {
+        async fn query_reference_value(
+            &self,
+            namespace: &str,
+            reference_value_id: &str,
+        ) -> Result<Option<Value>> {
             self.keyed_queries.fetch_add(1, Ordering::SeqCst);
-            Ok(self.values.get(reference_value_id).cloned())
+            Ok(self
+                .values
+                .get(&(namespace.to_string(), reference_value_id.to_string()))
+                .cloned())
         }
 
-        async fn get_reference_values(&self) -> Result<HashMap<String, Value>> {
+        async fn get_reference_values(&self, namespace: &str) -> Result<HashMap<String, Value>> {
             self.bulk_queries.fetch_add(1, Ordering::SeqCst);
-            Ok(self.values.clone())
+            Ok(self
+                .values
+                .iter()
+                .filter(|((ns, _), _)| ns == namespace)
+                .map(|((_, name), value)| (name.clone(), value.clone()))
+                .collect())
         }
 
-        async fn delete_reference_value(&self, _name: &str) -> Result<bool> {
+        async fn delete_reference_value(
+            &self,
+            _namespace: &str,
+            _name: &str,
+            _admin_token: Option<&str>,
+        ) -> Result<bool> {
             unreachable!()
         }
     }
 
     fn counting_rvps() -> Arc<CountingRvps> {
         Arc::new(CountingRvps {
-            values: HashMap::from([("svn".to_string(), serde_json::json!([1, 2]))]),
+            values: HashMap::from([
+                (
+                    (DEFAULT_NAMESPACE.to_string(), "svn".to_string()),
+                    serde_json::json!([1, 2]),
+                ),
+                (
+                    ("team-a".to_string(), "svn".to_string()),
+                    serde_json::json!([3]),
+                ),
+            ]),
             keyed_queries: AtomicUsize::new(0),
             bulk_queries: AtomicUsize::new(0),
         })
@@ -250,7 +357,8 @@ mod tests {
     #[tokio::test]
     async fn keyed_values_and_misses_are_cached() {
         let rvps = counting_rvps();
-        let resolver = ReferenceValueResolver::new(Arc::clone(&rvps) as Arc<dyn RvpsApi>);
+        let resolver =
+            ReferenceValueResolver::new(Arc::clone(&rvps) as Arc<dyn RvpsApi>, DEFAULT_NAMESPACE);
 
         assert_eq!(
             resolver.query_reference_value("svn").await.unwrap(),
@@ -269,12 +377,14 @@ mod tests {
             None
         );
         assert_eq!(rvps.keyed_queries.load(Ordering::SeqCst), 2);
+        assert_eq!(resolver.consulted().await, vec!["svn".to_string()]);
     }
 
     #[tokio::test]
     async fn bulk_snapshot_is_cached_and_serves_keyed_queries() {
         let rvps = counting_rvps();
-        let resolver = ReferenceValueResolver::new(Arc::clone(&rvps) as Arc<dyn RvpsApi>);
+        let resolver =
+            ReferenceValueResolver::new(Arc::clone(&rvps) as Arc<dyn RvpsApi>, DEFAULT_NAMESPACE);
 
         assert_eq!(resolver.get_reference_values().await.unwrap().len(), 1);
         assert_eq!(resolver.get_reference_values().await.unwrap().len(), 1);
@@ -289,4 +399,19 @@ mod tests {
         assert_eq!(rvps.bulk_queries.load(Ordering::SeqCst), 1);
         assert_eq!(rvps.keyed_queries.load(Ordering::SeqCst), 0);
     }
+
+    #[tokio::test]
+    async fn resolver_sees_only_its_namespace() {
+        let rvps = counting_rvps();
+        let resolver = ReferenceValueResolver::new(Arc::clone(&rvps) as Arc<dyn RvpsApi>, "team-a");
+
+        assert_eq!(
+            resolver.query_reference_value("svn").await.unwrap(),
+            Some(serde_json::json!([3]))
+        );
+        assert_eq!(
+            resolver.get_reference_values().await.unwrap(),
+            HashMap::from([("svn".to_string(), serde_json::json!([3]))])
+        );
+    }
 }
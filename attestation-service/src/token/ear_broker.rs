@@ -8,14 +8,16 @@ use anyhow::*;
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
 use const_format::concatcp;
+use coset::{iana, CoseSign1Builder, HeaderBuilder, TaggedCborSerializable};
 use ear::{
     Algorithm, Appraisal, Ear, ExtensionKind, ExtensionValue, Extensions, RawValue, TrustVector,
     VerifierID,
 };
 use jsonwebtoken::jwk;
 use kbs_types::Tee;
-use log::{debug, warn};
+use log::debug;
 use p256::elliptic_curve::sec1::ToEncodedPoint;
+#[cfg(all(test, feature = "fs"))]
 use p256::pkcs8::{EncodePrivateKey, LineEnding};
 use p256::SecretKey;
 use serde::Deserialize;
@@ -26,16 +28,17 @@ use serde_variant::to_variant_name;
 use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
 
 use crate::policy_engine::PolicyEngine;
 use crate::rvps::ReferenceValueResolver;
 use crate::token::DEFAULT_TOKEN_WORK_DIR;
 use crate::{AttestationTokenBroker, TeeClaims};
 
-use super::signer::SignKeyProvider;
+use super::signer::{SignAlgorithm, SignKeyProvider};
 #[cfg(feature = "fs")]
 use super::signer_transparency;
-use super::{COCO_AS_ISSUER_NAME, DEFAULT_TOKEN_DURATION};
+use super::{TokenFormat, COCO_AS_ISSUER_NAME, DEFAULT_TOKEN_DURATION};
 
 pub const DEFAULT_PROFILE: &str = "tag:github.com,2024:confidential-containers/Trustee";
 pub const DEFAULT_DEVELOPER_NAME: &str = "https://confidentialcontainers.org";
@@ -46,6 +49,21 @@ pub const DEFAULT_POLICY_ID: &str = "default.rego";
 
 pub use super::signer::SignerConfig;
 
+/// COSE header label of the signer's certificate chain (RFC 9360).
+pub const COSE_HEADER_X5CHAIN: i64 = 33;
+
+/// CWT key of the `exp` claim (RFC 8392).
+pub(crate) const CWT_EXP: i32 = 4;
+
+/// CWT key of the `cti` claim (RFC 8392), carrying the UTF-8 bytes of the
+/// `jti` in COSE tokens.
+pub(crate) const CWT_CTI: i32 = 7;
+
+/// Claim naming the RVPS namespace whose reference values the policies saw.
+pub const RVPS_NAMESPACE_CLAIM: &str = "rvps-namespace";
+/// CWT key of [`RVPS_NAMESPACE_CLAIM`], from the private use range.
+pub const RVPS_NAMESPACE_KEY: i32 = -75000;
+
 /// Part 1 — fs-free token-issuance metadata. This is the *only* part of the
 /// config the broker holds at runtime.
 #[derive(Deserialize, Debug, Clone, PartialEq)]
@@ -74,6 +92,12 @@ pub struct TokenBrokerSettings {
     /// Default: `tag:github.com,2024:confidential-containers/Trustee`
     #[serde(default = "default_profile")]
     pub profile_name: String,
+
+    /// The encoding of the issued EAR, `jwt` or `cose`. Requests can
+    /// override it.
+    /// Default: `jwt`
+    #[serde(default)]
+    pub token_format: TokenFormat,
 }
 
 impl Default for TokenBrokerSettings {
@@ -84,6 +108,7 @@ impl Default for TokenBrokerSettings {
             developer_name: default_developer(),
             build_name: default_build(),
             profile_name: default_profile(),
+            token_format: TokenFormat::default(),
         }
     }
 }
@@ -168,7 +193,9 @@ impl EarAttestationTokenBroker {
         log::info!("Loading default AS policy \"default.rego\"");
 
         let signer: Arc<dyn SignKeyProvider<SecretKey>> = match config.signer {
-            Some(sc) => Arc::new(super::signer::FsSigner::<SecretKey>::from_config(sc)?),
+            Some(sc) => {
+                super::signer::from_config::<SecretKey>(sc, config.settings.duration_min * 60)?
+            }
             None => {
                 log::info!(
                     "No Token Signer key in config file, create an ephemeral key and without CA pubkey cert"
@@ -213,23 +240,35 @@ impl AttestationTokenBroker for EarAttestationTokenBroker {
         policy_ids: Vec<String>,
         reference_value_resolver: Arc<ReferenceValueResolver>,
     ) -> Result<String> {
-        debug!("all_tee_claims: {:#?}", all_tee_claims);
+        self.issue_with_format(all_tee_claims, policy_ids, reference_value_resolver, None)
+            .await
+    }
 
-        if policy_ids.len() > 1 {
-            warn!("EAR token only accepts the first policy. The rest will be ignored.");
-        }
+    async fn issue_with_format(
+        &self,
+        all_tee_claims: Vec<TeeClaims>,
+        policy_ids: Vec<String>,
+        reference_value_resolver: Arc<ReferenceValueResolver>,
+        format: Option<TokenFormat>,
+    ) -> Result<String> {
+        debug!("all_tee_claims: {:#?}", all_tee_claims);
 
-        if policy_ids.is_empty() {
-            bail!("No policy is given for EAR token generation.");
-        }
+        let selection = PolicySelection::parse(&policy_ids)?;
+        selection.check_classes(
+            all_tee_claims
+                .iter()
+                .map(|claims| claims.tee_class.as_str()),
+        )?;
+        let nonce = super::eat_nonce(&all_tee_claims)
+            .map(serde_json::from_value)
+            .transpose()
+            .context("Illegal eat_nonce")?;
 
         let mut tee_class_indices: HashMap<String, u8> = HashMap::new();
         let mut submods = BTreeMap::new();
 
         // Create an appraisal for each device
         for tee_claims in all_tee_claims {
-            let mut appraisal = Appraisal::new();
-
             let tcb_claims = transform_claims(
                 tee_claims.claims,
                 tee_claims.init_data_claims.clone(),
@@ -239,49 +278,32 @@ impl AttestationTokenBroker for EarAttestationTokenBroker {
 
             let tcb_claims_json = serde_json::to_string(&tcb_claims)?;
 
-            let rules = TrustVector::new()
-                .into_iter()
-                .map(|c| c.tag().replace('-', "_").to_string())
-                .collect();
-
-            // There is a policy for each tee class.
-            // The cpu tee class is loaded as the default.
-            let policy_results = self
-                .policy_engine
-                .evaluate(
-                    &tcb_claims_json,
-                    &policy_ids[0],
-                    rules,
-                    Arc::clone(&reference_value_resolver),
-                )
-                .await?;
-
-            for (k, v) in &policy_results.rules_result {
-                let claim_value =
-                    i8::try_from(v.as_i64().context("Policy claim value is not an integer")?)
-                        .context("Policy claim value is outside the i8 range")?;
-                debug!("Policy claim: {}: {}", k, claim_value);
-
-                // The definition of Trustworthiness Claims in AR4SI
-                // (https://www.ietf.org/archive/id/draft-ietf-rats-ar4si-09.html#name-supportable-trustworthiness-cl)
-                // uses hyphens while the policy engine uses underscores.
-                // so we need to convert underscores to hyphens here.
-                let k = k.replace('_', "-");
-
-                appraisal
-                    .trust_vector
-                    .mut_by_name(&k)
-                    .unwrap()
-                    .set(claim_value);
-            }
-
-            if !appraisal.trust_vector.any_set() {
-                bail!("At least one policy claim must be set.");
+            // Each tee class is appraised by the policies mapped to it and the
+            // unmapped policies. When several policies apply, the worst value
+            // of each trust claim wins.
+            let class_policy_ids = selection.for_class(&tee_claims.tee_class)?;
+            let mut rules_result = HashMap::new();
+            for policy_id in class_policy_ids {
+                let policy_results = self
+                    .policy_engine
+                    .evaluate(
+                        &tcb_claims_json,
+                        policy_id,
+                        trust_vector_rules(),
+                        Arc::clone(&reference_value_resolver),
+                    )
+                    .await?;
+                merge_rules_result(&mut rules_result, policy_results.rules_result);
             }
 
-            appraisal.update_status_from_trust_vector();
+            let mut appraisal = appraisal_from_rules(&rules_result)?;
             appraisal.annotated_evidence = tcb_claims;
-            appraisal.policy_id = Some(policy_ids[0].clone());
+            if let Some(digest) = tee_claims.evidence_digest {
+                appraisal
+                    .annotated_evidence
+                    .insert("evidence_digest".to_string(), RawValue::Text(digest));
+            }
+            appraisal.policy_id = Some(class_policy_ids.join(","));
 
             if let Some(index) = tee_class_indices.get_mut(&tee_claims.tee_class) {
                 *index += 1;
@@ -303,9 +325,26 @@ impl AttestationTokenBroker for EarAttestationTokenBroker {
             .checked_add(Duration::minutes(self.settings.duration_min))
             .ok_or(anyhow!("Token expiration overflow."))?;
 
+        let format = format.unwrap_or(self.settings.token_format);
+        let jti = Uuid::new_v4().to_string();
+
         let mut extensions = Extensions::new();
-        extensions.register("exp", 4, ExtensionKind::Integer)?;
+        extensions.register("exp", CWT_EXP, ExtensionKind::Integer)?;
         extensions.set_by_name("exp", ExtensionValue::Integer(exp.unix_timestamp()))?;
+        // The CWT `cti` is a byte string, so `sign_cose` adds it instead.
+        if let TokenFormat::Jwt = format {
+            extensions.register("jti", CWT_CTI, ExtensionKind::String)?;
+            extensions.set_by_name("jti", ExtensionValue::String(jti.clone()))?;
+        }
+        extensions.register(
+            RVPS_NAMESPACE_CLAIM,
+            RVPS_NAMESPACE_KEY,
+            ExtensionKind::String,
+        )?;
+        extensions.set_by_name(
+            RVPS_NAMESPACE_CLAIM,
+            ExtensionValue::String(reference_value_resolver.namespace().to_string()),
+        )?;
 
         let ear = Ear {
             profile: self.settings.profile_name.clone(),
@@ -315,42 +354,15 @@ impl AttestationTokenBroker for EarAttestationTokenBroker {
                 developer: self.settings.developer_name.clone(),
             },
             raw_evidence: None,
-            nonce: None,
+            nonce,
             submods,
             extensions,
         };
-        let mut jwt_header = ear::new_jwt_header(&Algorithm::ES256)?;
-        jwt_header.jwk = Some(self.pubkey_jwk()?);
 
-        let private_key_bytes = self
-            .signer
-            .private_key()
-            .to_pkcs8_pem(LineEnding::LF)
-            .context("serialize EC private key to PKCS#8 PEM")?;
-        let private_key_bytes: &[u8] = private_key_bytes.as_bytes();
-
-        #[cfg(feature = "fs")]
-        let signed_ear = if let Some(transparency) = signer_transparency::load_signer_transparency()
-        {
-            let mut ear_claims = serde_json::to_value(&ear)?
-                .as_object()
-                .cloned()
-                .ok_or_else(|| {
-                    anyhow!("Internal Error: serialize EAR claims to JSON object failed")
-                })?;
-            ear_claims.insert("signer_transparency".to_string(), transparency);
-            jsonwebtoken::encode(
-                &jwt_header,
-                &Value::Object(ear_claims),
-                &jsonwebtoken::EncodingKey::from_ec_pem(private_key_bytes)?,
-            )?
-        } else {
-            ear.sign_jwt_pem_with_header(&jwt_header, private_key_bytes)?
-        };
-        #[cfg(not(feature = "fs"))]
-        let signed_ear = ear.sign_jwt_pem_with_header(&jwt_header, private_key_bytes)?;
-
-        Ok(signed_ear)
+        match format {
+            TokenFormat::Jwt => self.sign_jwt(&ear).await,
+            TokenFormat::Cose => self.sign_cose(&ear, &jti).await,
+        }
     }
 
     async fn set_policy(&self, policy_id: String, policy: String) -> Result<()> {
@@ -388,11 +400,103 @@ impl AttestationTokenBroker for EarAttestationTokenBroker {
     fn signer_cert_url(&self) -> Option<&str> {
         self.signer.cert_url()
     }
+
+    /// Publish the configured signer's EC public keys as a JWKS, with the
+    /// `kid` of each key of a rotating signer.
+    async fn configured_signer_jwks(&self) -> Result<Option<String>> {
+        super::signer::configured_jwks(self.signer.as_ref(), "ES256").await
+    }
 }
 
 impl EarAttestationTokenBroker {
+    async fn sign_jwt(&self, ear: &Ear) -> Result<String> {
+        let (kid, public_key) = self.signer.active_key().await?;
+        let mut jwt_header = ear::new_jwt_header(&Algorithm::ES256)?;
+        jwt_header.kid = kid.clone();
+        jwt_header.jwk = Some(self.pubkey_jwk(kid.clone(), &public_key)?);
+
+        #[allow(unused_mut)]
+        let mut ear_claims = serde_json::to_value(ear)?;
+        #[cfg(feature = "fs")]
+        if let Some(transparency) = signer_transparency::load_signer_transparency() {
+            ear_claims
+                .as_object_mut()
+                .ok_or_else(|| {
+                    anyhow!("Internal Error: serialize EAR claims to JSON object failed")
+                })?
+                .insert("signer_transparency".to_string(), transparency);
+        }
+
+        // The JWS is assembled here rather than by `jsonwebtoken`, since the
+        // signature may be computed by an HSM or a KMS.
+        let header_b64 = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&jwt_header)?);
+        let claims_b64 = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&ear_claims)?);
+        let signing_input = format!("{header_b64}.{claims_b64}");
+        let signature = self
+            .signer
+            .sign_with_key(
+                kid.as_deref(),
+                SignAlgorithm::Es256,
+                signing_input.as_bytes(),
+            )
+            .await?;
+
+        Ok(format!(
+            "{signing_input}.{}",
+            URL_SAFE_NO_PAD.encode(signature)
+        ))
+    }
+
+    /// Sign the EAR as a tagged COSE_Sign1 (RFC 9052) over its CBOR encoded
+    /// claims set, i.e. a CWT. The signer's certificate chain, if any, is
+    /// carried in the `x5chain` header and `jti` in the `cti` claim. The
+    /// signer transparency claim is only embedded in JWT EARs.
+    async fn sign_cose(&self, ear: &Ear, jti: &str) -> Result<String> {
+        let mut claims = coset::cbor::Value::serialized(ear)
+            .map_err(|e| anyhow!("CBOR encode EAR claims: {e}"))?;
+        claims
+            .as_map_mut()
+            .ok_or(anyhow!("Internal Error: EAR claims are not a CBOR map"))?
+            .push((
+                coset::cbor::Value::Integer(CWT_CTI.into()),
+                coset::cbor::Value::Bytes(jti.as_bytes().to_vec()),
+            ));
+        let mut payload = Vec::new();
+        coset::cbor::into_writer(&claims, &mut payload).context("CBOR encode EAR claims")?;
+
+        let mut unprotected = HeaderBuilder::new();
+        if let Some(chain) = self.signer.cert_chain().transpose()? {
+            let certs = chain
+                .iter()
+                .map(|cert| coset::cbor::Value::Bytes(cert.to_vec()))
+                .collect();
+            unprotected = unprotected.value(COSE_HEADER_X5CHAIN, coset::cbor::Value::Array(certs));
+        }
+
+        let (kid, _) = self.signer.active_key().await?;
+        let mut protected = HeaderBuilder::new().algorithm(iana::Algorithm::ES256);
+        if let Some(kid) = &kid {
+            protected = protected.key_id(kid.as_bytes().to_vec());
+        }
+
+        let mut sign1 = CoseSign1Builder::new()
+            .protected(protected.build())
+            .unprotected(unprotected.build())
+            .payload(payload)
+            .build();
+        sign1.signature = self
+            .signer
+            .sign_with_key(kid.as_deref(), SignAlgorithm::Es256, &sign1.tbs_data(&[]))
+            .await?;
+        let cose = sign1
+            .to_tagged_vec()
+            .map_err(|e| anyhow!("encode COSE_Sign1: {e}"))?;
+
+        Ok(URL_SAFE_NO_PAD.encode(cose))
+    }
+
     // TODO: converge this with the jwk function in the simple token broker
-    fn pubkey_jwk(&self) -> Result<jwk::Jwk> {
+    fn pubkey_jwk(&self, kid: Option<String>, public_key: &p256::PublicKey) -> Result<jwk::Jwk> {
         let chain = self
             .signer
             .cert_chain()
@@ -409,10 +513,10 @@ impl EarAttestationTokenBroker {
             key_algorithm: Some(jwk::KeyAlgorithm::ES256),
             x509_url: self.signer.cert_url().map(str::to_owned),
             x509_chain: chain,
+            key_id: kid,
             ..Default::default()
         };
 
-        let public_key = self.signer.private_key().public_key();
         let encoded = public_key.to_encoded_point(false);
         let x = encoded
             .x()
@@ -454,6 +558,160 @@ fn generate_ec_keys() -> Result<(SecretKey, Vec<u8>, Vec<u8>)> {
     ))
 }
 
+/// The rules the EAR broker evaluates: the AR4SI trust claims, with the
+/// hyphens of their names replaced by underscores to be valid rego rules.
+pub fn trust_vector_rules() -> Vec<String> {
+    TrustVector::new()
+        .into_iter()
+        .map(|c| c.tag().replace('-', "_").to_string())
+        .collect()
+}
+
+/// The policies that appraise each tee class, parsed from the `policy_ids`
+/// of an attestation request.
+///
+/// An entry of the form `class=policy_id` maps a tee class (`cpu`, `gpu`, ...)
+/// to a policy. A plain `policy_id` applies to every class, besides the
+/// policies mapped to it. Entries may be comma separated, so
+/// `cpu=tdx-prod,gpu=h100` maps both classes in a single id.
+#[derive(Debug, Default, PartialEq)]
+pub struct PolicySelection {
+    by_class: HashMap<String, Vec<String>>,
+    unmapped: Vec<String>,
+}
+
+impl PolicySelection {
+    pub fn parse(policy_ids: &[String]) -> Result<Self> {
+        let mut selection = Self::default();
+        for entry in policy_ids.iter().flat_map(|ids| ids.split(',')) {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            }
+
+            let Some((class, policy_id)) = entry.split_once('=') else {
+                selection.unmapped.push(entry.to_string());
+                continue;
+            };
+            let (class, policy_id) = (class.trim(), policy_id.trim());
+            if class.is_empty() || policy_id.is_empty() {
+                bail!("Illegal policy mapping `{entry}`, expected `class=policy_id`.");
+            }
+            selection
+                .by_class
+                .entry(class.to_string())
+                .or_default()
+                .push(policy_id.to_string());
+        }
+
+        if selection.by_class.is_empty() && selection.unmapped.is_empty() {
+            bail!("No policy is given for EAR token generation.");
+        }
+
+        Ok(selection)
+    }
+
+    /// The policies that appraise the given tee class, each once.
+    pub fn for_class(&self, tee_class: &str) -> Result<Vec<&str>> {
+        let mut policy_ids: Vec<&str> = Vec::new();
+        for policy_id in self
+            .by_class
+            .get(tee_class)
+            .into_iter()
+            .flatten()
+            .chain(&self.unmapped)
+        {
+            if !policy_ids.contains(&policy_id.as_str()) {
+                policy_ids.push(policy_id);
+            }
+        }
+        if policy_ids.is_empty() {
+            bail!("No policy is given for tee class `{tee_class}`.");
+        }
+        Ok(policy_ids)
+    }
+
+    /// Check that every tee class with mapped policies is among the classes
+    /// of the evidence, so that a mapped policy is never left unevaluated.
+    pub fn check_classes<'a>(&self, tee_classes: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        let tee_classes: Vec<&str> = tee_classes.into_iter().collect();
+        let mut missing: Vec<&str> = self
+            .by_class
+            .keys()
+            .map(String::as_str)
+            .filter(|class| !tee_classes.contains(class))
+            .collect();
+        if !missing.is_empty() {
+            missing.sort_unstable();
+            bail!(
+                "Policies are mapped to tee class `{}`, but no evidence of it is given.",
+                missing.join("`, `")
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Merge the trust claims evaluated by another policy into `merged`, keeping
+/// the worse value of each claim.
+fn merge_rules_result(merged: &mut HashMap<String, Value>, rules_result: HashMap<String, Value>) {
+    for (claim, value) in rules_result {
+        let worse = merged
+            .get(&claim)
+            .is_none_or(|current| claim_severity(&value) > claim_severity(current));
+        if worse {
+            merged.insert(claim, value);
+        }
+    }
+}
+
+/// Rank AR4SI trust claim values from best to worst: no claim, affirming,
+/// verifier error, warning, contraindicated and negative values, which AR4SI
+/// reserves and so must not pass for anything better. Values that are not
+/// `i8` integers rank last so `appraisal_from_rules` reports them.
+fn claim_severity(value: &Value) -> u8 {
+    match value.as_i64() {
+        Some(0) => 0,
+        Some(2..=31) => 1,
+        Some(1) => 2,
+        Some(32..=95) => 3,
+        Some(96..=127) => 4,
+        Some(-128..=-1) => 5,
+        _ => u8::MAX,
+    }
+}
+
+/// Build an appraisal from the trust claims a policy evaluated and derive its
+/// status from the resulting trust vector.
+pub fn appraisal_from_rules(rules_result: &HashMap<String, Value>) -> Result<Appraisal> {
+    let mut appraisal = Appraisal::new();
+
+    for (k, v) in rules_result {
+        let claim_value = i8::try_from(v.as_i64().context("Policy claim value is not an integer")?)
+            .context("Policy claim value is outside the i8 range")?;
+        debug!("Policy claim: {}: {}", k, claim_value);
+
+        // The definition of Trustworthiness Claims in AR4SI
+        // (https://www.ietf.org/archive/id/draft-ietf-rats-ar4si-09.html#name-supportable-trustworthiness-cl)
+        // uses hyphens while the policy engine uses underscores.
+        // so we need to convert underscores to hyphens here.
+        let k = k.replace('_', "-");
+
+        appraisal
+            .trust_vector
+            .mut_by_name(&k)
+            .with_context(|| format!("`{k}` is not an AR4SI trust claim"))?
+            .set(claim_value);
+    }
+
+    if !appraisal.trust_vector.any_set() {
+        bail!("At least one policy claim must be set.");
+    }
+
+    appraisal.update_status_from_trust_vector();
+    Ok(appraisal)
+}
+
 /// This function does three things.
 ///
 /// 1) If the input claims include an init_data claim (meaning that
@@ -522,10 +780,28 @@ mod tests {
     use std::io::Write;
     use tempfile::NamedTempFile;
 
+    use crate::policy_engine::opa::OPAInMemory;
+    use crate::token::signer::EphemeralSigner;
     use crate::TeeClaims;
 
     use super::*;
 
+    /// A broker without fs, appraising with the in-memory `policy` as the
+    /// default policy and signing with `signer`.
+    fn in_memory_broker(
+        policy: &str,
+        settings: TokenBrokerSettings,
+        signer: Arc<EphemeralSigner<SecretKey>>,
+    ) -> EarAttestationTokenBroker {
+        let policy_engine = OPAInMemory::with_raw_default_policy(
+            policy,
+            DEFAULT_POLICY_ID,
+            crate::config::DEFAULT_ARTIFACT_SERVER_ADDRESS,
+        )
+        .unwrap();
+        EarAttestationTokenBroker::from_components(settings, signer, Arc::new(policy_engine))
+    }
+
     #[cfg(feature = "fs")]
     #[tokio::test]
     async fn test_issue_ear_ephemeral_key() {
@@ -547,6 +823,8 @@ mod tests {
                     runtime_data_claims: json!({"runtime_data": "111"}),
                     init_data_claims: json!({"initdata": "111"}),
                     additional_data: None,
+                    nonce: None,
+                    evidence_digest: None,
                 }],
                 vec!["default".into()],
                 crate::rvps::empty_test_resolver(),
@@ -566,6 +844,9 @@ mod tests {
             key_path: private_key_file.path().to_str().unwrap().to_string(),
             cert_url: None,
             cert_path: None,
+            pkcs11: None,
+            kms: None,
+            rotation: None,
         };
 
         let mut config = Configuration::default();
@@ -585,6 +866,8 @@ mod tests {
                     runtime_data_claims: json!({"runtime_data": "111"}),
                     init_data_claims: json!({"initdata": "111"}),
                     additional_data: None,
+                    nonce: None,
+                    evidence_digest: None,
                 }],
                 vec!["default".into()],
                 crate::rvps::empty_test_resolver(),
@@ -628,6 +911,8 @@ mod tests {
                     runtime_data_claims: Value::Null,
                     init_data_claims: Value::Null,
                     additional_data: None,
+                    nonce: None,
+                    evidence_digest: None,
                 }],
                 vec!["default".into()],
                 crate::rvps::empty_test_resolver(),
@@ -671,19 +956,10 @@ default hardware := 97
 default configuration := 36
 default file_system := 35
 "#;
-        use crate::policy_engine::opa::OPAInMemory;
-        let policy_engine: Arc<dyn PolicyEngine> = Arc::new(
-            OPAInMemory::with_raw_default_policy(
-                TRIVIAL_EAR_POLICY,
-                DEFAULT_POLICY_ID,
-                crate::config::DEFAULT_ARTIFACT_SERVER_ADDRESS,
-            )
-            .unwrap(),
-        );
-        let broker = EarAttestationTokenBroker::from_components(
+        let broker = in_memory_broker(
+            TRIVIAL_EAR_POLICY,
             TokenBrokerSettings::default(),
-            Arc::new(crate::token::signer::EphemeralSigner::<SecretKey>::new()),
-            policy_engine,
+            Arc::new(EphemeralSigner::<SecretKey>::new()),
         );
 
         let _token = broker
@@ -695,6 +971,8 @@ default file_system := 35
                     runtime_data_claims: json!({"runtime_data": "111"}),
                     init_data_claims: json!({"initdata": "111"}),
                     additional_data: None,
+                    nonce: None,
+                    evidence_digest: None,
                 }],
                 vec!["default".into()],
                 crate::rvps::empty_test_resolver(),
@@ -703,6 +981,262 @@ default file_system := 35
             .unwrap();
     }
 
+    #[test]
+    fn policy_selection_maps_tee_classes() {
+        let selection =
+            PolicySelection::parse(&["cpu=tdx-prod,gpu=h100".into(), "default".into()]).unwrap();
+        assert_eq!(selection.for_class("cpu").unwrap(), ["tdx-prod", "default"]);
+        assert_eq!(selection.for_class("gpu").unwrap(), ["h100", "default"]);
+        assert_eq!(selection.for_class("npu").unwrap(), ["default"]);
+        selection.check_classes(["cpu", "gpu"]).unwrap();
+        assert!(selection.check_classes(["cpu", "npu"]).is_err());
+
+        let selection = PolicySelection::parse(&["cpu=tdx-prod".into()]).unwrap();
+        assert!(selection.for_class("gpu").is_err());
+
+        assert!(PolicySelection::parse(&[]).is_err());
+        assert!(PolicySelection::parse(&["cpu=".into()]).is_err());
+    }
+
+    #[test]
+    fn merged_rules_keep_the_worst_claim() {
+        let mut merged = HashMap::new();
+        merge_rules_result(
+            &mut merged,
+            HashMap::from([
+                ("executables".to_string(), json!(2)),
+                ("hardware".to_string(), json!(97)),
+                ("configuration".to_string(), json!(96)),
+            ]),
+        );
+        merge_rules_result(
+            &mut merged,
+            HashMap::from([
+                ("executables".to_string(), json!(33)),
+                ("hardware".to_string(), json!(2)),
+                ("configuration".to_string(), json!(-1)),
+                ("runtime_opaque".to_string(), json!(-128)),
+            ]),
+        );
+        merge_rules_result(
+            &mut merged,
+            HashMap::from([("runtime_opaque".to_string(), json!(127))]),
+        );
+        assert_eq!(merged["executables"], json!(33));
+        assert_eq!(merged["hardware"], json!(97));
+        assert_eq!(merged["configuration"], json!(-1));
+        assert_eq!(merged["runtime_opaque"], json!(-128));
+    }
+
+    #[tokio::test]
+    async fn appraises_each_tee_class_with_its_policies() {
+        const AFFIRMING: &str = "package policy\ndefault executables := 2\ndefault hardware := 2\n";
+        const WARNING: &str = "package policy\ndefault executables := 33\ndefault hardware := 2\n";
+
+        let broker = in_memory_broker(
+            AFFIRMING,
+            TokenBrokerSettings::default(),
+            Arc::new(EphemeralSigner::<SecretKey>::new()),
+        );
+        for (id, policy) in [("cpu-policy", AFFIRMING), ("gpu-policy", WARNING)] {
+            broker
+                .set_policy(id.to_string(), URL_SAFE_NO_PAD.encode(policy))
+                .await
+                .unwrap();
+        }
+
+        let tee_claims = |tee_class: &str| TeeClaims {
+            tee: Tee::Sample,
+            tee_class: tee_class.to_string(),
+            claims: json!({"claim": "claim1"}),
+            runtime_data_claims: Value::Null,
+            init_data_claims: Value::Null,
+            additional_data: None,
+            nonce: None,
+            evidence_digest: None,
+        };
+        let issue = |policy_ids: Vec<String>| {
+            broker.issue(
+                vec![tee_claims("cpu"), tee_claims("gpu")],
+                policy_ids,
+                crate::rvps::empty_test_resolver(),
+            )
+        };
+        let decode = |token: String| -> Value {
+            let payload = token.split('.').nth(1).unwrap().to_string();
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap()
+        };
+
+        let ear = decode(
+            issue(vec!["cpu=cpu-policy".into(), "gpu=gpu-policy".into()])
+                .await
+                .unwrap(),
+        );
+        let cpu = &ear["submods"]["cpu0"];
+        let gpu = &ear["submods"]["gpu0"];
+        assert_eq!(cpu["ear.appraisal-policy-id"], "cpu-policy");
+        assert_eq!(cpu["ear.status"], "affirming");
+        assert_eq!(gpu["ear.appraisal-policy-id"], "gpu-policy");
+        assert_eq!(gpu["ear.status"], "warning");
+
+        // Every supplied policy is honored: the cpu is appraised by both,
+        // and the unmapped policy appraises every class.
+        let ear = decode(
+            issue(vec![
+                "cpu=cpu-policy,cpu=gpu-policy".into(),
+                "cpu-policy".into(),
+            ])
+            .await
+            .unwrap(),
+        );
+        let cpu = &ear["submods"]["cpu0"];
+        assert_eq!(cpu["ear.appraisal-policy-id"], "cpu-policy,gpu-policy");
+        assert_eq!(cpu["ear.status"], "warning");
+        assert_eq!(ear["submods"]["gpu0"]["ear.status"], "affirming");
+
+        let ear = decode(
+            issue(vec!["gpu=cpu-policy".into(), "gpu-policy".into()])
+                .await
+                .unwrap(),
+        );
+        let gpu = &ear["submods"]["gpu0"];
+        assert_eq!(gpu["ear.appraisal-policy-id"], "cpu-policy,gpu-policy");
+        assert_eq!(gpu["ear.status"], "warning");
+
+        // The gpu has no policy, and the npu no evidence.
+        assert!(issue(vec!["cpu=cpu-policy".into()]).await.is_err());
+        assert!(issue(vec![
+            "cpu=cpu-policy,gpu=cpu-policy".into(),
+            "npu=gpu-policy".into()
+        ])
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn issues_cose_ear() {
+        use coset::{CoseSign1, TaggedCborSerializable};
+        use p256::ecdsa::signature::Verifier;
+        use p256::ecdsa::{Signature, SigningKey};
+
+        const POLICY: &str = "package policy\ndefault executables := 2\ndefault hardware := 2\n";
+        let signer = Arc::new(EphemeralSigner::<SecretKey>::new());
+        let verifying_key = SigningKey::from(signer.private_key())
+            .verifying_key()
+            .clone();
+        let broker = in_memory_broker(
+            POLICY,
+            TokenBrokerSettings {
+                token_format: TokenFormat::Cose,
+                ..Default::default()
+            },
+            signer,
+        );
+        let tee_claims = || {
+            vec![TeeClaims {
+                tee: Tee::Sample,
+                tee_class: "cpu".to_string(),
+                claims: json!({"claim": "claim1"}),
+                runtime_data_claims: Value::Null,
+                init_data_claims: Value::Null,
+                additional_data: None,
+                nonce: None,
+                evidence_digest: None,
+            }]
+        };
+
+        let token = broker
+            .issue(
+                tee_claims(),
+                vec![DEFAULT_POLICY_ID.into()],
+                crate::rvps::empty_test_resolver(),
+            )
+            .await
+            .unwrap();
+        assert!(!token.contains('.'));
+
+        let sign1 = CoseSign1::from_tagged_slice(&URL_SAFE_NO_PAD.decode(token).unwrap()).unwrap();
+        sign1
+            .verify_signature(&[], |signature, data| {
+                verifying_key.verify(data, &Signature::from_slice(signature)?)
+            })
+            .unwrap();
+
+        // Drop the `exp`, `jti` and namespace CWT claims, which are
+        // extensions of this broker, and read the rest back as an EAR.
+        let mut claims: coset::cbor::Value =
+            coset::cbor::from_reader(sign1.payload.unwrap().as_slice()).unwrap();
+        let cti = claims
+            .as_map()
+            .unwrap()
+            .iter()
+            .find(|(label, _)| *label == coset::cbor::Value::Integer(CWT_CTI.into()))
+            .map(|(_, cti)| cti.clone());
+        assert!(matches!(cti, Some(coset::cbor::Value::Bytes(_))), "{cti:?}");
+        claims.as_map_mut().unwrap().retain(|(label, _)| {
+            ![CWT_EXP, CWT_CTI, RVPS_NAMESPACE_KEY]
+                .into_iter()
+                .any(|key| *label == coset::cbor::Value::Integer(key.into()))
+        });
+        let ear: Ear = claims.deserialized().unwrap();
+        assert_eq!(ear.profile, DEFAULT_PROFILE);
+        assert_eq!(
+            ear.submods["cpu0"].policy_id.as_deref(),
+            Some(DEFAULT_POLICY_ID)
+        );
+
+        // The format requested per evaluation overrides the configured one.
+        let token = broker
+            .issue_with_format(
+                tee_claims(),
+                vec![DEFAULT_POLICY_ID.into()],
+                crate::rvps::empty_test_resolver(),
+                Some(TokenFormat::Jwt),
+            )
+            .await
+            .unwrap();
+        assert_eq!(token.split('.').count(), 3);
+    }
+
+    #[tokio::test]
+    async fn echoes_nonce_and_evidence_digest() {
+        const POLICY: &str = "package policy\ndefault executables := 2\ndefault hardware := 2\n";
+        let broker = in_memory_broker(
+            POLICY,
+            TokenBrokerSettings::default(),
+            Arc::new(EphemeralSigner::<SecretKey>::new()),
+        );
+        let nonce = "fCV9zNb0W3t2tJjMOz1ag2TU8nmw4ht6nIdyJAt6M4s";
+
+        let token = broker
+            .issue(
+                vec![TeeClaims {
+                    tee: Tee::Sample,
+                    tee_class: "cpu".to_string(),
+                    claims: json!({"claim": "claim1"}),
+                    runtime_data_claims: Value::Null,
+                    init_data_claims: Value::Null,
+                    additional_data: None,
+                    nonce: Some(nonce.to_string()),
+                    evidence_digest: Some("sha384:00ff".to_string()),
+                }],
+                vec![DEFAULT_POLICY_ID.into()],
+                crate::rvps::empty_test_resolver(),
+            )
+            .await
+            .unwrap();
+
+        let payload = token.split('.').nth(1).unwrap();
+        let ear: Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap();
+        assert_eq!(ear["eat_nonce"], nonce);
+        assert!(ear["jti"].is_string());
+        assert_eq!(ear[RVPS_NAMESPACE_CLAIM], crate::rvps::DEFAULT_NAMESPACE);
+        assert_eq!(
+            ear["submods"]["cpu0"]["ear.veraison.annotated-evidence"]["evidence_digest"],
+            "sha384:00ff"
+        );
+    }
+
     #[test]
     fn test_transform_claims() {
         let json = json!({
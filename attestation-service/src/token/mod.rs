@@ -7,16 +7,20 @@ use crate::rvps::ReferenceValueResolver;
 use crate::TeeClaims;
 use anyhow::*;
 use const_format::concatcp;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
-use strum::Display;
+use strum::{Display, EnumString};
 
 use crate::config::DEFAULT_WORK_DIR;
 
 pub mod ear_broker;
 pub mod oidc;
 pub mod signer;
+pub mod signer_kms;
+pub mod signer_pkcs11;
+pub mod signer_rotation;
 pub mod signer_transparency;
 pub mod simple;
 
@@ -44,6 +48,25 @@ pub trait AttestationTokenBroker: Send + Sync {
         reference_value_resolver: Arc<ReferenceValueResolver>,
     ) -> Result<String>;
 
+    /// Issue a token like [`Self::issue`], encoded in `format` instead of the
+    /// broker's configured format when it is given. Brokers that only issue
+    /// JWTs reject other formats. Default: JWT only.
+    async fn issue_with_format(
+        &self,
+        tee_claims: Vec<TeeClaims>,
+        policy_ids: Vec<String>,
+        reference_value_resolver: Arc<ReferenceValueResolver>,
+        format: Option<TokenFormat>,
+    ) -> Result<String> {
+        match format {
+            None | Some(TokenFormat::Jwt) => {
+                self.issue(tee_claims, policy_ids, reference_value_resolver)
+                    .await
+            }
+            Some(format) => bail!("This token broker cannot issue {format} tokens"),
+        }
+    }
+
     /// Set a policy for the given `policy_id`.
     /// The `policy` string is encoded in URL-safe base64 (no padding, i.e. URL_SAFE_NO_PAD).
     async fn set_policy(&self, policy_id: String, policy: String) -> Result<()>;
@@ -99,6 +122,47 @@ pub trait AttestationTokenBroker: Send + Sync {
     }
 }
 
+/// The `eat_nonce` claim echoing the verified challenge nonces of the
+/// evidence: a string for a single nonce, an array when the pieces of
+/// evidence answer different ones. `None` if no evidence carries a nonce.
+pub(crate) fn eat_nonce(all_tee_claims: &[TeeClaims]) -> Option<Value> {
+    let mut nonces: Vec<&str> = Vec::new();
+    for nonce in all_tee_claims.iter().filter_map(|c| c.nonce.as_deref()) {
+        if !nonces.contains(&nonce) {
+            nonces.push(nonce);
+        }
+    }
+
+    match nonces.as_slice() {
+        [] => None,
+        [nonce] => Some(Value::from(*nonce)),
+        nonces => Some(Value::from(nonces.to_vec())),
+    }
+}
+
+/// The evidence digests of an evaluation, in request order.
+pub(crate) fn evidence_digests(all_tee_claims: &[TeeClaims]) -> Vec<String> {
+    all_tee_claims
+        .iter()
+        .filter_map(|c| c.evidence_digest.clone())
+        .collect()
+}
+
+/// The encoding of an issued attestation token.
+#[derive(
+    Deserialize, Serialize, Debug, Clone, Copy, Default, Display, EnumString, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum TokenFormat {
+    /// A JWS compact serialized JWT.
+    #[default]
+    Jwt,
+    /// A COSE_Sign1 (CWT) carrying CBOR claims, URL-safe base64 encoded
+    /// without padding. Only the EAR broker issues it.
+    Cose,
+}
+
 #[derive(Deserialize, Debug, Clone, Display, PartialEq)]
 #[serde(tag = "type")]
 pub enum AttestationTokenConfig {
@@ -13,14 +13,11 @@ use base64::Engine;
 use const_format::concatcp;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
-use rsa::pkcs1v15::{Signature, SigningKey};
-use rsa::signature::Signer;
 use rsa::traits::PublicKeyParts;
-use rsa::RsaPrivateKey;
+use rsa::{RsaPrivateKey, RsaPublicKey};
 use serde::Deserialize;
 use serde_json::{json, Map, Value};
 use serde_variant::to_variant_name;
-use sha2::Sha256;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
@@ -29,7 +26,7 @@ use crate::rvps::ReferenceValueResolver;
 use crate::token::{AttestationTokenBroker, DEFAULT_TOKEN_WORK_DIR};
 use crate::TeeClaims;
 
-use super::signer::SignKeyProvider;
+use super::signer::{SignAlgorithm, SignKeyProvider};
 #[cfg(feature = "fs")]
 use super::signer_transparency;
 use super::{COCO_AS_ISSUER_NAME, DEFAULT_TOKEN_DURATION};
@@ -157,7 +154,9 @@ impl OIDCAttestationTokenBroker {
         log::info!("Loading default AS policy \"oidc_default_policy.rego\"");
 
         let signer: Arc<dyn SignKeyProvider<RsaPrivateKey>> = match config.signer {
-            Some(sc) => Arc::new(super::signer::FsSigner::<RsaPrivateKey>::from_config(sc)?),
+            Some(sc) => {
+                super::signer::from_config::<RsaPrivateKey>(sc, config.settings.duration_min * 60)?
+            }
             None => {
                 log::info!(
                     "No Token Signer key in config file, create an ephemeral key and without CA pubkey cert"
@@ -187,21 +186,22 @@ impl OIDCAttestationTokenBroker {
 }
 
 impl OIDCAttestationTokenBroker {
-    fn rs256_sign(&self, payload: &[u8]) -> Result<Vec<u8>> {
-        let signing_key = SigningKey::<Sha256>::new(self.signer.private_key().clone());
-        let sig: Signature = signing_key.sign(payload);
-        Ok(Box::<[u8]>::from(sig).to_vec())
+    async fn rs256_sign(&self, kid: Option<&str>, payload: &[u8]) -> Result<Vec<u8>> {
+        self.signer
+            .sign_with_key(kid, SignAlgorithm::Rs256, payload)
+            .await
     }
 
-    fn pubkey_jwks(&self) -> Result<String> {
-        let n = self.signer.private_key().n().to_bytes_be();
-        let e = self.signer.private_key().e().to_bytes_be();
+    fn pubkey_jwks(&self, kid: Option<String>, public_key: &RsaPublicKey) -> Result<String> {
+        let n = public_key.n().to_bytes_be();
+        let e = public_key.e().to_bytes_be();
 
         let mut jwk = Jwk {
             kty: "RSA".to_string(),
             alg: OIDC_TOKEN_ALG.to_string(),
             n: URL_SAFE_NO_PAD.encode(n),
             e: URL_SAFE_NO_PAD.encode(e),
+            kid,
             x5u: None,
             x5c: None,
         };
@@ -291,7 +291,7 @@ impl AttestationTokenBroker for OIDCAttestationTokenBroker {
             })
             .collect();
 
-        let token_claims = json!({
+        let mut token_claims = json!({
             "tee": to_variant_name(&all_tee_claims[0].tee)?,
             "evaluation-reports": policies,
             // "tcb-status": tcb_claims, // omitted due to size limit
@@ -300,12 +300,30 @@ impl AttestationTokenBroker for OIDCAttestationTokenBroker {
                 "runtime_data": all_tee_claims[0].runtime_data_claims,
             },
         });
+        if let Some(nonce) = super::eat_nonce(&all_tee_claims) {
+            token_claims["eat_nonce"] = nonce;
+        }
+        let evidence_digests = super::evidence_digests(&all_tee_claims);
+        if !evidence_digests.is_empty() {
+            token_claims["evidence-digests"] = json!(evidence_digests);
+        }
+        token_claims["rvps-namespace"] = json!(reference_value_resolver.namespace());
 
-        let header_value = json!({
+        let (kid, public_key) = self.signer.active_key().await?;
+        let mut header_value = json!({
             "typ": "JWT",
             "alg": OIDC_TOKEN_ALG,
-            "jwk": serde_json::from_str::<Value>(&self.pubkey_jwks()?)?["keys"][0].clone(),
         });
+        // Keys of a rotating signer are named by `kid` and looked up in the
+        // published JWKS rather than embedded.
+        match &kid {
+            Some(kid) => header_value["kid"] = json!(kid),
+            None => {
+                header_value["jwk"] =
+                    serde_json::from_str::<Value>(&self.pubkey_jwks(None, &public_key)?)?["keys"][0]
+                        .clone()
+            }
+        }
         let header_string = serde_json::to_string(&header_value)?;
         let header_b64 = URL_SAFE_NO_PAD.encode(header_string.as_bytes());
 
@@ -430,7 +448,9 @@ impl AttestationTokenBroker for OIDCAttestationTokenBroker {
         let claims_b64 = URL_SAFE_NO_PAD.encode(claims_string.as_bytes());
 
         let signature_payload = format!("{header_b64}.{claims_b64}");
-        let signature = self.rs256_sign(signature_payload.as_bytes())?;
+        let signature = self
+            .rs256_sign(kid.as_deref(), signature_payload.as_bytes())
+            .await?;
         let signature_b64 = URL_SAFE_NO_PAD.encode(signature);
 
         let token = format!("{signature_payload}.{signature_b64}");
@@ -474,26 +494,16 @@ impl AttestationTokenBroker for OIDCAttestationTokenBroker {
         self.signer.cert_url()
     }
 
-    /// Publish the configured signer's RSA public key as a JWKS
-    /// `{"keys":[...]}` with `kty/n/e` only — matching the historical service
-    /// endpoint output. Returns `None` when no signer is configured (i.e. the
+    /// Publish the configured signer's RSA public keys as a JWKS
+    /// `{"keys":[...]}` with `kty/n/e/alg`, plus the `kid` of each key of a
+    /// rotating signer. Returns `None` when no signer is configured (i.e. the
     /// signer is ephemeral, per [`SignKeyProvider::is_configured`]): an
     /// ephemeral key is freshly generated per process start and is not
     /// publishable, so the `/jwks` endpoint answers `404`. This preserves the
     /// long-standing behavior of only publishing a JWKS for an explicitly
     /// configured signer.
     async fn configured_signer_jwks(&self) -> Result<Option<String>> {
-        if !self.signer.is_configured() {
-            return Ok(None);
-        }
-        let n = self.signer.private_key().n().to_bytes_be();
-        let e = self.signer.private_key().e().to_bytes_be();
-        let jwk = json!({
-            "kty": "RSA",
-            "n": URL_SAFE_NO_PAD.encode(n),
-            "e": URL_SAFE_NO_PAD.encode(e),
-        });
-        Ok(Some(serde_json::to_string(&json!({ "keys": vec![jwk] }))?))
+        super::signer::configured_jwks(self.signer.as_ref(), OIDC_TOKEN_ALG).await
     }
 
     async fn oid_config_json(&self) -> Result<Option<String>> {
@@ -519,6 +529,8 @@ struct Jwk {
     n: String,
     e: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub x5u: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub x5c: Option<Vec<String>>,
@@ -577,6 +589,8 @@ mod tests {
                     runtime_data_claims: json!({"runtime_data": "111"}),
                     init_data_claims: json!({"initdata": "111"}),
                     additional_data: Some(json!({"additional_data": "111"})),
+                    nonce: None,
+                    evidence_digest: None,
                 }],
                 vec!["default".into()],
                 crate::rvps::empty_test_resolver(),
@@ -614,6 +628,8 @@ mod tests {
             runtime_data_claims: serde_json::Value::Null,
             init_data_claims: serde_json::Value::Null,
             additional_data: None,
+            nonce: None,
+            evidence_digest: None,
         };
         let token = broker
             .issue(
@@ -670,6 +686,8 @@ mod tests {
                     runtime_data_claims: json!({"runtime_data": "111"}),
                     init_data_claims: json!({"initdata": "111"}),
                     additional_data: None,
+                    nonce: None,
+                    evidence_digest: None,
                 }],
                 vec!["default".into()],
                 crate::rvps::empty_test_resolver(),
@@ -759,8 +777,8 @@ frJCGYDUg+8c
 ";
 
     #[cfg(feature = "fs")]
-    #[test]
-    fn test_oidc_signer_cert_chain_x5c() {
+    #[tokio::test]
+    async fn test_oidc_signer_cert_chain_x5c() {
         // Exercise the `signer = Some(...)` branch of
         // `OIDCAttestationTokenBroker::new` with a PEM private key and a
         // 2-cert PEM chain. This drives the previously-untested cert-chain
@@ -777,6 +795,9 @@ frJCGYDUg+8c
                 key_path: key_file.path().to_string_lossy().to_string(),
                 cert_url: None,
                 cert_path: Some(chain_file.path().to_string_lossy().to_string()),
+                pkcs11: None,
+                kms: None,
+                rotation: None,
             }),
             ..Configuration::default()
         };
@@ -787,8 +808,15 @@ frJCGYDUg+8c
         )
         .expect("broker construction with signer + cert chain must succeed");
 
+        let (kid, public_key) = broker
+            .signer
+            .active_key()
+            .await
+            .expect("active_key must succeed");
         let jwks = serde_json::from_str::<serde_json::Value>(
-            &broker.pubkey_jwks().expect("pubkey_jwks must succeed"),
+            &broker
+                .pubkey_jwks(kid, &public_key)
+                .expect("pubkey_jwks must succeed"),
         )
         .expect("pubkey_jwks must return valid JSON");
 
@@ -853,6 +881,9 @@ frJCGYDUg+8c
                 key_path: key_file.path().to_string_lossy().to_string(),
                 cert_url: None,
                 cert_path: None,
+                pkcs11: None,
+                kms: None,
+                rotation: None,
             }),
             ..Configuration::default()
         };
@@ -7,38 +7,246 @@
 //!
 //! [`SignKeyProvider`]`<K>` is the common trait; [`FsSigner`]`<K>` (key
 //! material loaded from a [`SignerConfig`] on disk, fs-gated construction)
-//! and [`EphemeralSigner`]`<K>` (a fresh key generated at runtime) are the
-//! two implementations. The shared cert-chain / cert-url / cert-pem-live
-//! plumbing is written once in the generic trait impls; K-specific
-//! construction (PEM parsing, key generation) is provided via concrete
-//! inherent impls on the specific key types the brokers use
-//! (`rsa::RsaPrivateKey`, `p256::SecretKey`).
+//! and [`EphemeralSigner`]`<K>` (a fresh key generated at runtime) hold the
+//! private key in process memory. [`super::signer_pkcs11::Pkcs11Signer`] and
+//! [`super::signer_kms::KmsSigner`] keep it in an HSM or a KMS and only ask
+//! the backend for signatures and the public key.
+//! [`super::signer_rotation::RotatingSigner`] rotates keys it generates, or
+//! keys of a PKCS#11 token or a KMS, publishing several `kid`-tagged keys at a time. The shared cert-chain /
+//! cert-url / cert-pem-live plumbing is written once in the generic trait
+//! impls; K-specific signing and PEM parsing live in the [`SignKey`] impls of
+//! the key types the brokers use (`rsa::RsaPrivateKey`, `p256::SecretKey`).
 
 #[cfg(feature = "fs")]
 use anyhow::Context;
-use anyhow::Result;
+use anyhow::{bail, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use p256::ecdsa::signature::Signer;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::pkcs8::{DecodePrivateKey as EcDecodePrivateKey, DecodePublicKey as EcDecodePublicKey};
 use p256::SecretKey;
 use rand::rngs::OsRng;
-use rsa::RsaPrivateKey;
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::{DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding};
+use rsa::signature::SignatureEncoding;
+use rsa::traits::PublicKeyParts;
+use rsa::{RsaPrivateKey, RsaPublicKey};
 use rustls_pki_types::CertificateDer;
 use serde::Deserialize;
-
-#[cfg(feature = "fs")]
-use p256::pkcs8::DecodePrivateKey as EcDecodePrivateKey;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256, Sha384};
 #[cfg(feature = "fs")]
-use rsa::pkcs1::DecodeRsaPrivateKey;
+use std::sync::Arc;
+
 #[cfg(feature = "fs")]
 use rustls_pki_types::pem::PemObject;
 
+pub use super::signer_kms::KmsSignerConfig;
+pub use super::signer_pkcs11::Pkcs11SignerConfig;
+pub use super::signer_rotation::KeyRotationConfig;
+
 /// Shared RSA key size (bits) for ephemeral RSA signers.
-const RSA_KEY_BITS: u32 = 2048;
-
-/// A signing-key provider. `K` is the concrete key type and is fixed per
-/// broker, so `dyn SignKeyProvider<RsaPrivateKey>` /
-/// `dyn SignKeyProvider<SecretKey>` are valid trait objects: every method has
-/// no method-level generics and does not return `Self`.
-pub trait SignKeyProvider<K>: Send + Sync {
-    fn private_key(&self) -> &K;
+pub(crate) const RSA_KEY_BITS: u32 = 2048;
+
+/// The JWS/COSE signature algorithms used by the token brokers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignAlgorithm {
+    /// RSASSA-PKCS1-v1_5 with SHA-256 (OIDC broker).
+    Rs256,
+    /// RSASSA-PKCS1-v1_5 with SHA-384 (simple broker).
+    Rs384,
+    /// ECDSA P-256 with SHA-256 (EAR broker).
+    Es256,
+}
+
+impl SignAlgorithm {
+    /// The digest of `message` that is signed under this algorithm.
+    pub fn digest(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Rs256 | Self::Es256 => Sha256::digest(message).to_vec(),
+            Self::Rs384 => Sha384::digest(message).to_vec(),
+        }
+    }
+}
+
+/// A key type the brokers sign with. Implemented for `RsaPrivateKey` and
+/// `p256::SecretKey`; also selects the public key type of the signers that
+/// keep the private key outside of the process.
+pub trait SignKey: Send + Sync + Sized + 'static {
+    type PublicKey: Clone + Send + Sync;
+    /// Whether this is an EC P-256 rather than an RSA key, for signers that
+    /// generate keys outside of the process.
+    const EC_P256: bool;
+
+    /// Sign `message` under `alg`. ES256 signatures are the fixed-size
+    /// `r || s` form used by JWS and COSE.
+    fn sign_message(&self, alg: SignAlgorithm, message: &[u8]) -> Result<Vec<u8>>;
+    fn public_key(&self) -> Self::PublicKey;
+    /// Parse a PEM private key.
+    fn from_pem(pem: &str) -> Result<Self>;
+    /// Parse a PEM SubjectPublicKeyInfo.
+    fn public_key_from_pem(pem: &str) -> Result<Self::PublicKey>;
+    /// The DER SubjectPublicKeyInfo of `public_key`.
+    fn public_key_der(public_key: &Self::PublicKey) -> Result<Vec<u8>>;
+    /// The key type specific JWK members (`kty`, `n`/`e` or `crv`/`x`/`y`).
+    fn jwk_params(public_key: &Self::PublicKey) -> Result<Map<String, Value>>;
+    /// Generate a fresh key.
+    fn generate() -> Result<Self>;
+    /// Encode the key as PKCS#8 PEM.
+    fn to_pem(&self) -> Result<String>;
+}
+
+/// The `kid` of a public key: the base64url SHA-256 digest of its
+/// SubjectPublicKeyInfo.
+pub fn key_id<K: SignKey>(public_key: &K::PublicKey) -> Result<String> {
+    Ok(URL_SAFE_NO_PAD.encode(Sha256::digest(K::public_key_der(public_key)?)))
+}
+
+impl SignKey for RsaPrivateKey {
+    type PublicKey = RsaPublicKey;
+    const EC_P256: bool = false;
+
+    fn sign_message(&self, alg: SignAlgorithm, message: &[u8]) -> Result<Vec<u8>> {
+        let signature = match alg {
+            SignAlgorithm::Rs256 => SigningKey::<Sha256>::new(self.clone()).sign(message),
+            SignAlgorithm::Rs384 => SigningKey::<Sha384>::new(self.clone()).sign(message),
+            SignAlgorithm::Es256 => bail!("ES256 requires an EC P-256 key"),
+        };
+        Ok(signature.to_vec())
+    }
+
+    fn public_key(&self) -> RsaPublicKey {
+        self.to_public_key()
+    }
+
+    /// PKCS#8, with a PKCS#1 fallback.
+    fn from_pem(pem: &str) -> Result<Self> {
+        Ok(RsaPrivateKey::from_pkcs8_pem(pem).or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))?)
+    }
+
+    fn public_key_from_pem(pem: &str) -> Result<RsaPublicKey> {
+        Ok(
+            RsaPublicKey::from_public_key_pem(pem)
+                .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))?,
+        )
+    }
+
+    fn public_key_der(public_key: &RsaPublicKey) -> Result<Vec<u8>> {
+        Ok(public_key.to_public_key_der()?.into_vec())
+    }
+
+    fn jwk_params(public_key: &RsaPublicKey) -> Result<Map<String, Value>> {
+        let jwk = json!({
+            "kty": "RSA",
+            "n": URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be()),
+            "e": URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be()),
+        });
+        Ok(jwk.as_object().cloned().unwrap_or_default())
+    }
+
+    fn generate() -> Result<Self> {
+        Ok(RsaPrivateKey::new(&mut OsRng, RSA_KEY_BITS as usize)?)
+    }
+
+    fn to_pem(&self) -> Result<String> {
+        Ok(self.to_pkcs8_pem(LineEnding::LF)?.to_string())
+    }
+}
+
+impl SignKey for SecretKey {
+    type PublicKey = p256::PublicKey;
+    const EC_P256: bool = true;
+
+    fn sign_message(&self, alg: SignAlgorithm, message: &[u8]) -> Result<Vec<u8>> {
+        if alg != SignAlgorithm::Es256 {
+            bail!("{alg:?} requires an RSA key");
+        }
+        let signature: p256::ecdsa::Signature = p256::ecdsa::SigningKey::from(self).sign(message);
+        Ok(signature.to_bytes().to_vec())
+    }
+
+    fn public_key(&self) -> p256::PublicKey {
+        SecretKey::public_key(self)
+    }
+
+    /// SEC1, with a PKCS#8 fallback.
+    fn from_pem(pem: &str) -> Result<Self> {
+        Ok(SecretKey::from_sec1_pem(pem).or_else(|_| SecretKey::from_pkcs8_pem(pem))?)
+    }
+
+    fn public_key_from_pem(pem: &str) -> Result<p256::PublicKey> {
+        Ok(p256::PublicKey::from_public_key_pem(pem)?)
+    }
+
+    fn public_key_der(public_key: &p256::PublicKey) -> Result<Vec<u8>> {
+        Ok(public_key.to_public_key_der()?.into_vec())
+    }
+
+    fn jwk_params(public_key: &p256::PublicKey) -> Result<Map<String, Value>> {
+        let point = public_key.to_encoded_point(false);
+        let (Some(x), Some(y)) = (point.x(), point.y()) else {
+            bail!("EC public key has no coordinates");
+        };
+        let jwk = json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "x": URL_SAFE_NO_PAD.encode(x),
+            "y": URL_SAFE_NO_PAD.encode(y),
+        });
+        Ok(jwk.as_object().cloned().unwrap_or_default())
+    }
+
+    fn generate() -> Result<Self> {
+        Ok(SecretKey::random(&mut OsRng))
+    }
+
+    fn to_pem(&self) -> Result<String> {
+        Ok(self.to_pkcs8_pem(LineEnding::LF)?.to_string())
+    }
+}
+
+/// A signing-key provider. `K` is the key type and is fixed per broker, so
+/// `dyn SignKeyProvider<RsaPrivateKey>` / `dyn SignKeyProvider<SecretKey>`
+/// are valid trait objects: every method has no method-level generics and
+/// does not return `Self`. Providers backed by an HSM or a KMS never hold a
+/// `K`; they only sign and report the public key.
+#[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait::async_trait(?Send))]
+#[cfg_attr(
+    not(all(
+        target_arch = "wasm32",
+        target_vendor = "unknown",
+        target_os = "unknown"
+    )),
+    async_trait::async_trait
+)]
+pub trait SignKeyProvider<K: SignKey>: Send + Sync {
+    /// Sign `message` under `alg` (see [`SignKey::sign_message`]).
+    async fn sign(&self, alg: SignAlgorithm, message: &[u8]) -> Result<Vec<u8>>;
+    /// The public key of the signing key, as published in JWKS and `jwk`
+    /// headers.
+    async fn public_key(&self) -> Result<K::PublicKey>;
+    /// The `kid` and public key of the key tokens are signed with now.
+    /// Signers holding a single key have no `kid`.
+    async fn active_key(&self) -> Result<(Option<String>, K::PublicKey)> {
+        Ok((None, self.public_key().await?))
+    }
+    /// Sign with the key `key_id` returned by [`Self::active_key`], so that
+    /// the signature matches the `kid` already put in the token header.
+    async fn sign_with_key(
+        &self,
+        _key_id: Option<&str>,
+        alg: SignAlgorithm,
+        message: &[u8],
+    ) -> Result<Vec<u8>> {
+        self.sign(alg, message).await
+    }
+    /// The keys published at `/jwks`, with their `kid`s: the active key, and
+    /// for a rotating signer also the next and the not yet retired keys.
+    async fn published_keys(&self) -> Result<Vec<(Option<String>, K::PublicKey)>> {
+        Ok(vec![self.active_key().await?])
+    }
     fn cert_chain(&self) -> Option<Result<Vec<CertificateDer<'static>>>>;
     fn cert_url(&self) -> Option<&str>;
     /// The signer's certificate-chain raw PEM bytes, read lazily from the
@@ -47,16 +255,16 @@ pub trait SignKeyProvider<K>: Send + Sync {
     /// `None`. The broker forwards this through `signer_cert_pem_live`.
     fn cert_pem_live(&self) -> Option<Result<Vec<u8>>>;
     /// Whether this signer was loaded from explicit configuration
-    /// ([`FsSigner`]) rather than an ephemeral key generated at runtime
-    /// ([`EphemeralSigner`]).
+    /// ([`FsSigner`], or an HSM/KMS backed signer) rather than an ephemeral
+    /// key generated at runtime ([`EphemeralSigner`]).
     ///
     /// Brokers use this to decide whether to publish the signer's public key
     /// at the `/jwks` endpoint: the attestation service has historically
     /// published a JWKS only when a signer was explicitly configured,
     /// answering `404` otherwise. An ephemeral key is freshly generated per
     /// process start, so clients cannot pin it and it is not published.
-    /// [`FsSigner`] overrides this to `true`; [`EphemeralSigner`] keeps the
-    /// default `false`.
+    /// Configured signers override this to `true`; [`EphemeralSigner`] keeps
+    /// the default `false`.
     fn is_configured(&self) -> bool {
         false
     }
@@ -99,10 +307,35 @@ pub struct EphemeralSigner<K> {
 }
 
 #[cfg(feature = "fs")]
-impl<K: Send + Sync> SignKeyProvider<K> for FsSigner<K> {
-    fn private_key(&self) -> &K {
+impl<K: SignKey> FsSigner<K> {
+    pub fn private_key(&self) -> &K {
+        &self.private_key
+    }
+}
+
+impl<K: SignKey> EphemeralSigner<K> {
+    pub fn private_key(&self) -> &K {
         &self.private_key
     }
+}
+
+#[cfg(feature = "fs")]
+#[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait::async_trait(?Send))]
+#[cfg_attr(
+    not(all(
+        target_arch = "wasm32",
+        target_vendor = "unknown",
+        target_os = "unknown"
+    )),
+    async_trait::async_trait
+)]
+impl<K: SignKey> SignKeyProvider<K> for FsSigner<K> {
+    async fn sign(&self, alg: SignAlgorithm, message: &[u8]) -> Result<Vec<u8>> {
+        self.private_key.sign_message(alg, message)
+    }
+    async fn public_key(&self) -> Result<K::PublicKey> {
+        Ok(SignKey::public_key(&self.private_key))
+    }
     // Returns the construction-time cached chain (see field doc). Never reads
     // disk here — the read+parse already happened in `from_config`, so by the
     // time this is called the result is infallible. (The `Result` in the
@@ -132,9 +365,21 @@ impl<K: Send + Sync> SignKeyProvider<K> for FsSigner<K> {
     }
 }
 
-impl<K: Send + Sync> SignKeyProvider<K> for EphemeralSigner<K> {
-    fn private_key(&self) -> &K {
-        &self.private_key
+#[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait::async_trait(?Send))]
+#[cfg_attr(
+    not(all(
+        target_arch = "wasm32",
+        target_vendor = "unknown",
+        target_os = "unknown"
+    )),
+    async_trait::async_trait
+)]
+impl<K: SignKey> SignKeyProvider<K> for EphemeralSigner<K> {
+    async fn sign(&self, alg: SignAlgorithm, message: &[u8]) -> Result<Vec<u8>> {
+        self.private_key.sign_message(alg, message)
+    }
+    async fn public_key(&self) -> Result<K::PublicKey> {
+        Ok(SignKey::public_key(&self.private_key))
     }
     fn cert_chain(&self) -> Option<Result<Vec<CertificateDer<'static>>>> {
         None
@@ -149,29 +394,148 @@ impl<K: Send + Sync> SignKeyProvider<K> for EphemeralSigner<K> {
 
 /// Shared signer configuration (deserialized from the token-broker config).
 ///
-/// `key_path` is required. `cert_url`/`cert_path` are optional: when omitted
-/// from the config they deserialize to `None` (serde already defaults a
-/// missing `Option<T>` field to `None`, so the `#[serde(default)]` attributes
-/// are kept for explicitness rather than out of necessity). When set, they
-/// only influence the token's `x5u`/`x5c`; they do not affect the signing key.
+/// `key_path` is required unless the key is held by a PKCS#11 token
+/// (`pkcs11`) or a KMS (`kms`), or generated by the signer (`rotation`). `cert_url`/`cert_path` are optional: when
+/// omitted from the config they deserialize to `None` (serde already defaults
+/// a missing `Option<T>` field to `None`, so the `#[serde(default)]`
+/// attributes are kept for explicitness rather than out of necessity). When
+/// set, they only influence the token's `x5u`/`x5c`; they do not affect the
+/// signing key.
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct SignerConfig {
+    #[serde(default)]
     pub key_path: String,
     #[serde(default)]
     pub cert_url: Option<String>,
     // PEM format certificate chain.
     #[serde(default)]
     pub cert_path: Option<String>,
+    /// Sign with a private key held by a PKCS#11 token (`pkcs11-signer`
+    /// feature).
+    #[serde(default)]
+    pub pkcs11: Option<Pkcs11SignerConfig>,
+    /// Sign with a private key held by a KMS (`kms-signer` feature).
+    #[serde(default)]
+    pub kms: Option<KmsSignerConfig>,
+    /// Rotate the signing keys on a schedule, in the PKCS#11 token or the
+    /// KMS if one is configured.
+    #[serde(default)]
+    pub rotation: Option<KeyRotationConfig>,
+}
+
+/// Resolve the signer of a configured [`SignerConfig`]: a PKCS#11 or KMS
+/// backed signer or a rotating signer when one is configured, otherwise the
+/// PEM key at `key_path`. `token_lifetime_secs` is the lifetime of the
+/// tokens the broker issues, for which a rotated-out key stays published.
+#[cfg(feature = "fs")]
+pub fn from_config<K: SignKey>(
+    signer: SignerConfig,
+    token_lifetime_secs: i64,
+) -> Result<Arc<dyn SignKeyProvider<K>>> {
+    if signer.pkcs11.is_some() && signer.kms.is_some() {
+        bail!("Token Signer can only use one of a PKCS#11 token and a KMS");
+    }
+    #[cfg(not(feature = "pkcs11-signer"))]
+    if signer.pkcs11.is_some() {
+        bail!("Token Signer uses a PKCS#11 token, but the `pkcs11-signer` feature is not enabled");
+    }
+    #[cfg(not(feature = "kms-signer"))]
+    if signer.kms.is_some() {
+        bail!("Token Signer uses a KMS, but the `kms-signer` feature is not enabled");
+    }
+
+    if let Some(rotation) = &signer.rotation {
+        if signer.cert_path.is_some() {
+            bail!("Token Signer can not certify rotated keys with the fixed chain of `cert_path`");
+        }
+        let keys = rotation_keys::<K>(&signer)?;
+        return Ok(Arc::new(super::signer_rotation::RotatingSigner::<K>::new(
+            rotation,
+            token_lifetime_secs,
+            keys,
+            signer.cert_url.clone(),
+        )?));
+    }
+
+    #[cfg(feature = "pkcs11-signer")]
+    if let Some(pkcs11) = &signer.pkcs11 {
+        let cert_chain = load_cert_chain(&signer.cert_path)?;
+        return Ok(Arc::new(super::signer_pkcs11::Pkcs11Signer::<K>::new(
+            pkcs11,
+            signer.cert_url.clone(),
+            signer.cert_path.clone(),
+            cert_chain,
+        )?));
+    }
+
+    #[cfg(feature = "kms-signer")]
+    if let Some(kms) = &signer.kms {
+        let cert_chain = load_cert_chain(&signer.cert_path)?;
+        return Ok(Arc::new(super::signer_kms::KmsSigner::<K>::new(
+            kms.clone(),
+            signer.cert_url.clone(),
+            signer.cert_path.clone(),
+            cert_chain,
+        )?));
+    }
+
+    Ok(Arc::new(FsSigner::<K>::from_config(signer)?))
+}
+
+/// Where a rotating signer gets its keys: generated in the configured
+/// PKCS#11 token, taken from the configured KMS, or generated as PEM keys.
+#[cfg(feature = "fs")]
+fn rotation_keys<K: SignKey>(
+    signer: &SignerConfig,
+) -> Result<Box<dyn super::signer_rotation::RotationKeys<K>>> {
+    #[cfg(feature = "pkcs11-signer")]
+    if let Some(pkcs11) = &signer.pkcs11 {
+        return Ok(Box::new(super::signer_pkcs11::Pkcs11Keys::<K>::new(
+            pkcs11.clone(),
+        )));
+    }
+    #[cfg(feature = "kms-signer")]
+    if let Some(kms) = &signer.kms {
+        return Ok(Box::new(super::signer_kms::KmsKeys::<K>::new(kms.clone())?));
+    }
+    let _ = signer;
+    Ok(Box::new(super::signer_rotation::PemKeys))
+}
+
+/// The `/jwks` document of a configured signer: every published key with
+/// its `alg`, and its `kid` when the signer has several. `None` for an
+/// ephemeral signer (see [`SignKeyProvider::is_configured`]).
+pub async fn configured_jwks<K: SignKey>(
+    signer: &dyn SignKeyProvider<K>,
+    alg: &str,
+) -> Result<Option<String>> {
+    if !signer.is_configured() {
+        return Ok(None);
+    }
+
+    let mut keys = Vec::new();
+    for (kid, public_key) in signer.published_keys().await? {
+        let mut jwk = K::jwk_params(&public_key)?;
+        jwk.insert("alg".to_string(), alg.into());
+        if let Some(kid) = kid {
+            jwk.insert("kid".to_string(), kid.into());
+        }
+        keys.push(Value::Object(jwk));
+    }
+
+    Ok(Some(serde_json::to_string(&json!({ "keys": keys }))?))
 }
 
 // --- Concrete construction impls ("specific code on the generic") ---
 
 /// Read and PEM-parse a certificate chain from `cert_path` once, returning
 /// the parsed `CertificateDer` list (or `None` when no `cert_path` is
-/// configured). Used by both `FsSigner::*::from_config` to populate the
-/// cached `cert_chain` field at construction time.
+/// configured). Used by [`FsSigner::from_config`] and the HSM/KMS backed
+/// signers to populate the cached `cert_chain` field at construction time.
 #[cfg(feature = "fs")]
-fn load_cert_chain(cert_path: &Option<String>) -> Result<Option<Vec<CertificateDer<'static>>>> {
+pub(crate) fn load_cert_chain(
+    cert_path: &Option<String>,
+) -> Result<Option<Vec<CertificateDer<'static>>>> {
     match cert_path {
         Some(cert_path) => {
             let pem_cert_chain =
@@ -185,37 +549,14 @@ fn load_cert_chain(cert_path: &Option<String>) -> Result<Option<Vec<CertificateD
 }
 
 #[cfg(feature = "fs")]
-impl FsSigner<RsaPrivateKey> {
-    /// Parse an RSA private key from `SignerConfig::key_path` (PKCS#8, with a
-    /// PKCS#1 fallback).
+impl<K: SignKey> FsSigner<K> {
+    /// Parse the private key at `SignerConfig::key_path` (see
+    /// [`SignKey::from_pem`] for the accepted encodings).
     pub fn from_config(signer: SignerConfig) -> Result<Self> {
         let pem_data = std::fs::read_to_string(&signer.key_path)
             .context("Read Token Signer private key failed")?;
-        let private_key = RsaPrivateKey::from_pkcs8_pem(&pem_data)
-            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(&pem_data))
-            .context("Parse Token Signer private key failed")?;
-        // Cache the cert chain at construction (see `cert_chain` field doc).
-        let cert_chain = load_cert_chain(&signer.cert_path)?;
-        Ok(Self {
-            private_key,
-            cert_url: signer.cert_url,
-            cert_path: signer.cert_path,
-            cert_chain,
-        })
-    }
-}
-
-#[cfg(feature = "fs")]
-impl FsSigner<SecretKey> {
-    /// Parse an EC P-256 private key from `SignerConfig::key_path` (SEC1, with
-    /// a PKCS#8 fallback).
-    pub fn from_config(signer: SignerConfig) -> Result<Self> {
-        let pem_data =
-            std::fs::read(&signer.key_path).context("Read Token Signer private key failed")?;
-        let pem_str = std::str::from_utf8(&pem_data).context("Token Signer key not UTF-8")?;
-        let private_key = SecretKey::from_sec1_pem(pem_str)
-            .or_else(|_| SecretKey::from_pkcs8_pem(pem_str))
-            .context("Parse Token Signer private key failed")?;
+        let private_key =
+            K::from_pem(&pem_data).context("Parse Token Signer private key failed")?;
         // Cache the cert chain at construction (see `cert_chain` field doc).
         let cert_chain = load_cert_chain(&signer.cert_path)?;
         Ok(Self {
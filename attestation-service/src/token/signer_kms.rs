@@ -0,0 +1,303 @@
+// Copyright (c) 2024 by Alibaba.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Token signer whose private key lives in a KMS, accessed through the
+//! [`kms::Signer`] API of the `kms` crate.
+//!
+//! The token digest is computed locally and signed by the KMS with
+//! `RSA_PKCS1_SHA_256` (RS256), `RSA_PKCS1_SHA_384` (RS384) or
+//! `ECDSA_SHA_256` (ES256). The KMS client is created and the public key is
+//! fetched on first use, then both are kept for the life of the signer.
+//!
+//! The KMS API can not create keys, so with key rotation [`KmsKeys`] takes
+//! each new key from the pre-provisioned `rotation_key_ids` that are not in
+//! use.
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// Configuration of [`KmsSigner`].
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct KmsSignerConfig {
+    /// KMS provider, e.g. `aliyun`.
+    pub provider: String,
+
+    /// Id of the asymmetric signing key in the KMS. Required unless the
+    /// signer rotates its keys.
+    #[serde(default)]
+    pub key_id: String,
+
+    /// Provider specific settings to create the KMS client, see the
+    /// `kms` crate.
+    #[serde(default)]
+    pub provider_settings: Map<String, Value>,
+
+    /// Ids of the keys a rotating signer rotates through. `key_id` is not
+    /// used then.
+    #[serde(default)]
+    pub rotation_key_ids: Vec<String>,
+}
+
+#[cfg(feature = "kms-signer")]
+pub use signer::KmsSigner;
+
+#[cfg(all(feature = "kms-signer", feature = "fs"))]
+pub use signer::KmsKeys;
+
+#[cfg(feature = "kms-signer")]
+mod signer {
+    use anyhow::{bail, Context, Result};
+    use rustls_pki_types::CertificateDer;
+    use tokio::sync::OnceCell;
+
+    use super::KmsSignerConfig;
+    use crate::token::signer::{SignAlgorithm, SignKey, SignKeyProvider};
+    #[cfg(feature = "fs")]
+    use crate::token::signer_rotation::RotationKeys;
+    #[cfg(feature = "fs")]
+    use std::sync::Arc;
+
+    pub struct KmsSigner<K: SignKey> {
+        config: KmsSignerConfig,
+        client: OnceCell<Box<dyn kms::Signer>>,
+        public_key: OnceCell<K::PublicKey>,
+        cert_url: Option<String>,
+        cert_path: Option<String>,
+        cert_chain: Option<Vec<CertificateDer<'static>>>,
+    }
+
+    impl<K: SignKey> KmsSigner<K> {
+        /// A signer with the `key_id` key of `config`, which a signer
+        /// rotating through `rotation_key_ids` leaves empty (see [`KmsKeys`]).
+        pub fn new(
+            config: KmsSignerConfig,
+            cert_url: Option<String>,
+            cert_path: Option<String>,
+            cert_chain: Option<Vec<CertificateDer<'static>>>,
+        ) -> Result<Self> {
+            if config.key_id.is_empty() {
+                bail!("KMS signer has no `key_id` and does not rotate through `rotation_key_ids`");
+            }
+
+            Ok(Self {
+                config,
+                client: OnceCell::new(),
+                public_key: OnceCell::new(),
+                cert_url,
+                cert_path,
+                cert_chain,
+            })
+        }
+
+        async fn client(&self) -> Result<&dyn kms::Signer> {
+            let client = self
+                .client
+                .get_or_try_init(|| async {
+                    kms::new_signer(&self.config.provider, self.config.provider_settings.clone())
+                        .await
+                        .with_context(|| format!("create {} KMS client", self.config.provider))
+                })
+                .await?;
+            Ok(client.as_ref())
+        }
+    }
+
+    #[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait::async_trait(?Send))]
+    #[cfg_attr(
+        not(all(
+            target_arch = "wasm32",
+            target_vendor = "unknown",
+            target_os = "unknown"
+        )),
+        async_trait::async_trait
+    )]
+    impl<K: SignKey> SignKeyProvider<K> for KmsSigner<K> {
+        async fn sign(&self, alg: SignAlgorithm, message: &[u8]) -> Result<Vec<u8>> {
+            let algorithm = match alg {
+                SignAlgorithm::Rs256 => "RSA_PKCS1_SHA_256",
+                SignAlgorithm::Rs384 => "RSA_PKCS1_SHA_384",
+                SignAlgorithm::Es256 => "ECDSA_SHA_256",
+            };
+            let signature = self
+                .client()
+                .await?
+                .sign(&alg.digest(message), &self.config.key_id, algorithm)
+                .await
+                .context("sign token with KMS")?;
+
+            match alg {
+                // KMS returns ASN.1 DER ECDSA signatures, tokens carry r || s.
+                SignAlgorithm::Es256 => Ok(p256::ecdsa::Signature::from_der(&signature)
+                    .context("parse KMS ECDSA signature")?
+                    .to_bytes()
+                    .to_vec()),
+                SignAlgorithm::Rs256 | SignAlgorithm::Rs384 => Ok(signature),
+            }
+        }
+        async fn public_key(&self) -> Result<K::PublicKey> {
+            let public_key = self
+                .public_key
+                .get_or_try_init(|| async {
+                    let pem = self
+                        .client()
+                        .await?
+                        .get_public_key(&self.config.key_id)
+                        .await
+                        .context("get public key from KMS")?;
+                    K::public_key_from_pem(&pem).context("KMS key does not match the token broker")
+                })
+                .await?;
+            Ok(public_key.clone())
+        }
+        fn cert_chain(&self) -> Option<Result<Vec<CertificateDer<'static>>>> {
+            self.cert_chain.clone().map(Ok)
+        }
+        fn cert_url(&self) -> Option<&str> {
+            self.cert_url.as_deref()
+        }
+        fn cert_pem_live(&self) -> Option<Result<Vec<u8>>> {
+            self.cert_path
+                .as_ref()
+                .map(|path| std::fs::read(path).context("Failed to read certificate file"))
+        }
+        fn is_configured(&self) -> bool {
+            true
+        }
+    }
+
+    /// The `rotation_key_ids` of a [`KmsSignerConfig`], for a
+    /// [`crate::token::signer_rotation::RotatingSigner`].
+    #[cfg(feature = "fs")]
+    pub struct KmsKeys<K: SignKey> {
+        config: KmsSignerConfig,
+        _key: std::marker::PhantomData<K>,
+    }
+
+    #[cfg(feature = "fs")]
+    impl<K: SignKey> KmsKeys<K> {
+        pub fn new(config: KmsSignerConfig) -> Result<Self> {
+            // The active, the next and a superseded key are in use at a time.
+            if config.rotation_key_ids.len() < 3 {
+                bail!("Rotating KMS keys needs at least three `rotation_key_ids`");
+            }
+            if config.rotation_key_ids.iter().any(String::is_empty) {
+                bail!("KMS `rotation_key_ids` has an empty key id");
+            }
+
+            Ok(Self {
+                config,
+                _key: std::marker::PhantomData,
+            })
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    #[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait::async_trait(?Send))]
+    #[cfg_attr(
+        not(all(
+            target_arch = "wasm32",
+            target_vendor = "unknown",
+            target_os = "unknown"
+        )),
+        async_trait::async_trait
+    )]
+    impl<K: SignKey> RotationKeys<K> for KmsKeys<K> {
+        async fn create(&self, _activates_at: i64, in_use: &[String]) -> Result<String> {
+            self.config
+                .rotation_key_ids
+                .iter()
+                .find(|key_id| !in_use.contains(key_id))
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("all KMS rotation_key_ids are in use"))
+        }
+
+        async fn open(&self, key: &str) -> Result<Arc<dyn SignKeyProvider<K>>> {
+            let config = KmsSignerConfig {
+                key_id: key.to_string(),
+                ..self.config.clone()
+            };
+            Ok(Arc::new(KmsSigner::<K>::new(config, None, None, None)?))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use p256::ecdsa::signature::Verifier;
+        use p256::pkcs8::{EncodePublicKey, LineEnding};
+        use p256::SecretKey;
+
+        /// A KMS holding one EC P-256 key, signing digests like Aliyun KMS.
+        struct TestKms {
+            key: SecretKey,
+        }
+
+        #[async_trait::async_trait]
+        impl kms::Signer for TestKms {
+            async fn sign(
+                &self,
+                digest: &[u8],
+                key_id: &str,
+                algorithm: &str,
+            ) -> kms::Result<Vec<u8>> {
+                use p256::ecdsa::signature::hazmat::PrehashSigner;
+
+                assert_eq!((key_id, algorithm), ("key", "ECDSA_SHA_256"));
+                let signature: p256::ecdsa::Signature = p256::ecdsa::SigningKey::from(&self.key)
+                    .sign_prehash(digest)
+                    .unwrap();
+                Ok(signature.to_der().as_bytes().to_vec())
+            }
+
+            async fn get_public_key(&self, _key_id: &str) -> kms::Result<String> {
+                Ok(self
+                    .key
+                    .public_key()
+                    .to_public_key_pem(LineEnding::LF)
+                    .unwrap())
+            }
+        }
+
+        fn config(key_id: &str, rotation_key_ids: &[&str]) -> KmsSignerConfig {
+            KmsSignerConfig {
+                provider: "aliyun".into(),
+                key_id: key_id.into(),
+                provider_settings: Default::default(),
+                rotation_key_ids: rotation_key_ids.iter().map(|id| id.to_string()).collect(),
+            }
+        }
+
+        #[test]
+        fn requires_key_id_unless_rotating() {
+            assert!(KmsSigner::<SecretKey>::new(config("", &[]), None, None, None).is_err());
+            assert!(
+                KmsSigner::<SecretKey>::new(config("", &["a", "b", "c"]), None, None, None)
+                    .is_err()
+            );
+
+            #[cfg(feature = "fs")]
+            {
+                assert!(KmsKeys::<SecretKey>::new(config("", &["a", "b", "c"])).is_ok());
+                assert!(KmsKeys::<SecretKey>::new(config("", &["a", "b"])).is_err());
+                assert!(KmsKeys::<SecretKey>::new(config("", &["a", "", "c"])).is_err());
+            }
+        }
+
+        #[tokio::test]
+        async fn signs_es256_with_kms_key() {
+            let key = SecretKey::random(&mut rand::rngs::OsRng);
+            let signer = KmsSigner::<SecretKey>::new(config("key", &[]), None, None, None).unwrap();
+            let _ = signer.client.set(Box::new(TestKms { key: key.clone() }));
+
+            let signature = signer.sign(SignAlgorithm::Es256, b"token").await.unwrap();
+            let public_key = signer.public_key().await.unwrap();
+            assert_eq!(public_key, key.public_key());
+
+            let signature = p256::ecdsa::Signature::from_slice(&signature).unwrap();
+            p256::ecdsa::VerifyingKey::from(public_key)
+                .verify(b"token", &signature)
+                .unwrap();
+        }
+    }
+}
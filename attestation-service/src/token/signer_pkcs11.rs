@@ -0,0 +1,489 @@
+// Copyright (c) 2024 by Alibaba.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Token signer whose private key lives in a PKCS#11 token (an HSM, or
+//! SoftHSM for testing).
+//!
+//! The key pair is looked up by its `CKA_LABEL`. The public key is read from
+//! the token once at construction; every signature is computed by the token:
+//! `CKM_SHA256_RSA_PKCS` / `CKM_SHA384_RSA_PKCS` for RS256 / RS384 and
+//! `CKM_ECDSA` over a SHA-256 digest for ES256, whose output already is the
+//! `r || s` form used in tokens.
+//!
+//! A module is loaded and initialized once per process, and its context is
+//! shared by every signer using it: initializing a module twice fails.
+//!
+//! With key rotation, [`Pkcs11Keys`] generates each key pair in the token,
+//! labelled `<key_label>-<activation time>`. Retired key pairs are left in
+//! the token.
+
+use serde::Deserialize;
+
+/// Configuration of [`Pkcs11Signer`].
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Pkcs11SignerConfig {
+    /// Path of the PKCS#11 module, e.g. `/usr/lib/softhsm/libsofthsm2.so`.
+    pub module: String,
+
+    /// Label of the token holding the key. If not set, the first slot with a
+    /// token is used.
+    #[serde(default)]
+    pub token_label: Option<String>,
+
+    /// User PIN of the token.
+    pub pin: String,
+
+    /// Label (`CKA_LABEL`) of the signing key pair.
+    pub key_label: String,
+}
+
+#[cfg(feature = "pkcs11-signer")]
+pub use signer::Pkcs11Signer;
+
+#[cfg(all(feature = "pkcs11-signer", feature = "fs"))]
+pub use signer::Pkcs11Keys;
+
+#[cfg(feature = "pkcs11-signer")]
+mod signer {
+    use anyhow::{anyhow, bail, Context, Result};
+    use cryptoki::context::{CInitializeArgs, Pkcs11};
+    use cryptoki::error::{Error, RvError};
+    use cryptoki::mechanism::Mechanism;
+    use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+    use cryptoki::session::{Session, UserType};
+    use cryptoki::slot::Slot;
+    use cryptoki::types::AuthPin;
+    use p256::pkcs8::EncodePublicKey as _;
+    use rsa::pkcs8::LineEnding;
+    use rsa::BigUint;
+    use rustls_pki_types::CertificateDer;
+    use std::collections::HashMap;
+    use std::sync::{Mutex, OnceLock};
+
+    use super::Pkcs11SignerConfig;
+    use crate::token::signer::{SignAlgorithm, SignKey, SignKeyProvider, RSA_KEY_BITS};
+    #[cfg(feature = "fs")]
+    use crate::token::signer_rotation::RotationKeys;
+    #[cfg(feature = "fs")]
+    use std::sync::Arc;
+
+    /// The initialized context of the PKCS#11 module at `module`, loading
+    /// the module on first use.
+    fn module_context(module: &str) -> Result<Pkcs11> {
+        static CONTEXTS: OnceLock<Mutex<HashMap<String, Pkcs11>>> = OnceLock::new();
+        let mut contexts = CONTEXTS
+            .get_or_init(Default::default)
+            .lock()
+            .map_err(|_| anyhow!("PKCS#11 context lock poisoned"))?;
+        if let Some(context) = contexts.get(module) {
+            return Ok(context.clone());
+        }
+
+        let context =
+            Pkcs11::new(module).with_context(|| format!("load PKCS#11 module {module}"))?;
+        context
+            .initialize(CInitializeArgs::OsThreads)
+            .context("initialize PKCS#11 module")?;
+        contexts.insert(module.to_string(), context.clone());
+        Ok(context)
+    }
+
+    pub struct Pkcs11Signer<K: SignKey> {
+        // Keeps the module loaded for as long as the session is used.
+        _context: Pkcs11,
+        // A PKCS#11 session must not be used by two threads at once.
+        session: Mutex<Session>,
+        private_key: ObjectHandle,
+        public_key: K::PublicKey,
+        cert_url: Option<String>,
+        cert_path: Option<String>,
+        cert_chain: Option<Vec<CertificateDer<'static>>>,
+    }
+
+    impl<K: SignKey> Pkcs11Signer<K> {
+        pub fn new(
+            config: &Pkcs11SignerConfig,
+            cert_url: Option<String>,
+            cert_path: Option<String>,
+            cert_chain: Option<Vec<CertificateDer<'static>>>,
+        ) -> Result<Self> {
+            let context = module_context(&config.module)?;
+            let session = context.open_ro_session(token_slot(&context, config)?)?;
+            login(&session, config)?;
+
+            let private_key = find_key(&session, ObjectClass::PRIVATE_KEY, &config.key_label)?;
+            let public_key = find_key(&session, ObjectClass::PUBLIC_KEY, &config.key_label)?;
+            let public_key = K::public_key_from_pem(&public_key_pem(&session, public_key)?)
+                .context("PKCS#11 key does not match the token broker")?;
+
+            Ok(Self {
+                _context: context,
+                session: Mutex::new(session),
+                private_key,
+                public_key,
+                cert_url,
+                cert_path,
+                cert_chain,
+            })
+        }
+    }
+
+    /// The slot of the token named in `config`.
+    fn token_slot(context: &Pkcs11, config: &Pkcs11SignerConfig) -> Result<Slot> {
+        for slot in context.get_slots_with_token()? {
+            let Some(label) = &config.token_label else {
+                return Ok(slot);
+            };
+            if context.get_token_info(slot)?.label() == label.as_str() {
+                return Ok(slot);
+            }
+        }
+        bail!(
+            "no PKCS#11 token {}",
+            config.token_label.as_deref().unwrap_or("present")
+        )
+    }
+
+    /// Log in as the user. The login state is shared by all sessions of the
+    /// process with a token, so an existing login is fine.
+    fn login(session: &Session, config: &Pkcs11SignerConfig) -> Result<()> {
+        match session.login(UserType::User, Some(&AuthPin::new(config.pin.clone()))) {
+            Err(Error::Pkcs11(RvError::UserAlreadyLoggedIn, ..)) => Ok(()),
+            result => result.context("log in to PKCS#11 token"),
+        }
+    }
+
+    fn find_key(session: &Session, class: ObjectClass, label: &str) -> Result<ObjectHandle> {
+        session
+            .find_objects(&[
+                Attribute::Class(class),
+                Attribute::Label(label.as_bytes().to_vec()),
+            ])?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("no PKCS#11 {class:?} labelled {label}"))
+    }
+
+    /// The PEM SubjectPublicKeyInfo of a PKCS#11 RSA or EC P-256 public key.
+    fn public_key_pem(session: &Session, key: ObjectHandle) -> Result<String> {
+        let attributes = session.get_attributes(
+            key,
+            &[
+                AttributeType::KeyType,
+                AttributeType::Modulus,
+                AttributeType::PublicExponent,
+                AttributeType::EcPoint,
+            ],
+        )?;
+
+        let (mut key_type, mut modulus, mut exponent, mut ec_point) = (None, None, None, None);
+        for attribute in attributes {
+            match attribute {
+                Attribute::KeyType(value) => key_type = Some(value),
+                Attribute::Modulus(value) => modulus = Some(value),
+                Attribute::PublicExponent(value) => exponent = Some(value),
+                Attribute::EcPoint(value) => ec_point = Some(value),
+                _ => {}
+            }
+        }
+
+        match (key_type, modulus, exponent, ec_point) {
+            (Some(KeyType::RSA), Some(n), Some(e), _) => {
+                let key =
+                    rsa::RsaPublicKey::new(BigUint::from_bytes_be(&n), BigUint::from_bytes_be(&e))?;
+                Ok(key.to_public_key_pem(LineEnding::LF)?)
+            }
+            (Some(KeyType::EC), _, _, Some(point)) => {
+                // CKA_EC_POINT is the DER OCTET STRING wrapping the
+                // uncompressed point.
+                let point = match point.as_slice() {
+                    [0x04, len, rest @ ..] if *len as usize == rest.len() => rest,
+                    point => point,
+                };
+                let key = p256::PublicKey::from_sec1_bytes(point)
+                    .context("PKCS#11 EC key is not a P-256 key")?;
+                Ok(key.to_public_key_pem(LineEnding::LF)?)
+            }
+            _ => bail!("unsupported PKCS#11 public key"),
+        }
+    }
+
+    #[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait::async_trait(?Send))]
+    #[cfg_attr(
+        not(all(
+            target_arch = "wasm32",
+            target_vendor = "unknown",
+            target_os = "unknown"
+        )),
+        async_trait::async_trait
+    )]
+    impl<K: SignKey> SignKeyProvider<K> for Pkcs11Signer<K> {
+        async fn sign(&self, alg: SignAlgorithm, message: &[u8]) -> Result<Vec<u8>> {
+            let session = self
+                .session
+                .lock()
+                .map_err(|_| anyhow!("PKCS#11 session lock poisoned"))?;
+            let signature = match alg {
+                SignAlgorithm::Rs256 => {
+                    session.sign(&Mechanism::Sha256RsaPkcs, self.private_key, message)?
+                }
+                SignAlgorithm::Rs384 => {
+                    session.sign(&Mechanism::Sha384RsaPkcs, self.private_key, message)?
+                }
+                SignAlgorithm::Es256 => {
+                    session.sign(&Mechanism::Ecdsa, self.private_key, &alg.digest(message))?
+                }
+            };
+            Ok(signature)
+        }
+        async fn public_key(&self) -> Result<K::PublicKey> {
+            Ok(self.public_key.clone())
+        }
+        fn cert_chain(&self) -> Option<Result<Vec<CertificateDer<'static>>>> {
+            self.cert_chain.clone().map(Ok)
+        }
+        fn cert_url(&self) -> Option<&str> {
+            self.cert_url.as_deref()
+        }
+        fn cert_pem_live(&self) -> Option<Result<Vec<u8>>> {
+            self.cert_path
+                .as_ref()
+                .map(|path| std::fs::read(path).context("Failed to read certificate file"))
+        }
+        fn is_configured(&self) -> bool {
+            true
+        }
+    }
+
+    /// Key pairs generated in the token of a [`Pkcs11SignerConfig`] for a
+    /// [`crate::token::signer_rotation::RotatingSigner`]. `key_label` is the
+    /// prefix of their labels.
+    #[cfg(feature = "fs")]
+    pub struct Pkcs11Keys<K: SignKey> {
+        config: Pkcs11SignerConfig,
+        _key: std::marker::PhantomData<K>,
+    }
+
+    #[cfg(feature = "fs")]
+    impl<K: SignKey> Pkcs11Keys<K> {
+        pub fn new(config: Pkcs11SignerConfig) -> Self {
+            Self {
+                config,
+                _key: std::marker::PhantomData,
+            }
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    #[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait::async_trait(?Send))]
+    #[cfg_attr(
+        not(all(
+            target_arch = "wasm32",
+            target_vendor = "unknown",
+            target_os = "unknown"
+        )),
+        async_trait::async_trait
+    )]
+    impl<K: SignKey> RotationKeys<K> for Pkcs11Keys<K> {
+        async fn create(&self, activates_at: i64, _in_use: &[String]) -> Result<String> {
+            let context = module_context(&self.config.module)?;
+            let session = context.open_rw_session(token_slot(&context, &self.config)?)?;
+            login(&session, &self.config)?;
+
+            let key_label = format!("{}-{activates_at}", self.config.key_label);
+            let label = Attribute::Label(key_label.as_bytes().to_vec());
+            let (mechanism, mut public) = if K::EC_P256 {
+                // DER of the prime256v1 OID.
+                let p256_oid = vec![0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+                (
+                    Mechanism::EccKeyPairGen,
+                    vec![Attribute::EcParams(p256_oid)],
+                )
+            } else {
+                (
+                    Mechanism::RsaPkcsKeyPairGen,
+                    vec![
+                        Attribute::ModulusBits((RSA_KEY_BITS as u64).into()),
+                        Attribute::PublicExponent(vec![0x01, 0x00, 0x01]),
+                    ],
+                )
+            };
+            public.extend([
+                Attribute::Token(true),
+                Attribute::Verify(true),
+                label.clone(),
+            ]);
+            let private = [
+                Attribute::Token(true),
+                Attribute::Private(true),
+                Attribute::Sensitive(true),
+                Attribute::Extractable(false),
+                Attribute::Sign(true),
+                label,
+            ];
+            session
+                .generate_key_pair(&mechanism, &public, &private)
+                .with_context(|| format!("generate PKCS#11 key pair {key_label}"))?;
+
+            Ok(key_label)
+        }
+
+        async fn open(&self, key: &str) -> Result<Arc<dyn SignKeyProvider<K>>> {
+            let config = Pkcs11SignerConfig {
+                key_label: key.to_string(),
+                ..self.config.clone()
+            };
+            Ok(Arc::new(Pkcs11Signer::<K>::new(&config, None, None, None)?))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use p256::ecdsa::signature::Verifier;
+        use p256::SecretKey;
+        use rsa::RsaPrivateKey;
+
+        const RSA_LABEL: &str = "as-rsa";
+        const EC_LABEL: &str = "as-ec";
+        const PIN: &str = "1234";
+
+        /// Initialize a SoftHSM token holding an RSA and an EC P-256 key pair.
+        fn init_softhsm(module: &str, dir: &std::path::Path) {
+            let conf = dir.join("softhsm2.conf");
+            std::fs::create_dir(dir.join("tokens")).unwrap();
+            std::fs::write(
+                &conf,
+                format!(
+                    "directories.tokendir = {}\nobjectstore.backend = file\n",
+                    dir.join("tokens").display()
+                ),
+            )
+            .unwrap();
+            std::env::set_var("SOFTHSM2_CONF", &conf);
+
+            let context = module_context(module).unwrap();
+            let slot = context.get_slots_with_token().unwrap()[0];
+            context
+                .init_token(slot, &AuthPin::new("so-pin".into()), "trustee")
+                .unwrap();
+            let session = context.open_rw_session(slot).unwrap();
+            session
+                .login(UserType::So, Some(&AuthPin::new("so-pin".into())))
+                .unwrap();
+            session.init_pin(&AuthPin::new(PIN.into())).unwrap();
+            session.logout().unwrap();
+            session
+                .login(UserType::User, Some(&AuthPin::new(PIN.into())))
+                .unwrap();
+
+            let label = |label: &str| Attribute::Label(label.as_bytes().to_vec());
+            let private = |key_label: &str| {
+                vec![
+                    Attribute::Token(true),
+                    Attribute::Private(true),
+                    Attribute::Sign(true),
+                    label(key_label),
+                ]
+            };
+            session
+                .generate_key_pair(
+                    &Mechanism::RsaPkcsKeyPairGen,
+                    &[
+                        Attribute::Token(true),
+                        Attribute::ModulusBits(2048.into()),
+                        Attribute::PublicExponent(vec![0x01, 0x00, 0x01]),
+                        label(RSA_LABEL),
+                    ],
+                    &private(RSA_LABEL),
+                )
+                .unwrap();
+            // DER of the prime256v1 OID.
+            let p256_oid = vec![0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+            session
+                .generate_key_pair(
+                    &Mechanism::EccKeyPairGen,
+                    &[
+                        Attribute::Token(true),
+                        Attribute::EcParams(p256_oid),
+                        label(EC_LABEL),
+                    ],
+                    &private(EC_LABEL),
+                )
+                .unwrap();
+        }
+
+        /// Needs SoftHSM: `SOFTHSM2_MODULE=/usr/lib/softhsm/libsofthsm2.so
+        /// cargo test --features softhsm-test softhsm`
+        #[cfg(feature = "softhsm-test")]
+        #[tokio::test]
+        async fn signs_with_softhsm_keys() {
+            let module = std::env::var("SOFTHSM2_MODULE").unwrap();
+            let dir = tempfile::tempdir().unwrap();
+            init_softhsm(&module, dir.path());
+
+            let config = |key_label: &str| Pkcs11SignerConfig {
+                module: module.clone(),
+                token_label: Some("trustee".into()),
+                pin: PIN.into(),
+                key_label: key_label.into(),
+            };
+
+            // Both signers share the module loaded above.
+            let rsa =
+                Pkcs11Signer::<RsaPrivateKey>::new(&config(RSA_LABEL), None, None, None).unwrap();
+            let ec = Pkcs11Signer::<SecretKey>::new(&config(EC_LABEL), None, None, None).unwrap();
+
+            let signature = rsa.sign(SignAlgorithm::Rs384, b"token").await.unwrap();
+            let public_key = rsa.public_key().await.unwrap();
+            rsa::pkcs1v15::VerifyingKey::<sha2::Sha384>::new(public_key)
+                .verify(
+                    b"token",
+                    &rsa::pkcs1v15::Signature::try_from(signature.as_slice()).unwrap(),
+                )
+                .unwrap();
+
+            let signature = ec.sign(SignAlgorithm::Es256, b"token").await.unwrap();
+            let public_key = ec.public_key().await.unwrap();
+            p256::ecdsa::VerifyingKey::from(public_key)
+                .verify(
+                    b"token",
+                    &p256::ecdsa::Signature::from_slice(&signature).unwrap(),
+                )
+                .unwrap();
+
+            // A rotating signer generates its keys in the token.
+            #[cfg(feature = "fs")]
+            {
+                use crate::token::signer_rotation::{KeyRotationConfig, RotatingSigner};
+
+                let rotating = RotatingSigner::<SecretKey>::new(
+                    &KeyRotationConfig {
+                        dir: dir.path().join("keys").to_string_lossy().into_owned(),
+                        rotation_interval_secs: 3600,
+                    },
+                    60,
+                    Box::new(Pkcs11Keys::<SecretKey>::new(config("as-rotated"))),
+                    None,
+                )
+                .unwrap();
+                let published = rotating.published_keys().await.unwrap();
+                assert_eq!(published.len(), 2);
+                let (kid, public_key) = rotating.active_key().await.unwrap();
+                assert_eq!(kid, published[0].0);
+
+                let signature = rotating
+                    .sign_with_key(kid.as_deref(), SignAlgorithm::Es256, b"token")
+                    .await
+                    .unwrap();
+                p256::ecdsa::VerifyingKey::from(public_key)
+                    .verify(
+                        b"token",
+                        &p256::ecdsa::Signature::from_slice(&signature).unwrap(),
+                    )
+                    .unwrap();
+            }
+        }
+    }
+}
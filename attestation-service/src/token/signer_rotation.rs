@@ -0,0 +1,535 @@
+// Copyright (c) 2024 by Alibaba.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Token signer that rotates its key on a schedule.
+//!
+//! The signer keeps several key generations, each identified by the `kid`
+//! derived from its public key (see [`super::signer::key_id`]):
+//!
+//! - the *active* key signs tokens;
+//! - the *next* key is generated one rotation interval ahead of its
+//!   activation and published at `/jwks` right away, so verifiers that cache
+//!   the JWKS already know it when the first token signed with it arrives;
+//! - *superseded* keys stay published until every token they signed has
+//!   expired, i.e. for the maximum token lifetime after they were replaced.
+//!
+//! Rotation is evaluated lazily whenever the signer is used. The generations
+//! are persisted under `dir/keys.json` before they are used, so restarts keep
+//! the keys that tokens in flight were signed with. The keys themselves come
+//! from a [`RotationKeys`] source: PEM keys generated by the signer and stored
+//! in `keys.json`, keys generated in a PKCS#11 token, or keys provisioned in a
+//! KMS.
+
+use serde::Deserialize;
+
+/// Configuration of [`RotatingSigner`].
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct KeyRotationConfig {
+    /// Directory the key generations are stored in.
+    pub dir: String,
+
+    /// Seconds a key is the active signing key before the next key takes
+    /// over. Default: 30 days.
+    #[serde(default = "default_rotation_interval_secs")]
+    pub rotation_interval_secs: u64,
+}
+
+fn default_rotation_interval_secs() -> u64 {
+    30 * 24 * 60 * 60
+}
+
+#[cfg(feature = "fs")]
+pub use signer::{PemKeys, RotatingSigner, RotationKeys};
+
+#[cfg(feature = "fs")]
+mod signer {
+    use anyhow::{anyhow, bail, Context, Result};
+    use rustls_pki_types::CertificateDer;
+    use serde::{Deserialize, Serialize};
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use time::OffsetDateTime;
+    use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+
+    use super::KeyRotationConfig;
+    use crate::token::signer::{key_id, SignAlgorithm, SignKey, SignKeyProvider};
+
+    const KEYS_FILE: &str = "keys.json";
+
+    /// Where a [`RotatingSigner`] creates its keys and finds them again.
+    /// Each key is referred to in `keys.json` by the string `create` returns.
+    #[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait::async_trait(?Send))]
+    #[cfg_attr(
+        not(all(
+            target_arch = "wasm32",
+            target_vendor = "unknown",
+            target_os = "unknown"
+        )),
+        async_trait::async_trait
+    )]
+    pub trait RotationKeys<K: SignKey>: Send + Sync {
+        /// Create the key of the generation activating at `activates_at`.
+        /// `in_use` are the references of the keys not yet retired.
+        async fn create(&self, activates_at: i64, in_use: &[String]) -> Result<String>;
+
+        /// The signer of the key referred to as `key`.
+        async fn open(&self, key: &str) -> Result<Arc<dyn SignKeyProvider<K>>>;
+    }
+
+    /// Keys generated by the signer and stored as PEM in `keys.json`.
+    pub struct PemKeys;
+
+    #[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait::async_trait(?Send))]
+    #[cfg_attr(
+        not(all(
+            target_arch = "wasm32",
+            target_vendor = "unknown",
+            target_os = "unknown"
+        )),
+        async_trait::async_trait
+    )]
+    impl<K: SignKey> RotationKeys<K> for PemKeys {
+        async fn create(&self, _activates_at: i64, _in_use: &[String]) -> Result<String> {
+            K::generate()
+                .context("generate token signing key")?
+                .to_pem()
+        }
+
+        async fn open(&self, key: &str) -> Result<Arc<dyn SignKeyProvider<K>>> {
+            Ok(Arc::new(PemKey(K::from_pem(key)?)))
+        }
+    }
+
+    struct PemKey<K>(K);
+
+    #[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait::async_trait(?Send))]
+    #[cfg_attr(
+        not(all(
+            target_arch = "wasm32",
+            target_vendor = "unknown",
+            target_os = "unknown"
+        )),
+        async_trait::async_trait
+    )]
+    impl<K: SignKey> SignKeyProvider<K> for PemKey<K> {
+        async fn sign(&self, alg: SignAlgorithm, message: &[u8]) -> Result<Vec<u8>> {
+            self.0.sign_message(alg, message)
+        }
+        async fn public_key(&self) -> Result<K::PublicKey> {
+            Ok(self.0.public_key())
+        }
+        fn cert_chain(&self) -> Option<Result<Vec<CertificateDer<'static>>>> {
+            None
+        }
+        fn cert_url(&self) -> Option<&str> {
+            None
+        }
+        fn cert_pem_live(&self) -> Option<Result<Vec<u8>>> {
+            None
+        }
+    }
+
+    struct Generation<K: SignKey> {
+        kid: String,
+        key: String,
+        signer: Arc<dyn SignKeyProvider<K>>,
+        public_key: K::PublicKey,
+        activates_at: i64,
+        superseded_at: Option<i64>,
+    }
+
+    impl<K: SignKey> Clone for Generation<K> {
+        fn clone(&self) -> Self {
+            Self {
+                kid: self.kid.clone(),
+                key: self.key.clone(),
+                signer: Arc::clone(&self.signer),
+                public_key: self.public_key.clone(),
+                activates_at: self.activates_at,
+                superseded_at: self.superseded_at,
+            }
+        }
+    }
+
+    /// On-disk form of a [`Generation`]. `key` is the reference returned by
+    /// [`RotationKeys::create`], the PEM private key for [`PemKeys`].
+    #[derive(Clone, Serialize, Deserialize)]
+    struct StoredGeneration {
+        kid: String,
+        key: String,
+        activates_at: i64,
+        superseded_at: Option<i64>,
+    }
+
+    impl<K: SignKey> Generation<K> {
+        async fn create(
+            keys: &dyn RotationKeys<K>,
+            activates_at: i64,
+            in_use: &[String],
+        ) -> Result<Self> {
+            let key = keys.create(activates_at, in_use).await?;
+            Self::open(
+                keys,
+                StoredGeneration {
+                    kid: String::new(),
+                    key,
+                    activates_at,
+                    superseded_at: None,
+                },
+            )
+            .await
+        }
+
+        async fn open(keys: &dyn RotationKeys<K>, stored: StoredGeneration) -> Result<Self> {
+            let signer = keys.open(&stored.key).await.with_context(|| {
+                format!(
+                    "open token signing key activating at {}",
+                    stored.activates_at
+                )
+            })?;
+            let public_key = signer.public_key().await?;
+            Ok(Self {
+                kid: key_id::<K>(&public_key)?,
+                key: stored.key,
+                signer,
+                public_key,
+                activates_at: stored.activates_at,
+                superseded_at: stored.superseded_at,
+            })
+        }
+
+        fn store(&self) -> StoredGeneration {
+            StoredGeneration {
+                kid: self.kid.clone(),
+                key: self.key.clone(),
+                activates_at: self.activates_at,
+                superseded_at: self.superseded_at,
+            }
+        }
+    }
+
+    struct Generations<K: SignKey> {
+        /// Read from `keys.json` and opened on first use.
+        stored: Vec<StoredGeneration>,
+        /// Sorted by `activates_at`.
+        open: Vec<Generation<K>>,
+    }
+
+    pub struct RotatingSigner<K: SignKey> {
+        path: PathBuf,
+        rotation_interval: i64,
+        token_lifetime: i64,
+        keys: Box<dyn RotationKeys<K>>,
+        cert_url: Option<String>,
+        generations: Mutex<Generations<K>>,
+    }
+
+    impl<K: SignKey> RotatingSigner<K> {
+        /// Load the key generations from `config.dir`. The active and the
+        /// next key are created from `keys` on first use if there are none.
+        /// Superseded keys are kept for `token_lifetime_secs`. `cert_url` is
+        /// published as the `x5u` of the tokens.
+        pub fn new(
+            config: &KeyRotationConfig,
+            token_lifetime_secs: i64,
+            keys: Box<dyn RotationKeys<K>>,
+            cert_url: Option<String>,
+        ) -> Result<Self> {
+            if config.rotation_interval_secs == 0 {
+                bail!("Token Signer key rotation interval must be greater than 0");
+            }
+
+            let path = Path::new(&config.dir).join(KEYS_FILE);
+            let mut stored = match std::fs::read(&path) {
+                Ok(content) => serde_json::from_slice::<Vec<StoredGeneration>>(&content)
+                    .with_context(|| format!("parse {}", path.display()))?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                Err(e) => {
+                    return Err(e).with_context(|| format!("read {}", path.display()));
+                }
+            };
+            stored.sort_by_key(|generation| generation.activates_at);
+
+            Ok(Self {
+                path,
+                rotation_interval: config.rotation_interval_secs as i64,
+                token_lifetime: token_lifetime_secs,
+                keys,
+                cert_url,
+                generations: Mutex::new(Generations {
+                    stored,
+                    open: Vec::new(),
+                }),
+            })
+        }
+
+        /// Bring `generations` up to date at `now`. Returns whether anything
+        /// changed.
+        async fn rotate(&self, generations: &mut Vec<Generation<K>>, now: i64) -> Result<bool> {
+            let mut changed = false;
+
+            if generations.is_empty() {
+                generations.push(Generation::create(self.keys.as_ref(), now, &[]).await?);
+                changed = true;
+            }
+
+            let active = active_index(generations, now);
+            let active_since = generations[active].activates_at;
+            for generation in &mut generations[..active] {
+                if generation.superseded_at.is_none() {
+                    generation.superseded_at = Some(active_since);
+                    changed = true;
+                }
+            }
+
+            let count = generations.len();
+            let token_lifetime = self.token_lifetime;
+            generations.retain(|generation| {
+                generation
+                    .superseded_at
+                    .map_or(true, |superseded_at| superseded_at + token_lifetime > now)
+            });
+            changed |= generations.len() != count;
+
+            if generations.last().map(|generation| generation.activates_at) == Some(active_since) {
+                let activates_at =
+                    (active_since + self.rotation_interval).max(now + self.rotation_interval);
+                let in_use: Vec<String> = generations
+                    .iter()
+                    .map(|generation| generation.key.clone())
+                    .collect();
+                generations
+                    .push(Generation::create(self.keys.as_ref(), activates_at, &in_use).await?);
+                changed = true;
+            }
+
+            Ok(changed)
+        }
+
+        fn persist(&self, generations: &[Generation<K>]) -> Result<()> {
+            let stored: Vec<_> = generations.iter().map(Generation::store).collect();
+            let content = serde_json::to_vec_pretty(&stored)?;
+
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("create {}", dir.display()))?;
+            }
+            let tmp = self.path.with_extension("json.tmp");
+            write_private(&tmp, &content).with_context(|| format!("write {}", tmp.display()))?;
+            std::fs::rename(&tmp, &self.path)
+                .with_context(|| format!("write {}", self.path.display()))?;
+
+            Ok(())
+        }
+
+        /// Lock the generations, rotated to the current time. A rotation is
+        /// only used once it has been persisted, so a failed write is retried
+        /// by the next call.
+        async fn generations(&self) -> Result<MappedMutexGuard<'_, Vec<Generation<K>>>> {
+            let mut generations = self.generations.lock().await;
+            if !generations.stored.is_empty() {
+                let mut open = Vec::with_capacity(generations.stored.len());
+                for stored in generations.stored.clone() {
+                    open.push(Generation::open(self.keys.as_ref(), stored).await?);
+                }
+                generations.open = open;
+                generations.stored.clear();
+            }
+
+            let mut rotated = generations.open.clone();
+            if self.rotate(&mut rotated, now()).await? {
+                self.persist(&rotated)?;
+                generations.open = rotated;
+            }
+            Ok(MutexGuard::map(generations, |generations| {
+                &mut generations.open
+            }))
+        }
+    }
+
+    fn now() -> i64 {
+        OffsetDateTime::now_utc().unix_timestamp()
+    }
+
+    /// The newest generation that is already active, or the oldest one if
+    /// none is (e.g. the clock went backwards).
+    fn active_index<K: SignKey>(generations: &[Generation<K>], now: i64) -> usize {
+        generations
+            .iter()
+            .rposition(|generation| generation.activates_at <= now)
+            .unwrap_or(0)
+    }
+
+    fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+        use std::io::Write;
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(path)?.write_all(content)
+    }
+
+    #[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait::async_trait(?Send))]
+    #[cfg_attr(
+        not(all(
+            target_arch = "wasm32",
+            target_vendor = "unknown",
+            target_os = "unknown"
+        )),
+        async_trait::async_trait
+    )]
+    impl<K: SignKey> SignKeyProvider<K> for RotatingSigner<K> {
+        async fn sign(&self, alg: SignAlgorithm, message: &[u8]) -> Result<Vec<u8>> {
+            self.sign_with_key(None, alg, message).await
+        }
+        async fn public_key(&self) -> Result<K::PublicKey> {
+            Ok(self.active_key().await?.1)
+        }
+        async fn active_key(&self) -> Result<(Option<String>, K::PublicKey)> {
+            let generations = self.generations().await?;
+            let active = &generations[active_index(&generations, now())];
+            Ok((Some(active.kid.clone()), active.public_key.clone()))
+        }
+        async fn sign_with_key(
+            &self,
+            key_id: Option<&str>,
+            alg: SignAlgorithm,
+            message: &[u8],
+        ) -> Result<Vec<u8>> {
+            let signer = {
+                let generations = self.generations().await?;
+                let generation = match key_id {
+                    Some(kid) => generations
+                        .iter()
+                        .find(|generation| generation.kid == kid)
+                        .ok_or_else(|| anyhow!("token signing key {kid} has been retired"))?,
+                    None => &generations[active_index(&generations, now())],
+                };
+                Arc::clone(&generation.signer)
+            };
+            signer.sign(alg, message).await
+        }
+        async fn published_keys(&self) -> Result<Vec<(Option<String>, K::PublicKey)>> {
+            let generations = self.generations().await?;
+            let active = active_index(&generations, now());
+            // Active key first, then the keys to come, then the superseded
+            // ones from the most recent.
+            let order = std::iter::once(active)
+                .chain(active + 1..generations.len())
+                .chain((0..active).rev());
+            Ok(order
+                .map(|i| {
+                    let generation = &generations[i];
+                    (Some(generation.kid.clone()), generation.public_key.clone())
+                })
+                .collect())
+        }
+        /// No certificate chain can certify keys generated on a schedule.
+        fn cert_chain(&self) -> Option<Result<Vec<CertificateDer<'static>>>> {
+            None
+        }
+        fn cert_url(&self) -> Option<&str> {
+            self.cert_url.as_deref()
+        }
+        fn cert_pem_live(&self) -> Option<Result<Vec<u8>>> {
+            None
+        }
+        fn is_configured(&self) -> bool {
+            true
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use p256::ecdsa::signature::Verifier;
+        use p256::SecretKey;
+
+        const DAY: i64 = 24 * 60 * 60;
+
+        fn signer(dir: &Path) -> RotatingSigner<SecretKey> {
+            RotatingSigner::new(
+                &KeyRotationConfig {
+                    dir: dir.to_string_lossy().into_owned(),
+                    rotation_interval_secs: 30 * DAY as u64,
+                },
+                DAY,
+                Box::new(PemKeys),
+                None,
+            )
+            .unwrap()
+        }
+
+        #[tokio::test]
+        async fn rotates_with_overlapping_keys() {
+            let dir = tempfile::tempdir().unwrap();
+            let signer = signer(dir.path());
+
+            // Active and next key are both published from the start.
+            let (active, _) = signer.active_key().await.unwrap();
+            let published = signer.published_keys().await.unwrap();
+            assert_eq!(published.len(), 2);
+            assert_eq!(published[0].0, active);
+            let next = published[1].0.clone();
+
+            // The next key takes over after one interval, the old key stays
+            // published for the token lifetime.
+            let start = now();
+            let mut generations = signer.generations().await.unwrap();
+            assert!(signer
+                .rotate(&mut generations, start + 30 * DAY)
+                .await
+                .unwrap());
+            let index = active_index(&generations, start + 30 * DAY);
+            assert_eq!(Some(generations[index].kid.clone()), next);
+            assert_eq!(generations.len(), 3);
+
+            // Once the token lifetime has passed, the old key is retired.
+            assert!(signer
+                .rotate(&mut generations, start + 31 * DAY)
+                .await
+                .unwrap());
+            assert_eq!(generations.len(), 2);
+            assert!(generations.iter().all(|g| Some(g.kid.clone()) != active));
+        }
+
+        #[tokio::test]
+        async fn keeps_keys_across_restarts() {
+            let dir = tempfile::tempdir().unwrap();
+            let published = signer(dir.path()).published_keys().await.unwrap();
+            let (active, _) = signer(dir.path()).active_key().await.unwrap();
+            assert_eq!(active, published[0].0);
+
+            let message = b"token";
+            let signature = signer(dir.path())
+                .sign_with_key(active.as_deref(), SignAlgorithm::Es256, message)
+                .await
+                .unwrap();
+            let signature = p256::ecdsa::Signature::from_slice(&signature).unwrap();
+            p256::ecdsa::VerifyingKey::from(&published[0].1)
+                .verify(message, &signature)
+                .unwrap();
+        }
+
+        #[tokio::test]
+        async fn retries_a_rotation_that_was_not_persisted() {
+            let dir = tempfile::tempdir().unwrap();
+            let keys = dir.path().join("keys");
+            let rotating = signer(&keys);
+
+            // A file in place of the key directory makes the write fail.
+            std::fs::write(&keys, b"").unwrap();
+            assert!(rotating.active_key().await.is_err());
+            assert!(rotating.generations.lock().await.open.is_empty());
+
+            std::fs::remove_file(&keys).unwrap();
+            let (active, _) = rotating.active_key().await.unwrap();
+            assert_eq!(signer(&keys).active_key().await.unwrap().0, active);
+        }
+    }
+}
@@ -13,14 +13,11 @@ use base64::Engine;
 use const_format::concatcp;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
-use rsa::pkcs1v15::{Signature, SigningKey};
-use rsa::signature::Signer;
 use rsa::traits::PublicKeyParts;
-use rsa::RsaPrivateKey;
+use rsa::{RsaPrivateKey, RsaPublicKey};
 use serde::Deserialize;
 use serde_json::{json, Map, Value};
 use serde_variant::to_variant_name;
-use sha2::Sha384;
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -29,7 +26,7 @@ use crate::rvps::ReferenceValueResolver;
 use crate::token::{AttestationTokenBroker, DEFAULT_TOKEN_WORK_DIR};
 use crate::{TeeClaims, TeeEvidenceParsedClaim};
 
-use super::signer::SignKeyProvider;
+use super::signer::{SignAlgorithm, SignKeyProvider};
 #[cfg(feature = "fs")]
 use super::signer_transparency;
 use super::{COCO_AS_ISSUER_NAME, DEFAULT_TOKEN_DURATION};
@@ -125,7 +122,9 @@ impl SimpleAttestationTokenBroker {
         log::info!("Loading default AS policy \"simple_default_policy.rego\"");
 
         let signer: Arc<dyn SignKeyProvider<RsaPrivateKey>> = match config.signer {
-            Some(sc) => Arc::new(super::signer::FsSigner::<RsaPrivateKey>::from_config(sc)?),
+            Some(sc) => {
+                super::signer::from_config::<RsaPrivateKey>(sc, config.settings.duration_min * 60)?
+            }
             None => {
                 log::info!(
                     "No Token Signer key in config file, create an ephemeral key and without CA pubkey cert"
@@ -155,21 +154,22 @@ impl SimpleAttestationTokenBroker {
 }
 
 impl SimpleAttestationTokenBroker {
-    fn rs384_sign(&self, payload: &[u8]) -> Result<Vec<u8>> {
-        let signing_key = SigningKey::<Sha384>::new(self.signer.private_key().clone());
-        let sig: Signature = signing_key.sign(payload);
-        Ok(Box::<[u8]>::from(sig).to_vec())
+    async fn rs384_sign(&self, kid: Option<&str>, payload: &[u8]) -> Result<Vec<u8>> {
+        self.signer
+            .sign_with_key(kid, SignAlgorithm::Rs384, payload)
+            .await
     }
 
-    fn pubkey_jwks(&self) -> Result<String> {
-        let n = self.signer.private_key().n().to_bytes_be();
-        let e = self.signer.private_key().e().to_bytes_be();
+    fn pubkey_jwks(&self, kid: Option<String>, public_key: &RsaPublicKey) -> Result<String> {
+        let n = public_key.n().to_bytes_be();
+        let e = public_key.e().to_bytes_be();
 
         let mut jwk = Jwk {
             kty: "RSA".to_string(),
             alg: SIMPLE_TOKEN_ALG.to_string(),
             n: URL_SAFE_NO_PAD.encode(n),
             e: URL_SAFE_NO_PAD.encode(e),
+            kid,
             x5u: None,
             x5c: None,
         };
@@ -254,7 +254,7 @@ impl AttestationTokenBroker for SimpleAttestationTokenBroker {
             })
             .collect();
 
-        let token_claims = json!({
+        let mut token_claims = json!({
             "tee": to_variant_name(&all_tee_claims[0].tee)?,
             "evaluation-reports": policies,
             "tcb-status": tcb_claims,
@@ -263,12 +263,30 @@ impl AttestationTokenBroker for SimpleAttestationTokenBroker {
                 "runtime_data": all_tee_claims[0].runtime_data_claims,
             },
         });
+        if let Some(nonce) = super::eat_nonce(&all_tee_claims) {
+            token_claims["eat_nonce"] = nonce;
+        }
+        let evidence_digests = super::evidence_digests(&all_tee_claims);
+        if !evidence_digests.is_empty() {
+            token_claims["evidence-digests"] = json!(evidence_digests);
+        }
+        token_claims["rvps-namespace"] = json!(reference_value_resolver.namespace());
 
-        let header_value = json!({
+        let (kid, public_key) = self.signer.active_key().await?;
+        let mut header_value = json!({
             "typ": "JWT",
             "alg": SIMPLE_TOKEN_ALG,
-            "jwk": serde_json::from_str::<Value>(&self.pubkey_jwks()?)?["keys"][0].clone(),
         });
+        // Keys of a rotating signer are named by `kid` and looked up in the
+        // published JWKS rather than embedded.
+        match &kid {
+            Some(kid) => header_value["kid"] = json!(kid),
+            None => {
+                header_value["jwk"] =
+                    serde_json::from_str::<Value>(&self.pubkey_jwks(None, &public_key)?)?["keys"][0]
+                        .clone()
+            }
+        }
         let header_string = serde_json::to_string(&header_value)?;
         let header_b64 = URL_SAFE_NO_PAD.encode(header_string.as_bytes());
 
@@ -308,7 +326,9 @@ impl AttestationTokenBroker for SimpleAttestationTokenBroker {
         let claims_b64 = URL_SAFE_NO_PAD.encode(claims_string.as_bytes());
 
         let signature_payload = format!("{header_b64}.{claims_b64}");
-        let signature = self.rs384_sign(signature_payload.as_bytes())?;
+        let signature = self
+            .rs384_sign(kid.as_deref(), signature_payload.as_bytes())
+            .await?;
         let signature_b64 = URL_SAFE_NO_PAD.encode(signature);
 
         let token = format!("{signature_payload}.{signature_b64}");
@@ -351,6 +371,12 @@ impl AttestationTokenBroker for SimpleAttestationTokenBroker {
     fn signer_cert_url(&self) -> Option<&str> {
         self.signer.cert_url()
     }
+
+    /// Publish the configured signer's RSA public keys as a JWKS, with the
+    /// `kid` of each key of a rotating signer.
+    async fn configured_signer_jwks(&self) -> Result<Option<String>> {
+        super::signer::configured_jwks(self.signer.as_ref(), SIMPLE_TOKEN_ALG).await
+    }
 }
 
 #[derive(serde::Serialize, Debug, Clone)]
@@ -360,6 +386,8 @@ struct Jwk {
     n: String,
     e: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub x5u: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub x5c: Option<Vec<String>>,
@@ -481,6 +509,8 @@ mod tests {
                     runtime_data_claims: json!({"runtime_data": "111"}),
                     init_data_claims: json!({"initdata": "111"}),
                     additional_data: None,
+                    nonce: None,
+                    evidence_digest: None,
                 }],
                 vec!["default".into()],
                 crate::rvps::empty_test_resolver(),
@@ -531,6 +561,8 @@ mod tests {
                     runtime_data_claims: json!({"runtime_data": "111"}),
                     init_data_claims: json!({"initdata": "111"}),
                     additional_data: None,
+                    nonce: None,
+                    evidence_digest: None,
                 }],
                 vec!["default".into()],
                 crate::rvps::empty_test_resolver(),
@@ -539,6 +571,62 @@ mod tests {
             .unwrap();
     }
 
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_issue_simple_echoes_nonces_and_digests() {
+        let broker = SimpleAttestationTokenBroker::from_config(
+            Configuration::default(),
+            crate::config::DEFAULT_ARTIFACT_SERVER_ADDRESS,
+        )
+        .unwrap();
+        let tee_claims = |nonce: &str, digest: &str| TeeClaims {
+            tee: Tee::Sample,
+            tee_class: "cpu".to_string(),
+            claims: json!({"claim": "claim1"}),
+            runtime_data_claims: json!({"runtime_data": "111"}),
+            init_data_claims: json!({"initdata": "111"}),
+            additional_data: None,
+            nonce: Some(nonce.to_string()),
+            evidence_digest: Some(digest.to_string()),
+        };
+
+        let token = broker
+            .issue(
+                vec![
+                    tee_claims("nonce-1", "sha384:01"),
+                    tee_claims("nonce-1", "sha384:02"),
+                ],
+                vec!["default".into()],
+                crate::rvps::empty_test_resolver(),
+            )
+            .await
+            .unwrap();
+        let payload = token.split('.').nth(1).unwrap();
+        let claims: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap();
+        assert_eq!(claims["eat_nonce"], "nonce-1");
+        assert_eq!(
+            claims["evidence-digests"],
+            json!(["sha384:01", "sha384:02"])
+        );
+
+        let token = broker
+            .issue(
+                vec![
+                    tee_claims("nonce-1", "sha384:01"),
+                    tee_claims("nonce-2", "sha384:02"),
+                ],
+                vec!["default".into()],
+                crate::rvps::empty_test_resolver(),
+            )
+            .await
+            .unwrap();
+        let payload = token.split('.').nth(1).unwrap();
+        let claims: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap();
+        assert_eq!(claims["eat_nonce"], json!(["nonce-1", "nonce-2"]));
+    }
+
     #[test]
     fn flatten() {
         let json = json!({
@@ -686,8 +774,8 @@ frJCGYDUg+8c
 ";
 
     #[cfg(feature = "fs")]
-    #[test]
-    fn test_simple_signer_cert_chain_x5c() {
+    #[tokio::test]
+    async fn test_simple_signer_cert_chain_x5c() {
         // Exercise the `signer = Some(...)` branch of
         // `SimpleAttestationTokenBroker::new` with a PEM private key and a
         // 2-cert PEM chain. This drives the previously-untested cert-chain
@@ -704,6 +792,9 @@ frJCGYDUg+8c
                 key_path: key_file.path().to_string_lossy().to_string(),
                 cert_url: None,
                 cert_path: Some(chain_file.path().to_string_lossy().to_string()),
+                pkcs11: None,
+                kms: None,
+                rotation: None,
             }),
             ..Configuration::default()
         };
@@ -714,8 +805,15 @@ frJCGYDUg+8c
         )
         .expect("broker construction with signer + cert chain must succeed");
 
+        let (kid, public_key) = broker
+            .signer
+            .active_key()
+            .await
+            .expect("active_key must succeed");
         let jwks = serde_json::from_str::<serde_json::Value>(
-            &broker.pubkey_jwks().expect("pubkey_jwks must succeed"),
+            &broker
+                .pubkey_jwks(kid, &public_key)
+                .expect("pubkey_jwks must succeed"),
         )
         .expect("pubkey_jwks must return valid JSON");
 
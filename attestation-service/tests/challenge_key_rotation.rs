@@ -30,6 +30,7 @@ fn config(work_dir: &Path, challenge_key_path: &Path) -> Config {
         work_dir: work_dir.join("work"),
         rvps_config: RvpsConfig::BuiltIn(RvpsCrateConfig {
             storage: ReferenceValueStorageConfig::InMemory(in_memory::Config::default()),
+            ..Default::default()
         }),
         attestation_token_broker: AttestationTokenConfig::Simple(simple::Configuration {
             settings: simple::TokenBrokerSettings {
@@ -15,6 +15,7 @@ use attestation_service::{AttestationService, HashAlgorithm, Tee, VerificationRe
 use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
 use base64::Engine;
 use reference_value_provider_service::client;
+use reference_value_provider_service::namespace::NamespaceConfig;
 use reference_value_provider_service::server;
 use reference_value_provider_service::storage::{in_memory, ReferenceValueStorageConfig};
 use serde_json::{json, Value};
@@ -64,6 +65,7 @@ impl Drop for RvpsServerGuard {
 fn in_memory_rvps_config() -> RvpsCrateConfig {
     RvpsCrateConfig {
         storage: ReferenceValueStorageConfig::InMemory(in_memory::Config::default()),
+        ..Default::default()
     }
 }
 
@@ -81,6 +83,10 @@ fn as_config(work_dir: &Path, rvps_config: RvpsConfig) -> Config {
             ..Default::default()
         }),
         challenge_key_path: None,
+        capture: None,
+        evidence_digest: None,
+        revocation: Default::default(),
+        policy_namespaces: Default::default(),
         artifact_server_address: attestation_service::config::DEFAULT_ARTIFACT_SERVER_ADDRESS
             .to_string(),
     }
@@ -127,12 +133,16 @@ async fn set_policy(service: &mut AttestationService, id: &str, policy: &str) {
         .unwrap();
 }
 
-fn assert_token(token: &str, policy_id: &str) {
+fn claims(token: &str) -> Value {
     let segments: Vec<_> = token.split('.').collect();
     assert_eq!(segments.len(), 3);
 
     let claims = URL_SAFE_NO_PAD.decode(segments[1]).unwrap();
-    let claims: Value = serde_json::from_slice(&claims).unwrap();
+    serde_json::from_slice(&claims).unwrap()
+}
+
+fn assert_token(token: &str, policy_id: &str) {
+    let claims = claims(token);
     assert_eq!(claims["iss"], "policy-rvps-e2e");
     let tcb_status: Value = serde_json::from_str(claims["tcb-status"].as_str().unwrap()).unwrap();
     assert_eq!(tcb_status["sample.measure_register"], MEASUREMENT);
@@ -267,3 +277,67 @@ async fn remote_rvps_supports_new_and_old_protocols_end_to_end() {
         .unwrap();
     assert_token(&token, "legacy");
 }
+
+#[tokio::test]
+async fn builtin_rvps_isolates_namespaces_end_to_end() {
+    let temp_dir = TempDir::new().unwrap();
+    let rvps_config = RvpsCrateConfig {
+        namespaces: [(
+            "team-a".to_string(),
+            NamespaceConfig {
+                admin_tokens: vec!["team-a-token".to_string()],
+            },
+        )]
+        .into(),
+        ..in_memory_rvps_config()
+    };
+    let mut config = as_config(temp_dir.path(), RvpsConfig::BuiltIn(rvps_config));
+    config.policy_namespaces = [("query".to_string(), "team-a".to_string())].into();
+    let mut service = AttestationService::new(config).await.unwrap();
+
+    assert!(service
+        .register_reference_value_in("team-a", &sample_message("7"), None)
+        .await
+        .is_err());
+    assert!(service
+        .register_reference_value_in("team-a", &sample_message("7"), Some("guess"))
+        .await
+        .is_err());
+    service
+        .register_reference_value_in("team-a", &sample_message("7"), Some("team-a-token"))
+        .await
+        .unwrap();
+    service
+        .register_reference_value(&sample_message("8"))
+        .await
+        .unwrap();
+
+    set_policy(&mut service, "query", QUERY_POLICY).await;
+    set_policy(&mut service, "query-default", QUERY_POLICY).await;
+    let token = service
+        .evaluate(vec![sample_request()], vec!["query".to_string()])
+        .await
+        .unwrap();
+    assert_token(&token, "query");
+    assert_eq!(claims(&token)["rvps-namespace"], "team-a");
+
+    // The request can't pick the namespace of an unlisted policy.
+    let error = service
+        .evaluate_in_namespace(
+            vec![sample_request()],
+            vec!["query-default".to_string()],
+            None,
+            Some("team-a"),
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("namespace"), "{error}");
+
+    let error = service
+        .evaluate(vec![sample_request()], vec!["query-default".to_string()])
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Reject by policy query-default"), "{error}");
+}
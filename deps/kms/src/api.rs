@@ -24,6 +24,7 @@
 //! - `Encrypter`: KMS's encrypt API.
 //! - `Getter`: Vault's get secret API.
 //! - `Setter`: Vault's set secret API.
+//! - `Signer`: KMS's asymmetric sign and get public key APIs.
 //!
 //! The rationality to distinguish these four different traits:
 //! - `Decrypter` and `Getter` are used in-guest, while `Encrypter` and `Setter`
@@ -77,3 +78,15 @@ pub trait Getter: Send + Sync {
     /// `annotations`.
     async fn get_secret(&self, name: &str, annotations: &Annotations) -> Result<Vec<u8>>;
 }
+
+#[async_trait]
+pub trait Signer: Send + Sync {
+    /// Use the asymmetric key of `key_id` to sign the `digest` with the
+    /// `algorithm` (e.g. `RSA_PKCS1_SHA_256`, `ECDSA_SHA_256`) inside KMS,
+    /// and return the signature. ECDSA signatures are ASN.1 DER encoded.
+    async fn sign(&self, digest: &[u8], key_id: &str, algorithm: &str) -> Result<Vec<u8>>;
+
+    /// Get the public key of the asymmetric key `key_id` as a PEM encoded
+    /// SubjectPublicKeyInfo.
+    async fn get_public_key(&self, key_id: &str) -> Result<String>;
+}
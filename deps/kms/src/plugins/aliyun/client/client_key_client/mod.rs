@@ -322,6 +322,60 @@ impl ClientKeyClient {
         Ok(secret_data)
     }
 
+    pub async fn sign(&self, digest: &[u8], key_id: &str, algorithm: &str) -> Result<Vec<u8>> {
+        let sign_request = dkms_api::SignRequest {
+            key_id: key_id.into(),
+            algorithm: algorithm.into(),
+            message: digest.to_vec(),
+            message_type: "DIGEST".into(),
+        };
+        let mut body = Vec::new();
+        sign_request.encode(&mut body).map_err(|e| {
+            Error::AliyunKmsError(format!("encode sign request using protobuf failed: {e:?}"))
+        })?;
+        let headers = self.build_headers("Sign", &body).map_err(|e| {
+            Error::AliyunKmsError(format!("build sign request http header failed: {e:?}"))
+        })?;
+
+        let res = self.do_request(body, headers).await.map_err(|e| {
+            Error::AliyunKmsError(format!("do request to kms server failed: {e:?}"))
+        })?;
+
+        let sign_response = dkms_api::SignResponse::decode(&res[..]).map_err(|e| {
+            Error::AliyunKmsError(format!("decode sign response using protobuf failed: {e:?}"))
+        })?;
+        Ok(sign_response.signature)
+    }
+
+    pub async fn get_public_key(&self, key_id: &str) -> Result<String> {
+        let get_public_key_request = dkms_api::GetPublicKeyRequest {
+            key_id: key_id.into(),
+        };
+        let mut body = Vec::new();
+        get_public_key_request.encode(&mut body).map_err(|e| {
+            Error::AliyunKmsError(format!(
+                "encode get_public_key request using protobuf failed: {e:?}"
+            ))
+        })?;
+        let headers = self.build_headers("GetPublicKey", &body).map_err(|e| {
+            Error::AliyunKmsError(format!(
+                "build get_public_key request http header failed: {e:?}"
+            ))
+        })?;
+
+        let res = self.do_request(body, headers).await.map_err(|e| {
+            Error::AliyunKmsError(format!("do request to kms server failed: {e:?}"))
+        })?;
+
+        let get_public_key_response =
+            dkms_api::GetPublicKeyResponse::decode(&res[..]).map_err(|e| {
+                Error::AliyunKmsError(format!(
+                    "decode get_public_key response using protobuf failed: {e:?}"
+                ))
+            })?;
+        Ok(get_public_key_response.public_key)
+    }
+
     const API_VERSION: &'static str = "dkms-gcs-0.2";
     const SIGNATURE_METHOD: &'static str = "RSA_PKCS1_SHA_256";
     const CONTENT_TYPE: &'static str = "application/x-protobuf";
@@ -15,7 +15,7 @@ pub mod oidc_with_ram;
 mod sts_token_client;
 
 use crate::plugins::_IN_GUEST_DEFAULT_KEY_PATH;
-use crate::{Annotations, Decrypter, Encrypter, Getter, ProviderSettings};
+use crate::{Annotations, Decrypter, Encrypter, Getter, ProviderSettings, Signer};
 use crate::{Error, Result};
 
 use client_key_client::ClientKeyClient;
@@ -289,6 +289,45 @@ impl Decrypter for AliyunKmsClient {
     }
 }
 
+#[async_trait]
+impl Signer for AliyunKmsClient {
+    async fn sign(&self, digest: &[u8], key_id: &str, algorithm: &str) -> Result<Vec<u8>> {
+        match &self {
+            AliyunKmsClient::ClientKey { ref inner } => inner.sign(digest, key_id, algorithm).await,
+            AliyunKmsClient::EcsRamRole { .. } => Err(Error::AliyunKmsError(
+                "Signer does not support accessing through Aliyun EcsRamRole".to_string(),
+            )),
+            AliyunKmsClient::AccessKey { .. } => Err(Error::AliyunKmsError(
+                "Signer does not support accessing through Aliyun AccessKey".to_string(),
+            )),
+            AliyunKmsClient::StsToken { .. } => Err(Error::AliyunKmsError(
+                "Signer does not support accessing through Aliyun StsToken".to_string(),
+            )),
+            AliyunKmsClient::OidcRam { .. } => Err(Error::AliyunKmsError(
+                "Signer does not support accessing through Aliyun OidcRam".to_string(),
+            )),
+        }
+    }
+
+    async fn get_public_key(&self, key_id: &str) -> Result<String> {
+        match &self {
+            AliyunKmsClient::ClientKey { ref inner } => inner.get_public_key(key_id).await,
+            AliyunKmsClient::EcsRamRole { .. } => Err(Error::AliyunKmsError(
+                "Signer does not support accessing through Aliyun EcsRamRole".to_string(),
+            )),
+            AliyunKmsClient::AccessKey { .. } => Err(Error::AliyunKmsError(
+                "Signer does not support accessing through Aliyun AccessKey".to_string(),
+            )),
+            AliyunKmsClient::StsToken { .. } => Err(Error::AliyunKmsError(
+                "Signer does not support accessing through Aliyun StsToken".to_string(),
+            )),
+            AliyunKmsClient::OidcRam { .. } => Err(Error::AliyunKmsError(
+                "Signer does not support accessing through Aliyun OidcRam".to_string(),
+            )),
+        }
+    }
+}
+
 #[async_trait]
 impl Getter for AliyunKmsClient {
     async fn get_secret(&self, name: &str, annotations: &Annotations) -> Result<Vec<u8>> {
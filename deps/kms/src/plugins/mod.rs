@@ -7,7 +7,7 @@ use std::str::FromStr;
 
 use strum::{AsRefStr, EnumString};
 
-use super::{Decrypter, Error, Getter, ProviderSettings, Result};
+use super::{Decrypter, Error, Getter, ProviderSettings, Result, Signer};
 
 const _IN_GUEST_DEFAULT_KEY_PATH: &str = "/run/confidential-containers/cdh/kms-credential";
 
@@ -52,3 +52,23 @@ pub async fn new_getter(
         ) as Box<dyn Getter>),
     }
 }
+
+#[derive(AsRefStr, EnumString)]
+pub enum SignerProvider {
+    #[strum(ascii_case_insensitive)]
+    Aliyun,
+}
+
+/// Create a new [`Signer`] by given provider name and [`ProviderSettings`]
+pub async fn new_signer(
+    provider_name: &str,
+    provider_settings: ProviderSettings,
+) -> Result<Box<dyn Signer>> {
+    let provider = SignerProvider::from_str(provider_name)
+        .map_err(|_| Error::UnsupportedProvider(provider_name.to_string()))?;
+    match provider {
+        SignerProvider::Aliyun => Ok(Box::new(
+            aliyun::AliyunKmsClient::from_provider_settings(&provider_settings).await?,
+        ) as Box<dyn Signer>),
+    }
+}
@@ -137,6 +137,7 @@ impl ApiServer {
                         web::resource([kbs_path!("{base_path}{additional_path:.*}")])
                             .route(web::get().to(api))
                             .route(web::post().to(api))
+                            .route(web::put().to(api))
                             .route(web::delete().to(api)),
                     )
             }
@@ -328,6 +329,19 @@ pub(crate) async fn api(
                 })?;
 
             let body = body.to_vec();
+            if plugin
+                .public(&body, query, additional_path, request.method())
+                .await
+                .map_err(|e| Error::PluginInternalError { source: e })?
+            {
+                let response = plugin
+                    .handle(&body, query, additional_path, request.method())
+                    .await
+                    .map_err(|e| Error::PluginInternalError { source: e })?;
+
+                return Ok(HttpResponse::Ok().content_type("text/xml").body(response));
+            }
+
             if plugin
                 .validate_auth(&body, query, additional_path, request.method())
                 .await
@@ -358,7 +372,7 @@ pub(crate) async fn api(
                 }
 
                 let response = plugin
-                    .handle(&body, query, additional_path, request.method())
+                    .handle_attested(&body, query, additional_path, request.method(), &claims)
                     .await
                     .map_err(|e| Error::PluginInternalError { source: e })?;
                 if plugin
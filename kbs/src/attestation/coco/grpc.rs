@@ -185,6 +185,8 @@ impl Attest for GrpcClientPool {
         let attestation_request = tonic::Request::new(AttestationRequest {
             verification_requests,
             policy_ids: vec!["default".to_string()],
+            token_format: String::new(),
+            namespace: String::new(),
         });
 
         let mut client = { self.pool.lock().await.get().await? };
@@ -240,6 +242,7 @@ impl Attest for GrpcClientPool {
     async fn register_reference_value(&self, message: &str) -> anyhow::Result<()> {
         let req = tonic::Request::new(ReferenceValueRegisterRequest {
             message: message.to_string(),
+            namespace: String::new(),
         });
 
         let mut client = { self.pool.lock().await.get().await? };
@@ -256,6 +259,7 @@ impl Attest for GrpcClientPool {
     async fn set_reference_value_list(&self, payload: &str) -> anyhow::Result<()> {
         let req = tonic::Request::new(ReferenceValueListRequest {
             payload: payload.to_string(),
+            namespace: String::new(),
         });
 
         let mut client = { self.pool.lock().await.get().await? };
@@ -272,6 +276,7 @@ impl Attest for GrpcClientPool {
     async fn query_reference_values(&self) -> anyhow::Result<HashMap<String, serde_json::Value>> {
         let req = tonic::Request::new(ReferenceValueQueryRequest {
             reference_value_id: String::new(),
+            namespace: String::new(),
         });
 
         let mut client = { self.pool.lock().await.get().await? };
@@ -291,6 +296,7 @@ impl Attest for GrpcClientPool {
     async fn delete_reference_value(&self, name: &str) -> anyhow::Result<bool> {
         let req = tonic::Request::new(ReferenceValueDeleteRequest {
             name: name.to_string(),
+            namespace: String::new(),
         });
 
         let mut client = { self.pool.lock().await.get().await? };
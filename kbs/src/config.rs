@@ -286,7 +286,9 @@ mod tests {
             trusted_certs_paths: vec!["/etc/ca".into(), "/etc/ca2".into()],
             insecure_key: false,
             trusted_jwk_sets: vec![],
+            jwks_refresh_interval_secs: 60,
             extra_teekey_paths: vec![],
+            revocation: None,
         },
         #[cfg(feature = "coco-as-grpc")]
         attestation_service: crate::attestation::config::AttestationConfig {
@@ -327,7 +329,9 @@ mod tests {
             trusted_certs_paths: vec![],
             insecure_key: false,
             trusted_jwk_sets: vec![],
+            jwks_refresh_interval_secs: 60,
             extra_teekey_paths: vec![],
+            revocation: None,
         },
         #[cfg(feature = "coco-as-builtin")]
         attestation_service: crate::attestation::config::AttestationConfig {
@@ -402,7 +406,9 @@ mod tests {
             trusted_certs_paths: vec![],
             insecure_key: false,
             trusted_jwk_sets: vec![],
+            jwks_refresh_interval_secs: 60,
             extra_teekey_paths: vec![],
+            revocation: None,
         },
         #[cfg(feature = "coco-as-builtin")]
         attestation_service: crate::attestation::config::AttestationConfig {
@@ -414,6 +420,7 @@ mod tests {
                             storage: ReferenceValueStorageConfig::LocalFs(local_fs::Config{
                                 file_path: "/opt/confidential-containers/attestation-service/reference_values".into(),
                             }),
+                            ..Default::default()
                         }),
                         attestation_token_broker: AttestationTokenConfig::Simple(simple::Configuration{
                             settings: simple::TokenBrokerSettings {
@@ -473,7 +480,9 @@ mod tests {
             trusted_certs_paths: vec![],
             insecure_key: false,
             trusted_jwk_sets: vec![],
+            jwks_refresh_interval_secs: 60,
             extra_teekey_paths: vec![],
+            revocation: None,
         },
         #[cfg(feature = "coco-as-builtin")]
         attestation_service: crate::attestation::config::AttestationConfig {
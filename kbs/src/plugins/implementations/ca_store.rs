@@ -0,0 +1,50 @@
+// Copyright (c) 2025 by Alibaba.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Files the CA plugins keep in their working directory: the issuance log,
+//! one JSON line per issued credential, and private CA keys.
+
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, io::Write, path::Path};
+
+/// Append `entry` to the issuance log at `path`. Callers serialize the
+/// appends of one log.
+pub(crate) fn append_issued<T: Serialize>(path: &Path, entry: &T) -> Result<()> {
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(&line))
+        .with_context(|| format!("Write issuance log {}", path.display()))
+}
+
+/// The entries of the issuance log at `path`, empty if there is none yet.
+pub(crate) fn read_issued<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Read {}", path.display())),
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).context("Parse issuance log"))
+        .collect()
+}
+
+/// Create `path` readable by the owner only and write `content` to it.
+/// Fails if `path` exists.
+pub(crate) fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(content)
+}
@@ -0,0 +1,109 @@
+// Copyright (c) 2025 by Alibaba.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Templates rendering attestation token claims into the identities the CA
+//! plugins put in the credentials they issue, e.g. certificate SANs or SSH
+//! principals.
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+/// Render `template`, replacing every `{<JSON pointer>}` with the string,
+/// number or boolean claim it points to, e.g.
+/// `spiffe://example.org/{/submods/cpu0/ear.veraison.annotated-evidence/init_data}`.
+pub(crate) fn render_template(template: &str, claims: &Value) -> Result<String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .with_context(|| format!("Unclosed `{{` in template `{template}`"))?;
+        let pointer = &rest[start + 1..start + end];
+        let value = match claims.pointer(pointer) {
+            Some(Value::String(value)) => value.clone(),
+            Some(Value::Number(value)) => value.to_string(),
+            Some(Value::Bool(value)) => value.to_string(),
+            Some(_) => bail!("Claim `{pointer}` is not a string, number or boolean"),
+            None => bail!("Claim `{pointer}` not found in the attestation token"),
+        };
+        rendered.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+/// Check that a host name rendered from the claims names a single host.
+/// Claims may come from guest-supplied init-data, and a wildcard or a list
+/// would make OpenSSH or TLS clients accept the credential for other hosts.
+pub(crate) fn check_host_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        bail!("Empty host name rendered from the attestation token");
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| matches!(c, '*' | '?' | ',') || c.is_whitespace() || c.is_control())
+    {
+        bail!(
+            "Host name `{}` rendered from the attestation token contains {c:?}",
+            name.escape_debug()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn claims() -> Value {
+        json!({
+            "submods": {
+                "cpu0": {
+                    "ear.veraison.annotated-evidence": {
+                        "init_data": "5b1bf8a2",
+                        "sample": { "svn": 1 },
+                    }
+                }
+            }
+        })
+    }
+
+    #[rstest]
+    #[case(
+        "spiffe://example.org/tee/{/submods/cpu0/ear.veraison.annotated-evidence/init_data}",
+        Some("spiffe://example.org/tee/5b1bf8a2")
+    )]
+    #[case(
+        "svn-{/submods/cpu0/ear.veraison.annotated-evidence/sample/svn}.workload",
+        Some("svn-1.workload")
+    )]
+    #[case("no-placeholder", Some("no-placeholder"))]
+    #[case("{/submods/cpu0/missing}", None)]
+    #[case("{/submods/cpu0}", None)]
+    #[case("{/submods", None)]
+    fn test_render_template(#[case] template: &str, #[case] expected: Option<&str>) {
+        let rendered = render_template(template, &claims());
+        assert_eq!(rendered.ok().as_deref(), expected);
+    }
+
+    #[rstest]
+    #[case("guest-1.example.org", true)]
+    #[case("10.0.0.1", true)]
+    #[case("", false)]
+    #[case("*", false)]
+    #[case("*.corp", false)]
+    #[case("guest-?", false)]
+    #[case("a,b", false)]
+    #[case("guest 1", false)]
+    #[case("guest\n", false)]
+    #[case("guest\u{7f}", false)]
+    fn test_check_host_name(#[case] name: &str, #[case] valid: bool) {
+        assert_eq!(check_host_name(name).is_ok(), valid);
+    }
+}
@@ -0,0 +1,498 @@
+// Copyright (c) 2025 by Alibaba.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Claims-bound key derivation plugin.
+//!
+//! This plugin derives per-workload keys on demand, so that they never need
+//! to be stored. A key is derived with HKDF-Expand (RFC 5869, SHA-256) from a
+//! root secret, held by a resource storage backend or a PKCS#11 token, and a
+//! context built from selected attestation token claims (e.g. the
+//! measurement and the init-data hash), the requested label and the key
+//! version. The same workload thus always gets the same key, and the key is
+//! returned wrapped to the TEE key.
+//!
+//! More information can be found in the
+//! [plugin](#kbs/docs/plugins/key_derivation.md) documentation.
+
+use actix_web::http::Method;
+use anyhow::{anyhow, bail, Context, Result};
+use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+use serde::Deserialize;
+use serde_json::Value;
+use zeroize::Zeroizing;
+
+#[cfg(feature = "pkcs11")]
+use std::path::PathBuf;
+
+use crate::plugins::{
+    plugin_manager::ClientPlugin,
+    resource::{RepositoryConfig, ResourceDesc, ResourceStorage},
+};
+
+/// Domain separation of the derivation context.
+const CONTEXT_PREFIX: &[u8] = b"trustee-kbs-key-derivation";
+/// Placeholder of the key version in the root secret location.
+const VERSION_PLACEHOLDER: &str = "{version}";
+/// SHA-256 output length, the length of one HKDF-Expand block.
+const HASH_LEN: usize = 32;
+/// Minimum length of a root secret held by a storage backend.
+const MIN_ROOT_SECRET_LEN: usize = HASH_LEN;
+/// Default length of a derived key.
+const DEFAULT_KEY_LENGTH: usize = 32;
+/// Maximum length of a derived key.
+const MAX_KEY_LENGTH: usize = 64;
+/// Maximum length of a requested label.
+const MAX_LABEL_LENGTH: usize = 128;
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct KeyDerivationPluginConfig {
+    /// Where the root secret is held.
+    root_secret: RootSecretConfig,
+
+    /// JSON pointers of the token claims that bind the derived keys, e.g.
+    /// the measurement and the init-data hash. Their order is part of the
+    /// derivation context.
+    claim_selectors: Vec<String>,
+
+    /// Current key version, used when a request does not ask for one.
+    #[serde(default = "default_key_version")]
+    key_version: u32,
+
+    /// Oldest key version that can still be derived.
+    #[serde(default = "default_key_version")]
+    min_key_version: u32,
+}
+
+fn default_key_version() -> u32 {
+    1
+}
+
+/// Where the root secret is held. Its location may contain a `{version}`
+/// placeholder, replaced by the key version, to rotate the root secret
+/// together with the key version.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum RootSecretConfig {
+    /// A resource of a storage backend, e.g. `default/kdf/root`. It must
+    /// hold at least 32 random bytes.
+    #[serde(alias = "resource")]
+    Resource {
+        #[serde(default)]
+        storage: RepositoryConfig,
+        resource: String,
+    },
+
+    /// An HMAC secret key held by a PKCS#11 token, found by its `CKA_LABEL`.
+    /// The key never leaves the token.
+    #[cfg(feature = "pkcs11")]
+    #[serde(alias = "pkcs11")]
+    Pkcs11 {
+        module: PathBuf,
+        #[serde(default)]
+        slot_index: u8,
+        pin: String,
+        key_label: String,
+    },
+}
+
+/// Query parameters of a `key` request.
+#[derive(Debug, Deserialize)]
+struct KeyParams {
+    /// Label of the key, chosen by the workload.
+    label: String,
+    /// Key version. Default: the current key version.
+    version: Option<u32>,
+    /// Key length in bytes. Default: 32.
+    length: Option<usize>,
+}
+
+enum RootSecret {
+    Resource {
+        storage: ResourceStorage,
+        resource: String,
+    },
+    #[cfg(feature = "pkcs11")]
+    Pkcs11(pkcs11_secret::Pkcs11RootSecret),
+}
+
+impl RootSecret {
+    /// HKDF-Expand `info` to `length` bytes with the root secret of
+    /// `version` as the pseudorandom key.
+    async fn expand(&self, version: u32, info: &[u8], length: usize) -> Result<Vec<u8>> {
+        match self {
+            RootSecret::Resource { storage, resource } => {
+                let desc = ResourceDesc::try_from(&*versioned(resource, version))
+                    .context("Illegal root secret resource")?;
+                let secret = Zeroizing::new(
+                    storage
+                        .get_secret_resource(desc)
+                        .await
+                        .context("Read root secret")?,
+                );
+                if secret.len() < MIN_ROOT_SECRET_LEN {
+                    bail!("Root secret must be at least {MIN_ROOT_SECRET_LEN} bytes");
+                }
+                let key = PKey::hmac(&secret)?;
+                hkdf_expand(info, length, |data| {
+                    Ok(Signer::new(MessageDigest::sha256(), &key)?.sign_oneshot_to_vec(data)?)
+                })
+            }
+            #[cfg(feature = "pkcs11")]
+            RootSecret::Pkcs11(secret) => secret.expand(version, info, length).await,
+        }
+    }
+}
+
+pub struct KeyDerivationPlugin {
+    root_secret: RootSecret,
+    claim_selectors: Vec<String>,
+    key_version: u32,
+    min_key_version: u32,
+}
+
+impl KeyDerivationPlugin {
+    pub async fn new(config: KeyDerivationPluginConfig) -> Result<Self> {
+        if config.claim_selectors.is_empty() {
+            bail!("At least one claim selector is required");
+        }
+        if config.min_key_version > config.key_version {
+            bail!("`min_key_version` is greater than `key_version`");
+        }
+
+        let root_secret = match config.root_secret {
+            RootSecretConfig::Resource { storage, resource } => RootSecret::Resource {
+                storage: ResourceStorage::new(storage).await?,
+                resource,
+            },
+            #[cfg(feature = "pkcs11")]
+            RootSecretConfig::Pkcs11 {
+                module,
+                slot_index,
+                pin,
+                key_label,
+            } => RootSecret::Pkcs11(pkcs11_secret::Pkcs11RootSecret::new(
+                module, slot_index, &pin, key_label,
+            )?),
+        };
+
+        Ok(Self {
+            root_secret,
+            claim_selectors: config.claim_selectors,
+            key_version: config.key_version,
+            min_key_version: config.min_key_version,
+        })
+    }
+
+    async fn derive(&self, params: &KeyParams, claims: &Value) -> Result<Vec<u8>> {
+        let version = params.version.unwrap_or(self.key_version);
+        if !(self.min_key_version..=self.key_version).contains(&version) {
+            bail!(
+                "Key version {version} not in [{}, {}]",
+                self.min_key_version,
+                self.key_version
+            );
+        }
+        let length = params.length.unwrap_or(DEFAULT_KEY_LENGTH);
+        if length == 0 || length > MAX_KEY_LENGTH {
+            bail!("Key length must be between 1 and {MAX_KEY_LENGTH} bytes");
+        }
+        if params.label.is_empty()
+            || params.label.len() > MAX_LABEL_LENGTH
+            || !params
+                .label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            bail!("Illegal key label `{}`", params.label);
+        }
+
+        let info = self.context(version, &params.label, length, claims)?;
+        let key = self.root_secret.expand(version, &info, length).await?;
+        log::info!(
+            "Derived key `{}` version {version} for attested workload",
+            params.label
+        );
+        Ok(key)
+    }
+
+    /// The derivation context: every field is length-prefixed, so that no
+    /// two different inputs encode to the same context.
+    fn context(&self, version: u32, label: &str, length: usize, claims: &Value) -> Result<Vec<u8>> {
+        let mut info = Vec::new();
+        put_field(&mut info, CONTEXT_PREFIX);
+        info.extend_from_slice(&version.to_be_bytes());
+        info.extend_from_slice(&(length as u32).to_be_bytes());
+        put_field(&mut info, label.as_bytes());
+        for selector in &self.claim_selectors {
+            let claim = claims
+                .pointer(selector)
+                .ok_or_else(|| anyhow!("Claim `{selector}` not found in token"))?;
+            let value = match claim {
+                Value::String(s) => s.as_bytes().to_vec(),
+                other => serde_json::to_vec(other)?,
+            };
+            put_field(&mut info, selector.as_bytes());
+            put_field(&mut info, &value);
+        }
+
+        Ok(info)
+    }
+}
+
+#[async_trait::async_trait]
+impl ClientPlugin for KeyDerivationPlugin {
+    async fn handle(
+        &self,
+        _body: &[u8],
+        _query: &str,
+        path: &str,
+        method: &Method,
+    ) -> Result<Vec<u8>> {
+        bail!("{method} {path} requires an attestation token")
+    }
+
+    async fn handle_attested(
+        &self,
+        _body: &[u8],
+        query: &str,
+        path: &str,
+        method: &Method,
+        claims: &Value,
+    ) -> Result<Vec<u8>> {
+        match (method.as_str(), path) {
+            ("GET", "/key") => {
+                let params: KeyParams = serde_qs::from_str(query)
+                    .map_err(|e| anyhow!("Parse key request params failed: {e}"))?;
+                self.derive(&params, claims).await
+            }
+            _ => bail!("{method} {path} not supported"),
+        }
+    }
+
+    async fn validate_auth(
+        &self,
+        _body: &[u8],
+        _query: &str,
+        _path: &str,
+        _method: &Method,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Derived keys are always wrapped to the TEE key.
+    async fn encrypted(
+        &self,
+        _body: &[u8],
+        _query: &str,
+        _path: &str,
+        _method: &Method,
+    ) -> Result<bool> {
+        Ok(true)
+    }
+}
+
+/// HKDF-Expand (RFC 5869) of `info` to `length` bytes, `hmac` being HMAC
+/// keyed with the pseudorandom key.
+fn hkdf_expand(
+    info: &[u8],
+    length: usize,
+    mut hmac: impl FnMut(&[u8]) -> Result<Vec<u8>>,
+) -> Result<Vec<u8>> {
+    if length > 255 * HASH_LEN {
+        bail!("HKDF output too long");
+    }
+
+    let mut okm = Vec::with_capacity(length + HASH_LEN);
+    let mut block = Zeroizing::new(Vec::new());
+    let mut counter = 1u8;
+    while okm.len() < length {
+        let mut input = Zeroizing::new(block.to_vec());
+        input.extend_from_slice(info);
+        input.push(counter);
+        block = Zeroizing::new(hmac(&input)?);
+        okm.extend_from_slice(&block);
+        counter = counter.wrapping_add(1);
+    }
+    okm.truncate(length);
+
+    Ok(okm)
+}
+
+fn versioned(location: &str, version: u32) -> String {
+    location.replace(VERSION_PLACEHOLDER, &version.to_string())
+}
+
+fn put_field(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+#[cfg(feature = "pkcs11")]
+mod pkcs11_secret {
+    use anyhow::{Context, Result};
+    use cryptoki::{
+        context::{CInitializeArgs, Pkcs11},
+        mechanism::Mechanism,
+        object::{Attribute, ObjectClass},
+        session::{Session, UserType},
+        types::AuthPin,
+    };
+    use std::path::PathBuf;
+    use tokio::sync::Mutex;
+
+    use super::{hkdf_expand, versioned};
+
+    /// A root secret held by a PKCS#11 token as an HMAC key, used with
+    /// `CKM_SHA256_HMAC`.
+    pub(super) struct Pkcs11RootSecret {
+        session: Mutex<Session>,
+        key_label: String,
+    }
+
+    impl Pkcs11RootSecret {
+        pub(super) fn new(
+            module: PathBuf,
+            slot_index: u8,
+            pin: &str,
+            key_label: String,
+        ) -> Result<Self> {
+            let pkcs11 = Pkcs11::new(module).context("unable to open pkcs11 module")?;
+            pkcs11.initialize(CInitializeArgs::OsThreads)?;
+
+            let slots = pkcs11.get_slots_with_token()?;
+            let slot = *slots
+                .get(usize::from(slot_index))
+                .context("Slot index out of range")?;
+            let session = pkcs11.open_ro_session(slot)?;
+            session.login(UserType::User, Some(&AuthPin::new(pin.to_string())))?;
+
+            Ok(Self {
+                session: Mutex::new(session),
+                key_label,
+            })
+        }
+
+        pub(super) async fn expand(
+            &self,
+            version: u32,
+            info: &[u8],
+            length: usize,
+        ) -> Result<Vec<u8>> {
+            let label = versioned(&self.key_label, version);
+            let session = self.session.lock().await;
+            let key = *session
+                .find_objects(&[
+                    Attribute::Class(ObjectClass::SECRET_KEY),
+                    Attribute::Label(label.as_bytes().to_vec()),
+                ])?
+                .first()
+                .with_context(|| format!("No secret key labelled `{label}`"))?;
+
+            hkdf_expand(info, length, |data| {
+                Ok(session.sign(&Mechanism::Sha256Hmac, key, data)?)
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::resource::local_fs::LocalFsRepoDesc;
+    use serde_json::json;
+
+    fn claims(measurement: &str) -> Value {
+        json!({
+            "submods": { "cpu0": { "ear.veraison.annotated-evidence": {
+                "sample": { "launch_digest": measurement },
+                "init_data": "8e2a",
+            }}}
+        })
+    }
+
+    fn hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_hkdf_expand() {
+        // RFC 5869, test case 1.
+        let prk = hex("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5");
+        let info = hex("f0f1f2f3f4f5f6f7f8f9");
+        let key = PKey::hmac(&prk).unwrap();
+        let okm = hkdf_expand(&info, 42, |data| {
+            Ok(Signer::new(MessageDigest::sha256(), &key)?.sign_oneshot_to_vec(data)?)
+        })
+        .unwrap();
+        assert_eq!(
+            okm,
+            hex("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_derive_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = RepositoryConfig::LocalFs(LocalFsRepoDesc {
+            dir_path: dir.path().to_string_lossy().into_owned(),
+        });
+        ResourceStorage::new(storage.clone())
+            .await
+            .unwrap()
+            .set_secret_resource(
+                ResourceDesc::try_from("default/kdf/root-v1").unwrap(),
+                &[7u8; 32],
+            )
+            .await
+            .unwrap();
+        let plugin = KeyDerivationPlugin::new(KeyDerivationPluginConfig {
+            root_secret: RootSecretConfig::Resource {
+                storage,
+                resource: "default/kdf/root-v{version}".into(),
+            },
+            claim_selectors: vec![
+                "/submods/cpu0/ear.veraison.annotated-evidence/sample/launch_digest".into(),
+                "/submods/cpu0/ear.veraison.annotated-evidence/init_data".into(),
+            ],
+            key_version: 1,
+            min_key_version: 1,
+        })
+        .await
+        .unwrap();
+
+        let derive = |query: &'static str, claims: Value| {
+            let plugin = &plugin;
+            async move {
+                plugin
+                    .handle_attested(&[], query, "/key", &Method::GET, &claims)
+                    .await
+            }
+        };
+
+        let key = derive("label=db", claims("aa")).await.unwrap();
+        assert_eq!(key.len(), DEFAULT_KEY_LENGTH);
+        assert_eq!(key, derive("label=db", claims("aa")).await.unwrap());
+        assert_ne!(key, derive("label=db", claims("bb")).await.unwrap());
+        assert_ne!(key, derive("label=disk", claims("aa")).await.unwrap());
+        assert_eq!(
+            derive("label=db&length=64", claims("aa"))
+                .await
+                .unwrap()
+                .len(),
+            64
+        );
+
+        // Unknown version, missing claim, illegal label, no token
+        assert!(derive("label=db&version=2", claims("aa")).await.is_err());
+        assert!(derive("label=db", json!({})).await.is_err());
+        assert!(derive("label=../db", claims("aa")).await.is_err());
+        assert!(plugin
+            .handle(&[], "label=db", "/key", &Method::GET)
+            .await
+            .is_err());
+    }
+}
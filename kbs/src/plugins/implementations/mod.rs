@@ -2,22 +2,42 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
+#[cfg(any(feature = "x509-ca-plugin", feature = "ssh-ca-plugin"))]
+pub mod ca_store;
+#[cfg(any(feature = "x509-ca-plugin", feature = "ssh-ca-plugin"))]
+pub mod claim_template;
+#[cfg(feature = "key-derivation-plugin")]
+pub mod key_derivation;
 #[cfg(feature = "nebula-ca-plugin")]
 pub mod nebula_ca;
 #[cfg(feature = "pkcs11")]
 pub mod pkcs11;
+#[cfg(feature = "sealing-plugin")]
+pub mod sealing;
+#[cfg(feature = "ssh-ca-plugin")]
+pub mod ssh_ca;
 #[cfg(feature = "tpm-pca")]
 pub mod tpm_pca;
+#[cfg(feature = "x509-ca-plugin")]
+pub mod x509_ca;
 
 pub mod resource;
 pub mod sample;
 
+#[cfg(feature = "key-derivation-plugin")]
+pub use key_derivation::{KeyDerivationPlugin, KeyDerivationPluginConfig};
 #[cfg(feature = "nebula-ca-plugin")]
 pub use nebula_ca::{NebulaCaPlugin, NebulaCaPluginConfig};
 #[cfg(feature = "pkcs11")]
 pub use pkcs11::{Pkcs11Backend, Pkcs11Config};
+#[cfg(feature = "sealing-plugin")]
+pub use sealing::{SealingPlugin, SealingPluginConfig};
+#[cfg(feature = "ssh-ca-plugin")]
+pub use ssh_ca::{SshCaPlugin, SshCaPluginConfig};
 #[cfg(feature = "tpm-pca")]
 pub use tpm_pca::{TpmCaConfig, TpmCaPlugin};
+#[cfg(feature = "x509-ca-plugin")]
+pub use x509_ca::{X509CaPlugin, X509CaPluginConfig};
 
 pub use resource::{RepositoryConfig, ResourceStorage};
 pub use sample::{Sample, SampleConfig};
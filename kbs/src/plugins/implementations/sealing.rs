@@ -0,0 +1,409 @@
+// Copyright (c) 2025 by Alibaba.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Sealing plugin.
+//!
+//! This plugin lets attested guests persist state they generate, like a TLS
+//! key or a database master key, so that only the same measured workload can
+//! retrieve it later. A blob is sealed to a binding, the values of the
+//! configured token claims of the guest that writes it, and can only be read
+//! back with a token carrying the same values. Blob size and per-identity
+//! quotas bound what a guest can store.
+//!
+//! More information can be found in the
+//! [plugin](#kbs/docs/plugins/sealing.md) documentation.
+
+use actix_web::http::Method;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::Mutex;
+
+use crate::plugins::{
+    plugin_manager::ClientPlugin,
+    resource::{local_fs::LocalFsRepoDesc, RepositoryConfig, ResourceDesc, ResourceStorage},
+};
+
+/// Default directory of the sealed blobs. It must not be shared with the
+/// resource plugin, which would serve the blobs to any guest passing the
+/// resource policy.
+const DEFAULT_STORAGE_DIR: &str = "/opt/confidential-containers/kbs/sealed";
+/// Default repository name of the sealed blobs in the storage backend.
+const DEFAULT_REPOSITORY: &str = "sealed";
+/// Default maximum size of a blob (64 KiB).
+const DEFAULT_MAX_BLOB_SIZE: usize = 64 * 1024;
+/// Default maximum number of blobs of an identity.
+const DEFAULT_MAX_BLOBS_PER_IDENTITY: usize = 16;
+/// Default maximum total size of the blobs of an identity (1 MiB).
+const DEFAULT_MAX_BYTES_PER_IDENTITY: usize = 1024 * 1024;
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SealingPluginConfig {
+    /// Storage backend of the sealed blobs, configured like for the
+    /// resource plugin.
+    #[serde(default = "default_storage")]
+    storage: RepositoryConfig,
+
+    /// Repository name of the sealed blobs in the storage backend.
+    #[serde(default = "default_repository")]
+    repository: String,
+
+    /// JSON pointers of the token claims a blob is bound to, e.g. the
+    /// measurement and the init-data hash.
+    binding_claims: Vec<String>,
+
+    /// Maximum size of a blob in bytes.
+    #[serde(default = "default_max_blob_size")]
+    max_blob_size: usize,
+
+    /// Maximum number of blobs of an identity.
+    #[serde(default = "default_max_blobs_per_identity")]
+    max_blobs_per_identity: usize,
+
+    /// Maximum total size of the blobs of an identity in bytes.
+    #[serde(default = "default_max_bytes_per_identity")]
+    max_bytes_per_identity: usize,
+}
+
+fn default_storage() -> RepositoryConfig {
+    RepositoryConfig::LocalFs(LocalFsRepoDesc {
+        dir_path: DEFAULT_STORAGE_DIR.into(),
+    })
+}
+
+fn default_repository() -> String {
+    DEFAULT_REPOSITORY.into()
+}
+
+fn default_max_blob_size() -> usize {
+    DEFAULT_MAX_BLOB_SIZE
+}
+
+fn default_max_blobs_per_identity() -> usize {
+    DEFAULT_MAX_BLOBS_PER_IDENTITY
+}
+
+fn default_max_bytes_per_identity() -> usize {
+    DEFAULT_MAX_BYTES_PER_IDENTITY
+}
+
+/// A sealed blob as kept in the storage backend.
+#[derive(Debug, Serialize, Deserialize)]
+struct SealedBlob {
+    /// The claim values the blob is bound to.
+    binding: BTreeMap<String, Value>,
+    /// Base64 encoded blob.
+    data: String,
+    sealed_at: u64,
+}
+
+/// The identity of a guest: the claim values blobs are bound to, and its
+/// digest, which names the blobs of the identity in the storage backend.
+struct Identity {
+    binding: BTreeMap<String, Value>,
+    id: String,
+}
+
+pub struct SealingPlugin {
+    storage: ResourceStorage,
+    repository: String,
+    binding_claims: Vec<String>,
+    max_blob_size: usize,
+    max_blobs_per_identity: usize,
+    max_bytes_per_identity: usize,
+    /// Serializes quota checks and writes.
+    write_lock: Mutex<()>,
+}
+
+impl SealingPlugin {
+    pub async fn new(config: SealingPluginConfig) -> Result<Self> {
+        if config.binding_claims.is_empty() {
+            bail!("At least one binding claim is required");
+        }
+
+        Ok(Self {
+            storage: ResourceStorage::new(config.storage).await?,
+            repository: config.repository,
+            binding_claims: config.binding_claims,
+            max_blob_size: config.max_blob_size,
+            max_blobs_per_identity: config.max_blobs_per_identity,
+            max_bytes_per_identity: config.max_bytes_per_identity,
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    fn identity(&self, claims: &Value) -> Result<Identity> {
+        let mut binding = BTreeMap::new();
+        let mut input = Vec::new();
+        for selector in &self.binding_claims {
+            let claim = claims
+                .pointer(selector)
+                .ok_or_else(|| anyhow!("Claim `{selector}` not found in token"))?;
+            let value = serde_json::to_vec(claim)?;
+            input.extend_from_slice(&(selector.len() as u32).to_be_bytes());
+            input.extend_from_slice(selector.as_bytes());
+            input.extend_from_slice(&(value.len() as u32).to_be_bytes());
+            input.extend_from_slice(&value);
+            binding.insert(selector.clone(), claim.clone());
+        }
+        let id = openssl::sha::sha256(&input)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+
+        Ok(Identity { binding, id })
+    }
+
+    fn blob_desc(&self, identity: &Identity, name: &str) -> Result<ResourceDesc> {
+        ResourceDesc::try_from(&*format!("{}/{}/{name}", self.repository, identity.id))
+            .map_err(|_| anyhow!("Illegal blob name `{name}`"))
+    }
+
+    /// Names of the blobs sealed by `identity`.
+    async fn list(&self, identity: &Identity) -> Result<Vec<String>> {
+        Ok(self
+            .storage
+            .list_secret_resources()
+            .await?
+            .into_iter()
+            .filter(|desc| {
+                desc.repository_name == self.repository && desc.resource_type == identity.id
+            })
+            .map(|desc| desc.resource_tag)
+            .collect())
+    }
+
+    /// Read a blob of `identity`, checking that it is bound to the identity.
+    async fn read(&self, identity: &Identity, name: &str) -> Result<Vec<u8>> {
+        let record = self
+            .storage
+            .get_secret_resource(self.blob_desc(identity, name)?)
+            .await
+            .with_context(|| format!("Sealed blob `{name}` not found"))?;
+        let blob: SealedBlob = serde_json::from_slice(&record).context("Parse sealed blob")?;
+        if blob.binding != identity.binding {
+            bail!("Sealed blob `{name}` is bound to another identity");
+        }
+
+        STANDARD
+            .decode(blob.data)
+            .context("Illegal sealed blob encoding")
+    }
+
+    async fn seal(&self, identity: &Identity, name: &str, data: &[u8]) -> Result<()> {
+        if data.len() > self.max_blob_size {
+            bail!(
+                "Blob of {} bytes exceeds the limit of {} bytes",
+                data.len(),
+                self.max_blob_size
+            );
+        }
+        let desc = self.blob_desc(identity, name)?;
+
+        let _lock = self.write_lock.lock().await;
+        // Quotas are checked against the other blobs of the identity, as
+        // sealing an existing name replaces its blob.
+        let others: Vec<String> = self
+            .list(identity)
+            .await?
+            .into_iter()
+            .filter(|other| other != name)
+            .collect();
+        if others.len() + 1 > self.max_blobs_per_identity {
+            bail!(
+                "Quota of {} blobs per identity exceeded",
+                self.max_blobs_per_identity
+            );
+        }
+        let mut total = data.len();
+        for other in &others {
+            total += self.read(identity, other).await?.len();
+        }
+        if total > self.max_bytes_per_identity {
+            bail!(
+                "Quota of {} bytes per identity exceeded",
+                self.max_bytes_per_identity
+            );
+        }
+
+        let blob = SealedBlob {
+            binding: identity.binding.clone(),
+            data: STANDARD.encode(data),
+            sealed_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        };
+        self.storage
+            .set_secret_resource(desc, &serde_json::to_vec(&blob)?)
+            .await?;
+        log::info!("Sealed blob `{name}` for identity {}", identity.id);
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ClientPlugin for SealingPlugin {
+    async fn handle(
+        &self,
+        _body: &[u8],
+        _query: &str,
+        path: &str,
+        method: &Method,
+    ) -> Result<Vec<u8>> {
+        bail!("{method} {path} requires an attestation token")
+    }
+
+    async fn handle_attested(
+        &self,
+        body: &[u8],
+        _query: &str,
+        path: &str,
+        method: &Method,
+        claims: &Value,
+    ) -> Result<Vec<u8>> {
+        let sub_path = path
+            .strip_prefix('/')
+            .context("accessed path is illegal, should start with `/`")?;
+        let identity = self.identity(claims)?;
+
+        match (method.as_str(), sub_path.split_once('/')) {
+            ("GET", None) if sub_path == "blobs" => {
+                Ok(serde_json::to_vec(&self.list(&identity).await?)?)
+            }
+            ("GET", Some(("blob", name))) => self.read(&identity, name).await,
+            ("PUT", Some(("blob", name))) => {
+                self.seal(&identity, name, body).await?;
+                Ok(Vec::new())
+            }
+            ("DELETE", Some(("blob", name))) => {
+                self.storage
+                    .delete_secret_resource(self.blob_desc(&identity, name)?)
+                    .await?;
+                Ok(Vec::new())
+            }
+            _ => bail!("{method} {sub_path} not supported"),
+        }
+    }
+
+    async fn validate_auth(
+        &self,
+        _body: &[u8],
+        _query: &str,
+        _path: &str,
+        _method: &Method,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Unsealed blobs are wrapped to the TEE key.
+    async fn encrypted(
+        &self,
+        _body: &[u8],
+        _query: &str,
+        path: &str,
+        method: &Method,
+    ) -> Result<bool> {
+        Ok(*method == Method::GET && path.starts_with("/blob/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn claims(measurement: &str) -> Value {
+        json!({
+            "submods": { "cpu0": { "ear.veraison.annotated-evidence": {
+                "sample": { "launch_digest": measurement },
+            }}}
+        })
+    }
+
+    #[tokio::test]
+    async fn test_seal_and_unseal() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = SealingPlugin::new(SealingPluginConfig {
+            storage: RepositoryConfig::LocalFs(LocalFsRepoDesc {
+                dir_path: dir.path().to_string_lossy().into_owned(),
+            }),
+            repository: default_repository(),
+            binding_claims: vec![
+                "/submods/cpu0/ear.veraison.annotated-evidence/sample/launch_digest".into(),
+            ],
+            max_blob_size: 8,
+            max_blobs_per_identity: 2,
+            max_bytes_per_identity: 12,
+        })
+        .await
+        .unwrap();
+
+        let request = |method: Method, path: &'static str, body: &'static [u8], claims: Value| {
+            let plugin = &plugin;
+            async move {
+                plugin
+                    .handle_attested(body, "", path, &method, &claims)
+                    .await
+            }
+        };
+
+        request(Method::PUT, "/blob/tls-key", b"secret", claims("aa"))
+            .await
+            .unwrap();
+        assert_eq!(
+            request(Method::GET, "/blob/tls-key", b"", claims("aa"))
+                .await
+                .unwrap(),
+            b"secret"
+        );
+        // Another measurement neither reads nor lists the blob.
+        assert!(request(Method::GET, "/blob/tls-key", b"", claims("bb"))
+            .await
+            .is_err());
+        assert_eq!(
+            request(Method::GET, "/blobs", b"", claims("bb"))
+                .await
+                .unwrap(),
+            b"[]"
+        );
+
+        // Size and quotas. Replacing a blob does not count twice.
+        assert!(
+            request(Method::PUT, "/blob/big", b"123456789", claims("aa"))
+                .await
+                .is_err()
+        );
+        request(Method::PUT, "/blob/tls-key", b"secret2", claims("aa"))
+            .await
+            .unwrap();
+        assert!(
+            request(Method::PUT, "/blob/db-key", b"123456", claims("aa"))
+                .await
+                .is_err()
+        );
+        request(Method::PUT, "/blob/db-key", b"12345", claims("aa"))
+            .await
+            .unwrap();
+        assert!(request(Method::PUT, "/blob/third", b"1", claims("aa"))
+            .await
+            .is_err());
+
+        request(Method::DELETE, "/blob/db-key", b"", claims("aa"))
+            .await
+            .unwrap();
+        assert_eq!(
+            request(Method::GET, "/blobs", b"", claims("aa"))
+                .await
+                .unwrap(),
+            br#"["tls-key"]"#
+        );
+        assert!(request(Method::PUT, "/blob/../x", b"1", claims("aa"))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,783 @@
+// Copyright (c) 2025 by Alibaba.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! SSH CA plugin.
+//!
+//! This plugin signs OpenSSH certificates (`PROTOCOL.certkeys`) with an
+//! Ed25519 CA key:
+//!
+//! - host certificates for attested guests, with principals rendered from
+//!   the attestation token claims, so that clients trusting the CA in
+//!   `known_hosts` only connect to attested instances;
+//! - user certificates through the admin-authenticated path, for break-glass
+//!   access to guests trusting the CA in `TrustedUserCAKeys`.
+//!
+//! Every certificate is recorded in an issuance log. More information can be
+//! found in the [plugin](#kbs/docs/plugins/ssh_ca.md) documentation.
+
+use actix_web::http::Method;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+    Engine,
+};
+use openssl::pkey::{PKey, Private};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::Mutex;
+
+use super::ca_store::{append_issued, read_issued, write_private};
+use super::claim_template::{check_host_name, render_template};
+use crate::plugins::plugin_manager::ClientPlugin;
+
+/// Default SSH CA working directory.
+/// It must have read-write permission.
+const DEFAULT_WORK_DIR: &str = "/opt/confidential-containers/kbs/ssh-ca";
+/// Default validity of host certificates (1 day).
+const DEFAULT_HOST_CERT_TTL_SECS: u64 = 86400;
+/// Default validity of user certificates (1 hour).
+const DEFAULT_USER_CERT_TTL_SECS: u64 = 3600;
+/// Default maximum validity of user certificates (1 day).
+const DEFAULT_USER_CERT_MAX_TTL_SECS: u64 = 86400;
+
+const CA_KEY_NAME: &str = "ca.key";
+const ISSUANCE_LOG_NAME: &str = "issued.jsonl";
+const CA_KEY_COMMENT: &str = "trustee-ssh-ca";
+
+const SSH_ED25519: &str = "ssh-ed25519";
+/// Key types a certificate can be issued for.
+const SUPPORTED_KEY_TYPES: [&str; 5] = [
+    SSH_ED25519,
+    "ssh-rsa",
+    "ecdsa-sha2-nistp256",
+    "ecdsa-sha2-nistp384",
+    "ecdsa-sha2-nistp521",
+];
+
+const SSH_CERT_TYPE_USER: u32 = 1;
+const SSH_CERT_TYPE_HOST: u32 = 2;
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SshCaPluginConfig {
+    /// Work directory holding the generated CA key and the issuance log.
+    work_dir: Option<String>,
+
+    /// PEM (PKCS#8) Ed25519 CA private key. Default: `work_dir/ca.key`,
+    /// generated if missing.
+    ca_key_path: Option<String>,
+
+    /// Templates of the principals of host certificates.
+    #[serde(default)]
+    host_principals: Vec<String>,
+
+    /// Template of the key id of host certificates. Default: the first
+    /// principal.
+    host_key_id: Option<String>,
+
+    /// Validity of host certificates in seconds.
+    #[serde(default = "default_host_cert_ttl_secs")]
+    host_cert_ttl_secs: u64,
+
+    /// Validity of user certificates in seconds, when the request does not
+    /// ask for one.
+    #[serde(default = "default_user_cert_ttl_secs")]
+    user_cert_ttl_secs: u64,
+
+    /// Maximum validity of user certificates in seconds.
+    #[serde(default = "default_user_cert_max_ttl_secs")]
+    user_cert_max_ttl_secs: u64,
+
+    /// Critical options of every user certificate, e.g. `force-command`.
+    /// A request may add options, but not change these.
+    #[serde(default)]
+    user_critical_options: BTreeMap<String, String>,
+
+    /// Extensions of user certificates, when the request does not list any.
+    #[serde(default = "default_user_extensions")]
+    user_extensions: Vec<String>,
+}
+
+fn default_host_cert_ttl_secs() -> u64 {
+    DEFAULT_HOST_CERT_TTL_SECS
+}
+
+fn default_user_cert_ttl_secs() -> u64 {
+    DEFAULT_USER_CERT_TTL_SECS
+}
+
+fn default_user_cert_max_ttl_secs() -> u64 {
+    DEFAULT_USER_CERT_MAX_TTL_SECS
+}
+
+fn default_user_extensions() -> Vec<String> {
+    vec!["permit-pty".into()]
+}
+
+/// Query parameters of a `host-certificate` request.
+#[derive(Debug, Default, Deserialize)]
+struct HostCertParams {
+    /// Requested validity in seconds, capped by `host_cert_ttl_secs`.
+    ttl_secs: Option<u64>,
+}
+
+/// Body of a `user-certificate` request.
+#[derive(Debug, Deserialize)]
+struct UserCertRequest {
+    /// OpenSSH public key, e.g. `ssh-ed25519 AAAA... alice@laptop`.
+    public_key: String,
+    principals: Vec<String>,
+    key_id: String,
+    /// Requested validity in seconds, capped by `user_cert_max_ttl_secs`.
+    ttl_secs: Option<u64>,
+    #[serde(default)]
+    critical_options: BTreeMap<String, String>,
+    extensions: Option<Vec<String>>,
+}
+
+/// One line of the issuance log.
+#[derive(Debug, Serialize, Deserialize)]
+struct IssuedCert {
+    serial: u64,
+    cert_type: String,
+    key_id: String,
+    principals: Vec<String>,
+    /// SHA-256 fingerprint of the certified key.
+    fingerprint: String,
+    valid_after: u64,
+    valid_before: u64,
+}
+
+/// What a certificate is issued for.
+struct CertSpec<'a> {
+    cert_type: u32,
+    key_id: &'a str,
+    principals: &'a [String],
+    ttl_secs: u64,
+    critical_options: &'a BTreeMap<String, String>,
+    extensions: &'a [String],
+}
+
+pub struct SshCaPlugin {
+    work_dir: PathBuf,
+    ca_key: PKey<Private>,
+    /// The CA public key in OpenSSH wire format.
+    ca_public_key: Vec<u8>,
+    host_principals: Vec<String>,
+    host_key_id: Option<String>,
+    host_cert_ttl_secs: u64,
+    user_cert_ttl_secs: u64,
+    user_cert_max_ttl_secs: u64,
+    user_critical_options: BTreeMap<String, String>,
+    user_extensions: Vec<String>,
+    /// Serializes writes to the issuance log.
+    log_lock: Mutex<()>,
+}
+
+impl TryFrom<SshCaPluginConfig> for SshCaPlugin {
+    type Error = anyhow::Error;
+
+    fn try_from(config: SshCaPluginConfig) -> Result<Self> {
+        let work_dir = PathBuf::from(config.work_dir.unwrap_or(DEFAULT_WORK_DIR.into()));
+        fs::create_dir_all(&work_dir)
+            .with_context(|| format!("Create {} dir", work_dir.display()))?;
+
+        let ca_key_path = match config.ca_key_path {
+            Some(path) => PathBuf::from(path),
+            None => {
+                let path = work_dir.join(CA_KEY_NAME);
+                if !path.exists() {
+                    let key = PKey::generate_ed25519()?;
+                    write_private(&path, &key.private_key_to_pem_pkcs8()?)
+                        .with_context(|| format!("Write SSH CA key {}", path.display()))?;
+                    log::warn!("SSH CA key created");
+                }
+                path
+            }
+        };
+        let pem = fs::read(&ca_key_path)
+            .with_context(|| format!("Read SSH CA key {}", ca_key_path.display()))?;
+        let ca_key = PKey::private_key_from_pem(&pem).context("Parse SSH CA key")?;
+        if ca_key.id() != openssl::pkey::Id::ED25519 {
+            bail!("SSH CA key must be an Ed25519 key");
+        }
+
+        let mut ca_public_key = Vec::new();
+        put_string(&mut ca_public_key, SSH_ED25519.as_bytes());
+        put_string(&mut ca_public_key, &ca_key.raw_public_key()?);
+
+        Ok(Self {
+            work_dir,
+            ca_key,
+            ca_public_key,
+            host_principals: config.host_principals,
+            host_key_id: config.host_key_id,
+            host_cert_ttl_secs: config.host_cert_ttl_secs,
+            user_cert_ttl_secs: config.user_cert_ttl_secs,
+            user_cert_max_ttl_secs: config.user_cert_max_ttl_secs,
+            user_critical_options: config.user_critical_options,
+            user_extensions: config.user_extensions,
+            log_lock: Mutex::new(()),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ClientPlugin for SshCaPlugin {
+    async fn handle(
+        &self,
+        body: &[u8],
+        _query: &str,
+        path: &str,
+        method: &Method,
+    ) -> Result<Vec<u8>> {
+        let sub_path = path
+            .strip_prefix('/')
+            .context("accessed path is illegal, should start with `/`")?;
+
+        match (method.as_str(), sub_path) {
+            // The CA public key, for `TrustedUserCAKeys`.
+            ("GET", "ca-public-key") => Ok(self.ca_public_key_line().into_bytes()),
+            // The CA public key as a `known_hosts` line.
+            ("GET", "known-hosts") => {
+                Ok(format!("@cert-authority * {}", self.ca_public_key_line()).into_bytes())
+            }
+            ("GET", "issued") => Ok(serde_json::to_vec(&self.issued()?)?),
+            ("POST", "user-certificate") => {
+                let request: UserCertRequest = serde_json::from_slice(body)
+                    .map_err(|e| anyhow!("Parse user certificate request failed: {e}"))?;
+                self.issue_user_cert(&request).await
+            }
+            ("POST", "host-certificate") => {
+                bail!("Issuing a host certificate requires an attestation token")
+            }
+            _ => bail!("{method} {sub_path} not supported"),
+        }
+    }
+
+    async fn handle_attested(
+        &self,
+        body: &[u8],
+        query: &str,
+        path: &str,
+        method: &Method,
+        claims: &Value,
+    ) -> Result<Vec<u8>> {
+        if *method == Method::POST && path == "/host-certificate" {
+            let params: HostCertParams = serde_qs::from_str(query)
+                .map_err(|e| anyhow!("Parse host certificate request params failed: {e}"))?;
+            let public_key = std::str::from_utf8(body).context("Illegal host public key")?;
+            return self.issue_host_cert(public_key, &params, claims).await;
+        }
+
+        self.handle(body, query, path, method).await
+    }
+
+    /// The CA public key is public in both of its forms, for clients'
+    /// `known_hosts` and for guests' `TrustedUserCAKeys`.
+    async fn public(
+        &self,
+        _body: &[u8],
+        _query: &str,
+        path: &str,
+        method: &Method,
+    ) -> Result<bool> {
+        Ok(matches!(
+            (method.as_str(), path),
+            ("GET", "/ca-public-key") | ("GET", "/known-hosts")
+        ))
+    }
+
+    /// User certificates and the issuance log are for admins. Host
+    /// certificates require an attestation token.
+    async fn validate_auth(
+        &self,
+        _body: &[u8],
+        _query: &str,
+        path: &str,
+        method: &Method,
+    ) -> Result<bool> {
+        Ok(matches!(
+            (method.as_str(), path),
+            ("POST", "/user-certificate") | ("GET", "/issued")
+        ))
+    }
+
+    /// Host certificates are public, no need to encrypt them.
+    async fn encrypted(
+        &self,
+        _body: &[u8],
+        _query: &str,
+        _path: &str,
+        _method: &Method,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+impl SshCaPlugin {
+    fn ca_public_key_line(&self) -> String {
+        format!(
+            "{SSH_ED25519} {} {CA_KEY_COMMENT}\n",
+            STANDARD.encode(&self.ca_public_key)
+        )
+    }
+
+    async fn issue_host_cert(
+        &self,
+        public_key: &str,
+        params: &HostCertParams,
+        claims: &Value,
+    ) -> Result<Vec<u8>> {
+        let principals = self
+            .host_principals
+            .iter()
+            .map(|template| {
+                let principal = render_template(template, claims)?;
+                check_host_name(&principal)?;
+                Ok(principal)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let key_id = match &self.host_key_id {
+            Some(template) => render_template(template, claims)?,
+            None => principals.first().cloned().unwrap_or_default(),
+        };
+        let ttl_secs = params
+            .ttl_secs
+            .unwrap_or(self.host_cert_ttl_secs)
+            .min(self.host_cert_ttl_secs);
+
+        self.issue(
+            public_key,
+            &CertSpec {
+                cert_type: SSH_CERT_TYPE_HOST,
+                key_id: &key_id,
+                principals: &principals,
+                ttl_secs,
+                critical_options: &BTreeMap::new(),
+                extensions: &[],
+            },
+        )
+        .await
+    }
+
+    async fn issue_user_cert(&self, request: &UserCertRequest) -> Result<Vec<u8>> {
+        // The configured options restrict every user certificate, a request
+        // must not override them.
+        if let Some(name) = request
+            .critical_options
+            .keys()
+            .find(|name| self.user_critical_options.contains_key(*name))
+        {
+            bail!("Critical option `{name}` is set by the SSH CA configuration");
+        }
+        let mut critical_options = self.user_critical_options.clone();
+        critical_options.extend(request.critical_options.clone());
+        let ttl_secs = request
+            .ttl_secs
+            .unwrap_or(self.user_cert_ttl_secs)
+            .min(self.user_cert_max_ttl_secs);
+
+        self.issue(
+            &request.public_key,
+            &CertSpec {
+                cert_type: SSH_CERT_TYPE_USER,
+                key_id: &request.key_id,
+                principals: &request.principals,
+                ttl_secs,
+                critical_options: &critical_options,
+                extensions: request.extensions.as_ref().unwrap_or(&self.user_extensions),
+            },
+        )
+        .await
+    }
+
+    /// Sign a certificate for the OpenSSH `public_key` and return it as an
+    /// OpenSSH public key line.
+    async fn issue(&self, public_key: &str, spec: &CertSpec<'_>) -> Result<Vec<u8>> {
+        // A certificate without principals is valid for any principal.
+        if spec.principals.is_empty() || spec.principals.iter().any(String::is_empty) {
+            bail!("SSH certificates need non-empty principals");
+        }
+
+        let (key_type, key_blob) = parse_public_key(public_key)?;
+        let key_fields = &key_blob[4 + key_type.len()..];
+        let cert_key_type = format!("{key_type}-cert-v01@openssh.com");
+
+        let mut nonce = [0u8; 32];
+        openssl::rand::rand_bytes(&mut nonce)?;
+        let mut serial = [0u8; 8];
+        openssl::rand::rand_bytes(&mut serial)?;
+        let serial = u64::from_be_bytes(serial);
+        let valid_after = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let valid_before = valid_after + spec.ttl_secs;
+
+        let mut principals = Vec::new();
+        for principal in spec.principals {
+            put_string(&mut principals, principal.as_bytes());
+        }
+        // Option data is a string in a string; an empty value is empty data.
+        let mut critical_options = Vec::new();
+        for (name, value) in spec.critical_options {
+            put_string(&mut critical_options, name.as_bytes());
+            let mut data = Vec::new();
+            if !value.is_empty() {
+                put_string(&mut data, value.as_bytes());
+            }
+            put_string(&mut critical_options, &data);
+        }
+        // Extensions must be sorted and unique, like critical options.
+        let mut extension_names = spec.extensions.to_vec();
+        extension_names.sort();
+        extension_names.dedup();
+        let mut extensions = Vec::new();
+        for name in &extension_names {
+            put_string(&mut extensions, name.as_bytes());
+            put_string(&mut extensions, &[]);
+        }
+
+        let mut cert = Vec::new();
+        put_string(&mut cert, cert_key_type.as_bytes());
+        put_string(&mut cert, &nonce);
+        cert.extend_from_slice(key_fields);
+        cert.extend_from_slice(&serial.to_be_bytes());
+        cert.extend_from_slice(&spec.cert_type.to_be_bytes());
+        put_string(&mut cert, spec.key_id.as_bytes());
+        put_string(&mut cert, &principals);
+        cert.extend_from_slice(&valid_after.to_be_bytes());
+        cert.extend_from_slice(&valid_before.to_be_bytes());
+        put_string(&mut cert, &critical_options);
+        put_string(&mut cert, &extensions);
+        // Reserved
+        put_string(&mut cert, &[]);
+        put_string(&mut cert, &self.ca_public_key);
+
+        let signature =
+            openssl::sign::Signer::new_without_digest(&self.ca_key)?.sign_oneshot_to_vec(&cert)?;
+        let mut signature_blob = Vec::new();
+        put_string(&mut signature_blob, SSH_ED25519.as_bytes());
+        put_string(&mut signature_blob, &signature);
+        put_string(&mut cert, &signature_blob);
+
+        let entry = IssuedCert {
+            serial,
+            cert_type: match spec.cert_type {
+                SSH_CERT_TYPE_HOST => "host".into(),
+                _ => "user".into(),
+            },
+            key_id: spec.key_id.to_string(),
+            principals: spec.principals.to_vec(),
+            fingerprint: format!(
+                "SHA256:{}",
+                STANDARD_NO_PAD.encode(openssl::sha::sha256(&key_blob))
+            ),
+            valid_after,
+            valid_before,
+        };
+        self.log_issued(&entry).await?;
+        log::info!(
+            "SSH CA issued {} certificate {} for {:?}",
+            entry.cert_type,
+            entry.serial,
+            entry.principals
+        );
+
+        Ok(format!(
+            "{cert_key_type} {} {}\n",
+            STANDARD.encode(&cert),
+            spec.key_id
+        )
+        .into_bytes())
+    }
+
+    async fn log_issued(&self, entry: &IssuedCert) -> Result<()> {
+        let _lock = self.log_lock.lock().await;
+        append_issued(&self.work_dir.join(ISSUANCE_LOG_NAME), entry)
+    }
+
+    /// The issuance log.
+    fn issued(&self) -> Result<Vec<IssuedCert>> {
+        read_issued(&self.work_dir.join(ISSUANCE_LOG_NAME))
+    }
+}
+
+/// Parse an OpenSSH public key line into its key type and wire format blob.
+fn parse_public_key(line: &str) -> Result<(String, Vec<u8>)> {
+    let mut fields = line.split_whitespace();
+    let (Some(key_type), Some(blob)) = (fields.next(), fields.next()) else {
+        bail!("Illegal OpenSSH public key");
+    };
+    if !SUPPORTED_KEY_TYPES.contains(&key_type) {
+        bail!("Unsupported SSH key type `{key_type}`");
+    }
+
+    let blob = STANDARD
+        .decode(blob)
+        .context("Illegal OpenSSH public key encoding")?;
+    let blob_type = blob
+        .get(..4)
+        .map(|len| u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize)
+        .and_then(|len| blob.get(4..4 + len))
+        .context("Illegal OpenSSH public key")?;
+    if blob_type != key_type.as_bytes() {
+        bail!("OpenSSH public key type mismatch");
+    }
+
+    Ok((key_type.to_string(), blob))
+}
+
+/// Append an SSH `string`: a u32 length followed by the bytes.
+fn put_string(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::Path;
+
+    /// Reads the fields of an OpenSSH certificate.
+    struct Reader<'a>(&'a [u8]);
+
+    impl<'a> Reader<'a> {
+        fn take(&mut self, n: usize) -> &'a [u8] {
+            let (head, tail) = self.0.split_at(n);
+            self.0 = tail;
+            head
+        }
+
+        fn u32(&mut self) -> u32 {
+            u32::from_be_bytes(self.take(4).try_into().unwrap())
+        }
+
+        fn u64(&mut self) -> u64 {
+            u64::from_be_bytes(self.take(8).try_into().unwrap())
+        }
+
+        fn string(&mut self) -> &'a [u8] {
+            let len = self.u32() as usize;
+            self.take(len)
+        }
+
+        fn strings(data: &'a [u8]) -> Vec<&'a [u8]> {
+            let mut reader = Reader(data);
+            let mut strings = Vec::new();
+            while !reader.0.is_empty() {
+                strings.push(reader.string());
+            }
+            strings
+        }
+    }
+
+    fn plugin(work_dir: &Path) -> SshCaPlugin {
+        SshCaPlugin::try_from(SshCaPluginConfig {
+            work_dir: Some(work_dir.to_string_lossy().into_owned()),
+            ca_key_path: None,
+            host_principals: vec![
+                "{/submods/cpu0/ear.veraison.annotated-evidence/sample/hostname}".into(),
+            ],
+            host_key_id: None,
+            host_cert_ttl_secs: DEFAULT_HOST_CERT_TTL_SECS,
+            user_cert_ttl_secs: DEFAULT_USER_CERT_TTL_SECS,
+            user_cert_max_ttl_secs: DEFAULT_USER_CERT_MAX_TTL_SECS,
+            user_critical_options: BTreeMap::from([("source-address".into(), "10.0.0.0/8".into())]),
+            user_extensions: default_user_extensions(),
+        })
+        .unwrap()
+    }
+
+    fn ed25519_public_key() -> String {
+        let key = PKey::generate_ed25519().unwrap();
+        let mut blob = Vec::new();
+        put_string(&mut blob, SSH_ED25519.as_bytes());
+        put_string(&mut blob, &key.raw_public_key().unwrap());
+        format!("{SSH_ED25519} {} guest", STANDARD.encode(blob))
+    }
+
+    /// Check the CA signature of `cert_line` and return the certificate
+    /// type, key id, principals, critical options and extensions.
+    fn verify(plugin: &SshCaPlugin, cert_line: &[u8]) -> (u32, String, Vec<String>, Vec<String>) {
+        let cert_line = std::str::from_utf8(cert_line).unwrap();
+        let cert = STANDARD
+            .decode(cert_line.split_whitespace().nth(1).unwrap())
+            .unwrap();
+        let mut reader = Reader(&cert);
+        assert_eq!(reader.string(), b"ssh-ed25519-cert-v01@openssh.com");
+        assert_eq!(reader.string().len(), 32);
+        assert_eq!(reader.string().len(), 32);
+        reader.u64();
+        let cert_type = reader.u32();
+        let key_id = String::from_utf8(reader.string().to_vec()).unwrap();
+        let principals = Reader::strings(reader.string())
+            .into_iter()
+            .map(|p| String::from_utf8(p.to_vec()).unwrap())
+            .collect();
+        let valid_after = reader.u64();
+        assert!(reader.u64() > valid_after);
+        let options = Reader::strings(reader.string())
+            .into_iter()
+            .map(|p| String::from_utf8(p.to_vec()).unwrap())
+            .collect();
+        reader.string();
+        reader.string();
+        assert_eq!(reader.string(), plugin.ca_public_key);
+        let signed = &cert[..cert.len() - reader.0.len()];
+        let mut signature = Reader(reader.string());
+        assert_eq!(signature.string(), SSH_ED25519.as_bytes());
+        assert!(reader.0.is_empty());
+
+        let mut ca_public_key = Reader(&plugin.ca_public_key);
+        ca_public_key.string();
+        let ca_key =
+            PKey::public_key_from_raw_bytes(ca_public_key.string(), openssl::pkey::Id::ED25519)
+                .unwrap();
+        assert!(openssl::sign::Verifier::new_without_digest(&ca_key)
+            .unwrap()
+            .verify_oneshot(signature.string(), signed)
+            .unwrap());
+
+        (cert_type, key_id, principals, options)
+    }
+
+    #[tokio::test]
+    async fn test_host_certificate() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let plugin = plugin(work_dir.path());
+        let claims = json!({
+            "submods": { "cpu0": { "ear.veraison.annotated-evidence": {
+                "sample": { "hostname": "cvm-1.example.org" }
+            }}}
+        });
+
+        let cert = plugin
+            .handle_attested(
+                ed25519_public_key().as_bytes(),
+                "",
+                "/host-certificate",
+                &Method::POST,
+                &claims,
+            )
+            .await
+            .unwrap();
+        let (cert_type, key_id, principals, options) = verify(&plugin, &cert);
+        assert_eq!(cert_type, SSH_CERT_TYPE_HOST);
+        assert_eq!(key_id, "cvm-1.example.org");
+        assert_eq!(principals, vec!["cvm-1.example.org"]);
+        assert!(options.is_empty());
+
+        // Without an attestation token, or without the claim, no certificate.
+        assert!(plugin
+            .handle(
+                ed25519_public_key().as_bytes(),
+                "",
+                "/host-certificate",
+                &Method::POST
+            )
+            .await
+            .is_err());
+        assert!(plugin
+            .handle_attested(
+                ed25519_public_key().as_bytes(),
+                "",
+                "/host-certificate",
+                &Method::POST,
+                &json!({}),
+            )
+            .await
+            .is_err());
+
+        // A wildcard principal would match other hosts.
+        let claims = json!({
+            "submods": { "cpu0": { "ear.veraison.annotated-evidence": {
+                "sample": { "hostname": "*.example.org" }
+            }}}
+        });
+        assert!(plugin
+            .handle_attested(
+                ed25519_public_key().as_bytes(),
+                "",
+                "/host-certificate",
+                &Method::POST,
+                &claims,
+            )
+            .await
+            .is_err());
+        assert_eq!(plugin.issued().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_user_certificate() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let plugin = plugin(work_dir.path());
+        let request = json!({
+            "public_key": ed25519_public_key(),
+            "principals": ["root"],
+            "key_id": "break-glass-alice",
+            "critical_options": { "force-command": "/usr/bin/journalctl" },
+        });
+
+        let cert = plugin
+            .handle(
+                &serde_json::to_vec(&request).unwrap(),
+                "",
+                "/user-certificate",
+                &Method::POST,
+            )
+            .await
+            .unwrap();
+        let (cert_type, key_id, principals, options) = verify(&plugin, &cert);
+        assert_eq!(cert_type, SSH_CERT_TYPE_USER);
+        assert_eq!(key_id, "break-glass-alice");
+        assert_eq!(principals, vec!["root"]);
+        assert_eq!(options[0], "force-command");
+        assert_eq!(options[2], "source-address");
+
+        let issued = plugin.issued().unwrap();
+        assert_eq!(issued.len(), 1);
+        assert_eq!(issued[0].key_id, "break-glass-alice");
+        assert!(issued[0].fingerprint.starts_with("SHA256:"));
+
+        // The configured critical options can not be overridden.
+        let request = json!({
+            "public_key": ed25519_public_key(),
+            "principals": ["root"],
+            "key_id": "break-glass-bob",
+            "critical_options": { "source-address": "0.0.0.0/0" },
+        });
+        assert!(plugin
+            .handle(
+                &serde_json::to_vec(&request).unwrap(),
+                "",
+                "/user-certificate",
+                &Method::POST,
+            )
+            .await
+            .is_err());
+        assert_eq!(plugin.issued().unwrap().len(), 1);
+
+        assert!(plugin
+            .validate_auth(&[], "", "/user-certificate", &Method::POST)
+            .await
+            .unwrap());
+        assert!(!plugin
+            .validate_auth(&[], "", "/host-certificate", &Method::POST)
+            .await
+            .unwrap());
+        // Both forms of the CA public key are public.
+        for path in ["/ca-public-key", "/known-hosts"] {
+            assert!(plugin.public(&[], "", path, &Method::GET).await.unwrap());
+            assert!(!plugin
+                .validate_auth(&[], "", path, &Method::GET)
+                .await
+                .unwrap());
+        }
+    }
+}
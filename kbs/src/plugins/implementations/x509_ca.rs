@@ -0,0 +1,1014 @@
+// Copyright (c) 2025 by Alibaba.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! X.509 workload CA plugin.
+//!
+//! This plugin issues short-lived X.509 certificates to attested workloads.
+//! The guest sends a CSR together with its attestation token; the resource
+//! policy is evaluated on the token claims like for any other plugin, and the
+//! subject and SANs of the certificate are rendered from the claims (e.g. a
+//! SPIFFE ID carrying the image digest or the init-data hash), never from
+//! the CSR. Only the public key of the CSR is used. Issued certificates are
+//! appended to an issuance log, and revoked ones are published in a CRL.
+//! More information can be found in the [plugin](#kbs/docs/plugins/x509_ca.md)
+//! documentation.
+
+use actix_web::http::Method;
+use anyhow::{anyhow, bail, Context, Result};
+use openssl::{
+    asn1::Asn1Time,
+    bn::{BigNum, MsbOption},
+    ec::{EcGroup, EcKey},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{Id, PKey, Private},
+    x509::{
+        extension::{BasicConstraints, KeyUsage as OpensslKeyUsage, SubjectKeyIdentifier},
+        X509Crl, X509NameBuilder, X509Req, X509,
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::Mutex;
+use x509_cert::{
+    attr::AttributeTypeAndValue,
+    certificate::{Certificate, TbsCertificate, Version},
+    crl::{CertificateList, RevokedCert, TbsCertList},
+    der::{
+        asn1::{BitString, Ia5String, Null, OctetString, SetOfVec, UtcTime, Utf8StringRef},
+        oid::{
+            db::{rfc4519, rfc5280},
+            AssociatedOid, ObjectIdentifier,
+        },
+        Any, Decode, DecodePem, Encode,
+    },
+    ext::{
+        pkix::{
+            name::GeneralName, AuthorityKeyIdentifier, BasicConstraints as PkixBasicConstraints,
+            ExtendedKeyUsage, KeyUsage, KeyUsages, SubjectAltName,
+            SubjectKeyIdentifier as PkixSubjectKeyIdentifier,
+        },
+        Extension,
+    },
+    name::{Name, RdnSequence, RelativeDistinguishedName},
+    request::CertReq,
+    serial_number::SerialNumber,
+    spki::{AlgorithmIdentifierOwned, SubjectPublicKeyInfoOwned},
+    time::{Time, Validity},
+};
+
+use super::ca_store::{append_issued, read_issued, write_private};
+use super::claim_template::{check_host_name, render_template};
+use crate::plugins::plugin_manager::ClientPlugin;
+
+/// Default X.509 CA working directory.
+/// It must have read-write permission.
+const DEFAULT_WORK_DIR: &str = "/opt/confidential-containers/kbs/x509-ca";
+/// Default name of the self-signed CA.
+const DEFAULT_CA_NAME: &str = "Trustee X.509 Workload CA";
+/// Validity of the self-signed CA certificate (10 years).
+const SELF_SIGNED_CA_DAYS: u32 = 3650;
+/// Default validity of issued certificates (1 hour).
+const DEFAULT_CERT_TTL_SECS: u64 = 3600;
+/// Default validity of the CRL (1 day).
+const DEFAULT_CRL_TTL_SECS: u64 = 86400;
+
+const CA_KEY_NAME: &str = "ca.key";
+const CA_CERT_NAME: &str = "ca.crt";
+const ISSUANCE_LOG_NAME: &str = "issued.jsonl";
+const REVOCATIONS_NAME: &str = "revoked.json";
+
+const SHA_256_WITH_RSA_ENCRYPTION: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.11");
+const ECDSA_WITH_SHA_256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+const RSA_ENCRYPTION: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct X509CaPluginConfig {
+    /// Work directory holding the self-signed CA, the issuance log and the
+    /// revocations.
+    work_dir: Option<String>,
+
+    /// Where the CA private key is stored. Default: a PEM file in
+    /// `work_dir`, generated with a self-signed CA certificate if missing.
+    #[serde(default)]
+    ca_key: CaKeyConfig,
+
+    /// PEM CA certificate (chain) matching `ca_key`. Required unless the
+    /// self-signed CA of `work_dir` is used.
+    ca_cert_path: Option<String>,
+
+    /// Common name of the self-signed CA.
+    ca_name: Option<String>,
+
+    /// Maximum validity of an issued certificate in seconds. A guest may
+    /// ask for less with the `ttl_secs` query parameter.
+    #[serde(default = "default_cert_ttl_secs")]
+    cert_ttl_secs: u64,
+
+    /// Validity of the CRL in seconds, i.e. its `nextUpdate`.
+    #[serde(default = "default_crl_ttl_secs")]
+    crl_ttl_secs: u64,
+
+    /// Template of the subject common name.
+    subject_cn: Option<String>,
+
+    /// Templates of the DNS name SANs.
+    #[serde(default)]
+    dns_sans: Vec<String>,
+
+    /// Templates of the URI SANs, e.g. SPIFFE IDs.
+    #[serde(default)]
+    uri_sans: Vec<String>,
+
+    /// Templates of the IP address SANs.
+    #[serde(default)]
+    ip_sans: Vec<String>,
+}
+
+fn default_cert_ttl_secs() -> u64 {
+    DEFAULT_CERT_TTL_SECS
+}
+
+fn default_crl_ttl_secs() -> u64 {
+    DEFAULT_CRL_TTL_SECS
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum CaKeyConfig {
+    /// PEM private key file. Default: `work_dir/ca.key`.
+    #[serde(alias = "file")]
+    File { path: Option<String> },
+
+    /// Private key held by a PKCS#11 token, found by its `CKA_LABEL`.
+    #[cfg(feature = "pkcs11")]
+    #[serde(alias = "pkcs11")]
+    Pkcs11 {
+        module: PathBuf,
+        #[serde(default)]
+        slot_index: u8,
+        pin: String,
+        key_label: String,
+    },
+}
+
+impl Default for CaKeyConfig {
+    fn default() -> Self {
+        Self::File { path: None }
+    }
+}
+
+/// Query parameters of a `csr` request.
+#[derive(Debug, Default, Deserialize)]
+struct CsrParams {
+    /// Requested validity in seconds, capped by `cert_ttl_secs`.
+    ttl_secs: Option<u64>,
+}
+
+/// Query parameters of a `revoke` request.
+#[derive(Debug, Deserialize)]
+struct RevokeParams {
+    /// Hex serial number of the certificate.
+    serial: String,
+}
+
+/// One line of the issuance log.
+#[derive(Debug, Serialize, Deserialize)]
+struct IssuedCert {
+    serial: String,
+    subject: String,
+    #[serde(default)]
+    sans: Vec<String>,
+    not_before: i64,
+    not_after: i64,
+}
+
+/// A revoked certificate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Revocation {
+    serial: String,
+    revoked_at: i64,
+}
+
+/// The CA signing key.
+enum CaKey {
+    File(PKey<Private>),
+    #[cfg(feature = "pkcs11")]
+    Pkcs11(pkcs11_key::Pkcs11CaKey),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CaKeyType {
+    Rsa,
+    Ec,
+}
+
+impl CaKey {
+    fn key_type(&self) -> Result<CaKeyType> {
+        match self {
+            CaKey::File(pkey) => match pkey.id() {
+                Id::RSA => Ok(CaKeyType::Rsa),
+                Id::EC => Ok(CaKeyType::Ec),
+                id => bail!("unsupported CA key type {id:?}"),
+            },
+            #[cfg(feature = "pkcs11")]
+            CaKey::Pkcs11(key) => Ok(key.key_type),
+        }
+    }
+
+    fn algorithm(&self) -> Result<AlgorithmIdentifierOwned> {
+        Ok(match self.key_type()? {
+            CaKeyType::Rsa => AlgorithmIdentifierOwned {
+                oid: SHA_256_WITH_RSA_ENCRYPTION,
+                parameters: Some(Any::from(Null)),
+            },
+            CaKeyType::Ec => AlgorithmIdentifierOwned {
+                oid: ECDSA_WITH_SHA_256,
+                parameters: None,
+            },
+        })
+    }
+
+    /// Sign `tbs` with SHA-256. ECDSA signatures are DER encoded.
+    async fn sign(&self, tbs: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            CaKey::File(pkey) => {
+                let mut signer = openssl::sign::Signer::new(MessageDigest::sha256(), pkey)?;
+                Ok(signer.sign_oneshot_to_vec(tbs)?)
+            }
+            #[cfg(feature = "pkcs11")]
+            CaKey::Pkcs11(key) => key.sign(tbs).await,
+        }
+    }
+}
+
+pub struct X509CaPlugin {
+    work_dir: PathBuf,
+    ca_key: CaKey,
+    ca_cert: Certificate,
+    /// PEM CA certificate chain appended to issued certificates.
+    ca_chain_pem: String,
+    cert_ttl_secs: u64,
+    crl_ttl_secs: u64,
+    subject_cn: Option<String>,
+    dns_sans: Vec<String>,
+    uri_sans: Vec<String>,
+    ip_sans: Vec<String>,
+    /// Serializes writes to the issuance log and the revocations.
+    state_lock: Mutex<()>,
+    /// The last signed CRL, dropped on revocation. Taken before
+    /// `state_lock` when both are held.
+    crl_cache: Mutex<Option<CachedCrl>>,
+}
+
+/// A signed PEM CRL, served until `refresh_at`.
+struct CachedCrl {
+    pem: String,
+    refresh_at: SystemTime,
+}
+
+impl TryFrom<X509CaPluginConfig> for X509CaPlugin {
+    type Error = anyhow::Error;
+
+    fn try_from(config: X509CaPluginConfig) -> Result<Self> {
+        let work_dir = PathBuf::from(config.work_dir.unwrap_or(DEFAULT_WORK_DIR.into()));
+        fs::create_dir_all(&work_dir)
+            .with_context(|| format!("Create {} dir", work_dir.display()))?;
+
+        let (ca_key, ca_cert_path) = match config.ca_key {
+            CaKeyConfig::File { path } => {
+                let (key_path, cert_path) = match (path, config.ca_cert_path) {
+                    (Some(key), Some(cert)) => (PathBuf::from(key), PathBuf::from(cert)),
+                    (None, None) => {
+                        let key = work_dir.join(CA_KEY_NAME);
+                        let cert = work_dir.join(CA_CERT_NAME);
+                        if !key.exists() && !cert.exists() {
+                            let name = config.ca_name.as_deref().unwrap_or(DEFAULT_CA_NAME);
+                            create_self_signed_ca(name, &key, &cert)?;
+                            log::warn!("Self-signed X.509 workload CA created");
+                        }
+                        (key, cert)
+                    }
+                    _ => bail!("`ca_key.path` and `ca_cert_path` must be given together"),
+                };
+                let pem = fs::read(&key_path)
+                    .with_context(|| format!("Read CA key {}", key_path.display()))?;
+                let pkey = PKey::private_key_from_pem(&pem).context("Parse CA private key")?;
+                (CaKey::File(pkey), cert_path)
+            }
+            #[cfg(feature = "pkcs11")]
+            CaKeyConfig::Pkcs11 {
+                module,
+                slot_index,
+                pin,
+                key_label,
+            } => {
+                let key = pkcs11_key::Pkcs11CaKey::new(module, slot_index, &pin, &key_label)?;
+                let cert_path = config
+                    .ca_cert_path
+                    .context("`ca_cert_path` is required with a PKCS#11 CA key")?;
+                (CaKey::Pkcs11(key), PathBuf::from(cert_path))
+            }
+        };
+
+        let ca_chain_pem = fs::read_to_string(&ca_cert_path)
+            .with_context(|| format!("Read CA certificate {}", ca_cert_path.display()))?;
+        let ca_cert =
+            Certificate::from_pem(ca_chain_pem.as_bytes()).context("Parse CA certificate")?;
+        // Fail early on keys we can not sign with.
+        ca_key.algorithm()?;
+
+        Ok(Self {
+            work_dir,
+            ca_key,
+            ca_cert,
+            ca_chain_pem,
+            cert_ttl_secs: config.cert_ttl_secs,
+            crl_ttl_secs: config.crl_ttl_secs,
+            subject_cn: config.subject_cn,
+            dns_sans: config.dns_sans,
+            uri_sans: config.uri_sans,
+            ip_sans: config.ip_sans,
+            state_lock: Mutex::new(()),
+            crl_cache: Mutex::new(None),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ClientPlugin for X509CaPlugin {
+    async fn handle(
+        &self,
+        _body: &[u8],
+        query: &str,
+        path: &str,
+        method: &Method,
+    ) -> Result<Vec<u8>> {
+        let sub_path = path
+            .strip_prefix('/')
+            .context("accessed path is illegal, should start with `/`")?;
+
+        match (method.as_str(), sub_path) {
+            // Get CA certificate chain
+            ("GET", "certificate") => Ok(self.ca_chain_pem.clone().into_bytes()),
+            ("GET", "crl") => Ok(self.crl().await?.into_bytes()),
+            ("GET", "issued") => Ok(serde_json::to_vec(&self.issued()?)?),
+            ("POST", "revoke") => {
+                let params: RevokeParams = serde_qs::from_str(query)
+                    .map_err(|e| anyhow!("Parse revoke request params failed: {e}"))?;
+                self.revoke(&params.serial).await?;
+                Ok(Vec::new())
+            }
+            ("POST", "csr") => bail!("Issuing a certificate requires an attestation token"),
+            _ => bail!("{method} {sub_path} not supported"),
+        }
+    }
+
+    async fn handle_attested(
+        &self,
+        body: &[u8],
+        query: &str,
+        path: &str,
+        method: &Method,
+        claims: &Value,
+    ) -> Result<Vec<u8>> {
+        if *method == Method::POST && path == "/csr" {
+            let params: CsrParams = serde_qs::from_str(query)
+                .map_err(|e| anyhow!("Parse CSR request params failed: {e}"))?;
+            return self.issue(body, &params, claims).await;
+        }
+
+        self.handle(body, query, path, method).await
+    }
+
+    /// The CA chain and the CRL are public, so that relying parties can
+    /// fetch them.
+    async fn public(
+        &self,
+        _body: &[u8],
+        _query: &str,
+        path: &str,
+        method: &Method,
+    ) -> Result<bool> {
+        Ok(matches!(
+            (method.as_str(), path),
+            ("GET", "/certificate") | ("GET", "/crl")
+        ))
+    }
+
+    /// Revoking and listing the issued certificates are admin operations.
+    /// Issuing a certificate requires an attestation token.
+    async fn validate_auth(
+        &self,
+        _body: &[u8],
+        _query: &str,
+        path: &str,
+        method: &Method,
+    ) -> Result<bool> {
+        Ok(matches!(
+            (method.as_str(), path),
+            ("POST", "/revoke") | ("GET", "/issued")
+        ))
+    }
+
+    /// Issued certificates are public, no need to encrypt them.
+    async fn encrypted(
+        &self,
+        _body: &[u8],
+        _query: &str,
+        _path: &str,
+        _method: &Method,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+impl X509CaPlugin {
+    /// Issue a certificate for the public key of the PEM or DER `csr`, with
+    /// the subject and SANs rendered from `claims`. Returns the PEM
+    /// certificate followed by the CA chain.
+    async fn issue(&self, csr: &[u8], params: &CsrParams, claims: &Value) -> Result<Vec<u8>> {
+        let public_key = csr_public_key(csr)?;
+
+        let subject_cn = match &self.subject_cn {
+            Some(template) => render_template(template, claims)?,
+            None => String::new(),
+        };
+        let mut sans = Vec::new();
+        let mut san_strings = Vec::new();
+        for template in &self.dns_sans {
+            let dns = render_template(template, claims)?;
+            check_host_name(&dns)?;
+            sans.push(GeneralName::DnsName(
+                Ia5String::new(&dns).with_context(|| format!("Invalid DNS SAN `{dns}`"))?,
+            ));
+            san_strings.push(format!("DNS:{dns}"));
+        }
+        for template in &self.uri_sans {
+            let uri = render_template(template, claims)?;
+            sans.push(GeneralName::UniformResourceIdentifier(
+                Ia5String::new(&uri).with_context(|| format!("Invalid URI SAN `{uri}`"))?,
+            ));
+            san_strings.push(format!("URI:{uri}"));
+        }
+        for template in &self.ip_sans {
+            let ip = render_template(template, claims)?;
+            let addr: IpAddr = ip
+                .parse()
+                .with_context(|| format!("Invalid IP SAN `{ip}`"))?;
+            let octets = match addr {
+                IpAddr::V4(v4) => v4.octets().to_vec(),
+                IpAddr::V6(v6) => v6.octets().to_vec(),
+            };
+            sans.push(GeneralName::IpAddress(OctetString::new(octets)?));
+            san_strings.push(format!("IP:{ip}"));
+        }
+        if subject_cn.is_empty() && sans.is_empty() {
+            bail!("X.509 CA plugin has neither a `subject_cn` nor SAN templates");
+        }
+
+        let ttl = params
+            .ttl_secs
+            .unwrap_or(self.cert_ttl_secs)
+            .min(self.cert_ttl_secs);
+        let now = SystemTime::now();
+        let not_after = now + Duration::from_secs(ttl);
+
+        let mut serial = [0u8; 16];
+        openssl::rand::rand_bytes(&mut serial)?;
+        // Positive, without a leading zero byte.
+        serial[0] = (serial[0] & 0x7f) | 0x40;
+
+        let mut extensions = vec![
+            extension(
+                true,
+                &PkixBasicConstraints {
+                    ca: false,
+                    path_len_constraint: None,
+                },
+            )?,
+            extension(
+                true,
+                &KeyUsage(if public_key.algorithm.oid == RSA_ENCRYPTION {
+                    KeyUsages::DigitalSignature | KeyUsages::KeyEncipherment
+                } else {
+                    KeyUsages::DigitalSignature.into()
+                }),
+            )?,
+            extension(
+                false,
+                &ExtendedKeyUsage(vec![rfc5280::ID_KP_SERVER_AUTH, rfc5280::ID_KP_CLIENT_AUTH]),
+            )?,
+            extension(
+                false,
+                &PkixSubjectKeyIdentifier(OctetString::new(key_identifier(&public_key))?),
+            )?,
+            extension(false, &self.authority_key_identifier()?)?,
+        ];
+        if !sans.is_empty() {
+            // The SAN extension is critical when the subject is empty.
+            extensions.push(extension(subject_cn.is_empty(), &SubjectAltName(sans))?);
+        }
+
+        let tbs = TbsCertificate {
+            version: Version::V3,
+            serial_number: SerialNumber::new(&serial)?,
+            signature: self.ca_key.algorithm()?,
+            issuer: self.ca_cert.tbs_certificate.subject.clone(),
+            validity: Validity {
+                not_before: utc_time(now)?,
+                not_after: utc_time(not_after)?,
+            },
+            subject: common_name(&subject_cn)?,
+            subject_public_key_info: public_key,
+            issuer_unique_id: None,
+            subject_unique_id: None,
+            extensions: Some(extensions),
+        };
+        let signature = self.ca_key.sign(&tbs.to_der()?).await?;
+        let cert = Certificate {
+            tbs_certificate: tbs,
+            signature_algorithm: self.ca_key.algorithm()?,
+            signature: BitString::from_bytes(&signature)?,
+        };
+
+        let entry = IssuedCert {
+            serial: hex_string(&serial),
+            subject: subject_cn,
+            sans: san_strings,
+            not_before: unix_time(now),
+            not_after: unix_time(not_after),
+        };
+        self.log_issued(&entry).await?;
+        log::info!(
+            "X.509 CA issued certificate {} for `{}`",
+            entry.serial,
+            entry.subject
+        );
+
+        let mut pem = X509::from_der(&cert.to_der()?)?.to_pem()?;
+        pem.extend_from_slice(self.ca_chain_pem.as_bytes());
+        Ok(pem)
+    }
+
+    fn authority_key_identifier(&self) -> Result<AuthorityKeyIdentifier> {
+        Ok(AuthorityKeyIdentifier {
+            key_identifier: Some(OctetString::new(key_identifier(
+                &self.ca_cert.tbs_certificate.subject_public_key_info,
+            ))?),
+            authority_cert_issuer: None,
+            authority_cert_serial_number: None,
+        })
+    }
+
+    async fn log_issued(&self, entry: &IssuedCert) -> Result<()> {
+        let _lock = self.state_lock.lock().await;
+        append_issued(&self.work_dir.join(ISSUANCE_LOG_NAME), entry)
+    }
+
+    /// The issuance log.
+    fn issued(&self) -> Result<Vec<IssuedCert>> {
+        read_issued(&self.work_dir.join(ISSUANCE_LOG_NAME))
+    }
+
+    fn revocations(&self) -> Result<Vec<Revocation>> {
+        let path = self.work_dir.join(REVOCATIONS_NAME);
+        match fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content).context("Parse revocations"),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e).with_context(|| format!("Read {}", path.display())),
+        }
+    }
+
+    /// Revoke the issued certificate `serial` (hex).
+    async fn revoke(&self, serial: &str) -> Result<()> {
+        let serial = serial.to_ascii_lowercase();
+        let mut crl_cache = self.crl_cache.lock().await;
+        let _lock = self.state_lock.lock().await;
+        if !self.issued()?.iter().any(|cert| cert.serial == serial) {
+            bail!("Certificate {serial} was not issued by this CA");
+        }
+
+        let mut revocations = self.revocations()?;
+        if revocations.iter().any(|revoked| revoked.serial == serial) {
+            return Ok(());
+        }
+        revocations.push(Revocation {
+            serial: serial.clone(),
+            revoked_at: unix_time(SystemTime::now()),
+        });
+
+        let path = self.work_dir.join(REVOCATIONS_NAME);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&revocations)?)
+            .with_context(|| format!("Write {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("Write {}", path.display()))?;
+        *crl_cache = None;
+        log::info!("X.509 CA revoked certificate {serial}");
+
+        Ok(())
+    }
+
+    /// The PEM CRL of the revoked certificates. It is signed again after a
+    /// revocation, or once half of its validity has passed so that a fetched
+    /// CRL never is close to its `nextUpdate`.
+    async fn crl(&self) -> Result<String> {
+        let mut cache = self.crl_cache.lock().await;
+        if let Some(cached) = cache
+            .as_ref()
+            .filter(|cached| SystemTime::now() < cached.refresh_at)
+        {
+            return Ok(cached.pem.clone());
+        }
+
+        let pem = self.sign_crl().await?;
+        *cache = Some(CachedCrl {
+            pem: pem.clone(),
+            refresh_at: SystemTime::now() + Duration::from_secs(self.crl_ttl_secs / 2),
+        });
+        Ok(pem)
+    }
+
+    /// A freshly signed PEM CRL of the revoked certificates.
+    async fn sign_crl(&self) -> Result<String> {
+        let revoked = {
+            let _lock = self.state_lock.lock().await;
+            self.revocations()?
+        };
+        let revoked_certificates = revoked
+            .iter()
+            .map(|revocation| {
+                Ok(RevokedCert {
+                    serial_number: SerialNumber::new(&hex_bytes(&revocation.serial)?)?,
+                    revocation_date: utc_time(
+                        UNIX_EPOCH + Duration::from_secs(revocation.revoked_at.max(0) as u64),
+                    )?,
+                    crl_entry_extensions: None,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let now = SystemTime::now();
+        let tbs = TbsCertList {
+            version: Version::V2,
+            signature: self.ca_key.algorithm()?,
+            issuer: self.ca_cert.tbs_certificate.subject.clone(),
+            this_update: utc_time(now)?,
+            next_update: Some(utc_time(now + Duration::from_secs(self.crl_ttl_secs))?),
+            revoked_certificates: (!revoked_certificates.is_empty())
+                .then_some(revoked_certificates),
+            crl_extensions: Some(vec![extension(false, &self.authority_key_identifier()?)?]),
+        };
+        let signature = self.ca_key.sign(&tbs.to_der()?).await?;
+        let crl = CertificateList {
+            tbs_cert_list: tbs,
+            signature_algorithm: self.ca_key.algorithm()?,
+            signature: BitString::from_bytes(&signature)?,
+        };
+
+        Ok(String::from_utf8(
+            X509Crl::from_der(&crl.to_der()?)?.to_pem()?,
+        )?)
+    }
+}
+
+/// The public key of a PEM or DER CSR, after checking its self-signature.
+fn csr_public_key(csr: &[u8]) -> Result<SubjectPublicKeyInfoOwned> {
+    let req = X509Req::from_pem(csr)
+        .or_else(|_| X509Req::from_der(csr))
+        .context("Parse CSR")?;
+    if !req.verify(&req.public_key()?)? {
+        bail!("CSR signature verification failed");
+    }
+    let req = CertReq::from_der(&req.to_der()?).context("Parse CSR")?;
+
+    Ok(req.info.public_key)
+}
+
+fn extension<T: AssociatedOid + Encode>(critical: bool, value: &T) -> Result<Extension> {
+    Ok(Extension {
+        extn_id: T::OID,
+        critical,
+        extn_value: OctetString::new(value.to_der()?)?,
+    })
+}
+
+/// The SHA-1 key identifier of RFC 5280, 4.2.1.2 (1).
+fn key_identifier(public_key: &SubjectPublicKeyInfoOwned) -> Vec<u8> {
+    openssl::sha::sha1(public_key.subject_public_key.raw_bytes()).to_vec()
+}
+
+/// A subject of just a common name, or an empty subject.
+fn common_name(cn: &str) -> Result<Name> {
+    if cn.is_empty() {
+        return Ok(RdnSequence::default());
+    }
+
+    let cn = AttributeTypeAndValue {
+        oid: rfc4519::CN,
+        value: Any::encode_from(&Utf8StringRef::new(cn)?)?,
+    };
+    Ok(RdnSequence(vec![RelativeDistinguishedName(
+        SetOfVec::try_from(vec![cn])?,
+    )]))
+}
+
+fn utc_time(time: SystemTime) -> Result<Time> {
+    Ok(Time::UtcTime(UtcTime::from_system_time(time)?))
+}
+
+fn unix_time(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn hex_bytes(hex: &str) -> Result<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        bail!("Illegal serial number `{hex}`");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .with_context(|| format!("Illegal serial number `{hex}`"))
+        })
+        .collect()
+}
+
+/// Create an EC P-256 CA key and a self-signed CA certificate.
+fn create_self_signed_ca(name: &str, key_path: &Path, cert_path: &Path) -> Result<()> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let pkey = PKey::from_ec_key(EcKey::generate(&group)?)?;
+
+    let mut name_builder = X509NameBuilder::new()?;
+    name_builder.append_entry_by_nid(Nid::COMMONNAME, name)?;
+    let name = name_builder.build();
+
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    let serial = {
+        let mut bn = BigNum::new()?;
+        bn.rand(127, MsbOption::MAYBE_ZERO, false)?;
+        bn.to_asn1_integer()?
+    };
+    builder.set_serial_number(&serial)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(&pkey)?;
+    builder.set_not_before(Asn1Time::days_from_now(0)?.as_ref())?;
+    builder.set_not_after(Asn1Time::days_from_now(SELF_SIGNED_CA_DAYS)?.as_ref())?;
+    builder.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+    builder.append_extension(
+        OpensslKeyUsage::new()
+            .critical()
+            .key_cert_sign()
+            .crl_sign()
+            .build()?,
+    )?;
+    let subject_key_identifier =
+        SubjectKeyIdentifier::new().build(&builder.x509v3_context(None, None))?;
+    builder.append_extension(subject_key_identifier)?;
+    builder.sign(&pkey, MessageDigest::sha256())?;
+
+    write_private(key_path, &pkey.private_key_to_pem_pkcs8()?)
+        .with_context(|| format!("Write CA key {}", key_path.display()))?;
+    fs::write(cert_path, builder.build().to_pem()?)
+        .with_context(|| format!("Write CA certificate {}", cert_path.display()))?;
+
+    Ok(())
+}
+
+#[cfg(feature = "pkcs11")]
+mod pkcs11_key {
+    use anyhow::{bail, Context, Result};
+    use cryptoki::{
+        context::{CInitializeArgs, Pkcs11},
+        mechanism::Mechanism,
+        object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle},
+        session::{Session, UserType},
+        types::AuthPin,
+    };
+    use std::path::PathBuf;
+    use tokio::sync::Mutex;
+
+    use super::CaKeyType;
+
+    /// A CA private key held by a PKCS#11 token. RSA keys sign with
+    /// `CKM_SHA256_RSA_PKCS`, EC P-256 keys with `CKM_ECDSA` over the
+    /// SHA-256 digest.
+    pub(super) struct Pkcs11CaKey {
+        session: Mutex<Session>,
+        key: ObjectHandle,
+        pub(super) key_type: CaKeyType,
+    }
+
+    impl Pkcs11CaKey {
+        pub(super) fn new(
+            module: PathBuf,
+            slot_index: u8,
+            pin: &str,
+            key_label: &str,
+        ) -> Result<Self> {
+            let pkcs11 = Pkcs11::new(module).context("unable to open pkcs11 module")?;
+            pkcs11.initialize(CInitializeArgs::OsThreads)?;
+
+            let slots = pkcs11.get_slots_with_token()?;
+            let slot = *slots
+                .get(usize::from(slot_index))
+                .context("Slot index out of range")?;
+            let session = pkcs11.open_ro_session(slot)?;
+            session.login(UserType::User, Some(&AuthPin::new(pin.to_string())))?;
+
+            let key = *session
+                .find_objects(&[
+                    Attribute::Class(ObjectClass::PRIVATE_KEY),
+                    Attribute::Label(key_label.as_bytes().to_vec()),
+                ])?
+                .first()
+                .with_context(|| format!("No private key labelled `{key_label}`"))?;
+            let key_type = match session
+                .get_attributes(key, &[AttributeType::KeyType])?
+                .first()
+            {
+                Some(Attribute::KeyType(key_type)) if *key_type == KeyType::RSA => CaKeyType::Rsa,
+                Some(Attribute::KeyType(key_type)) if *key_type == KeyType::EC => CaKeyType::Ec,
+                _ => bail!("CA key `{key_label}` is neither an RSA nor an EC key"),
+            };
+
+            Ok(Self {
+                session: Mutex::new(session),
+                key,
+                key_type,
+            })
+        }
+
+        pub(super) async fn sign(&self, tbs: &[u8]) -> Result<Vec<u8>> {
+            let session = self.session.lock().await;
+            match self.key_type {
+                CaKeyType::Rsa => Ok(session.sign(&Mechanism::Sha256RsaPkcs, self.key, tbs)?),
+                CaKeyType::Ec => {
+                    let digest = openssl::sha::sha256(tbs);
+                    let signature = session.sign(&Mechanism::Ecdsa, self.key, &digest)?;
+                    // PKCS#11 returns r || s, X.509 carries a DER signature.
+                    Ok(p256::ecdsa::Signature::from_slice(&signature)
+                        .context("CA key is not an EC P-256 key")?
+                        .to_der()
+                        .as_bytes()
+                        .to_vec())
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::{
+        stack::Stack,
+        x509::{extension::SubjectAlternativeName, X509ReqBuilder},
+    };
+    use serde_json::json;
+
+    fn claims() -> Value {
+        json!({
+            "submods": {
+                "cpu0": {
+                    "ear.veraison.annotated-evidence": {
+                        "init_data": "5b1bf8a2",
+                        "sample": { "svn": 1 },
+                    }
+                }
+            }
+        })
+    }
+
+    fn csr() -> Vec<u8> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let pkey = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        let mut builder = X509ReqBuilder::new().unwrap();
+        builder.set_pubkey(&pkey).unwrap();
+        let mut extensions = Stack::new().unwrap();
+        // A SAN requested by the guest must not end up in the certificate.
+        extensions
+            .push(
+                SubjectAlternativeName::new()
+                    .dns("evil.example.org")
+                    .build(&builder.x509v3_context(None))
+                    .unwrap(),
+            )
+            .unwrap();
+        builder.add_extensions(&extensions).unwrap();
+        builder.sign(&pkey, MessageDigest::sha256()).unwrap();
+        builder.build().to_pem().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_issue_and_revoke() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let plugin = X509CaPlugin::try_from(X509CaPluginConfig {
+            work_dir: Some(work_dir.path().to_string_lossy().into_owned()),
+            ca_key: CaKeyConfig::default(),
+            ca_cert_path: None,
+            ca_name: None,
+            cert_ttl_secs: 600,
+            crl_ttl_secs: DEFAULT_CRL_TTL_SECS,
+            subject_cn: Some("workload".into()),
+            dns_sans: vec![],
+            uri_sans: vec![
+                "spiffe://example.org/tee/{/submods/cpu0/ear.veraison.annotated-evidence/init_data}"
+                    .into(),
+            ],
+            ip_sans: vec![],
+        })
+        .unwrap();
+
+        let pem = plugin
+            .handle_attested(&csr(), "ttl_secs=60", "/csr", &Method::POST, &claims())
+            .await
+            .unwrap();
+        let chain = X509::stack_from_pem(&pem).unwrap();
+        assert_eq!(chain.len(), 2);
+        let (cert, ca) = (&chain[0], &chain[1]);
+        assert!(cert.verify(&ca.public_key().unwrap()).unwrap());
+        let sans: Vec<_> = cert
+            .subject_alt_names()
+            .unwrap()
+            .iter()
+            .filter_map(|name| name.uri().map(str::to_owned))
+            .collect();
+        assert_eq!(sans, vec!["spiffe://example.org/tee/5b1bf8a2"]);
+        assert!(cert
+            .subject_alt_names()
+            .unwrap()
+            .iter()
+            .all(|n| n.dnsname().is_none()));
+
+        // The CA chain and the CRL are public, issuing is attested and
+        // revoking is an admin operation.
+        for (path, method) in [("/certificate", Method::GET), ("/crl", Method::GET)] {
+            assert!(plugin.public(&[], "", path, &method).await.unwrap());
+        }
+        for (path, method) in [("/csr", Method::POST), ("/revoke", Method::POST)] {
+            assert!(!plugin.public(&[], "", path, &method).await.unwrap());
+        }
+        assert!(!plugin
+            .validate_auth(&csr(), "", "/csr", &Method::POST)
+            .await
+            .unwrap());
+
+        // A CSR without an attestation token is refused.
+        assert!(plugin
+            .handle(&csr(), "", "/csr", &Method::POST)
+            .await
+            .is_err());
+
+        let issued = plugin.issued().unwrap();
+        assert_eq!(issued.len(), 1);
+        let serial = &issued[0].serial;
+        assert_eq!(
+            cert.serial_number()
+                .to_bn()
+                .unwrap()
+                .to_hex_str()
+                .unwrap()
+                .to_lowercase(),
+            *serial
+        );
+
+        // The CRL is signed once and served until a revocation.
+        let empty = plugin.handle(&[], "", "/crl", &Method::GET).await.unwrap();
+        assert!(plugin.crl_cache.lock().await.is_some());
+        assert_eq!(
+            plugin.handle(&[], "", "/crl", &Method::GET).await.unwrap(),
+            empty
+        );
+
+        plugin
+            .handle(&[], &format!("serial={serial}"), "/revoke", &Method::POST)
+            .await
+            .unwrap();
+        assert!(plugin.crl_cache.lock().await.is_none());
+        let crl = plugin.handle(&[], "", "/crl", &Method::GET).await.unwrap();
+        assert_ne!(crl, empty);
+        let crl = X509Crl::from_pem(&crl).unwrap();
+        assert!(crl.verify(&ca.public_key().unwrap()).unwrap());
+        assert!(matches!(
+            crl.get_by_serial(cert.serial_number()),
+            openssl::x509::CrlStatus::Revoked(_)
+        ));
+    }
+}
@@ -7,6 +7,7 @@ use std::{collections::HashMap, fmt::Display, sync::Arc};
 use actix_web::http::Method;
 use anyhow::{Context, Result};
 use serde::Deserialize;
+use serde_json::Value;
 
 use super::{sample, RepositoryConfig, ResourceStorage};
 
@@ -19,6 +20,18 @@ use super::{Pkcs11Backend, Pkcs11Config};
 #[cfg(feature = "tpm-pca")]
 use super::{TpmCaConfig, TpmCaPlugin};
 
+#[cfg(feature = "x509-ca-plugin")]
+use super::{X509CaPlugin, X509CaPluginConfig};
+
+#[cfg(feature = "ssh-ca-plugin")]
+use super::{SshCaPlugin, SshCaPluginConfig};
+
+#[cfg(feature = "key-derivation-plugin")]
+use super::{KeyDerivationPlugin, KeyDerivationPluginConfig};
+
+#[cfg(feature = "sealing-plugin")]
+use super::{SealingPlugin, SealingPluginConfig};
+
 type ClientPluginInstance = Arc<dyn ClientPlugin>;
 
 #[async_trait::async_trait]
@@ -37,6 +50,35 @@ pub trait ClientPlugin: Send + Sync {
         method: &Method,
     ) -> Result<Vec<u8>>;
 
+    /// Handle a request authorized by an attestation token and the resource
+    /// policy. `claims` are the verified token claims the policy was
+    /// evaluated on, for plugins whose response depends on the attested
+    /// guest. By default the claims are ignored and [`Self::handle`] is
+    /// called.
+    async fn handle_attested(
+        &self,
+        body: &[u8],
+        query: &str,
+        path: &str,
+        method: &Method,
+        _claims: &Value,
+    ) -> Result<Vec<u8>> {
+        self.handle(body, query, path, method).await
+    }
+
+    /// Whether the concrete request is served without any authorization,
+    /// e.g. a CA certificate or a CRL that relying parties fetch. Checked
+    /// before [`Self::validate_auth`]. By default no request is public.
+    async fn public(
+        &self,
+        _body: &[u8],
+        _query: &str,
+        _path: &str,
+        _method: &Method,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
     /// Whether the concrete request needs to validate the admin auth.
     /// If returns `Ok(true)`, the KBS server will perform an admin auth
     /// validation before handle the request.
@@ -80,6 +122,22 @@ pub enum PluginsConfig {
     #[cfg(feature = "tpm-pca")]
     #[serde(alias = "tpm-pca")]
     TpmPca(TpmCaConfig),
+
+    #[cfg(feature = "x509-ca-plugin")]
+    #[serde(alias = "x509-ca")]
+    X509Ca(X509CaPluginConfig),
+
+    #[cfg(feature = "ssh-ca-plugin")]
+    #[serde(alias = "ssh-ca")]
+    SshCa(SshCaPluginConfig),
+
+    #[cfg(feature = "key-derivation-plugin")]
+    #[serde(alias = "key-derivation")]
+    KeyDerivation(KeyDerivationPluginConfig),
+
+    #[cfg(feature = "sealing-plugin")]
+    #[serde(alias = "sealing")]
+    Sealing(SealingPluginConfig),
 }
 
 impl Display for PluginsConfig {
@@ -93,6 +151,14 @@ impl Display for PluginsConfig {
             PluginsConfig::Pkcs11(_) => f.write_str("pkcs11"),
             #[cfg(feature = "tpm-pca")]
             PluginsConfig::TpmPca(_) => f.write_str("tpm-pca"),
+            #[cfg(feature = "x509-ca-plugin")]
+            PluginsConfig::X509Ca(_) => f.write_str("x509-ca"),
+            #[cfg(feature = "ssh-ca-plugin")]
+            PluginsConfig::SshCa(_) => f.write_str("ssh-ca"),
+            #[cfg(feature = "key-derivation-plugin")]
+            PluginsConfig::KeyDerivation(_) => f.write_str("key-derivation"),
+            #[cfg(feature = "sealing-plugin")]
+            PluginsConfig::Sealing(_) => f.write_str("sealing"),
         }
     }
 }
@@ -129,6 +195,32 @@ impl PluginsConfig {
                     .context("Initialize 'tpm-pca' plugin failed")?;
                 Arc::new(tpm_pca) as _
             }
+            #[cfg(feature = "x509-ca-plugin")]
+            PluginsConfig::X509Ca(x509_ca_config) => {
+                let x509_ca = X509CaPlugin::try_from(x509_ca_config)
+                    .context("Initialize 'x509-ca' plugin failed")?;
+                Arc::new(x509_ca) as _
+            }
+            #[cfg(feature = "ssh-ca-plugin")]
+            PluginsConfig::SshCa(ssh_ca_config) => {
+                let ssh_ca = SshCaPlugin::try_from(ssh_ca_config)
+                    .context("Initialize 'ssh-ca' plugin failed")?;
+                Arc::new(ssh_ca) as _
+            }
+            #[cfg(feature = "key-derivation-plugin")]
+            PluginsConfig::KeyDerivation(key_derivation_config) => {
+                let key_derivation = KeyDerivationPlugin::new(key_derivation_config)
+                    .await
+                    .context("Initialize 'key-derivation' plugin failed")?;
+                Arc::new(key_derivation) as _
+            }
+            #[cfg(feature = "sealing-plugin")]
+            PluginsConfig::Sealing(sealing_config) => {
+                let sealing = SealingPlugin::new(sealing_config)
+                    .await
+                    .context("Initialize 'sealing' plugin failed")?;
+                Arc::new(sealing) as _
+            }
         };
 
         Ok(plugin)
@@ -0,0 +1,304 @@
+// Copyright (c) 2024 by Alibaba.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Decoding of COSE encoded EARs: a tagged or untagged COSE_Sign1 (RFC 9052)
+//! over the CBOR encoded EAR claims set, URL-safe base64 encoded without
+//! padding. The KBS tells them apart from JWTs by the absence of `.`.
+
+use anyhow::{anyhow, bail, Context};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use coset::cbor::Value as CborValue;
+use coset::{iana, CborSerializable, CoseSign1, TaggedCborSerializable};
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk};
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use rustls_pki_types::CertificateDer;
+use serde_json::Value;
+use x509_cert::der::Decode;
+use x509_cert::Certificate;
+
+/// COSE header label of the signer's certificate chain (RFC 9360).
+const HEADER_X5CHAIN: i64 = 33;
+
+/// CWT claim keys of the expiration time and of the token id (RFC 8392).
+const CLAIM_EXP: i64 = 4;
+const CLAIM_CTI: i64 = 7;
+/// Private use CWT claim key the AS puts the `rvps-namespace` claim under.
+const CLAIM_RVPS_NAMESPACE: i64 = -75000;
+
+/// Whether `token` is a COSE encoded EAR rather than a JWT.
+pub fn is_cose(token: &str) -> bool {
+    !token.contains('.')
+}
+
+pub fn decode(token: &str) -> anyhow::Result<CoseSign1> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(token.trim())
+        .context("Failed to base64 decode COSE attestation token")?;
+    CoseSign1::from_tagged_slice(&bytes)
+        .or_else(|_| CoseSign1::from_slice(&bytes))
+        .map_err(|e| anyhow!("Failed to decode COSE_Sign1: {e}"))
+}
+
+/// The certificate chain in the `x5chain` header, leaf first.
+pub fn x5chain(sign1: &CoseSign1) -> anyhow::Result<Option<Vec<CertificateDer<'static>>>> {
+    let value = [&sign1.protected.header, &sign1.unprotected]
+        .into_iter()
+        .flat_map(|header| header.rest.iter())
+        .find(|(label, _)| *label == coset::Label::Int(HEADER_X5CHAIN))
+        .map(|(_, value)| value);
+
+    let certs = match value {
+        None => return Ok(None),
+        Some(CborValue::Bytes(cert)) => vec![cert.clone()],
+        Some(CborValue::Array(certs)) => certs
+            .iter()
+            .map(|cert| {
+                cert.as_bytes()
+                    .cloned()
+                    .ok_or_else(|| anyhow!("x5chain entry is not a byte string"))
+            })
+            .collect::<anyhow::Result<_>>()?,
+        Some(_) => bail!("Illegal x5chain header"),
+    };
+    if certs.is_empty() {
+        bail!("Empty x5chain header");
+    }
+
+    Ok(Some(certs.into_iter().map(CertificateDer::from).collect()))
+}
+
+/// The `kid` of the protected or unprotected header.
+pub fn key_id(sign1: &CoseSign1) -> anyhow::Result<String> {
+    let kid = [&sign1.protected.header, &sign1.unprotected]
+        .into_iter()
+        .map(|header| &header.key_id)
+        .find(|kid| !kid.is_empty())
+        .ok_or(anyhow!("Failed to find kid in the COSE token header"))?;
+    String::from_utf8(kid.clone()).context("kid in the COSE token header is not UTF-8")
+}
+
+pub fn cert_verifying_key(cert: &CertificateDer<'_>) -> anyhow::Result<VerifyingKey> {
+    let cert = Certificate::from_der(cert.as_ref()).context("Invalid x509 in x5chain")?;
+    let spki = &cert.tbs_certificate.subject_public_key_info;
+    VerifyingKey::from_sec1_bytes(spki.subject_public_key.raw_bytes())
+        .map_err(|_| anyhow!("x5chain leaf certificate does not hold a P-256 key"))
+}
+
+pub fn jwk_verifying_key(key: &Jwk) -> anyhow::Result<VerifyingKey> {
+    let AlgorithmParameters::EllipticCurve(ec) = &key.algorithm else {
+        bail!("COSE attestation tokens are only verified with P-256 JWKs");
+    };
+    if ec.curve != EllipticCurve::P256 {
+        bail!("COSE attestation tokens are only verified with P-256 JWKs");
+    }
+
+    let mut point = vec![0x04];
+    point.extend(
+        URL_SAFE_NO_PAD
+            .decode(&ec.x)
+            .context("decode EC public key parameter x")?,
+    );
+    point.extend(
+        URL_SAFE_NO_PAD
+            .decode(&ec.y)
+            .context("decode EC public key parameter y")?,
+    );
+    VerifyingKey::from_sec1_bytes(&point).map_err(|_| anyhow!("Illegal P-256 JWK"))
+}
+
+/// Check the ES256 signature of `sign1` and return its claims in the JSON
+/// form of the EAR, the same shape a JWT EAR has.
+pub fn verify(sign1: &CoseSign1, key: &VerifyingKey) -> anyhow::Result<Value> {
+    if sign1.protected.header.alg != Some(coset::Algorithm::Assigned(iana::Algorithm::ES256)) {
+        bail!("Unsupported COSE attestation token algorithm, only ES256 is supported");
+    }
+    sign1
+        .verify_signature(&[], |signature, data| {
+            let signature = Signature::from_slice(signature)?;
+            key.verify(data, &signature)
+        })
+        .map_err(|e| anyhow!("Failed to verify COSE attestation token signature: {e}"))?;
+
+    let payload = sign1
+        .payload
+        .as_ref()
+        .ok_or(anyhow!("COSE attestation token has no payload"))?;
+    claims(payload)
+}
+
+fn claims(payload: &[u8]) -> anyhow::Result<Value> {
+    let mut claims: CborValue =
+        coset::cbor::from_reader(payload).context("Failed to decode CBOR EAR claims")?;
+    let entries = claims
+        .as_map_mut()
+        .ok_or(anyhow!("EAR claims are not a CBOR map"))?;
+
+    // `exp`, `jti` and `rvps-namespace` are not part of the EAR claims set
+    // but CWT claims the AS adds, so they are checked and carried over here.
+    let mut take = |key: i64| {
+        let label = CborValue::Integer(key.into());
+        entries
+            .iter()
+            .position(|(l, _)| *l == label)
+            .map(|index| entries.remove(index).1)
+    };
+    // Like a JWT attestation token, a COSE one must expire.
+    let exp = take(CLAIM_EXP)
+        .ok_or(anyhow!("COSE attestation token has no exp claim"))?
+        .as_integer()
+        .and_then(|exp| i64::try_from(exp).ok())
+        .ok_or(anyhow!("Illegal exp claim in COSE attestation token"))?;
+    let jti = take(CLAIM_CTI).map(cti_to_jti).transpose()?;
+    let namespace = take(CLAIM_RVPS_NAMESPACE)
+        .map(|namespace| match namespace {
+            CborValue::Text(namespace) => Ok(namespace),
+            _ => Err(anyhow!(
+                "Illegal rvps-namespace claim in COSE attestation token"
+            )),
+        })
+        .transpose()?;
+    if exp < time::OffsetDateTime::now_utc().unix_timestamp() {
+        bail!("COSE attestation token has expired");
+    }
+
+    let ear: ear::Ear = claims
+        .deserialized()
+        .context("Failed to decode CBOR EAR claims")?;
+    let mut claims = serde_json::to_value(&ear)?;
+    if let Some(claims) = claims.as_object_mut() {
+        claims.insert("exp".into(), exp.into());
+        if let Some(jti) = jti {
+            claims.insert("jti".into(), jti.into());
+        }
+        if let Some(namespace) = namespace {
+            claims.insert("rvps-namespace".into(), namespace.into());
+        }
+    }
+
+    Ok(claims)
+}
+
+/// The `cti` claim is a byte string (RFC 8392), holding the UTF-8 bytes of
+/// the `jti` the AS gives the token. Text is accepted from older tokens.
+fn cti_to_jti(cti: CborValue) -> anyhow::Result<String> {
+    match cti {
+        CborValue::Bytes(bytes) => String::from_utf8(bytes)
+            .map_err(|_| anyhow!("Illegal jti claim in COSE attestation token")),
+        CborValue::Text(text) => Ok(text),
+        _ => bail!("Illegal jti claim in COSE attestation token"),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use coset::{CoseSign1Builder, HeaderBuilder};
+    use ear::{Appraisal, Ear, ExtensionKind, ExtensionValue, Extensions, VerifierID};
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::SigningKey;
+    use std::collections::BTreeMap;
+
+    /// A COSE EAR like the AS issues, with a `kid` header, the `jti`
+    /// `ear-jti` and the `rvps-namespace` `default`.
+    pub(crate) fn cose_ear(key: &SigningKey, kid: &str, exp: i64) -> String {
+        cose_ear_expiring(key, kid, Some(exp))
+    }
+
+    fn cose_ear_expiring(key: &SigningKey, kid: &str, exp: Option<i64>) -> String {
+        let mut extensions = Extensions::new();
+        if let Some(exp) = exp {
+            extensions
+                .register("exp", 4, ExtensionKind::Integer)
+                .unwrap();
+            extensions
+                .set_by_name("exp", ExtensionValue::Integer(exp))
+                .unwrap();
+        }
+        let ear = Ear {
+            profile: "tag:github.com,2024:confidential-containers/Trustee".into(),
+            iat: 0,
+            vid: VerifierID {
+                build: "test".into(),
+                developer: "test".into(),
+            },
+            raw_evidence: None,
+            nonce: None,
+            submods: BTreeMap::from([("cpu0".to_string(), Appraisal::new())]),
+            extensions,
+        };
+        let mut claims = CborValue::serialized(&ear).unwrap();
+        claims.as_map_mut().unwrap().push((
+            CborValue::Integer(CLAIM_CTI.into()),
+            CborValue::Bytes(b"ear-jti".to_vec()),
+        ));
+        claims.as_map_mut().unwrap().push((
+            CborValue::Integer(CLAIM_RVPS_NAMESPACE.into()),
+            CborValue::Text("default".into()),
+        ));
+        let mut payload = Vec::new();
+        coset::cbor::into_writer(&claims, &mut payload).unwrap();
+
+        let sign1 = CoseSign1Builder::new()
+            .protected(
+                HeaderBuilder::new()
+                    .algorithm(iana::Algorithm::ES256)
+                    .key_id(kid.as_bytes().to_vec())
+                    .build(),
+            )
+            .payload(payload)
+            .create_signature(&[], |data| {
+                let signature: Signature = key.sign(data);
+                signature.to_bytes().to_vec()
+            })
+            .build();
+        URL_SAFE_NO_PAD.encode(sign1.to_tagged_vec().unwrap())
+    }
+
+    fn far_future() -> i64 {
+        time::OffsetDateTime::now_utc().unix_timestamp() + 300
+    }
+
+    #[test]
+    fn verifies_cose_ear() {
+        let key = SigningKey::random(&mut rand::rngs::OsRng);
+        let token = cose_ear(&key, "ear-key", far_future());
+        assert!(is_cose(&token));
+
+        let sign1 = decode(&token).unwrap();
+        assert_eq!(key_id(&sign1).unwrap(), "ear-key");
+        assert!(x5chain(&sign1).unwrap().is_none());
+
+        let claims = verify(&sign1, key.verifying_key()).unwrap();
+        assert_eq!(
+            claims["eat_profile"],
+            "tag:github.com,2024:confidential-containers/Trustee"
+        );
+        assert!(claims["submods"]["cpu0"].is_object());
+        assert!(claims["exp"].is_i64());
+        assert_eq!(claims["jti"], "ear-jti");
+        assert_eq!(claims["rvps-namespace"], "default");
+    }
+
+    #[test]
+    fn rejects_wrong_key_tampering_and_expiry() {
+        let key = SigningKey::random(&mut rand::rngs::OsRng);
+        let other = SigningKey::random(&mut rand::rngs::OsRng);
+        let token = cose_ear(&key, "ear-key", far_future());
+
+        let sign1 = decode(&token).unwrap();
+        assert!(verify(&sign1, other.verifying_key()).is_err());
+
+        let mut tampered = sign1.clone();
+        tampered.payload.as_mut().unwrap().push(0);
+        assert!(verify(&tampered, key.verifying_key()).is_err());
+
+        let expired = decode(&cose_ear(&key, "ear-key", 1)).unwrap();
+        assert!(verify(&expired, key.verifying_key()).is_err());
+
+        let unexpiring = decode(&cose_ear_expiring(&key, "ear-key", None)).unwrap();
+        assert!(verify(&unexpiring, key.verifying_key()).is_err());
+    }
+}
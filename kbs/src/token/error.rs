@@ -15,6 +15,15 @@ pub enum Error {
         source: anyhow::Error,
     },
 
+    #[error("Attestation Token {jti} has been revoked")]
+    TokenRevoked { jti: String },
+
+    #[error("Failed to check the revocation of Attestation Token: {source}")]
+    TokenRevocationCheckFailed {
+        #[source]
+        source: anyhow::Error,
+    },
+
     #[error("Failed to initialize Token Verifier: {source}")]
     TokenVerifierInitialization {
         #[source]
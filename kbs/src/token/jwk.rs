@@ -2,19 +2,25 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::token::AttestationTokenVerifierConfig;
+use crate::token::{cose, AttestationTokenVerifierConfig};
 use anyhow::{anyhow, bail, Context};
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
+use coset::CoseSign1;
 use jsonwebtoken::jwk::{AlgorithmParameters, Jwk};
 use jsonwebtoken::{decode, decode_header, jwk, Algorithm, DecodingKey, Header, Validation};
+use log::warn;
+use p256::ecdsa::VerifyingKey;
 use reqwest::Url;
 use rustls_pki_types::pem::PemObject;
 use rustls_pki_types::{CertificateDer, UnixTime};
 use serde::Deserialize;
 use serde_json::Value;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use tokio::sync::{Mutex, RwLock};
 use webpki::ring::{
     ECDSA_P256_SHA256, ECDSA_P256_SHA384, ECDSA_P384_SHA256, ECDSA_P384_SHA384,
     RSA_PKCS1_2048_8192_SHA256, RSA_PKCS1_2048_8192_SHA384, RSA_PKCS1_2048_8192_SHA512,
@@ -42,7 +48,13 @@ struct OpenIDConfig {
 
 #[derive(Clone)]
 pub struct JwkAttestationTokenVerifier {
-    trusted_jwk_sets: jwk::JwkSet,
+    client: reqwest::Client,
+    jwk_set_sources: Vec<String>,
+    trusted_jwk_sets: Arc<RwLock<jwk::JwkSet>>,
+    /// When the JWK sets were last fetched. Used to rate limit the refresh
+    /// on an unknown `kid`.
+    jwk_sets_fetched_at: Arc<Mutex<Instant>>,
+    jwks_refresh_interval: Option<Duration>,
     trusted_certs: Vec<CertificateDer<'static>>,
     insecure_key: bool,
 }
@@ -91,6 +103,18 @@ async fn get_jwks_from_file_or_url(
     }
 }
 
+/// Fetch and merge the JWK sets of all `sources`.
+async fn get_jwk_sets(client: &reqwest::Client, sources: &[String]) -> anyhow::Result<jwk::JwkSet> {
+    let mut trusted_jwk_sets = jwk::JwkSet { keys: Vec::new() };
+    for path in sources {
+        match get_jwks_from_file_or_url(client, path).await {
+            Ok(mut jwkset) => trusted_jwk_sets.keys.append(&mut jwkset.keys),
+            Err(e) => bail!("error getting JWKS: {:?}", e),
+        }
+    }
+    Ok(trusted_jwk_sets)
+}
+
 fn new_http_client() -> reqwest::Client {
     reqwest::Client::builder()
         .user_agent(format!("kbs/{}", env!("CARGO_PKG_VERSION")))
@@ -101,14 +125,7 @@ fn new_http_client() -> reqwest::Client {
 impl JwkAttestationTokenVerifier {
     pub async fn new(config: &AttestationTokenVerifierConfig) -> anyhow::Result<Self> {
         let client = new_http_client();
-        let mut trusted_jwk_sets = jwk::JwkSet { keys: Vec::new() };
-
-        for path in &config.trusted_jwk_sets {
-            match get_jwks_from_file_or_url(&client, path).await {
-                Ok(mut jwkset) => trusted_jwk_sets.keys.append(&mut jwkset.keys),
-                Err(e) => bail!("error getting JWKS: {:?}", e),
-            }
-        }
+        let trusted_jwk_sets = get_jwk_sets(&client, &config.trusted_jwk_sets).await?;
 
         let mut trusted_certs = Vec::new();
 
@@ -125,13 +142,55 @@ impl JwkAttestationTokenVerifier {
             trusted_certs.extend(certs);
         }
 
+        let jwks_refresh_interval = match config.jwks_refresh_interval_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+
         Ok(Self {
-            trusted_jwk_sets,
+            client,
+            jwk_set_sources: config.trusted_jwk_sets.clone(),
+            trusted_jwk_sets: Arc::new(RwLock::new(trusted_jwk_sets)),
+            jwk_sets_fetched_at: Arc::new(Mutex::new(Instant::now())),
+            jwks_refresh_interval,
             trusted_certs,
             insecure_key: config.insecure_key,
         })
     }
 
+    /// The trusted JWK named `kid`. An unknown `kid` may belong to a key the
+    /// AS published after the JWK sets were fetched, so they are fetched
+    /// again, at most once per `jwks_refresh_interval_secs`.
+    async fn find_trusted_jwk(&self, kid: &str) -> anyhow::Result<Jwk> {
+        {
+            let trusted_jwk_sets = self.trusted_jwk_sets.read().await;
+            if trusted_jwk_sets.keys.is_empty() {
+                bail!("Cannot verify token since trusted JWK Set is empty");
+            }
+            if let Some(key) = trusted_jwk_sets.find(kid) {
+                return Ok(key.clone());
+            }
+        }
+
+        if let Some(interval) = self.jwks_refresh_interval {
+            let mut fetched_at = self.jwk_sets_fetched_at.lock().await;
+            if fetched_at.elapsed() >= interval {
+                *fetched_at = Instant::now();
+                match get_jwk_sets(&self.client, &self.jwk_set_sources).await {
+                    Ok(jwk_sets) => *self.trusted_jwk_sets.write().await = jwk_sets,
+                    Err(e) => warn!("Failed to refresh trusted JWK sets: {e:#}"),
+                }
+            }
+        }
+
+        self.trusted_jwk_sets
+            .read()
+            .await
+            .find(kid)
+            .cloned()
+            .ok_or(anyhow!("Failed to find Jwk with kid {kid} in JwkSet"))
+    }
+
     fn verify_jwk_endorsement(&self, key: &Jwk) -> anyhow::Result<()> {
         let Some(x5c) = &key.common.x509_chain else {
             bail!("No x5c extension inside JWK. Invalid public key.")
@@ -148,8 +207,25 @@ impl JwkAttestationTokenVerifier {
             self.verify_jwk_matches_cert(key, &leaf_cert)?;
         }
 
-        let leaf_cert = CertificateDer::from(leaf_der);
-        let end_entity = EndEntityCert::try_from(&leaf_cert)
+        let mut intermediates = Vec::new();
+        for cert_pem in &x5c[1..] {
+            let pem = cert_pem.split('\n').collect::<String>();
+            let der = URL_SAFE_NO_PAD.decode(&pem).context("Illegal x5c cert")?;
+            intermediates.push(CertificateDer::from(der));
+        }
+
+        self.verify_cert_chain(&CertificateDer::from(leaf_der), &intermediates)
+            .map_err(|e| anyhow!("JWK cannot be validated by trust anchor: {}", e))
+    }
+
+    /// Validate a leaf certificate and its intermediates against the trusted
+    /// certificates.
+    fn verify_cert_chain(
+        &self,
+        leaf_cert: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+    ) -> anyhow::Result<()> {
+        let end_entity = EndEntityCert::try_from(leaf_cert)
             .map_err(|e| anyhow!("Failed to parse end entity certificate: {}", e))?;
 
         let trust_anchors: Vec<_> = self
@@ -161,13 +237,6 @@ impl JwkAttestationTokenVerifier {
             })
             .collect::<Result<_, _>>()?;
 
-        let mut intermediates = Vec::new();
-        for cert_pem in &x5c[1..] {
-            let pem = cert_pem.split('\n').collect::<String>();
-            let der = URL_SAFE_NO_PAD.decode(&pem).context("Illegal x5c cert")?;
-            intermediates.push(CertificateDer::from(der));
-        }
-
         let supported_algs = &[
             ECDSA_P256_SHA256,
             ECDSA_P256_SHA384,
@@ -183,13 +252,13 @@ impl JwkAttestationTokenVerifier {
             .verify_for_usage(
                 supported_algs,
                 &trust_anchors,
-                &intermediates,
+                intermediates,
                 time,
                 webpki::KeyUsage::client_auth(),
                 None,
                 None,
             )
-            .map_err(|e| anyhow!("JWK cannot be validated by trust anchor: {}", e))?;
+            .map_err(|e| anyhow!("{}", e))?;
 
         Ok(())
     }
@@ -233,20 +302,16 @@ impl JwkAttestationTokenVerifier {
         Ok(())
     }
 
-    fn get_verification_jwk<'a>(&'a self, header: &'a Header) -> anyhow::Result<&'a Jwk> {
+    async fn get_verification_jwk(&self, header: &Header) -> anyhow::Result<Jwk> {
         if let Some(key) = &header.jwk {
             if self.insecure_key {
-                return Ok(key);
+                return Ok(key.clone());
             }
             if self.trusted_certs.is_empty() {
                 bail!("Cannot verify token since trusted cert is empty");
             }
             self.verify_jwk_endorsement(key)?;
-            return Ok(key);
-        }
-
-        if self.trusted_jwk_sets.keys.is_empty() {
-            bail!("Cannot verify token since trusted JWK Set is empty");
+            return Ok(key.clone());
         }
 
         let kid = header
@@ -254,19 +319,39 @@ impl JwkAttestationTokenVerifier {
             .as_ref()
             .ok_or(anyhow!("Failed to decode kid in the token header"))?;
 
-        let key = &self
-            .trusted_jwk_sets
-            .find(kid)
-            .ok_or(anyhow!("Failed to find Jwk with kid {kid} in JwkSet"))?;
+        self.find_trusted_jwk(kid).await
+    }
 
-        Ok(key)
+    /// The key of a COSE EAR: the leaf of its `x5chain` header, endorsed by
+    /// the trusted certificates, or the trusted JWK named by its `kid`.
+    async fn get_cose_verification_key(&self, sign1: &CoseSign1) -> anyhow::Result<VerifyingKey> {
+        if let Some(chain) = cose::x5chain(sign1)? {
+            if !self.insecure_key {
+                if self.trusted_certs.is_empty() {
+                    bail!("Cannot verify token since trusted cert is empty");
+                }
+                self.verify_cert_chain(&chain[0], &chain[1..])
+                    .map_err(|e| anyhow!("x5chain cannot be validated by trust anchor: {}", e))?;
+            }
+            return cose::cert_verifying_key(&chain[0]);
+        }
+
+        let kid = cose::key_id(sign1)?;
+        let key = self.find_trusted_jwk(&kid).await?;
+        cose::jwk_verifying_key(&key)
     }
 
     pub async fn verify(&self, token: String) -> anyhow::Result<Value> {
+        if cose::is_cose(&token) {
+            let sign1 = cose::decode(&token)?;
+            let key = self.get_cose_verification_key(&sign1).await?;
+            return cose::verify(&sign1, &key);
+        }
+
         let header = decode_header(&token)
             .map_err(|e| anyhow!("Failed to decode attestation token header: {}", e))?;
 
-        let key = self.get_verification_jwk(&header)?;
+        let key = self.get_verification_jwk(&header).await?;
         let key_alg = key
             .common
             .key_algorithm
@@ -274,7 +359,7 @@ impl JwkAttestationTokenVerifier {
             .to_string();
 
         let alg = Algorithm::from_str(key_alg.as_str())?;
-        let dkey = DecodingKey::from_jwk(key)?;
+        let dkey = DecodingKey::from_jwk(&key)?;
         let mut validation = Validation::new(alg);
         #[cfg(test)]
         {
@@ -291,7 +376,8 @@ impl JwkAttestationTokenVerifier {
 
 #[cfg(test)]
 mod tests {
-    use super::get_jwks_from_file_or_url;
+    use super::{get_jwks_from_file_or_url, JwkAttestationTokenVerifier};
+    use crate::token::AttestationTokenVerifierConfig;
     use rstest::rstest;
 
     #[rstest]
@@ -333,4 +419,108 @@ mod tests {
             get_jwks_from_file_or_url(&client, &p).await.is_err()
         )
     }
+
+    #[tokio::test]
+    async fn test_verify_cose_ear_with_jwks() {
+        use crate::token::cose::tests::cose_ear;
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        use base64::Engine;
+        use p256::ecdsa::SigningKey;
+        use p256::elliptic_curve::sec1::ToEncodedPoint;
+
+        let key = SigningKey::random(&mut rand::rngs::OsRng);
+        let point = key.verifying_key().to_encoded_point(false);
+        let jwks = serde_json::json!({"keys": [{
+            "kty": "EC",
+            "crv": "P-256",
+            "alg": "ES256",
+            "kid": "ear-key",
+            "x": URL_SAFE_NO_PAD.encode(point.x().unwrap()),
+            "y": URL_SAFE_NO_PAD.encode(point.y().unwrap()),
+        }]});
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let jwks_file = tmp_dir.path().join("ear.jwks");
+        std::fs::write(&jwks_file, jwks.to_string()).unwrap();
+
+        let verifier = JwkAttestationTokenVerifier::new(&AttestationTokenVerifierConfig {
+            trusted_jwk_sets: vec![format!("file://{}", jwks_file.display())],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let exp = time::OffsetDateTime::now_utc().unix_timestamp() + 300;
+        let claims = verifier
+            .verify(cose_ear(&key, "ear-key", exp))
+            .await
+            .unwrap();
+        assert!(claims["submods"]["cpu0"].is_object());
+
+        assert!(verifier
+            .verify(cose_ear(&key, "other-key", exp))
+            .await
+            .is_err());
+        let other = SigningKey::random(&mut rand::rngs::OsRng);
+        assert!(verifier
+            .verify(cose_ear(&other, "ear-key", exp))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_jwks_on_unknown_kid() {
+        use crate::token::cose::tests::cose_ear;
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        use base64::Engine;
+        use p256::ecdsa::SigningKey;
+        use p256::elliptic_curve::sec1::ToEncodedPoint;
+        use std::time::{Duration, Instant};
+
+        let jwk = |key: &SigningKey, kid: &str| {
+            let point = key.verifying_key().to_encoded_point(false);
+            serde_json::json!({
+                "kty": "EC",
+                "crv": "P-256",
+                "alg": "ES256",
+                "kid": kid,
+                "x": URL_SAFE_NO_PAD.encode(point.x().unwrap()),
+                "y": URL_SAFE_NO_PAD.encode(point.y().unwrap()),
+            })
+        };
+        let current = SigningKey::random(&mut rand::rngs::OsRng);
+        let next = SigningKey::random(&mut rand::rngs::OsRng);
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let jwks_file = tmp_dir.path().join("as.jwks");
+        std::fs::write(
+            &jwks_file,
+            serde_json::json!({"keys": [jwk(&current, "current")]}).to_string(),
+        )
+        .unwrap();
+
+        let verifier = JwkAttestationTokenVerifier::new(&AttestationTokenVerifierConfig {
+            trusted_jwk_sets: vec![format!("file://{}", jwks_file.display())],
+            jwks_refresh_interval_secs: 3600,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // The AS publishes the next key.
+        std::fs::write(
+            &jwks_file,
+            serde_json::json!({"keys": [jwk(&current, "current"), jwk(&next, "next")]}).to_string(),
+        )
+        .unwrap();
+
+        let exp = time::OffsetDateTime::now_utc().unix_timestamp() + 300;
+        // Fetched just now, so the refresh is rate limited.
+        assert!(verifier.verify(cose_ear(&next, "next", exp)).await.is_err());
+
+        *verifier.jwk_sets_fetched_at.lock().await = Instant::now() - Duration::from_secs(3600);
+        verifier.verify(cose_ear(&next, "next", exp)).await.unwrap();
+        verifier
+            .verify(cose_ear(&current, "current", exp))
+            .await
+            .unwrap();
+    }
 }
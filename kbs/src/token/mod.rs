@@ -5,18 +5,26 @@
 use jwk::JwkAttestationTokenVerifier;
 use kbs_types::TeePubKey;
 use log::debug;
+use revocation::{RevocationCheckConfig, RevocationChecker};
 use serde::Deserialize;
 use serde_json::Value;
+use std::sync::Arc;
 
+mod cose;
 mod error;
 pub(crate) mod jwk;
+pub mod revocation;
 pub use error::*;
 
 pub const TOKEN_TEE_PUBKEY_PATH_COCO: &str = "/customized_claims/runtime_data/tee-pubkey";
 pub const TOKEN_TEE_PUBKEY_PATH_EAR: &str =
     "/submods/cpu0/ear.veraison.annotated-evidence/runtime_data_claims/tee-pubkey";
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+fn default_jwks_refresh_interval_secs() -> u64 {
+    60
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct AttestationTokenVerifierConfig {
     #[serde(default)]
     /// The paths to the tee public key in the JWT body. For example,
@@ -41,6 +49,14 @@ pub struct AttestationTokenVerifierConfig {
     #[serde(default)]
     pub trusted_jwk_sets: Vec<String>,
 
+    /// Minimum seconds between two fetches of `trusted_jwk_sets` triggered
+    /// by a token whose `kid` is not in the trusted JWK sets, e.g. after the
+    /// AS rotated its signing key. 0 disables the refresh.
+    ///
+    /// Default: 60
+    #[serde(default = "default_jwks_refresh_interval_secs")]
+    pub jwks_refresh_interval_secs: u64,
+
     /// Whether the token signing key is (not) validated.
     /// If true, the attestation token can be modified in flight.
     /// This should only be set to true for testing.
@@ -53,20 +69,61 @@ pub struct AttestationTokenVerifierConfig {
     /// Default: false
     #[serde(default = "bool::default")]
     pub insecure_key: bool,
+
+    /// Check the `jti` of every attestation token against the revocation
+    /// list of the AS. Tokens without a `jti` are refused when set.
+    ///
+    /// Default: not checked
+    #[serde(default)]
+    pub revocation: Option<RevocationCheckConfig>,
+}
+
+impl Default for AttestationTokenVerifierConfig {
+    fn default() -> Self {
+        Self {
+            extra_teekey_paths: Vec::new(),
+            trusted_certs_paths: Vec::new(),
+            trusted_jwk_sets: Vec::new(),
+            jwks_refresh_interval_secs: default_jwks_refresh_interval_secs(),
+            insecure_key: false,
+            revocation: None,
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct TokenVerifier {
     verifier: JwkAttestationTokenVerifier,
     extra_teekey_paths: Vec<String>,
+    revocation: Option<Arc<RevocationChecker>>,
 }
 
 impl TokenVerifier {
     pub async fn verify(&self, token: String) -> Result<Value> {
-        self.verifier
+        let claims = self
+            .verifier
             .verify(token)
             .await
-            .map_err(|e| Error::TokenVerificationFailed { source: e })
+            .map_err(|e| Error::TokenVerificationFailed { source: e })?;
+
+        if let Some(revocation) = &self.revocation {
+            let jti = claims["jti"]
+                .as_str()
+                .ok_or(Error::TokenRevocationCheckFailed {
+                    source: anyhow::anyhow!("Attestation Token has no jti"),
+                })?;
+            let revoked = revocation
+                .is_revoked(jti)
+                .await
+                .map_err(|e| Error::TokenRevocationCheckFailed { source: e })?;
+            if revoked {
+                return Err(Error::TokenRevoked {
+                    jti: jti.to_string(),
+                });
+            }
+        }
+
+        Ok(claims)
     }
 
     pub async fn from_config(config: AttestationTokenVerifierConfig) -> Result<Self> {
@@ -78,9 +135,15 @@ impl TokenVerifier {
         extra_teekey_paths.push(TOKEN_TEE_PUBKEY_PATH_COCO.into());
         extra_teekey_paths.push(TOKEN_TEE_PUBKEY_PATH_EAR.into());
 
+        let revocation = config
+            .revocation
+            .as_ref()
+            .map(|config| Arc::new(RevocationChecker::new(config)));
+
         Ok(Self {
             verifier,
             extra_teekey_paths,
+            revocation,
         })
     }
 
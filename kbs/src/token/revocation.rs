@@ -0,0 +1,202 @@
+// Copyright (c) 2024 by Alibaba.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Check of attestation tokens against the revocation list of the AS.
+//!
+//! The list is fetched from `GET /revocations` of the restful AS and cached
+//! for `cache_ttl_secs`. When a refresh fails the cached list is kept in use
+//! and the refresh is retried on the next check; without any list the token
+//! is refused. Checks made while another one refreshes the list use the
+//! cached list rather than wait for the fetch.
+
+use std::collections::HashSet;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use log::warn;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+fn default_cache_ttl_secs() -> u64 {
+    60
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RevocationCheckConfig {
+    /// URL of the revocation list, e.g. `https://as:8080/revocations`.
+    pub url: String,
+
+    /// Seconds a fetched revocation list is used before it is fetched again.
+    ///
+    /// Default: 60
+    #[serde(default = "default_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+}
+
+#[derive(Deserialize)]
+struct RevokedToken {
+    jti: String,
+}
+
+#[derive(Deserialize)]
+struct RevocationEntry {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    jti: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RevocationList {
+    #[serde(default)]
+    entries: Vec<RevocationEntry>,
+    #[serde(default)]
+    revoked_tokens: Vec<RevokedToken>,
+}
+
+impl RevocationList {
+    fn revoked_jtis(self) -> HashSet<String> {
+        let by_rule = self
+            .entries
+            .into_iter()
+            .filter(|entry| entry.kind == "token")
+            .filter_map(|entry| entry.jti);
+        self.revoked_tokens
+            .into_iter()
+            .map(|token| token.jti)
+            .chain(by_rule)
+            .collect()
+    }
+}
+
+struct Cached {
+    fetched_at: Instant,
+    revoked: HashSet<String>,
+}
+
+pub struct RevocationChecker {
+    client: reqwest::Client,
+    url: String,
+    ttl: Duration,
+    cache: RwLock<Option<Cached>>,
+    /// Held while the list is fetched, so that one check fetches it at a
+    /// time. The cache itself is never locked across the fetch.
+    refresh: Mutex<()>,
+}
+
+impl RevocationChecker {
+    pub fn new(config: &RevocationCheckConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: config.url.clone(),
+            ttl: Duration::from_secs(config.cache_ttl_secs),
+            cache: RwLock::new(None),
+            refresh: Mutex::new(()),
+        }
+    }
+
+    /// Look `jti` up in the cached list: whether the list is fresh and
+    /// whether it revokes the token. `None` without a list.
+    fn lookup(&self, jti: &str) -> Option<(bool, bool)> {
+        let cache = self.cache.read().expect("revocation cache lock poisoned");
+        cache.as_ref().map(|cached| {
+            (
+                cached.fetched_at.elapsed() < self.ttl,
+                cached.revoked.contains(jti),
+            )
+        })
+    }
+
+    async fn fetch(&self) -> Result<HashSet<String>> {
+        let list: RevocationList = self
+            .client
+            .get(&self.url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("fetch revocation list from {}", self.url))?
+            .json()
+            .await
+            .context("parse revocation list")?;
+        Ok(list.revoked_jtis())
+    }
+
+    /// Whether the token `jti` has been revoked.
+    pub async fn is_revoked(&self, jti: &str) -> Result<bool> {
+        let cached = self.lookup(jti);
+        if let Some((true, revoked)) = cached {
+            return Ok(revoked);
+        }
+        let _refresh = match (cached, self.refresh.try_lock()) {
+            (_, Ok(guard)) => guard,
+            (Some((_, revoked)), Err(_)) => return Ok(revoked),
+            (None, Err(_)) => self.refresh.lock().await,
+        };
+        // Another check may have refreshed the list meanwhile.
+        if let Some((true, revoked)) = self.lookup(jti) {
+            return Ok(revoked);
+        }
+
+        match self.fetch().await {
+            Ok(revoked) => {
+                let is_revoked = revoked.contains(jti);
+                *self.cache.write().expect("revocation cache lock poisoned") = Some(Cached {
+                    fetched_at: Instant::now(),
+                    revoked,
+                });
+                Ok(is_revoked)
+            }
+            Err(e) => match self.lookup(jti) {
+                Some((_, revoked)) => {
+                    warn!("Using the cached revocation list: {e:#}");
+                    Ok(revoked)
+                }
+                None => Err(e),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_revoked_jtis() {
+        let list: RevocationList = serde_json::from_str(
+            r#"{
+                "entries": [
+                    {"type": "token", "jti": "a", "revoked_at": 1},
+                    {"type": "tee_identity", "claim": "chip_id", "value": "c", "revoked_at": 1}
+                ],
+                "revoked_tokens": [{"jti": "b", "exp": 2}]
+            }"#,
+        )
+        .unwrap();
+
+        let revoked = list.revoked_jtis();
+        assert_eq!(revoked, HashSet::from(["a".to_string(), "b".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn uses_the_cached_list_while_it_is_refreshed() {
+        let checker = RevocationChecker::new(&RevocationCheckConfig {
+            url: "http://127.0.0.1:9/revocations".to_string(),
+            cache_ttl_secs: 0,
+        });
+        *checker.cache.write().unwrap() = Some(Cached {
+            fetched_at: Instant::now(),
+            revoked: HashSet::from(["a".to_string()]),
+        });
+
+        let refresh = checker.refresh.lock().await;
+        assert!(checker.is_revoked("a").await.unwrap());
+        assert!(!checker.is_revoked("b").await.unwrap());
+        drop(refresh);
+
+        // A failed refresh falls back to the cached list.
+        assert!(checker.is_revoked("a").await.unwrap());
+    }
+}
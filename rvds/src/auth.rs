@@ -0,0 +1,540 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use actix_web::HttpRequest;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use log::{debug, warn};
+use reqwest::Client;
+use serde::Deserialize;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::config::AuthConfig;
+use crate::error::ApiError;
+
+/// Header carrying the id of the shared secret used to sign a publish request.
+pub const KEY_ID_HEADER: &str = "X-RVDS-Key-Id";
+/// Header carrying the unix timestamp (seconds) covered by the request signature.
+pub const TIMESTAMP_HEADER: &str = "X-RVDS-Timestamp";
+/// Header carrying the hex encoded HMAC-SHA256 over `"{timestamp}.{body}"`.
+pub const SIGNATURE_HEADER: &str = "X-RVDS-Signature";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Identity of an authenticated publisher, used for logging and auditing.
+#[derive(Debug, Clone)]
+pub enum Publisher {
+    /// Publisher authentication is disabled.
+    Anonymous,
+    /// Request signed with a configured shared secret.
+    SharedKey(String),
+    /// Request carrying an OIDC ID token from a trusted issuer.
+    Oidc { issuer: String, subject: String },
+}
+
+impl std::fmt::Display for Publisher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Publisher::Anonymous => write!(f, "anonymous"),
+            Publisher::SharedKey(key_id) => write!(f, "key:{key_id}"),
+            Publisher::Oidc { issuer, subject } => write!(f, "oidc:{issuer}#{subject}"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UnverifiedClaims {
+    iss: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OidcClaims {
+    sub: String,
+}
+
+#[derive(Deserialize)]
+struct OidcDiscovery {
+    jwks_uri: String,
+}
+
+/// Authenticates publishers (signed requests or OIDC tokens) and administrators.
+pub struct Authenticator {
+    cfg: AuthConfig,
+    http_client: Client,
+    /// JWKS of each trusted issuer, refreshed when an unknown `kid` shows up.
+    jwks: RwLock<HashMap<String, JwkSet>>,
+    /// When the JWKS of each issuer was last fetched, successfully or not.
+    /// Used to rate limit the refresh on an unknown `kid`.
+    jwks_fetched_at: Mutex<HashMap<String, Instant>>,
+}
+
+impl Authenticator {
+    pub fn new(cfg: AuthConfig, http_client: Client) -> Self {
+        if !cfg.publisher_auth_enabled() {
+            warn!("No publisher authentication configured; publish events are accepted from any caller.");
+        }
+        if cfg.admin_token.is_none() {
+            warn!("RVDS_ADMIN_TOKEN is not set; subscription management is open to any caller.");
+        }
+
+        Self {
+            cfg,
+            http_client,
+            jwks: RwLock::new(HashMap::new()),
+            jwks_fetched_at: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Authenticate the caller of subscription management endpoints.
+    pub fn authorize_admin(&self, req: &HttpRequest) -> Result<(), ApiError> {
+        let Some(expected) = &self.cfg.admin_token else {
+            return Ok(());
+        };
+
+        let token = bearer_token(req)
+            .ok_or_else(|| ApiError::Unauthorized("missing bearer token".to_string()))?;
+        if bool::from(token.as_bytes().ct_eq(expected.as_bytes())) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden("invalid admin token".to_string()))
+        }
+    }
+
+    /// Authenticate a trustee pulling the event log. Both the events token and
+    /// the admin token are accepted.
+    pub fn authorize_reader(&self, req: &HttpRequest) -> Result<(), ApiError> {
+        let accepted: Vec<&String> = [&self.cfg.events_token, &self.cfg.admin_token]
+            .into_iter()
+            .flatten()
+            .collect();
+        if accepted.is_empty() {
+            return Ok(());
+        }
+
+        let token = bearer_token(req)
+            .ok_or_else(|| ApiError::Unauthorized("missing bearer token".to_string()))?;
+        if accepted
+            .iter()
+            .any(|expected| bool::from(token.as_bytes().ct_eq(expected.as_bytes())))
+        {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden("invalid events token".to_string()))
+        }
+    }
+
+    /// Authenticate the caller of the publish endpoint against the raw request body.
+    pub async fn authorize_publisher(
+        &self,
+        req: &HttpRequest,
+        body: &[u8],
+    ) -> Result<Publisher, ApiError> {
+        if !self.cfg.publisher_auth_enabled() {
+            return Ok(Publisher::Anonymous);
+        }
+
+        if req.headers().contains_key(SIGNATURE_HEADER) && !self.cfg.publisher_hmac_keys.is_empty()
+        {
+            return self
+                .verify_signed_request(req, body)
+                .map_err(|e| ApiError::Unauthorized(format!("signed request rejected: {e}")));
+        }
+
+        if let Some(token) = bearer_token(req) {
+            if !self.cfg.oidc_issuers.is_empty() {
+                return self
+                    .verify_oidc_token(token)
+                    .await
+                    .map_err(|e| ApiError::Unauthorized(format!("OIDC token rejected: {e}")));
+            }
+        }
+
+        Err(ApiError::Unauthorized(
+            "publisher credentials required".to_string(),
+        ))
+    }
+
+    fn verify_signed_request(&self, req: &HttpRequest, body: &[u8]) -> Result<Publisher> {
+        let key_id = header_str(req, KEY_ID_HEADER)?;
+        let timestamp = header_str(req, TIMESTAMP_HEADER)?;
+        let signature = header_str(req, SIGNATURE_HEADER)?;
+
+        let secret = self
+            .cfg
+            .publisher_hmac_keys
+            .get(key_id)
+            .ok_or_else(|| anyhow!("unknown key id `{key_id}`"))?;
+
+        // Bound the replay window of a captured request.
+        let signed_at: i64 = timestamp.parse().context("parse timestamp")?;
+        let skew = (chrono::Utc::now().timestamp() - signed_at).unsigned_abs();
+        if skew > self.cfg.max_clock_skew.as_secs() {
+            bail!("timestamp outside the accepted window ({skew}s skew)");
+        }
+
+        let signature = hex::decode(signature.trim_start_matches("sha256="))
+            .context("signature is not hex encoded")?;
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).context("init hmac")?;
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        mac.verify_slice(&signature)
+            .map_err(|_| anyhow!("signature mismatch"))?;
+
+        Ok(Publisher::SharedKey(key_id.to_string()))
+    }
+
+    async fn verify_oidc_token(&self, token: &str) -> Result<Publisher> {
+        let header = decode_header(token).context("decode token header")?;
+        if !matches!(
+            header.alg,
+            Algorithm::RS256
+                | Algorithm::RS384
+                | Algorithm::RS512
+                | Algorithm::PS256
+                | Algorithm::PS384
+                | Algorithm::PS512
+                | Algorithm::ES256
+                | Algorithm::ES384
+                | Algorithm::EdDSA
+        ) {
+            bail!("unsupported token algorithm {:?}", header.alg);
+        }
+        let kid = header.kid.context("token header has no kid")?;
+
+        // The issuer selects the JWKS; it is only trusted after signature validation.
+        let raw_issuer = unverified_issuer(token)?;
+        let issuer = raw_issuer.trim_end_matches('/').to_string();
+        if !self.cfg.oidc_issuers.contains(&issuer) {
+            bail!("issuer `{issuer}` is not trusted");
+        }
+
+        let jwk_set = self.issuer_keys(&issuer, &kid).await?;
+        let jwk = jwk_set
+            .find(&kid)
+            .ok_or_else(|| anyhow!("issuer `{issuer}` has no key `{kid}`"))?;
+        let key = DecodingKey::from_jwk(jwk).context("load issuer key")?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[raw_issuer.as_str()]);
+        match &self.cfg.oidc_audience {
+            Some(aud) => validation.set_audience(&[aud.as_str()]),
+            None => validation.validate_aud = false,
+        }
+        let claims = decode::<OidcClaims>(token, &key, &validation)
+            .context("validate token")?
+            .claims;
+
+        if !self.cfg.oidc_subjects.is_empty()
+            && !self
+                .cfg
+                .oidc_subjects
+                .iter()
+                .any(|pattern| subject_matches(pattern, &claims.sub))
+        {
+            bail!("subject `{}` is not allowed to publish", claims.sub);
+        }
+
+        Ok(Publisher::Oidc {
+            issuer,
+            subject: claims.sub,
+        })
+    }
+
+    /// Return the cached JWKS of `issuer`, refetching it when `kid` is
+    /// unknown, at most once per `oidc_jwks_refresh_interval`.
+    async fn issuer_keys(&self, issuer: &str, kid: &str) -> Result<JwkSet> {
+        if let Some(set) = self.jwks.read().await.get(issuer) {
+            if set.find(kid).is_some() {
+                return Ok(set.clone());
+            }
+        }
+
+        // Held across the fetch, so that concurrent requests fetch once.
+        let mut fetched_at = self.jwks_fetched_at.lock().await;
+        if let Some(at) = fetched_at.get(issuer) {
+            if at.elapsed() < self.cfg.oidc_jwks_refresh_interval {
+                return self
+                    .jwks
+                    .read()
+                    .await
+                    .get(issuer)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("JWKS of issuer `{issuer}` is unavailable"));
+            }
+        }
+        fetched_at.insert(issuer.to_string(), Instant::now());
+
+        debug!("Fetching JWKS for issuer {issuer}");
+        let discovery: OidcDiscovery = self
+            .http_client
+            .get(format!("{issuer}/.well-known/openid-configuration"))
+            .send()
+            .await
+            .context("fetch OIDC discovery document")?
+            .error_for_status()
+            .context("OIDC discovery status")?
+            .json()
+            .await
+            .context("parse OIDC discovery document")?;
+        let set: JwkSet = self
+            .http_client
+            .get(&discovery.jwks_uri)
+            .send()
+            .await
+            .context("fetch JWKS")?
+            .error_for_status()
+            .context("JWKS status")?
+            .json()
+            .await
+            .context("parse JWKS")?;
+
+        self.jwks
+            .write()
+            .await
+            .insert(issuer.to_string(), set.clone());
+        Ok(set)
+    }
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get("Authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+fn header_str<'a>(req: &'a HttpRequest, name: &str) -> Result<&'a str> {
+    req.headers()
+        .get(name)
+        .ok_or_else(|| anyhow!("missing {name} header"))?
+        .to_str()
+        .with_context(|| format!("invalid {name} header"))
+}
+
+fn unverified_issuer(token: &str) -> Result<String> {
+    let payload = token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| anyhow!("malformed token"))?;
+    let raw = URL_SAFE_NO_PAD
+        .decode(payload)
+        .context("decode token payload")?;
+    let claims: UnverifiedClaims = serde_json::from_slice(&raw).context("parse token payload")?;
+    claims.iss.ok_or_else(|| anyhow!("token has no iss claim"))
+}
+
+fn subject_matches(pattern: &str, subject: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => subject.starts_with(prefix),
+        None => pattern == subject,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use actix_web::test::TestRequest;
+    use base64::engine::general_purpose::STANDARD;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    use super::*;
+
+    const ISSUER: &str = "https://token.actions.example.com";
+    const AUDIENCE: &str = "rvds";
+    const KID: &str = "test-key";
+    /// PKCS#8 DER of the P-256 key signing the test tokens.
+    const SIGNING_KEY: &str = "MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQgrGL+p9QXs9UI+qHQi3QVM7rNiSpTWF3ombiZBmpG5IWhRANCAAT7wzPzrvxe1m//jhoyBQcwErqtmUcg3cdFmouaHMZ4KSKYp/YGkNTowJGdB3YivVhIffTWaJjzx083WQ07Xzzk";
+
+    fn authenticator() -> Authenticator {
+        authenticator_for(ISSUER)
+    }
+
+    fn authenticator_for(issuer: &str) -> Authenticator {
+        let cfg = AuthConfig {
+            publisher_hmac_keys: HashMap::from([("ci".to_string(), "secret".to_string())]),
+            oidc_issuers: vec![issuer.to_string()],
+            oidc_audience: Some(AUDIENCE.to_string()),
+            oidc_subjects: vec!["repo:org/repo:*".to_string()],
+            oidc_jwks_refresh_interval: Duration::from_secs(60),
+            admin_token: None,
+            events_token: None,
+            max_clock_skew: Duration::from_secs(300),
+        };
+        let jwks: JwkSet = serde_json::from_value(json!({
+            "keys": [{
+                "kty": "EC",
+                "crv": "P-256",
+                "kid": KID,
+                "alg": "ES256",
+                "x": "-8Mz8678XtZv_44aMgUHMBK6rZlHIN3HRZqLmhzGeCk",
+                "y": "Ipin9gaQ1OjAkZ0HdiK9WEh99NZomPPHTzdZDTtfPOQ",
+            }]
+        }))
+        .unwrap();
+        Authenticator {
+            cfg,
+            http_client: Client::new(),
+            jwks: RwLock::new(HashMap::from([(issuer.to_string(), jwks)])),
+            jwks_fetched_at: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Serve the OIDC discovery document and the JWKS of the test key on a
+    /// local port. Returns the issuer URL and the number of requests served.
+    async fn stand_in_issuer() -> (String, Arc<AtomicUsize>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let served = Arc::new(AtomicUsize::new(0));
+        let jwks = authenticator().jwks.into_inner().remove(ISSUER).unwrap();
+        let (counter, base) = (served.clone(), issuer.clone());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let body = if request.starts_with("GET /.well-known/openid-configuration ") {
+                    json!({ "jwks_uri": format!("{base}/jwks") }).to_string()
+                } else {
+                    serde_json::to_string(&jwks).unwrap()
+                };
+                counter.fetch_add(1, Ordering::SeqCst);
+                let reply = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+        (issuer, served)
+    }
+
+    fn signed_request(key_id: &str, secret: &str, timestamp: i64, body: &[u8]) -> HttpRequest {
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("{timestamp}.").as_bytes());
+        mac.update(body);
+        TestRequest::default()
+            .insert_header((KEY_ID_HEADER, key_id))
+            .insert_header((TIMESTAMP_HEADER, timestamp.to_string()))
+            .insert_header((SIGNATURE_HEADER, hex::encode(mac.finalize().into_bytes())))
+            .to_http_request()
+    }
+
+    fn token_request(claims: serde_json::Value) -> HttpRequest {
+        token_request_with_kid(claims, KID)
+    }
+
+    fn token_request_with_kid(claims: serde_json::Value, kid: &str) -> HttpRequest {
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(kid.to_string());
+        let key = EncodingKey::from_ec_der(&STANDARD.decode(SIGNING_KEY).unwrap());
+        let token = encode(&header, &claims, &key).unwrap();
+        TestRequest::default()
+            .insert_header(("Authorization", format!("Bearer {token}")))
+            .to_http_request()
+    }
+
+    fn claims(iss: &str, aud: &str, sub: &str, exp: i64) -> serde_json::Value {
+        json!({ "iss": iss, "aud": aud, "sub": sub, "exp": exp })
+    }
+
+    #[actix_web::test]
+    async fn signed_requests() {
+        let auth = authenticator();
+        let body = br#"{"type":"sample"}"#;
+        let now = chrono::Utc::now().timestamp();
+
+        let publisher = auth
+            .authorize_publisher(&signed_request("ci", "secret", now, body), body)
+            .await
+            .unwrap();
+        assert_eq!(publisher.to_string(), "key:ci");
+
+        // Wrong secret, tampered body, unknown key id and stale timestamp
+        for (req, body) in [
+            (signed_request("ci", "other", now, body), &body[..]),
+            (signed_request("ci", "secret", now, body), b"{}"),
+            (signed_request("unknown", "secret", now, body), &body[..]),
+            (signed_request("ci", "secret", now - 600, body), &body[..]),
+        ] {
+            assert!(matches!(
+                auth.authorize_publisher(&req, body).await,
+                Err(ApiError::Unauthorized(_))
+            ));
+        }
+
+        // No credentials at all
+        assert!(auth
+            .authorize_publisher(&TestRequest::default().to_http_request(), body)
+            .await
+            .is_err());
+    }
+
+    #[actix_web::test]
+    async fn oidc_tokens() {
+        let auth = authenticator();
+        let exp = chrono::Utc::now().timestamp() + 600;
+        let subject = "repo:org/repo:ref:refs/heads/main";
+
+        let publisher = auth
+            .authorize_publisher(&token_request(claims(ISSUER, AUDIENCE, subject, exp)), b"")
+            .await
+            .unwrap();
+        assert_eq!(publisher.to_string(), format!("oidc:{ISSUER}#{subject}"));
+
+        // Expired, wrong audience, untrusted issuer and unknown subject
+        for claims in [
+            claims(ISSUER, AUDIENCE, subject, exp - 3600),
+            claims(ISSUER, "other", subject, exp),
+            claims("https://evil.example.com", AUDIENCE, subject, exp),
+            claims(ISSUER, AUDIENCE, "repo:other/repo:ref:refs/heads/main", exp),
+        ] {
+            assert!(matches!(
+                auth.authorize_publisher(&token_request(claims), b"").await,
+                Err(ApiError::Unauthorized(_))
+            ));
+        }
+    }
+
+    #[actix_web::test]
+    async fn jwks_refresh_is_rate_limited() {
+        let (issuer, served) = stand_in_issuer().await;
+        let mut auth = authenticator_for(&issuer);
+        auth.jwks = RwLock::new(HashMap::new());
+        let exp = chrono::Utc::now().timestamp() + 600;
+        let subject = "repo:org/repo:ref:refs/heads/main";
+
+        auth.authorize_publisher(&token_request(claims(&issuer, AUDIENCE, subject, exp)), b"")
+            .await
+            .unwrap();
+        assert_eq!(served.load(Ordering::SeqCst), 2);
+
+        // An unknown kid right after the fetch does not refetch the JWKS.
+        let unknown = || token_request_with_kid(claims(&issuer, AUDIENCE, subject, exp), "next");
+        for _ in 0..3 {
+            assert!(auth.authorize_publisher(&unknown(), b"").await.is_err());
+        }
+        assert_eq!(served.load(Ordering::SeqCst), 2);
+
+        auth.jwks_fetched_at
+            .lock()
+            .await
+            .insert(issuer.clone(), Instant::now() - Duration::from_secs(60));
+        assert!(auth.authorize_publisher(&unknown(), b"").await.is_err());
+        assert_eq!(served.load(Ordering::SeqCst), 4);
+    }
+}
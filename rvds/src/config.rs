@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::env;
 use std::path::PathBuf;
 use std::time::Duration;
@@ -18,6 +19,34 @@ pub struct LedgerConfig {
     pub eth_gateway_api_key: Option<String>,
 }
 
+#[derive(Clone, Debug)]
+pub struct AuthConfig {
+    /// Shared secrets for HMAC-signed publisher requests, keyed by key id.
+    pub publisher_hmac_keys: HashMap<String, String>,
+    /// OIDC issuers whose ID tokens are accepted from CI publishers.
+    pub oidc_issuers: Vec<String>,
+    /// Expected `aud` claim of publisher OIDC tokens.
+    pub oidc_audience: Option<String>,
+    /// Allowed `sub` claims of publisher OIDC tokens; a trailing `*` matches a prefix.
+    pub oidc_subjects: Vec<String>,
+    /// Minimum time between two fetches of the JWKS of an issuer, triggered
+    /// by a token whose `kid` is not in the cached one.
+    pub oidc_jwks_refresh_interval: Duration,
+    /// Bearer token required for subscription management.
+    pub admin_token: Option<String>,
+    /// Bearer token accepted, besides the admin token, for pulling the event log.
+    pub events_token: Option<String>,
+    /// Maximum accepted distance between a signed request timestamp and now.
+    pub max_clock_skew: Duration,
+}
+
+impl AuthConfig {
+    /// Whether any publisher authentication method is configured.
+    pub fn publisher_auth_enabled(&self) -> bool {
+        !self.publisher_hmac_keys.is_empty() || !self.oidc_issuers.is_empty()
+    }
+}
+
 /// Application level configuration loaded from environment variables.
 #[derive(Clone, Debug)]
 pub struct AppConfig {
@@ -25,6 +54,7 @@ pub struct AppConfig {
     pub data_dir: PathBuf,
     pub request_timeout: Duration,
     pub ledger: LedgerConfig,
+    pub auth: AuthConfig,
 }
 
 impl AppConfig {
@@ -57,6 +87,37 @@ impl AppConfig {
         let eth_gateway_endpoint = env::var("RVDS_LEDGER_ETH_GATEWAY").ok();
         let eth_gateway_api_key = env::var("RVDS_LEDGER_ETH_GATEWAY_API_KEY").ok();
 
+        // Publisher keys are given as `key_id=secret` pairs separated by commas.
+        let mut publisher_hmac_keys = HashMap::new();
+        for pair in split_list(env::var("RVDS_PUBLISHER_HMAC_KEYS").ok()) {
+            let (key_id, secret) = pair.split_once('=').ok_or_else(|| {
+                anyhow!("RVDS_PUBLISHER_HMAC_KEYS entry `{pair}` is not key_id=secret")
+            })?;
+            if key_id.is_empty() || secret.is_empty() {
+                return Err(anyhow!(
+                    "RVDS_PUBLISHER_HMAC_KEYS entry `{pair}` has an empty key id or secret"
+                ));
+            }
+            publisher_hmac_keys.insert(key_id.to_string(), secret.to_string());
+        }
+
+        let oidc_issuers = split_list(env::var("RVDS_PUBLISHER_OIDC_ISSUERS").ok())
+            .into_iter()
+            .map(|issuer| issuer.trim_end_matches('/').to_string())
+            .collect();
+        let oidc_audience = env::var("RVDS_PUBLISHER_OIDC_AUDIENCE").ok();
+        let oidc_subjects = split_list(env::var("RVDS_PUBLISHER_OIDC_SUBJECTS").ok());
+        let oidc_jwks_refresh_secs: u64 = env::var("RVDS_PUBLISHER_OIDC_JWKS_REFRESH_SECS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()
+            .context("parse RVDS_PUBLISHER_OIDC_JWKS_REFRESH_SECS")?;
+        let admin_token = env::var("RVDS_ADMIN_TOKEN").ok().filter(|t| !t.is_empty());
+        let events_token = env::var("RVDS_EVENTS_TOKEN").ok().filter(|t| !t.is_empty());
+        let max_clock_skew_secs: u64 = env::var("RVDS_SIGNATURE_MAX_SKEW_SECS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse()
+            .context("parse RVDS_SIGNATURE_MAX_SKEW_SECS")?;
+
         Ok(Self {
             listen_addr,
             data_dir,
@@ -68,6 +129,26 @@ impl AppConfig {
                 eth_gateway_endpoint,
                 eth_gateway_api_key,
             },
+            auth: AuthConfig {
+                publisher_hmac_keys,
+                oidc_issuers,
+                oidc_audience,
+                oidc_subjects,
+                oidc_jwks_refresh_interval: Duration::from_secs(oidc_jwks_refresh_secs),
+                admin_token,
+                events_token,
+                max_clock_skew: Duration::from_secs(max_clock_skew_secs),
+            },
         })
     }
 }
+
+/// Split a comma separated environment value into trimmed, non-empty items.
+fn split_list(raw: Option<String>) -> Vec<String> {
+    raw.unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
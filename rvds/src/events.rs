@@ -0,0 +1,239 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::ledger::LedgerReceipt;
+
+/// A publish event as kept in the RVDS event log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// Monotonic sequence number, starting at 1.
+    pub seq: u64,
+    pub recorded_at: DateTime<Utc>,
+    pub artifact_type: String,
+    /// Hex SHA-256 of the canonical payload (the event without `audit_proof`).
+    pub payload_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ledger_receipt: Option<LedgerReceipt>,
+    /// RVPS message envelope, exactly as forwarded to trustees.
+    pub message: String,
+}
+
+/// Append-only event log persisted as JSON lines, so that trustees can pull
+/// events they missed while they were not subscribed or offline.
+pub struct EventLog {
+    path: PathBuf,
+    records: RwLock<Vec<EventRecord>>,
+}
+
+impl EventLog {
+    /// Open the log at `path`, replaying previously persisted records.
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let records = Self::load(&path)?;
+        Ok(Self {
+            path,
+            records: RwLock::new(records),
+        })
+    }
+
+    /// Append a new event and return the persisted record.
+    pub async fn append(
+        &self,
+        artifact_type: String,
+        payload_hash: String,
+        ledger_receipt: Option<LedgerReceipt>,
+        message: String,
+    ) -> Result<EventRecord> {
+        // Holding the write lock across the file write keeps sequence numbers
+        // and on-disk order consistent.
+        let mut guard = self.records.write().await;
+        let record = EventRecord {
+            seq: guard.last().map(|r| r.seq + 1).unwrap_or(1),
+            recorded_at: Utc::now(),
+            artifact_type,
+            payload_hash,
+            ledger_receipt,
+            message,
+        };
+
+        let mut line = serde_json::to_string(&record).context("serialize event record")?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context("open event log")?;
+        file.write_all(line.as_bytes())
+            .context("append event log")?;
+        // The event is acknowledged to the publisher once on disk.
+        file.sync_data().context("sync event log")?;
+
+        guard.push(record.clone());
+        Ok(record)
+    }
+
+    /// Return up to `limit` events with a sequence number greater than `since`,
+    /// optionally restricted to the given artifact types.
+    pub async fn since(
+        &self,
+        since: u64,
+        limit: usize,
+        artifact_types: &[String],
+    ) -> Vec<EventRecord> {
+        let guard = self.records.read().await;
+        // Sequence numbers are dense, so the start index is derived directly.
+        let start = usize::try_from(since)
+            .unwrap_or(usize::MAX)
+            .min(guard.len());
+        guard[start..]
+            .iter()
+            .filter(|r| {
+                artifact_types.is_empty()
+                    || artifact_types
+                        .iter()
+                        .any(|t| t.eq_ignore_ascii_case(&r.artifact_type))
+            })
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Sequence number of the newest event, or 0 if the log is empty.
+    pub async fn latest_seq(&self) -> u64 {
+        self.records.read().await.last().map(|r| r.seq).unwrap_or(0)
+    }
+
+    fn load(path: &Path) -> Result<Vec<EventRecord>> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut raw = fs::read_to_string(path).context("read event log")?;
+        // Every record is written with its newline at once, so a last line
+        // without one is an append torn by a crash, never acknowledged.
+        // Drop it so that the next append starts on a line of its own.
+        let complete = raw.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        if complete < raw.len() {
+            warn!(
+                "Dropping a torn record of {} byte(s) at the end of the event log",
+                raw.len() - complete
+            );
+            OpenOptions::new()
+                .write(true)
+                .open(path)
+                .and_then(|file| {
+                    file.set_len(complete as u64)?;
+                    file.sync_data()
+                })
+                .context("truncate event log")?;
+            raw.truncate(complete);
+        }
+
+        let mut records = Vec::new();
+        for (idx, line) in raw.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: EventRecord = serde_json::from_str(line)
+                .with_context(|| format!("parse event log line {}", idx + 1))?;
+            if record.seq != records.len() as u64 + 1 {
+                anyhow::bail!(
+                    "event log is not contiguous at line {}: expected seq {}, found {}",
+                    idx + 1,
+                    records.len() + 1,
+                    record.seq
+                );
+            }
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn append(log: &EventLog, artifact_type: &str) -> EventRecord {
+        log.append(
+            artifact_type.to_string(),
+            "00".repeat(32),
+            None,
+            format!("{{\"type\":\"{artifact_type}\"}}"),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn append_persists_records_in_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("events.jsonl");
+
+        let log = EventLog::open(path.clone()).unwrap();
+        assert_eq!(log.latest_seq().await, 0);
+        assert_eq!(append(&log, "rpm").await.seq, 1);
+        assert_eq!(append(&log, "oci").await.seq, 2);
+
+        let reopened = EventLog::open(path).unwrap();
+        assert_eq!(reopened.latest_seq().await, 2);
+        let records = reopened.since(0, 10, &[]).await;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].artifact_type, "oci");
+        assert_eq!(records[1].message, r#"{"type":"oci"}"#);
+        assert_eq!(append(&reopened, "rpm").await.seq, 3);
+    }
+
+    #[tokio::test]
+    async fn since_pages_and_filters() {
+        let tmp = tempfile::tempdir().unwrap();
+        let log = EventLog::open(tmp.path().join("events.jsonl")).unwrap();
+        for artifact_type in ["rpm", "oci", "rpm", "oci", "rpm"] {
+            append(&log, artifact_type).await;
+        }
+
+        let seqs = |records: Vec<EventRecord>| records.iter().map(|r| r.seq).collect::<Vec<_>>();
+        assert_eq!(seqs(log.since(0, 2, &[]).await), [1, 2]);
+        assert_eq!(seqs(log.since(2, 2, &[]).await), [3, 4]);
+        assert_eq!(seqs(log.since(4, 10, &[]).await), [5]);
+        assert!(log.since(5, 10, &[]).await.is_empty());
+        assert!(log.since(u64::MAX, 10, &[]).await.is_empty());
+        assert_eq!(seqs(log.since(1, 10, &["RPM".to_string()]).await), [3, 5]);
+    }
+
+    #[tokio::test]
+    async fn load_drops_torn_tail() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("events.jsonl");
+        {
+            let log = EventLog::open(path.clone()).unwrap();
+            append(&log, "rpm").await;
+            append(&log, "oci").await;
+        }
+        let intact = fs::read_to_string(&path).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"seq":3,"recorded_at":"#).unwrap();
+
+        let log = EventLog::open(path.clone()).unwrap();
+        assert_eq!(log.latest_seq().await, 2);
+        assert_eq!(fs::read_to_string(&path).unwrap(), intact);
+
+        assert_eq!(append(&log, "rpm").await.seq, 3);
+        let reopened = EventLog::open(path).unwrap();
+        assert_eq!(reopened.latest_seq().await, 3);
+    }
+
+    #[test]
+    fn load_rejects_corrupt_lines() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("events.jsonl");
+        fs::write(&path, "not json\n").unwrap();
+        assert!(EventLog::open(path).is_err());
+    }
+}
@@ -1,5 +1,7 @@
+mod auth;
 mod config;
 mod error;
+mod events;
 mod ledger;
 mod models;
 mod routes;
@@ -26,6 +28,12 @@ async fn run() -> anyhow::Result<()> {
     let cfg = config::AppConfig::from_env()?;
     let bind_addr = cfg.listen_addr.clone();
     let state = state::AppState::initialize(&cfg).await?;
+    let authenticator = web::Data::new(auth::Authenticator::new(
+        cfg.auth.clone(),
+        reqwest::Client::builder()
+            .timeout(cfg.request_timeout)
+            .build()?,
+    ));
 
     info!(
         "RVDS starting on {} with data dir {:?}",
@@ -36,6 +44,7 @@ async fn run() -> anyhow::Result<()> {
         App::new()
             .app_data(web::Data::new(cfg.clone()))
             .app_data(web::Data::new(state.clone()))
+            .app_data(authenticator.clone())
             .configure(routes::init_routes)
     })
     .bind(bind_addr)?
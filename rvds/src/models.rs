@@ -17,6 +17,9 @@ pub struct AuditProof {
 #[derive(Debug, Deserialize)]
 pub struct SubscribeRequest {
     pub trustee_url: Vec<String>,
+    /// Artifact types the trustees want to receive; empty means every type.
+    #[serde(default)]
+    pub artifact_types: Vec<String>,
 }
 
 impl SubscribeRequest {
@@ -29,6 +32,42 @@ impl SubscribeRequest {
     }
 }
 
+/// Remove trustee subscription request body.
+#[derive(Debug, Deserialize)]
+pub struct UnsubscribeRequest {
+    pub trustee_url: Vec<String>,
+}
+
+impl UnsubscribeRequest {
+    /// Validate basic constraints for unsubscription input.
+    pub fn validate(&self) -> Result<()> {
+        if self.trustee_url.is_empty() {
+            return Err(anyhow!("trustee_url cannot be empty"));
+        }
+        Ok(())
+    }
+}
+
+/// A registered trustee and the artifact types it is interested in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subscriber {
+    pub trustee_url: String,
+    /// Empty means the trustee receives events of every artifact type.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifact_types: Vec<String>,
+}
+
+impl Subscriber {
+    /// Whether events of `artifact_type` should be forwarded to this trustee.
+    pub fn accepts(&self, artifact_type: &str) -> bool {
+        self.artifact_types.is_empty()
+            || self
+                .artifact_types
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(artifact_type))
+    }
+}
+
 /// Publish event payload coming from CI workflows.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PublishEventRequest {
@@ -86,9 +125,41 @@ pub struct SubscribeResponse {
     pub registered: Vec<String>,
 }
 
+/// Response shape for unsubscription endpoint.
+#[derive(Debug, Serialize)]
+pub struct UnsubscribeResponse {
+    pub removed: Vec<String>,
+}
+
+/// Response shape for subscriber listing endpoint.
+#[derive(Debug, Serialize)]
+pub struct SubscriberListResponse {
+    pub subscribers: Vec<Subscriber>,
+}
+
 /// Response shape for publish endpoint.
 #[derive(Debug, Serialize)]
 pub struct PublishResponse {
     pub forwarded: Vec<ForwardResult>,
     pub ledger_receipt: Option<crate::ledger::LedgerReceipt>,
+    /// Sequence number assigned to the event in the event log.
+    pub event_seq: u64,
+}
+
+/// Query parameters of the event pull endpoint.
+#[derive(Debug, Deserialize)]
+pub struct EventsQuery {
+    /// Return events with a sequence number greater than this one.
+    #[serde(default)]
+    pub since: u64,
+    pub limit: Option<usize>,
+    /// Comma separated artifact types to filter on.
+    pub artifact_type: Option<String>,
+}
+
+/// Response shape for the event pull endpoint.
+#[derive(Debug, Serialize)]
+pub struct EventsResponse {
+    pub events: Vec<crate::events::EventRecord>,
+    pub latest_seq: u64,
 }
@@ -1,37 +1,86 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use log::info;
 
+use crate::auth::Authenticator;
 use crate::config::AppConfig;
 use crate::error::ApiError;
-use crate::models::{PublishEventRequest, PublishResponse, SubscribeRequest, SubscribeResponse};
+use crate::models::{
+    EventsQuery, PublishEventRequest, SubscribeRequest, SubscribeResponse, SubscriberListResponse,
+    UnsubscribeRequest, UnsubscribeResponse,
+};
 use crate::state::AppState;
 
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/rvds")
             .route("/subscribe/trustee", web::post().to(subscribe_trustee))
-            .route("/rv-publish-event", web::post().to(rv_publish_event)),
+            .route("/subscribe/trustee", web::get().to(list_trustees))
+            .route("/unsubscribe/trustee", web::post().to(unsubscribe_trustee))
+            .route("/rv-publish-event", web::post().to(rv_publish_event))
+            .route("/events", web::get().to(list_events)),
     );
 }
 
 async fn subscribe_trustee(
-    _cfg: web::Data<AppConfig>,
+    req: HttpRequest,
+    auth: web::Data<Authenticator>,
     state: web::Data<AppState>,
     payload: web::Json<SubscribeRequest>,
 ) -> Result<HttpResponse, ApiError> {
+    auth.authorize_admin(&req)?;
     let added = state.add_trustees(&payload).await?;
     info!("Registered trustees: {:?}", added);
     Ok(HttpResponse::Ok().json(SubscribeResponse { registered: added }))
 }
 
+async fn list_trustees(
+    req: HttpRequest,
+    auth: web::Data<Authenticator>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    auth.authorize_admin(&req)?;
+    let subscribers = state.list_trustees().await;
+    Ok(HttpResponse::Ok().json(SubscriberListResponse { subscribers }))
+}
+
+async fn unsubscribe_trustee(
+    req: HttpRequest,
+    auth: web::Data<Authenticator>,
+    state: web::Data<AppState>,
+    payload: web::Json<UnsubscribeRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.authorize_admin(&req)?;
+    let removed = state.remove_trustees(&payload).await?;
+    info!("Unregistered trustees: {:?}", removed);
+    Ok(HttpResponse::Ok().json(UnsubscribeResponse { removed }))
+}
+
 async fn rv_publish_event(
+    req: HttpRequest,
     _cfg: web::Data<AppConfig>,
+    auth: web::Data<Authenticator>,
+    state: web::Data<AppState>,
+    body: web::Bytes,
+) -> Result<HttpResponse, ApiError> {
+    // Signed requests cover the raw body, so authenticate before parsing it.
+    let publisher = auth.authorize_publisher(&req, &body).await?;
+    let payload: PublishEventRequest = serde_json::from_slice(&body)
+        .map_err(|e| ApiError::Validation(format!("invalid publish event: {e}")))?;
+    info!(
+        "Publish event for artifact type {} from {publisher}",
+        payload.artifact_type
+    );
+
+    let response = state.forward_publish_event(payload).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+async fn list_events(
+    req: HttpRequest,
+    auth: web::Data<Authenticator>,
     state: web::Data<AppState>,
-    payload: web::Json<PublishEventRequest>,
+    query: web::Query<EventsQuery>,
 ) -> Result<HttpResponse, ApiError> {
-    let (results, receipt) = state.forward_publish_event(payload.into_inner()).await?;
-    Ok(HttpResponse::Ok().json(PublishResponse {
-        forwarded: results,
-        ledger_receipt: receipt,
-    }))
+    auth.authorize_reader(&req)?;
+    Ok(HttpResponse::Ok().json(state.list_events(&query).await))
 }
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
@@ -7,23 +7,40 @@ use anyhow::{Context, Result};
 use futures::future::join_all;
 use log::{debug, info, warn};
 use reqwest::Client;
+use sha2::{Digest, Sha256};
 use tokio::sync::RwLock;
 use url::Url;
 
 use crate::config::AppConfig;
 use crate::error::ApiError;
-use crate::ledger::{build_ledger, LedgerAdapter, LedgerReceipt};
+use crate::events::EventLog;
+use crate::ledger::{build_ledger, LedgerAdapter};
 use crate::models::{
-    ForwardResult, PublishEventRequest, RvpsMessageEnvelope, RvpsRegisterRequest, SubscribeRequest,
+    EventsQuery, EventsResponse, ForwardResult, PublishEventRequest, PublishResponse,
+    RvpsMessageEnvelope, RvpsRegisterRequest, SubscribeRequest, Subscriber, UnsubscribeRequest,
 };
 
+/// Default and maximum number of events returned by one pull.
+const DEFAULT_EVENTS_PAGE: usize = 100;
+const MAX_EVENTS_PAGE: usize = 1000;
+
+/// On-disk layout of the subscriber registry. Older releases stored a plain
+/// list of trustee URLs, which is still accepted on load.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum RegistryFile {
+    Subscribers(Vec<Subscriber>),
+    Legacy(HashSet<String>),
+}
+
 #[derive(Clone)]
 pub struct AppState {
-    subscribers: std::sync::Arc<RwLock<HashSet<String>>>,
+    subscribers: std::sync::Arc<RwLock<BTreeMap<String, Subscriber>>>,
     storage_path: PathBuf,
     http_client: Client,
     request_timeout: Duration,
     ledger: std::sync::Arc<dyn LedgerAdapter>,
+    events: std::sync::Arc<EventLog>,
 }
 
 impl AppState {
@@ -41,6 +58,7 @@ impl AppState {
             .build()
             .context("build reqwest client")?;
         let ledger = build_ledger(&cfg.ledger, http_client.clone());
+        let events = EventLog::open(cfg.data_dir.join("events.jsonl"))?;
 
         Ok(Self {
             subscribers: std::sync::Arc::new(RwLock::new(subscribers)),
@@ -48,28 +66,35 @@ impl AppState {
             http_client,
             request_timeout: cfg.request_timeout,
             ledger,
+            events: std::sync::Arc::new(events),
         })
     }
 
-    /// Register trustee endpoints and persist them.
+    /// Register trustee endpoints and persist them. Re-subscribing an existing
+    /// trustee replaces its artifact type filter.
     pub async fn add_trustees(&self, req: &SubscribeRequest) -> Result<Vec<String>, ApiError> {
         req.validate()
             .map_err(|e| ApiError::Validation(e.to_string()))?;
 
-        let mut normalized = Vec::new();
-        for raw in &req.trustee_url {
-            // Normalize URLs to avoid duplicated entries that only differ in trailing slash.
-            let mut url = Url::parse(raw)
-                .map_err(|e| ApiError::Validation(format!("invalid url {raw}: {e}")))?;
-            let trimmed_path = url.path().trim_end_matches('/').to_string();
-            url.set_path(&trimmed_path);
-            normalized.push(url.to_string());
-        }
+        let normalized = normalize_urls(&req.trustee_url)?;
+        let mut artifact_types: Vec<String> = req
+            .artifact_types
+            .iter()
+            .map(|t| t.trim().to_ascii_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+        artifact_types.sort();
+        artifact_types.dedup();
 
         let mut guard = self.subscribers.write().await;
         let mut newly_added = Vec::new();
         for url in normalized {
-            if guard.insert(url.clone()) {
+            let subscriber = Subscriber {
+                trustee_url: url.clone(),
+                artifact_types: artifact_types.clone(),
+            };
+            if guard.get(&url) != Some(&subscriber) {
+                guard.insert(url.clone(), subscriber);
                 newly_added.push(url);
             }
         }
@@ -80,36 +105,60 @@ impl AppState {
         Ok(newly_added)
     }
 
-    /// Forward publish events to every registered trustee.
+    /// Remove trustee endpoints and persist the registry.
+    pub async fn remove_trustees(&self, req: &UnsubscribeRequest) -> Result<Vec<String>, ApiError> {
+        req.validate()
+            .map_err(|e| ApiError::Validation(e.to_string()))?;
+
+        let normalized = normalize_urls(&req.trustee_url)?;
+        let mut guard = self.subscribers.write().await;
+        let removed = normalized
+            .into_iter()
+            .filter(|url| guard.remove(url).is_some())
+            .collect();
+
+        self.persist_registry(&guard)
+            .map_err(|e| ApiError::Storage(e.to_string()))?;
+
+        Ok(removed)
+    }
+
+    /// List registered trustees with their artifact type filters.
+    pub async fn list_trustees(&self) -> Vec<Subscriber> {
+        self.subscribers.read().await.values().cloned().collect()
+    }
+
+    /// Record publish events in the event log and forward them to every
+    /// trustee subscribed to their artifact type.
     pub async fn forward_publish_event(
         &self,
         mut event: PublishEventRequest,
-    ) -> Result<(Vec<ForwardResult>, Option<LedgerReceipt>), ApiError> {
+    ) -> Result<PublishResponse, ApiError> {
         event
             .validate()
             .map_err(|e| ApiError::Validation(e.to_string()))?;
 
-        // Build the RVPS envelope once and reuse it for every subscriber.
+        // The canonical payload is the event without any audit proof; it is what
+        // the ledger and the event log hash.
+        event.audit_proof = None;
         let payload_json = serde_json::to_string(&event)
             .map_err(|e| ApiError::Internal(format!("serialize event: {e}")))?;
-        let message_envelope = RvpsMessageEnvelope {
-            version: "0.1.0".to_string(),
-            typ: "slsa".to_string(),
-            payload: payload_json.clone(),
-        };
-        let envelope_str = serde_json::to_string(&message_envelope)
-            .map_err(|e| ApiError::Internal(format!("serialize envelope: {e}")))?;
-        let register_request = RvpsRegisterRequest {
-            message: envelope_str,
-        };
+        let payload_hash = hex::encode(Sha256::digest(payload_json.as_bytes()));
 
         let subscribers = {
             let guard = self.subscribers.read().await;
-            guard.iter().cloned().collect::<Vec<_>>()
+            guard
+                .values()
+                .filter(|s| s.accepts(&event.artifact_type))
+                .map(|s| s.trustee_url.clone())
+                .collect::<Vec<_>>()
         };
 
         if subscribers.is_empty() {
-            warn!("No trustee subscribers registered; skipping forward.");
+            warn!(
+                "No trustee subscribed to artifact type {}; skipping forward.",
+                event.artifact_type
+            );
         }
 
         // Record in external ledger (if enabled).
@@ -134,31 +183,101 @@ impl AppState {
             }
         };
 
+        // Build the RVPS envelope once, with the audit proof attached, and reuse
+        // it for every subscriber and for the event log.
+        let forwarded_payload = serde_json::to_string(&event)
+            .map_err(|e| ApiError::Internal(format!("serialize event: {e}")))?;
+        let message_envelope = RvpsMessageEnvelope {
+            version: "0.1.0".to_string(),
+            typ: "slsa".to_string(),
+            payload: forwarded_payload,
+        };
+        let envelope_str = serde_json::to_string(&message_envelope)
+            .map_err(|e| ApiError::Internal(format!("serialize envelope: {e}")))?;
+
+        let record = self
+            .events
+            .append(
+                event.artifact_type.clone(),
+                payload_hash,
+                ledger_receipt.clone(),
+                envelope_str.clone(),
+            )
+            .await
+            .map_err(|e| ApiError::Storage(e.to_string()))?;
+        info!("Recorded publish event seq={}", record.seq);
+
+        let register_request = RvpsRegisterRequest {
+            message: envelope_str,
+        };
+
         // Dispatch webhooks concurrently to reduce tail latency.
         let futs = subscribers
             .into_iter()
             .map(|target| self.send_to_trustee(target, register_request.clone()));
 
         let results = join_all(futs).await;
-        Ok((results, ledger_receipt))
+        Ok(PublishResponse {
+            forwarded: results,
+            ledger_receipt,
+            event_seq: record.seq,
+        })
+    }
+
+    /// Return logged events newer than `query.since`, for trustees catching up.
+    pub async fn list_events(&self, query: &EventsQuery) -> EventsResponse {
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_EVENTS_PAGE)
+            .clamp(1, MAX_EVENTS_PAGE);
+        let artifact_types = query
+            .artifact_type
+            .as_deref()
+            .map(|raw| {
+                raw.split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        EventsResponse {
+            events: self.events.since(query.since, limit, &artifact_types).await,
+            latest_seq: self.events.latest_seq().await,
+        }
     }
 
     /// Persist subscriber registry to disk.
-    fn persist_registry(&self, data: &HashSet<String>) -> Result<()> {
-        let serialized = serde_json::to_string_pretty(data).context("serialize subscribers")?;
+    fn persist_registry(&self, data: &BTreeMap<String, Subscriber>) -> Result<()> {
+        let subscribers = data.values().collect::<Vec<_>>();
+        let serialized =
+            serde_json::to_string_pretty(&subscribers).context("serialize subscribers")?;
         fs::write(&self.storage_path, serialized).context("write subscribers registry")
     }
 
     /// Load registry from disk if the file exists.
-    fn load_registry(path: &Path) -> Result<HashSet<String>> {
+    fn load_registry(path: &Path) -> Result<BTreeMap<String, Subscriber>> {
         if !path.exists() {
-            return Ok(HashSet::new());
+            return Ok(BTreeMap::new());
         }
 
         let raw = fs::read_to_string(path).context("read subscribers registry")?;
-        let parsed: HashSet<String> =
+        let parsed: RegistryFile =
             serde_json::from_str(&raw).context("parse subscribers registry")?;
-        Ok(parsed)
+        let subscribers = match parsed {
+            RegistryFile::Subscribers(list) => list,
+            RegistryFile::Legacy(urls) => urls
+                .into_iter()
+                .map(|trustee_url| Subscriber {
+                    trustee_url,
+                    artifact_types: Vec::new(),
+                })
+                .collect(),
+        };
+        Ok(subscribers
+            .into_iter()
+            .map(|s| (s.trustee_url.clone(), s))
+            .collect())
     }
 
     async fn send_to_trustee(&self, target: String, req: RvpsRegisterRequest) -> ForwardResult {
@@ -206,3 +325,16 @@ impl AppState {
         }
     }
 }
+
+/// Normalize URLs to avoid duplicated entries that only differ in trailing slash.
+fn normalize_urls(raw_urls: &[String]) -> Result<Vec<String>, ApiError> {
+    let mut normalized = Vec::new();
+    for raw in raw_urls {
+        let mut url =
+            Url::parse(raw).map_err(|e| ApiError::Validation(format!("invalid url {raw}: {e}")))?;
+        let trimmed_path = url.path().trim_end_matches('/').to_string();
+        url.set_path(&trimmed_path);
+        normalized.push(url.to_string());
+    }
+    Ok(normalized)
+}
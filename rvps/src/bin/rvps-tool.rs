@@ -1,45 +1,335 @@
 //! This tool is to connect the RVPS
 
 use anyhow::*;
-use clap::{Args, Parser};
-use log::info;
+use base64::Engine;
+use chrono::SecondsFormat;
+use clap::{Args, Parser, ValueEnum};
+use log::{info, warn};
 use shadow_rs::shadow;
 
 use reference_value_provider_service::client;
+use reference_value_provider_service::extractors::extractor_modules::launch_measurement::{
+    compute_reference_values, Blob, BootVariable, LaunchMeasurementProvenance, SnpInputs,
+    TdxInputs, VmmType,
+};
+use reference_value_provider_service::namespace;
+use reference_value_provider_service::snapshot::{self, DiffKind, Snapshot, SnapshotEnvelope};
 
 shadow!(build);
 
 /// Default address of RVPS
 const DEFAULT_ADDR: &str = "http://127.0.0.1:50003";
 
-async fn register(addr: &str, provenance_path: &str) -> Result<()> {
+async fn register(addr: &str, scope: &NamespaceArgs, provenance_path: &str) -> Result<()> {
     let message = std::fs::read_to_string(provenance_path).context("read provenance")?;
 
-    client::register(addr.to_string(), message).await?;
+    client::register_in(
+        addr.to_string(),
+        scope.namespace.clone(),
+        message,
+        scope.token.clone(),
+    )
+    .await?;
     info!("Register provenance succeeded.");
 
     Ok(())
 }
 
-async fn query(addr: &str, reference_value_id: Option<&str>) -> Result<()> {
+async fn query(addr: &str, namespace: &str, reference_value_id: Option<&str>) -> Result<()> {
     let rvs = match reference_value_id {
-        Some(reference_value_id) => {
-            client::query_by_id(addr.to_string(), reference_value_id.to_string())
-                .await?
-                .unwrap_or_else(|| "null".to_string())
-        }
-        None => client::query(addr.to_string()).await?,
+        Some(reference_value_id) => client::query_by_id_in(
+            addr.to_string(),
+            namespace.to_string(),
+            reference_value_id.to_string(),
+        )
+        .await?
+        .unwrap_or_else(|| "null".to_string()),
+        None => client::query_in(addr.to_string(), namespace.to_string()).await?,
     };
     info!("Get reference value(s) succeeded:\n {rvs}");
     Ok(())
 }
 
-async fn delete(addr: &str, name: &str) -> Result<()> {
-    client::delete(addr.to_string(), name.to_string()).await?;
+async fn delete(addr: &str, scope: &NamespaceArgs, name: &str) -> Result<()> {
+    client::delete_in(
+        addr.to_string(),
+        scope.namespace.clone(),
+        name.to_string(),
+        scope.token.clone(),
+    )
+    .await?;
     info!("Delete reference value succeeded.");
     Ok(())
 }
 
+async fn history(addr: &str, namespace: &str, name: &str) -> Result<()> {
+    let history =
+        client::history(addr.to_string(), namespace.to_string(), name.to_string()).await?;
+    info!("Get reference value history succeeded:\n {history}");
+    Ok(())
+}
+
+async fn query_at(addr: &str, namespace: &str, name: &str, timestamp: &str) -> Result<()> {
+    let rv = client::query_at(
+        addr.to_string(),
+        namespace.to_string(),
+        name.to_string(),
+        timestamp.to_string(),
+    )
+    .await?
+    .unwrap_or_else(|| "null".to_string());
+    info!("Get reference value at {timestamp} succeeded:\n {rv}");
+    Ok(())
+}
+
+async fn rollback(addr: &str, scope: &NamespaceArgs, name: &str, revision: u64) -> Result<()> {
+    client::rollback(
+        addr.to_string(),
+        scope.namespace.clone(),
+        name.to_string(),
+        revision,
+        scope.token.clone(),
+    )
+    .await?;
+    info!("Roll back reference value succeeded.");
+    Ok(())
+}
+
+async fn expiry_report(addr: &str, namespace: &str, within_hours: u64) -> Result<()> {
+    let rvs = client::expiring(addr.to_string(), namespace.to_string(), within_hours).await?;
+    if rvs.is_empty() {
+        info!("No reference value expires within {within_hours} hours.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<25} {:<8} {:<20} NAME",
+        "EXPIRATION", "STATUS", "NAMESPACE"
+    );
+    for rv in rvs {
+        let status = if rv.expired { "expired" } else { "expiring" };
+        println!(
+            "{:<25} {:<8} {:<20} {}",
+            rv.expiration.to_rfc3339_opts(SecondsFormat::Secs, true),
+            status,
+            rv.namespace,
+            rv.name
+        );
+    }
+    Ok(())
+}
+
+async fn export(args: &ExportArgs) -> Result<()> {
+    let rvs = client::export(args.addr.clone(), args.namespace.clone()).await?;
+    let snapshot = Snapshot::new(namespace::normalize(&args.namespace), rvs);
+    let mut envelope = SnapshotEnvelope::new(&snapshot)?;
+    match &args.signing_key {
+        Some(path) => {
+            let pem = std::fs::read_to_string(path).with_context(|| format!("read {path}"))?;
+            envelope.sign(&args.signer, &pem)?;
+        }
+        None => warn!("Snapshot is not signed; pass --signing-key to sign it."),
+    }
+
+    std::fs::write(&args.output, serde_json::to_string_pretty(&envelope)?)
+        .with_context(|| format!("write {}", args.output))?;
+    info!(
+        "Exported {} reference value(s) to {}.",
+        snapshot.reference_values.len(),
+        args.output
+    );
+    Ok(())
+}
+
+/// Read the snapshot at `path`, checking its signature with the public key
+/// at `verify_key`, if given.
+fn read_snapshot(path: &str, verify_key: Option<&str>) -> Result<Snapshot> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("read {path}"))?;
+    let envelope =
+        SnapshotEnvelope::from_json(&json).with_context(|| format!("parse snapshot {path}"))?;
+    match verify_key {
+        Some(key) => {
+            let pem = std::fs::read_to_string(key).with_context(|| format!("read {key}"))?;
+            envelope
+                .verify(&pem)
+                .with_context(|| format!("verify snapshot {path}"))?;
+        }
+        None => {
+            warn!("Signature of snapshot {path} is not checked; pass --verify-key to check it.")
+        }
+    }
+    envelope
+        .snapshot()
+        .with_context(|| format!("parse snapshot {path}"))
+}
+
+async fn import(args: &ImportArgs) -> Result<()> {
+    // The RVPS checks the signature of the exact file contents.
+    let snapshot =
+        std::fs::read_to_string(&args.input).with_context(|| format!("read {}", args.input))?;
+    let results = client::import(
+        args.addr.clone(),
+        args.scope.namespace.clone(),
+        snapshot,
+        args.strategy.clone(),
+        args.dry_run,
+        args.scope.token.clone(),
+    )
+    .await?;
+
+    println!("{:<10} NAME", "ACTION");
+    for result in &results {
+        println!("{:<10} {}", result.action.to_string(), result.name);
+    }
+    if args.dry_run {
+        info!("Dry run: no reference value imported.");
+    } else {
+        info!("Import reference values succeeded.");
+    }
+    Ok(())
+}
+
+async fn diff(args: &DiffArgs) -> Result<()> {
+    let verify_key = args.verify_key.as_deref();
+    let old = read_snapshot(&args.from, verify_key)?.reference_values;
+    let new = match &args.to {
+        Some(path) => read_snapshot(path, verify_key)?.reference_values,
+        None => client::export(args.addr.clone(), args.namespace.clone()).await?,
+    };
+
+    let diffs = snapshot::diff(&old, &new);
+    if diffs.is_empty() {
+        info!("No difference.");
+        return Ok(());
+    }
+
+    for diff in diffs {
+        match diff.kind {
+            DiffKind::Added => println!("+ {}", diff.name),
+            DiffKind::Removed => println!("- {}", diff.name),
+            DiffKind::Changed => println!("~ {} ({})", diff.name, diff.fields.join(", ")),
+        }
+        for digest in &diff.added_digests {
+            println!("    + {digest}");
+        }
+        for digest in &diff.removed_digests {
+            println!("    - {digest}");
+        }
+    }
+    Ok(())
+}
+
+fn read_blob(path: &str) -> Result<Blob> {
+    Ok(Blob(
+        std::fs::read(path).with_context(|| format!("read {path}"))?,
+    ))
+}
+
+fn read_optional_blob(path: Option<&str>) -> Result<Option<Blob>> {
+    path.map(read_blob).transpose()
+}
+
+fn launch_measurement_provenance(
+    args: &LaunchMeasurementArgs,
+) -> Result<LaunchMeasurementProvenance> {
+    let ovmf = read_blob(&args.ovmf)?;
+    let initrd = read_optional_blob(args.initrd.as_deref())?;
+
+    let provenance = match args.tee {
+        Tee::Tdx => {
+            let boot_variables = args
+                .boot_variable
+                .iter()
+                .map(|variable| {
+                    let (name, path) = variable
+                        .split_once('=')
+                        .ok_or_else(|| anyhow!("boot variable must be NAME=PATH: {variable}"))?;
+                    Ok(BootVariable {
+                        name: name.to_string(),
+                        data: read_blob(path)?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            LaunchMeasurementProvenance {
+                tdx: Some(TdxInputs {
+                    ovmf,
+                    kernel: read_blob(args.kernel.as_deref().context("TDX needs --kernel")?)?,
+                    initrd,
+                    cmdline: args.cmdline.clone().unwrap_or_default(),
+                    memory_mb: args.memory_mb.context("TDX needs --memory-mb")?,
+                    td_hob: read_optional_blob(args.td_hob.as_deref())?,
+                    acpi_loader: read_optional_blob(args.acpi_loader.as_deref())?,
+                    acpi_rsdp: read_optional_blob(args.acpi_rsdp.as_deref())?,
+                    acpi_tables: read_optional_blob(args.acpi_tables.as_deref())?,
+                    boot_variables,
+                }),
+                snp: None,
+            }
+        }
+        Tee::Snp => LaunchMeasurementProvenance {
+            tdx: None,
+            snp: Some(SnpInputs {
+                ovmf,
+                kernel: read_optional_blob(args.kernel.as_deref())?,
+                initrd,
+                cmdline: args.cmdline.clone(),
+                vcpus: args.vcpus.context("SNP needs --vcpus")?,
+                vcpu_type: args.vcpu_type.clone(),
+                vcpu_sig: args
+                    .vcpu_sig
+                    .map(u32::try_from)
+                    .transpose()
+                    .context("--vcpu-sig does not fit in 32 bits")?,
+                vmm_type: match args.vmm_type {
+                    Vmm::Qemu => VmmType::Qemu,
+                    Vmm::Ec2 => VmmType::Ec2,
+                },
+                guest_features: args.guest_features,
+                policy: args.policy,
+            }),
+        },
+    };
+
+    Ok(provenance)
+}
+
+async fn launch_measurement(args: &LaunchMeasurementArgs) -> Result<()> {
+    let provenance = launch_measurement_provenance(args)?;
+
+    if args.print {
+        let rvs = compute_reference_values(&provenance)?;
+        println!("{}", serde_json::to_string_pretty(&rvs)?);
+        return Ok(());
+    }
+
+    let payload =
+        base64::engine::general_purpose::STANDARD.encode(serde_json::to_vec(&provenance)?);
+    let message = serde_json::json!({
+        "version": "0.1.0",
+        "type": "launch-measurement",
+        "payload": payload,
+    });
+    client::register_in(
+        args.addr.clone(),
+        args.scope.namespace.clone(),
+        message.to_string(),
+        args.scope.token.clone(),
+    )
+    .await?;
+    info!("Register launch measurements succeeded.");
+
+    Ok(())
+}
+
+/// Parse an integer given in decimal or `0x` prefixed hex.
+fn parse_u64(s: &str) -> std::result::Result<u64, std::num::ParseIntError> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+}
+
 /// RVPS command-line arguments.
 #[derive(Parser)]
 #[command(name = "rvps-tool")]
@@ -54,6 +344,44 @@ enum Cli {
 
     /// Delete reference value
     Delete(DeleteArgs),
+
+    /// Show the history of a reference value
+    History(HistoryArgs),
+
+    /// Restore a reference value recorded in its history
+    Rollback(RollbackArgs),
+
+    /// List the reference values expiring soon, and those expired
+    ExpiryReport(ExpiryReportArgs),
+
+    /// Write a snapshot of all the reference values of a namespace to a file
+    Export(ExportArgs),
+
+    /// Import the reference values of a snapshot
+    Import(ImportArgs),
+
+    /// Compare a snapshot with the live reference values, or with another
+    /// snapshot
+    Diff(DiffArgs),
+
+    /// Compute the launch measurements of a TDX or SNP guest and register
+    /// them as reference values
+    LaunchMeasurement(Box<LaunchMeasurementArgs>),
+}
+
+/// The namespace of the reference values and the admin token presented to
+/// change them.
+#[derive(Args)]
+struct NamespaceArgs {
+    /// The namespace of the reference values. Empty selects the default
+    /// namespace
+    #[arg(long, default_value = "")]
+    namespace: String,
+
+    /// Admin token of the namespace, needed to change its reference values
+    /// if it is configured with admin tokens
+    #[arg(long)]
+    token: Option<String>,
 }
 
 #[derive(Args)]
@@ -63,6 +391,9 @@ struct RegisterArgs {
     #[arg(short, long, default_value = DEFAULT_ADDR)]
     addr: String,
 
+    #[command(flatten)]
+    scope: NamespaceArgs,
+
     /// The path to the provenance json file
     #[arg(short, long)]
     path: String,
@@ -75,9 +406,19 @@ struct QueryArgs {
     #[arg(short, long, default_value = DEFAULT_ADDR)]
     addr: String,
 
+    /// The namespace of the reference values. Empty selects the default
+    /// namespace
+    #[arg(long, default_value = "")]
+    namespace: String,
+
     /// Optional reference value identifier. Omit it for the legacy bulk query.
     #[arg(short = 'i', long)]
     reference_value_id: Option<String>,
+
+    /// Query the reference value as stored at this RFC 3339 time, from its
+    /// history
+    #[arg(long, requires = "reference_value_id")]
+    at: Option<String>,
 }
 
 #[derive(Args)]
@@ -87,11 +428,238 @@ struct DeleteArgs {
     #[arg(short, long, default_value = DEFAULT_ADDR)]
     addr: String,
 
+    #[command(flatten)]
+    scope: NamespaceArgs,
+
     /// The name of the reference value to delete
     #[arg(short, long)]
     name: String,
 }
 
+#[derive(Args)]
+#[command(author, version, about, long_about = None)]
+struct HistoryArgs {
+    /// The address of target RVPS
+    #[arg(short, long, default_value = DEFAULT_ADDR)]
+    addr: String,
+
+    /// The namespace of the reference value. Empty selects the default
+    /// namespace
+    #[arg(long, default_value = "")]
+    namespace: String,
+
+    /// The name of the reference value
+    #[arg(short, long)]
+    name: String,
+}
+
+#[derive(Args)]
+#[command(author, version, about, long_about = None)]
+struct RollbackArgs {
+    /// The address of target RVPS
+    #[arg(short, long, default_value = DEFAULT_ADDR)]
+    addr: String,
+
+    #[command(flatten)]
+    scope: NamespaceArgs,
+
+    /// The name of the reference value to roll back
+    #[arg(short, long)]
+    name: String,
+
+    /// The revision of the history entry to restore
+    #[arg(short, long)]
+    revision: u64,
+}
+
+#[derive(Args)]
+#[command(author, version, about, long_about = None)]
+struct ExpiryReportArgs {
+    /// The address of target RVPS
+    #[arg(short, long, default_value = DEFAULT_ADDR)]
+    addr: String,
+
+    /// The namespace of the reference values. Empty selects the default
+    /// namespace
+    #[arg(long, default_value = "")]
+    namespace: String,
+
+    /// List the reference values expiring within this many hours
+    #[arg(short, long, default_value = "168")]
+    within_hours: u64,
+}
+
+#[derive(Args)]
+#[command(author, version, about, long_about = None)]
+struct ExportArgs {
+    /// The address of target RVPS
+    #[arg(short, long, default_value = DEFAULT_ADDR)]
+    addr: String,
+
+    /// The namespace of the reference values. Empty selects the default
+    /// namespace
+    #[arg(long, default_value = "")]
+    namespace: String,
+
+    /// The path to write the snapshot to
+    #[arg(short, long)]
+    output: String,
+
+    /// The path to a PEM PKCS#8 ECDSA P-256/P-384 private key signing the
+    /// snapshot
+    #[arg(long)]
+    signing_key: Option<String>,
+
+    /// Who signs the snapshot, recorded next to the signature
+    #[arg(long, default_value = "rvps-tool")]
+    signer: String,
+}
+
+#[derive(Args)]
+#[command(author, version, about, long_about = None)]
+struct ImportArgs {
+    /// The address of target RVPS
+    #[arg(short, long, default_value = DEFAULT_ADDR)]
+    addr: String,
+
+    #[command(flatten)]
+    scope: NamespaceArgs,
+
+    /// The path to the snapshot to import. The RVPS only imports it if it is
+    /// signed by one of its trusted snapshot keys, unless configured to
+    /// accept unsigned snapshots
+    #[arg(short, long)]
+    input: String,
+
+    /// How reference values already stored with different contents are
+    /// handled
+    #[arg(long, default_value = "merge", value_parser = ["merge", "replace", "skip"])]
+    strategy: String,
+
+    /// Only report what the import would do
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args)]
+#[command(author, version, about, long_about = None)]
+struct DiffArgs {
+    /// The address of target RVPS
+    #[arg(short, long, default_value = DEFAULT_ADDR)]
+    addr: String,
+
+    /// The namespace of the live reference values. Empty selects the default
+    /// namespace
+    #[arg(long, default_value = "")]
+    namespace: String,
+
+    /// The path to the snapshot to compare from
+    from: String,
+
+    /// The path to the snapshot to compare to. The live reference values
+    /// if omitted
+    to: Option<String>,
+
+    /// The path to the PEM public key the snapshots must be signed with
+    #[arg(long)]
+    verify_key: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum Tee {
+    Tdx,
+    Snp,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum Vmm {
+    Qemu,
+    Ec2,
+}
+
+#[derive(Args)]
+#[command(author, version, about, long_about = None)]
+struct LaunchMeasurementArgs {
+    /// The address of target RVPS
+    #[arg(short, long, default_value = DEFAULT_ADDR)]
+    addr: String,
+
+    #[command(flatten)]
+    scope: NamespaceArgs,
+
+    /// Print the computed reference values instead of registering them
+    #[arg(long)]
+    print: bool,
+
+    /// The TEE the guest runs in
+    #[arg(long, value_enum)]
+    tee: Tee,
+
+    /// The path to the OVMF firmware image
+    #[arg(long)]
+    ovmf: String,
+
+    /// The path to the kernel image of a direct kernel boot
+    #[arg(long)]
+    kernel: Option<String>,
+
+    /// The path to the initrd
+    #[arg(long)]
+    initrd: Option<String>,
+
+    /// The kernel command line
+    #[arg(long)]
+    cmdline: Option<String>,
+
+    /// TDX: guest memory size in MiB
+    #[arg(long)]
+    memory_mb: Option<u64>,
+
+    /// TDX: the path to the TD HOB, needed for RTMR[0]
+    #[arg(long)]
+    td_hob: Option<String>,
+
+    /// TDX: the path to the ACPI table loader, needed for RTMR[0]
+    #[arg(long)]
+    acpi_loader: Option<String>,
+
+    /// TDX: the path to the ACPI RSDP, needed for RTMR[0]
+    #[arg(long)]
+    acpi_rsdp: Option<String>,
+
+    /// TDX: the path to the ACPI tables, needed for RTMR[0]
+    #[arg(long)]
+    acpi_tables: Option<String>,
+
+    /// TDX: a UEFI boot variable measured into RTMR[0], as NAME=PATH
+    #[arg(long)]
+    boot_variable: Vec<String>,
+
+    /// SNP: number of vCPUs
+    #[arg(long)]
+    vcpus: Option<u32>,
+
+    /// SNP: QEMU vCPU model
+    #[arg(long, default_value = "EPYC-v4")]
+    vcpu_type: String,
+
+    /// SNP: CPUID signature of the vCPUs, overriding --vcpu-type
+    #[arg(long, value_parser = parse_u64)]
+    vcpu_sig: Option<u64>,
+
+    /// SNP: the VMM launching the guest
+    #[arg(long, value_enum, default_value = "qemu")]
+    vmm_type: Vmm,
+
+    /// SNP: SEV features enabled in the VMSA
+    #[arg(long, value_parser = parse_u64, default_value = "0x1")]
+    guest_features: u64,
+
+    /// SNP: guest policy, whose fields are registered as well
+    #[arg(long, value_parser = parse_u64)]
+    policy: Option<u64>,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
@@ -108,8 +676,27 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli {
-        Cli::Register(para) => register(&para.addr, &para.path).await,
-        Cli::Query(para) => query(&para.addr, para.reference_value_id.as_deref()).await,
-        Cli::Delete(para) => delete(&para.addr, &para.name).await,
+        Cli::Register(para) => register(&para.addr, &para.scope, &para.path).await,
+        Cli::Query(para) => match (&para.reference_value_id, &para.at) {
+            (Some(name), Some(at)) => query_at(&para.addr, &para.namespace, name, at).await,
+            _ => {
+                query(
+                    &para.addr,
+                    &para.namespace,
+                    para.reference_value_id.as_deref(),
+                )
+                .await
+            }
+        },
+        Cli::Delete(para) => delete(&para.addr, &para.scope, &para.name).await,
+        Cli::History(para) => history(&para.addr, &para.namespace, &para.name).await,
+        Cli::Rollback(para) => rollback(&para.addr, &para.scope, &para.name, para.revision).await,
+        Cli::ExpiryReport(para) => {
+            expiry_report(&para.addr, &para.namespace, para.within_hours).await
+        }
+        Cli::Export(para) => export(&para).await,
+        Cli::Import(para) => import(&para).await,
+        Cli::Diff(para) => diff(&para).await,
+        Cli::LaunchMeasurement(para) => launch_measurement(&para).await,
     }
 }
@@ -6,15 +6,53 @@
 
 use anyhow::*;
 
+use crate::expiry::ExpiringReferenceValue;
 use crate::rvps_api::reference::{
+    reference_value_bulk_client::ReferenceValueBulkClient,
+    reference_value_history_client::ReferenceValueHistoryClient,
     reference_value_provider_service_client::ReferenceValueProviderServiceClient,
-    ReferenceValueDeleteRequest, ReferenceValueListRequest, ReferenceValueQueryRequest,
-    ReferenceValueRegisterRequest,
+    ReferenceValueAtRequest, ReferenceValueDeleteRequest, ReferenceValueExpiryRequest,
+    ReferenceValueExportRequest, ReferenceValueHistoryRequest, ReferenceValueImportRequest,
+    ReferenceValueListRequest, ReferenceValueQueryRequest, ReferenceValueRegisterRequest,
+    ReferenceValueRollbackRequest,
 };
+use crate::snapshot::ImportResult;
+use crate::ReferenceValue;
+
+/// Maximum size of the exported reference values the client accepts, above
+/// the 4 MiB gRPC default, matching what the server accepts for imports.
+const MAX_DECODING_MESSAGE_SIZE: usize = 256 * 1024 * 1024;
+
+/// A request carrying `admin_token`, if any, as bearer token, to change the
+/// reference values of a namespace configured with admin tokens.
+fn request<T>(message: T, admin_token: Option<String>) -> Result<tonic::Request<T>> {
+    let mut req = tonic::Request::new(message);
+    if let Some(token) = admin_token {
+        let value = format!("Bearer {token}")
+            .parse()
+            .context("invalid admin token")?;
+        req.metadata_mut().insert("authorization", value);
+    }
+
+    Ok(req)
+}
 
 pub async fn register(address: String, message: String) -> Result<()> {
+    register_in(address, String::new(), message, None).await
+}
+
+/// Register a message to `namespace`.
+pub async fn register_in(
+    address: String,
+    namespace: String,
+    message: String,
+    admin_token: Option<String>,
+) -> Result<()> {
     let mut client = ReferenceValueProviderServiceClient::connect(address).await?;
-    let req = tonic::Request::new(ReferenceValueRegisterRequest { message });
+    let req = request(
+        ReferenceValueRegisterRequest { message, namespace },
+        admin_token,
+    )?;
 
     client.register_reference_value(req).await?;
 
@@ -22,15 +60,32 @@ pub async fn register(address: String, message: String) -> Result<()> {
 }
 
 pub async fn query(address: String) -> Result<String> {
-    Ok(query_by_id(address, String::new())
+    query_in(address, String::new()).await
+}
+
+/// Query all reference values of `namespace`.
+pub async fn query_in(address: String, namespace: String) -> Result<String> {
+    Ok(query_by_id_in(address, namespace, String::new())
         .await?
         .unwrap_or_else(|| "{}".to_string()))
 }
 
 /// Query one reference value. A missing or expired value returns `None`.
 pub async fn query_by_id(address: String, reference_value_id: String) -> Result<Option<String>> {
+    query_by_id_in(address, String::new(), reference_value_id).await
+}
+
+/// Query one reference value of `namespace`.
+pub async fn query_by_id_in(
+    address: String,
+    namespace: String,
+    reference_value_id: String,
+) -> Result<Option<String>> {
     let mut client = ReferenceValueProviderServiceClient::connect(address).await?;
-    let req = tonic::Request::new(ReferenceValueQueryRequest { reference_value_id });
+    let req = tonic::Request::new(ReferenceValueQueryRequest {
+        reference_value_id,
+        namespace,
+    });
 
     let rvs = client
         .query_reference_value(req)
@@ -42,8 +97,18 @@ pub async fn query_by_id(address: String, reference_value_id: String) -> Result<
 }
 
 pub async fn delete(address: String, name: String) -> Result<()> {
+    delete_in(address, String::new(), name, None).await
+}
+
+/// Delete a reference value of `namespace`.
+pub async fn delete_in(
+    address: String,
+    namespace: String,
+    name: String,
+    admin_token: Option<String>,
+) -> Result<()> {
     let mut client = ReferenceValueProviderServiceClient::connect(address).await?;
-    let req = tonic::Request::new(ReferenceValueDeleteRequest { name });
+    let req = request(ReferenceValueDeleteRequest { name, namespace }, admin_token)?;
 
     client.delete_reference_value(req).await?;
 
@@ -51,10 +116,153 @@ pub async fn delete(address: String, name: String) -> Result<()> {
 }
 
 pub async fn set_reference_value_list(address: String, payload: String) -> Result<()> {
+    set_reference_value_list_in(address, String::new(), payload, None).await
+}
+
+/// Set a reference value list of `namespace`.
+pub async fn set_reference_value_list_in(
+    address: String,
+    namespace: String,
+    payload: String,
+    admin_token: Option<String>,
+) -> Result<()> {
     let mut client = ReferenceValueProviderServiceClient::connect(address).await?;
-    let req = tonic::Request::new(ReferenceValueListRequest { payload });
+    let req = request(
+        ReferenceValueListRequest { payload, namespace },
+        admin_token,
+    )?;
 
     client.set_reference_value_list(req).await?;
 
     Ok(())
 }
+
+/// Get the history of a reference value, as a JSON array.
+pub async fn history(address: String, namespace: String, name: String) -> Result<String> {
+    let mut client = ReferenceValueHistoryClient::connect(address).await?;
+    let req = tonic::Request::new(ReferenceValueHistoryRequest { name, namespace });
+
+    let history = client
+        .get_reference_value_history(req)
+        .await?
+        .into_inner()
+        .history;
+
+    Ok(history)
+}
+
+/// Query a reference value as stored at `timestamp` (RFC 3339). `None` if
+/// it did not exist then.
+pub async fn query_at(
+    address: String,
+    namespace: String,
+    name: String,
+    timestamp: String,
+) -> Result<Option<String>> {
+    let mut client = ReferenceValueHistoryClient::connect(address).await?;
+    let req = tonic::Request::new(ReferenceValueAtRequest {
+        name,
+        timestamp,
+        namespace,
+    });
+
+    let rv = client
+        .query_reference_value_at(req)
+        .await?
+        .into_inner()
+        .reference_value;
+
+    Ok((!rv.is_empty()).then_some(rv))
+}
+
+pub async fn rollback(
+    address: String,
+    namespace: String,
+    name: String,
+    revision: u64,
+    admin_token: Option<String>,
+) -> Result<()> {
+    let mut client = ReferenceValueHistoryClient::connect(address).await?;
+    let req = request(
+        ReferenceValueRollbackRequest {
+            name,
+            revision,
+            namespace,
+        },
+        admin_token,
+    )?;
+
+    client.rollback_reference_value(req).await?;
+
+    Ok(())
+}
+
+/// Get the reference values expiring within `within_hours`, including those
+/// expired already, soonest first.
+pub async fn expiring(
+    address: String,
+    namespace: String,
+    within_hours: u64,
+) -> Result<Vec<ExpiringReferenceValue>> {
+    let mut client = ReferenceValueHistoryClient::connect(address).await?;
+    let req = tonic::Request::new(ReferenceValueExpiryRequest {
+        namespace,
+        within_hours,
+    });
+
+    let reference_values = client
+        .get_expiring_reference_values(req)
+        .await?
+        .into_inner()
+        .reference_values;
+
+    serde_json::from_str(&reference_values).context("parse expiring reference values")
+}
+
+/// Get all the reference values stored in a namespace, expired or not,
+/// sorted by name.
+pub async fn export(address: String, namespace: String) -> Result<Vec<ReferenceValue>> {
+    let mut client = ReferenceValueBulkClient::connect(address)
+        .await?
+        .max_decoding_message_size(MAX_DECODING_MESSAGE_SIZE);
+    let req = tonic::Request::new(ReferenceValueExportRequest { namespace });
+
+    let reference_values = client
+        .export_reference_values(req)
+        .await?
+        .into_inner()
+        .reference_values;
+
+    serde_json::from_str(&reference_values).context("parse exported reference values")
+}
+
+/// Store the reference values of a snapshot file in a namespace, handling
+/// those already stored with different contents by `strategy` (`merge`,
+/// `replace` or `skip`). With `dry_run`, only report what would be done.
+pub async fn import(
+    address: String,
+    namespace: String,
+    snapshot: String,
+    strategy: String,
+    dry_run: bool,
+    admin_token: Option<String>,
+) -> Result<Vec<ImportResult>> {
+    let mut client = ReferenceValueBulkClient::connect(address).await?;
+    let req = request(
+        ReferenceValueImportRequest {
+            snapshot,
+            strategy,
+            dry_run,
+            namespace,
+        },
+        admin_token,
+    )?;
+
+    let results = client
+        .import_reference_values(req)
+        .await?
+        .into_inner()
+        .results;
+
+    serde_json::from_str(&results).context("parse import results")
+}
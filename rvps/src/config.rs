@@ -2,14 +2,66 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 //
+use std::collections::HashMap;
+
 use serde::Deserialize;
 
+use crate::expiry::ExpiryConfig;
+use crate::extractors::ExtractorsConfig;
+use crate::ledger::LedgerConfig;
+use crate::namespace::NamespaceConfig;
+use crate::pre_processor::PreProcessorConfig;
+use crate::provenance_source::ProvenanceSourceConfig;
+use crate::rvds::RvdsSyncConfig;
+use crate::snapshot::SnapshotConfig;
 use crate::storage::ReferenceValueStorageConfig;
 
 #[derive(Deserialize, Clone, Debug, PartialEq, Default)]
 pub struct Config {
     #[serde(default)]
     pub storage: ReferenceValueStorageConfig,
+
+    /// Periodically pull events missed from RVDS. Only used by the `rvps`
+    /// server; disabled when absent.
+    #[serde(default)]
+    pub rvds_sync: Option<RvdsSyncConfig>,
+
+    /// Periodically sweep expired reference values and warn about those
+    /// nearing expiry. Only used by the `rvps` server; disabled when absent.
+    #[serde(default)]
+    pub expiry: Option<ExpiryConfig>,
+
+    /// Confirm RVDS audit proofs against the ledger they were recorded on
+    /// before registering reference values. Disabled by default.
+    #[serde(default)]
+    pub ledger: LedgerConfig,
+
+    /// Wares messages pass through before the extractors.
+    #[serde(default)]
+    pub pre_processor: PreProcessorConfig,
+
+    /// Verification settings of the provenance extractors.
+    #[serde(default)]
+    pub extractors: ExtractorsConfig,
+
+    /// Namespaces reference values can be registered to, by name. The
+    /// default namespace may be changed by anyone unless configured here.
+    #[serde(default)]
+    pub namespaces: HashMap<String, NamespaceConfig>,
+
+    /// Snapshots accepted for import. Only signed ones by default.
+    #[serde(default)]
+    pub snapshot: SnapshotConfig,
+
+    /// Where the provenance material of reference value lists may be
+    /// fetched from.
+    #[serde(default)]
+    pub provenance_source: ProvenanceSourceConfig,
+
+    /// Largest register or import request the `rvps` server accepts, in
+    /// bytes. The 4 MiB gRPC default applies when absent.
+    #[serde(default)]
+    pub max_request_bytes: Option<usize>,
 }
 
 #[cfg(feature = "bin")]
@@ -0,0 +1,103 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! ECDSA P-256/P-384 signature verification, shared by the `Signature`
+//! pre-processor ware, the SLSA and CoRIM extractors and snapshots.
+
+// Without `fs`, only the CoRIM extractor uses this module.
+#![cfg_attr(not(feature = "fs"), allow(dead_code))]
+
+use anyhow::{anyhow, Result};
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use p256::pkcs8::DecodePublicKey;
+use sha2::{Digest, Sha256, Sha384};
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum HashAlg {
+    Sha256,
+    Sha384,
+}
+
+impl HashAlg {
+    pub(crate) fn digest(self, message: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlg::Sha256 => Sha256::digest(message).to_vec(),
+            HashAlg::Sha384 => Sha384::digest(message).to_vec(),
+        }
+    }
+}
+
+/// An ECDSA public key.
+#[derive(Clone, Debug)]
+pub(crate) enum PublicKey {
+    P256(p256::ecdsa::VerifyingKey),
+    P384(p384::ecdsa::VerifyingKey),
+}
+
+impl PublicKey {
+    /// Parse a DER `SubjectPublicKeyInfo`.
+    pub(crate) fn from_der(der: &[u8]) -> Result<Self> {
+        if let Ok(key) = p256::ecdsa::VerifyingKey::from_public_key_der(der) {
+            return Ok(Self::P256(key));
+        }
+        p384::ecdsa::VerifyingKey::from_public_key_der(der)
+            .map(Self::P384)
+            .map_err(|_| anyhow!("unsupported public key, only ECDSA P-256/P-384 keys are"))
+    }
+
+    pub(crate) fn from_pem(pem: &str) -> Result<Self> {
+        if let Ok(key) = p256::ecdsa::VerifyingKey::from_public_key_pem(pem) {
+            return Ok(Self::P256(key));
+        }
+        p384::ecdsa::VerifyingKey::from_public_key_pem(pem)
+            .map(Self::P384)
+            .map_err(|_| anyhow!("unsupported public key, only ECDSA P-256/P-384 keys are"))
+    }
+
+    /// The hash algorithm matching the curve of the key.
+    pub(crate) fn hash_alg(&self) -> HashAlg {
+        match self {
+            PublicKey::P256(_) => HashAlg::Sha256,
+            PublicKey::P384(_) => HashAlg::Sha384,
+        }
+    }
+
+    /// Verify a DER or fixed-size ECDSA signature over `message` hashed
+    /// with `alg`.
+    pub(crate) fn verify_with(&self, alg: HashAlg, message: &[u8], signature: &[u8]) -> Result<()> {
+        let digest = alg.digest(message);
+        match self {
+            PublicKey::P256(key) => {
+                let signature = p256::ecdsa::Signature::from_der(signature)
+                    .or_else(|_| p256::ecdsa::Signature::from_slice(signature))?;
+                key.verify_prehash(&digest, &signature)?;
+            }
+            PublicKey::P384(key) => {
+                let signature = p384::ecdsa::Signature::from_der(signature)
+                    .or_else(|_| p384::ecdsa::Signature::from_slice(signature))?;
+                key.verify_prehash(&digest, &signature)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify a signature with the hash algorithm matching the curve.
+    pub(crate) fn verify(&self, message: &[u8], signature: &[u8]) -> Result<()> {
+        self.verify_with(self.hash_alg(), message, signature)
+    }
+}
+
+/// DSSE pre-authentication encoding, binding `payload` to its type.
+pub(crate) fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut pae = format!(
+        "DSSEv1 {} {} {} ",
+        payload_type.len(),
+        payload_type,
+        payload.len()
+    )
+    .into_bytes();
+    pae.extend_from_slice(payload);
+    pae
+}
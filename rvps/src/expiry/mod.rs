@@ -0,0 +1,126 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Expiry of reference values.
+//!
+//! Expired reference values are never returned by queries, but stay in the
+//! storage until removed. With the `bin` feature, the sweeper of this module
+//! periodically removes or archives them, and warns about reference values
+//! nearing their expiry before they lapse.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::ReferenceValue;
+
+#[cfg(feature = "bin")]
+mod sweeper;
+#[cfg(feature = "bin")]
+pub use sweeper::ExpirySweeper;
+
+fn default_interval_secs() -> u64 {
+    3600
+}
+
+fn default_warning_window_hours() -> u64 {
+    7 * 24
+}
+
+/// What the sweeper does with expired reference values.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum ExpiredAction {
+    /// Remove them. Their history keeps the values they had.
+    #[default]
+    Remove,
+    /// Remove them, then append them to `archive_path`.
+    Archive,
+    /// Keep them, only notifying that they expired.
+    Keep,
+}
+
+/// Configuration of the expiry sweeper.
+///
+/// Sweepers do not coordinate. With a storage shared by several RVPS
+/// replicas, configure the sweeper on one of them only: each value is
+/// removed and archived once whichever replica sweeps it, but every replica
+/// would post its own notifications.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct ExpiryConfig {
+    /// Interval between two sweeps, in seconds.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+
+    /// Reference values expiring within this many hours are reported as
+    /// nearing expiry.
+    #[serde(default = "default_warning_window_hours")]
+    pub warning_window_hours: u64,
+
+    #[serde(default)]
+    pub action: ExpiredAction,
+
+    /// JSON lines file expired reference values are archived to.
+    #[serde(default)]
+    pub archive_path: Option<String>,
+
+    /// URL notifications are posted to. They are only logged when absent.
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+
+    /// Bearer token presented to the webhook.
+    #[serde(default)]
+    pub webhook_token: Option<String>,
+}
+
+impl Default for ExpiryConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: default_interval_secs(),
+            warning_window_hours: default_warning_window_hours(),
+            action: ExpiredAction::default(),
+            archive_path: None,
+            webhook_url: None,
+            webhook_token: None,
+        }
+    }
+}
+
+/// A reference value nearing or past its expiry, as reported to
+/// `rvps-tool` and webhooks.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ExpiringReferenceValue {
+    pub namespace: String,
+    pub name: String,
+    pub expiration: DateTime<Utc>,
+    pub expired: bool,
+}
+
+impl From<&ReferenceValue> for ExpiringReferenceValue {
+    fn from(rv: &ReferenceValue) -> Self {
+        Self {
+            namespace: rv.namespace().clone(),
+            name: rv.name().clone(),
+            expiration: rv.expiration,
+            expired: rv.expired(),
+        }
+    }
+}
+
+/// Kind of an expiry notification.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpiryEvent {
+    /// The reference values expire within the warning window.
+    Expiring,
+    /// The reference values expired, and were removed unless the action
+    /// is `Keep`.
+    Expired,
+}
+
+/// Body of the notifications posted to the webhook.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ExpiryNotification {
+    pub event: ExpiryEvent,
+    pub reference_values: Vec<ExpiringReferenceValue>,
+}
@@ -0,0 +1,311 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use reqwest::Client;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+use super::{ExpiredAction, ExpiryConfig, ExpiryEvent, ExpiryNotification};
+use crate::{ReferenceValue, Rvps};
+
+/// Actor recorded in the history of the reference values the sweeper
+/// removes.
+const SWEEPER_ACTOR: &str = "expiry-sweeper";
+
+/// Periodically handles expired reference values as configured, and
+/// notifies about them and about those nearing expiry.
+pub struct ExpirySweeper {
+    config: ExpiryConfig,
+    http: Client,
+    rvps: Arc<RwLock<Rvps>>,
+    /// Notifications sent, by event, storage key and expiration of the
+    /// reference value, so that each is sent once.
+    notified: HashSet<(ExpiryEvent, String, DateTime<Utc>)>,
+}
+
+impl ExpirySweeper {
+    pub fn new(config: ExpiryConfig, rvps: Arc<RwLock<Rvps>>) -> Result<Self> {
+        if config.interval_secs == 0 {
+            bail!("expiry.interval_secs must be greater than zero");
+        }
+        if config.action == ExpiredAction::Archive && config.archive_path.is_none() {
+            bail!("expiry.action Archive needs an archive_path");
+        }
+
+        let http = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .context("build expiry webhook http client")?;
+
+        Ok(Self {
+            config,
+            http,
+            rvps,
+            notified: HashSet::new(),
+        })
+    }
+
+    /// Run the sweep loop forever. Failures are logged and retried at the next tick.
+    pub async fn run(mut self) {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.interval_secs));
+        loop {
+            interval.tick().await;
+            match self.sweep_once().await {
+                Ok(0) => debug!("Expiry sweep: no expired reference values"),
+                Ok(handled) => info!("Expiry sweep: handled {handled} expired reference value(s)"),
+                Err(e) => warn!("Expiry sweep failed: {e:#}"),
+            }
+        }
+    }
+
+    /// Handle the expired reference values and send the notifications due.
+    /// Returns the number of expired reference values handled.
+    pub async fn sweep_once(&mut self) -> Result<usize> {
+        let window = chrono::Duration::hours(self.config.warning_window_hours as i64);
+        let values = self
+            .rvps
+            .read()
+            .await
+            .expiring_reference_values(None, Utc::now() + window)
+            .await?;
+        let (expired, expiring): (Vec<_>, Vec<_>) = values.iter().partition(|rv| rv.expired());
+
+        let mut handled = Vec::new();
+        for rv in expired.iter().copied() {
+            match self.handle_expired(rv).await {
+                Ok(true) => handled.push(rv),
+                Ok(false) => {}
+                Err(e) => warn!("Expiry sweep: skip {}: {e:#}", rv.name()),
+            }
+        }
+
+        self.notify(ExpiryEvent::Expired, &handled).await;
+        self.notify(ExpiryEvent::Expiring, &expiring).await;
+
+        // Forget the values no longer reported, so that a value coming back
+        // is notified about again.
+        let reported: HashSet<_> = values.iter().map(ReferenceValue::storage_key).collect();
+        self.notified.retain(|(_, key, _)| reported.contains(key));
+
+        Ok(handled.len())
+    }
+
+    /// Apply the configured action to an expired reference value. Returns
+    /// whether it was handled, rather than refreshed or removed meanwhile.
+    /// Values are archived once removed, so that only the RVPS removing a
+    /// value archives it.
+    async fn handle_expired(&self, rv: &ReferenceValue) -> Result<bool> {
+        if self.config.action == ExpiredAction::Keep {
+            return Ok(true);
+        }
+
+        let Some(removed) = self
+            .rvps
+            .write()
+            .await
+            .remove_expired_reference_value(rv.namespace(), rv.name(), SWEEPER_ACTOR)
+            .await?
+        else {
+            return Ok(false);
+        };
+
+        if self.config.action == ExpiredAction::Archive {
+            if let Err(e) = self.archive(&removed).await {
+                warn!(
+                    "Expiry sweep: archive {} failed, its history keeps its value: {e:#}",
+                    removed.name()
+                );
+            }
+        }
+        Ok(true)
+    }
+
+    async fn archive(&self, rv: &ReferenceValue) -> Result<()> {
+        let Some(path) = &self.config.archive_path else {
+            bail!("no archive_path configured");
+        };
+        let mut line = serde_json::to_string(&serde_json::json!({
+            "archived_at": Utc::now(),
+            "reference_value": rv,
+        }))?;
+        line.push('\n');
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("open archive {path}"))?;
+        file.write_all(line.as_bytes())
+            .await
+            .with_context(|| format!("append to archive {path}"))?;
+        file.flush()
+            .await
+            .with_context(|| format!("flush archive {path}"))
+    }
+
+    /// Log `event` for the `values` not notified about yet, and post it to
+    /// the webhook. Values are notified about again at the next sweep if the
+    /// webhook fails.
+    async fn notify(&mut self, event: ExpiryEvent, values: &[&ReferenceValue]) {
+        let fresh: Vec<_> = values
+            .iter()
+            .copied()
+            .filter(|rv| {
+                !self
+                    .notified
+                    .contains(&(event, rv.storage_key(), rv.expiration))
+            })
+            .collect();
+        if fresh.is_empty() {
+            return;
+        }
+
+        for rv in &fresh {
+            match event {
+                ExpiryEvent::Expiring => warn!(
+                    "Reference value {} of namespace {} expires at {}.",
+                    rv.name(),
+                    rv.namespace(),
+                    rv.expiration
+                ),
+                ExpiryEvent::Expired => warn!(
+                    "Reference value {} of namespace {} expired at {}.",
+                    rv.name(),
+                    rv.namespace(),
+                    rv.expiration
+                ),
+            }
+        }
+
+        if let Some(url) = &self.config.webhook_url {
+            let notification = ExpiryNotification {
+                event,
+                reference_values: fresh.iter().map(|rv| (*rv).into()).collect(),
+            };
+            if let Err(e) = self.post(url, &notification).await {
+                warn!("Expiry webhook {url} failed: {e:#}");
+                return;
+            }
+        }
+
+        self.notified.extend(
+            fresh
+                .iter()
+                .map(|rv| (event, rv.storage_key(), rv.expiration)),
+        );
+    }
+
+    async fn post(&self, url: &str, notification: &ExpiryNotification) -> Result<()> {
+        let mut req = self.http.post(url).json(notification);
+        if let Some(token) = &self.config.webhook_token {
+            req = req.bearer_auth(token);
+        }
+        req.send()
+            .await
+            .context("post notification")?
+            .error_for_status()
+            .context("webhook status")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::HistoryOperation;
+    use crate::storage::{in_memory, ReferenceValueStorageConfig};
+    use crate::Config;
+
+    fn reference_value(name: &str, expiration: DateTime<Utc>) -> ReferenceValue {
+        ReferenceValue::new()
+            .unwrap()
+            .set_name(name)
+            .set_expiration(expiration)
+            .set_value(serde_json::json!(name))
+    }
+
+    #[tokio::test]
+    async fn test_sweep_archives_expired_values() {
+        let rvps = Rvps::new(Config {
+            storage: ReferenceValueStorageConfig::InMemory(in_memory::Config::default()),
+            ..Default::default()
+        })
+        .unwrap();
+        let now = Utc::now();
+        let expiring = reference_value("expiring", now + chrono::Duration::hours(1));
+        for rv in [
+            reference_value("expired", now - chrono::Duration::hours(1)),
+            expiring.clone(),
+            reference_value("valid", now + chrono::Duration::days(30)),
+        ] {
+            rvps.storage.set(rv.name().clone(), rv).await.unwrap();
+        }
+
+        let archive = tempfile::NamedTempFile::new().unwrap();
+        let rvps = Arc::new(RwLock::new(rvps));
+        let mut sweeper = ExpirySweeper::new(
+            ExpiryConfig {
+                action: ExpiredAction::Archive,
+                archive_path: Some(archive.path().to_string_lossy().into_owned()),
+                ..Default::default()
+            },
+            rvps.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(sweeper.sweep_once().await.unwrap(), 1);
+        assert!(sweeper.notified.contains(&(
+            ExpiryEvent::Expiring,
+            "expiring".to_string(),
+            expiring.expiration
+        )));
+        assert_eq!(sweeper.sweep_once().await.unwrap(), 0);
+
+        let archived = std::fs::read_to_string(archive.path()).unwrap();
+        assert_eq!(archived.lines().count(), 1);
+        assert!(archived.contains("\"name\":\"expired\""));
+
+        let rvps = rvps.read().await;
+        assert!(rvps.storage.get("expired").await.unwrap().is_none());
+        let history = rvps
+            .reference_value_history(crate::namespace::DEFAULT_NAMESPACE, "expired")
+            .await
+            .unwrap();
+        assert_eq!(history.last().unwrap().operation, HistoryOperation::Expire);
+        assert!(rvps.storage.get("expiring").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_archives_only_values_it_removed() {
+        let rvps = Rvps::new(Config {
+            storage: ReferenceValueStorageConfig::InMemory(in_memory::Config::default()),
+            ..Default::default()
+        })
+        .unwrap();
+        let archive = tempfile::NamedTempFile::new().unwrap();
+        let sweeper = ExpirySweeper::new(
+            ExpiryConfig {
+                action: ExpiredAction::Archive,
+                archive_path: Some(archive.path().to_string_lossy().into_owned()),
+                ..Default::default()
+            },
+            Arc::new(RwLock::new(rvps)),
+        )
+        .unwrap();
+
+        // Removed by another replica since it was listed.
+        let expired = reference_value("expired", Utc::now() - chrono::Duration::hours(1));
+        assert!(!sweeper.handle_expired(&expired).await.unwrap());
+        assert!(std::fs::read_to_string(archive.path()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,854 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Extractor for signed IETF CoRIM (draft-ietf-rats-corim) carrying CoMID
+//! reference value triples.
+//!
+//! Every reference triple becomes one `ReferenceValue` per measurement. Its
+//! name is derived from the measurement environment and the measurement key,
+//! and its value is a JSON object describing the expected measurement.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use chrono::{DateTime, Months, Timelike, Utc};
+use coset::{
+    cbor::value::{Integer, Value},
+    iana, Algorithm, AsCborValue, ContentType, CoseSign1, Label, RegisteredLabelWithPrivate,
+};
+use serde_json::{json, Map};
+
+use crate::{
+    crypto::PublicKey, extractors::CorimVerificationConfig,
+    reference_value::REFERENCE_VALUE_VERSION, ReferenceValue,
+};
+
+use super::Extractor;
+
+/// Content type of an unsigned CoRIM.
+const CORIM_CONTENT_TYPE: &str = "application/rim+cbor";
+
+/// CBOR tags of draft-ietf-rats-corim.
+const TAG_COSE_SIGN1: u64 = 18;
+const TAG_EPOCH_TIME: u64 = 1;
+const TAG_UUID: u64 = 37;
+const TAG_OID: u64 = 111;
+const TAG_UNSIGNED_CORIM: u64 = 501;
+const TAG_SIGNED_CORIM: u64 = 502;
+const TAG_COMID: u64 = 506;
+const TAG_SVN: u64 = 552;
+const TAG_MIN_SVN: u64 = 553;
+const TAG_MASKED_RAW_VALUE: u64 = 563;
+
+/// COSE header parameter of the `corim-meta` map.
+const HEADER_CORIM_META: i64 = 8;
+
+/// Names of the `flags-map` entries.
+const FLAGS: [&str; 10] = [
+    "is_configured",
+    "is_secure",
+    "is_recovery",
+    "is_debug",
+    "is_replay_protected",
+    "is_integrity_protected",
+    "is_runtime_measured",
+    "is_immutable",
+    "is_tcb",
+    "is_confidentiality_protected",
+];
+
+/// The reference value will be expired in the default time (months), unless
+/// the CoRIM carries its own validity.
+const MONTHS_BEFORE_EXPIRATION: u32 = 12;
+
+/// Verify the COSE_Sign1 `signature` over `tbs` with `key`, which must
+/// match the algorithm of the protected header.
+fn verify_cose(key: &PublicKey, alg: &Algorithm, tbs: &[u8], signature: &[u8]) -> Result<()> {
+    match (key, alg) {
+        (PublicKey::P256(_), RegisteredLabelWithPrivate::Assigned(iana::Algorithm::ES256))
+        | (PublicKey::P384(_), RegisteredLabelWithPrivate::Assigned(iana::Algorithm::ES384)) => {
+            key.verify(tbs, signature)
+        }
+        _ => bail!("key does not match algorithm {alg:?}"),
+    }
+}
+
+/// Extractor for signed CoRIM.
+#[derive(Clone, Default)]
+pub struct CorimExtractor {
+    keys: Arc<Vec<PublicKey>>,
+}
+
+impl CorimExtractor {
+    pub fn new(config: Option<&CorimVerificationConfig>) -> Result<Self> {
+        let keys = config
+            .map(|config| config.public_key_paths.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|path| {
+                let pem = std::fs::read_to_string(path)
+                    .with_context(|| format!("read corim public key {path}"))?;
+                PublicKey::from_pem(&pem).with_context(|| format!("load corim public key {path}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            keys: Arc::new(keys),
+        })
+    }
+
+    /// Verify the COSE_Sign1 signature, and return the unsigned CoRIM map
+    /// with the validity of the signed meta data.
+    fn verify(&self, corim: &[u8]) -> Result<(Vec<(Value, Value)>, Validity)> {
+        if self.keys.is_empty() {
+            bail!("no corim public keys configured");
+        }
+
+        let mut value: Value = coset::cbor::de::from_reader(corim).context("parse corim cbor")?;
+        if let Value::Tag(TAG_SIGNED_CORIM, inner) = value {
+            value = *inner;
+        }
+        let value = match value {
+            Value::Tag(TAG_COSE_SIGN1, inner) => *inner,
+            Value::Tag(TAG_UNSIGNED_CORIM, _) => bail!("unsigned corim is not accepted"),
+            value => value,
+        };
+        let sign1 = CoseSign1::from_cbor_value(value)
+            .map_err(|e| anyhow!("parse corim COSE_Sign1: {e:?}"))?;
+
+        let header = &sign1.protected.header;
+        let alg = header
+            .alg
+            .as_ref()
+            .ok_or_else(|| anyhow!("corim signature algorithm missing"))?;
+        match &header.content_type {
+            None => {}
+            Some(ContentType::Text(content_type)) if content_type == CORIM_CONTENT_TYPE => {}
+            Some(content_type) => bail!("unexpected corim content type {content_type:?}"),
+        }
+
+        let verified = self.keys.iter().any(|key| {
+            sign1
+                .verify_signature(b"", |signature, tbs| verify_cose(key, alg, tbs, signature))
+                .is_ok()
+        });
+        if !verified {
+            bail!("corim signature does not verify with any configured key");
+        }
+
+        let mut validity = Validity::default();
+        if let Some((_, meta)) = header
+            .rest
+            .iter()
+            .find(|(label, _)| *label == Label::Int(HEADER_CORIM_META))
+        {
+            if let Some(v) = map_get(meta, 1) {
+                validity = Validity::parse(v)?;
+            }
+        }
+
+        let payload = sign1
+            .payload
+            .as_deref()
+            .ok_or_else(|| anyhow!("corim payload is detached"))?;
+        let unsigned: Value =
+            coset::cbor::de::from_reader(payload).context("parse unsigned corim")?;
+        match unsigned {
+            Value::Tag(TAG_UNSIGNED_CORIM, inner) => match *inner {
+                Value::Map(map) => Ok((map, validity)),
+                _ => bail!("unsigned corim is not a map"),
+            },
+            Value::Map(map) => Ok((map, validity)),
+            _ => bail!("unexpected unsigned corim"),
+        }
+    }
+}
+
+/// A `validity-map`.
+#[derive(Default)]
+struct Validity {
+    not_before: Option<DateTime<Utc>>,
+    not_after: Option<DateTime<Utc>>,
+}
+
+impl Validity {
+    fn parse(value: &Value) -> Result<Self> {
+        let time = |key| -> Result<Option<DateTime<Utc>>> {
+            let Some(value) = map_get(value, key) else {
+                return Ok(None);
+            };
+            let secs = match value {
+                Value::Tag(TAG_EPOCH_TIME, inner) => as_i64(inner),
+                value => as_i64(value),
+            }
+            .ok_or_else(|| anyhow!("invalid corim validity time"))?;
+            DateTime::from_timestamp(secs, 0)
+                .map(Some)
+                .ok_or_else(|| anyhow!("corim validity time out of range"))
+        };
+        Ok(Self {
+            not_before: time(0)?,
+            not_after: time(1)?,
+        })
+    }
+
+    /// Narrow `self` with `other`.
+    fn intersect(self, other: Self) -> Self {
+        Self {
+            not_before: self.not_before.max(other.not_before),
+            not_after: match (self.not_after, other.not_after) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+}
+
+fn map_get(map: &Value, key: i64) -> Option<&Value> {
+    map.as_map()?
+        .iter()
+        .find(|(k, _)| as_i64(k) == Some(key))
+        .map(|(_, v)| v)
+}
+
+fn as_i64(value: &Value) -> Option<i64> {
+    value.as_integer().and_then(|i| i64::try_from(i).ok())
+}
+
+fn integer_to_json(i: Integer) -> serde_json::Value {
+    let i = i128::from(i);
+    match (i64::try_from(i), u64::try_from(i)) {
+        (Ok(i), _) => json!(i),
+        (_, Ok(u)) => json!(u),
+        _ => json!(i.to_string()),
+    }
+}
+
+/// Decode a BER encoded object identifier into its dotted form.
+fn oid_to_string(bytes: &[u8]) -> Result<String> {
+    let mut arcs = Vec::new();
+    let mut arc: u64 = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        arc = arc
+            .checked_mul(128)
+            .ok_or_else(|| anyhow!("oid arc overflow"))?
+            | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (arc / 40).min(2);
+                arcs.push(first);
+                arcs.push(arc - first * 40);
+            } else {
+                arcs.push(arc);
+            }
+            arc = 0;
+        } else if i == bytes.len() - 1 {
+            bail!("truncated oid");
+        }
+    }
+    if arcs.is_empty() {
+        bail!("empty oid");
+    }
+    Ok(arcs
+        .iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join("."))
+}
+
+fn uuid_to_string(bytes: &[u8]) -> Result<String> {
+    if bytes.len() != 16 {
+        bail!("uuid must be 16 bytes");
+    }
+    let hex = hex::encode(bytes);
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+/// Render a CoRIM identifier (class id, instance, mkey, ...) as a string.
+fn id_to_string(value: &Value) -> Result<String> {
+    Ok(match value {
+        Value::Text(s) => s.clone(),
+        Value::Integer(i) => i128::from(*i).to_string(),
+        Value::Bytes(b) => hex::encode(b),
+        Value::Tag(TAG_OID, inner) => match &**inner {
+            Value::Bytes(b) => oid_to_string(b)?,
+            _ => bail!("oid must be bytes"),
+        },
+        Value::Tag(TAG_UUID, inner) => match &**inner {
+            Value::Bytes(b) => uuid_to_string(b)?,
+            _ => bail!("uuid must be bytes"),
+        },
+        Value::Tag(_, inner) => id_to_string(inner)?,
+        _ => bail!("unsupported corim identifier"),
+    })
+}
+
+/// Convert any CBOR value to JSON: byte strings become hex, and the tagged
+/// identifiers of CoRIM their usual string forms.
+fn cbor_to_json(value: &Value) -> Result<serde_json::Value> {
+    Ok(match value {
+        Value::Integer(i) => integer_to_json(*i),
+        Value::Bytes(b) => json!(hex::encode(b)),
+        Value::Float(f) => json!(f),
+        Value::Text(s) => json!(s),
+        Value::Bool(b) => json!(b),
+        Value::Null => serde_json::Value::Null,
+        Value::Tag(TAG_OID | TAG_UUID, _) => json!(id_to_string(value)?),
+        Value::Tag(_, inner) => cbor_to_json(inner)?,
+        Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(cbor_to_json).collect::<Result<_>>()?)
+        }
+        Value::Map(entries) => {
+            let mut map = Map::new();
+            for (k, v) in entries {
+                map.insert(id_to_string(k)?, cbor_to_json(v)?);
+            }
+            serde_json::Value::Object(map)
+        }
+        _ => bail!("unsupported cbor value"),
+    })
+}
+
+/// Name of a hash algorithm of the IANA Named Information registry.
+fn hash_alg_name(alg: &Value) -> Result<String> {
+    Ok(match alg {
+        Value::Text(name) => name.to_lowercase(),
+        alg => match as_i64(alg).ok_or_else(|| anyhow!("invalid digest algorithm"))? {
+            1 => "sha256".into(),
+            7 => "sha384".into(),
+            8 => "sha512".into(),
+            10 => "sha3-256".into(),
+            11 => "sha3-384".into(),
+            12 => "sha3-512".into(),
+            id => format!("alg-{id}"),
+        },
+    })
+}
+
+/// Convert `digests` (`[+ [alg, bytes]]`) to `{ "<alg>": ["<hex>"] }`.
+fn digests_to_json(digests: &Value) -> Result<serde_json::Value> {
+    let mut map = Map::new();
+    for digest in digests
+        .as_array()
+        .ok_or_else(|| anyhow!("digests must be an array"))?
+    {
+        let [alg, Value::Bytes(value)] = digest.as_array().map(Vec::as_slice).unwrap_or_default()
+        else {
+            bail!("invalid digest entry");
+        };
+        let entry = map.entry(hash_alg_name(alg)?).or_insert_with(|| json!([]));
+        if let Some(values) = entry.as_array_mut() {
+            values.push(json!(hex::encode(value)));
+        }
+    }
+    Ok(serde_json::Value::Object(map))
+}
+
+/// Convert a `measurement-values-map`.
+fn measurement_values_to_json(mval: &Value) -> Result<Map<String, serde_json::Value>> {
+    let mut out = Map::new();
+    for (key, value) in mval
+        .as_map()
+        .ok_or_else(|| anyhow!("measurement values must be a map"))?
+    {
+        let Some(key) = as_i64(key) else {
+            out.insert(id_to_string(key)?, cbor_to_json(value)?);
+            continue;
+        };
+        match key {
+            0 => {
+                if let Some(Value::Text(version)) = map_get(value, 0) {
+                    out.insert("version".into(), json!(version));
+                }
+                if let Some(scheme) = map_get(value, 1) {
+                    out.insert("version_scheme".into(), cbor_to_json(scheme)?);
+                }
+            }
+            1 => {
+                let svn = match value {
+                    Value::Tag(TAG_MIN_SVN, inner) => json!({ "min": cbor_to_json(inner)? }),
+                    Value::Tag(TAG_SVN, inner) => json!({ "exact": cbor_to_json(inner)? }),
+                    value => json!({ "exact": cbor_to_json(value)? }),
+                };
+                out.insert("svn".into(), svn);
+            }
+            2 => {
+                out.insert("digests".into(), digests_to_json(value)?);
+            }
+            3 => {
+                let mut flags = Map::new();
+                for (flag, set) in value
+                    .as_map()
+                    .ok_or_else(|| anyhow!("flags must be a map"))?
+                {
+                    let name = as_i64(flag)
+                        .and_then(|i| usize::try_from(i).ok())
+                        .and_then(|i| FLAGS.get(i))
+                        .map(|name| name.to_string())
+                        .map_or_else(|| id_to_string(flag), Ok)?;
+                    flags.insert(name, cbor_to_json(set)?);
+                }
+                out.insert("flags".into(), serde_json::Value::Object(flags));
+            }
+            4 => match value {
+                Value::Tag(TAG_MASKED_RAW_VALUE, inner) => {
+                    let [Value::Bytes(raw), Value::Bytes(mask)] =
+                        inner.as_array().map(Vec::as_slice).unwrap_or_default()
+                    else {
+                        bail!("invalid masked raw value");
+                    };
+                    out.insert("raw_value".into(), json!(hex::encode(raw)));
+                    out.insert("raw_value_mask".into(), json!(hex::encode(mask)));
+                }
+                value => {
+                    out.insert("raw_value".into(), cbor_to_json(value)?);
+                }
+            },
+            5 => {
+                out.insert("raw_value_mask".into(), cbor_to_json(value)?);
+            }
+            14 => {
+                let mut registers = Map::new();
+                for (index, digests) in value
+                    .as_map()
+                    .ok_or_else(|| anyhow!("integrity registers must be a map"))?
+                {
+                    registers.insert(id_to_string(index)?, digests_to_json(digests)?);
+                }
+                out.insert(
+                    "integrity_registers".into(),
+                    serde_json::Value::Object(registers),
+                );
+            }
+            key => {
+                let name = match key {
+                    6 => "mac_addr".to_string(),
+                    7 => "ip_addr".to_string(),
+                    8 => "serial_number".to_string(),
+                    9 => "ueid".to_string(),
+                    10 => "uuid".to_string(),
+                    11 => "name".to_string(),
+                    13 => "cryptokeys".to_string(),
+                    // Profile defined extensions
+                    key => key.to_string(),
+                };
+                out.insert(name, cbor_to_json(value)?);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Convert an `environment-map`, and return it with the reference value
+/// name prefix it maps to.
+fn environment_to_json(env: &Value) -> Result<(String, serde_json::Value)> {
+    let mut parts = Vec::new();
+    let mut out = Map::new();
+
+    if let Some(class) = map_get(env, 0) {
+        let mut class_json = Map::new();
+        let id = map_get(class, 0).map(id_to_string).transpose()?;
+        let vendor = map_get(class, 1).map(id_to_string).transpose()?;
+        let model = map_get(class, 2).map(id_to_string).transpose()?;
+        let layer = map_get(class, 3).and_then(as_i64);
+        let index = map_get(class, 4).and_then(as_i64);
+
+        // The class id identifies the class on its own; vendor and model
+        // name it otherwise.
+        match &id {
+            Some(id) => parts.push(id.clone()),
+            None => parts.extend(vendor.iter().chain(model.iter()).cloned()),
+        }
+        if let Some(layer) = layer {
+            parts.push(format!("layer-{layer}"));
+        }
+        if let Some(index) = index {
+            parts.push(format!("index-{index}"));
+        }
+
+        for (name, value) in [("id", id), ("vendor", vendor), ("model", model)] {
+            if let Some(value) = value {
+                class_json.insert(name.into(), json!(value));
+            }
+        }
+        for (name, value) in [("layer", layer), ("index", index)] {
+            if let Some(value) = value {
+                class_json.insert(name.into(), json!(value));
+            }
+        }
+        out.insert("class".into(), serde_json::Value::Object(class_json));
+    }
+    if let Some(instance) = map_get(env, 1) {
+        let instance = id_to_string(instance)?;
+        parts.push(instance.clone());
+        out.insert("instance".into(), json!(instance));
+    }
+    if let Some(group) = map_get(env, 2) {
+        out.insert("group".into(), json!(id_to_string(group)?));
+    }
+
+    if parts.is_empty() {
+        bail!("corim environment has neither class nor instance");
+    }
+    Ok((parts.join("/"), serde_json::Value::Object(out)))
+}
+
+/// Merge a measurement into an already extracted one of the same name:
+/// digests are alternatives, other fields keep their first value.
+fn merge_measurement(into: &mut serde_json::Value, from: serde_json::Value) {
+    let (Some(into), serde_json::Value::Object(from)) = (into.as_object_mut(), from) else {
+        return;
+    };
+    for (key, value) in from {
+        match (into.get_mut(&key), value) {
+            (Some(serde_json::Value::Object(digests)), serde_json::Value::Object(more))
+                if key == "digests" =>
+            {
+                for (alg, values) in more {
+                    let entry = digests.entry(alg).or_insert_with(|| json!([]));
+                    if let (Some(entry), serde_json::Value::Array(values)) =
+                        (entry.as_array_mut(), values)
+                    {
+                        for value in values {
+                            if !entry.contains(&value) {
+                                entry.push(value);
+                            }
+                        }
+                    }
+                }
+            }
+            (None, value) => {
+                into.insert(key, value);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Extractor for CorimExtractor {
+    /// The provenance is a base64 encoded signed CoRIM.
+    fn verify_and_extract(&self, provenance_base64: &str) -> Result<Vec<ReferenceValue>> {
+        let corim = base64::engine::general_purpose::STANDARD
+            .decode(provenance_base64.trim())
+            .context("base64 decode")?;
+        let (corim, signed_validity) = self.verify(&corim)?;
+        let corim = Value::Map(corim);
+
+        let validity = match map_get(&corim, 4) {
+            Some(rim_validity) => signed_validity.intersect(Validity::parse(rim_validity)?),
+            None => signed_validity,
+        };
+        let now = Utc::now();
+        if validity.not_before.is_some_and(|t| now < t) {
+            bail!("corim is not valid yet");
+        }
+        if validity.not_after.is_some_and(|t| t < now) {
+            bail!("corim has expired");
+        }
+        let expiration = match validity.not_after {
+            Some(not_after) => not_after,
+            None => now
+                .with_nanosecond(0)
+                .and_then(|t| t.checked_add_months(Months::new(MONTHS_BEFORE_EXPIRATION)))
+                .ok_or_else(|| anyhow!("failed to compute expiration time"))?,
+        };
+
+        let tags = map_get(&corim, 1)
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow!("corim carries no tags"))?;
+
+        let mut measurements: Vec<(String, serde_json::Value)> = Vec::new();
+        for tag in tags {
+            // Only CoMID tags carry reference values; CoSWID and CoTL tags
+            // are skipped.
+            let Value::Tag(TAG_COMID, comid) = tag else {
+                continue;
+            };
+            let Value::Bytes(comid) = &**comid else {
+                bail!("comid tag must wrap a byte string");
+            };
+            let comid: Value =
+                coset::cbor::de::from_reader(comid.as_slice()).context("parse comid")?;
+            let tag_id = map_get(&comid, 1)
+                .and_then(|identity| map_get(identity, 0))
+                .map(id_to_string)
+                .transpose()?;
+
+            let Some(reference_triples) = map_get(&comid, 4).and_then(|t| map_get(t, 0)) else {
+                continue;
+            };
+            for triple in reference_triples
+                .as_array()
+                .ok_or_else(|| anyhow!("reference triples must be an array"))?
+            {
+                let [env, mmaps] = triple.as_array().map(Vec::as_slice).unwrap_or_default() else {
+                    bail!("invalid reference triple");
+                };
+                let (env_name, env_json) = environment_to_json(env)?;
+
+                // Older drafts carry a single measurement map.
+                let mmaps = match mmaps {
+                    Value::Array(mmaps) => mmaps.as_slice(),
+                    mmap => std::slice::from_ref(mmap),
+                };
+                for mmap in mmaps {
+                    let mkey = map_get(mmap, 0).map(id_to_string).transpose()?;
+                    let mval =
+                        map_get(mmap, 1).ok_or_else(|| anyhow!("measurement without values"))?;
+
+                    let mut value = measurement_values_to_json(mval)?;
+                    value.insert("environment".into(), env_json.clone());
+                    if let Some(mkey) = &mkey {
+                        value.insert("mkey".into(), json!(mkey));
+                    }
+                    if let Some(tag_id) = &tag_id {
+                        value.insert("tag_id".into(), json!(tag_id));
+                    }
+
+                    let name = match &mkey {
+                        Some(mkey) => format!("{env_name}/{mkey}"),
+                        None => env_name.clone(),
+                    };
+                    let value = serde_json::Value::Object(value);
+                    match measurements.iter_mut().find(|(n, _)| *n == name) {
+                        Some((_, existing)) => merge_measurement(existing, value),
+                        None => measurements.push((name, value)),
+                    }
+                }
+            }
+        }
+
+        if measurements.is_empty() {
+            bail!("corim carries no reference values");
+        }
+
+        measurements
+            .into_iter()
+            .map(|(name, value)| {
+                Ok(ReferenceValue::new()?
+                    .set_version(REFERENCE_VALUE_VERSION)
+                    .set_name(&name)
+                    .set_expiration(expiration)
+                    .set_value(value))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coset::{cbor::value::Value, CoseSign1Builder, HeaderBuilder, TaggedCborSerializable};
+    use p256::ecdsa::{signature::Signer, SigningKey};
+    use p256::pkcs8::EncodePublicKey;
+
+    fn cbor(value: Value) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        coset::cbor::ser::into_writer(&value, &mut bytes).unwrap();
+        bytes
+    }
+
+    fn int(i: i64) -> Value {
+        Value::Integer(i.into())
+    }
+
+    fn map(entries: Vec<(i64, Value)>) -> Value {
+        Value::Map(entries.into_iter().map(|(k, v)| (int(k), v)).collect())
+    }
+
+    fn corim(not_after: i64) -> Vec<u8> {
+        let digest = |alg, byte| Value::Array(vec![int(alg), Value::Bytes(vec![byte; 4])]);
+        let comid = map(vec![
+            (1, map(vec![(0, Value::Text("tdx-comid".into()))])),
+            (
+                4,
+                map(vec![(
+                    0,
+                    Value::Array(vec![
+                        Value::Array(vec![
+                            map(vec![(
+                                0,
+                                map(vec![
+                                    (1, Value::Text("Intel Corporation".into())),
+                                    (2, Value::Text("TDX".into())),
+                                ]),
+                            )]),
+                            Value::Array(vec![
+                                map(vec![
+                                    (0, Value::Text("mrtd".into())),
+                                    (1, map(vec![(2, Value::Array(vec![digest(7, 0xaa)]))])),
+                                ]),
+                                map(vec![
+                                    (0, Value::Text("tcb".into())),
+                                    (
+                                        1,
+                                        map(vec![
+                                            (1, Value::Tag(TAG_MIN_SVN, Box::new(int(3)))),
+                                            (3, map(vec![(3, Value::Bool(false))])),
+                                            (-72, Value::Text("2025-01-01".into())),
+                                        ]),
+                                    ),
+                                ]),
+                            ]),
+                        ]),
+                        // Another acceptable MRTD
+                        Value::Array(vec![
+                            map(vec![(
+                                0,
+                                map(vec![
+                                    (1, Value::Text("Intel Corporation".into())),
+                                    (2, Value::Text("TDX".into())),
+                                ]),
+                            )]),
+                            Value::Array(vec![map(vec![
+                                (0, Value::Text("mrtd".into())),
+                                (1, map(vec![(2, Value::Array(vec![digest(7, 0xbb)]))])),
+                            ])]),
+                        ]),
+                        Value::Array(vec![
+                            map(vec![(
+                                0,
+                                map(vec![(
+                                    0,
+                                    Value::Tag(
+                                        TAG_OID,
+                                        Box::new(Value::Bytes(vec![0x2b, 0x06, 0x01, 0x04, 0x01])),
+                                    ),
+                                )]),
+                            )]),
+                            Value::Array(vec![map(vec![(
+                                1,
+                                map(vec![(
+                                    4,
+                                    Value::Tag(
+                                        TAG_MASKED_RAW_VALUE,
+                                        Box::new(Value::Array(vec![
+                                            Value::Bytes(vec![1, 2]),
+                                            Value::Bytes(vec![0xff, 0]),
+                                        ])),
+                                    ),
+                                )]),
+                            )])]),
+                        ]),
+                    ]),
+                )]),
+            ),
+        ]);
+        let unsigned = Value::Tag(
+            TAG_UNSIGNED_CORIM,
+            Box::new(map(vec![
+                (0, Value::Text("corim-1".into())),
+                (
+                    1,
+                    Value::Array(vec![Value::Tag(
+                        TAG_COMID,
+                        Box::new(Value::Bytes(cbor(comid))),
+                    )]),
+                ),
+                (
+                    4,
+                    map(vec![(
+                        1,
+                        Value::Tag(TAG_EPOCH_TIME, Box::new(int(not_after))),
+                    )]),
+                ),
+            ])),
+        );
+        cbor(unsigned)
+    }
+
+    fn sign(payload: Vec<u8>, key: &SigningKey) -> String {
+        let protected = HeaderBuilder::new()
+            .algorithm(iana::Algorithm::ES256)
+            .content_type(CORIM_CONTENT_TYPE.into())
+            .build();
+        let sign1 = CoseSign1Builder::new()
+            .protected(protected)
+            .payload(payload)
+            .create_signature(b"", |tbs| {
+                let signature: p256::ecdsa::Signature = key.sign(tbs);
+                signature.to_vec()
+            })
+            .build();
+        base64::engine::general_purpose::STANDARD.encode(sign1.to_tagged_vec().unwrap())
+    }
+
+    fn extractor(key: &SigningKey) -> CorimExtractor {
+        let pem = key
+            .verifying_key()
+            .to_public_key_pem(Default::default())
+            .unwrap();
+        CorimExtractor {
+            keys: Arc::new(vec![PublicKey::from_pem(&pem).unwrap()]),
+        }
+    }
+
+    #[test]
+    fn test_extract_signed_corim() {
+        let key = SigningKey::from_slice(&[9u8; 32]).unwrap();
+        let not_after = Utc::now().timestamp() + 3600;
+        let rvs = extractor(&key)
+            .verify_and_extract(&sign(corim(not_after), &key))
+            .unwrap();
+
+        let names: Vec<_> = rvs.iter().map(|rv| rv.name().as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Intel Corporation/TDX/mrtd",
+                "Intel Corporation/TDX/tcb",
+                "1.3.6.1.4.1"
+            ]
+        );
+        assert_eq!(rvs[0].expiration.timestamp(), not_after);
+        assert_eq!(
+            rvs[0].policy_value(),
+            json!({
+                "digests": { "sha384": ["aaaaaaaa", "bbbbbbbb"] },
+                "environment": { "class": { "vendor": "Intel Corporation", "model": "TDX" } },
+                "mkey": "mrtd",
+                "tag_id": "tdx-comid",
+            })
+        );
+        assert_eq!(rvs[1].policy_value()["svn"], json!({ "min": 3 }));
+        assert_eq!(rvs[1].policy_value()["flags"], json!({ "is_debug": false }));
+        assert_eq!(rvs[1].policy_value()["-72"], json!("2025-01-01"));
+        assert_eq!(rvs[2].policy_value()["raw_value"], json!("0102"));
+        assert_eq!(rvs[2].policy_value()["raw_value_mask"], json!("ff00"));
+    }
+
+    #[test]
+    fn test_reject_corim() {
+        let key = SigningKey::from_slice(&[9u8; 32]).unwrap();
+        let other = SigningKey::from_slice(&[10u8; 32]).unwrap();
+        let not_after = Utc::now().timestamp() + 3600;
+
+        // Signed by an unknown key
+        assert!(extractor(&key)
+            .verify_and_extract(&sign(corim(not_after), &other))
+            .is_err());
+
+        // Expired
+        assert!(extractor(&key)
+            .verify_and_extract(&sign(corim(not_after - 7200), &key))
+            .is_err());
+
+        // Unsigned
+        let unsigned = base64::engine::general_purpose::STANDARD.encode(corim(not_after));
+        assert!(extractor(&key).verify_and_extract(&unsigned).is_err());
+
+        // No keys configured
+        assert!(CorimExtractor::default()
+            .verify_and_extract(&sign(corim(not_after), &key))
+            .is_err());
+    }
+}
@@ -0,0 +1,369 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Compute the launch measurements of a confidential guest from the boot
+//! artifacts and VM configuration, and register them as reference values.
+
+mod ovmf;
+mod snp;
+mod tdx;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use chrono::{Months, Timelike, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::{reference_value::REFERENCE_VALUE_VERSION, ReferenceValue};
+
+use super::Extractor;
+
+pub use snp::VmmType;
+
+/// The reference value will be expired in the default time (months)
+const MONTHS_BEFORE_EXPIRATION: u32 = 12;
+
+/// Hash algorithm of both the TDX and SNP measurements.
+const MEASUREMENT_ALG: &str = "sha384";
+
+/// Binary content, base64 encoded in JSON.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Blob(pub Vec<u8>);
+
+impl Serialize for Blob {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Blob {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map(Blob)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// A UEFI boot variable measured into RTMR[0].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BootVariable {
+    pub name: String,
+    pub data: Blob,
+}
+
+/// Artifacts and configuration of a TD launched by QEMU.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct TdxInputs {
+    pub ovmf: Blob,
+    pub kernel: Blob,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initrd: Option<Blob>,
+    #[serde(default)]
+    pub cmdline: String,
+    /// Guest memory size, which decides where QEMU places the initrd.
+    pub memory_mb: u64,
+
+    /// The TD HOB and ACPI tables QEMU hands to TDVF. They depend on the
+    /// whole VM configuration, so RTMR[0] is only computed when all of them
+    /// are given, usually dumped from a reference VM.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub td_hob: Option<Blob>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acpi_loader: Option<Blob>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acpi_rsdp: Option<Blob>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acpi_tables: Option<Blob>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub boot_variables: Vec<BootVariable>,
+}
+
+/// Artifacts and configuration of an SEV-SNP guest.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SnpInputs {
+    pub ovmf: Blob,
+    /// Kernel, initrd and cmdline of a direct kernel boot. They are measured
+    /// through the hashes table of OVMF.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kernel: Option<Blob>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initrd: Option<Blob>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cmdline: Option<String>,
+
+    pub vcpus: u32,
+    /// QEMU vCPU model, e.g. `EPYC-Milan`. Ignored if `vcpu_sig` is set.
+    #[serde(default = "default_vcpu_type")]
+    pub vcpu_type: String,
+    /// CPUID signature of the vCPUs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vcpu_sig: Option<u32>,
+    #[serde(default)]
+    pub vmm_type: VmmType,
+    /// SEV features enabled in the VMSA.
+    #[serde(default = "default_guest_features")]
+    pub guest_features: u64,
+
+    /// Guest policy the guest is launched with. When set, the policy fields
+    /// checked by the attestation policy are registered too.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<u64>,
+}
+
+fn default_vcpu_type() -> String {
+    "EPYC-v4".into()
+}
+
+fn default_guest_features() -> u64 {
+    // SNPActive
+    0x1
+}
+
+/// Payload of a `launch-measurement` message. At least one TEE must be
+/// given.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct LaunchMeasurementProvenance {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tdx: Option<TdxInputs>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snp: Option<SnpInputs>,
+}
+
+#[derive(Default)]
+pub struct LaunchMeasurementExtractor;
+
+impl Extractor for LaunchMeasurementExtractor {
+    fn verify_and_extract(&self, provenance_base64: &str) -> Result<Vec<ReferenceValue>> {
+        let provenance = base64::engine::general_purpose::STANDARD
+            .decode(provenance_base64)
+            .context("base64 decode")?;
+        let provenance: LaunchMeasurementProvenance = serde_json::from_slice(&provenance)
+            .context("deserialize launch measurement provenance")?;
+
+        compute_reference_values(&provenance)
+    }
+}
+
+/// Compute the reference values of the launch measurements described by
+/// `provenance`.
+pub fn compute_reference_values(
+    provenance: &LaunchMeasurementProvenance,
+) -> Result<Vec<ReferenceValue>> {
+    if provenance.tdx.is_none() && provenance.snp.is_none() {
+        bail!("launch measurement provenance has neither tdx nor snp inputs");
+    }
+
+    let expiration = Utc::now()
+        .with_nanosecond(0)
+        .and_then(|t| t.checked_add_months(Months::new(MONTHS_BEFORE_EXPIRATION)))
+        .ok_or_else(|| anyhow!("failed to compute expiration time"))?;
+    let rv = |name: &str| -> Result<ReferenceValue> {
+        Ok(ReferenceValue::new()?
+            .set_version(REFERENCE_VALUE_VERSION)
+            .set_name(name)
+            .set_expiration(expiration))
+    };
+    let digest = |name: &str, digest: &[u8]| -> Result<ReferenceValue> {
+        Ok(rv(name)?.add_hash_value(MEASUREMENT_ALG.into(), hex::encode(digest)))
+    };
+
+    let mut rvs = Vec::new();
+    if let Some(inputs) = &provenance.tdx {
+        let measurements = tdx::measure(inputs).context("compute TDX measurements")?;
+        rvs.push(digest("tdx.mr_td", &measurements.mr_td)?);
+        if let Some(rtmr_0) = &measurements.rtmr_0 {
+            rvs.push(digest("tdx.rtmr_0", rtmr_0)?);
+        }
+        rvs.push(digest("tdx.rtmr_1", &measurements.rtmr_1)?);
+        rvs.push(digest("tdx.rtmr_2", &measurements.rtmr_2)?);
+    }
+
+    if let Some(inputs) = &provenance.snp {
+        let measurement = snp::measure(inputs).context("compute SNP measurement")?;
+        // The SNP verifier reports the measurement base64 encoded.
+        rvs.push(rv("snp.measurement")?.add_hash_value(
+            MEASUREMENT_ALG.into(),
+            base64::engine::general_purpose::STANDARD.encode(measurement),
+        ));
+
+        if let Some(policy) = inputs.policy {
+            for (name, value) in [
+                ("snp.policy_abi_minor", policy & 0xff),
+                ("snp.policy_abi_major", (policy >> 8) & 0xff),
+                ("snp.policy_smt_allowed", (policy >> 16) & 1),
+                ("snp.policy_migrate_ma", (policy >> 18) & 1),
+                ("snp.policy_debug_allowed", (policy >> 19) & 1),
+                ("snp.policy_single_socket", (policy >> 20) & 1),
+            ] {
+                rvs.push(rv(name)?.set_value(Value::Array(vec![value.to_string().into()])));
+            }
+        }
+    }
+
+    Ok(rvs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ovmf::tests::build_ovmf;
+    use super::*;
+
+    const SEV_METADATA_OFFSET_GUID: &str = "dc886566-984a-4798-a75e-5585a7bf67cc";
+    const SEV_ES_RESET_BLOCK_GUID: &str = "00f771de-1a7e-4fcb-890e-68c77e2fb44e";
+    const SEV_HASH_TABLE_RV_GUID: &str = "7255371f-3a3b-4b04-927b-1da6efa8d454";
+    const TDX_METADATA_OFFSET_GUID: &str = "e47a6535-984a-4798-865e-4685a7bf8ec2";
+
+    /// An OVMF image carrying both TDVF and SEV metadata.
+    fn ovmf() -> Vec<u8> {
+        let size = 0x4000;
+        let mut image = build_ovmf(
+            size,
+            &[
+                (
+                    TDX_METADATA_OFFSET_GUID,
+                    ((size - 0x100) as u32).to_le_bytes().to_vec(),
+                ),
+                (
+                    SEV_METADATA_OFFSET_GUID,
+                    ((size - 0x200) as u32).to_le_bytes().to_vec(),
+                ),
+                (
+                    SEV_ES_RESET_BLOCK_GUID,
+                    0xffff_f000u32.to_le_bytes().to_vec(),
+                ),
+                (SEV_HASH_TABLE_RV_GUID, 0x80c00u32.to_le_bytes().to_vec()),
+            ],
+        );
+
+        // TDVF: a BFV measured page by page and a CFV, both read from the
+        // image.
+        let tdvf = 0x100;
+        image[tdvf..tdvf + 4].copy_from_slice(b"TDVF");
+        image[tdvf + 12..tdvf + 16].copy_from_slice(&2u32.to_le_bytes());
+        for (i, (offset, address, section_type, attributes)) in [
+            (0u32, 0xffff_c000u64, 0u32, 1u32),
+            (0x1000, 0xffff_d000, 1, 0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let entry = tdvf + 16 + i * 32;
+            image[entry..entry + 4].copy_from_slice(&offset.to_le_bytes());
+            image[entry + 4..entry + 8].copy_from_slice(&0x1000u32.to_le_bytes());
+            image[entry + 8..entry + 16].copy_from_slice(&address.to_le_bytes());
+            image[entry + 16..entry + 24].copy_from_slice(&0x1000u64.to_le_bytes());
+            image[entry + 24..entry + 28].copy_from_slice(&section_type.to_le_bytes());
+            image[entry + 28..entry + 32].copy_from_slice(&attributes.to_le_bytes());
+        }
+
+        // SEV: secrets, CPUID and kernel hashes pages.
+        let asev = 0x200;
+        image[asev..asev + 4].copy_from_slice(b"ASEV");
+        image[asev + 12..asev + 16].copy_from_slice(&3u32.to_le_bytes());
+        for (i, (gpa, section_type)) in [(0x80000u32, 2u32), (0x81000, 3), (0x80000, 0x10)]
+            .into_iter()
+            .enumerate()
+        {
+            let entry = asev + 16 + i * 12;
+            image[entry..entry + 4].copy_from_slice(&gpa.to_le_bytes());
+            image[entry + 4..entry + 8].copy_from_slice(&0x1000u32.to_le_bytes());
+            image[entry + 8..entry + 12].copy_from_slice(&section_type.to_le_bytes());
+        }
+
+        image[0x1000..0x2000].fill(0xcf);
+        image
+    }
+
+    /// A bzImage setup header wrapped in a minimal PE32+ image.
+    fn kernel() -> Vec<u8> {
+        let mut kernel = vec![0u8; 0x400];
+        kernel[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        kernel[0x40..0x44].copy_from_slice(b"PE\0\0");
+        kernel[0x58..0x5a].copy_from_slice(&0x20bu16.to_le_bytes());
+        kernel[0x58 + 60..0x58 + 64].copy_from_slice(&0x400u32.to_le_bytes());
+        kernel[0x202..0x206].copy_from_slice(b"HdrS");
+        kernel[0x206..0x208].copy_from_slice(&0x20fu16.to_le_bytes());
+        kernel[0x211] = 0x01;
+        kernel[0x22c..0x230].copy_from_slice(&0x7fff_ffffu32.to_le_bytes());
+        kernel
+    }
+
+    #[test]
+    fn test_compute_reference_values() {
+        let provenance = LaunchMeasurementProvenance {
+            tdx: Some(TdxInputs {
+                ovmf: Blob(ovmf()),
+                kernel: Blob(kernel()),
+                initrd: Some(Blob(vec![1; 16])),
+                cmdline: "console=hvc0".into(),
+                memory_mb: 2048,
+                ..Default::default()
+            }),
+            snp: Some(SnpInputs {
+                ovmf: Blob(ovmf()),
+                kernel: Some(Blob(kernel())),
+                cmdline: Some("console=hvc0".into()),
+                vcpus: 2,
+                vcpu_type: default_vcpu_type(),
+                guest_features: default_guest_features(),
+                policy: Some(0x30000),
+                ..Default::default()
+            }),
+        };
+        let provenance_base64 = base64::engine::general_purpose::STANDARD
+            .encode(serde_json::to_vec(&provenance).unwrap());
+        let rvs = LaunchMeasurementExtractor
+            .verify_and_extract(&provenance_base64)
+            .unwrap();
+
+        let names: Vec<_> = rvs.iter().map(|rv| rv.name()).collect();
+        assert_eq!(
+            names,
+            [
+                "tdx.mr_td",
+                "tdx.rtmr_1",
+                "tdx.rtmr_2",
+                "snp.measurement",
+                "snp.policy_abi_minor",
+                "snp.policy_abi_major",
+                "snp.policy_smt_allowed",
+                "snp.policy_migrate_ma",
+                "snp.policy_debug_allowed",
+                "snp.policy_single_socket",
+            ]
+        );
+        // Values this implementation computes for the inputs above, kept to
+        // catch regressions. They are not checked against another measurement
+        // tool, so changing one needs a reason from the TDX module or SNP
+        // firmware ABI specification.
+        let values: Vec<_> = rvs[..4]
+            .iter()
+            .map(|rv| rv.hash_values()[0].value().as_str())
+            .collect();
+        assert_eq!(
+            values,
+            [
+                "5523a6aa0b4f0b93781f743042d56c8fd964a22b33c51d3d10f67fb0b63265969a0cb1529e083324b66a5e6a777d4f15",
+                "d4a12c3f8bb6bd5f58b170e4dccd9a626f2df3206c1856d693901f38b766d223f21370e61eed714d39f7270c30b6239b",
+                "b3ff9fb4b67719d3f9e96c7aa1803ebe2d53c45724e7a2a330b926a281ca873598f082aa97fa03c404624394beb9a904",
+                "I763+8x4HJVJZq+whmhLqVoxcsmZiDq2Ha+Qs7q4pB0VMUZOsbuojHOXPqpbJDdm",
+            ]
+        );
+        assert_eq!(rvs[6].policy_value(), serde_json::json!(["1"]));
+
+        // Every input is measured.
+        let mut changed = provenance.clone();
+        changed.tdx.as_mut().unwrap().cmdline.push_str(" quiet");
+        changed.snp.as_mut().unwrap().vcpus = 1;
+        let changed = compute_reference_values(&changed).unwrap();
+        assert_eq!(changed[0].hash_values(), rvs[0].hash_values());
+        assert_ne!(changed[2].hash_values(), rvs[2].hash_values());
+        assert_ne!(changed[3].hash_values(), rvs[3].hash_values());
+
+        assert!(compute_reference_values(&LaunchMeasurementProvenance::default()).is_err());
+    }
+}
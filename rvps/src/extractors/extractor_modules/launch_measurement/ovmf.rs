@@ -0,0 +1,255 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Parsing of the OVMF footer GUID table and of the TDX and SEV metadata it
+//! points to.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+
+/// The firmware is mapped right below 4 GiB.
+const FOUR_GB: u64 = 0x1_0000_0000;
+
+const OVMF_TABLE_FOOTER_GUID: &str = "96b582de-1fb2-45f7-baea-a366c55a082d";
+const TDX_METADATA_OFFSET_GUID: &str = "e47a6535-984a-4798-865e-4685a7bf8ec2";
+const SEV_METADATA_OFFSET_GUID: &str = "dc886566-984a-4798-a75e-5585a7bf67cc";
+const SEV_ES_RESET_BLOCK_GUID: &str = "00f771de-1a7e-4fcb-890e-68c77e2fb44e";
+const SEV_HASH_TABLE_RV_GUID: &str = "7255371f-3a3b-4b04-927b-1da6efa8d454";
+
+/// Size of a footer table entry header: a `u16` length and a GUID.
+const ENTRY_HEADER_SIZE: usize = 18;
+
+/// Encode a GUID string the way EFI lays it out in memory.
+pub(super) fn guid(s: &str) -> [u8; 16] {
+    let hex: String = s.chars().filter(|c| *c != '-').collect();
+    let mut bytes = [0u8; 16];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).expect("valid GUID literal");
+    }
+    bytes[0..4].reverse();
+    bytes[4..6].reverse();
+    bytes[6..8].reverse();
+    bytes
+}
+
+pub(super) fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    let bytes = data.get(offset..offset + 2).context("read out of bounds")?;
+    Ok(u16::from_le_bytes(bytes.try_into()?))
+}
+
+pub(super) fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = data.get(offset..offset + 4).context("read out of bounds")?;
+    Ok(u32::from_le_bytes(bytes.try_into()?))
+}
+
+pub(super) fn read_u64(data: &[u8], offset: usize) -> Result<u64> {
+    let bytes = data.get(offset..offset + 8).context("read out of bounds")?;
+    Ok(u64::from_le_bytes(bytes.try_into()?))
+}
+
+/// TDVF section types.
+pub(super) const TDX_SECTION_CFV: u32 = 1;
+
+/// TDVF section attributes.
+pub(super) const TDX_ATTRIBUTE_MR_EXTEND: u32 = 1 << 0;
+pub(super) const TDX_ATTRIBUTE_PAGE_AUG: u32 = 1 << 1;
+
+/// A section of the TDVF metadata.
+#[derive(Debug)]
+pub(super) struct TdxSection {
+    pub data_offset: u32,
+    pub raw_data_size: u32,
+    pub memory_address: u64,
+    pub memory_data_size: u64,
+    pub section_type: u32,
+    pub attributes: u32,
+}
+
+/// SEV metadata section types.
+pub(super) const SEV_SECTION_SNP_SEC_MEMORY: u32 = 1;
+pub(super) const SEV_SECTION_SNP_SECRETS: u32 = 2;
+pub(super) const SEV_SECTION_CPUID: u32 = 3;
+pub(super) const SEV_SECTION_SVSM_CAA: u32 = 4;
+pub(super) const SEV_SECTION_SNP_KERNEL_HASHES: u32 = 0x10;
+
+/// A section of the SEV metadata.
+#[derive(Debug)]
+pub(super) struct SevSection {
+    pub gpa: u32,
+    pub size: u32,
+    pub section_type: u32,
+}
+
+/// An OVMF image.
+pub(super) struct Ovmf<'a> {
+    data: &'a [u8],
+    table: HashMap<[u8; 16], &'a [u8]>,
+}
+
+impl<'a> Ovmf<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let footer = data
+            .len()
+            .checked_sub(32 + ENTRY_HEADER_SIZE)
+            .context("OVMF image too small")?;
+        if data[footer + 2..footer + ENTRY_HEADER_SIZE] != guid(OVMF_TABLE_FOOTER_GUID) {
+            bail!("OVMF image has no footer GUID table");
+        }
+        let table_size = (read_u16(data, footer)? as usize)
+            .checked_sub(ENTRY_HEADER_SIZE)
+            .context("invalid OVMF footer table size")?;
+        let mut entries = data
+            .get(
+                footer
+                    .checked_sub(table_size)
+                    .context("invalid OVMF footer table size")?..footer,
+            )
+            .context("invalid OVMF footer table size")?;
+
+        // Entries are laid out backwards from the footer, each one followed
+        // by its length and GUID.
+        let mut table = HashMap::new();
+        while entries.len() >= ENTRY_HEADER_SIZE {
+            let header = entries.len() - ENTRY_HEADER_SIZE;
+            let size = read_u16(entries, header)? as usize;
+            if size < ENTRY_HEADER_SIZE || size > entries.len() {
+                bail!("invalid OVMF footer table entry");
+            }
+            let guid: [u8; 16] = entries[header + 2..].try_into()?;
+            table.insert(guid, &entries[entries.len() - size..header]);
+            entries = &entries[..entries.len() - size];
+        }
+
+        Ok(Self { data, table })
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Guest physical address the image is mapped at.
+    pub fn gpa(&self) -> u64 {
+        FOUR_GB - self.data.len() as u64
+    }
+
+    fn entry(&self, name: &str) -> Result<&'a [u8]> {
+        self.table
+            .get(&guid(name))
+            .copied()
+            .with_context(|| format!("OVMF footer table has no {name} entry"))
+    }
+
+    /// Offset of a metadata block given as distance from the end of image.
+    fn metadata_offset(&self, name: &str, signature: &[u8; 4]) -> Result<usize> {
+        let from_end = read_u32(self.entry(name)?, 0)? as usize;
+        let offset = self
+            .data
+            .len()
+            .checked_sub(from_end)
+            .context("invalid OVMF metadata offset")?;
+        if self.data.get(offset..offset + 4) != Some(signature.as_slice()) {
+            bail!("invalid OVMF metadata signature");
+        }
+        Ok(offset)
+    }
+
+    pub fn tdx_sections(&self) -> Result<Vec<TdxSection>> {
+        let offset = self.metadata_offset(TDX_METADATA_OFFSET_GUID, b"TDVF")?;
+        let count = read_u32(self.data, offset + 12)? as usize;
+        (0..count)
+            .map(|i| {
+                let entry = offset + 16 + i * 32;
+                Ok(TdxSection {
+                    data_offset: read_u32(self.data, entry)?,
+                    raw_data_size: read_u32(self.data, entry + 4)?,
+                    memory_address: read_u64(self.data, entry + 8)?,
+                    memory_data_size: read_u64(self.data, entry + 16)?,
+                    section_type: read_u32(self.data, entry + 24)?,
+                    attributes: read_u32(self.data, entry + 28)?,
+                })
+            })
+            .collect()
+    }
+
+    pub fn sev_sections(&self) -> Result<Vec<SevSection>> {
+        let offset = self.metadata_offset(SEV_METADATA_OFFSET_GUID, b"ASEV")?;
+        let count = read_u32(self.data, offset + 12)? as usize;
+        (0..count)
+            .map(|i| {
+                let entry = offset + 16 + i * 12;
+                Ok(SevSection {
+                    gpa: read_u32(self.data, entry)?,
+                    size: read_u32(self.data, entry + 4)?,
+                    section_type: read_u32(self.data, entry + 8)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Reset vector of the application processors.
+    pub fn sev_es_reset_eip(&self) -> Result<u32> {
+        read_u32(self.entry(SEV_ES_RESET_BLOCK_GUID)?, 0)
+    }
+
+    /// Guest physical address of the table of kernel, initrd and cmdline
+    /// hashes.
+    pub fn sev_hashes_table_gpa(&self) -> Result<u32> {
+        read_u32(self.entry(SEV_HASH_TABLE_RV_GUID)?, 0)
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// Build an OVMF image of `size` bytes with the given footer table
+    /// entries, and metadata blocks placed at the given offsets.
+    pub fn build_ovmf(size: usize, entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut table = Vec::new();
+        for (name, data) in entries {
+            table.extend_from_slice(data);
+            table.extend_from_slice(&((data.len() + ENTRY_HEADER_SIZE) as u16).to_le_bytes());
+            table.extend_from_slice(&guid(name));
+        }
+        table.extend_from_slice(&((table.len() + ENTRY_HEADER_SIZE) as u16).to_le_bytes());
+        table.extend_from_slice(&guid(OVMF_TABLE_FOOTER_GUID));
+
+        let mut image = vec![0u8; size];
+        let end = size - 32;
+        image[end - table.len()..end].copy_from_slice(&table);
+        image
+    }
+
+    #[test]
+    fn test_guid() {
+        assert_eq!(
+            hex::encode(guid("96b582de-1fb2-45f7-baea-a366c55a082d")),
+            "de82b596b21ff745baeaa366c55a082d"
+        );
+    }
+
+    #[test]
+    fn test_footer_table() {
+        let image = build_ovmf(
+            0x1000,
+            &[
+                (
+                    SEV_ES_RESET_BLOCK_GUID,
+                    0xffff_f000u32.to_le_bytes().to_vec(),
+                ),
+                (
+                    SEV_HASH_TABLE_RV_GUID,
+                    [0x10c00u32.to_le_bytes(), 0x400u32.to_le_bytes()].concat(),
+                ),
+            ],
+        );
+        let ovmf = Ovmf::parse(&image).unwrap();
+        assert_eq!(ovmf.gpa(), 0xffff_f000);
+        assert_eq!(ovmf.sev_es_reset_eip().unwrap(), 0xffff_f000);
+        assert_eq!(ovmf.sev_hashes_table_gpa().unwrap(), 0x10c00);
+        assert!(ovmf.tdx_sections().is_err());
+    }
+}
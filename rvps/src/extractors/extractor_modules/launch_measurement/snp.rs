@@ -0,0 +1,282 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Expected launch measurement of an SEV-SNP guest booted with OVMF, and
+//! optionally a direct kernel boot through the SEV hashes table.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384};
+
+use super::ovmf::{
+    guid, Ovmf, SEV_SECTION_CPUID, SEV_SECTION_SNP_KERNEL_HASHES, SEV_SECTION_SNP_SECRETS,
+    SEV_SECTION_SNP_SEC_MEMORY, SEV_SECTION_SVSM_CAA,
+};
+use super::SnpInputs;
+
+const PAGE_SIZE: usize = 0x1000;
+const LD_SIZE: usize = 48;
+
+/// The VMSA pages are measured at this fixed GPA.
+const VMSA_GPA: u64 = 0xffff_ffff_f000;
+/// Reset vector of the bootstrap processor.
+const BSP_EIP: u32 = 0xffff_fff0;
+
+const PAGE_TYPE_NORMAL: u8 = 0x01;
+const PAGE_TYPE_VMSA: u8 = 0x02;
+const PAGE_TYPE_ZERO: u8 = 0x03;
+const PAGE_TYPE_SECRETS: u8 = 0x05;
+const PAGE_TYPE_CPUID: u8 = 0x06;
+
+const SEV_HASH_TABLE_HEADER_GUID: &str = "9438d606-4f22-4cc9-b479-a793d411fd21";
+const SEV_CMDLINE_ENTRY_GUID: &str = "97d02dd8-bd20-4c94-aa78-e7714d36ab2a";
+const SEV_INITRD_ENTRY_GUID: &str = "44baf731-3a2f-4bd7-9af1-41e29169781d";
+const SEV_KERNEL_ENTRY_GUID: &str = "4de79437-abd2-427f-b835-d5b172d2045b";
+
+/// Size of a hashes table entry: GUID, `u16` length and SHA-256 digest.
+const SEV_HASH_TABLE_ENTRY_SIZE: usize = 16 + 2 + 32;
+const SEV_HASH_TABLE_SIZE: usize = 16 + 2 + 3 * SEV_HASH_TABLE_ENTRY_SIZE;
+
+/// The VMM the guest is launched by. It decides how the CPUID page is
+/// measured and the initial register state of the vCPUs.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VmmType {
+    #[default]
+    Qemu,
+    Ec2,
+}
+
+/// Compute the SNP launch digest.
+pub(super) fn measure(inputs: &SnpInputs) -> Result<Vec<u8>> {
+    let ovmf = Ovmf::parse(&inputs.ovmf.0).context("parse OVMF")?;
+    let vcpu_sig = match inputs.vcpu_sig {
+        Some(sig) => sig,
+        None => vcpu_sig(&inputs.vcpu_type)?,
+    };
+
+    let mut gctx = Gctx::new();
+    gctx.update_normal_pages(ovmf.gpa(), ovmf.data())?;
+
+    for section in ovmf.sev_sections().context("parse SEV metadata")? {
+        let gpa = section.gpa as u64;
+        let size = section.size as usize;
+        match section.section_type {
+            SEV_SECTION_SNP_SEC_MEMORY | SEV_SECTION_SVSM_CAA => gctx.update_zero_pages(gpa, size),
+            SEV_SECTION_SNP_SECRETS => gctx.update_page(PAGE_TYPE_SECRETS, gpa, None),
+            SEV_SECTION_CPUID => {
+                if inputs.vmm_type != VmmType::Ec2 {
+                    gctx.update_page(PAGE_TYPE_CPUID, gpa, None);
+                }
+            }
+            SEV_SECTION_SNP_KERNEL_HASHES => match &inputs.kernel {
+                Some(kernel) => {
+                    let page = hashes_page(
+                        &ovmf,
+                        &kernel.0,
+                        inputs.initrd.as_ref().map(|i| i.0.as_slice()),
+                        inputs.cmdline.as_deref().unwrap_or_default(),
+                    )?;
+                    gctx.update_normal_pages(gpa, &page)?;
+                }
+                None => gctx.update_zero_pages(gpa, size),
+            },
+            other => bail!("unknown SEV metadata section type {other:#x}"),
+        }
+    }
+
+    // EC2 measures the CPUID page after all the other metadata sections.
+    if inputs.vmm_type == VmmType::Ec2 {
+        for section in ovmf.sev_sections()? {
+            if section.section_type == SEV_SECTION_CPUID {
+                gctx.update_page(PAGE_TYPE_CPUID, section.gpa as u64, None);
+            }
+        }
+    }
+
+    if inputs.vcpus == 0 {
+        bail!("vcpus must not be zero");
+    }
+    let ap_eip = ovmf.sev_es_reset_eip()?;
+    for vcpu in 0..inputs.vcpus {
+        let eip = if vcpu == 0 { BSP_EIP } else { ap_eip };
+        let vmsa = vmsa_page(eip, vcpu_sig, inputs.guest_features, inputs.vmm_type);
+        gctx.update_page(PAGE_TYPE_VMSA, VMSA_GPA, Some(&vmsa));
+    }
+
+    Ok(gctx.ld.to_vec())
+}
+
+/// Guest context of the `SNP_LAUNCH_UPDATE` command.
+struct Gctx {
+    ld: [u8; LD_SIZE],
+}
+
+impl Gctx {
+    fn new() -> Self {
+        Self { ld: [0; LD_SIZE] }
+    }
+
+    /// Extend the launch digest with a `PAGE_INFO` structure.
+    fn update(&mut self, page_type: u8, gpa: u64, contents: &[u8; LD_SIZE]) {
+        let mut page_info = Vec::with_capacity(0x70);
+        page_info.extend_from_slice(&self.ld);
+        page_info.extend_from_slice(contents);
+        page_info.extend_from_slice(&0x70u16.to_le_bytes());
+        page_info.push(page_type);
+        // IS_IMI, VMPL3, VMPL2 and VMPL1 permissions, reserved.
+        page_info.extend_from_slice(&[0; 5]);
+        page_info.extend_from_slice(&gpa.to_le_bytes());
+        self.ld = Sha384::digest(&page_info).into();
+    }
+
+    fn update_page(&mut self, page_type: u8, gpa: u64, page: Option<&[u8]>) {
+        let contents = match page {
+            Some(page) => Sha384::digest(page).into(),
+            None => [0; LD_SIZE],
+        };
+        self.update(page_type, gpa, &contents);
+    }
+
+    fn update_normal_pages(&mut self, gpa: u64, data: &[u8]) -> Result<()> {
+        if !data.len().is_multiple_of(PAGE_SIZE) {
+            bail!("measured region is not page aligned");
+        }
+        for (i, page) in data.chunks(PAGE_SIZE).enumerate() {
+            self.update_page(PAGE_TYPE_NORMAL, gpa + (i * PAGE_SIZE) as u64, Some(page));
+        }
+        Ok(())
+    }
+
+    fn update_zero_pages(&mut self, gpa: u64, size: usize) {
+        for offset in (0..size).step_by(PAGE_SIZE) {
+            self.update_page(PAGE_TYPE_ZERO, gpa + offset as u64, None);
+        }
+    }
+}
+
+/// The page holding the SEV hashes table QEMU fills in for a direct kernel
+/// boot.
+fn hashes_page(
+    ovmf: &Ovmf,
+    kernel: &[u8],
+    initrd: Option<&[u8]>,
+    cmdline: &str,
+) -> Result<Vec<u8>> {
+    let mut cmdline = cmdline.as_bytes().to_vec();
+    cmdline.push(0);
+
+    let mut table = Vec::with_capacity(SEV_HASH_TABLE_SIZE);
+    table.extend_from_slice(&guid(SEV_HASH_TABLE_HEADER_GUID));
+    table.extend_from_slice(&(SEV_HASH_TABLE_SIZE as u16).to_le_bytes());
+    for (name, data) in [
+        (SEV_CMDLINE_ENTRY_GUID, cmdline.as_slice()),
+        (SEV_INITRD_ENTRY_GUID, initrd.unwrap_or_default()),
+        (SEV_KERNEL_ENTRY_GUID, kernel),
+    ] {
+        table.extend_from_slice(&guid(name));
+        table.extend_from_slice(&(SEV_HASH_TABLE_ENTRY_SIZE as u16).to_le_bytes());
+        table.extend_from_slice(&Sha256::digest(data));
+    }
+    // QEMU pads the table to a 16 byte boundary.
+    table.resize(SEV_HASH_TABLE_SIZE.next_multiple_of(16), 0);
+
+    let offset = ovmf.sev_hashes_table_gpa()? as usize & (PAGE_SIZE - 1);
+    let mut page = vec![0u8; PAGE_SIZE];
+    page.get_mut(offset..offset + table.len())
+        .context("SEV hashes table crosses a page boundary")?
+        .copy_from_slice(&table);
+    Ok(page)
+}
+
+/// Write a segment register of the VMSA.
+fn segment(vmsa: &mut [u8], offset: usize, selector: u16, attrib: u16, limit: u32, base: u64) {
+    vmsa[offset..offset + 2].copy_from_slice(&selector.to_le_bytes());
+    vmsa[offset + 2..offset + 4].copy_from_slice(&attrib.to_le_bytes());
+    vmsa[offset + 4..offset + 8].copy_from_slice(&limit.to_le_bytes());
+    vmsa[offset + 8..offset + 16].copy_from_slice(&base.to_le_bytes());
+}
+
+fn write_u64(vmsa: &mut [u8], offset: usize, value: u64) {
+    vmsa[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Initial VMSA of a vCPU at reset.
+fn vmsa_page(eip: u32, vcpu_sig: u32, sev_features: u64, vmm_type: VmmType) -> Vec<u8> {
+    let (cs_flags, ss_flags, tr_flags, rdx, mxcsr, fcw) = match vmm_type {
+        VmmType::Qemu => (0x9b, 0x93, 0x8b, vcpu_sig as u64, 0x1f80u32, 0x37fu16),
+        VmmType::Ec2 if eip == BSP_EIP => (0x9a, 0x92, 0x83, 0, 0, 0),
+        VmmType::Ec2 => (0x9b, 0x92, 0x83, 0, 0, 0),
+    };
+
+    let mut vmsa = vec![0u8; PAGE_SIZE];
+    segment(&mut vmsa, 0x00, 0, 0x93, 0xffff, 0); // es
+    segment(
+        &mut vmsa,
+        0x10,
+        0xf000,
+        cs_flags,
+        0xffff,
+        (eip & 0xffff_0000) as u64,
+    ); // cs
+    segment(&mut vmsa, 0x20, 0, ss_flags, 0xffff, 0); // ss
+    segment(&mut vmsa, 0x30, 0, 0x93, 0xffff, 0); // ds
+    segment(&mut vmsa, 0x40, 0, 0x93, 0xffff, 0); // fs
+    segment(&mut vmsa, 0x50, 0, 0x93, 0xffff, 0); // gs
+    segment(&mut vmsa, 0x60, 0, 0, 0xffff, 0); // gdtr
+    segment(&mut vmsa, 0x70, 0, 0x82, 0xffff, 0); // ldtr
+    segment(&mut vmsa, 0x80, 0, 0, 0xffff, 0); // idtr
+    segment(&mut vmsa, 0x90, 0, tr_flags, 0xffff, 0); // tr
+
+    write_u64(&mut vmsa, 0xd0, 0x1000); // efer: SVME
+    write_u64(&mut vmsa, 0x148, 0x40); // cr4: MCE
+    write_u64(&mut vmsa, 0x158, 0x10); // cr0: ET
+    write_u64(&mut vmsa, 0x160, 0x400); // dr7
+    write_u64(&mut vmsa, 0x168, 0xffff_0ff0); // dr6
+    write_u64(&mut vmsa, 0x170, 0x2); // rflags
+    write_u64(&mut vmsa, 0x178, (eip & 0xffff) as u64); // rip
+    write_u64(&mut vmsa, 0x268, 0x0007_0406_0007_0406); // g_pat
+    write_u64(&mut vmsa, 0x308, rdx); // rdx
+    write_u64(&mut vmsa, 0x3e0, sev_features); // sev_features
+    write_u64(&mut vmsa, 0x418, 0x1); // xcr0
+    vmsa[0x438..0x43c].copy_from_slice(&mxcsr.to_le_bytes());
+    vmsa[0x440..0x442].copy_from_slice(&fcw.to_le_bytes());
+    vmsa
+}
+
+/// CPUID signature (leaf 1 EAX) of the named QEMU vCPU model.
+fn vcpu_sig(vcpu_type: &str) -> Result<u32> {
+    let (family, model, stepping) = match vcpu_type {
+        "EPYC" | "EPYC-v1" | "EPYC-v2" | "EPYC-v3" | "EPYC-v4" | "EPYC-IBPB" => (23, 1, 2),
+        t if t.starts_with("EPYC-Rome") => (23, 49, 0),
+        t if t.starts_with("EPYC-Milan") => (25, 1, 1),
+        t if t.starts_with("EPYC-Genoa") => (25, 17, 0),
+        other => bail!("unknown vCPU type {other}, set vcpu_sig instead"),
+    };
+
+    let (family_low, family_high) = if family > 0xf {
+        (0xf, family - 0xf)
+    } else {
+        (family, 0)
+    };
+    Ok((family_high << 20)
+        | ((model >> 4) << 16)
+        | (family_low << 8)
+        | ((model & 0xf) << 4)
+        | stepping)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vcpu_sig() {
+        assert_eq!(vcpu_sig("EPYC-v4").unwrap(), 0x800f12);
+        assert_eq!(vcpu_sig("EPYC-Milan-v2").unwrap(), 0xa00f11);
+        assert_eq!(vcpu_sig("EPYC-Genoa").unwrap(), 0xa10f10);
+        assert!(vcpu_sig("Skylake").is_err());
+    }
+}
@@ -0,0 +1,346 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Expected MRTD and RTMR[0..2] of a TD booted by QEMU with TDVF (OVMF) and
+//! a direct kernel boot.
+
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha384};
+
+use super::ovmf::{
+    guid, read_u16, read_u32, Ovmf, TDX_ATTRIBUTE_MR_EXTEND, TDX_ATTRIBUTE_PAGE_AUG,
+    TDX_SECTION_CFV,
+};
+use super::TdxInputs;
+
+const PAGE_SIZE: u64 = 0x1000;
+const MR_EXTEND_CHUNK_SIZE: usize = 256;
+
+const EFI_GLOBAL_VARIABLE: &str = "8be4df61-93ca-11d2-aa0d-00e098032b8c";
+const EFI_IMAGE_SECURITY_DATABASE: &str = "d719b2cb-3d3a-4596-a3bc-dad00e67656f";
+
+/// QEMU reserves this much memory below 4 GiB for ACPI data
+/// (`pcmc->acpi_data_size`).
+const QEMU_ACPI_DATA_SIZE: u64 = 0x20000 + 0x8000;
+
+/// Expected TD measurements.
+pub(super) struct TdxMeasurements {
+    pub mr_td: Vec<u8>,
+    /// Only computed when the QEMU generated TD HOB and ACPI tables are
+    /// given.
+    pub rtmr_0: Option<Vec<u8>>,
+    pub rtmr_1: Vec<u8>,
+    pub rtmr_2: Vec<u8>,
+}
+
+pub(super) fn measure(inputs: &TdxInputs) -> Result<TdxMeasurements> {
+    let ovmf = Ovmf::parse(&inputs.ovmf.0).context("parse OVMF")?;
+    let sections = ovmf.tdx_sections().context("parse TDVF metadata")?;
+
+    let rtmr_0 = match (
+        &inputs.td_hob,
+        &inputs.acpi_loader,
+        &inputs.acpi_rsdp,
+        &inputs.acpi_tables,
+    ) {
+        (Some(td_hob), Some(loader), Some(rsdp), Some(tables)) => {
+            let cfv = sections
+                .iter()
+                .find(|s| s.section_type == TDX_SECTION_CFV)
+                .context("TDVF metadata has no CFV section")?;
+            let cfv = section_data(ovmf.data(), cfv.data_offset, cfv.raw_data_size)?;
+
+            let mut events = vec![sha384(&td_hob.0), sha384(cfv)];
+            for (namespace, name) in [
+                (EFI_GLOBAL_VARIABLE, "SecureBoot"),
+                (EFI_GLOBAL_VARIABLE, "PK"),
+                (EFI_GLOBAL_VARIABLE, "KEK"),
+                (EFI_IMAGE_SECURITY_DATABASE, "db"),
+                (EFI_IMAGE_SECURITY_DATABASE, "dbx"),
+            ] {
+                events.push(sha384(&uefi_variable_data(namespace, name, &[])));
+            }
+            events.push(separator());
+            events.extend([sha384(&loader.0), sha384(&rsdp.0), sha384(&tables.0)]);
+            // Boot variables are measured by their data only.
+            events.extend(inputs.boot_variables.iter().map(|v| sha384(&v.data.0)));
+            Some(replay(&events))
+        }
+        (None, None, None, None) => None,
+        _ => bail!("RTMR[0] needs td_hob, acpi_loader, acpi_rsdp and acpi_tables"),
+    };
+
+    let initrd = inputs.initrd.as_ref().map(|i| i.0.as_slice());
+    let kernel = patch_qemu_kernel(&inputs.kernel.0, initrd.map(<[u8]>::len), inputs.memory_mb)?;
+    let rtmr_1 = replay(&[
+        authenticode_sha384(&kernel).context("hash kernel image")?,
+        sha384(b"Calling EFI Application from Boot Option"),
+        separator(),
+        sha384(b"Exit Boot Services Invocation"),
+        sha384(b"Exit Boot Services Returned with Success"),
+    ]);
+
+    // OVMF passes the initrd to the kernel through its command line.
+    let mut cmdline = inputs.cmdline.clone();
+    if initrd.is_some() {
+        cmdline.push_str(" initrd=initrd");
+    }
+    let mut events = vec![sha384(&utf16_with_nul(&cmdline))];
+    if let Some(initrd) = initrd {
+        events.push(sha384(initrd));
+    }
+    let rtmr_2 = replay(&events);
+
+    Ok(TdxMeasurements {
+        mr_td: mr_td(&ovmf, &sections)?,
+        rtmr_0,
+        rtmr_1,
+        rtmr_2,
+    })
+}
+
+fn sha384(data: &[u8]) -> Vec<u8> {
+    Sha384::digest(data).to_vec()
+}
+
+fn separator() -> Vec<u8> {
+    sha384(&0u32.to_le_bytes())
+}
+
+/// Extend a zeroed RTMR with the given event digests.
+fn replay(events: &[Vec<u8>]) -> Vec<u8> {
+    events.iter().fold(vec![0u8; 48], |rtmr, event| {
+        let mut hasher = Sha384::new();
+        hasher.update(&rtmr);
+        hasher.update(event);
+        hasher.finalize().to_vec()
+    })
+}
+
+fn section_data(image: &[u8], offset: u32, size: u32) -> Result<&[u8]> {
+    image
+        .get(offset as usize..offset as usize + size as usize)
+        .context("TDVF section out of image")
+}
+
+/// Replay the TDH.MEM.PAGE.ADD and TDH.MR.EXTEND operations QEMU issues for
+/// the TDVF sections.
+fn mr_td(ovmf: &Ovmf, sections: &[super::ovmf::TdxSection]) -> Result<Vec<u8>> {
+    let mut hasher = Sha384::new();
+    for section in sections {
+        let data = section_data(ovmf.data(), section.data_offset, section.raw_data_size)?;
+        if !section.memory_address.is_multiple_of(PAGE_SIZE)
+            || !section.memory_data_size.is_multiple_of(PAGE_SIZE)
+        {
+            bail!("TDVF section is not page aligned");
+        }
+
+        for page in 0..section.memory_data_size / PAGE_SIZE {
+            let gpa = section.memory_address + page * PAGE_SIZE;
+            if section.attributes & TDX_ATTRIBUTE_PAGE_AUG == 0 {
+                hasher.update(operation(b"MEM.PAGE.ADD", gpa));
+            }
+            if section.attributes & TDX_ATTRIBUTE_MR_EXTEND != 0 {
+                for chunk in 0..PAGE_SIZE as usize / MR_EXTEND_CHUNK_SIZE {
+                    let offset = (page * PAGE_SIZE) as usize + chunk * MR_EXTEND_CHUNK_SIZE;
+                    let mut buffer = [0u8; MR_EXTEND_CHUNK_SIZE];
+                    if let Some(bytes) = data.get(offset..) {
+                        let len = bytes.len().min(MR_EXTEND_CHUNK_SIZE);
+                        buffer[..len].copy_from_slice(&bytes[..len]);
+                    }
+                    hasher.update(operation(
+                        b"MR.EXTEND",
+                        gpa + (chunk * MR_EXTEND_CHUNK_SIZE) as u64,
+                    ));
+                    hasher.update(buffer);
+                }
+            }
+        }
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+/// The 128 bytes a TDX module operation contributes to MRTD.
+fn operation(name: &[u8], gpa: u64) -> [u8; 128] {
+    let mut buffer = [0u8; 128];
+    buffer[..name.len()].copy_from_slice(name);
+    buffer[16..24].copy_from_slice(&gpa.to_le_bytes());
+    buffer
+}
+
+fn utf16_with_nul(s: &str) -> Vec<u8> {
+    s.encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+/// `UEFI_VARIABLE_DATA` of an `EV_EFI_VARIABLE_DRIVER_CONFIG` event.
+fn uefi_variable_data(namespace: &str, name: &str, data: &[u8]) -> Vec<u8> {
+    let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let mut out = guid(namespace).to_vec();
+    out.extend_from_slice(&((name.len() / 2) as u64).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    out.extend_from_slice(&name);
+    out.extend_from_slice(data);
+    out
+}
+
+/// Apply the setup header changes QEMU's `x86_load_linux()` makes to a
+/// bzImage loaded by `-kernel` on a q35 machine, since the firmware
+/// measures the kernel as QEMU hands it over.
+fn patch_qemu_kernel(kernel: &[u8], initrd_size: Option<usize>, memory_mb: u64) -> Result<Vec<u8>> {
+    let mut kernel = kernel.to_vec();
+    if kernel.len() < 0x240 || read_u32(&kernel, 0x202)? != 0x5372_6448 {
+        // Not a bzImage ("HdrS"): nothing to patch.
+        return Ok(kernel);
+    }
+    let protocol = read_u16(&kernel, 0x206)?;
+
+    let ram_size = memory_mb << 20;
+    let lowmem = if ram_size >= 0xb000_0000 {
+        0x8000_0000
+    } else {
+        0xb000_0000
+    };
+    let below_4g_mem_size = ram_size.min(lowmem);
+
+    let (real_addr, cmdline_addr): (u32, u32) = if protocol < 0x202 || kernel[0x211] & 0x01 == 0 {
+        bail!("only kernels of boot protocol 2.02 or later loaded high are supported");
+    } else {
+        (0x10000, 0x20000)
+    };
+
+    let mut initrd_max: u64 = if protocol >= 0x20c && read_u16(&kernel, 0x236)? & 0x2 != 0 {
+        // XLF_CAN_BE_LOADED_ABOVE_4G
+        u32::MAX as u64
+    } else if protocol >= 0x203 {
+        read_u32(&kernel, 0x22c)? as u64
+    } else {
+        0x37ff_ffff
+    };
+    if initrd_max >= below_4g_mem_size - QEMU_ACPI_DATA_SIZE {
+        initrd_max = below_4g_mem_size - QEMU_ACPI_DATA_SIZE - 1;
+    }
+
+    kernel[0x228..0x22c].copy_from_slice(&cmdline_addr.to_le_bytes());
+    // Loader type: QEMU
+    kernel[0x210] = 0xb0;
+    // CAN_USE_HEAP
+    kernel[0x211] |= 0x80;
+    kernel[0x224..0x226]
+        .copy_from_slice(&((cmdline_addr - real_addr - 0x200) as u16).to_le_bytes());
+
+    if let Some(initrd_size) = initrd_size {
+        let initrd_size = initrd_size as u64;
+        if initrd_size >= initrd_max {
+            bail!("initrd is too large for {memory_mb} MiB of memory");
+        }
+        let initrd_addr = (initrd_max - initrd_size) & !(PAGE_SIZE - 1);
+        kernel[0x218..0x21c].copy_from_slice(&(initrd_addr as u32).to_le_bytes());
+        kernel[0x21c..0x220].copy_from_slice(&(initrd_size as u32).to_le_bytes());
+    }
+
+    Ok(kernel)
+}
+
+/// Authenticode digest of a PE image, as UEFI measures loaded images.
+fn authenticode_sha384(image: &[u8]) -> Result<Vec<u8>> {
+    let pe = read_u32(image, 0x3c)? as usize;
+    if image.get(pe..pe + 4) != Some(b"PE\0\0".as_slice()) {
+        bail!("not a PE image");
+    }
+    let coff = pe + 4;
+    let sections_count = read_u16(image, coff + 2)? as usize;
+    let optional_header_size = read_u16(image, coff + 16)? as usize;
+    let optional = coff + 20;
+    let data_directories = match read_u16(image, optional)? {
+        0x10b => optional + 96,
+        0x20b => optional + 112,
+        magic => bail!("unknown PE optional header magic {magic:#x}"),
+    };
+    let checksum = optional + 64;
+    let certificate_directory = data_directories + 4 * 8;
+    let headers_size = read_u32(image, optional + 60)? as usize;
+    let certificate_size = if certificate_directory + 8 <= optional + optional_header_size {
+        read_u32(image, certificate_directory + 4)? as usize
+    } else {
+        0
+    };
+
+    let range = |from: usize, to: usize| image.get(from..to).context("PE image truncated");
+    let mut hasher = Sha384::new();
+    hasher.update(range(0, checksum)?);
+    hasher.update(range(checksum + 4, certificate_directory)?);
+    hasher.update(range(certificate_directory + 8, headers_size)?);
+
+    let section_table = optional + optional_header_size;
+    let mut sections = (0..sections_count)
+        .map(|i| {
+            let entry = section_table + i * 40;
+            Ok((
+                read_u32(image, entry + 20)? as usize,
+                read_u32(image, entry + 16)? as usize,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    sections.sort_unstable();
+
+    let mut hashed = headers_size;
+    for (offset, size) in sections {
+        if size == 0 {
+            continue;
+        }
+        hasher.update(range(offset, offset + size)?);
+        hashed += size;
+    }
+    let end = image
+        .len()
+        .checked_sub(certificate_size)
+        .context("invalid PE certificate table size")?;
+    if end > hashed {
+        hasher.update(range(hashed, end)?);
+    }
+
+    Ok(hasher.finalize().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authenticode_skips_checksum_and_certificates() {
+        // A PE32+ image with one section and an appended certificate.
+        let mut image = vec![0u8; 0x400];
+        image[0x3c..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        image[0x80..0x84].copy_from_slice(b"PE\0\0");
+        let optional = 0x80 + 24;
+        image[0x80 + 6..0x80 + 8].copy_from_slice(&1u16.to_le_bytes());
+        image[0x80 + 20..0x80 + 22].copy_from_slice(&240u16.to_le_bytes());
+        image[optional..optional + 2].copy_from_slice(&0x20bu16.to_le_bytes());
+        image[optional + 60..optional + 64].copy_from_slice(&0x200u32.to_le_bytes());
+        let section = optional + 240;
+        image[section + 16..section + 20].copy_from_slice(&0x100u32.to_le_bytes());
+        image[section + 20..section + 24].copy_from_slice(&0x200u32.to_le_bytes());
+        image[0x200..0x300].fill(0xaa);
+        let certificate_directory = optional + 112 + 32;
+        image[certificate_directory..certificate_directory + 4]
+            .copy_from_slice(&0x300u32.to_le_bytes());
+        image[certificate_directory + 4..certificate_directory + 8]
+            .copy_from_slice(&0x100u32.to_le_bytes());
+
+        let digest = authenticode_sha384(&image).unwrap();
+
+        let mut signed = image.clone();
+        signed[optional + 64] = 0x42;
+        signed[0x300..].fill(0x55);
+        assert_eq!(authenticode_sha384(&signed).unwrap(), digest);
+
+        let mut modified = image.clone();
+        modified[0x280] = 0;
+        assert_ne!(authenticode_sha384(&modified).unwrap(), digest);
+    }
+}
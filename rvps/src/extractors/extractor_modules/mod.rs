@@ -8,10 +8,13 @@
 use anyhow::*;
 use std::collections::HashMap;
 
-use crate::ReferenceValue;
+use crate::{extractors::ExtractorsConfig, ReferenceValue};
 
+#[cfg(feature = "corim")]
+pub mod corim;
 #[cfg(feature = "in-toto")]
 pub mod in_toto;
+pub mod launch_measurement;
 #[cfg(feature = "reproducible-build")]
 pub mod reproducible_build;
 pub mod sample;
@@ -32,8 +35,9 @@ pub struct ExtractorModuleList {
     mod_list: HashMap<String, ExtractorInstantiateFunc>,
 }
 
-impl Default for ExtractorModuleList {
-    fn default() -> ExtractorModuleList {
+impl ExtractorModuleList {
+    #[cfg_attr(not(any(feature = "fs", feature = "corim")), allow(unused_variables))]
+    pub fn new(config: &ExtractorsConfig) -> Result<ExtractorModuleList> {
         // TODO: when new extractor is added, change mod_list
         // to mutable.
         let mut mod_list = HashMap::new();
@@ -44,13 +48,29 @@ impl Default for ExtractorModuleList {
             mod_list.insert("sample".to_string(), instantiate_func);
         }
 
+        {
+            let instantiate_func: ExtractorInstantiateFunc = Box::new(|| -> ExtractorInstance {
+                Box::<launch_measurement::LaunchMeasurementExtractor>::default()
+            });
+            mod_list.insert("launch-measurement".to_string(), instantiate_func);
+        }
+
         #[cfg(feature = "fs")]
         {
+            let extractor = slsa::SlsaExtractor::new(config.slsa_verification.as_ref())?;
             let instantiate_func: ExtractorInstantiateFunc =
-                Box::new(|| -> ExtractorInstance { Box::new(slsa::SlsaExtractor) });
+                Box::new(move || -> ExtractorInstance { Box::new(extractor.clone()) });
             mod_list.insert("slsa".to_string(), instantiate_func);
         }
 
+        #[cfg(feature = "corim")]
+        {
+            let extractor = corim::CorimExtractor::new(config.corim_verification.as_ref())?;
+            let instantiate_func: ExtractorInstantiateFunc =
+                Box::new(move || -> ExtractorInstance { Box::new(extractor.clone()) });
+            mod_list.insert("corim".to_string(), instantiate_func);
+        }
+
         #[cfg(feature = "in-toto")]
         {
             let instantiate_func: ExtractorInstantiateFunc =
@@ -66,11 +86,9 @@ impl Default for ExtractorModuleList {
             mod_list.insert("reproducible-build".to_string(), instantiate_func);
         }
 
-        ExtractorModuleList { mod_list }
+        Ok(ExtractorModuleList { mod_list })
     }
-}
 
-impl ExtractorModuleList {
     pub fn get_func(&self, extractor_name: &str) -> Result<&ExtractorInstantiateFunc> {
         let instantiate_func: &ExtractorInstantiateFunc =
             self.mod_list.get(extractor_name).ok_or_else(|| {
@@ -15,6 +15,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::{
+    namespace::DEFAULT_NAMESPACE,
     reference_value::{HashValuePair, REFERENCE_VALUE_VERSION},
     ReferenceValue,
 };
@@ -77,6 +78,7 @@ impl Extractor for SampleExtractor {
                         Some(ReferenceValue {
                             version: REFERENCE_VALUE_VERSION.into(),
                             name: name.to_string(),
+                            namespace: DEFAULT_NAMESPACE.into(),
                             expiration,
                             hash_value,
                             value,
@@ -0,0 +1,1239 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Native verification of signed SLSA provenance.
+//!
+//! Provenance is accepted as a Sigstore bundle carrying a DSSE envelope, or
+//! as a bare DSSE envelope signed with one of the configured public keys.
+//! For a bundle, everything is checked offline against a TUF
+//! `trusted_root.json`:
+//!
+//! - the Fulcio certificate chain of the signing certificate, at the time
+//!   the signature was integrated into Rekor;
+//! - the Rekor signed entry timestamp, which vouches for the integrated
+//!   time, the inclusion proof and its signed checkpoint when present, and
+//!   that the Rekor entry is for this payload, certificate and signature;
+//! - the DSSE signature over the in-toto statement;
+//! - the certificate identity and OIDC issuer constraints.
+//!
+//! Entries timestamped by an RFC 3161 authority instead of a signed entry
+//! timestamp are not supported.
+//!
+//! The builder id of the verified statement is finally checked against the
+//! configured one.
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use x509_cert::{
+    der::{asn1::Utf8StringRef, oid::ObjectIdentifier, Decode, DecodePem, Encode},
+    ext::pkix::{name::GeneralName, BasicConstraints, ExtendedKeyUsage, SubjectAltName},
+    Certificate,
+};
+
+use crate::crypto::{pae, HashAlg, PublicKey};
+use crate::extractors::SlsaVerificationConfig;
+
+/// DSSE payload type of in-toto statements.
+const IN_TOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+/// Media type prefix of Sigstore bundles.
+const BUNDLE_MEDIA_TYPE_PREFIX: &str = "application/vnd.dev.sigstore.bundle";
+
+const ECDSA_WITH_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+const ECDSA_WITH_SHA384: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.3");
+const KP_CODE_SIGNING: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.3.3");
+/// Fulcio OIDC issuer extension, raw string value (deprecated).
+const FULCIO_ISSUER_V1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.57264.1.1");
+/// Fulcio OIDC issuer extension, DER `UTF8String` value.
+const FULCIO_ISSUER_V2: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.57264.1.8");
+
+/// The public key certified by a certificate.
+fn certificate_key(cert: &Certificate) -> Result<PublicKey> {
+    PublicKey::from_der(&cert.tbs_certificate.subject_public_key_info.to_der()?)
+}
+
+/// Accept int64 values both as JSON numbers and as strings, as the protobuf
+/// JSON encoding of Sigstore bundles uses strings.
+fn int64<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<i64, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Number(n) => n
+            .as_i64()
+            .ok_or_else(|| serde::de::Error::custom("integer out of range")),
+        Value::String(s) => s.parse().map_err(serde::de::Error::custom),
+        _ => Err(serde::de::Error::custom("expected an integer")),
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ValidFor {
+    #[serde(default)]
+    start: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end: Option<DateTime<Utc>>,
+}
+
+impl ValidFor {
+    fn contains(&self, time: i64) -> bool {
+        self.start.is_none_or(|start| start.timestamp() <= time)
+            && self.end.is_none_or(|end| time <= end.timestamp())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawBytes {
+    raw_bytes: String,
+}
+
+impl RawBytes {
+    fn certificate(&self) -> Result<Certificate> {
+        let der = STANDARD
+            .decode(&self.raw_bytes)
+            .context("decode certificate")?;
+        Certificate::from_der(&der).context("parse certificate")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CertChain {
+    certificates: Vec<RawBytes>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogId {
+    key_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrustedRootFile {
+    #[serde(default)]
+    tlogs: Vec<TlogInstance>,
+    #[serde(default)]
+    certificate_authorities: Vec<CertificateAuthorityEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TlogInstance {
+    public_key: TlogPublicKey,
+    log_id: LogId,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TlogPublicKey {
+    raw_bytes: String,
+    #[serde(default)]
+    valid_for: ValidFor,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CertificateAuthorityEntry {
+    cert_chain: CertChain,
+    #[serde(default)]
+    valid_for: ValidFor,
+}
+
+struct TransparencyLog {
+    key_id: Vec<u8>,
+    key: PublicKey,
+    valid_for: ValidFor,
+}
+
+struct CertificateAuthority {
+    /// The CA chain, from the certificate issuing signing certificates up
+    /// to the root.
+    chain: Vec<Certificate>,
+    valid_for: ValidFor,
+}
+
+/// The Fulcio CAs and Rekor logs of a TUF `trusted_root.json`.
+struct TrustedRoot {
+    tlogs: Vec<TransparencyLog>,
+    cas: Vec<CertificateAuthority>,
+}
+
+impl TrustedRoot {
+    fn from_json(json: &str) -> Result<Self> {
+        let file: TrustedRootFile = serde_json::from_str(json).context("parse trusted root")?;
+
+        let mut tlogs = Vec::new();
+        for tlog in file.tlogs {
+            let der = STANDARD.decode(&tlog.public_key.raw_bytes)?;
+            tlogs.push(TransparencyLog {
+                key_id: STANDARD.decode(&tlog.log_id.key_id)?,
+                key: PublicKey::from_der(&der).context("parse Rekor public key")?,
+                valid_for: tlog.public_key.valid_for,
+            });
+        }
+
+        let mut cas = Vec::new();
+        for ca in file.certificate_authorities {
+            let chain = ca
+                .cert_chain
+                .certificates
+                .iter()
+                .map(RawBytes::certificate)
+                .collect::<Result<Vec<_>>>()
+                .context("parse Fulcio CA chain")?;
+            if chain.is_empty() {
+                bail!("Fulcio CA without certificates in trusted root");
+            }
+            cas.push(CertificateAuthority {
+                chain,
+                valid_for: ca.valid_for,
+            });
+        }
+
+        if tlogs.is_empty() || cas.is_empty() {
+            bail!("trusted root needs at least one Rekor log and one Fulcio CA");
+        }
+
+        Ok(Self { tlogs, cas })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Envelope {
+    pub(super) payload_type: String,
+    pub(super) payload: String,
+    #[serde(default)]
+    signatures: Vec<EnvelopeSignature>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnvelopeSignature {
+    sig: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Bundle {
+    media_type: String,
+    verification_material: VerificationMaterial,
+    pub(super) dsse_envelope: Option<Envelope>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerificationMaterial {
+    #[serde(default)]
+    x509_certificate_chain: Option<CertChain>,
+    #[serde(default)]
+    certificate: Option<RawBytes>,
+    #[serde(default)]
+    tlog_entries: Vec<TlogEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TlogEntry {
+    #[serde(deserialize_with = "int64")]
+    log_index: i64,
+    log_id: LogId,
+    kind_version: KindVersion,
+    #[serde(deserialize_with = "int64")]
+    integrated_time: i64,
+    #[serde(default)]
+    inclusion_promise: Option<InclusionPromise>,
+    #[serde(default)]
+    inclusion_proof: Option<InclusionProof>,
+    canonicalized_body: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KindVersion {
+    kind: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InclusionPromise {
+    signed_entry_timestamp: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InclusionProof {
+    #[serde(deserialize_with = "int64")]
+    log_index: i64,
+    root_hash: String,
+    #[serde(deserialize_with = "int64")]
+    tree_size: i64,
+    #[serde(default)]
+    hashes: Vec<String>,
+    checkpoint: Checkpoint,
+}
+
+#[derive(Debug, Deserialize)]
+struct Checkpoint {
+    envelope: String,
+}
+
+/// A signed provenance document.
+pub(super) enum SignedDocument {
+    Bundle(Bundle),
+    Envelope(Envelope),
+}
+
+impl SignedDocument {
+    /// Parse a Sigstore bundle or a DSSE envelope. Returns `None` for other
+    /// documents, e.g. a bare in-toto statement.
+    pub(super) fn parse(doc: &str) -> Option<Self> {
+        let value: Value = serde_json::from_str(doc).ok()?;
+        if value.get("mediaType").is_some() {
+            serde_json::from_value(value).ok().map(Self::Bundle)
+        } else if value.get("payloadType").is_some() {
+            serde_json::from_value(value).ok().map(Self::Envelope)
+        } else {
+            None
+        }
+    }
+
+    pub(super) fn envelope(&self) -> Option<&Envelope> {
+        match self {
+            SignedDocument::Bundle(bundle) => bundle.dsse_envelope.as_ref(),
+            SignedDocument::Envelope(envelope) => Some(envelope),
+        }
+    }
+}
+
+/// RFC 6962 leaf hash.
+fn hash_leaf(leaf: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update(leaf);
+    hasher.finalize().to_vec()
+}
+
+/// RFC 6962 interior node hash.
+fn hash_children(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().to_vec()
+}
+
+/// Compute the root of a Merkle tree of `tree_size` leaves from the leaf at
+/// `index` and its audit path (RFC 9162, section 2.1.3.2).
+fn root_from_inclusion_proof(
+    index: u64,
+    tree_size: u64,
+    leaf_hash: Vec<u8>,
+    path: &[Vec<u8>],
+) -> Result<Vec<u8>> {
+    if index >= tree_size {
+        bail!("inclusion proof index {index} out of tree of size {tree_size}");
+    }
+
+    let mut fnode = index;
+    let mut snode = tree_size - 1;
+    let mut root = leaf_hash;
+    for hash in path {
+        if snode == 0 {
+            bail!("inclusion proof has too many hashes");
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            root = hash_children(hash, &root);
+            while fnode & 1 == 0 && fnode != 0 {
+                fnode >>= 1;
+                snode >>= 1;
+            }
+        } else {
+            root = hash_children(&root, hash);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    if snode != 0 {
+        bail!("inclusion proof has too few hashes");
+    }
+
+    Ok(root)
+}
+
+fn check_validity(cert: &Certificate, time: i64) -> Result<()> {
+    let validity = &cert.tbs_certificate.validity;
+    let not_before = validity.not_before.to_unix_duration().as_secs() as i64;
+    let not_after = validity.not_after.to_unix_duration().as_secs() as i64;
+    if time < not_before || time > not_after {
+        bail!(
+            "certificate {} not valid at {time}",
+            cert.tbs_certificate.subject
+        );
+    }
+    Ok(())
+}
+
+/// Verify that `issuer` signed `cert`.
+fn check_issued_by(cert: &Certificate, issuer: &Certificate) -> Result<()> {
+    if cert.tbs_certificate.issuer != issuer.tbs_certificate.subject {
+        bail!(
+            "certificate issuer {} does not match {}",
+            cert.tbs_certificate.issuer,
+            issuer.tbs_certificate.subject
+        );
+    }
+    let is_ca = issuer
+        .tbs_certificate
+        .get::<BasicConstraints>()?
+        .is_some_and(|(_, constraints)| constraints.ca);
+    if !is_ca {
+        bail!("issuer {} is not a CA", issuer.tbs_certificate.subject);
+    }
+
+    let alg = match cert.signature_algorithm.oid {
+        ECDSA_WITH_SHA256 => HashAlg::Sha256,
+        ECDSA_WITH_SHA384 => HashAlg::Sha384,
+        oid => bail!("unsupported certificate signature algorithm {oid}"),
+    };
+    let signature = cert
+        .signature
+        .as_bytes()
+        .context("certificate signature has unused bits")?;
+    certificate_key(issuer)?
+        .verify_with(alg, &cert.tbs_certificate.to_der()?, signature)
+        .with_context(|| {
+            format!(
+                "signature of certificate {} does not verify",
+                cert.tbs_certificate.subject
+            )
+        })
+}
+
+/// The URI and email subject alternative names of a certificate.
+fn subject_alt_names(cert: &Certificate) -> Result<Vec<String>> {
+    let Some((_, san)) = cert.tbs_certificate.get::<SubjectAltName>()? else {
+        return Ok(Vec::new());
+    };
+    Ok(san
+        .0
+        .into_iter()
+        .filter_map(|name| match name {
+            GeneralName::UniformResourceIdentifier(uri) => Some(uri.to_string()),
+            GeneralName::Rfc822Name(email) => Some(email.to_string()),
+            _ => None,
+        })
+        .collect())
+}
+
+/// The OIDC issuer of a Fulcio certificate.
+fn oidc_issuer(cert: &Certificate) -> Result<Option<String>> {
+    let extensions = cert.tbs_certificate.extensions.as_deref().unwrap_or(&[]);
+    for extension in extensions {
+        if extension.extn_id == FULCIO_ISSUER_V2 {
+            let issuer = Utf8StringRef::from_der(extension.extn_value.as_bytes())?;
+            return Ok(Some(issuer.as_str().to_string()));
+        }
+    }
+    for extension in extensions {
+        if extension.extn_id == FULCIO_ISSUER_V1 {
+            return Ok(Some(String::from_utf8(
+                extension.extn_value.as_bytes().to_vec(),
+            )?));
+        }
+    }
+    Ok(None)
+}
+
+/// Whether `value` matches `pattern`, where a trailing `*` matches any
+/// suffix.
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => value.starts_with(prefix),
+        None => value == pattern,
+    }
+}
+
+/// Verifier of signed SLSA provenance.
+pub struct SigstoreVerifier {
+    trusted_root: Option<TrustedRoot>,
+    public_keys: Vec<PublicKey>,
+    certificate_identities: Vec<String>,
+    certificate_oidc_issuer: Option<String>,
+    builder_id: Option<String>,
+}
+
+impl SigstoreVerifier {
+    pub fn new(config: &SlsaVerificationConfig) -> Result<Self> {
+        let trusted_root = config
+            .trusted_root_path
+            .as_ref()
+            .map(|path| {
+                let json = std::fs::read_to_string(path)
+                    .with_context(|| format!("read trusted root {path}"))?;
+                TrustedRoot::from_json(&json).with_context(|| format!("load trusted root {path}"))
+            })
+            .transpose()?;
+        let public_keys = config
+            .public_key_paths
+            .iter()
+            .map(|path| {
+                let pem = std::fs::read_to_string(path)
+                    .with_context(|| format!("read public key {path}"))?;
+                PublicKey::from_pem(&pem).with_context(|| format!("load public key {path}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if trusted_root.is_none() && public_keys.is_empty() {
+            bail!("slsa verification needs a trusted root or public keys");
+        }
+        // Any Fulcio certificate chains to the trusted root: without these,
+        // anyone with an OIDC account could sign provenance.
+        if trusted_root.is_some()
+            && (config.certificate_identities.is_empty()
+                || config.certificate_oidc_issuer.is_none())
+        {
+            bail!("a trusted root needs certificate_identities and certificate_oidc_issuer");
+        }
+
+        Ok(Self {
+            trusted_root,
+            public_keys,
+            certificate_identities: config.certificate_identities.clone(),
+            certificate_oidc_issuer: config.certificate_oidc_issuer.clone(),
+            builder_id: config.builder_id.clone(),
+        })
+    }
+
+    /// Verify a signed provenance document and return the in-toto
+    /// statement it carries.
+    pub fn verify(&self, doc: &str) -> Result<Value> {
+        let signed = SignedDocument::parse(doc).ok_or_else(|| {
+            anyhow!("provenance is neither a Sigstore bundle nor a DSSE envelope")
+        })?;
+        let payload = match &signed {
+            SignedDocument::Bundle(bundle) => self.verify_bundle(bundle)?,
+            SignedDocument::Envelope(envelope) => self.verify_keyed_envelope(envelope)?,
+        };
+
+        let statement: Value =
+            serde_json::from_slice(&payload).context("parse in-toto statement")?;
+        self.check_builder_id(&statement)?;
+        Ok(statement)
+    }
+
+    fn verify_bundle(&self, bundle: &Bundle) -> Result<Vec<u8>> {
+        let root = self
+            .trusted_root
+            .as_ref()
+            .context("Sigstore bundles need a trusted root")?;
+        if !bundle.media_type.starts_with(BUNDLE_MEDIA_TYPE_PREFIX) {
+            bail!("unexpected bundle media type {}", bundle.media_type);
+        }
+        let envelope = bundle
+            .dsse_envelope
+            .as_ref()
+            .context("Sigstore bundle carries no DSSE envelope")?;
+        let payload = envelope_payload(envelope)?;
+
+        let material = &bundle.verification_material;
+        let leaf = match (&material.certificate, &material.x509_certificate_chain) {
+            (Some(cert), _) => cert.certificate()?,
+            (None, Some(chain)) => chain
+                .certificates
+                .first()
+                .context("empty certificate chain in bundle")?
+                .certificate()?,
+            (None, None) => bail!("Sigstore bundle carries no signing certificate"),
+        };
+
+        // The signing certificate is short-lived: it is checked at the time
+        // Rekor integrated the signature, which the log vouches for.
+        let integrated_time =
+            self.verify_tlog_entries(root, &material.tlog_entries, envelope, &leaf, &payload)?;
+        Self::verify_certificate_chain(root, &leaf, integrated_time)?;
+        self.check_identity(&leaf)?;
+
+        let key = certificate_key(&leaf)?;
+        verify_envelope_signatures(envelope, &payload, &[key])
+            .context("DSSE signature does not verify with the signing certificate")?;
+
+        Ok(payload)
+    }
+
+    fn verify_keyed_envelope(&self, envelope: &Envelope) -> Result<Vec<u8>> {
+        if self.public_keys.is_empty() {
+            bail!("DSSE envelopes outside of a Sigstore bundle need configured public keys");
+        }
+        let payload = envelope_payload(envelope)?;
+        verify_envelope_signatures(envelope, &payload, &self.public_keys)
+            .context("DSSE signature does not verify with any configured public key")?;
+        Ok(payload)
+    }
+
+    /// Verify the Rekor entries of a bundle, and return the integrated time
+    /// of the first one that verifies.
+    fn verify_tlog_entries(
+        &self,
+        root: &TrustedRoot,
+        entries: &[TlogEntry],
+        envelope: &Envelope,
+        leaf: &Certificate,
+        payload: &[u8],
+    ) -> Result<i64> {
+        let mut errors = Vec::new();
+        for entry in entries {
+            match Self::verify_tlog_entry(root, entry, envelope, leaf, payload) {
+                Ok(()) => return Ok(entry.integrated_time),
+                Err(e) => errors.push(format!("{e:#}")),
+            }
+        }
+        if errors.is_empty() {
+            bail!("Sigstore bundle carries no Rekor entry");
+        }
+        bail!("no Rekor entry verifies: {}", errors.join("; "))
+    }
+
+    fn verify_tlog_entry(
+        root: &TrustedRoot,
+        entry: &TlogEntry,
+        envelope: &Envelope,
+        leaf: &Certificate,
+        payload: &[u8],
+    ) -> Result<()> {
+        let log_id = STANDARD.decode(&entry.log_id.key_id)?;
+        let log = root
+            .tlogs
+            .iter()
+            .find(|log| log.key_id == log_id)
+            .context("Rekor log of the entry is not in the trusted root")?;
+        if !log.valid_for.contains(entry.integrated_time) {
+            bail!("Rekor log key not valid at {}", entry.integrated_time);
+        }
+
+        let body = STANDARD
+            .decode(&entry.canonicalized_body)
+            .context("decode Rekor entry body")?;
+        check_entry_body(&entry.kind_version.kind, &body, envelope, leaf, payload)?;
+
+        // Only the SET vouches for the integrated time: an inclusion proof
+        // alone says nothing about when the entry was added.
+        let promise = entry
+            .inclusion_promise
+            .as_ref()
+            .context("Rekor entry carries no signed entry timestamp")?;
+        // The SET signs the canonical JSON of these fields.
+        let set_payload = format!(
+            r#"{{"body":"{}","integratedTime":{},"logID":"{}","logIndex":{}}}"#,
+            STANDARD.encode(&body),
+            entry.integrated_time,
+            hex::encode(&log_id),
+            entry.log_index
+        );
+        let set = STANDARD.decode(&promise.signed_entry_timestamp)?;
+        log.key
+            .verify(set_payload.as_bytes(), &set)
+            .context("signed entry timestamp does not verify")?;
+
+        if let Some(proof) = &entry.inclusion_proof {
+            Self::verify_inclusion_proof(log, proof, &body)?;
+        }
+
+        Ok(())
+    }
+
+    fn verify_inclusion_proof(
+        log: &TransparencyLog,
+        proof: &InclusionProof,
+        body: &[u8],
+    ) -> Result<()> {
+        let path = proof
+            .hashes
+            .iter()
+            .map(|h| hex::decode(h).context("decode inclusion proof hash"))
+            .collect::<Result<Vec<_>>>()?;
+        let root_hash = hex::decode(&proof.root_hash).context("decode root hash")?;
+        let computed = root_from_inclusion_proof(
+            proof.log_index as u64,
+            proof.tree_size as u64,
+            hash_leaf(body),
+            &path,
+        )?;
+        if computed != root_hash {
+            bail!("inclusion proof does not lead to the root hash");
+        }
+
+        // The checkpoint is a signed note: the origin, the tree size and the
+        // root hash, an empty line, then signature lines.
+        let (note, signatures) = proof
+            .checkpoint
+            .envelope
+            .split_once("\n\n")
+            .context("malformed checkpoint")?;
+        let mut lines = note.lines().skip(1);
+        let tree_size: i64 = lines
+            .next()
+            .context("checkpoint without tree size")?
+            .parse()?;
+        let checkpoint_root =
+            STANDARD.decode(lines.next().context("checkpoint without root hash")?)?;
+        if tree_size != proof.tree_size || checkpoint_root != root_hash {
+            bail!("checkpoint does not match the inclusion proof");
+        }
+
+        let signed = format!("{note}\n");
+        let signed_by_log = signatures.lines().any(|line| {
+            let Some((_, signature)) = line
+                .strip_prefix("\u{2014} ")
+                .and_then(|line| line.rsplit_once(' '))
+            else {
+                return false;
+            };
+            let Ok(signature) = STANDARD.decode(signature) else {
+                return false;
+            };
+            // A 4 bytes key hint, the start of the log id, precedes the
+            // signature.
+            signature.len() > 4
+                && signature[..4] == log.key_id[..4.min(log.key_id.len())]
+                && log.key.verify(signed.as_bytes(), &signature[4..]).is_ok()
+        });
+        if !signed_by_log {
+            bail!("checkpoint is not signed by the Rekor log");
+        }
+
+        Ok(())
+    }
+
+    fn verify_certificate_chain(root: &TrustedRoot, leaf: &Certificate, time: i64) -> Result<()> {
+        check_validity(leaf, time)?;
+        let code_signing = leaf
+            .tbs_certificate
+            .get::<ExtendedKeyUsage>()?
+            .is_some_and(|(_, eku)| eku.0.contains(&KP_CODE_SIGNING));
+        if !code_signing {
+            bail!("signing certificate is not for code signing");
+        }
+
+        let mut errors = Vec::new();
+        for ca in root.cas.iter().filter(|ca| ca.valid_for.contains(time)) {
+            let mut child = leaf;
+            let result = ca.chain.iter().try_for_each(|issuer| {
+                check_validity(issuer, time)?;
+                check_issued_by(child, issuer)?;
+                child = issuer;
+                Ok::<_, anyhow::Error>(())
+            });
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) => errors.push(format!("{e:#}")),
+            }
+        }
+        bail!(
+            "signing certificate does not chain to a trusted Fulcio CA: {}",
+            errors.join("; ")
+        )
+    }
+
+    fn check_identity(&self, leaf: &Certificate) -> Result<()> {
+        let Some(expected) = &self.certificate_oidc_issuer else {
+            bail!("no certificate OIDC issuer configured");
+        };
+
+        let names = subject_alt_names(leaf)?;
+        let matched = names.iter().any(|name| {
+            self.certificate_identities
+                .iter()
+                .any(|pattern| matches_pattern(pattern, name))
+        });
+        if !matched {
+            bail!("certificate identities {names:?} not allowed");
+        }
+
+        let issuer = oidc_issuer(leaf)?;
+        if issuer.as_deref() != Some(expected.as_str()) {
+            bail!("certificate OIDC issuer {issuer:?} is not {expected}");
+        }
+
+        Ok(())
+    }
+
+    /// A configured builder id without `@` matches any version of the
+    /// builder, like `slsa-verifier --builder-id`.
+    fn check_builder_id(&self, statement: &Value) -> Result<()> {
+        let Some(expected) = &self.builder_id else {
+            return Ok(());
+        };
+        let builder_id = statement
+            .pointer("/predicate/runDetails/builder/id")
+            .or_else(|| statement.pointer("/predicate/builder/id"))
+            .and_then(Value::as_str)
+            .context("provenance carries no builder id")?;
+
+        let matched = if expected.contains('@') {
+            builder_id == expected
+        } else {
+            builder_id.split('@').next() == Some(expected.as_str())
+        };
+        if !matched {
+            bail!("builder id {builder_id} is not {expected}");
+        }
+
+        Ok(())
+    }
+}
+
+/// The decoded payload of an in-toto DSSE envelope.
+pub(super) fn envelope_payload(envelope: &Envelope) -> Result<Vec<u8>> {
+    if envelope.payload_type != IN_TOTO_PAYLOAD_TYPE {
+        bail!("unexpected DSSE payload type {}", envelope.payload_type);
+    }
+    STANDARD
+        .decode(&envelope.payload)
+        .context("decode DSSE payload")
+}
+
+fn verify_envelope_signatures(
+    envelope: &Envelope,
+    payload: &[u8],
+    keys: &[PublicKey],
+) -> Result<()> {
+    let pae = pae(&envelope.payload_type, payload);
+    let verified = envelope.signatures.iter().any(|signature| {
+        STANDARD
+            .decode(&signature.sig)
+            .is_ok_and(|sig| keys.iter().any(|key| key.verify(&pae, &sig).is_ok()))
+    });
+    if !verified {
+        bail!("no valid DSSE signature");
+    }
+    Ok(())
+}
+
+/// Check that a Rekor entry is for `payload`, signed by `leaf` with one of
+/// the signatures of `envelope`.
+fn check_entry_body(
+    kind: &str,
+    body: &[u8],
+    envelope: &Envelope,
+    leaf: &Certificate,
+    payload: &[u8],
+) -> Result<()> {
+    let body: Value = serde_json::from_slice(body).context("parse Rekor entry body")?;
+    // Where the payload hash and the signatures are, and the field names of
+    // a signature and of its base64 PEM certificate.
+    let (hash_pointer, signatures_pointer, sig_field, cert_field) = match kind {
+        "dsse" => (
+            "/spec/payloadHash/value",
+            "/spec/signatures",
+            "signature",
+            "verifier",
+        ),
+        "intoto" => (
+            "/spec/content/payloadHash/value",
+            "/spec/content/envelope/signatures",
+            "sig",
+            "publicKey",
+        ),
+        kind => bail!("unsupported Rekor entry kind {kind}"),
+    };
+    let payload_hash = body
+        .pointer(hash_pointer)
+        .and_then(Value::as_str)
+        .context("Rekor entry carries no payload hash")?;
+    if !payload_hash.eq_ignore_ascii_case(&hex::encode(Sha256::digest(payload))) {
+        bail!("Rekor entry is for another payload");
+    }
+
+    let envelope_sigs: Vec<Vec<u8>> = envelope
+        .signatures
+        .iter()
+        .filter_map(|signature| STANDARD.decode(&signature.sig).ok())
+        .collect();
+    let signed_by_leaf = body
+        .pointer(signatures_pointer)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .any(|signature| {
+            let field = |name| {
+                signature
+                    .get(name)
+                    .and_then(Value::as_str)
+                    .and_then(|value| STANDARD.decode(value).ok())
+            };
+            let (Some(sig), Some(pem)) = (field(sig_field), field(cert_field)) else {
+                return false;
+            };
+            // `intoto` entries encode the base64 signature once more.
+            let sig = match std::str::from_utf8(&sig).map(|sig| STANDARD.decode(sig)) {
+                Ok(Ok(decoded)) if kind == "intoto" => decoded,
+                _ => sig,
+            };
+            envelope_sigs.contains(&sig)
+                && Certificate::from_pem(&pem).is_ok_and(|cert| &cert == leaf)
+        });
+    if !signed_by_leaf {
+        bail!("Rekor entry is for another signature or certificate");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::Signer, DerSignature};
+    use p256::pkcs8::EncodePublicKey;
+    use std::{str::FromStr, time::Duration};
+    use x509_cert::der::EncodePem;
+    use x509_cert::{
+        builder::{Builder, CertificateBuilder, Profile},
+        der::asn1::Ia5String,
+        ext::{AsExtension, Extension},
+        name::Name,
+        serial_number::SerialNumber,
+        spki::SubjectPublicKeyInfoOwned,
+        time::{Time, Validity},
+    };
+
+    const BUILDER_ID: &str =
+        "https://github.com/slsa-framework/slsa-github-generator/.github/workflows/generator_generic_slsa3.yml";
+    const IDENTITY: &str =
+        "https://github.com/org/repo/.github/workflows/release.yml@refs/tags/v1.0.0";
+    const ISSUER: &str = "https://token.actions.githubusercontent.com";
+
+    /// The Fulcio OIDC issuer extension.
+    struct FulcioIssuer(&'static str);
+
+    impl AsExtension for FulcioIssuer {
+        fn critical(&self, _: &Name, _: &[Extension]) -> bool {
+            false
+        }
+    }
+
+    impl x509_cert::der::oid::AssociatedOid for FulcioIssuer {
+        const OID: ObjectIdentifier = FULCIO_ISSUER_V2;
+    }
+
+    impl x509_cert::der::Encode for FulcioIssuer {
+        fn encoded_len(&self) -> x509_cert::der::Result<x509_cert::der::Length> {
+            Utf8StringRef::new(self.0)?.encoded_len()
+        }
+
+        fn encode(&self, writer: &mut impl x509_cert::der::Writer) -> x509_cert::der::Result<()> {
+            Utf8StringRef::new(self.0)?.encode(writer)
+        }
+    }
+
+    struct Fixture {
+        trusted_root: String,
+        bundle: Value,
+        leaf_key: p256::ecdsa::SigningKey,
+    }
+
+    fn validity(from: u64, to: u64) -> Validity {
+        Validity {
+            not_before: Time::try_from(std::time::UNIX_EPOCH + Duration::from_secs(from)).unwrap(),
+            not_after: Time::try_from(std::time::UNIX_EPOCH + Duration::from_secs(to)).unwrap(),
+        }
+    }
+
+    fn statement(builder_id: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v0.1",
+            "predicateType": "https://slsa.dev/provenance/v0.2",
+            "subject": [{ "name": "kernel", "digest": { "sha256": "aa" } }],
+            "predicate": { "builder": { "id": builder_id } },
+        }))
+        .unwrap()
+    }
+
+    fn fixture(payload: &[u8], ca_seed: u8) -> Fixture {
+        const INTEGRATED_TIME: u64 = 1_700_000_000;
+
+        // Fulcio root (P-384) and leaf (P-256), as issued by Sigstore.
+        let ca_key = p384::ecdsa::SigningKey::from_slice(&[ca_seed; 48]).unwrap();
+        let ca_name = Name::from_str("CN=sigstore,O=sigstore.dev").unwrap();
+        let ca_cert = CertificateBuilder::new(
+            Profile::Root,
+            SerialNumber::from(1u32),
+            validity(INTEGRATED_TIME - 86400, INTEGRATED_TIME + 86400),
+            ca_name.clone(),
+            SubjectPublicKeyInfoOwned::from_key(*ca_key.verifying_key()).unwrap(),
+            &ca_key,
+        )
+        .unwrap()
+        .build::<p384::ecdsa::DerSignature>()
+        .unwrap();
+
+        let leaf_key = p256::ecdsa::SigningKey::from_slice(&[5u8; 32]).unwrap();
+        let mut builder = CertificateBuilder::new(
+            Profile::Leaf {
+                issuer: ca_name,
+                enable_key_agreement: false,
+                enable_key_encipherment: false,
+            },
+            SerialNumber::from(2u32),
+            validity(INTEGRATED_TIME - 60, INTEGRATED_TIME + 540),
+            Name::default(),
+            SubjectPublicKeyInfoOwned::from_key(*leaf_key.verifying_key()).unwrap(),
+            &ca_key,
+        )
+        .unwrap();
+        builder
+            .add_extension(&SubjectAltName(vec![
+                GeneralName::UniformResourceIdentifier(Ia5String::new(IDENTITY).unwrap()),
+            ]))
+            .unwrap();
+        builder
+            .add_extension(&ExtendedKeyUsage(vec![KP_CODE_SIGNING]))
+            .unwrap();
+        builder.add_extension(&FulcioIssuer(ISSUER)).unwrap();
+        let leaf_cert = builder.build::<p384::ecdsa::DerSignature>().unwrap();
+        let leaf_pem = leaf_cert
+            .to_pem(x509_cert::der::pem::LineEnding::LF)
+            .unwrap();
+
+        // DSSE envelope signed by the leaf key.
+        let signature: DerSignature = leaf_key.sign(&pae(IN_TOTO_PAYLOAD_TYPE, payload));
+        let sig = STANDARD.encode(signature.as_bytes());
+
+        // Rekor: a tree of 3 entries, ours at index 2.
+        let rekor_key = p256::ecdsa::SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let rekor_der = rekor_key
+            .verifying_key()
+            .to_public_key_der()
+            .unwrap()
+            .into_vec();
+        let log_id = Sha256::digest(&rekor_der).to_vec();
+        let body = serde_json::to_vec(&serde_json::json!({
+            "apiVersion": "0.0.1",
+            "kind": "dsse",
+            "spec": {
+                "payloadHash": { "algorithm": "sha256", "value": hex::encode(Sha256::digest(payload)) },
+                "signatures": [{ "signature": sig, "verifier": STANDARD.encode(leaf_pem) }],
+            }
+        }))
+        .unwrap();
+        let leaves = [hash_leaf(b"a"), hash_leaf(b"b"), hash_leaf(&body)];
+        let root_hash = hash_children(&hash_children(&leaves[0], &leaves[1]), &leaves[2]);
+        let path = vec![hex::encode(hash_children(&leaves[0], &leaves[1]))];
+
+        let set_payload = format!(
+            r#"{{"body":"{}","integratedTime":{},"logID":"{}","logIndex":2}}"#,
+            STANDARD.encode(&body),
+            INTEGRATED_TIME,
+            hex::encode(&log_id),
+        );
+        let set: DerSignature = rekor_key.sign(set_payload.as_bytes());
+        let note = format!("rekor.example - 1\n3\n{}\n", STANDARD.encode(&root_hash));
+        let note_signature: DerSignature = rekor_key.sign(note.as_bytes());
+        let mut hinted = log_id[..4].to_vec();
+        hinted.extend_from_slice(note_signature.as_bytes());
+        let checkpoint = format!(
+            "{note}\n\u{2014} rekor.example {}\n",
+            STANDARD.encode(hinted)
+        );
+
+        let trusted_root = serde_json::json!({
+            "mediaType": "application/vnd.dev.sigstore.trustedroot+json;version=0.1",
+            "tlogs": [{
+                "baseUrl": "https://rekor.example",
+                "hashAlgorithm": "SHA2_256",
+                "publicKey": {
+                    "rawBytes": STANDARD.encode(&rekor_der),
+                    "keyDetails": "PKIX_ECDSA_P256_SHA_256",
+                    "validFor": { "start": "2020-01-01T00:00:00Z" },
+                },
+                "logId": { "keyId": STANDARD.encode(&log_id) },
+            }],
+            "certificateAuthorities": [{
+                "certChain": { "certificates": [{ "rawBytes": STANDARD.encode(ca_cert.to_der().unwrap()) }] },
+                "validFor": { "start": "2020-01-01T00:00:00Z" },
+            }],
+        })
+        .to_string();
+
+        let bundle = serde_json::json!({
+            "mediaType": "application/vnd.dev.sigstore.bundle.v0.3+json",
+            "verificationMaterial": {
+                "certificate": { "rawBytes": STANDARD.encode(leaf_cert.to_der().unwrap()) },
+                "tlogEntries": [{
+                    "logIndex": "2",
+                    "logId": { "keyId": STANDARD.encode(&log_id) },
+                    "kindVersion": { "kind": "dsse", "version": "0.0.1" },
+                    "integratedTime": INTEGRATED_TIME.to_string(),
+                    "inclusionPromise": { "signedEntryTimestamp": STANDARD.encode(set.as_bytes()) },
+                    "inclusionProof": {
+                        "logIndex": "2",
+                        "rootHash": hex::encode(&root_hash),
+                        "treeSize": "3",
+                        "hashes": path,
+                        "checkpoint": { "envelope": checkpoint },
+                    },
+                    "canonicalizedBody": STANDARD.encode(&body),
+                }],
+            },
+            "dsseEnvelope": {
+                "payloadType": IN_TOTO_PAYLOAD_TYPE,
+                "payload": STANDARD.encode(payload),
+                "signatures": [{ "sig": sig, "keyid": "" }],
+            },
+        });
+
+        Fixture {
+            trusted_root,
+            bundle,
+            leaf_key,
+        }
+    }
+
+    fn verifier(trusted_root: &str, config: SlsaVerificationConfig) -> SigstoreVerifier {
+        SigstoreVerifier {
+            trusted_root: Some(TrustedRoot::from_json(trusted_root).unwrap()),
+            public_keys: Vec::new(),
+            certificate_identities: config.certificate_identities,
+            certificate_oidc_issuer: config.certificate_oidc_issuer,
+            builder_id: config.builder_id,
+        }
+    }
+
+    fn config(identity: &str, issuer: &str, builder_id: &str) -> SlsaVerificationConfig {
+        SlsaVerificationConfig {
+            certificate_identities: vec![identity.into()],
+            certificate_oidc_issuer: Some(issuer.into()),
+            builder_id: Some(builder_id.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_inclusion_proof() {
+        let leaves: Vec<Vec<u8>> = (0..5u8).map(|i| hash_leaf(&[i])).collect();
+        let h01 = hash_children(&leaves[0], &leaves[1]);
+        let h23 = hash_children(&leaves[2], &leaves[3]);
+        let root = hash_children(&hash_children(&h01, &h23), &leaves[4]);
+
+        let proof = [leaves[2].clone(), h01.clone(), leaves[4].clone()];
+        assert_eq!(
+            root_from_inclusion_proof(3, 5, leaves[3].clone(), &proof).unwrap(),
+            root
+        );
+        let proof = [hash_children(&h01, &h23)];
+        assert_eq!(
+            root_from_inclusion_proof(4, 5, leaves[4].clone(), &proof).unwrap(),
+            root
+        );
+        assert!(root_from_inclusion_proof(5, 5, leaves[4].clone(), &proof).is_err());
+        assert_ne!(
+            root_from_inclusion_proof(
+                3,
+                5,
+                leaves[2].clone(),
+                &[leaves[2].clone(), h01, leaves[4].clone()]
+            )
+            .unwrap(),
+            root
+        );
+    }
+
+    #[test]
+    fn test_verify_bundle() {
+        let payload = statement(&format!("{BUILDER_ID}@refs/tags/v2.0.0"));
+        let trusted = fixture(&payload, 3);
+        let doc = trusted.bundle.to_string();
+
+        let verified = verifier(
+            &trusted.trusted_root,
+            config(
+                "https://github.com/org/repo/.github/workflows/release.yml@*",
+                ISSUER,
+                BUILDER_ID,
+            ),
+        )
+        .verify(&doc)
+        .unwrap();
+        assert_eq!(verified["subject"][0]["name"], "kernel");
+
+        // Identity, issuer and builder constraints
+        for config in [
+            config("https://github.com/other/*", ISSUER, BUILDER_ID),
+            config(IDENTITY, "https://accounts.google.com", BUILDER_ID),
+            config(IDENTITY, ISSUER, "https://example.com/builder"),
+            config(IDENTITY, ISSUER, &format!("{BUILDER_ID}@refs/tags/v1.0.0")),
+        ] {
+            assert!(verifier(&trusted.trusted_root, config)
+                .verify(&doc)
+                .is_err());
+        }
+
+        // Tampered payload, signature or Rekor entry
+        let constraints = || config(IDENTITY, ISSUER, BUILDER_ID);
+        let mut tampered = trusted.bundle.clone();
+        tampered["dsseEnvelope"]["payload"] =
+            Value::String(STANDARD.encode(statement("https://evil.example")));
+        assert!(verifier(&trusted.trusted_root, constraints())
+            .verify(&tampered.to_string())
+            .is_err());
+
+        let mut tampered = trusted.bundle.clone();
+        let forged: DerSignature = trusted.leaf_key.sign(b"something else");
+        tampered["dsseEnvelope"]["signatures"][0]["sig"] =
+            Value::String(STANDARD.encode(forged.as_bytes()));
+        assert!(verifier(&trusted.trusted_root, constraints())
+            .verify(&tampered.to_string())
+            .is_err());
+
+        let mut tampered = trusted.bundle.clone();
+        tampered["verificationMaterial"]["tlogEntries"][0]["integratedTime"] =
+            Value::String("1700000001".into());
+        tampered["verificationMaterial"]["tlogEntries"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("inclusionProof");
+        assert!(verifier(&trusted.trusted_root, constraints())
+            .verify(&tampered.to_string())
+            .is_err());
+
+        // Only the signed entry timestamp vouches for the integrated time.
+        let mut tampered = trusted.bundle.clone();
+        tampered["verificationMaterial"]["tlogEntries"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("inclusionPromise");
+        assert!(verifier(&trusted.trusted_root, constraints())
+            .verify(&tampered.to_string())
+            .is_err());
+
+        // A Rekor entry of the same payload signed by another certificate
+        let other = fixture(&payload, 4);
+        let mut tampered = trusted.bundle.clone();
+        tampered["verificationMaterial"]["tlogEntries"][0]["canonicalizedBody"] =
+            other.bundle["verificationMaterial"]["tlogEntries"][0]["canonicalizedBody"].clone();
+        let error = verifier(&trusted.trusted_root, constraints())
+            .verify(&tampered.to_string())
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("another signature or certificate"));
+
+        // Any Fulcio certificate is accepted without identity constraints.
+        let mut unconstrained = constraints();
+        unconstrained.certificate_oidc_issuer = None;
+        assert!(verifier(&trusted.trusted_root, unconstrained)
+            .verify(&doc)
+            .is_err());
+        let root_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(root_file.path(), &trusted.trusted_root).unwrap();
+        let mut config = constraints();
+        config.trusted_root_path = Some(root_file.path().to_string_lossy().into_owned());
+        assert!(SigstoreVerifier::new(&config).is_ok());
+        config.certificate_identities.clear();
+        assert!(SigstoreVerifier::new(&config).is_err());
+
+        // A certificate issued by an untrusted CA
+        let untrusted = fixture(&payload, 4);
+        assert!(verifier(&trusted.trusted_root, constraints())
+            .verify(&untrusted.bundle.to_string())
+            .is_err());
+    }
+}
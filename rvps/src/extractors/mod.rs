@@ -8,12 +8,63 @@
 pub mod extractor_modules;
 
 use anyhow::*;
+use serde::Deserialize;
 use std::collections::HashMap;
 
 use self::extractor_modules::{ExtractorInstance, ExtractorModuleList};
 use super::{Message, ReferenceValue};
 
-#[derive(Default)]
+/// Configuration of the extractors.
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct ExtractorsConfig {
+    /// Verify the signatures of SLSA provenance. When absent, the `slsa`
+    /// extractor only parses provenance without checking who signed it.
+    #[serde(default)]
+    pub slsa_verification: Option<SlsaVerificationConfig>,
+
+    /// Keys signed CoRIM are verified against. When absent, the `corim`
+    /// extractor rejects every CoRIM.
+    #[serde(default)]
+    pub corim_verification: Option<CorimVerificationConfig>,
+}
+
+/// Trust anchors and identity constraints of signed SLSA provenance.
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct SlsaVerificationConfig {
+    /// Path to a Sigstore TUF `trusted_root.json`, holding the Fulcio CAs
+    /// and Rekor logs Sigstore bundles are checked against.
+    #[serde(default)]
+    pub trusted_root_path: Option<String>,
+
+    /// Paths to PEM public keys accepted for DSSE envelopes that are not
+    /// wrapped in a Sigstore bundle.
+    #[serde(default)]
+    pub public_key_paths: Vec<String>,
+
+    /// Accepted subject alternative names of the Fulcio signing
+    /// certificate. A trailing `*` matches any suffix. Required, like the
+    /// OIDC issuer, with a trusted root.
+    #[serde(default)]
+    pub certificate_identities: Vec<String>,
+
+    /// Expected OIDC issuer of the Fulcio signing certificate.
+    #[serde(default)]
+    pub certificate_oidc_issuer: Option<String>,
+
+    /// Expected builder id of the provenance. Without an `@<ref>` suffix,
+    /// any version of the builder matches.
+    #[serde(default)]
+    pub builder_id: Option<String>,
+}
+
+/// Trust anchors of signed CoRIM.
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct CorimVerificationConfig {
+    /// Paths to PEM ECDSA P-256/P-384 public keys of the CoRIM signers.
+    #[serde(default)]
+    pub public_key_paths: Vec<String>,
+}
+
 pub struct Extractors {
     /// A map of provenance types to Extractor initializers
     extractors_module_list: ExtractorModuleList,
@@ -23,6 +74,13 @@ pub struct Extractors {
 }
 
 impl Extractors {
+    pub fn new(config: &ExtractorsConfig) -> Result<Self> {
+        Ok(Self {
+            extractors_module_list: ExtractorModuleList::new(config)?,
+            extractors_instance_map: HashMap::new(),
+        })
+    }
+
     /// Register an `Extractor` instance to `Extractors`. The `Extractor` is responsible for
     /// handling specific kind of provenance (as `extractor_name` indicates).
     fn register_instance(&mut self, extractor_name: String, extractor_instance: ExtractorInstance) {
@@ -0,0 +1,102 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Append-only history of the reference values.
+//!
+//! Every change RVPS makes to a reference value appends an entry to the
+//! history of its name, recording the value it resulted in and where the
+//! change came from. The history is kept by the storage next to the values,
+//! serves point-in-time queries, and is what a rollback restores from.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::reference_value::AuditProof;
+use crate::ReferenceValue;
+
+/// Actor recorded for changes made through the library API without naming
+/// one.
+pub const LOCAL_ACTOR: &str = "local";
+
+/// The kind of change a history entry records.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryOperation {
+    /// The reference value was created.
+    Add,
+    /// New digests were merged into the reference value.
+    Merge,
+    /// The reference value was replaced.
+    Refresh,
+    /// The reference value was deleted.
+    Delete,
+    /// The reference value was restored from an earlier entry.
+    Rollback,
+    /// The reference value expired and was removed by the expiry sweeper.
+    Expire,
+}
+
+/// One change of a reference value.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    /// Assigned by the storage. Revisions increase within the history of a
+    /// name but are not necessarily contiguous.
+    pub revision: u64,
+    pub operation: HistoryOperation,
+    pub timestamp: DateTime<Utc>,
+    /// Who made the change, e.g. the admin token a gRPC client presented
+    /// or the RVDS event applied.
+    pub actor: String,
+    /// `sha256:<hex>` digest of the message or reference value list the
+    /// change was derived from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub source_digest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub audit_proof: Option<AuditProof>,
+    /// Revision of the entry a rollback restored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub rollback_to: Option<u64>,
+    /// The reference value after the change, `None` if it was deleted.
+    pub value: Option<ReferenceValue>,
+}
+
+impl HistoryEntry {
+    pub(crate) fn new(
+        operation: HistoryOperation,
+        actor: &str,
+        source_digest: Option<String>,
+        value: Option<ReferenceValue>,
+    ) -> Self {
+        Self {
+            revision: 0,
+            operation,
+            timestamp: Utc::now(),
+            actor: actor.to_string(),
+            source_digest,
+            audit_proof: value.as_ref().and_then(|rv| rv.audit_proof.clone()),
+            rollback_to: None,
+            value,
+        }
+    }
+}
+
+/// The value `history` (oldest first) recorded at `at`, `None` if the name
+/// did not exist then.
+pub fn value_at(history: &[HistoryEntry], at: DateTime<Utc>) -> Option<&ReferenceValue> {
+    history
+        .iter()
+        .rev()
+        .find(|entry| entry.timestamp <= at)
+        .and_then(|entry| entry.value.as_ref())
+}
+
+/// `sha256:<hex>` digest of a source document.
+pub(crate) fn source_digest(source: &str) -> String {
+    format!("sha256:{:x}", Sha256::digest(source.as_bytes()))
+}
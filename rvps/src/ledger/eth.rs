@@ -0,0 +1,300 @@
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{hash_eq, LedgerVerifier};
+use crate::reference_value::AuditProof;
+use crate::MessageRejected;
+
+/// Backend name RVDS writes into proofs recorded through its ethereum gateway.
+const BACKEND: &str = "ethereum-gateway";
+
+/// Selector of `record(bytes32,string)`, the call the RVDS ethereum gateway sends.
+const RECORD_SELECTOR: [u8; 4] = [0xb0, 0xd0, 0xf4, 0xb8];
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Config {
+    /// Ethereum JSON-RPC endpoint.
+    pub rpc_url: String,
+
+    /// Address of the `RvdsEventLog` contract the gateway writes to.
+    pub contract_address: String,
+
+    /// Blocks that must be mined on top of the recording transaction,
+    /// counting its own block.
+    #[serde(default)]
+    pub min_confirmations: u64,
+}
+
+/// Confirms proofs against the transaction the RVDS ethereum gateway sent,
+/// using only standard JSON-RPC calls.
+pub struct EthLedgerVerifier {
+    config: Config,
+    http: Client,
+}
+
+impl EthLedgerVerifier {
+    pub fn new(config: Config) -> Result<Self> {
+        let address = config.contract_address.trim_start_matches("0x");
+        if address.len() != 40 || hex::decode(address).is_err() {
+            bail!(
+                "invalid ledger contract address `{}`",
+                config.contract_address
+            );
+        }
+
+        Ok(Self {
+            config,
+            http: Client::new(),
+        })
+    }
+
+    async fn rpc(&self, method: &str, params: Value) -> Result<Value> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let mut resp: Value = self
+            .http
+            .post(&self.config.rpc_url)
+            .json(&body)
+            .send()
+            .await
+            .with_context(|| format!("call {method}"))?
+            .error_for_status()
+            .with_context(|| format!("{method} status"))?
+            .json()
+            .await
+            .with_context(|| format!("parse {method} response"))?;
+
+        if let Some(err) = resp.get("error") {
+            bail!("{method} failed: {err}");
+        }
+        Ok(resp["result"].take())
+    }
+}
+
+#[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait(?Send))]
+#[cfg_attr(
+    not(all(
+        target_arch = "wasm32",
+        target_vendor = "unknown",
+        target_os = "unknown"
+    )),
+    async_trait
+)]
+impl LedgerVerifier for EthLedgerVerifier {
+    async fn verify(&self, proof: &AuditProof) -> Result<()> {
+        if proof.backend != BACKEND {
+            return Err(anyhow!(
+                "proof was recorded by `{}`, expected `{BACKEND}`",
+                proof.backend
+            )
+            .context(MessageRejected));
+        }
+
+        // A transaction unknown or short of confirmations may still be
+        // mined, so only a transaction contradicting the proof rejects it.
+        let receipt = self
+            .rpc("eth_getTransactionReceipt", json!([proof.handle]))
+            .await?;
+        if receipt.is_null() {
+            bail!("transaction {} is unknown or still pending", proof.handle);
+        }
+        check_receipt(&receipt, &self.config.contract_address, proof).context(MessageRejected)?;
+
+        if self.config.min_confirmations > 0 {
+            let block = quantity(&receipt["blockNumber"]).context("receipt blockNumber")?;
+            let head = quantity(&self.rpc("eth_blockNumber", json!([])).await?)
+                .context("eth_blockNumber")?;
+            let confirmations = head.saturating_sub(block) + 1;
+            if confirmations < self.config.min_confirmations {
+                bail!(
+                    "transaction {} has {confirmations} confirmation(s), {} required",
+                    proof.handle,
+                    self.config.min_confirmations
+                );
+            }
+        }
+
+        let tx = self
+            .rpc("eth_getTransactionByHash", json!([proof.handle]))
+            .await?;
+        check_record_call(&tx, proof).context(MessageRejected)
+    }
+}
+
+/// Check that `receipt` is of a successful call to the ledger contract.
+fn check_receipt(receipt: &Value, contract_address: &str, proof: &AuditProof) -> Result<()> {
+    if receipt["status"].as_str() != Some("0x1") {
+        bail!("transaction {} did not succeed", proof.handle);
+    }
+    let to = receipt["to"].as_str().unwrap_or_default();
+    if !hash_eq(to, contract_address) {
+        bail!(
+            "transaction {} was sent to {to}, not the ledger contract",
+            proof.handle
+        );
+    }
+    Ok(())
+}
+
+/// Compare the hashes recorded by the `record` call of `tx` with those
+/// `proof` claims.
+fn check_record_call(tx: &Value, proof: &AuditProof) -> Result<()> {
+    let input = tx["input"]
+        .as_str()
+        .ok_or_else(|| anyhow!("transaction {} has no input", proof.handle))?;
+    let (event_hash, payload_hash) = decode_record_call(input)?;
+
+    if !hash_eq(&event_hash, &proof.event_hash) {
+        bail!(
+            "chain records event_hash {event_hash} in {}, proof claims {}",
+            proof.handle,
+            proof.event_hash
+        );
+    }
+    match &proof.payload_hash {
+        Some(claimed) if hash_eq(&payload_hash, claimed) => Ok(()),
+        Some(claimed) => bail!(
+            "chain records payload_hash {payload_hash} in {}, proof claims {claimed}",
+            proof.handle
+        ),
+        None => bail!("audit proof has no payload_hash"),
+    }
+}
+
+/// Parse a JSON-RPC hex quantity.
+fn quantity(value: &Value) -> Result<u64> {
+    let raw = value
+        .as_str()
+        .ok_or_else(|| anyhow!("quantity is not a string"))?;
+    u64::from_str_radix(raw.trim_start_matches("0x"), 16).context("parse hex quantity")
+}
+
+/// Decode the ABI-encoded arguments of a `record(bytes32,string)` call into
+/// the hex event hash and the payload hash string.
+fn decode_record_call(input: &str) -> Result<(String, String)> {
+    let data = hex::decode(input.trim_start_matches("0x")).context("decode call data")?;
+    if data.len() < 4 + 32 * 3 || data[..4] != RECORD_SELECTOR {
+        bail!("transaction is not a record(bytes32,string) call");
+    }
+    let args = &data[4..];
+
+    let event_hash = hex::encode(&args[..32]);
+    let offset = abi_usize(&args[32..64])?;
+    let len_end = offset
+        .checked_add(32)
+        .filter(|end| *end <= args.len())
+        .ok_or_else(|| anyhow!("string offset out of range"))?;
+    let len = abi_usize(&args[offset..len_end])?;
+    let value = args
+        .get(len_end..len_end.saturating_add(len))
+        .ok_or_else(|| anyhow!("string length out of range"))?;
+    let payload_hash = String::from_utf8(value.to_vec()).context("payload hash is not UTF-8")?;
+
+    Ok((event_hash, payload_hash))
+}
+
+/// Read a 32-byte big-endian ABI word that must fit in a `usize`.
+fn abi_usize(word: &[u8]) -> Result<usize> {
+    if word[..24].iter().any(|b| *b != 0) {
+        bail!("ABI word out of range");
+    }
+    let mut raw = [0u8; 8];
+    raw.copy_from_slice(&word[24..32]);
+    usize::try_from(u64::from_be_bytes(raw)).context("ABI word out of range")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::tests::{proof_for, stand_in_ledger};
+
+    const CONTRACT: &str = "0x00000000000000000000000000000000000000aa";
+    const TX: &str = "0xfeed";
+    const HASH: &str = "3333333333333333333333333333333333333333333333333333333333333333";
+
+    /// ABI-encode `record(bytes32 event_hash, string payload_hash)`.
+    fn record_call(event_hash: &str, payload_hash: &str) -> String {
+        let mut data = RECORD_SELECTOR.to_vec();
+        data.extend(hex::decode(event_hash).unwrap());
+        let mut offset = [0u8; 32];
+        offset[31] = 0x40;
+        data.extend(offset);
+        let mut len = [0u8; 32];
+        len[31] = payload_hash.len() as u8;
+        data.extend(len);
+        let mut value = payload_hash.as_bytes().to_vec();
+        value.resize(payload_hash.len().div_ceil(32) * 32, 0);
+        data.extend(value);
+        format!("0x{}", hex::encode(data))
+    }
+
+    async fn verifier(min_confirmations: u64) -> EthLedgerVerifier {
+        let input = record_call(HASH, HASH);
+        let rpc_url = stand_in_ledger(move |_, body| {
+            let req: Value = serde_json::from_str(body).unwrap();
+            let known = req["params"][0] == TX;
+            let result = match req["method"].as_str().unwrap() {
+                "eth_getTransactionReceipt" if known => {
+                    json!({"status": "0x1", "to": CONTRACT, "blockNumber": "0x10"})
+                }
+                "eth_getTransactionByHash" if known => json!({"input": input}),
+                "eth_blockNumber" => json!("0x11"),
+                _ => Value::Null,
+            };
+            (
+                200,
+                json!({"jsonrpc": "2.0", "id": 1, "result": result}).to_string(),
+            )
+        })
+        .await;
+        EthLedgerVerifier::new(Config {
+            rpc_url,
+            contract_address: CONTRACT.to_string(),
+            min_confirmations,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn decodes_record_call() {
+        let (event_hash, payload_hash) = decode_record_call(&record_call(HASH, "abc")).unwrap();
+        assert_eq!(event_hash, HASH);
+        assert_eq!(payload_hash, "abc");
+        assert!(decode_record_call("0x00").is_err());
+    }
+
+    #[tokio::test]
+    async fn confirms_recorded_proof() {
+        verifier(2)
+            .await
+            .verify(&proof_for(BACKEND, TX, HASH))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_unconfirmed_unknown_or_mismatching_proof() {
+        assert!(verifier(3)
+            .await
+            .verify(&proof_for(BACKEND, TX, HASH))
+            .await
+            .is_err());
+
+        let verifier = verifier(0).await;
+        assert!(verifier
+            .verify(&proof_for(BACKEND, "0xbeef", HASH))
+            .await
+            .is_err());
+        assert!(verifier
+            .verify(&proof_for(BACKEND, TX, &"44".repeat(32)))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+
+use super::{hash_eq, LedgerVerifier};
+use crate::reference_value::AuditProof;
+use crate::MessageRejected;
+
+/// Backend name RVDS writes into proofs recorded through its http ledger.
+const BACKEND: &str = "http";
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Config {
+    /// Base URL of the ledger gateway. A record is looked up with
+    /// `GET {endpoint}/{handle}`.
+    pub endpoint: String,
+
+    /// Optional bearer token for the ledger gateway.
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LedgerRecord {
+    event_hash: String,
+    #[serde(default)]
+    payload_hash: Option<String>,
+}
+
+/// Confirms proofs against the ledger gateway used by the RVDS `http` backend.
+pub struct HttpLedgerVerifier {
+    config: Config,
+    http: Client,
+}
+
+impl HttpLedgerVerifier {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            http: Client::new(),
+        }
+    }
+}
+
+#[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait(?Send))]
+#[cfg_attr(
+    not(all(
+        target_arch = "wasm32",
+        target_vendor = "unknown",
+        target_os = "unknown"
+    )),
+    async_trait
+)]
+impl LedgerVerifier for HttpLedgerVerifier {
+    async fn verify(&self, proof: &AuditProof) -> Result<()> {
+        if proof.backend != BACKEND {
+            return Err(anyhow!(
+                "proof was recorded by `{}`, expected `{BACKEND}`",
+                proof.backend
+            )
+            .context(MessageRejected));
+        }
+
+        let url = format!(
+            "{}/{}",
+            self.config.endpoint.trim_end_matches('/'),
+            proof.handle
+        );
+        let mut req = self.http.get(&url);
+        if let Some(key) = &self.config.api_key {
+            req = req.bearer_auth(key);
+        }
+
+        let resp = req.send().await.context("query ledger gateway")?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Err(anyhow!("ledger has no record {}", proof.handle).context(MessageRejected));
+        }
+        let record: LedgerRecord = resp
+            .error_for_status()
+            .context("ledger gateway status")?
+            .json()
+            .await
+            .context("parse ledger record")?;
+
+        check_record(&record, proof).context(MessageRejected)
+    }
+}
+
+/// Compare the hashes the ledger recorded with those `proof` claims.
+fn check_record(record: &LedgerRecord, proof: &AuditProof) -> Result<()> {
+    if !hash_eq(&record.event_hash, &proof.event_hash) {
+        bail!(
+            "ledger records event_hash {} for {}, proof claims {}",
+            record.event_hash,
+            proof.handle,
+            proof.event_hash
+        );
+    }
+    if let (Some(recorded), Some(claimed)) = (&record.payload_hash, &proof.payload_hash) {
+        if !hash_eq(recorded, claimed) {
+            bail!(
+                "ledger records payload_hash {recorded} for {}, proof claims {claimed}",
+                proof.handle
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::tests::{proof_for, stand_in_ledger};
+
+    const HASH: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+
+    async fn verifier() -> HttpLedgerVerifier {
+        let endpoint = stand_in_ledger(|path, _| match path {
+            "/records/h1" => (
+                200,
+                format!(r#"{{"event_hash":"{HASH}","payload_hash":"{HASH}"}}"#),
+            ),
+            _ => (404, "{}".to_string()),
+        })
+        .await;
+        HttpLedgerVerifier::new(Config {
+            endpoint: format!("{endpoint}/records"),
+            api_key: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn confirms_recorded_proof() {
+        let verifier = verifier().await;
+        verifier
+            .verify(&proof_for("http", "h1", HASH))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_or_mismatching_proof() {
+        let verifier = verifier().await;
+        assert!(verifier
+            .verify(&proof_for("http", "missing", HASH))
+            .await
+            .is_err());
+        assert!(verifier
+            .verify(&proof_for("http", "h1", &"22".repeat(32)))
+            .await
+            .is_err());
+        assert!(verifier
+            .verify(&proof_for("ethereum-gateway", "h1", HASH))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,355 @@
+//! Ledger verification of RVDS audit proofs.
+//!
+//! RVDS records the hash of each publish event in an external ledger and
+//! attaches the receipt to the forwarded message as an [`AuditProof`]. This
+//! stage recomputes the canonical payload hash and asks the ledger the event
+//! was recorded through to confirm the receipt, before any reference value is
+//! extracted from the message.
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::reference_value::AuditProof;
+use crate::rvds::canonical_payload;
+use crate::MessageRejected;
+
+pub mod eth;
+pub mod http;
+
+/// A ledger that can confirm audit proofs, the verifying counterpart of
+/// the RVDS `LedgerAdapter`.
+#[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait(?Send))]
+#[cfg_attr(
+    not(all(
+        target_arch = "wasm32",
+        target_vendor = "unknown",
+        target_os = "unknown"
+    )),
+    async_trait
+)]
+pub trait LedgerVerifier {
+    /// Confirm that the event and payload hashes in `proof` are recorded on
+    /// the ledger under `proof.handle`. Errors for proofs the ledger
+    /// contradicts carry [`MessageRejected`], errors reaching it do not.
+    async fn verify(&self, proof: &AuditProof) -> Result<()>;
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Default)]
+#[serde(tag = "type")]
+pub enum LedgerBackendConfig {
+    /// Audit proofs are not confirmed against any ledger.
+    #[default]
+    None,
+    /// Ledger gateway reached over HTTP, matching the RVDS `http` backend.
+    Http(http::Config),
+    /// Ethereum chain written by the RVDS ethereum gateway.
+    Eth(eth::Config),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Default)]
+pub struct LedgerConfig {
+    #[serde(default)]
+    pub backend: LedgerBackendConfig,
+
+    /// Reject messages that do not carry an audit proof.
+    #[serde(default)]
+    pub require_proof: bool,
+}
+
+impl LedgerConfig {
+    /// Build the verification stage, or `None` when it is disabled.
+    pub fn to_verification(&self) -> Result<Option<LedgerVerification>> {
+        let verifier: Box<dyn LedgerVerifier + Send + Sync> = match &self.backend {
+            LedgerBackendConfig::None => {
+                if self.require_proof {
+                    bail!("ledger.require_proof needs a ledger backend to confirm proofs");
+                }
+                return Ok(None);
+            }
+            LedgerBackendConfig::Http(cfg) => Box::new(http::HttpLedgerVerifier::new(cfg.clone())),
+            LedgerBackendConfig::Eth(cfg) => Box::new(eth::EthLedgerVerifier::new(cfg.clone())?),
+        };
+
+        Ok(Some(LedgerVerification {
+            verifier,
+            require_proof: self.require_proof,
+        }))
+    }
+}
+
+/// Verification stage run on every registered message.
+pub struct LedgerVerification {
+    verifier: Box<dyn LedgerVerifier + Send + Sync>,
+    require_proof: bool,
+}
+
+impl LedgerVerification {
+    pub fn new(verifier: Box<dyn LedgerVerifier + Send + Sync>, require_proof: bool) -> Self {
+        Self {
+            verifier,
+            require_proof,
+        }
+    }
+
+    /// Check the audit proof carried by a message payload, if any.
+    pub async fn verify_payload(&self, payload: &str) -> Result<()> {
+        let Some((payload, proof)) = extract_audit_proof(payload).context(MessageRejected)? else {
+            if self.require_proof {
+                return Err(anyhow!("message carries no audit proof").context(MessageRejected));
+            }
+            return Ok(());
+        };
+
+        verify_proof_hashes(&payload, &proof).context(MessageRejected)?;
+        self.verifier
+            .verify(&proof)
+            .await
+            .with_context(|| format!("confirm audit proof {} on {}", proof.handle, proof.backend))
+    }
+}
+
+/// Return the decoded payload and its audit proof when the payload is an
+/// RVDS publish event (raw or base64-encoded JSON) carrying one.
+fn extract_audit_proof(payload: &str) -> Result<Option<(String, AuditProof)>> {
+    let payload = match serde_json::from_str::<Value>(payload) {
+        Ok(_) => payload.to_string(),
+        Err(_) => match base64::engine::general_purpose::STANDARD.decode(payload) {
+            Ok(decoded) => String::from_utf8(decoded).context("payload is not UTF-8")?,
+            Err(_) => return Ok(None),
+        },
+    };
+
+    let Ok(Value::Object(map)) = serde_json::from_str::<Value>(&payload) else {
+        return Ok(None);
+    };
+    let Some(proof) = map.get("audit_proof").filter(|p| !p.is_null()) else {
+        return Ok(None);
+    };
+    let proof: AuditProof =
+        serde_json::from_value(proof.clone()).context("parse audit_proof of the payload")?;
+
+    Ok(Some((payload, proof)))
+}
+
+/// Recompute the canonical payload hash and compare it with the proof.
+fn verify_proof_hashes(payload: &str, proof: &AuditProof) -> Result<()> {
+    let canonical = canonical_payload(payload)?;
+    let payload_hash = crate::rvds::canonical_payload_hash(payload)?;
+
+    if !hash_eq(&proof.event_hash, &payload_hash) {
+        bail!(
+            "audit proof event_hash {} does not match the payload ({payload_hash})",
+            proof.event_hash
+        );
+    }
+    match &proof.payload_hash {
+        Some(hash) if hash_eq(hash, &payload_hash) => {}
+        Some(hash) => {
+            bail!("audit proof payload_hash {hash} does not match the payload ({payload_hash})")
+        }
+        None => bail!("audit proof has no payload_hash"),
+    }
+    if let Some(b64) = &proof.payload_b64 {
+        let recorded = base64::engine::general_purpose::STANDARD
+            .decode(b64)
+            .context("decode audit proof payload_b64")?;
+        if recorded != canonical.as_bytes() {
+            bail!("audit proof payload_b64 does not match the payload");
+        }
+    }
+
+    Ok(())
+}
+
+/// Compare two hex digests, ignoring case and an optional `0x` prefix.
+pub(crate) fn hash_eq(a: &str, b: &str) -> bool {
+    a.trim_start_matches("0x")
+        .eq_ignore_ascii_case(b.trim_start_matches("0x"))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Arc;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    type Handler = dyn Fn(&str, &str) -> (u16, String) + Send + Sync;
+
+    /// Serve a stand-in ledger on a local port. `handler` receives the request
+    /// line path and body and returns the status and JSON body to answer.
+    pub(crate) async fn stand_in_ledger(
+        handler: impl Fn(&str, &str) -> (u16, String) + Send + Sync + 'static,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    let (head_end, content_length) = loop {
+                        let n = stream.read(&mut chunk).await.unwrap();
+                        if n == 0 {
+                            return;
+                        }
+                        buf.extend_from_slice(&chunk[..n]);
+                        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                            let head = String::from_utf8_lossy(&buf[..pos]).to_lowercase();
+                            let len = head
+                                .lines()
+                                .find_map(|l| l.strip_prefix("content-length:"))
+                                .map(|v| v.trim().parse::<usize>().unwrap())
+                                .unwrap_or(0);
+                            break (pos + 4, len);
+                        }
+                    };
+                    while buf.len() < head_end + content_length {
+                        let n = stream.read(&mut chunk).await.unwrap();
+                        buf.extend_from_slice(&chunk[..n]);
+                    }
+
+                    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+                    let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    let body = String::from_utf8_lossy(&buf[head_end..]).to_string();
+                    let (status, response) = handler(&path, &body);
+                    let reply = format!(
+                        "HTTP/1.1 {status} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                        response.len()
+                    );
+                    let _ = stream.write_all(reply.as_bytes()).await;
+                });
+            }
+        });
+
+        format!("http://{addr}")
+    }
+
+    /// An RVDS publish event payload and its canonical hash.
+    pub(crate) fn rvds_payload(proof: Option<AuditProof>) -> (String, String) {
+        let canonical = serde_json::json!({
+            "artifact_type": "rpm",
+            "slsa_provenance": ["e30="],
+            "artifacts_download_url": ["https://example.com/a.rpm"],
+        });
+        let hash = crate::rvds::canonical_payload_hash(&canonical.to_string()).unwrap();
+        let mut payload = canonical;
+        if let Some(proof) = proof {
+            payload["audit_proof"] = serde_json::to_value(proof).unwrap();
+        }
+        (payload.to_string(), hash)
+    }
+
+    pub(crate) fn proof_for(backend: &str, handle: &str, hash: &str) -> AuditProof {
+        AuditProof {
+            backend: backend.to_string(),
+            handle: handle.to_string(),
+            event_hash: hash.to_string(),
+            payload_hash: Some(hash.to_string()),
+            payload_b64: None,
+        }
+    }
+
+    struct AcceptAll;
+
+    #[async_trait]
+    impl LedgerVerifier for AcceptAll {
+        async fn verify(&self, _proof: &AuditProof) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn payload_hash_mismatch_is_rejected() {
+        let stage = LedgerVerification::new(Box::new(AcceptAll), false);
+        let (_, hash) = rvds_payload(None);
+        let (payload, _) = rvds_payload(Some(proof_for("http", "h", &hash)));
+        stage.verify_payload(&payload).await.unwrap();
+
+        let (payload, _) = rvds_payload(Some(proof_for("http", "h", &"ab".repeat(32))));
+        assert!(stage.verify_payload(&payload).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn missing_proof_depends_on_require_proof() {
+        let (payload, _) = rvds_payload(None);
+        LedgerVerification::new(Box::new(AcceptAll), false)
+            .verify_payload(&payload)
+            .await
+            .unwrap();
+        assert!(LedgerVerification::new(Box::new(AcceptAll), true)
+            .verify_payload(&payload)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn rvps_rejects_messages_the_ledger_does_not_confirm() {
+        let statement = serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "predicateType": "https://slsa.dev/provenance/v1",
+            "subject": [{"name": "a.rpm", "digest": {"sha256": "aa".repeat(32)}}],
+            "predicate": {}
+        });
+        let mut payload = serde_json::json!({
+            "artifact_type": "rpm",
+            "slsa_provenance": [base64::engine::general_purpose::STANDARD.encode(statement.to_string())],
+            "artifacts_download_url": ["https://example.com/a.rpm"],
+        });
+        let hash = crate::rvds::canonical_payload_hash(&payload.to_string()).unwrap();
+
+        let recorded = hash.clone();
+        let endpoint = stand_in_ledger(move |path, _| match path {
+            "/known" => (
+                200,
+                format!(r#"{{"event_hash":"{recorded}","payload_hash":"{recorded}"}}"#),
+            ),
+            _ => (404, "{}".to_string()),
+        })
+        .await;
+        let mut rvps = crate::Rvps::new(crate::Config {
+            storage: crate::storage::ReferenceValueStorageConfig::InMemory(Default::default()),
+            ledger: LedgerConfig {
+                backend: LedgerBackendConfig::Http(http::Config {
+                    endpoint,
+                    api_key: None,
+                }),
+                require_proof: true,
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        let message = |payload: &Value| {
+            serde_json::json!({"type": "slsa", "payload": payload.to_string()}).to_string()
+        };
+
+        payload["audit_proof"] = serde_json::to_value(proof_for("http", "unknown", &hash)).unwrap();
+        assert!(rvps.verify_and_extract(&message(&payload)).await.is_err());
+        assert!(rvps.query_reference_value("a.rpm").await.unwrap().is_none());
+
+        payload["audit_proof"] = serde_json::to_value(proof_for("http", "known", &hash)).unwrap();
+        rvps.verify_and_extract(&message(&payload)).await.unwrap();
+        assert!(rvps.query_reference_value("a.rpm").await.unwrap().is_some());
+    }
+
+    #[test]
+    fn require_proof_needs_backend() {
+        let cfg = LedgerConfig {
+            backend: LedgerBackendConfig::None,
+            require_proof: true,
+        };
+        assert!(cfg.to_verification().is_err());
+        assert!(LedgerConfig::default().to_verification().unwrap().is_none());
+    }
+}
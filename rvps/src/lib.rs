@@ -6,35 +6,49 @@
 #[cfg(feature = "bin")]
 pub mod client;
 pub mod config;
+#[cfg(any(feature = "fs", feature = "corim"))]
+mod crypto;
+pub mod expiry;
 pub mod extractors;
+pub mod history;
+pub mod ledger;
+pub mod namespace;
 pub mod pre_processor;
 mod provenance_source;
 pub mod reference_value;
 mod rekor;
 pub mod rv_list;
+pub mod rvds;
 #[cfg(feature = "bin")]
 pub mod rvps_api;
 #[cfg(feature = "bin")]
 pub mod server;
+pub mod snapshot;
 pub mod storage;
 
 pub use config::Config;
+pub use provenance_source::ProvenanceSourceConfig;
 pub use reference_value::{ReferenceValue, TrustedDigest};
 pub use storage::ReferenceValueStorage;
 
 use extractors::Extractors;
-use pre_processor::{PreProcessor, PreProcessorAPI};
+use history::{HistoryEntry, HistoryOperation, LOCAL_ACTOR};
+use ledger::LedgerVerification;
+use namespace::{NamespaceConfig, DEFAULT_NAMESPACE};
+use pre_processor::rate_limit::RateLimitExceeded;
+use pre_processor::{PreProcessor, PreProcessorAPI, Ware};
+use snapshot::{ImportAction, ImportResult, ImportStrategy, SnapshotEnvelope, SnapshotVerifier};
 
 use anyhow::{bail, Context, Result};
 use base64::Engine;
-use chrono::{Months, Timelike, Utc};
-use log::{info, warn};
+use chrono::{DateTime, Months, Timelike, Utc};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sha2::Digest;
 use std::collections::{HashMap, HashSet};
 
-use provenance_source::{OciProvenanceFetcher, ProvenanceFetcher, ProvenanceSource};
+use provenance_source::ProvenanceSource;
 use rv_list::{
     extract_release_manifest_digests, extract_slsa_digests, parse_reference_value_list,
     parse_release_manifest_documents_from_material, ReferenceValueOperation,
@@ -43,6 +57,26 @@ use rv_list::{
 /// Default version of Message
 static MESSAGE_VERSION: &str = "0.1.0";
 
+/// Type of the messages carrying a reference value list, so that lists pass
+/// through the pre-processor wares like other messages.
+pub const REFERENCE_VALUE_LIST_TYPE: &str = "rv-list";
+
+/// Context of the errors rejecting a message for what it carries, rather
+/// than failing to verify or store it for now: registering the same message
+/// again fails again.
+#[derive(Debug)]
+pub struct MessageRejected;
+
+impl std::fmt::Display for MessageRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("message rejected")
+    }
+}
+
+/// How many times a reference value update is retried when another RVPS
+/// sharing the storage changes the same value concurrently.
+const MAX_UPDATE_ATTEMPTS: usize = 8;
+
 /// Message is an overall packet that Reference Value Provider Service
 /// receives. It will contain payload (content of different provenance,
 /// JSON format), provenance type (indicates the type of the payload)
@@ -51,12 +85,22 @@ static MESSAGE_VERSION: &str = "0.1.0";
 /// * `version`: version of this message.
 /// * `payload`: content of the provenance, JSON encoded.
 /// * `type`: provenance type of the payload.
+/// * `publisher` and `signature`: who published the payload and their
+///   signature of it, checked by the `Signature` pre-processor ware.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Message {
     #[serde(default = "default_version")]
     version: String,
     payload: String,
     r#type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    publisher: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+    /// Rules renaming the reference values extracted from the message,
+    /// attached by the `Rename` pre-processor ware.
+    #[serde(skip)]
+    renames: Vec<pre_processor::RenameRule>,
 }
 
 /// Set the default version for Message
@@ -66,9 +110,13 @@ fn default_version() -> String {
 
 /// The core of the RVPS, s.t. componants except communication componants.
 pub struct Rvps {
+    ledger: Option<LedgerVerification>,
     pre_processor: PreProcessor,
     extractors: Extractors,
     storage: Box<dyn ReferenceValueStorage + Send + Sync>,
+    namespaces: HashMap<String, NamespaceConfig>,
+    snapshots: SnapshotVerifier,
+    provenance_sources: ProvenanceSourceConfig,
 }
 
 fn merge_reference_values(old: ReferenceValue, new: ReferenceValue) -> ReferenceValue {
@@ -122,70 +170,197 @@ fn reference_payload_eq(old: &ReferenceValue, new: &ReferenceValue) -> bool {
     }
 }
 
+/// What importing `new` over the stored value `old` does by `strategy`, and
+/// the change to store, if any.
+fn import_change(
+    old: Option<&ReferenceValue>,
+    new: &ReferenceValue,
+    strategy: ImportStrategy,
+) -> (ImportAction, Option<(HistoryOperation, ReferenceValue)>) {
+    let Some(old) = old else {
+        return (
+            ImportAction::Add,
+            Some((HistoryOperation::Add, new.clone())),
+        );
+    };
+    if old == new {
+        return (ImportAction::Unchanged, None);
+    }
+
+    match strategy {
+        ImportStrategy::Merge => {
+            let merged = merge_reference_values(old.clone(), new.clone());
+            if merged == *old {
+                (ImportAction::Unchanged, None)
+            } else {
+                (ImportAction::Merge, Some((HistoryOperation::Merge, merged)))
+            }
+        }
+        ImportStrategy::Replace => (
+            ImportAction::Replace,
+            Some((HistoryOperation::Refresh, new.clone())),
+        ),
+        ImportStrategy::Skip => (ImportAction::Skip, None),
+    }
+}
+
 impl Rvps {
     /// Instantiate a new RVPS
     pub fn new(config: Config) -> Result<Self> {
-        let pre_processor = PreProcessor::default();
-        let extractors = Extractors::default();
+        let ledger = config.ledger.to_verification()?;
+        let pre_processor = PreProcessor::new(&config.pre_processor)?;
+        let extractors = Extractors::new(&config.extractors)?;
         let storage = config.storage.to_storage()?;
+        let snapshots = SnapshotVerifier::new(&config.snapshot)?;
+        for namespace in config.namespaces.keys() {
+            namespace::validate(namespace)?;
+        }
 
         Ok(Rvps {
+            ledger,
             pre_processor,
             extractors,
             storage,
+            namespaces: config.namespaces,
+            snapshots,
+            provenance_sources: config.provenance_source,
         })
     }
 
-    /// Add Ware to the Core's Pre-Processor
-    pub fn with_ware(&mut self, _ware: &str) -> &Self {
-        // TODO: no wares implemented now.
+    /// Add Ware to the Core's Pre-Processor, after the configured ones
+    pub fn with_ware(&mut self, ware: Box<dyn Ware + Send + Sync>) -> &Self {
+        self.pre_processor.add_ware(ware);
         self
     }
 
+    /// Check that `admin_token` may change the reference values of
+    /// `namespace`, returning the actor to record for the change. The
+    /// library API itself does not check it; servers exposing it do.
+    pub fn authorize(&self, namespace: &str, admin_token: Option<&str>) -> Result<String> {
+        namespace::authorize(&self.namespaces, namespace, admin_token)
+    }
+
     pub async fn verify_and_extract(&mut self, message: &str) -> Result<()> {
-        let mut message: Message = serde_json::from_str(message).context("parse message")?;
+        self.verify_and_extract_as(DEFAULT_NAMESPACE, message, LOCAL_ACTOR)
+            .await
+    }
+
+    /// Like [`Rvps::verify_and_extract`], registering the reference values to
+    /// `namespace` and recording `actor` in their history.
+    pub async fn verify_and_extract_as(
+        &mut self,
+        namespace: &str,
+        message: &str,
+        actor: &str,
+    ) -> Result<()> {
+        let source = ChangeSource::new(actor, message);
+        let mut message: Message = serde_json::from_str(message)
+            .context("parse message")
+            .context(MessageRejected)?;
 
         // Judge the version field
         if message.version != MESSAGE_VERSION {
-            bail!(
+            return Err(anyhow::anyhow!(
                 "Version unmatched! Need {}, given {}.",
                 MESSAGE_VERSION,
                 message.version
-            );
+            )
+            .context(MessageRejected));
         }
 
-        self.pre_processor.process(&mut message)?;
+        if let Some(ledger) = &self.ledger {
+            ledger
+                .verify_payload(&message.payload)
+                .await
+                .context("ledger verification")?;
+        }
 
-        let rv = self.extractors.process(message)?;
-        for v in rv.iter() {
+        self.pre_processor.process(&mut message).map_err(|e| {
+            if e.is::<RateLimitExceeded>() {
+                e
+            } else {
+                e.context(MessageRejected)
+            }
+        })?;
+
+        let renames = std::mem::take(&mut message.renames);
+        let rv = self.extractors.process(message).context(MessageRejected)?;
+        for mut v in rv {
+            if let Some(name) = pre_processor::rename::rename(&renames, v.name()) {
+                debug!("Reference value {} is renamed to {name}.", v.name());
+                v = v.set_name(&name);
+            }
+            let v = &v.set_namespace(namespace);
             let name = v.name().to_string();
-            if let Some(old) = self.storage.get(&name).await? {
+            self.update_reference_value(namespace, &name, &source, |old| match old {
                 // If the policy-facing payload is identical, skip and do not replace.
-                if reference_payload_eq(&old, v) {
+                Some(old) if reference_payload_eq(old, v) => {
                     info!(
                         "Reference value of {} unchanged (same hashes); skip update.",
                         name
                     );
-                    continue;
+                    None
                 }
-
-                let merged = merge_reference_values(old.clone(), v.clone());
-                let _ = self.storage.set(name, merged).await?;
-                info!(
-                    "Reference value of {} is extended (hash list merged) instead of replaced.",
-                    old.name()
-                );
-            } else {
-                let _ = self.storage.set(name, v.clone()).await?;
-                info!("Reference value of {} is added.", v.name());
-            }
+                Some(old) => {
+                    info!(
+                        "Reference value of {} is extended (hash list merged) instead of replaced.",
+                        old.name()
+                    );
+                    Some((
+                        HistoryOperation::Merge,
+                        merge_reference_values(old.clone(), v.clone()),
+                    ))
+                }
+                None => {
+                    info!("Reference value of {} is added.", v.name());
+                    Some((HistoryOperation::Add, v.clone()))
+                }
+            })
+            .await?;
         }
 
         Ok(())
     }
 
     pub async fn set_reference_value_list(&mut self, payload: &str) -> Result<()> {
-        let request = parse_reference_value_list(payload)?;
+        self.set_reference_value_list_as(DEFAULT_NAMESPACE, payload, LOCAL_ACTOR)
+            .await
+    }
+
+    /// Like [`Rvps::set_reference_value_list`], registering the reference
+    /// values to `namespace` and recording `actor` in their history.
+    ///
+    /// `payload` is the list, or a [`Message`] of type
+    /// [`REFERENCE_VALUE_LIST_TYPE`] carrying it, e.g. to sign it for the
+    /// `Signature` ware. Either passes through the pre-processor wares.
+    pub async fn set_reference_value_list_as(
+        &mut self,
+        namespace: &str,
+        payload: &str,
+        actor: &str,
+    ) -> Result<()> {
+        let source = ChangeSource::new(actor, payload);
+        let mut message = match serde_json::from_str::<Message>(payload) {
+            Ok(message) if message.r#type == REFERENCE_VALUE_LIST_TYPE => message,
+            _ => Message {
+                version: MESSAGE_VERSION.into(),
+                payload: payload.into(),
+                r#type: REFERENCE_VALUE_LIST_TYPE.into(),
+                publisher: None,
+                signature: None,
+                renames: Vec::new(),
+            },
+        };
+        if message.version != MESSAGE_VERSION {
+            bail!(
+                "Version unmatched! Need {}, given {}.",
+                MESSAGE_VERSION,
+                message.version
+            );
+        }
+        self.pre_processor.process(&mut message)?;
+        let renames = std::mem::take(&mut message.renames);
+        let request = parse_reference_value_list(&message.payload)?;
 
         for item in request.rv_list {
             let operation = ReferenceValueOperation::parse(&item.operation_type)?;
@@ -216,6 +391,13 @@ impl Rvps {
                 None if provenance_type == "rv-release-manifest" => item.id.clone(),
                 None => format!("measurement.{}.{}", item.rv_type, item.id),
             };
+            let name = match pre_processor::rename::rename(&renames, &name) {
+                Some(renamed) => {
+                    debug!("Reference value {name} is renamed to {renamed}.");
+                    renamed
+                }
+                None => name,
+            };
 
             let digest_set = if provenance_type == "rv-release-manifest" {
                 let source = item.provenance_source.as_ref().ok_or_else(|| {
@@ -223,7 +405,7 @@ impl Rvps {
                         "rv-release-manifest requires provenance_source with release manifest material"
                     )
                 })?;
-                let material = fetch_provenance_material(source).await?;
+                let material = fetch_provenance_material(&self.provenance_sources, source).await?;
                 let manifests = parse_release_manifest_documents_from_material(&material.raw_bytes)
                     .with_context(|| {
                         format!(
@@ -242,7 +424,8 @@ impl Rvps {
                 digest_set
             } else {
                 let slsa_docs = if let Some(source) = &item.provenance_source {
-                    let material = fetch_provenance_material(source).await?;
+                    let material =
+                        fetch_provenance_material(&self.provenance_sources, source).await?;
                     parse_slsa_documents_from_material(&material.raw_bytes).with_context(|| {
                         format!(
                             "parse fetched provenance material for `{}` (media type: {:?})",
@@ -280,6 +463,7 @@ impl Rvps {
             let mut rv = ReferenceValue::new()?
                 .set_version(reference_value::REFERENCE_VALUE_VERSION)
                 .set_name(&name)
+                .set_namespace(namespace)
                 .set_expiration(expiration);
 
             for (alg, value) in digest_set.iter() {
@@ -291,35 +475,71 @@ impl Rvps {
                 );
             }
 
-            if let Some(old) = self.storage.get(&name).await? {
-                if hash_set(&old) == hash_set(&rv) {
+            self.update_reference_value(namespace, &name, &source, |old| match old {
+                Some(old) if hash_set(old) == hash_set(&rv) => {
                     info!("Reference value of {} unchanged; skip update.", name);
-                    continue;
+                    None
                 }
-
-                match operation {
+                Some(old) => match operation {
                     ReferenceValueOperation::Add => {
-                        let merged = merge_reference_values(old.clone(), rv.clone());
-                        let _ = self.storage.set(name.clone(), merged).await?;
                         info!("Reference value of {} extended (add).", name);
+                        Some((
+                            HistoryOperation::Merge,
+                            merge_reference_values(old.clone(), rv.clone()),
+                        ))
                     }
                     ReferenceValueOperation::Refresh => {
-                        let _ = self.storage.set(name.clone(), rv.clone()).await?;
                         info!("Reference value of {} refreshed.", name);
+                        Some((HistoryOperation::Refresh, rv.clone()))
                     }
+                },
+                None => {
+                    info!("Reference value of {} is added.", name);
+                    Some((HistoryOperation::Add, rv.clone()))
                 }
-            } else {
-                let _ = self.storage.set(name.clone(), rv.clone()).await?;
-                info!("Reference value of {} is added.", name);
-            }
+            })
+            .await?;
         }
 
         Ok(())
     }
 
+    /// Store the value `update` derives from the stored value of `name` in
+    /// `namespace`, or nothing if it returns `None`, and record the change in
+    /// the history. If another RVPS sharing the storage changes the value in
+    /// between, `update` is applied again to the new value.
+    async fn update_reference_value(
+        &self,
+        namespace: &str,
+        name: &str,
+        source: &ChangeSource<'_>,
+        mut update: impl FnMut(Option<&ReferenceValue>) -> Option<(HistoryOperation, ReferenceValue)>,
+    ) -> Result<()> {
+        let key = storage_key(namespace, name)?;
+        for _ in 0..MAX_UPDATE_ATTEMPTS {
+            let old = self.storage.get(&key).await?;
+            let Some((operation, rv)) = update(old.as_ref()) else {
+                return Ok(());
+            };
+
+            let entry = source.entry(operation, Some(rv.clone()));
+            if self
+                .storage
+                .compare_and_commit(key.clone(), old.as_ref(), Some(rv), entry)
+                .await?
+                .is_some()
+            {
+                return Ok(());
+            }
+            debug!("Reference value of {} changed concurrently; retry.", name);
+        }
+
+        bail!("Reference value of {name} keeps changing concurrently")
+    }
+
     pub async fn get_digests(&self) -> Result<HashMap<String, Vec<String>>> {
         let mut rv_map = HashMap::new();
-        let reference_values = self.storage.get_values().await?;
+        let reference_values = self.storage.get_namespace_values(DEFAULT_NAMESPACE).await?;
 
         for rv in reference_values {
             if rv.expired() {
@@ -343,8 +563,14 @@ impl Rvps {
     /// This keeps the legacy bulk API available while allowing flexible JSON
     /// values to be represented without converting them to digest lists.
     pub async fn get_reference_values(&self) -> Result<HashMap<String, Value>> {
+        self.get_reference_values_in(DEFAULT_NAMESPACE).await
+    }
+
+    /// Query all non-expired policy-facing reference values of `namespace`.
+    pub async fn get_reference_values_in(&self, namespace: &str) -> Result<HashMap<String, Value>> {
+        namespace::validate(namespace::normalize(namespace))?;
         let mut rv_map = HashMap::new();
-        let reference_values = self.storage.get_values().await?;
+        let reference_values = self.storage.get_namespace_values(namespace).await?;
 
         for rv in reference_values {
             if rv.expired() {
@@ -360,7 +586,18 @@ impl Rvps {
 
     /// Query one policy-facing reference value by identifier.
     pub async fn query_reference_value(&self, reference_value_id: &str) -> Result<Option<Value>> {
-        let Some(reference_value) = self.storage.get(reference_value_id).await? else {
+        self.query_reference_value_in(DEFAULT_NAMESPACE, reference_value_id)
+            .await
+    }
+
+    /// Query one policy-facing reference value of `namespace` by identifier.
+    pub async fn query_reference_value_in(
+        &self,
+        namespace: &str,
+        reference_value_id: &str,
+    ) -> Result<Option<Value>> {
+        let key = storage_key(namespace, reference_value_id)?;
+        let Some(reference_value) = self.storage.get(&key).await? else {
             return Ok(None);
         };
 
@@ -373,53 +610,303 @@ impl Rvps {
     }
 
     pub async fn delete_reference_value(&mut self, name: &str) -> Result<bool> {
-        match self.storage.delete(name).await? {
-            Some(deleted_rv) => {
+        self.delete_reference_value_as(DEFAULT_NAMESPACE, name, LOCAL_ACTOR)
+            .await
+    }
+
+    /// Like [`Rvps::delete_reference_value`], deleting the reference value
+    /// of `namespace` and recording `actor` in its history.
+    pub async fn delete_reference_value_as(
+        &mut self,
+        namespace: &str,
+        name: &str,
+        actor: &str,
+    ) -> Result<bool> {
+        let key = storage_key(namespace, name)?;
+        for _ in 0..MAX_UPDATE_ATTEMPTS {
+            let Some(old) = self.storage.get(&key).await? else {
+                warn!("Reference value {} not found for deletion.", name);
+                return Ok(false);
+            };
+
+            let entry = ChangeSource::local(actor).entry(HistoryOperation::Delete, None);
+            if self
+                .storage
+                .compare_and_commit(key.clone(), Some(&old), None, entry)
+                .await?
+                .is_some()
+            {
+                info!("Reference value {} deleted successfully.", old.name());
+                return Ok(true);
+            }
+            debug!("Reference value of {} changed concurrently; retry.", name);
+        }
+
+        bail!("Reference value of {name} keeps changing concurrently")
+    }
+
+    /// All the reference values stored in `namespace`, expired or not,
+    /// sorted by name.
+    pub async fn export_reference_values(&self, namespace: &str) -> Result<Vec<ReferenceValue>> {
+        namespace::validate(namespace::normalize(namespace))?;
+        let mut values = self.storage.get_namespace_values(namespace).await?;
+        values.sort_by(|a, b| a.name().cmp(b.name()));
+        Ok(values)
+    }
+
+    /// Store the reference values of the snapshot file `snapshot` (see
+    /// [`SnapshotEnvelope`]) in `namespace`, handling those already stored
+    /// with different contents by `strategy`, and recording `actor` in their
+    /// history. With `dry_run`, nothing is stored. Returns the action taken,
+    /// or that would be taken, for each reference value.
+    ///
+    /// The reference values bypass the extractors and wares, so the snapshot
+    /// must be signed by a trusted key of the [`snapshot::SnapshotConfig`],
+    /// unless it allows unsigned snapshots.
+    pub async fn import_snapshot(
+        &mut self,
+        namespace: &str,
+        snapshot: &str,
+        strategy: ImportStrategy,
+        dry_run: bool,
+        actor: &str,
+    ) -> Result<Vec<ImportResult>> {
+        let envelope = SnapshotEnvelope::from_json(snapshot)?;
+        let reference_values = self.snapshots.verify(&envelope)?.reference_values;
+        let source = ChangeSource::new(actor, snapshot);
+        self.import_reference_values(namespace, reference_values, strategy, dry_run, &source)
+            .await
+    }
+
+    async fn import_reference_values(
+        &mut self,
+        namespace: &str,
+        reference_values: Vec<ReferenceValue>,
+        strategy: ImportStrategy,
+        dry_run: bool,
+        source: &ChangeSource<'_>,
+    ) -> Result<Vec<ImportResult>> {
+        let reference_values = reference_values
+            .into_iter()
+            .map(|rv| {
+                let key = storage_key(namespace, rv.name())?;
+                Ok((key, rv.set_namespace(namespace)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut results = Vec::new();
+        for (key, rv) in reference_values {
+            let name = rv.name().to_string();
+            let action = if dry_run {
+                let old = self.storage.get(&key).await?;
+                import_change(old.as_ref(), &rv, strategy).0
+            } else {
+                let mut action = ImportAction::Unchanged;
+                self.update_reference_value(namespace, &name, source, |old| {
+                    let (taken, change) = import_change(old, &rv, strategy);
+                    action = taken;
+                    change
+                })
+                .await?;
+                action
+            };
+
+            debug!("Import of reference value {}: {:?}", name, action);
+            results.push(ImportResult { name, action });
+        }
+
+        Ok(results)
+    }
+
+    /// Reference values expiring before `until`, soonest first, including
+    /// those expired already. Values of all namespaces if `namespace` is
+    /// `None`.
+    pub async fn expiring_reference_values(
+        &self,
+        namespace: Option<&str>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<ReferenceValue>> {
+        let values = match namespace {
+            Some(namespace) => {
+                namespace::validate(namespace::normalize(namespace))?;
+                self.storage.get_namespace_values(namespace).await?
+            }
+            None => self.storage.get_values().await?,
+        };
+        let mut values: Vec<_> = values
+            .into_iter()
+            .filter(|rv| rv.expiration <= until)
+            .collect();
+        values.sort_by_key(|rv| rv.expiration);
+        Ok(values)
+    }
+
+    /// Remove the reference value `name` of `namespace` if it is expired,
+    /// recording `actor` in its history. Returns the value removed, `None`
+    /// if it is not expired or was changed meanwhile.
+    pub async fn remove_expired_reference_value(
+        &mut self,
+        namespace: &str,
+        name: &str,
+        actor: &str,
+    ) -> Result<Option<ReferenceValue>> {
+        let key = storage_key(namespace, name)?;
+        let Some(stored) = self.storage.get(&key).await? else {
+            return Ok(None);
+        };
+        if !stored.expired() {
+            return Ok(None);
+        }
+
+        let entry = ChangeSource::local(actor).entry(HistoryOperation::Expire, None);
+        if self
+            .storage
+            .compare_and_commit(key, Some(&stored), None, entry)
+            .await?
+            .is_none()
+        {
+            // Refreshed or removed by another RVPS sharing the storage.
+            return Ok(None);
+        }
+
+        info!("Expired reference value {} removed.", stored.name());
+        Ok(Some(stored))
+    }
+
+    /// The history of the reference value `name` of `namespace`, oldest
+    /// first.
+    pub async fn reference_value_history(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> Result<Vec<HistoryEntry>> {
+        self.storage.history(&storage_key(namespace, name)?).await
+    }
+
+    /// The reference value `name` of `namespace` as it was stored at `at`,
+    /// expired or not. Changes made before the history was kept are not
+    /// known.
+    pub async fn reference_value_at(
+        &self,
+        namespace: &str,
+        name: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Option<ReferenceValue>> {
+        let history = self.reference_value_history(namespace, name).await?;
+        Ok(history::value_at(&history, at).cloned())
+    }
+
+    /// Restore the reference value `name` of `namespace` recorded by the
+    /// history entry `revision`, deleting it if that entry recorded a
+    /// deletion. Returns the revision of the history entry recording the
+    /// rollback, `None` if the value was already the one restored.
+    pub async fn rollback_reference_value(
+        &mut self,
+        namespace: &str,
+        name: &str,
+        revision: u64,
+        actor: &str,
+    ) -> Result<Option<u64>> {
+        let key = storage_key(namespace, name)?;
+        let history = self.storage.history(&key).await?;
+        let target = history
+            .into_iter()
+            .find(|entry| entry.revision == revision)
+            .ok_or_else(|| {
+                anyhow::anyhow!("reference value {name} has no history revision {revision}")
+            })?;
+        let source = ChangeSource::local(actor);
+
+        let restored = target.value;
+        if restored.as_ref().is_some_and(|rv| rv.expired()) {
+            warn!("Reference value {} is restored expired.", name);
+        }
+
+        for _ in 0..MAX_UPDATE_ATTEMPTS {
+            let old = self.storage.get(&key).await?;
+            if old == restored {
+                match restored {
+                    Some(_) => info!(
+                        "Reference value {} is already at revision {}.",
+                        name, revision
+                    ),
+                    None => info!("Reference value {} is already deleted.", name),
+                }
+                return Ok(None);
+            }
+
+            let mut entry = source.entry(HistoryOperation::Rollback, restored.clone());
+            entry.rollback_to = Some(revision);
+            if let Some(rollback) = self
+                .storage
+                .compare_and_commit(key.clone(), old.as_ref(), restored.clone(), entry)
+                .await?
+            {
                 info!(
-                    "Reference value {} deleted successfully.",
-                    deleted_rv.name()
+                    "Reference value {} rolled back to revision {}.",
+                    name, revision
                 );
-                Ok(true)
-            }
-            None => {
-                warn!("Reference value {} not found for deletion.", name);
-                Ok(false)
+                return Ok(Some(rollback));
             }
+            debug!("Reference value of {} changed concurrently; retry.", name);
         }
+
+        bail!("Reference value of {name} keeps changing concurrently")
     }
 }
 
-async fn fetch_provenance_material(
-    source: &rv_list::ReferenceValueProvenanceSource,
-) -> Result<provenance_source::FetchedProvenanceMaterial> {
-    let protocol = source.protocol.to_ascii_lowercase();
-    match protocol.as_str() {
-        "oci" => {
-            let fetcher = OciProvenanceFetcher::new();
-            let src = ProvenanceSource {
-                protocol: source.protocol.clone(),
-                uri: source.uri.clone(),
-                artifact: source.artifact.clone(),
-            };
-            fetcher
-                .fetch(&src)
-                .await
-                .with_context(|| format!("fetch provenance from OCI `{}`", src.uri))
+/// The storage key of the reference value `name` of `namespace`, checking
+/// that neither can be confused with another.
+fn storage_key(namespace: &str, name: &str) -> Result<String> {
+    namespace::validate(namespace::normalize(namespace))?;
+    namespace::validate_name(name)?;
+    Ok(namespace::storage_key(namespace, name))
+}
+
+/// Where a change of reference values comes from, as recorded in their
+/// history.
+struct ChangeSource<'a> {
+    actor: &'a str,
+    digest: Option<String>,
+}
+
+impl<'a> ChangeSource<'a> {
+    /// A change derived from the document `source`.
+    fn new(actor: &'a str, source: &str) -> Self {
+        Self {
+            actor,
+            digest: Some(history::source_digest(source)),
         }
-        #[cfg(feature = "fs")]
-        "file" => {
-            let path = source.uri.strip_prefix("file://").unwrap_or(&source.uri);
-            let raw_bytes = std::fs::read(path)
-                .with_context(|| format!("read provenance material from file `{path}`"))?;
-            Ok(provenance_source::FetchedProvenanceMaterial {
-                media_type: source.artifact.clone(),
-                raw_bytes,
-            })
+    }
+
+    /// A change not derived from any document, e.g. a deletion.
+    fn local(actor: &'a str) -> Self {
+        Self {
+            actor,
+            digest: None,
         }
-        other => bail!("unsupported provenance_source.protocol `{other}`"),
+    }
+
+    fn entry(&self, operation: HistoryOperation, value: Option<ReferenceValue>) -> HistoryEntry {
+        HistoryEntry::new(operation, self.actor, self.digest.clone(), value)
     }
 }
 
+async fn fetch_provenance_material(
+    config: &ProvenanceSourceConfig,
+    source: &rv_list::ReferenceValueProvenanceSource,
+) -> Result<provenance_source::FetchedProvenanceMaterial> {
+    let src = ProvenanceSource {
+        protocol: source.protocol.clone(),
+        uri: source.uri.clone(),
+        artifact: source.artifact.clone(),
+        digest: source.digest.clone(),
+    };
+    provenance_source::fetch(config, &src)
+        .await
+        .with_context(|| format!("fetch provenance from {} `{}`", src.protocol, src.uri))
+}
+
 fn parse_slsa_documents_from_material(raw_bytes: &[u8]) -> Result<Vec<String>> {
     let text = std::str::from_utf8(raw_bytes).context("provenance material is not UTF-8 text")?;
     let trimmed = text.trim();
@@ -643,10 +1130,122 @@ mod tests {
     fn in_memory_rvps() -> Rvps {
         Rvps::new(Config {
             storage: ReferenceValueStorageConfig::InMemory(in_memory::Config::default()),
+            ..Default::default()
         })
         .unwrap()
     }
 
+    async fn import_actions(
+        rvps: &mut Rvps,
+        values: Vec<ReferenceValue>,
+        strategy: ImportStrategy,
+        dry_run: bool,
+    ) -> Vec<ImportAction> {
+        let source = ChangeSource::local(LOCAL_ACTOR);
+        rvps.import_reference_values(DEFAULT_NAMESPACE, values, strategy, dry_run, &source)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|result| result.action)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn import_resolves_conflicts_by_strategy() {
+        let mut rvps = in_memory_rvps();
+        let expiration = Utc::now() + Duration::days(30);
+        let rv = |name: &str, digest: &str| {
+            ReferenceValue::new()
+                .unwrap()
+                .set_name(name)
+                .set_expiration(expiration)
+                .add_hash_value("sha256".into(), digest.into())
+        };
+        use ImportAction::*;
+
+        let initial = vec![rv("a", "1"), rv("b", "2")];
+        assert_eq!(
+            import_actions(&mut rvps, initial, ImportStrategy::Merge, false).await,
+            [Add, Add]
+        );
+
+        let incoming = || vec![rv("a", "1"), rv("b", "3"), rv("c", "4")];
+        assert_eq!(
+            import_actions(&mut rvps, incoming(), ImportStrategy::Replace, true).await,
+            [Unchanged, Replace, Add]
+        );
+        assert_eq!(
+            import_actions(&mut rvps, incoming(), ImportStrategy::Skip, false).await,
+            [Unchanged, Skip, Add]
+        );
+        assert_eq!(
+            import_actions(&mut rvps, incoming(), ImportStrategy::Merge, false).await,
+            [Unchanged, Merge, Unchanged]
+        );
+        assert_eq!(
+            import_actions(
+                &mut rvps,
+                vec![rv("b", "5")],
+                ImportStrategy::Replace,
+                false
+            )
+            .await,
+            [Replace]
+        );
+
+        let values = rvps
+            .export_reference_values(DEFAULT_NAMESPACE)
+            .await
+            .unwrap();
+        let names: Vec<_> = values.iter().map(|rv| rv.name().as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert_eq!(hash_set(&values[1]), hash_set(&rv("b", "5")));
+        let history = rvps
+            .reference_value_history(DEFAULT_NAMESPACE, "b")
+            .await
+            .unwrap();
+        let operations: Vec<_> = history.iter().map(|entry| entry.operation).collect();
+        assert_eq!(
+            operations,
+            [
+                HistoryOperation::Add,
+                HistoryOperation::Merge,
+                HistoryOperation::Refresh
+            ]
+        );
+
+        // Snapshots bypass the extractors, so unsigned ones are refused
+        // unless allowed.
+        let snapshot = snapshot::Snapshot::new(DEFAULT_NAMESPACE, vec![rv("d", "6")]);
+        let file = serde_json::to_string(&SnapshotEnvelope::new(&snapshot).unwrap()).unwrap();
+        assert!(rvps
+            .import_snapshot(
+                DEFAULT_NAMESPACE,
+                &file,
+                ImportStrategy::Merge,
+                false,
+                LOCAL_ACTOR
+            )
+            .await
+            .is_err());
+        rvps.snapshots = SnapshotVerifier::new(&snapshot::SnapshotConfig {
+            allow_unsigned: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let results = rvps
+            .import_snapshot(
+                DEFAULT_NAMESPACE,
+                &file,
+                ImportStrategy::Merge,
+                false,
+                LOCAL_ACTOR,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results[0].action, Add);
+    }
+
     #[tokio::test]
     async fn keyed_query_supports_legacy_and_flexible_values() {
         let mut rvps = in_memory_rvps();
@@ -710,6 +1309,186 @@ mod tests {
             .contains_key("expired"));
     }
 
+    fn sample_message(payload: serde_json::Value) -> String {
+        serde_json::json!({
+            "version": MESSAGE_VERSION,
+            "type": "sample",
+            "payload": base64::engine::general_purpose::STANDARD.encode(payload.to_string())
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn history_point_in_time_and_rollback() {
+        let mut rvps = in_memory_rvps();
+        let first = sample_message(serde_json::json!({"svn": ["digest-a"]}));
+        let second = sample_message(serde_json::json!({"svn": ["digest-b"]}));
+
+        rvps.verify_and_extract_as(DEFAULT_NAMESPACE, &first, "alice")
+            .await
+            .unwrap();
+        let before_merge = Utc::now();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        rvps.verify_and_extract_as(DEFAULT_NAMESPACE, &second, "bob")
+            .await
+            .unwrap();
+        rvps.delete_reference_value_as(DEFAULT_NAMESPACE, "svn", "carol")
+            .await
+            .unwrap();
+
+        let history = rvps
+            .reference_value_history(DEFAULT_NAMESPACE, "svn")
+            .await
+            .unwrap();
+        let operations: Vec<_> = history.iter().map(|e| e.operation).collect();
+        assert_eq!(
+            operations,
+            [
+                HistoryOperation::Add,
+                HistoryOperation::Merge,
+                HistoryOperation::Delete
+            ]
+        );
+        assert_eq!(history[0].actor, "alice");
+        assert_eq!(
+            history[0].source_digest,
+            Some(history::source_digest(&first))
+        );
+        assert_eq!(history[2].value, None);
+
+        let at = rvps
+            .reference_value_at(DEFAULT_NAMESPACE, "svn", before_merge)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(at.policy_value(), serde_json::json!(["digest-a"]));
+        assert_eq!(
+            rvps.reference_value_at(DEFAULT_NAMESPACE, "svn", Utc::now())
+                .await
+                .unwrap(),
+            None
+        );
+
+        let revision = rvps
+            .rollback_reference_value(DEFAULT_NAMESPACE, "svn", history[0].revision, "dave")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            rvps.query_reference_value("svn").await.unwrap(),
+            Some(serde_json::json!(["digest-a"]))
+        );
+        // Rolling back to the value already stored changes nothing.
+        assert_eq!(
+            rvps.rollback_reference_value(DEFAULT_NAMESPACE, "svn", history[0].revision, "dave")
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(rvps
+            .rollback_reference_value(DEFAULT_NAMESPACE, "svn", 100, "dave")
+            .await
+            .is_err());
+
+        let history = rvps
+            .reference_value_history(DEFAULT_NAMESPACE, "svn")
+            .await
+            .unwrap();
+        let last = history.last().unwrap();
+        assert_eq!(last.revision, revision);
+        assert_eq!(last.operation, HistoryOperation::Rollback);
+        assert_eq!(last.rollback_to, Some(history[0].revision));
+    }
+
+    #[tokio::test]
+    async fn namespaces_are_isolated() {
+        let mut rvps = in_memory_rvps();
+        let team_a = sample_message(serde_json::json!({"svn": ["digest-a"]}));
+        let team_b = sample_message(serde_json::json!({"svn": ["digest-b"]}));
+
+        rvps.verify_and_extract_as("team-a", &team_a, "alice")
+            .await
+            .unwrap();
+        rvps.verify_and_extract_as("team-b", &team_b, "bob")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            rvps.query_reference_value_in("team-a", "svn")
+                .await
+                .unwrap(),
+            Some(serde_json::json!(["digest-a"]))
+        );
+        assert_eq!(
+            rvps.query_reference_value_in("team-b", "svn")
+                .await
+                .unwrap(),
+            Some(serde_json::json!(["digest-b"]))
+        );
+        assert_eq!(rvps.query_reference_value("svn").await.unwrap(), None);
+        assert_eq!(
+            rvps.get_reference_values_in("team-a").await.unwrap(),
+            HashMap::from([("svn".to_string(), serde_json::json!(["digest-a"]))])
+        );
+        assert!(rvps.get_reference_values().await.unwrap().is_empty());
+
+        assert!(rvps
+            .delete_reference_value_as("team-a", "svn", "alice")
+            .await
+            .unwrap());
+        assert_eq!(
+            rvps.query_reference_value_in("team-b", "svn")
+                .await
+                .unwrap(),
+            Some(serde_json::json!(["digest-b"]))
+        );
+        assert_eq!(
+            rvps.reference_value_history("team-b", "svn")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(rvps
+            .query_reference_value_in("team/a", "svn")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn configured_wares_run_before_extraction() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "storage": {"type": "InMemory"},
+            "pre_processor": {"wares": [
+                {"type": "Validation", "types": ["sample"], "required_fields": {"sample": ["/svn"]}},
+                {"type": "Rename", "rules": [{"from": "svn", "to": "team-a.svn"}]}
+            ]}
+        }))
+        .unwrap();
+        let mut rvps = Rvps::new(config).unwrap();
+
+        rvps.verify_and_extract(&sample_message(serde_json::json!({"svn": ["digest-a"]})))
+            .await
+            .unwrap();
+        assert_eq!(
+            rvps.query_reference_value("team-a.svn").await.unwrap(),
+            Some(serde_json::json!(["digest-a"]))
+        );
+        assert_eq!(rvps.query_reference_value("svn").await.unwrap(), None);
+
+        assert!(rvps
+            .verify_and_extract(&sample_message(serde_json::json!({"other": ["digest-a"]})))
+            .await
+            .is_err());
+
+        // Reference value lists pass through the wares too.
+        let err = rvps
+            .set_reference_value_list(r#"{"rv_list": []}"#)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("`rv-list` is not accepted"));
+    }
+
     #[cfg(feature = "fs")]
     #[tokio::test]
     async fn set_reference_value_list_from_release_manifest_file() {
@@ -723,6 +1502,10 @@ mod tests {
             storage: ReferenceValueStorageConfig::LocalJson(local_json::Config {
                 file_path: storage_path.to_string_lossy().to_string(),
             }),
+            provenance_source: ProvenanceSourceConfig {
+                file_root: Some(tmp.path().to_string_lossy().to_string()),
+            },
+            ..Default::default()
         })
         .unwrap();
         let payload = serde_json::json!({
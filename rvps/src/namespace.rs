@@ -0,0 +1,162 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Namespaces of reference values.
+//!
+//! Every reference value belongs to a namespace, so that tenants publishing
+//! the same artifact id keep separate reference values. Values registered
+//! without naming a namespace belong to [`DEFAULT_NAMESPACE`], and are stored
+//! under their bare names as before namespaces existed. Values of the other
+//! namespaces are stored under a key qualified with the namespace.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// The namespace of reference values registered without naming one.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Separates the namespace from the name in storage keys.
+const KEY_SEPARATOR: char = '\u{1f}';
+
+/// Settings of a namespace.
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct NamespaceConfig {
+    /// Bearer tokens allowed to change the reference values of the
+    /// namespace. Anyone may change them if empty.
+    #[serde(default)]
+    pub admin_tokens: Vec<String>,
+}
+
+/// `namespace`, or [`DEFAULT_NAMESPACE`] if it is empty.
+pub fn normalize(namespace: &str) -> &str {
+    if namespace.is_empty() {
+        DEFAULT_NAMESPACE
+    } else {
+        namespace
+    }
+}
+
+/// Check that `namespace` is a valid namespace name: ASCII letters, digits,
+/// `-`, `_` and `.`.
+pub fn validate(namespace: &str) -> Result<()> {
+    if namespace.is_empty()
+        || !namespace
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        bail!("invalid namespace `{namespace}`");
+    }
+    Ok(())
+}
+
+/// Check that `name` can be stored as the name of a reference value.
+pub(crate) fn validate_name(name: &str) -> Result<()> {
+    if name.contains(KEY_SEPARATOR) {
+        bail!("reference value name {name:?} contains a reserved character");
+    }
+    Ok(())
+}
+
+/// The key the reference value `name` of `namespace` is stored under.
+pub fn storage_key(namespace: &str, name: &str) -> String {
+    let namespace = normalize(namespace);
+    if namespace == DEFAULT_NAMESPACE {
+        name.to_string()
+    } else {
+        format!("{namespace}{KEY_SEPARATOR}{name}")
+    }
+}
+
+/// The namespace and name of the reference value stored under `key`.
+pub fn split_storage_key(key: &str) -> (&str, &str) {
+    key.split_once(KEY_SEPARATOR)
+        .unwrap_or((DEFAULT_NAMESPACE, key))
+}
+
+/// Actor recorded for changes to namespaces without admin tokens, which
+/// authenticate nobody.
+pub const ANONYMOUS_ACTOR: &str = "anonymous";
+
+/// Check `token` against the admin tokens of `namespace`. Namespaces other
+/// than [`DEFAULT_NAMESPACE`] must be configured to be changed at all.
+/// Returns the actor to record in the history: `admin:<id>`, `<id>` being
+/// the start of the SHA-256 digest of the token, or [`ANONYMOUS_ACTOR`].
+pub(crate) fn authorize(
+    namespaces: &HashMap<String, NamespaceConfig>,
+    namespace: &str,
+    token: Option<&str>,
+) -> Result<String> {
+    let namespace = normalize(namespace);
+    let Some(config) = namespaces.get(namespace) else {
+        if namespace == DEFAULT_NAMESPACE {
+            return Ok(ANONYMOUS_ACTOR.to_string());
+        }
+        bail!("namespace `{namespace}` is not configured");
+    };
+
+    if config.admin_tokens.is_empty() {
+        return Ok(ANONYMOUS_ACTOR.to_string());
+    }
+
+    // Compare digests so that the time taken does not depend on how much
+    // of a token was guessed right.
+    let Some(token) = token else {
+        bail!("namespace `{namespace}` requires an admin token");
+    };
+    let token = Sha256::digest(token.as_bytes());
+    if !config
+        .admin_tokens
+        .iter()
+        .any(|admin| Sha256::digest(admin.as_bytes()) == token)
+    {
+        bail!("admin token rejected for namespace `{namespace}`");
+    }
+    Ok(format!("admin:{}", hex::encode(&token[..8])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn storage_keys() {
+        assert_eq!(storage_key("", "a"), "a");
+        assert_eq!(storage_key(DEFAULT_NAMESPACE, "a"), "a");
+        assert_ne!(storage_key("team-a", "a"), storage_key("team-b", "a"));
+        assert_eq!(
+            split_storage_key(&storage_key("team-a", "a")),
+            ("team-a", "a")
+        );
+        assert_eq!(split_storage_key("a"), (DEFAULT_NAMESPACE, "a"));
+        assert!(validate_name(&storage_key("team-a", "a")).is_err());
+        assert!(validate("team-a").is_ok());
+        assert!(validate("team/a").is_err());
+    }
+
+    #[test]
+    fn admin_tokens() {
+        let namespaces = HashMap::from([
+            (
+                "team-a".to_string(),
+                NamespaceConfig {
+                    admin_tokens: vec!["secret".into()],
+                },
+            ),
+            ("open".to_string(), NamespaceConfig::default()),
+        ]);
+
+        assert_eq!(authorize(&namespaces, "", None).unwrap(), ANONYMOUS_ACTOR);
+        let actor = authorize(&namespaces, "team-a", Some("secret")).unwrap();
+        assert!(actor.starts_with("admin:"));
+        assert!(!actor.contains("secret"));
+        assert!(authorize(&namespaces, "team-a", Some("guess")).is_err());
+        assert!(authorize(&namespaces, "team-a", None).is_err());
+        assert!(authorize(&namespaces, "open", None).is_ok());
+        assert!(authorize(&namespaces, "team-b", None).is_err());
+    }
+}
@@ -8,9 +8,110 @@
 use std::collections::HashMap;
 
 use anyhow::*;
+use serde::Deserialize;
 
 use super::Message;
 
+pub mod rate_limit;
+pub mod rename;
+#[cfg(feature = "fs")]
+pub mod signature;
+pub mod validation;
+
+pub use rename::RenameRule;
+
+/// Context key under which the `Signature` ware records the publisher whose
+/// signature it verified.
+pub const PUBLISHER: &str = "publisher";
+
+/// Configuration of the Pre-Processor.
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct PreProcessorConfig {
+    /// Wares every message passes through, in this order, before the
+    /// extractors.
+    #[serde(default)]
+    pub wares: Vec<WareConfig>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub enum WareConfig {
+    /// Admit only messages signed by an allow-listed publisher.
+    Signature(SignatureConfig),
+    /// Check the size, type and fields of messages.
+    Validation(ValidationConfig),
+    /// Limit how many messages each publisher may send. Must follow a
+    /// `Signature` ware.
+    RateLimit(RateLimitConfig),
+    /// Rename the reference values extracted from messages.
+    Rename(RenameConfig),
+}
+
+/// Publishers allowed to sign messages.
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct SignatureConfig {
+    /// Paths to PEM ECDSA P-256/P-384 public keys, by publisher id.
+    #[serde(default)]
+    pub publishers: HashMap<String, String>,
+}
+
+/// Constraints on the messages.
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct ValidationConfig {
+    /// Largest accepted payload in bytes. Unlimited when absent.
+    #[serde(default)]
+    pub max_payload_bytes: Option<usize>,
+
+    /// Accepted message types. Any type is accepted when empty.
+    #[serde(default)]
+    pub types: Vec<String>,
+
+    /// JSON pointers that must be present in the payload, by message type.
+    /// Payloads of these types must be JSON, or base64-encoded JSON.
+    #[serde(default)]
+    pub required_fields: HashMap<String, Vec<String>>,
+}
+
+/// Messages a publisher may send per period.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct RateLimitConfig {
+    /// Messages a publisher may send within `period_secs`, and at once.
+    pub max_messages: u32,
+
+    #[serde(default = "default_period_secs")]
+    pub period_secs: u64,
+}
+
+fn default_period_secs() -> u64 {
+    60
+}
+
+/// Rules renaming extracted reference values.
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct RenameConfig {
+    /// The first matching rule renames a reference value.
+    #[serde(default)]
+    pub rules: Vec<RenameRule>,
+}
+
+impl WareConfig {
+    /// Build the ware.
+    pub fn to_ware(&self) -> Result<Box<dyn Ware + Send + Sync>> {
+        let ware: Box<dyn Ware + Send + Sync> = match self {
+            #[cfg(feature = "fs")]
+            WareConfig::Signature(config) => Box::new(signature::SignatureWare::new(config)?),
+            #[cfg(not(feature = "fs"))]
+            WareConfig::Signature(_) => bail!("the Signature ware requires the `fs` feature"),
+            WareConfig::Validation(config) => {
+                Box::new(validation::ValidationWare::new(config.clone())?)
+            }
+            WareConfig::RateLimit(config) => Box::new(rate_limit::RateLimitWare::new(config)?),
+            WareConfig::Rename(config) => Box::new(rename::RenameWare::new(config.rules.clone())),
+        };
+        Ok(ware)
+    }
+}
+
 /// A Ware loaded in Pre-Processor will process all the messages passing
 /// through the Pre-Processor. A series of Wares organized in order can
 /// process all the messages in need before they are consumed by the
@@ -67,6 +168,32 @@ pub struct PreProcessor {
     wares: Vec<Box<dyn Ware + Send + Sync>>,
 }
 
+impl PreProcessor {
+    /// Create a Pre-Processor running the configured wares.
+    pub fn new(config: &PreProcessorConfig) -> Result<Self> {
+        let signed = config
+            .wares
+            .iter()
+            .position(|ware| matches!(ware, WareConfig::Signature(_)));
+        let limited = config
+            .wares
+            .iter()
+            .position(|ware| matches!(ware, WareConfig::RateLimit(_)));
+        if let Some(limited) = limited {
+            if signed.is_none_or(|signed| signed > limited) {
+                bail!("the RateLimit ware needs a preceding Signature ware");
+            }
+        }
+
+        let wares = config
+            .wares
+            .iter()
+            .map(WareConfig::to_ware)
+            .collect::<Result<_>>()?;
+        Ok(Self { wares })
+    }
+}
+
 impl PreProcessorAPI for PreProcessor {
     fn process(&self, message: &mut Message) -> Result<()> {
         let mut context = HashMap::new();
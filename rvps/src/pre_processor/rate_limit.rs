@@ -0,0 +1,171 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Ware limiting how many messages each publisher may send.
+//!
+//! Each publisher may send `max_messages` at once, and one more every
+//! `period_secs / max_messages` seconds after that. A message is attributed
+//! to the publisher verified by a preceding `Signature` ware, which the ware
+//! requires: the publisher a message merely names could be changed at will
+//! to get a fresh limit.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+
+use super::{Next, RateLimitConfig, Ware, PUBLISHER};
+use crate::Message;
+
+/// Buckets kept before the least recently used one is dropped.
+const MAX_BUCKETS: usize = 1024;
+
+struct Bucket {
+    tokens: f64,
+    updated: DateTime<Utc>,
+}
+
+pub struct RateLimitWare {
+    capacity: f64,
+    /// Messages regained per second.
+    rate: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimitWare {
+    pub fn new(config: &RateLimitConfig) -> Result<Self> {
+        if config.max_messages == 0 || config.period_secs == 0 {
+            bail!("rate limit needs a positive max_messages and period_secs");
+        }
+        let capacity = f64::from(config.max_messages);
+        Ok(Self {
+            capacity,
+            rate: capacity / config.period_secs as f64,
+            buckets: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Take one message of `publisher` at `now` if it is within its limit.
+    fn admit(&self, publisher: &str, now: DateTime<Utc>) -> Result<bool> {
+        let mut buckets = self
+            .buckets
+            .lock()
+            .map_err(|_| anyhow!("rate limit buckets poisoned"))?;
+
+        if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(publisher) {
+            let lru = buckets
+                .iter()
+                .min_by_key(|(_, bucket)| bucket.updated)
+                .map(|(publisher, _)| publisher.clone());
+            if let Some(lru) = lru {
+                buckets.remove(&lru);
+            }
+        }
+
+        let bucket = buckets.entry(publisher.to_string()).or_insert(Bucket {
+            tokens: self.capacity,
+            updated: now,
+        });
+        bucket.tokens = self.refill(bucket, now);
+        bucket.updated = now;
+        if bucket.tokens < 1.0 {
+            return Ok(false);
+        }
+        bucket.tokens -= 1.0;
+        Ok(true)
+    }
+
+    fn refill(&self, bucket: &Bucket, now: DateTime<Utc>) -> f64 {
+        let elapsed = (now - bucket.updated).num_milliseconds().max(0) as f64 / 1000.0;
+        (bucket.tokens + elapsed * self.rate).min(self.capacity)
+    }
+}
+
+/// Error of a message refused by the ware. Unlike those of the other wares,
+/// the same message may be admitted later.
+#[derive(Debug)]
+pub struct RateLimitExceeded {
+    pub publisher: String,
+}
+
+impl std::fmt::Display for RateLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "publisher `{}` exceeded its rate limit", self.publisher)
+    }
+}
+
+impl std::error::Error for RateLimitExceeded {}
+
+impl Ware for RateLimitWare {
+    fn handle(
+        &self,
+        message: &mut Message,
+        context: &mut HashMap<String, String>,
+        next: Next<'_>,
+    ) -> Result<()> {
+        let publisher = context
+            .get(PUBLISHER)
+            .ok_or_else(|| anyhow!("rate limit needs a publisher verified by a Signature ware"))?;
+        if !self.admit(publisher, Utc::now())? {
+            return Err(RateLimitExceeded {
+                publisher: publisher.clone(),
+            }
+            .into());
+        }
+        next.run(message, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pre_processor::{PreProcessor, PreProcessorConfig, WareConfig};
+    use chrono::Duration;
+
+    #[test]
+    fn test_rate_limit() {
+        let ware = RateLimitWare::new(&RateLimitConfig {
+            max_messages: 2,
+            period_secs: 60,
+        })
+        .unwrap();
+        let now = Utc::now();
+
+        assert!(ware.admit("a", now).unwrap());
+        assert!(ware.admit("a", now).unwrap());
+        assert!(!ware.admit("a", now).unwrap());
+        assert!(ware.admit("b", now).unwrap());
+
+        // One message is regained every 30 seconds.
+        assert!(!ware.admit("a", now + Duration::seconds(29)).unwrap());
+        assert!(ware.admit("a", now + Duration::seconds(31)).unwrap());
+        assert!(!ware.admit("a", now + Duration::seconds(32)).unwrap());
+
+        // The least recently used bucket makes room for new publishers.
+        for i in 0..MAX_BUCKETS {
+            assert!(ware
+                .admit(&i.to_string(), now + Duration::seconds(40))
+                .unwrap());
+        }
+        assert_eq!(ware.buckets.lock().unwrap().len(), MAX_BUCKETS);
+        assert!(!ware.buckets.lock().unwrap().contains_key("b"));
+
+        assert!(RateLimitWare::new(&RateLimitConfig {
+            max_messages: 0,
+            period_secs: 60,
+        })
+        .is_err());
+
+        // Without a verified publisher, any name would get a fresh limit.
+        let config = PreProcessorConfig {
+            wares: vec![WareConfig::RateLimit(RateLimitConfig {
+                max_messages: 2,
+                period_secs: 60,
+            })],
+        };
+        assert!(PreProcessor::new(&config).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Ware renaming the reference values extracted from messages.
+//!
+//! Names are chosen by the extractors, so the ware attaches the rules
+//! applying to a message's type to the message, and the reference values
+//! extracted from it are renamed before they are stored.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use super::{Next, Ware};
+use crate::Message;
+
+/// Renames the reference values named `from` to `to`. A trailing `*` in
+/// `from` matches any suffix, which replaces a trailing `*` in `to`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct RenameRule {
+    pub from: String,
+    pub to: String,
+
+    /// Message types the rule applies to. It applies to all when empty.
+    #[serde(default)]
+    pub types: Vec<String>,
+}
+
+impl RenameRule {
+    /// The new name of `name`, if the rule matches it.
+    fn rename(&self, name: &str) -> Option<String> {
+        let Some(prefix) = self.from.strip_suffix('*') else {
+            return (name == self.from).then(|| self.to.clone());
+        };
+        let suffix = name.strip_prefix(prefix)?;
+        Some(match self.to.strip_suffix('*') {
+            Some(to) => format!("{to}{suffix}"),
+            None => self.to.clone(),
+        })
+    }
+}
+
+/// The new name of `name` given by the first of `rules` matching it.
+pub(crate) fn rename(rules: &[RenameRule], name: &str) -> Option<String> {
+    rules.iter().find_map(|rule| rule.rename(name))
+}
+
+pub struct RenameWare {
+    rules: Vec<RenameRule>,
+}
+
+impl RenameWare {
+    pub fn new(rules: Vec<RenameRule>) -> Self {
+        Self { rules }
+    }
+}
+
+impl Ware for RenameWare {
+    fn handle(
+        &self,
+        message: &mut Message,
+        context: &mut HashMap<String, String>,
+        next: Next<'_>,
+    ) -> Result<()> {
+        let rules = self
+            .rules
+            .iter()
+            .filter(|rule| rule.types.is_empty() || rule.types.contains(&message.r#type))
+            .cloned();
+        message.renames.extend(rules);
+        next.run(message, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(from: &str, to: &str) -> RenameRule {
+        RenameRule {
+            from: from.into(),
+            to: to.into(),
+            types: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_rename_rules() {
+        let rules = [
+            rule("measurement.rpm.kernel", "kernel"),
+            rule("measurement.rpm.*", "team-a.rpm.*"),
+            rule("legacy.*", "legacy"),
+        ];
+
+        assert_eq!(
+            rename(&rules, "measurement.rpm.kernel").as_deref(),
+            Some("kernel")
+        );
+        assert_eq!(
+            rename(&rules, "measurement.rpm.shim").as_deref(),
+            Some("team-a.rpm.shim")
+        );
+        assert_eq!(rename(&rules, "legacy.x").as_deref(), Some("legacy"));
+        assert_eq!(rename(&rules, "measurement.file.x"), None);
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Ware admitting only messages signed by an allow-listed publisher.
+//!
+//! A message names its publisher in `publisher` and carries in `signature`
+//! the base64 ECDSA signature (DER or fixed size) of the DSSE
+//! pre-authentication encoding of its `type` and `payload`, so that a signed
+//! payload cannot be replayed as another type. The signature must verify
+//! with the public key configured for the publisher.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+
+use super::{Next, SignatureConfig, Ware, PUBLISHER};
+use crate::crypto::{pae, PublicKey};
+use crate::Message;
+
+pub struct SignatureWare {
+    publishers: HashMap<String, PublicKey>,
+}
+
+impl SignatureWare {
+    pub fn new(config: &SignatureConfig) -> Result<Self> {
+        let publishers = config
+            .publishers
+            .iter()
+            .map(|(publisher, path)| {
+                let pem = std::fs::read_to_string(path)
+                    .with_context(|| format!("read public key {path} of publisher {publisher}"))?;
+                let key = PublicKey::from_pem(&pem)
+                    .with_context(|| format!("load public key {path} of publisher {publisher}"))?;
+                Ok((publisher.clone(), key))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { publishers })
+    }
+}
+
+impl Ware for SignatureWare {
+    fn handle(
+        &self,
+        message: &mut Message,
+        context: &mut HashMap<String, String>,
+        next: Next<'_>,
+    ) -> Result<()> {
+        let publisher = message
+            .publisher
+            .as_deref()
+            .ok_or_else(|| anyhow!("message names no publisher"))?;
+        let key = self
+            .publishers
+            .get(publisher)
+            .ok_or_else(|| anyhow!("publisher `{publisher}` is not allowed"))?;
+        let signature = message
+            .signature
+            .as_deref()
+            .ok_or_else(|| anyhow!("message of publisher `{publisher}` is not signed"))?;
+        let signature = base64::engine::general_purpose::STANDARD
+            .decode(signature)
+            .context("decode message signature")?;
+        key.verify(
+            &pae(&message.r#type, message.payload.as_bytes()),
+            &signature,
+        )
+        .with_context(|| format!("verify signature of publisher `{publisher}`"))?;
+
+        context.insert(PUBLISHER.to_string(), publisher.to_string());
+        next.run(message, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pre_processor::{PreProcessor, PreProcessorAPI};
+    use p256::ecdsa::{signature::Signer, SigningKey};
+
+    fn message(r#type: &str, publisher: Option<&str>, signature: Option<Vec<u8>>) -> Message {
+        Message {
+            version: "0.1.0".into(),
+            payload: "payload".into(),
+            r#type: r#type.into(),
+            publisher: publisher.map(Into::into),
+            signature: signature.map(|s| base64::engine::general_purpose::STANDARD.encode(s)),
+            renames: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_signature_ware() {
+        let key = SigningKey::from_slice(&[9u8; 32]).unwrap();
+        let other = SigningKey::from_slice(&[10u8; 32]).unwrap();
+        let mut pre_processor = PreProcessor::default();
+        pre_processor.add_ware(Box::new(SignatureWare {
+            publishers: HashMap::from([(
+                "vendor".to_string(),
+                PublicKey::P256(*key.verifying_key()),
+            )]),
+        }));
+
+        let sign = |key: &SigningKey| {
+            let signature: p256::ecdsa::Signature = key.sign(&pae("sample", b"payload"));
+            signature
+        };
+
+        let der = sign(&key).to_der().as_bytes().to_vec();
+        assert!(pre_processor
+            .process(&mut message("sample", Some("vendor"), Some(der.clone())))
+            .is_ok());
+        let fixed = sign(&key).to_bytes().to_vec();
+        assert!(pre_processor
+            .process(&mut message("sample", Some("vendor"), Some(fixed.clone())))
+            .is_ok());
+
+        let forged = sign(&other).to_bytes().to_vec();
+        assert!(pre_processor
+            .process(&mut message("sample", Some("vendor"), Some(forged)))
+            .is_err());
+        assert!(pre_processor
+            .process(&mut message("sample", Some("stranger"), Some(fixed)))
+            .is_err());
+        assert!(pre_processor
+            .process(&mut message("sample", Some("vendor"), None))
+            .is_err());
+        assert!(pre_processor
+            .process(&mut message("slsa", Some("vendor"), Some(der)))
+            .is_err());
+        assert!(pre_processor
+            .process(&mut message("sample", None, None))
+            .is_err());
+    }
+}
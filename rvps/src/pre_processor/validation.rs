@@ -0,0 +1,122 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Ware checking the size, type and fields of messages.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use serde_json::Value;
+
+use super::{Next, ValidationConfig, Ware};
+use crate::Message;
+
+pub struct ValidationWare {
+    config: ValidationConfig,
+}
+
+impl ValidationWare {
+    pub fn new(config: ValidationConfig) -> Result<Self> {
+        for pointer in config.required_fields.values().flatten() {
+            if !pointer.starts_with('/') {
+                bail!("required field `{pointer}` is not a JSON pointer");
+            }
+        }
+        Ok(Self { config })
+    }
+
+    fn validate(&self, message: &Message) -> Result<()> {
+        if let Some(max) = self.config.max_payload_bytes {
+            if message.payload.len() > max {
+                bail!(
+                    "payload of {} bytes exceeds the limit of {max} bytes",
+                    message.payload.len()
+                );
+            }
+        }
+
+        if !self.config.types.is_empty() && !self.config.types.contains(&message.r#type) {
+            bail!("message type `{}` is not accepted", message.r#type);
+        }
+
+        if let Some(pointers) = self.config.required_fields.get(&message.r#type) {
+            let payload = json_payload(&message.payload)
+                .with_context(|| format!("parse payload of type `{}`", message.r#type))?;
+            if let Some(missing) = pointers.iter().find(|p| payload.pointer(p).is_none()) {
+                bail!(
+                    "payload of type `{}` lacks required field `{missing}`",
+                    message.r#type
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse a payload that is JSON, or base64-encoded JSON.
+fn json_payload(payload: &str) -> Result<Value> {
+    if let Ok(value) = serde_json::from_str(payload) {
+        return Ok(value);
+    }
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(payload)
+        .map_err(|_| anyhow!("payload is neither JSON nor base64"))?;
+    serde_json::from_slice(&decoded).context("payload is not JSON")
+}
+
+impl Ware for ValidationWare {
+    fn handle(
+        &self,
+        message: &mut Message,
+        context: &mut HashMap<String, String>,
+        next: Next<'_>,
+    ) -> Result<()> {
+        self.validate(message)?;
+        next.run(message, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(r#type: &str, payload: &str) -> Message {
+        serde_json::from_value(json!({"type": r#type, "payload": payload})).unwrap()
+    }
+
+    #[test]
+    fn test_validation_ware() {
+        let ware = ValidationWare::new(ValidationConfig {
+            max_payload_bytes: Some(64),
+            types: vec!["sample".into(), "slsa".into()],
+            required_fields: HashMap::from([(
+                "sample".to_string(),
+                vec!["/svn".to_string(), "/debug/enabled".to_string()],
+            )]),
+        })
+        .unwrap();
+
+        let complete = json!({"svn": 1, "debug": {"enabled": false}}).to_string();
+        assert!(ware.validate(&message("sample", &complete)).is_ok());
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&complete);
+        assert!(ware.validate(&message("sample", &encoded)).is_ok());
+        assert!(ware.validate(&message("slsa", "anything")).is_ok());
+
+        let partial = json!({"svn": 1}).to_string();
+        assert!(ware.validate(&message("sample", &partial)).is_err());
+        assert!(ware.validate(&message("sample", "not json")).is_err());
+        assert!(ware.validate(&message("corim", "")).is_err());
+        assert!(ware.validate(&message("slsa", &"a".repeat(65))).is_err());
+
+        assert!(ValidationWare::new(ValidationConfig {
+            required_fields: HashMap::from([("sample".to_string(), vec!["svn".to_string()])]),
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Fetcher of provenance material in local files, e.g. in a directory
+//! mounted into the RVPS pod.
+//!
+//! `file://<path>` names a file, or a directory holding the material, in the
+//! configured `file_root`, which relative paths are resolved against. In a
+//! directory, `artifact` names the file to read, or selects it like in an
+//! OCI manifest: a bundle (`*bundle*`, `*.sigstore.json`, `*.jsonl`) for
+//! `bundle`, the default, or an in-toto statement (`*intoto*`, `*in-toto*`,
+//! `*provenance*`, `*dsse*`) for `provenance`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+
+use super::{
+    FetchedProvenanceMaterial, ProvenanceFetcher, ProvenanceSource, ProvenanceSourceConfig,
+};
+
+pub struct FileProvenanceFetcher {
+    /// Canonical path of the directory the material must lie in.
+    root: PathBuf,
+}
+
+impl FileProvenanceFetcher {
+    pub fn new(config: &ProvenanceSourceConfig) -> Result<Self> {
+        let root = config.file_root.as_ref().ok_or_else(|| {
+            anyhow!("`file` provenance sources need a provenance_source.file_root")
+        })?;
+        let root = std::fs::canonicalize(root)
+            .with_context(|| format!("resolve provenance file_root `{root}`"))?;
+        Ok(Self { root })
+    }
+
+    /// The canonical path of `path`, checked to lie in the root, so that
+    /// neither `..` nor symlinks lead out of it.
+    async fn confine(&self, path: &Path) -> Result<PathBuf> {
+        let resolved = tokio::fs::canonicalize(path)
+            .await
+            .with_context(|| format!("resolve provenance material `{}`", path.display()))?;
+        if !resolved.starts_with(&self.root) {
+            bail!(
+                "provenance material `{}` is outside of file_root `{}`",
+                path.display(),
+                self.root.display()
+            );
+        }
+        Ok(resolved)
+    }
+}
+
+#[async_trait]
+impl ProvenanceFetcher for FileProvenanceFetcher {
+    async fn fetch(&self, source: &ProvenanceSource) -> Result<FetchedProvenanceMaterial> {
+        let requested = Path::new(source.uri.strip_prefix("file://").unwrap_or(&source.uri));
+        let path = self.confine(&self.root.join(requested)).await?;
+        let metadata = tokio::fs::metadata(&path)
+            .await
+            .with_context(|| format!("stat provenance material `{}`", path.display()))?;
+        let path = if metadata.is_dir() {
+            self.confine(&select_file(&path, source.artifact.as_deref()).await?)
+                .await?
+        } else {
+            path
+        };
+
+        let raw_bytes = tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("read provenance material from file `{}`", path.display()))?;
+        Ok(FetchedProvenanceMaterial {
+            media_type: source.artifact.clone(),
+            raw_bytes,
+        })
+    }
+}
+
+/// The file of `dir` holding `artifact`.
+async fn select_file(dir: &Path, artifact: Option<&str>) -> Result<PathBuf> {
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .with_context(|| format!("read provenance directory `{}`", dir.display()))?;
+    let mut names = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        // Follow symlinks, which is how mounted ConfigMaps and Secrets
+        // expose their files.
+        if tokio::fs::metadata(entry.path()).await?.is_file() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+
+    let artifact = artifact.unwrap_or("bundle");
+    let name = select_name(&names, artifact).ok_or_else(|| {
+        anyhow!(
+            "no `{artifact}` file in provenance directory `{}`",
+            dir.display()
+        )
+    })?;
+    Ok(dir.join(name))
+}
+
+fn select_name<'a>(names: &'a [String], artifact: &str) -> Option<&'a String> {
+    if let Some(name) = names.iter().find(|name| *name == artifact) {
+        return Some(name);
+    }
+
+    let patterns: &[&str] = match artifact.to_ascii_lowercase().as_str() {
+        "bundle" => &["bundle", ".sigstore.json", ".jsonl"],
+        "provenance" => &["intoto", "in-toto", "provenance", "dsse"],
+        _ => return None,
+    };
+    patterns.iter().find_map(|pattern| {
+        names
+            .iter()
+            .find(|name| name.to_ascii_lowercase().contains(pattern))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(uri: &Path, artifact: Option<&str>) -> ProvenanceSource {
+        ProvenanceSource {
+            protocol: "file".into(),
+            uri: format!("file://{}", uri.display()),
+            artifact: artifact.map(Into::into),
+            digest: None,
+        }
+    }
+
+    async fn fetch(root: &Path, uri: &Path, artifact: Option<&str>) -> Result<String> {
+        let config = ProvenanceSourceConfig {
+            file_root: Some(root.to_string_lossy().into_owned()),
+        };
+        let material = FileProvenanceFetcher::new(&config)?
+            .fetch(&source(uri, artifact))
+            .await?;
+        Ok(String::from_utf8(material.raw_bytes)?)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content) in [
+            ("README.md", "readme"),
+            ("app.intoto.json", "statement"),
+            ("release.sigstore.json", "bundle"),
+        ] {
+            std::fs::write(dir.path().join(name), content).unwrap();
+        }
+
+        let root = dir.path();
+        assert_eq!(fetch(root, root, None).await.unwrap(), "bundle");
+        assert_eq!(
+            fetch(root, root, Some("provenance")).await.unwrap(),
+            "statement"
+        );
+        assert_eq!(
+            fetch(root, root, Some("README.md")).await.unwrap(),
+            "readme"
+        );
+        assert!(fetch(root, root, Some("signature")).await.is_err());
+
+        let file = root.join("app.intoto.json");
+        assert_eq!(
+            fetch(root, &file, Some("bundle")).await.unwrap(),
+            "statement"
+        );
+        assert_eq!(
+            fetch(root, Path::new("app.intoto.json"), None)
+                .await
+                .unwrap(),
+            "statement"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_confined_to_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(dir.path().join("secret.json"), "secret").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("secret.json"), root.join("link.json")).unwrap();
+
+        assert!(fetch(&root, &dir.path().join("secret.json"), None)
+            .await
+            .is_err());
+        assert!(fetch(&root, Path::new("../secret.json"), None)
+            .await
+            .is_err());
+        assert!(fetch(&root, Path::new("link.json"), None).await.is_err());
+        // Directories select through symlinks too.
+        assert!(fetch(&root, &root, Some("link.json")).await.is_err());
+
+        let config = ProvenanceSourceConfig::default();
+        assert!(FileProvenanceFetcher::new(&config).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Fetcher of provenance material served over HTTPS, e.g. by an artifact
+//! store. Unlike OCI digests, HTTPS URLs are not content addressed, so the
+//! material must be pinned by the `digest` of its source.
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+
+use super::{
+    http_client, read_body, FetchedProvenanceMaterial, ProvenanceFetcher, ProvenanceSource,
+};
+
+pub struct HttpsProvenanceFetcher {
+    http: Client,
+}
+
+impl HttpsProvenanceFetcher {
+    pub fn new() -> Self {
+        Self {
+            http: http_client(),
+        }
+    }
+}
+
+#[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait(?Send))]
+#[cfg_attr(
+    not(all(
+        target_arch = "wasm32",
+        target_vendor = "unknown",
+        target_os = "unknown"
+    )),
+    async_trait
+)]
+impl ProvenanceFetcher for HttpsProvenanceFetcher {
+    async fn fetch(&self, source: &ProvenanceSource) -> Result<FetchedProvenanceMaterial> {
+        let url = &source.uri;
+        if !url.starts_with("https://") {
+            bail!("unsupported HTTPS URI `{url}`; expected prefix https://");
+        }
+        if source.digest.is_none() {
+            bail!("provenance material {url} must be pinned by a digest");
+        }
+
+        let resp = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .with_context(|| format!("GET {url}"))?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            bail!("request {url} failed with {status}: {text}");
+        }
+
+        let media_type = resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+            .or(source.artifact.clone());
+        let raw_bytes = read_body(resp).await?;
+
+        Ok(FetchedProvenanceMaterial {
+            media_type,
+            raw_bytes,
+        })
+    }
+}
@@ -4,7 +4,63 @@ use log::debug;
 use reqwest::header::{ACCEPT, AUTHORIZATION, WWW_AUTHENTICATE};
 use reqwest::{Client, Response, StatusCode};
 use serde::Deserialize;
+use sha2::{Digest, Sha256, Sha384, Sha512};
 use std::collections::HashMap;
+#[cfg(not(all(
+    target_arch = "wasm32",
+    target_vendor = "unknown",
+    target_os = "unknown"
+)))]
+use std::time::Duration;
+
+#[cfg(feature = "fs")]
+mod file;
+mod https;
+mod referrers;
+
+#[cfg(feature = "fs")]
+pub use file::FileProvenanceFetcher;
+pub use https::HttpsProvenanceFetcher;
+pub use referrers::OciReferrersProvenanceFetcher;
+
+const OCI_MANIFEST_ACCEPT: &str = "application/vnd.oci.image.manifest.v1+json,application/vnd.oci.image.index.v1+json,application/vnd.docker.distribution.manifest.v2+json";
+
+/// Largest response body the fetchers read. Material is fetched while RVPS
+/// holds the lock on its reference values, so neither its size nor the time
+/// taken to fetch it may be left to the server.
+const MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+#[cfg(not(all(
+    target_arch = "wasm32",
+    target_vendor = "unknown",
+    target_os = "unknown"
+)))]
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest wait for the next bytes of a response.
+#[cfg(not(all(
+    target_arch = "wasm32",
+    target_vendor = "unknown",
+    target_os = "unknown"
+)))]
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest time a request may take, body included.
+#[cfg(not(all(
+    target_arch = "wasm32",
+    target_vendor = "unknown",
+    target_os = "unknown"
+)))]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Where provenance material may be fetched from.
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct ProvenanceSourceConfig {
+    /// Directory `file` sources must lie in. Relative paths are resolved
+    /// against it. `file` sources are refused when absent.
+    #[serde(default)]
+    pub file_root: Option<String>,
+}
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct ProvenanceSource {
@@ -12,6 +68,9 @@ pub struct ProvenanceSource {
     pub uri: String,
     #[serde(default)]
     pub artifact: Option<String>,
+    /// `<algorithm>:<hex>` digest the fetched material must have.
+    #[serde(default)]
+    pub digest: Option<String>,
 }
 
 pub struct FetchedProvenanceMaterial {
@@ -32,6 +91,110 @@ pub trait ProvenanceFetcher {
     async fn fetch(&self, source: &ProvenanceSource) -> Result<FetchedProvenanceMaterial>;
 }
 
+/// Fetch the material of `source` with the fetcher of its protocol, and
+/// check it against the digest `source` pins, if any.
+#[cfg_attr(not(feature = "fs"), allow(unused_variables))]
+pub async fn fetch(
+    config: &ProvenanceSourceConfig,
+    source: &ProvenanceSource,
+) -> Result<FetchedProvenanceMaterial> {
+    let material = match source.protocol.to_ascii_lowercase().as_str() {
+        "oci" => OciProvenanceFetcher::new().fetch(source).await?,
+        "oci-referrers" => OciReferrersProvenanceFetcher::new().fetch(source).await?,
+        "https" => HttpsProvenanceFetcher::new().fetch(source).await?,
+        #[cfg(feature = "fs")]
+        "file" => FileProvenanceFetcher::new(config)?.fetch(source).await?,
+        other => bail!("unsupported provenance_source.protocol `{other}`"),
+    };
+
+    if let Some(pinned) = &source.digest {
+        verify_digest(&material.raw_bytes, pinned)?;
+    }
+    Ok(material)
+}
+
+fn verify_digest(raw_bytes: &[u8], pinned: &str) -> Result<()> {
+    let (algorithm, expected) = pinned
+        .split_once(':')
+        .ok_or_else(|| anyhow!("digest `{pinned}` is not of the form <algorithm>:<hex>"))?;
+    let actual = match algorithm.to_ascii_lowercase().as_str() {
+        "sha256" => hex::encode(Sha256::digest(raw_bytes)),
+        "sha384" => hex::encode(Sha384::digest(raw_bytes)),
+        "sha512" => hex::encode(Sha512::digest(raw_bytes)),
+        other => bail!("unsupported digest algorithm `{other}`"),
+    };
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!("provenance material has digest {algorithm}:{actual}, expected {pinned}");
+    }
+    Ok(())
+}
+
+/// The HTTP client of the fetchers, with bounded connect and read times.
+pub(crate) fn http_client() -> Client {
+    #[cfg(not(all(
+        target_arch = "wasm32",
+        target_vendor = "unknown",
+        target_os = "unknown"
+    )))]
+    let builder = Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT);
+    #[cfg(all(
+        target_arch = "wasm32",
+        target_vendor = "unknown",
+        target_os = "unknown"
+    ))]
+    let builder = Client::builder();
+    builder.build().expect("build provenance HTTP client")
+}
+
+/// The body of `resp`, refused if larger than [`MAX_BODY_BYTES`].
+pub(crate) async fn read_body(mut resp: Response) -> Result<Vec<u8>> {
+    let url = resp.url().to_string();
+    if resp
+        .content_length()
+        .is_some_and(|len| len > MAX_BODY_BYTES as u64)
+    {
+        bail!("response of {url} exceeds {MAX_BODY_BYTES} bytes");
+    }
+
+    #[cfg(not(all(
+        target_arch = "wasm32",
+        target_vendor = "unknown",
+        target_os = "unknown"
+    )))]
+    {
+        let mut body = Vec::new();
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .with_context(|| format!("read body of {url}"))?
+        {
+            if body.len() + chunk.len() > MAX_BODY_BYTES {
+                bail!("response of {url} exceeds {MAX_BODY_BYTES} bytes");
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+    #[cfg(all(
+        target_arch = "wasm32",
+        target_vendor = "unknown",
+        target_os = "unknown"
+    ))]
+    {
+        let body = resp
+            .bytes()
+            .await
+            .with_context(|| format!("read body of {url}"))?;
+        if body.len() > MAX_BODY_BYTES {
+            bail!("response of {url} exceeds {MAX_BODY_BYTES} bytes");
+        }
+        Ok(body.to_vec())
+    }
+}
+
 pub struct OciProvenanceFetcher {
     http: Client,
 }
@@ -39,7 +202,7 @@ pub struct OciProvenanceFetcher {
 impl OciProvenanceFetcher {
     pub fn new() -> Self {
         Self {
-            http: Client::new(),
+            http: http_client(),
         }
     }
 }
@@ -64,6 +227,8 @@ struct OciManifest {
 struct OciDescriptor {
     #[serde(rename = "mediaType")]
     media_type: Option<String>,
+    #[serde(rename = "artifactType")]
+    artifact_type: Option<String>,
     digest: String,
 }
 
@@ -85,45 +250,71 @@ struct BearerTokenResponse {
 impl ProvenanceFetcher for OciProvenanceFetcher {
     async fn fetch(&self, source: &ProvenanceSource) -> Result<FetchedProvenanceMaterial> {
         let reference = parse_oci_reference(&source.uri)?;
+        let mut auth_header = None;
+        let (manifest, _) = self
+            .fetch_manifest(&reference, &reference.reference, &mut auth_header)
+            .await?;
+
+        let descriptor = select_provenance_descriptor(&manifest, source.artifact.as_deref())
+            .with_context(|| {
+                format!(
+                    "select provenance descriptor from OCI manifest {}",
+                    source.uri
+                )
+            })?;
+
+        self.fetch_blob(&reference, &descriptor, &mut auth_header)
+            .await
+    }
+}
+
+impl OciProvenanceFetcher {
+    /// Fetch the manifest `tag_or_digest` of the repository of `oci`, and
+    /// its digest.
+    async fn fetch_manifest(
+        &self,
+        oci: &OciReference,
+        tag_or_digest: &str,
+        auth_header: &mut Option<String>,
+    ) -> Result<(OciManifest, String)> {
         let manifest_url = format!(
             "{}://{}/v2/{}/manifests/{}",
-            reference.scheme, reference.registry, reference.repository, reference.reference
+            oci.scheme, oci.registry, oci.repository, tag_or_digest
         );
-
-        let mut auth_header = None;
         let manifest_resp = self
-            .get_with_bearer_retry(
-                &manifest_url,
-                Some(
-                    "application/vnd.oci.image.manifest.v1+json,application/vnd.oci.image.index.v1+json,application/vnd.docker.distribution.manifest.v2+json",
-                ),
-                &mut auth_header,
-            )
+            .get_with_bearer_retry(&manifest_url, Some(OCI_MANIFEST_ACCEPT), auth_header)
             .await
             .context("fetch OCI manifest")?;
 
-        let manifest_text = manifest_resp
-            .text()
+        let digest = manifest_resp
+            .headers()
+            .get("Docker-Content-Digest")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let manifest_bytes = read_body(manifest_resp)
             .await
             .context("read OCI manifest body")?;
+        let digest = digest
+            .unwrap_or_else(|| format!("sha256:{}", hex::encode(Sha256::digest(&manifest_bytes))));
         let manifest: OciManifest =
-            serde_json::from_str(&manifest_text).context("parse OCI manifest JSON")?;
+            serde_json::from_slice(&manifest_bytes).context("parse OCI manifest JSON")?;
         debug!("OCI manifest media type: {:?}", manifest.media_type);
 
-        let descriptor = select_provenance_descriptor(&manifest, source.artifact.as_deref())
-            .with_context(|| {
-                format!(
-                    "select provenance descriptor from OCI manifest {}",
-                    source.uri
-                )
-            })?;
+        Ok((manifest, digest))
+    }
 
+    async fn fetch_blob(
+        &self,
+        oci: &OciReference,
+        descriptor: &OciDescriptor,
+        auth_header: &mut Option<String>,
+    ) -> Result<FetchedProvenanceMaterial> {
         let blob_url = format!(
             "{}://{}/v2/{}/blobs/{}",
-            reference.scheme, reference.registry, reference.repository, descriptor.digest
+            oci.scheme, oci.registry, oci.repository, descriptor.digest
         );
         let blob_resp = self
-            .get_with_bearer_retry(&blob_url, None, &mut auth_header)
+            .get_with_bearer_retry(&blob_url, None, auth_header)
             .await
             .context("fetch OCI blob")?;
 
@@ -133,26 +324,32 @@ impl ProvenanceFetcher for OciProvenanceFetcher {
             .and_then(|v| v.to_str().ok())
             .map(|v| v.to_string())
             .or(descriptor.media_type.clone());
-        let raw_bytes = blob_resp
-            .bytes()
-            .await
-            .context("read OCI blob bytes")?
-            .to_vec();
+        let raw_bytes = read_body(blob_resp).await.context("read OCI blob bytes")?;
 
         Ok(FetchedProvenanceMaterial {
             media_type: blob_media_type,
             raw_bytes,
         })
     }
-}
 
-impl OciProvenanceFetcher {
     async fn get_with_bearer_retry(
         &self,
         url: &str,
         accept: Option<&str>,
         auth_header: &mut Option<String>,
     ) -> Result<Response> {
+        self.try_get_with_bearer_retry(url, accept, auth_header)
+            .await?
+            .ok_or_else(|| anyhow!("request {url} failed with {}", StatusCode::NOT_FOUND))
+    }
+
+    /// Like `get_with_bearer_retry`, but `None` if `url` is not found.
+    async fn try_get_with_bearer_retry(
+        &self,
+        url: &str,
+        accept: Option<&str>,
+        auth_header: &mut Option<String>,
+    ) -> Result<Option<Response>> {
         let mut req = self.http.get(url);
         if let Some(accept_val) = accept {
             req = req.header(ACCEPT, accept_val);
@@ -163,12 +360,15 @@ impl OciProvenanceFetcher {
         let resp = req.send().await.with_context(|| format!("GET {url}"))?;
 
         if resp.status() != StatusCode::UNAUTHORIZED {
+            if resp.status() == StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
             if !resp.status().is_success() {
                 let status = resp.status();
                 let text = resp.text().await.unwrap_or_default();
                 bail!("request {url} failed with {status}: {text}");
             }
-            return Ok(resp);
+            return Ok(Some(resp));
         }
 
         let challenge = resp
@@ -192,12 +392,15 @@ impl OciProvenanceFetcher {
             .send()
             .await
             .with_context(|| format!("retry GET {url}"))?;
+        if retry_resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
         if !retry_resp.status().is_success() {
             let status = retry_resp.status();
             let text = retry_resp.text().await.unwrap_or_default();
             bail!("request {url} failed after auth with {status}: {text}");
         }
-        Ok(retry_resp)
+        Ok(Some(retry_resp))
     }
 
     async fn fetch_bearer_token(&self, challenge: &str) -> Result<String> {
@@ -229,7 +432,9 @@ impl OciProvenanceFetcher {
             bail!("bearer token request failed with {status}: {text}");
         }
 
-        let body: BearerTokenResponse = resp.json().await.context("parse bearer token JSON")?;
+        let body = read_body(resp).await.context("read bearer token")?;
+        let body: BearerTokenResponse =
+            serde_json::from_slice(&body).context("parse bearer token JSON")?;
         body.token
             .or(body.access_token)
             .ok_or_else(|| anyhow!("token endpoint response missing token/access_token"))
@@ -399,4 +604,14 @@ mod tests {
         assert_eq!(m.get("realm").unwrap(), "https://auth.example/token");
         assert_eq!(m.get("service").unwrap(), "registry.example");
     }
+
+    #[test]
+    fn test_verify_digest() {
+        let sha256 = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert!(verify_digest(b"hello", sha256).is_ok());
+        assert!(verify_digest(b"hello", &sha256.to_uppercase().replace("SHA", "sha")).is_ok());
+        assert!(verify_digest(b"hello!", sha256).is_err());
+        assert!(verify_digest(b"hello", "md5:5d41402abc4b2a76b9719d911017c592").is_err());
+        assert!(verify_digest(b"hello", "2cf24dba").is_err());
+    }
 }
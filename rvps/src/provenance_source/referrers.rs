@@ -0,0 +1,197 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Fetcher of the attestations attached to an OCI image.
+//!
+//! The attestations of `oci://<registry>/<repo>:<tag>` (or `@<digest>`) are
+//! looked up with the OCI referrers API. On registries without it, or when
+//! no referrer matches, they are read from the tags cosign attaches them
+//! with: `sha256-<hex>.att`, or `sha256-<hex>.sig` for `artifact: signature`.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use log::debug;
+
+use super::{
+    parse_oci_reference, read_body, select_provenance_descriptor, FetchedProvenanceMaterial,
+    OciDescriptor, OciManifest, OciProvenanceFetcher, OciReference, ProvenanceFetcher,
+    ProvenanceSource,
+};
+
+const OCI_INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+
+pub struct OciReferrersProvenanceFetcher {
+    oci: OciProvenanceFetcher,
+}
+
+impl OciReferrersProvenanceFetcher {
+    pub fn new() -> Self {
+        Self {
+            oci: OciProvenanceFetcher::new(),
+        }
+    }
+
+    /// The manifest of the referrer of the image `digest` matching
+    /// `artifact`, if the registry serves the referrers API and has one.
+    async fn referrer_manifest(
+        &self,
+        image: &OciReference,
+        digest: &str,
+        artifact: Option<&str>,
+        auth_header: &mut Option<String>,
+    ) -> Result<Option<OciManifest>> {
+        let referrers_url = format!(
+            "{}://{}/v2/{}/referrers/{}",
+            image.scheme, image.registry, image.repository, digest
+        );
+        let Some(resp) = self
+            .oci
+            .try_get_with_bearer_retry(&referrers_url, Some(OCI_INDEX_MEDIA_TYPE), auth_header)
+            .await
+            .context("fetch OCI referrers")?
+        else {
+            debug!("OCI registry {} has no referrers API", image.registry);
+            return Ok(None);
+        };
+
+        let index = read_body(resp).await.context("read OCI referrers index")?;
+        let index: OciManifest =
+            serde_json::from_slice(&index).context("parse OCI referrers index")?;
+        let referrers = index.manifests.unwrap_or_default();
+        let Some(referrer) = select_referrer(&referrers, artifact) else {
+            debug!("No referrer of {digest} matches artifact {artifact:?}");
+            return Ok(None);
+        };
+
+        let (manifest, _) = self
+            .oci
+            .fetch_manifest(image, &referrer.digest, auth_header)
+            .await?;
+        Ok(Some(manifest))
+    }
+}
+
+/// The referrer whose artifact type best matches `artifact`: a bundle by
+/// default, an in-toto statement for `provenance` or a signature for
+/// `signature`.
+fn select_referrer<'a>(
+    referrers: &'a [OciDescriptor],
+    artifact: Option<&str>,
+) -> Option<&'a OciDescriptor> {
+    let patterns: &[&str] = match artifact.map(str::to_ascii_lowercase).as_deref() {
+        Some("provenance") => &["in-toto", "dsse.envelope", "provenance"],
+        Some("signature") => &["sigstore.bundle", "cosign", "signature"],
+        _ => &[
+            "sigstore.bundle",
+            "in-toto.bundle",
+            "in-toto",
+            "dsse.envelope",
+        ],
+    };
+    patterns.iter().find_map(|pattern| {
+        referrers.iter().find(|referrer| {
+            referrer
+                .artifact_type
+                .as_deref()
+                .or(referrer.media_type.as_deref())
+                .is_some_and(|t| t.to_ascii_lowercase().contains(pattern))
+        })
+    })
+}
+
+/// The tag cosign attaches the attestations, or the signatures, of the
+/// image `digest` with.
+fn cosign_tag(digest: &str, artifact: Option<&str>) -> String {
+    let suffix = match artifact {
+        Some(a) if a.eq_ignore_ascii_case("signature") => "sig",
+        _ => "att",
+    };
+    format!("{}.{suffix}", digest.replace(':', "-"))
+}
+
+#[cfg_attr(all(target_arch = "wasm32", target_vendor = "unknown", target_os = "unknown"), async_trait(?Send))]
+#[cfg_attr(
+    not(all(
+        target_arch = "wasm32",
+        target_vendor = "unknown",
+        target_os = "unknown"
+    )),
+    async_trait
+)]
+impl ProvenanceFetcher for OciReferrersProvenanceFetcher {
+    async fn fetch(&self, source: &ProvenanceSource) -> Result<FetchedProvenanceMaterial> {
+        let image = parse_oci_reference(&source.uri)?;
+        let artifact = source.artifact.as_deref();
+        let mut auth_header = None;
+
+        // Tags cannot contain `:`, digests always do.
+        let digest = if image.reference.contains(':') {
+            image.reference.clone()
+        } else {
+            let (_, digest) = self
+                .oci
+                .fetch_manifest(&image, &image.reference, &mut auth_header)
+                .await
+                .context("resolve OCI image digest")?;
+            digest
+        };
+
+        let manifest = match self
+            .referrer_manifest(&image, &digest, artifact, &mut auth_header)
+            .await?
+        {
+            Some(manifest) => manifest,
+            None => {
+                let tag = cosign_tag(&digest, artifact);
+                debug!("Fetch attestations of {} from tag {tag}", source.uri);
+                let (manifest, _) = self
+                    .oci
+                    .fetch_manifest(&image, &tag, &mut auth_header)
+                    .await
+                    .with_context(|| format!("fetch cosign tag {tag}"))?;
+                manifest
+            }
+        };
+
+        let descriptor = select_provenance_descriptor(&manifest, artifact).with_context(|| {
+            format!("select attestation descriptor of OCI image {}", source.uri)
+        })?;
+        self.oci
+            .fetch_blob(&image, &descriptor, &mut auth_header)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn descriptor(artifact_type: &str, digest: &str) -> OciDescriptor {
+        OciDescriptor {
+            media_type: Some("application/vnd.oci.image.manifest.v1+json".into()),
+            artifact_type: Some(artifact_type.into()),
+            digest: digest.into(),
+        }
+    }
+
+    #[test]
+    fn test_select_referrer_and_cosign_tag() {
+        let referrers = [
+            descriptor("application/vnd.example.sbom+json", "sha256:1"),
+            descriptor("application/vnd.in-toto+json", "sha256:2"),
+            descriptor("application/vnd.dev.sigstore.bundle.v0.3+json", "sha256:3"),
+        ];
+        let selected = |artifact| select_referrer(&referrers, artifact).map(|r| r.digest.as_str());
+        assert_eq!(selected(None), Some("sha256:3"));
+        assert_eq!(selected(Some("provenance")), Some("sha256:2"));
+        assert!(select_referrer(&referrers[..1], None).is_none());
+
+        assert_eq!(cosign_tag("sha256:abcd", None), "sha256-abcd.att");
+        assert_eq!(
+            cosign_tag("sha256:abcd", Some("Signature")),
+            "sha256-abcd.sig"
+        );
+    }
+}
@@ -10,6 +10,8 @@ use chrono::{DateTime, NaiveDateTime, Timelike, Utc};
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
+use crate::namespace::DEFAULT_NAMESPACE;
+
 /// Default version of ReferenceValue
 pub const REFERENCE_VALUE_VERSION: &str = "0.1.0";
 
@@ -101,6 +103,12 @@ pub struct ReferenceValue {
     #[serde(default = "default_version")]
     pub version: String,
     pub name: String,
+    /// The namespace of the reference value. Omitted for
+    /// [`DEFAULT_NAMESPACE`], which records from before namespaces existed
+    /// belong to.
+    #[serde(default = "default_namespace")]
+    #[serde(skip_serializing_if = "is_default_namespace")]
+    pub namespace: String,
     #[serde(deserialize_with = "primitive_date_time_from_str")]
     pub expiration: DateTime<Utc>,
     #[serde(rename = "hash-value")]
@@ -135,6 +143,14 @@ fn default_version() -> String {
     REFERENCE_VALUE_VERSION.into()
 }
 
+fn default_namespace() -> String {
+    DEFAULT_NAMESPACE.into()
+}
+
+fn is_default_namespace(namespace: &String) -> bool {
+    namespace == DEFAULT_NAMESPACE
+}
+
 impl ReferenceValue {
     /// Create a new `ReferenceValue`, the `expiration`
     /// field's nanosecond will be set to 0. This avoid
@@ -144,6 +160,7 @@ impl ReferenceValue {
         Ok(ReferenceValue {
             version: REFERENCE_VALUE_VERSION.into(),
             name: String::new(),
+            namespace: default_namespace(),
             expiration: Utc::now()
                 .with_nanosecond(0)
                 .ok_or_else(|| anyhow!("set nanosecond failed."))?,
@@ -244,6 +261,23 @@ impl ReferenceValue {
     pub fn name(&self) -> &String {
         &self.name
     }
+
+    /// Set the namespace of the ReferenceValue, [`DEFAULT_NAMESPACE`] if
+    /// empty.
+    pub fn set_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = crate::namespace::normalize(namespace).into();
+        self
+    }
+
+    /// Get the namespace of the ReferenceValue.
+    pub fn namespace(&self) -> &String {
+        &self.namespace
+    }
+
+    /// The key the ReferenceValue is stored under.
+    pub fn storage_key(&self) -> String {
+        crate::namespace::storage_key(&self.namespace, &self.name)
+    }
 }
 
 /// Trusted Digest is what RVPS actually delivered to
@@ -33,10 +33,14 @@ pub struct ReferenceValueProvenanceInfo {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ReferenceValueProvenanceSource {
+    /// `oci`, `oci-referrers`, `https` or `file`.
     pub protocol: String,
     pub uri: String,
     #[serde(default)]
     pub artifact: Option<String>,
+    /// `<algorithm>:<hex>` digest pinning the fetched material.
+    #[serde(default)]
+    pub digest: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -0,0 +1,95 @@
+//! Integration with RVDS (Reference Value Distribution Service).
+//!
+//! RVDS pushes publish events to subscribed trustees and keeps an event log
+//! that can be pulled with `GET /rvds/events?since=<seq>`. This module holds
+//! the shapes shared by both paths and, with the `bin` feature, the client
+//! that periodically pulls missed events into RVPS.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "bin")]
+mod sync;
+#[cfg(feature = "bin")]
+pub use sync::RvdsSyncClient;
+
+/// Default local file recording the last applied RVDS event sequence number.
+const CURSOR_PATH: &str = "/opt/confidential-containers/attestation-service/rvds_sync_cursor";
+
+fn default_interval_secs() -> u64 {
+    60
+}
+
+fn default_cursor_path() -> String {
+    CURSOR_PATH.to_string()
+}
+
+/// Configuration of the RVDS catch-up sync client.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct RvdsSyncConfig {
+    /// Base URL of RVDS, e.g. `http://rvds:8090`.
+    pub endpoint: String,
+
+    /// Bearer token presented to the RVDS event API.
+    #[serde(default)]
+    pub token: Option<String>,
+
+    /// Interval between two pulls, in seconds.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+
+    /// Only pull events of these artifact types. Empty means all types.
+    #[serde(default)]
+    pub artifact_types: Vec<String>,
+
+    /// File recording the last applied event sequence number.
+    #[serde(default = "default_cursor_path")]
+    pub cursor_path: String,
+
+    /// Namespace the pulled reference values are registered to, the default
+    /// namespace if empty.
+    #[serde(default)]
+    pub namespace: String,
+}
+
+/// One entry of the RVDS event log.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RvdsEvent {
+    pub seq: u64,
+    pub artifact_type: String,
+    /// Hex SHA-256 of the canonical payload, see [`canonical_payload_hash`].
+    pub payload_hash: String,
+    /// RVPS message envelope as forwarded by RVDS.
+    pub message: String,
+}
+
+/// A page of the RVDS event log.
+#[derive(Deserialize, Debug)]
+pub struct RvdsEventsPage {
+    pub events: Vec<RvdsEvent>,
+    pub latest_seq: u64,
+}
+
+/// The fields of an RVDS publish event covered by its hashes, in the order
+/// RVDS serializes them. The `audit_proof` attached by RVDS is not part of it.
+#[derive(Deserialize, Serialize)]
+struct CanonicalEvent {
+    artifact_type: String,
+    slsa_provenance: Vec<String>,
+    artifacts_download_url: Vec<String>,
+}
+
+/// Rebuild the canonical form of an RVDS publish event payload, as hashed by
+/// RVDS and its ledger before the audit proof is attached.
+pub fn canonical_payload(payload: &str) -> Result<String> {
+    let event: CanonicalEvent =
+        serde_json::from_str(payload).context("parse RVDS publish event payload")?;
+    serde_json::to_string(&event).context("serialize canonical RVDS payload")
+}
+
+/// Hex SHA-256 of [`canonical_payload`].
+pub fn canonical_payload_hash(payload: &str) -> Result<String> {
+    let canonical = canonical_payload(payload)?;
+    Ok(format!("{:x}", Sha256::digest(canonical.as_bytes())))
+}
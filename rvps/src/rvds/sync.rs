@@ -0,0 +1,384 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use log::{debug, info, warn};
+use reqwest::Client;
+use tokio::sync::RwLock;
+
+use super::{canonical_payload_hash, RvdsEvent, RvdsEventsPage, RvdsSyncConfig};
+use crate::{Message, MessageRejected, Rvps};
+
+/// Number of events requested per pull.
+const PAGE_SIZE: usize = 100;
+
+/// Periodically pulls the RVDS event log and registers events RVPS has not
+/// seen yet, so that a trustee which subscribed late or was restored from a
+/// backup converges with the events published meanwhile.
+pub struct RvdsSyncClient {
+    config: RvdsSyncConfig,
+    http: Client,
+    rvps: Arc<RwLock<Rvps>>,
+}
+
+impl RvdsSyncClient {
+    pub fn new(config: RvdsSyncConfig, rvps: Arc<RwLock<Rvps>>) -> Result<Self> {
+        if config.interval_secs == 0 {
+            bail!("rvds_sync.interval_secs must be greater than zero");
+        }
+
+        let http = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .context("build RVDS http client")?;
+
+        Ok(Self { config, http, rvps })
+    }
+
+    /// Run the sync loop forever. Failures are logged and retried at the next tick.
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.interval_secs));
+        loop {
+            interval.tick().await;
+            match self.sync_once().await {
+                Ok(0) => debug!("RVDS sync: no new events"),
+                Ok(applied) => info!("RVDS sync: registered {applied} missed event(s)"),
+                Err(e) => warn!("RVDS sync failed: {e:#}"),
+            }
+        }
+    }
+
+    /// Pull every event after the stored cursor and register it.
+    /// Returns the number of events registered successfully.
+    pub async fn sync_once(&self) -> Result<usize> {
+        let mut cursor = self.load_cursor().await?;
+        let mut applied = 0;
+
+        loop {
+            let page = self.fetch(cursor).await?;
+            if page.events.is_empty() {
+                break;
+            }
+
+            let outcome = self.apply(&page.events, &mut cursor).await;
+            self.store_cursor(cursor).await?;
+            applied += outcome?;
+
+            if cursor >= page.latest_seq {
+                break;
+            }
+        }
+
+        Ok(applied)
+    }
+
+    async fn fetch(&self, since: u64) -> Result<RvdsEventsPage> {
+        let url = format!("{}/rvds/events", self.config.endpoint.trim_end_matches('/'));
+        let mut query = vec![
+            ("since", since.to_string()),
+            ("limit", PAGE_SIZE.to_string()),
+        ];
+        if !self.config.artifact_types.is_empty() {
+            query.push(("artifact_type", self.config.artifact_types.join(",")));
+        }
+
+        let mut req = self.http.get(&url).query(&query);
+        if let Some(token) = &self.config.token {
+            req = req.bearer_auth(token);
+        }
+
+        req.send()
+            .await
+            .context("request RVDS events")?
+            .error_for_status()
+            .context("RVDS events status")?
+            .json()
+            .await
+            .context("parse RVDS events")
+    }
+
+    /// Register `events` in order and advance `cursor` past each of them.
+    /// Returns the number of events registered.
+    ///
+    /// An event that fails its integrity check, whose audit proof the ledger
+    /// contradicts or that is rejected by the wares or the extractor is
+    /// skipped, as replaying it would fail again. Any other failure, e.g. of
+    /// the storage or reaching the ledger, stops at the event, leaving
+    /// `cursor` before it, so that it is pulled again at the next sync.
+    pub(crate) async fn apply(&self, events: &[RvdsEvent], cursor: &mut u64) -> Result<usize> {
+        let mut applied = 0;
+        for event in events {
+            if event.seq <= *cursor {
+                continue;
+            }
+
+            match self.apply_one(event).await {
+                Ok(()) => applied += 1,
+                Err(e) if e.is::<MessageRejected>() => {
+                    warn!("RVDS sync: skip event seq={}: {e:#}", event.seq)
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("register event seq={}", event.seq))
+                }
+            }
+            *cursor = event.seq;
+        }
+        Ok(applied)
+    }
+
+    async fn apply_one(&self, event: &RvdsEvent) -> Result<()> {
+        let message: Message = serde_json::from_str(&event.message)
+            .context("parse RVPS message envelope")
+            .context(MessageRejected)?;
+        let payload_hash = canonical_payload_hash(&message.payload).context(MessageRejected)?;
+        if payload_hash != event.payload_hash {
+            return Err(anyhow!(
+                "payload hash mismatch: event log has {}, payload hashes to {}",
+                event.payload_hash,
+                payload_hash
+            )
+            .context(MessageRejected));
+        }
+
+        let actor = format!("rvds:{}#{}", self.config.endpoint, event.seq);
+        self.rvps
+            .write()
+            .await
+            .verify_and_extract_as(&self.config.namespace, &event.message, &actor)
+            .await
+    }
+
+    async fn load_cursor(&self) -> Result<u64> {
+        match tokio::fs::read_to_string(&self.config.cursor_path).await {
+            Ok(raw) => raw
+                .trim()
+                .parse()
+                .with_context(|| format!("parse RVDS cursor `{}`", self.config.cursor_path)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => {
+                Err(e).with_context(|| format!("read RVDS cursor `{}`", self.config.cursor_path))
+            }
+        }
+    }
+
+    async fn store_cursor(&self, cursor: u64) -> Result<()> {
+        tokio::fs::write(&self.config.cursor_path, cursor.to_string())
+            .await
+            .with_context(|| format!("write RVDS cursor `{}`", self.config.cursor_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::ledger::tests::{proof_for, stand_in_ledger};
+    use crate::ledger::{http, LedgerBackendConfig, LedgerConfig};
+    use crate::namespace::DEFAULT_NAMESPACE;
+    use crate::storage::{in_memory, ReferenceValueStorageConfig};
+    use crate::Config;
+
+    const DIGEST: &str = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+
+    fn rvds_event(seq: u64, subject: &str) -> RvdsEvent {
+        rvds_event_with_proof(
+            seq,
+            subject,
+            serde_json::json!({"backend": "none", "handle": "noop", "event_hash": "00"}),
+        )
+    }
+
+    /// An event whose payload carries `audit_proof`, which may use the
+    /// placeholder `HASH` for the canonical payload hash.
+    fn rvds_event_with_proof(seq: u64, subject: &str, audit_proof: Value) -> RvdsEvent {
+        let statement = serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "predicateType": "https://slsa.dev/provenance/v1",
+            "subject": [{"name": subject, "digest": {"sha256": DIGEST}}],
+            "predicate": {}
+        });
+        let mut payload = serde_json::json!({
+            "artifact_type": "rpm",
+            "slsa_provenance": [base64::engine::general_purpose::STANDARD.encode(statement.to_string())],
+            "artifacts_download_url": ["https://example.com/a.rpm"],
+        });
+        let hash = canonical_payload_hash(&payload.to_string()).unwrap();
+        payload["audit_proof"] =
+            serde_json::from_str(&audit_proof.to_string().replace("HASH", &hash)).unwrap();
+        let payload = payload.to_string();
+        let message = serde_json::json!({
+            "version": "0.1.0",
+            "type": "slsa",
+            "payload": payload,
+        })
+        .to_string();
+
+        RvdsEvent {
+            seq,
+            artifact_type: "rpm".to_string(),
+            payload_hash: canonical_payload_hash(&payload).unwrap(),
+            message,
+        }
+    }
+
+    fn client(cursor_path: &std::path::Path) -> RvdsSyncClient {
+        client_with_ledger(cursor_path, LedgerConfig::default())
+    }
+
+    fn client_with_ledger(cursor_path: &std::path::Path, ledger: LedgerConfig) -> RvdsSyncClient {
+        let rvps = Rvps::new(Config {
+            storage: ReferenceValueStorageConfig::InMemory(in_memory::Config::default()),
+            ledger,
+            ..Default::default()
+        })
+        .unwrap();
+        RvdsSyncClient::new(
+            RvdsSyncConfig {
+                endpoint: "http://127.0.0.1:1".to_string(),
+                token: None,
+                interval_secs: 60,
+                artifact_types: Vec::new(),
+                cursor_path: cursor_path.to_string_lossy().to_string(),
+                namespace: String::new(),
+            },
+            Arc::new(RwLock::new(rvps)),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn apply_registers_new_events_and_skips_tampered_ones() {
+        let tmp = tempfile::tempdir().unwrap();
+        let client = client(&tmp.path().join("cursor"));
+
+        let mut tampered = rvds_event(2, "tampered.rpm");
+        tampered.payload_hash = "00".repeat(32);
+        let events = vec![
+            rvds_event(1, "good.rpm"),
+            tampered,
+            rvds_event(3, "late.rpm"),
+        ];
+
+        let mut cursor = 0;
+        assert_eq!(client.apply(&events, &mut cursor).await.unwrap(), 2);
+        assert_eq!(cursor, 3);
+
+        let rvps = client.rvps.read().await;
+        assert!(rvps
+            .query_reference_value("good.rpm")
+            .await
+            .unwrap()
+            .is_some());
+        assert!(rvps
+            .query_reference_value("late.rpm")
+            .await
+            .unwrap()
+            .is_some());
+        assert!(rvps
+            .query_reference_value("tampered.rpm")
+            .await
+            .unwrap()
+            .is_none());
+
+        let history = rvps
+            .reference_value_history(DEFAULT_NAMESPACE, "late.rpm")
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].actor, "rvds:http://127.0.0.1:1#3");
+    }
+
+    #[tokio::test]
+    async fn apply_stops_at_events_failing_to_register() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut client = client(&tmp.path().join("cursor"));
+        // Fails to store the reference values, not to verify the message.
+        client.config.namespace = "team/a".to_string();
+
+        let mut tampered = rvds_event(2, "tampered.rpm");
+        tampered.payload_hash = "00".repeat(32);
+        let mut cursor = 0;
+        assert!(client
+            .apply(&[tampered, rvds_event(3, "good.rpm")], &mut cursor)
+            .await
+            .is_err());
+        // Past the rejected event, before the one to retry.
+        assert_eq!(cursor, 2);
+    }
+
+    fn http_ledger(endpoint: String) -> LedgerConfig {
+        LedgerConfig {
+            backend: LedgerBackendConfig::Http(http::Config {
+                endpoint,
+                api_key: None,
+            }),
+            require_proof: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn apply_skips_events_with_bad_receipts() {
+        let endpoint = stand_in_ledger(|path, _| match path {
+            "/forged" => (200, format!(r#"{{"event_hash":"{}"}}"#, "00".repeat(32))),
+            _ => (404, "{}".to_string()),
+        })
+        .await;
+        let tmp = tempfile::tempdir().unwrap();
+        let client = client_with_ledger(&tmp.path().join("cursor"), http_ledger(endpoint));
+
+        let proof = |handle| serde_json::to_value(proof_for("http", handle, "HASH")).unwrap();
+        let events = vec![
+            rvds_event_with_proof(1, "forged.rpm", proof("forged")),
+            rvds_event_with_proof(2, "unrecorded.rpm", proof("unrecorded")),
+        ];
+
+        let mut cursor = 0;
+        assert_eq!(client.apply(&events, &mut cursor).await.unwrap(), 0);
+        assert_eq!(cursor, 2);
+    }
+
+    #[tokio::test]
+    async fn apply_stops_at_events_the_ledger_cannot_be_asked_about() {
+        let tmp = tempfile::tempdir().unwrap();
+        let client = client_with_ledger(
+            &tmp.path().join("cursor"),
+            http_ledger("http://127.0.0.1:1".to_string()),
+        );
+
+        let proof = serde_json::to_value(proof_for("http", "h", "HASH")).unwrap();
+        let mut cursor = 0;
+        assert!(client
+            .apply(&[rvds_event_with_proof(1, "a.rpm", proof)], &mut cursor)
+            .await
+            .is_err());
+        assert_eq!(cursor, 0);
+    }
+
+    #[tokio::test]
+    async fn apply_ignores_events_before_cursor() {
+        let tmp = tempfile::tempdir().unwrap();
+        let client = client(&tmp.path().join("cursor"));
+
+        let mut cursor = 5;
+        assert_eq!(
+            client
+                .apply(&[rvds_event(4, "old.rpm")], &mut cursor)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(cursor, 5);
+    }
+
+    #[tokio::test]
+    async fn cursor_round_trips_through_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let client = client(&tmp.path().join("cursor"));
+
+        assert_eq!(client.load_cursor().await.unwrap(), 0);
+        client.store_cursor(42).await.unwrap();
+        assert_eq!(client.load_cursor().await.unwrap(), 42);
+    }
+}
@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use log::{debug, info};
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -6,15 +7,28 @@ use tokio::sync::RwLock;
 use tonic::transport::Server;
 use tonic::{Request, Response, Status};
 
+use crate::expiry::{ExpiringReferenceValue, ExpirySweeper};
+use crate::rvds::RvdsSyncClient;
+use crate::snapshot::ImportStrategy;
 use crate::{Config, Rvps};
 
+use crate::rvps_api::reference::reference_value_bulk_server::{
+    ReferenceValueBulk, ReferenceValueBulkServer,
+};
+use crate::rvps_api::reference::reference_value_history_server::{
+    ReferenceValueHistory, ReferenceValueHistoryServer,
+};
 use crate::rvps_api::reference::reference_value_provider_service_server::{
     ReferenceValueProviderService, ReferenceValueProviderServiceServer,
 };
 use crate::rvps_api::reference::{
-    ReferenceValueDeleteRequest, ReferenceValueDeleteResponse, ReferenceValueListRequest,
-    ReferenceValueListResponse, ReferenceValueQueryRequest, ReferenceValueQueryResponse,
-    ReferenceValueRegisterRequest, ReferenceValueRegisterResponse,
+    ReferenceValueAtRequest, ReferenceValueAtResponse, ReferenceValueDeleteRequest,
+    ReferenceValueDeleteResponse, ReferenceValueExpiryRequest, ReferenceValueExpiryResponse,
+    ReferenceValueExportRequest, ReferenceValueExportResponse, ReferenceValueHistoryRequest,
+    ReferenceValueHistoryResponse, ReferenceValueImportRequest, ReferenceValueImportResponse,
+    ReferenceValueListRequest, ReferenceValueListResponse, ReferenceValueQueryRequest,
+    ReferenceValueQueryResponse, ReferenceValueRegisterRequest, ReferenceValueRegisterResponse,
+    ReferenceValueRollbackRequest, ReferenceValueRollbackResponse,
 };
 
 pub struct RvpsServer {
@@ -27,18 +41,42 @@ impl RvpsServer {
     }
 }
 
+/// The bearer token of `request`, presented to change the reference values
+/// of a namespace.
+fn admin_token<T>(request: &Request<T>) -> Option<&str> {
+    request
+        .metadata()
+        .get("authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+impl RvpsServer {
+    /// Check that `request` may change the reference values of `namespace`,
+    /// returning the authenticated actor to record in the history.
+    async fn authorize<T>(&self, request: &Request<T>, namespace: &str) -> Result<String, Status> {
+        self.rvps
+            .read()
+            .await
+            .authorize(namespace, admin_token(request))
+            .map_err(|e| Status::permission_denied(format!("{e:#}")))
+    }
+}
+
 #[tonic::async_trait]
 impl ReferenceValueProviderService for RvpsServer {
     async fn query_reference_value(
         &self,
         request: Request<ReferenceValueQueryRequest>,
     ) -> Result<Response<ReferenceValueQueryResponse>, Status> {
-        let reference_value_id = request.into_inner().reference_value_id;
+        let request = request.into_inner();
+        let reference_value_id = request.reference_value_id;
         let rvps = self.rvps.read().await;
 
         let reference_value_results = if reference_value_id.is_empty() {
             let rvs = rvps
-                .get_reference_values()
+                .get_reference_values_in(&request.namespace)
                 .await
                 .map_err(|e| Status::aborted(format!("Query reference values: {e}")))?;
 
@@ -46,7 +84,7 @@ impl ReferenceValueProviderService for RvpsServer {
                 .map_err(|e| Status::aborted(format!("Serialize reference values: {e}")))?
         } else {
             let value = rvps
-                .query_reference_value(&reference_value_id)
+                .query_reference_value_in(&request.namespace, &reference_value_id)
                 .await
                 .map_err(|e| Status::aborted(format!("Query reference value: {e}")))?;
 
@@ -68,6 +106,9 @@ impl ReferenceValueProviderService for RvpsServer {
         &self,
         request: Request<ReferenceValueRegisterRequest>,
     ) -> Result<Response<ReferenceValueRegisterResponse>, Status> {
+        let actor = self
+            .authorize(&request, &request.get_ref().namespace)
+            .await?;
         let request = request.into_inner();
 
         debug!("registry reference value: {}", request.message);
@@ -75,7 +116,7 @@ impl ReferenceValueProviderService for RvpsServer {
         self.rvps
             .write()
             .await
-            .verify_and_extract(&request.message)
+            .verify_and_extract_as(&request.namespace, &request.message, &actor)
             .await
             .map_err(|e| Status::aborted(format!("Register reference value: {e}")))?;
 
@@ -87,6 +128,9 @@ impl ReferenceValueProviderService for RvpsServer {
         &self,
         request: Request<ReferenceValueDeleteRequest>,
     ) -> Result<Response<ReferenceValueDeleteResponse>, Status> {
+        let actor = self
+            .authorize(&request, &request.get_ref().namespace)
+            .await?;
         let request = request.into_inner();
 
         debug!("Delete reference value: {}", request.name);
@@ -95,7 +139,7 @@ impl ReferenceValueProviderService for RvpsServer {
             .rvps
             .write()
             .await
-            .delete_reference_value(&request.name)
+            .delete_reference_value_as(&request.namespace, &request.name, &actor)
             .await
             .map_err(|e| Status::aborted(format!("Delete reference value: {e}")))?;
 
@@ -113,6 +157,9 @@ impl ReferenceValueProviderService for RvpsServer {
         &self,
         request: Request<ReferenceValueListRequest>,
     ) -> Result<Response<ReferenceValueListResponse>, Status> {
+        let actor = self
+            .authorize(&request, &request.get_ref().namespace)
+            .await?;
         let request = request.into_inner();
 
         debug!(
@@ -123,7 +170,7 @@ impl ReferenceValueProviderService for RvpsServer {
         self.rvps
             .write()
             .await
-            .set_reference_value_list(&request.payload)
+            .set_reference_value_list_as(&request.namespace, &request.payload, &actor)
             .await
             .map_err(|e| Status::aborted(format!("Set reference value list: {e}")))?;
 
@@ -132,13 +179,221 @@ impl ReferenceValueProviderService for RvpsServer {
     }
 }
 
+#[tonic::async_trait]
+impl ReferenceValueHistory for RvpsServer {
+    async fn get_reference_value_history(
+        &self,
+        request: Request<ReferenceValueHistoryRequest>,
+    ) -> Result<Response<ReferenceValueHistoryResponse>, Status> {
+        let request = request.into_inner();
+
+        debug!("Get history of reference value: {}", request.name);
+
+        let history = self
+            .rvps
+            .read()
+            .await
+            .reference_value_history(&request.namespace, &request.name)
+            .await
+            .map_err(|e| Status::aborted(format!("Get reference value history: {e}")))?;
+        let history = serde_json::to_string(&history)
+            .map_err(|e| Status::aborted(format!("Serialize reference value history: {e}")))?;
+
+        let res = ReferenceValueHistoryResponse { history };
+        Ok(Response::new(res))
+    }
+
+    async fn query_reference_value_at(
+        &self,
+        request: Request<ReferenceValueAtRequest>,
+    ) -> Result<Response<ReferenceValueAtResponse>, Status> {
+        let request = request.into_inner();
+
+        debug!(
+            "Query reference value {} at {}",
+            request.name, request.timestamp
+        );
+
+        let at = DateTime::parse_from_rfc3339(&request.timestamp)
+            .map_err(|e| Status::invalid_argument(format!("Parse timestamp: {e}")))?
+            .with_timezone(&Utc);
+        let value = self
+            .rvps
+            .read()
+            .await
+            .reference_value_at(&request.namespace, &request.name, at)
+            .await
+            .map_err(|e| Status::aborted(format!("Query reference value: {e}")))?;
+        let reference_value = value
+            .map(|value| serde_json::to_string(&value))
+            .transpose()
+            .map_err(|e| Status::aborted(format!("Serialize reference value: {e}")))?
+            .unwrap_or_default();
+
+        let res = ReferenceValueAtResponse { reference_value };
+        Ok(Response::new(res))
+    }
+
+    async fn rollback_reference_value(
+        &self,
+        request: Request<ReferenceValueRollbackRequest>,
+    ) -> Result<Response<ReferenceValueRollbackResponse>, Status> {
+        let actor = self
+            .authorize(&request, &request.get_ref().namespace)
+            .await?;
+        let request = request.into_inner();
+
+        debug!(
+            "Roll back reference value {} to revision {}",
+            request.name, request.revision
+        );
+
+        self.rvps
+            .write()
+            .await
+            .rollback_reference_value(&request.namespace, &request.name, request.revision, &actor)
+            .await
+            .map_err(|e| Status::aborted(format!("Roll back reference value: {e}")))?;
+
+        let res = ReferenceValueRollbackResponse {};
+        Ok(Response::new(res))
+    }
+
+    async fn get_expiring_reference_values(
+        &self,
+        request: Request<ReferenceValueExpiryRequest>,
+    ) -> Result<Response<ReferenceValueExpiryResponse>, Status> {
+        let request = request.into_inner();
+
+        debug!(
+            "Get reference values expiring within {} hours",
+            request.within_hours
+        );
+
+        let within = i64::try_from(request.within_hours)
+            .ok()
+            .and_then(chrono::Duration::try_hours)
+            .ok_or_else(|| Status::invalid_argument("within_hours is too large"))?;
+        let values = self
+            .rvps
+            .read()
+            .await
+            .expiring_reference_values(Some(&request.namespace), Utc::now() + within)
+            .await
+            .map_err(|e| Status::aborted(format!("Get expiring reference values: {e}")))?;
+        let values: Vec<ExpiringReferenceValue> = values.iter().map(Into::into).collect();
+        let reference_values = serde_json::to_string(&values)
+            .map_err(|e| Status::aborted(format!("Serialize reference values: {e}")))?;
+
+        let res = ReferenceValueExpiryResponse { reference_values };
+        Ok(Response::new(res))
+    }
+}
+
+#[tonic::async_trait]
+impl ReferenceValueBulk for RvpsServer {
+    async fn export_reference_values(
+        &self,
+        request: Request<ReferenceValueExportRequest>,
+    ) -> Result<Response<ReferenceValueExportResponse>, Status> {
+        let request = request.into_inner();
+
+        debug!(
+            "Export reference values of namespace: {}",
+            request.namespace
+        );
+
+        let values = self
+            .rvps
+            .read()
+            .await
+            .export_reference_values(&request.namespace)
+            .await
+            .map_err(|e| Status::aborted(format!("Export reference values: {e}")))?;
+        let reference_values = serde_json::to_string(&values)
+            .map_err(|e| Status::aborted(format!("Serialize reference values: {e}")))?;
+
+        let res = ReferenceValueExportResponse { reference_values };
+        Ok(Response::new(res))
+    }
+
+    async fn import_reference_values(
+        &self,
+        request: Request<ReferenceValueImportRequest>,
+    ) -> Result<Response<ReferenceValueImportResponse>, Status> {
+        let actor = self
+            .authorize(&request, &request.get_ref().namespace)
+            .await?;
+        let request = request.into_inner();
+
+        debug!(
+            "Import reference values, strategy: {}, dry run: {}",
+            request.strategy, request.dry_run
+        );
+
+        let strategy = ImportStrategy::parse(&request.strategy)
+            .map_err(|e| Status::invalid_argument(format!("{e}")))?;
+        let results = self
+            .rvps
+            .write()
+            .await
+            .import_snapshot(
+                &request.namespace,
+                &request.snapshot,
+                strategy,
+                request.dry_run,
+                &actor,
+            )
+            .await
+            .map_err(|e| Status::aborted(format!("Import reference values: {e}")))?;
+        let results = serde_json::to_string(&results)
+            .map_err(|e| Status::aborted(format!("Serialize import results: {e}")))?;
+
+        let res = ReferenceValueImportResponse { results };
+        Ok(Response::new(res))
+    }
+}
+
 pub async fn start(socket: SocketAddr, config: Config) -> Result<()> {
+    let rvds_sync = config.rvds_sync.clone();
+    let expiry = config.expiry.clone();
+    let max_request_bytes = config.max_request_bytes;
     let service = Rvps::new(config)?;
     let inner = Arc::new(RwLock::new(service));
-    let rvps_server = RvpsServer::new(inner.clone());
+
+    if let Some(sync_config) = rvds_sync {
+        info!(
+            "RVDS catch-up sync enabled against {}",
+            sync_config.endpoint
+        );
+        let client = RvdsSyncClient::new(sync_config, inner.clone())?;
+        tokio::spawn(client.run());
+    }
+
+    if let Some(expiry_config) = expiry {
+        info!(
+            "Expiry sweeper enabled, every {}s",
+            expiry_config.interval_secs
+        );
+        let sweeper = ExpirySweeper::new(expiry_config, inner.clone())?;
+        tokio::spawn(sweeper.run());
+    }
+
+    let rvps_server = Arc::new(RvpsServer::new(inner.clone()));
+
+    // Only registering and importing take large requests, e.g.
+    // `launch-measurement` messages carrying whole images.
+    let mut provider = ReferenceValueProviderServiceServer::from_arc(rvps_server.clone());
+    let mut bulk = ReferenceValueBulkServer::from_arc(rvps_server.clone());
+    if let Some(limit) = max_request_bytes {
+        provider = provider.max_decoding_message_size(limit);
+        bulk = bulk.max_decoding_message_size(limit);
+    }
 
     Server::builder()
-        .add_service(ReferenceValueProviderServiceServer::new(rvps_server))
+        .add_service(provider)
+        .add_service(ReferenceValueHistoryServer::from_arc(rvps_server))
+        .add_service(bulk)
         .serve(socket)
         .await
         .context("gRPC error")
@@ -0,0 +1,451 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Snapshots of the reference values of a namespace.
+//!
+//! A snapshot holds every reference value stored in a namespace, expired or
+//! not, to move them between RVPS instances (e.g. from staging to
+//! production) or to audit what changed between two points in time.
+//!
+//! Snapshot files are DSSE envelopes carrying the serialized snapshot as
+//! payload, so that signatures cover its exact bytes. With the `fs`
+//! feature, they can be signed with ECDSA P-256/P-384 keys, like the
+//! messages the `Signature` pre-processor ware admits. Importing a snapshot
+//! bypasses the extractors and wares, so the `rvps` server only imports
+//! snapshots signed by a trusted key, unless told to accept unsigned ones.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+#[cfg(feature = "fs")]
+use crate::crypto::{pae, PublicKey};
+use crate::ReferenceValue;
+
+/// Version of the snapshot format.
+pub const SNAPSHOT_VERSION: &str = "0.1.0";
+
+/// DSSE payload type of snapshots.
+pub const SNAPSHOT_PAYLOAD_TYPE: &str = "application/vnd.rvps.snapshot+json";
+
+/// Snapshots the `rvps` server accepts for import.
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct SnapshotConfig {
+    /// Paths to PEM ECDSA P-256/P-384 public keys. A snapshot must be signed
+    /// by one of them to be imported.
+    #[serde(default)]
+    pub trusted_keys: Vec<String>,
+
+    /// Also import unsigned snapshots. Disabled by default.
+    #[serde(default)]
+    pub allow_unsigned: bool,
+}
+
+/// The reference values of a namespace at one point in time.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub version: String,
+    pub namespace: String,
+    pub created_at: DateTime<Utc>,
+    /// Sorted by name.
+    pub reference_values: Vec<ReferenceValue>,
+}
+
+impl Snapshot {
+    pub fn new(namespace: &str, mut reference_values: Vec<ReferenceValue>) -> Self {
+        reference_values.sort_by(|a, b| a.name().cmp(b.name()));
+        Self {
+            version: SNAPSHOT_VERSION.into(),
+            namespace: namespace.into(),
+            created_at: Utc::now(),
+            reference_values,
+        }
+    }
+}
+
+/// Signature of a snapshot envelope.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SnapshotSignature {
+    /// Who signed the snapshot, for information only.
+    pub keyid: String,
+    /// Base64 DER ECDSA signature of the DSSE pre-authentication encoding
+    /// of the payload.
+    pub sig: String,
+}
+
+/// A snapshot file: a DSSE envelope carrying a serialized [`Snapshot`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotEnvelope {
+    pub payload_type: String,
+    /// Base64 serialized snapshot.
+    pub payload: String,
+    #[serde(default)]
+    pub signatures: Vec<SnapshotSignature>,
+}
+
+impl SnapshotEnvelope {
+    /// An unsigned envelope of `snapshot`.
+    pub fn new(snapshot: &Snapshot) -> Result<Self> {
+        Ok(Self {
+            payload_type: SNAPSHOT_PAYLOAD_TYPE.into(),
+            payload: STANDARD.encode(serde_json::to_vec(snapshot)?),
+            signatures: Vec::new(),
+        })
+    }
+
+    /// Parse a snapshot file, checking its payload type.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let envelope: Self = serde_json::from_str(json).context("parse snapshot envelope")?;
+        if envelope.payload_type != SNAPSHOT_PAYLOAD_TYPE {
+            bail!("unexpected snapshot payload type {}", envelope.payload_type);
+        }
+        Ok(envelope)
+    }
+
+    pub fn is_signed(&self) -> bool {
+        !self.signatures.is_empty()
+    }
+
+    /// The snapshot, whether signed or not, checking its version.
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        let payload = STANDARD
+            .decode(&self.payload)
+            .context("decode snapshot payload")?;
+        let snapshot: Snapshot = serde_json::from_slice(&payload).context("parse snapshot")?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            bail!(
+                "Version unmatched! Need {}, given {}.",
+                SNAPSHOT_VERSION,
+                snapshot.version
+            );
+        }
+        Ok(snapshot)
+    }
+
+    /// The bytes the signatures cover.
+    #[cfg(feature = "fs")]
+    fn signed_bytes(&self) -> Result<Vec<u8>> {
+        let payload = STANDARD
+            .decode(&self.payload)
+            .context("decode snapshot payload")?;
+        Ok(pae(&self.payload_type, &payload))
+    }
+
+    /// Sign the snapshot as `signer` with a PEM PKCS#8 ECDSA P-256/P-384
+    /// private key.
+    #[cfg(feature = "fs")]
+    pub fn sign(&mut self, signer: &str, private_key_pem: &str) -> Result<()> {
+        use anyhow::anyhow;
+        use p256::ecdsa::signature::Signer;
+        use p256::pkcs8::DecodePrivateKey;
+
+        let message = self.signed_bytes()?;
+        let signature = if let Ok(key) = p256::ecdsa::SigningKey::from_pkcs8_pem(private_key_pem) {
+            let signature: p256::ecdsa::Signature = key.sign(&message);
+            signature.to_der().as_bytes().to_vec()
+        } else {
+            let key = p384::ecdsa::SigningKey::from_pkcs8_pem(private_key_pem)
+                .map_err(|_| anyhow!("unsupported private key, only ECDSA P-256/P-384 keys are"))?;
+            let signature: p384::ecdsa::Signature = key.sign(&message);
+            signature.to_der().as_bytes().to_vec()
+        };
+
+        self.signatures.push(SnapshotSignature {
+            keyid: signer.into(),
+            sig: STANDARD.encode(signature),
+        });
+        Ok(())
+    }
+
+    /// Check that the snapshot is signed by the PEM ECDSA P-256/P-384
+    /// public key.
+    #[cfg(feature = "fs")]
+    pub fn verify(&self, public_key_pem: &str) -> Result<()> {
+        self.verify_with(&[PublicKey::from_pem(public_key_pem)?])
+    }
+
+    /// Check that the snapshot is signed by one of `keys`.
+    #[cfg(feature = "fs")]
+    fn verify_with(&self, keys: &[PublicKey]) -> Result<()> {
+        if !self.is_signed() {
+            bail!("snapshot is not signed");
+        }
+        let message = self.signed_bytes()?;
+        let verified = self.signatures.iter().any(|signature| {
+            STANDARD
+                .decode(&signature.sig)
+                .is_ok_and(|sig| keys.iter().any(|key| key.verify(&message, &sig).is_ok()))
+        });
+        if !verified {
+            bail!("snapshot signature does not verify with any trusted key");
+        }
+        Ok(())
+    }
+}
+
+/// Checks the snapshots to import against the [`SnapshotConfig`].
+pub(crate) struct SnapshotVerifier {
+    #[cfg(feature = "fs")]
+    keys: Vec<PublicKey>,
+    allow_unsigned: bool,
+}
+
+impl SnapshotVerifier {
+    pub(crate) fn new(config: &SnapshotConfig) -> Result<Self> {
+        #[cfg(feature = "fs")]
+        let keys = config
+            .trusted_keys
+            .iter()
+            .map(|path| {
+                let pem = std::fs::read_to_string(path)
+                    .with_context(|| format!("read trusted snapshot key {path}"))?;
+                PublicKey::from_pem(&pem)
+                    .with_context(|| format!("load trusted snapshot key {path}"))
+            })
+            .collect::<Result<_>>()?;
+        #[cfg(not(feature = "fs"))]
+        if !config.trusted_keys.is_empty() {
+            bail!("trusted snapshot keys require the `fs` feature");
+        }
+
+        Ok(Self {
+            #[cfg(feature = "fs")]
+            keys,
+            allow_unsigned: config.allow_unsigned,
+        })
+    }
+
+    /// The snapshot of `envelope`, if it is signed by a trusted key, or
+    /// unsigned and unsigned snapshots are allowed.
+    pub(crate) fn verify(&self, envelope: &SnapshotEnvelope) -> Result<Snapshot> {
+        if !envelope.is_signed() {
+            if !self.allow_unsigned {
+                bail!("unsigned snapshots are not accepted");
+            }
+        } else {
+            #[cfg(feature = "fs")]
+            envelope.verify_with(&self.keys)?;
+            #[cfg(not(feature = "fs"))]
+            bail!("verifying snapshot signatures requires the `fs` feature");
+        }
+        envelope.snapshot()
+    }
+}
+
+/// How an import handles reference values already stored with different
+/// contents.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStrategy {
+    /// Merge the imported digests into the stored ones, like registering
+    /// them does.
+    #[default]
+    Merge,
+    /// Replace the stored value with the imported one.
+    Replace,
+    /// Keep the stored value.
+    Skip,
+}
+
+impl ImportStrategy {
+    pub fn parse(strategy: &str) -> Result<Self> {
+        match strategy.to_ascii_lowercase().as_str() {
+            "" | "merge" => Ok(Self::Merge),
+            "replace" => Ok(Self::Replace),
+            "skip" => Ok(Self::Skip),
+            other => bail!("unsupported import strategy `{other}`"),
+        }
+    }
+}
+
+/// What an import does with one reference value.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Display, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ImportAction {
+    /// Stored, as no value of that name was.
+    Add,
+    /// Merged into the stored value.
+    Merge,
+    /// Replaced the stored value.
+    Replace,
+    /// Not stored, as a different value is stored and the strategy is
+    /// `skip`.
+    Skip,
+    /// Not stored, as the stored value is the same.
+    Unchanged,
+}
+
+/// The action an import takes, or would take in a dry run, for a
+/// reference value.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ImportResult {
+    pub name: String,
+    pub action: ImportAction,
+}
+
+/// How a reference value differs between two sets.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReferenceValueDiff {
+    pub name: String,
+    pub kind: DiffKind,
+    /// Fields of a changed value that differ, e.g. `expiration`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<String>,
+    /// `<alg>:<digest>` pairs only in the new value.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added_digests: Vec<String>,
+    /// `<alg>:<digest>` pairs only in the old value.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_digests: Vec<String>,
+}
+
+fn digests(rv: &ReferenceValue) -> BTreeSet<String> {
+    rv.hash_values()
+        .iter()
+        .map(|pair| format!("{}:{}", pair.alg(), pair.value()))
+        .collect()
+}
+
+/// The differences from the reference values `old` to `new`, by name.
+pub fn diff(old: &[ReferenceValue], new: &[ReferenceValue]) -> Vec<ReferenceValueDiff> {
+    let old: BTreeMap<_, _> = old.iter().map(|rv| (rv.name(), rv)).collect();
+    let new: BTreeMap<_, _> = new.iter().map(|rv| (rv.name(), rv)).collect();
+    let names: BTreeSet<_> = old.keys().chain(new.keys()).collect();
+
+    let mut diffs = Vec::new();
+    for name in names {
+        let (old_rv, new_rv) = (old.get(name), new.get(name));
+        let kind = match (old_rv, new_rv) {
+            (None, Some(_)) => DiffKind::Added,
+            (Some(_), None) => DiffKind::Removed,
+            (Some(a), Some(b)) if a != b => DiffKind::Changed,
+            _ => continue,
+        };
+
+        let old_digests = old_rv.map(|rv| digests(rv)).unwrap_or_default();
+        let new_digests = new_rv.map(|rv| digests(rv)).unwrap_or_default();
+        let mut fields = Vec::new();
+        if let (Some(a), Some(b)) = (old_rv, new_rv) {
+            for (field, differs) in [
+                ("version", a.version != b.version),
+                ("expiration", a.expiration != b.expiration),
+                ("hash-value", old_digests != new_digests),
+                ("value", a.value != b.value),
+                ("audit_proof", a.audit_proof != b.audit_proof),
+            ] {
+                if differs {
+                    fields.push(field.to_string());
+                }
+            }
+        }
+
+        diffs.push(ReferenceValueDiff {
+            name: name.to_string(),
+            kind,
+            fields,
+            added_digests: new_digests.difference(&old_digests).cloned().collect(),
+            removed_digests: old_digests.difference(&new_digests).cloned().collect(),
+        });
+    }
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn reference_value(name: &str, digests: &[&str]) -> ReferenceValue {
+        let expiration = Utc::now() + Duration::days(30);
+        digests.iter().fold(
+            ReferenceValue::new()
+                .unwrap()
+                .set_name(name)
+                .set_expiration(expiration),
+            |rv, digest| rv.add_hash_value("sha256".into(), digest.to_string()),
+        )
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = [
+            reference_value("kept", &["a"]),
+            reference_value("changed", &["b", "c"]),
+            reference_value("removed", &["d"]),
+        ];
+        let new = [
+            reference_value("added", &["e"]),
+            old[0].clone(),
+            reference_value("changed", &["c", "f"]),
+        ];
+
+        let diffs = diff(&old, &new);
+        let kinds: Vec<_> = diffs.iter().map(|d| (d.name.as_str(), d.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("added", DiffKind::Added),
+                ("changed", DiffKind::Changed),
+                ("removed", DiffKind::Removed),
+            ]
+        );
+        assert_eq!(diffs[1].fields, ["hash-value"]);
+        assert_eq!(diffs[1].added_digests, ["sha256:f"]);
+        assert_eq!(diffs[1].removed_digests, ["sha256:b"]);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_sign_and_verify_snapshot() {
+        use p256::ecdsa::SigningKey;
+        use p256::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+
+        let key = SigningKey::from_slice(&[9u8; 32]).unwrap();
+        let private_pem = key.to_pkcs8_pem(LineEnding::LF).unwrap();
+        let public_pem = key
+            .verifying_key()
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        let trusted = SnapshotVerifier {
+            keys: vec![PublicKey::from_pem(&public_pem).unwrap()],
+            allow_unsigned: false,
+        };
+
+        let snapshot = Snapshot::new("default", vec![reference_value("rv", &["a"])]);
+        let mut envelope = SnapshotEnvelope::new(&snapshot).unwrap();
+        assert!(envelope.verify(&public_pem).is_err());
+        assert!(trusted.verify(&envelope).is_err());
+        envelope.sign("staging", &private_pem).unwrap();
+
+        let json = serde_json::to_string_pretty(&envelope).unwrap();
+        let parsed = SnapshotEnvelope::from_json(&json).unwrap();
+        parsed.verify(&public_pem).unwrap();
+        assert_eq!(trusted.verify(&parsed).unwrap(), snapshot);
+
+        let mut tampered = parsed.clone();
+        let mut changed = snapshot.clone();
+        changed.reference_values[0] = reference_value("rv", &["b"]);
+        tampered.payload = SnapshotEnvelope::new(&changed).unwrap().payload;
+        assert!(tampered.verify(&public_pem).is_err());
+
+        let mut retyped = parsed;
+        retyped.payload_type = "application/json".into();
+        assert!(retyped.verify(&public_pem).is_err());
+    }
+}
@@ -16,6 +16,7 @@ use serde::Deserialize;
 use tokio::sync::RwLock;
 
 use super::ReferenceValueStorage;
+use crate::history::HistoryEntry;
 use crate::ReferenceValue;
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq)]
@@ -23,12 +24,14 @@ pub struct Config {}
 
 pub struct InMemory {
     map: Arc<RwLock<HashMap<String, ReferenceValue>>>,
+    history: Arc<RwLock<HashMap<String, Vec<HistoryEntry>>>>,
 }
 
 impl InMemory {
     pub fn new(_config: Config) -> Result<Self> {
         Ok(Self {
             map: Arc::new(RwLock::new(HashMap::new())),
+            history: Arc::new(RwLock::new(HashMap::new())),
         })
     }
 }
@@ -54,6 +57,41 @@ impl ReferenceValueStorage for InMemory {
         let mut m = self.map.write().await;
         Ok(m.remove(name))
     }
+
+    async fn append_history(&self, name: &str, mut entry: HistoryEntry) -> Result<u64> {
+        let mut h = self.history.write().await;
+        let entries = h.entry(name.to_string()).or_default();
+        entry.revision = entries.len() as u64 + 1;
+        entries.push(entry);
+        Ok(entries.len() as u64)
+    }
+
+    async fn history(&self, name: &str) -> Result<Vec<HistoryEntry>> {
+        let h = self.history.read().await;
+        Ok(h.get(name).cloned().unwrap_or_default())
+    }
+
+    async fn compare_and_commit(
+        &self,
+        name: String,
+        expected: Option<&ReferenceValue>,
+        rv: Option<ReferenceValue>,
+        mut entry: HistoryEntry,
+    ) -> Result<Option<u64>> {
+        let mut m = self.map.write().await;
+        if m.get(&name) != expected {
+            return Ok(None);
+        }
+        let mut h = self.history.write().await;
+        let entries = h.entry(name.clone()).or_default();
+        entry.revision = entries.len() as u64 + 1;
+        entries.push(entry);
+        match rv {
+            Some(rv) => m.insert(name, rv),
+            None => m.remove(&name),
+        };
+        Ok(Some(entries.len() as u64))
+    }
 }
 
 #[cfg(test)]
@@ -65,6 +103,7 @@ mod tests {
         ReferenceValue {
             version: "0.1.0".to_string(),
             name: name.to_string(),
+            namespace: crate::namespace::DEFAULT_NAMESPACE.to_string(),
             expiration: chrono::Utc.with_ymd_and_hms(2099, 1, 1, 0, 0, 0).unwrap(),
             hash_value: vec![],
             value: None,
@@ -5,14 +5,22 @@
 
 //! This Store stores RV information inside a local file
 
+use std::time::{Duration, Instant};
+
 use anyhow::*;
 use async_trait::async_trait;
 use serde::Deserialize;
+use sled::transaction::{abort, TransactionError};
+use sled::Transactional;
 
+use crate::history::HistoryEntry;
 use crate::ReferenceValue;
 
 use super::ReferenceValueStorage;
 
+/// Name of the sled tree keeping the history of the reference values.
+const HISTORY_TREE: &str = "history";
+
 /// Local directory path to store the reference values,
 /// which is created by sled engine.
 const FILE_PATH: &str = "/opt/confidential-containers/attestation-service/reference_values";
@@ -21,6 +29,7 @@ const FILE_PATH: &str = "/opt/confidential-containers/attestation-service/refere
 /// it uses rocksdb inside.
 pub struct LocalFs {
     engine: sled::Db,
+    history: sled::Tree,
 }
 
 fn default_file_path() -> String {
@@ -41,14 +50,65 @@ impl Default for Config {
     }
 }
 
+/// How long opening the store waits for the lock on its directory. Sled
+/// releases the lock from its background threads, shortly after the last
+/// handle of a database is dropped, so a store reopened by the same process
+/// may find it still held.
+const LOCK_WAIT: Duration = Duration::from_secs(5);
+
+/// Interval of the attempts to take the lock.
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
 impl LocalFs {
     /// Create a new [`LocalFs`] with given config
     pub fn new(config: Config) -> Result<Self> {
-        let engine = sled::open(config.file_path)?;
-        Ok(Self { engine })
+        let deadline = Instant::now() + LOCK_WAIT;
+        let engine = loop {
+            match sled::open(&config.file_path) {
+                Result::Ok(engine) => break engine,
+                // sled reports a held lock as an `Other` I/O error only.
+                Err(sled::Error::Io(e))
+                    if e.to_string().contains("could not acquire lock")
+                        && Instant::now() < deadline =>
+                {
+                    std::thread::sleep(LOCK_RETRY_INTERVAL);
+                }
+                Err(e) => return Err(e).context(format!("open {}", config.file_path)),
+            }
+        };
+        let history = engine
+            .open_tree(HISTORY_TREE)
+            .context("open history tree")?;
+        Ok(Self { engine, history })
+    }
+
+    /// Revision of the latest history entry of `name`, 0 if none.
+    fn last_revision(&self, name: &str) -> Result<u64> {
+        match self.history.scan_prefix(history_prefix(name)).last() {
+            Some(kv) => {
+                let (_k, v) = kv.context("read history from sled")?;
+                Ok(serde_json::from_slice::<HistoryEntry>(&v)?.revision)
+            }
+            None => Ok(0),
+        }
     }
 }
 
+/// Key prefix of the history entries of `name`. Entries are keyed by the
+/// prefix followed by the big-endian revision, so they iterate in order.
+fn history_prefix(name: &str) -> Vec<u8> {
+    let mut prefix = name.as_bytes().to_vec();
+    prefix.push(0);
+    prefix
+}
+
+/// Key of the history entry `revision` of `name`.
+fn history_key(name: &str, revision: u64) -> Vec<u8> {
+    let mut key = history_prefix(name);
+    key.extend_from_slice(&revision.to_be_bytes());
+    key
+}
+
 #[async_trait]
 impl ReferenceValueStorage for LocalFs {
     async fn set(&self, name: String, rv: ReferenceValue) -> Result<Option<ReferenceValue>> {
@@ -99,12 +159,82 @@ impl ReferenceValueStorage for LocalFs {
             None => Ok(None),
         }
     }
+
+    async fn append_history(&self, name: &str, mut entry: HistoryEntry) -> Result<u64> {
+        entry.revision = self.last_revision(name)? + 1;
+        self.history
+            .insert(
+                history_key(name, entry.revision),
+                serde_json::to_vec(&entry)?,
+            )
+            .context("insert history into sled")?;
+        self.history.flush()?;
+        Ok(entry.revision)
+    }
+
+    async fn history(&self, name: &str) -> Result<Vec<HistoryEntry>> {
+        let mut entries = Vec::new();
+        for kv in self.history.scan_prefix(history_prefix(name)) {
+            let (_k, v) = kv.context("read history from sled")?;
+            entries.push(serde_json::from_slice(&v)?);
+        }
+        Ok(entries)
+    }
+
+    async fn compare_and_commit(
+        &self,
+        name: String,
+        expected: Option<&ReferenceValue>,
+        rv: Option<ReferenceValue>,
+        mut entry: HistoryEntry,
+    ) -> Result<Option<u64>> {
+        let value = rv.as_ref().map(serde_json::to_vec).transpose()?;
+        let revision = self.last_revision(&name)? + 1;
+        entry.revision = revision;
+        let key = history_key(&name, revision);
+        let entry = serde_json::to_vec(&entry)?;
+
+        let committed = (&*self.engine, &self.history)
+            .transaction(|(values, history)| {
+                let stored = values
+                    .get(name.as_bytes())?
+                    .map(|v| serde_json::from_slice::<ReferenceValue>(&v))
+                    .transpose();
+                let stored = match stored {
+                    Result::Ok(stored) => stored,
+                    Err(e) => return abort(anyhow!(e)),
+                };
+                if stored.as_ref() != expected {
+                    return Result::Ok(false);
+                }
+                if history.get(&key)?.is_some() {
+                    return abort(anyhow!("history of {name} changed concurrently"));
+                }
+                history.insert(key.as_slice(), entry.as_slice())?;
+                match &value {
+                    Some(value) => values.insert(name.as_bytes(), value.as_slice())?,
+                    None => values.remove(name.as_bytes())?,
+                };
+                Result::Ok(true)
+            })
+            .map_err(|e| match e {
+                TransactionError::Abort(e) => e,
+                TransactionError::Storage(e) => anyhow!(e).context("commit to sled"),
+            })?;
+
+        if !committed {
+            return Ok(None);
+        }
+        self.engine.flush()?;
+        Ok(Some(revision))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use serial_test::serial;
 
+    use crate::history::{HistoryEntry, HistoryOperation};
     use crate::{ReferenceValue, ReferenceValueStorage};
 
     use super::{Config, LocalFs};
@@ -207,4 +337,94 @@ mod tests {
             assert_eq!(got, rv);
         }
     }
+
+    /// This test will test the history of [`LocalFs`], which
+    /// must be kept apart per name and survive a restart.
+    #[tokio::test]
+    #[serial]
+    async fn history() {
+        let temp_dir = tempfile::tempdir().expect("create tempdir failed");
+        let dir_str = temp_dir.path().to_string_lossy().to_string();
+        let rv = ReferenceValue::new().expect("create ReferenceValue failed.");
+        {
+            let storage = LocalFs::new(Config {
+                file_path: dir_str.clone(),
+            })
+            .expect("create local fs store failed.");
+            for (name, operation) in [
+                (KEY, HistoryOperation::Add),
+                ("test10", HistoryOperation::Add),
+                (KEY, HistoryOperation::Delete),
+            ] {
+                let value = (operation == HistoryOperation::Add).then(|| rv.clone());
+                storage
+                    .append_history(name, HistoryEntry::new(operation, "test", None, value))
+                    .await
+                    .expect("append history failed.");
+            }
+            assert!(
+                storage
+                    .get_values()
+                    .await
+                    .expect("get rvs failed.")
+                    .is_empty(),
+                "history leaked into the reference values"
+            );
+        }
+        {
+            let storage =
+                LocalFs::new(Config { file_path: dir_str }).expect("create local fs store failed.");
+            let history = storage.history(KEY).await.expect("get history failed.");
+            assert_eq!(history.len(), 2);
+            assert_eq!(history[0].revision, 1);
+            assert_eq!(history[0].value, Some(rv));
+            assert_eq!(history[1].revision, 2);
+            assert_eq!(history[1].operation, HistoryOperation::Delete);
+        }
+    }
+
+    /// This test will test that [`LocalFs`] writes a value along with its
+    /// history entry, only if the stored value is the expected one.
+    #[tokio::test]
+    #[serial]
+    async fn compare_and_commit() {
+        let temp_dir = tempfile::tempdir().expect("create tempdir failed");
+        let dir_str = temp_dir.path().to_string_lossy().to_string();
+        let storage =
+            LocalFs::new(Config { file_path: dir_str }).expect("create local fs store failed.");
+        let rv = ReferenceValue::new().expect("create ReferenceValue failed.");
+        let entry = |operation| HistoryEntry::new(operation, "test", None, None);
+
+        let committed = storage
+            .compare_and_commit(
+                KEY.into(),
+                None,
+                Some(rv.clone()),
+                entry(HistoryOperation::Add),
+            )
+            .await
+            .expect("commit failed.");
+        assert_eq!(committed, Some(1));
+        // A writer deriving from an outdated value loses.
+        let committed = storage
+            .compare_and_commit(KEY.into(), None, None, entry(HistoryOperation::Delete))
+            .await
+            .expect("commit failed.");
+        assert_eq!(committed, None);
+        let committed = storage
+            .compare_and_commit(KEY.into(), Some(&rv), None, entry(HistoryOperation::Delete))
+            .await
+            .expect("commit failed.");
+        assert_eq!(committed, Some(2));
+
+        assert!(storage.get(KEY).await.expect("get rv failed.").is_none());
+        assert_eq!(
+            storage
+                .history(KEY)
+                .await
+                .expect("get history failed.")
+                .len(),
+            2
+        );
+    }
 }
@@ -1,6 +1,7 @@
-use std::{fs, path::PathBuf};
+use std::{collections::HashMap, fs, path::PathBuf};
 
 use super::ReferenceValueStorage;
+use crate::history::HistoryEntry;
 use crate::ReferenceValue;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
@@ -10,8 +11,11 @@ use tokio::sync::RwLock;
 
 const FILE_PATH: &str = "/opt/confidential-containers/attestation-service/reference_values.json";
 
+/// `LocalJson` keeps the history of the reference values in a file next to
+/// `file_path`, named after it with a `.history.json` extension.
 pub struct LocalJson {
     file_path: String,
+    history_path: PathBuf,
     lock: RwLock<i32>,
 }
 
@@ -49,8 +53,15 @@ impl LocalJson {
             std::fs::write(config.file_path.clone(), "[]")?;
         }
 
+        let history_path = path.with_extension("history.json");
+        if !history_path.exists() {
+            debug!("Creating empty file for LocalJson reference value history.");
+            std::fs::write(&history_path, "{}")?;
+        }
+
         Ok(Self {
             file_path: config.file_path,
+            history_path,
             lock: RwLock::new(0),
         })
     }
@@ -63,7 +74,7 @@ impl ReferenceValueStorage for LocalJson {
         let file = tokio::fs::read(&self.file_path).await?;
         let mut rvs: Vec<ReferenceValue> = serde_json::from_slice(&file)?;
         let mut res = None;
-        if let Some(item) = rvs.iter_mut().find(|it| it.name == name) {
+        if let Some(item) = rvs.iter_mut().find(|it| it.storage_key() == name) {
             res = Some(item.to_owned());
             *item = rv;
         } else {
@@ -79,7 +90,7 @@ impl ReferenceValueStorage for LocalJson {
         let _ = self.lock.read().await;
         let file = tokio::fs::read(&self.file_path).await?;
         let rvs: Vec<ReferenceValue> = serde_json::from_slice(&file)?;
-        let rv = rvs.into_iter().find(|rv| rv.name == name);
+        let rv = rvs.into_iter().find(|rv| rv.storage_key() == name);
         Ok(rv)
     }
 
@@ -96,7 +107,7 @@ impl ReferenceValueStorage for LocalJson {
         let mut rvs: Vec<ReferenceValue> = serde_json::from_slice(&file)?;
 
         let mut deleted_rv = None;
-        if let Some(pos) = rvs.iter().position(|rv| rv.name == name) {
+        if let Some(pos) = rvs.iter().position(|rv| rv.storage_key() == name) {
             deleted_rv = Some(rvs.remove(pos));
         }
 
@@ -104,4 +115,25 @@ impl ReferenceValueStorage for LocalJson {
         tokio::fs::write(&self.file_path, contents).await?;
         Ok(deleted_rv)
     }
+
+    async fn append_history(&self, name: &str, mut entry: HistoryEntry) -> Result<u64> {
+        let _guard = self.lock.write().await;
+        let file = tokio::fs::read(&self.history_path).await?;
+        let mut history: HashMap<String, Vec<HistoryEntry>> = serde_json::from_slice(&file)?;
+        let entries = history.entry(name.to_string()).or_default();
+        entry.revision = entries.last().map_or(0, |last| last.revision) + 1;
+        let revision = entry.revision;
+        entries.push(entry);
+
+        let contents = serde_json::to_vec(&history)?;
+        tokio::fs::write(&self.history_path, contents).await?;
+        Ok(revision)
+    }
+
+    async fn history(&self, name: &str) -> Result<Vec<HistoryEntry>> {
+        let _guard = self.lock.read().await;
+        let file = tokio::fs::read(&self.history_path).await?;
+        let mut history: HashMap<String, Vec<HistoryEntry>> = serde_json::from_slice(&file)?;
+        Ok(history.remove(name).unwrap_or_default())
+    }
 }
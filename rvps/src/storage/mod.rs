@@ -14,14 +14,19 @@ use strum::Display;
 use self::local_fs::LocalFs;
 #[cfg(feature = "fs")]
 use self::local_json::LocalJson;
+#[cfg(feature = "sql")]
+use self::sql::Sql;
 
-use super::ReferenceValue;
+use crate::history::HistoryEntry;
+use crate::ReferenceValue;
 
 pub mod in_memory;
 #[cfg(feature = "fs")]
 pub mod local_fs;
 #[cfg(feature = "fs")]
 pub mod local_json;
+#[cfg(feature = "sql")]
+pub mod sql;
 
 #[derive(Clone, Debug, Deserialize, Display, PartialEq)]
 #[serde(tag = "type")]
@@ -31,6 +36,8 @@ pub enum ReferenceValueStorageConfig {
     LocalFs(local_fs::Config),
     #[cfg(feature = "fs")]
     LocalJson(local_json::Config),
+    #[cfg(feature = "sql")]
+    Sql(sql::Config),
 }
 
 impl Default for ReferenceValueStorageConfig {
@@ -61,12 +68,19 @@ impl ReferenceValueStorageConfig {
                 Ok(Box::new(LocalJson::new(cfg.clone())?)
                     as Box<dyn ReferenceValueStorage + Send + Sync>)
             }
+            #[cfg(feature = "sql")]
+            ReferenceValueStorageConfig::Sql(cfg) => {
+                Ok(Box::new(Sql::new(cfg.clone())?)
+                    as Box<dyn ReferenceValueStorage + Send + Sync>)
+            }
         }
     }
 }
 
 /// Interface for `ReferenceValueStorage`.
 /// Reference value storage facilities should implement this trait.
+/// The `name` of a reference value here is the key it is stored under,
+/// qualified with its namespace by [`crate::namespace::storage_key`].
 #[async_trait]
 pub trait ReferenceValueStorage {
     /// Store a reference value. If the given `name` exists,
@@ -79,6 +93,57 @@ pub trait ReferenceValueStorage {
     // Retrieve reference values
     async fn get_values(&self) -> Result<Vec<ReferenceValue>>;
 
+    /// Retrieve the reference values of `namespace`.
+    async fn get_namespace_values(&self, namespace: &str) -> Result<Vec<ReferenceValue>> {
+        let namespace = crate::namespace::normalize(namespace);
+        Ok(self
+            .get_values()
+            .await?
+            .into_iter()
+            .filter(|rv| rv.namespace() == namespace)
+            .collect())
+    }
+
     // Delete reference value by name. Return the deleted value if exists
     async fn delete(&self, name: &str) -> Result<Option<ReferenceValue>>;
+
+    /// Append `entry` to the history of `name`, ignoring its `revision`.
+    /// Return the revision assigned to it, greater than those of the
+    /// entries already in the history.
+    async fn append_history(&self, name: &str, entry: HistoryEntry) -> Result<u64>;
+
+    /// Retrieve the history of `name`, oldest first.
+    async fn history(&self, name: &str) -> Result<Vec<HistoryEntry>>;
+
+    /// Store `rv` as the value of `name`, or delete it if `None`, only if
+    /// the stored value is still `expected` (`None` if absent), the value
+    /// `rv` was derived from, and append `entry` to the history of `name`
+    /// along with it. Return the revision assigned to `entry`, `None` if the
+    /// stored value changed and nothing was written.
+    ///
+    /// The default suits storages written by a single RVPS, which already
+    /// serializes its writes. It appends the history first, so that no
+    /// change goes unrecorded if the write fails. Storages shared by several
+    /// RVPS instances must check and write atomically.
+    async fn compare_and_commit(
+        &self,
+        name: String,
+        expected: Option<&ReferenceValue>,
+        rv: Option<ReferenceValue>,
+        entry: HistoryEntry,
+    ) -> Result<Option<u64>> {
+        if self.get(&name).await?.as_ref() != expected {
+            return Ok(None);
+        }
+        let revision = self.append_history(&name, entry).await?;
+        match rv {
+            Some(rv) => {
+                self.set(name, rv).await?;
+            }
+            None => {
+                self.delete(&name).await?;
+            }
+        }
+        Ok(Some(revision))
+    }
 }
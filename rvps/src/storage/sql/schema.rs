@@ -0,0 +1,179 @@
+// Copyright (c) 2025 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Versioned schema migrations of the `Sql` storage.
+//!
+//! Every replica runs the pending migrations when it first touches the
+//! database. Applied versions are recorded in `rvps_schema_migrations`, and
+//! all statements are idempotent, so replicas starting at once converge. On
+//! MySQL a named advisory lock additionally serializes the migrations.
+
+use anyhow::{anyhow, Context, Result};
+
+use super::DbPool;
+
+/// Name of the MySQL advisory lock taken during schema migration.
+const MIGRATION_LOCK_NAME: &str = "trustee_rvps_migrate";
+
+/// Lock timeout in seconds (`GET_LOCK` second argument).
+const MIGRATION_LOCK_TIMEOUT_SECS: i64 = 30;
+
+const MYSQL_CREATE_MIGRATIONS: &str = r#"
+CREATE TABLE IF NOT EXISTS rvps_schema_migrations (
+    version     BIGINT NOT NULL PRIMARY KEY,
+    applied_at  BIGINT NOT NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4
+"#;
+
+const SQLITE_CREATE_MIGRATIONS: &str = r#"
+CREATE TABLE IF NOT EXISTS rvps_schema_migrations (
+    version     INTEGER NOT NULL PRIMARY KEY,
+    applied_at  INTEGER NOT NULL
+)
+"#;
+
+/// A schema version and the statements upgrading to it from the previous
+/// one. Append new versions, never edit applied ones.
+struct Migration {
+    version: i64,
+    mysql: &'static [&'static str],
+    sqlite: &'static [&'static str],
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        mysql: &[r#"
+CREATE TABLE IF NOT EXISTS rvps_reference_values (
+    name      VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL PRIMARY KEY,
+    value     MEDIUMTEXT NOT NULL,
+    revision  BIGINT     NOT NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4
+"#],
+        sqlite: &[r#"
+CREATE TABLE IF NOT EXISTS rvps_reference_values (
+    name      TEXT    NOT NULL PRIMARY KEY,
+    value     TEXT    NOT NULL,
+    revision  INTEGER NOT NULL
+)
+"#],
+    },
+    Migration {
+        version: 2,
+        mysql: &[r#"
+CREATE TABLE IF NOT EXISTS rvps_history (
+    id     BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    name   VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL,
+    entry  MEDIUMTEXT NOT NULL,
+    KEY idx_name_id (name, id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4
+"#],
+        sqlite: &[
+            r#"
+CREATE TABLE IF NOT EXISTS rvps_history (
+    id     INTEGER PRIMARY KEY AUTOINCREMENT,
+    name   TEXT    NOT NULL,
+    entry  TEXT    NOT NULL
+)
+"#,
+            "CREATE INDEX IF NOT EXISTS idx_rvps_history_name_id ON rvps_history(name, id)",
+        ],
+    },
+];
+
+/// Apply all pending migrations.
+pub async fn migrate(pool: &DbPool) -> Result<()> {
+    match pool {
+        DbPool::MySql(p) => {
+            let row: (Option<i64>,) = sqlx::query_as("SELECT GET_LOCK(?, ?)")
+                .bind(MIGRATION_LOCK_NAME)
+                .bind(MIGRATION_LOCK_TIMEOUT_SECS)
+                .fetch_one(p)
+                .await
+                .context("acquire migration lock")?;
+            if row.0 != Some(1) {
+                return Err(anyhow!(
+                    "failed to acquire MySQL advisory lock `{MIGRATION_LOCK_NAME}` within {MIGRATION_LOCK_TIMEOUT_SECS}s"
+                ));
+            }
+            let result = migrate_mysql(p).await;
+            // Best-effort release.
+            let _ = sqlx::query("SELECT RELEASE_LOCK(?)")
+                .bind(MIGRATION_LOCK_NAME)
+                .execute(p)
+                .await;
+            result
+        }
+        DbPool::Sqlite(p) => migrate_sqlite(p).await,
+    }
+}
+
+async fn migrate_mysql(pool: &sqlx::MySqlPool) -> Result<()> {
+    sqlx::query(MYSQL_CREATE_MIGRATIONS)
+        .execute(pool)
+        .await
+        .context("create rvps_schema_migrations")?;
+    let (current,): (Option<i64>,) =
+        sqlx::query_as("SELECT MAX(version) FROM rvps_schema_migrations")
+            .fetch_one(pool)
+            .await
+            .context("read schema version")?;
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|m| m.version > current.unwrap_or(0))
+    {
+        for stmt in migration.mysql {
+            sqlx::query(stmt).execute(pool).await.with_context(|| {
+                format!("migrate to v{}: {}", migration.version, first_line(stmt))
+            })?;
+        }
+        sqlx::query(
+            "INSERT IGNORE INTO rvps_schema_migrations (version, applied_at) VALUES (?, ?)",
+        )
+        .bind(migration.version)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(pool)
+        .await
+        .with_context(|| format!("record schema v{}", migration.version))?;
+    }
+    Ok(())
+}
+
+async fn migrate_sqlite(pool: &sqlx::SqlitePool) -> Result<()> {
+    sqlx::query(SQLITE_CREATE_MIGRATIONS)
+        .execute(pool)
+        .await
+        .context("create rvps_schema_migrations")?;
+    let (current,): (Option<i64>,) =
+        sqlx::query_as("SELECT MAX(version) FROM rvps_schema_migrations")
+            .fetch_one(pool)
+            .await
+            .context("read schema version")?;
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|m| m.version > current.unwrap_or(0))
+    {
+        for stmt in migration.sqlite {
+            sqlx::query(stmt).execute(pool).await.with_context(|| {
+                format!("migrate to v{}: {}", migration.version, first_line(stmt))
+            })?;
+        }
+        sqlx::query(
+            "INSERT OR IGNORE INTO rvps_schema_migrations (version, applied_at) VALUES (?, ?)",
+        )
+        .bind(migration.version)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(pool)
+        .await
+        .with_context(|| format!("record schema v{}", migration.version))?;
+    }
+    Ok(())
+}
+
+fn first_line(s: &str) -> &str {
+    s.lines().find(|l| !l.trim().is_empty()).unwrap_or(s).trim()
+}